@@ -13,6 +13,10 @@ fn main() -> Result<(), Box<dyn Error>> {
                 "../proto/user.proto",
                 "../proto/queries.proto",
                 "../proto/authentication.proto",
+                "../proto/public_registration.proto",
+                "../proto/schema_template.proto",
+                "../proto/metrics.proto",
+                "../proto/maintenance.proto",
             ],
             &["../proto"],
         )?;
@@ -0,0 +1,11 @@
+CREATE TABLE permissions
+(
+	id           TEXT                                                                                                                                    NOT NULL PRIMARY KEY,
+	user         TEXT                                                                                                                                    NOT NULL,
+	role         TEXT CHECK( role IN ("SERVER_ADMIN", "ORGANIZATION_ADMIN", "ORGANIZATION_VIEWER", "EVENT_ADMIN", "EVENT_EDITOR", "EVENT_VIEWER") ) NOT NULL,
+	organization TEXT,
+	event        TEXT,
+	FOREIGN KEY (user) REFERENCES users (id) ON DELETE CASCADE,
+	FOREIGN KEY (organization) REFERENCES organizations (id) ON DELETE CASCADE,
+	FOREIGN KEY (event) REFERENCES events (id) ON DELETE CASCADE
+);
@@ -0,0 +1,2 @@
+ALTER TABLE events ADD COLUMN starts_at TEXT;
+ALTER TABLE events ADD COLUMN ends_at TEXT;
@@ -0,0 +1 @@
+ALTER TABLE registrations ADD COLUMN status TEXT CHECK( status IN ("CONFIRMED", "WAITLISTED", "CANCELLED") ) NOT NULL DEFAULT "CONFIRMED";
@@ -0,0 +1,8 @@
+CREATE TABLE schema_templates
+(
+	id           TEXT NOT NULL PRIMARY KEY,
+	organization TEXT NOT NULL,
+	name         TEXT NOT NULL,
+	items        BLOB NOT NULL,
+	FOREIGN KEY (organization) REFERENCES organizations (id) ON DELETE CASCADE
+);
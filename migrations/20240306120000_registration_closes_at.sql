@@ -0,0 +1 @@
+ALTER TABLE events ADD COLUMN registration_closes_at TEXT;
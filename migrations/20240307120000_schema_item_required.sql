@@ -0,0 +1 @@
+ALTER TABLE registration_schema_items ADD COLUMN required INTEGER CHECK( required IN (TRUE, FALSE) ) NOT NULL DEFAULT FALSE;
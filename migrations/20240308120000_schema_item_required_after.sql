@@ -0,0 +1 @@
+ALTER TABLE registration_schema_items ADD COLUMN required_after TEXT;
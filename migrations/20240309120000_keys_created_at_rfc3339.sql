@@ -0,0 +1,4 @@
+ALTER TABLE keys RENAME COLUMN created_at TO created_at_unix;
+ALTER TABLE keys ADD COLUMN created_at TEXT NOT NULL DEFAULT '';
+UPDATE keys SET created_at = strftime('%Y-%m-%dT%H:%M:%SZ', created_at_unix, 'unixepoch');
+ALTER TABLE keys DROP COLUMN created_at_unix;
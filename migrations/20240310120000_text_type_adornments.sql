@@ -0,0 +1,3 @@
+ALTER TABLE registration_schema_items ADD COLUMN text_type_placeholder TEXT;
+ALTER TABLE registration_schema_items ADD COLUMN text_type_prefix TEXT;
+ALTER TABLE registration_schema_items ADD COLUMN text_type_suffix TEXT;
@@ -0,0 +1,2 @@
+ALTER TABLE events ADD COLUMN require_account INTEGER CHECK( require_account IN (TRUE, FALSE) ) NOT NULL DEFAULT FALSE;
+ALTER TABLE registrations ADD COLUMN user TEXT REFERENCES users (id) ON DELETE SET NULL;
@@ -0,0 +1,8 @@
+-- Rows created before slugs existed use their id, which is already unique.
+ALTER TABLE organizations ADD COLUMN slug TEXT;
+UPDATE organizations SET slug = id;
+CREATE UNIQUE INDEX organizations_slug ON organizations (slug);
+
+ALTER TABLE events ADD COLUMN slug TEXT;
+UPDATE events SET slug = id;
+CREATE UNIQUE INDEX events_organization_slug ON events (organization, slug);
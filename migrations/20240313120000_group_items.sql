@@ -0,0 +1,107 @@
+-- SQLite can't alter a CHECK constraint in place, so the schema item table is
+-- rebuilt to allow groups. Items inside a group belong to the group through
+-- parent rather than to the event, so exactly one of the two is set. Child
+-- tables are rebuilt alongside it so that dropping the old table doesn't
+-- cascade-delete their rows.
+CREATE TABLE registration_schema_items_new
+(
+	id                         TEXT                                                                                                                 NOT NULL PRIMARY KEY,
+	event                      TEXT,
+	parent                     TEXT,
+	idx                        INTEGER                                                                                                              NOT NULL,
+	name                       TEXT                                                                                                                 NOT NULL,
+	required                   INTEGER CHECK( required IN (TRUE, FALSE) )                                                                           NOT NULL DEFAULT FALSE,
+	required_after             TEXT,
+	item_type                  TEXT CHECK( item_type IN ("TextType", "CheckboxType", "SelectType", "MultiSelectType", "SectionType", "GroupType") ) NOT NULL,
+	text_type_default          TEXT,
+	text_type_display          TEXT CHECK( text_type_display IN ("SMALL", "LARGE") ),
+	text_type_placeholder      TEXT,
+	text_type_prefix           TEXT,
+	text_type_suffix           TEXT,
+	checkbox_type_default      INTEGER CHECK( checkbox_type_default IN (TRUE, FALSE) ),
+	select_type_default        INTEGER,
+	select_type_display        TEXT CHECK( select_type_display IN ("RADIO", "DROPDOWN") ),
+	multi_select_type_defaults TEXT,
+	multi_select_type_display  TEXT CHECK( multi_select_type_display IN ("CHECKBOXES", "MULTISELECT_BOX") ),
+	section_type_body          TEXT,
+	CHECK( (event IS NULL) != (parent IS NULL) ),
+	FOREIGN KEY (event) REFERENCES events (id) ON DELETE CASCADE,
+	FOREIGN KEY (parent) REFERENCES registration_schema_items_new (id) ON DELETE CASCADE
+);
+
+INSERT INTO registration_schema_items_new(
+	id,
+	event,
+	idx,
+	name,
+	required,
+	required_after,
+	item_type,
+	text_type_default,
+	text_type_display,
+	text_type_placeholder,
+	text_type_prefix,
+	text_type_suffix,
+	checkbox_type_default,
+	select_type_default,
+	select_type_display,
+	multi_select_type_defaults,
+	multi_select_type_display,
+	section_type_body
+) SELECT
+	id,
+	event,
+	idx,
+	name,
+	required,
+	required_after,
+	item_type,
+	text_type_default,
+	text_type_display,
+	text_type_placeholder,
+	text_type_prefix,
+	text_type_suffix,
+	checkbox_type_default,
+	select_type_default,
+	select_type_display,
+	multi_select_type_defaults,
+	multi_select_type_display,
+	section_type_body
+FROM registration_schema_items;
+
+CREATE TABLE registration_schema_select_options_new
+(
+	id          TEXT    NOT NULL PRIMARY KEY,
+	schema_item TEXT    NOT NULL,
+	idx         INTEGER NOT NULL,
+	name        TEXT    NOT NULL,
+	product_id  TEXT    NOT NULL,
+	FOREIGN KEY (schema_item) REFERENCES registration_schema_items_new (id) ON DELETE CASCADE
+);
+
+INSERT INTO registration_schema_select_options_new(id, schema_item, idx, name, product_id)
+	SELECT id, schema_item, idx, name, product_id FROM registration_schema_select_options;
+
+-- Values for items inside a group are told apart by which repeat of the group
+-- they belong to.
+CREATE TABLE registration_items_new
+(
+	id            TEXT    NOT NULL PRIMARY KEY,
+	registration  TEXT    NOT NULL,
+	schema_item   TEXT    NOT NULL,
+	instance      INTEGER NOT NULL DEFAULT 0,
+	value         TEXT    NOT NULL,
+	FOREIGN KEY (registration) REFERENCES registrations (id) ON DELETE CASCADE,
+	FOREIGN KEY (schema_item) REFERENCES registration_schema_items_new (id) ON DELETE CASCADE
+);
+
+INSERT INTO registration_items_new(id, registration, schema_item, value)
+	SELECT id, registration, schema_item, value FROM registration_items;
+
+DROP TABLE registration_items;
+DROP TABLE registration_schema_select_options;
+DROP TABLE registration_schema_items;
+
+ALTER TABLE registration_schema_items_new RENAME TO registration_schema_items;
+ALTER TABLE registration_schema_select_options_new RENAME TO registration_schema_select_options;
+ALTER TABLE registration_items_new RENAME TO registration_items;
@@ -0,0 +1 @@
+ALTER TABLE registration_schema_select_options ADD COLUMN archived INTEGER CHECK( archived IN (TRUE, FALSE) ) NOT NULL DEFAULT FALSE;
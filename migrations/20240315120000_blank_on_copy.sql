@@ -0,0 +1 @@
+ALTER TABLE registration_schema_items ADD COLUMN blank_on_copy INTEGER CHECK( blank_on_copy IN (TRUE, FALSE) ) NOT NULL DEFAULT FALSE;
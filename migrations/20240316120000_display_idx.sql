@@ -0,0 +1 @@
+ALTER TABLE registration_schema_items ADD COLUMN display_idx INTEGER CHECK( display_idx >= 0 );
@@ -0,0 +1,2 @@
+ALTER TABLE events ADD COLUMN dedup_item TEXT REFERENCES registration_schema_items (id) ON DELETE SET NULL;
+ALTER TABLE events ADD COLUMN block_duplicates INTEGER CHECK( block_duplicates IN (TRUE, FALSE) ) NOT NULL DEFAULT FALSE;
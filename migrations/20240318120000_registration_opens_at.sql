@@ -0,0 +1 @@
+ALTER TABLE events ADD COLUMN registration_opens_at TEXT;
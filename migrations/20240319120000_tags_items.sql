@@ -0,0 +1,121 @@
+-- SQLite can't alter a CHECK constraint in place, so the schema item table is
+-- rebuilt to allow tags, along with its child tables as for groups. Dropping
+-- the old table clears the events' dedup items, so they're put back afterwards.
+CREATE TABLE event_dedup_items AS
+	SELECT id, dedup_item FROM events WHERE dedup_item IS NOT NULL;
+
+CREATE TABLE registration_schema_items_new
+(
+	id                         TEXT                                                                                                                             NOT NULL PRIMARY KEY,
+	event                      TEXT,
+	parent                     TEXT,
+	idx                        INTEGER                                                                                                                          NOT NULL,
+	name                       TEXT                                                                                                                             NOT NULL,
+	required                   INTEGER CHECK( required IN (TRUE, FALSE) )                                                                                       NOT NULL DEFAULT FALSE,
+	required_after             TEXT,
+	item_type                  TEXT CHECK( item_type IN ("TextType", "CheckboxType", "SelectType", "MultiSelectType", "SectionType", "GroupType", "TagsType") ) NOT NULL,
+	text_type_default          TEXT,
+	text_type_display          TEXT CHECK( text_type_display IN ("SMALL", "LARGE") ),
+	text_type_placeholder      TEXT,
+	text_type_prefix           TEXT,
+	text_type_suffix           TEXT,
+	checkbox_type_default      INTEGER CHECK( checkbox_type_default IN (TRUE, FALSE) ),
+	select_type_default        INTEGER,
+	select_type_display        TEXT CHECK( select_type_display IN ("RADIO", "DROPDOWN") ),
+	multi_select_type_defaults TEXT,
+	multi_select_type_display  TEXT CHECK( multi_select_type_display IN ("CHECKBOXES", "MULTISELECT_BOX") ),
+	section_type_body          TEXT,
+	blank_on_copy              INTEGER CHECK( blank_on_copy IN (TRUE, FALSE) )                                                                                  NOT NULL DEFAULT FALSE,
+	display_idx                INTEGER CHECK( display_idx >= 0 ),
+	tags_type_max_tags         INTEGER CHECK( tags_type_max_tags >= 0 ),
+	CHECK( (event IS NULL) != (parent IS NULL) ),
+	FOREIGN KEY (event) REFERENCES events (id) ON DELETE CASCADE,
+	FOREIGN KEY (parent) REFERENCES registration_schema_items_new (id) ON DELETE CASCADE
+);
+
+INSERT INTO registration_schema_items_new(
+	id,
+	event,
+	parent,
+	idx,
+	name,
+	required,
+	required_after,
+	item_type,
+	text_type_default,
+	text_type_display,
+	text_type_placeholder,
+	text_type_prefix,
+	text_type_suffix,
+	checkbox_type_default,
+	select_type_default,
+	select_type_display,
+	multi_select_type_defaults,
+	multi_select_type_display,
+	section_type_body,
+	blank_on_copy,
+	display_idx
+) SELECT
+	id,
+	event,
+	parent,
+	idx,
+	name,
+	required,
+	required_after,
+	item_type,
+	text_type_default,
+	text_type_display,
+	text_type_placeholder,
+	text_type_prefix,
+	text_type_suffix,
+	checkbox_type_default,
+	select_type_default,
+	select_type_display,
+	multi_select_type_defaults,
+	multi_select_type_display,
+	section_type_body,
+	blank_on_copy,
+	display_idx
+FROM registration_schema_items;
+
+CREATE TABLE registration_schema_select_options_new
+(
+	id          TEXT    NOT NULL PRIMARY KEY,
+	schema_item TEXT    NOT NULL,
+	idx         INTEGER NOT NULL,
+	name        TEXT    NOT NULL,
+	product_id  TEXT    NOT NULL,
+	archived    INTEGER CHECK( archived IN (TRUE, FALSE) ) NOT NULL DEFAULT FALSE,
+	FOREIGN KEY (schema_item) REFERENCES registration_schema_items_new (id) ON DELETE CASCADE
+);
+
+INSERT INTO registration_schema_select_options_new(id, schema_item, idx, name, product_id, archived)
+	SELECT id, schema_item, idx, name, product_id, archived FROM registration_schema_select_options;
+
+CREATE TABLE registration_items_new
+(
+	id            TEXT    NOT NULL PRIMARY KEY,
+	registration  TEXT    NOT NULL,
+	schema_item   TEXT    NOT NULL,
+	instance      INTEGER NOT NULL DEFAULT 0,
+	value         TEXT    NOT NULL,
+	FOREIGN KEY (registration) REFERENCES registrations (id) ON DELETE CASCADE,
+	FOREIGN KEY (schema_item) REFERENCES registration_schema_items_new (id) ON DELETE CASCADE
+);
+
+INSERT INTO registration_items_new(id, registration, schema_item, instance, value)
+	SELECT id, registration, schema_item, instance, value FROM registration_items;
+
+DROP TABLE registration_items;
+DROP TABLE registration_schema_select_options;
+DROP TABLE registration_schema_items;
+
+ALTER TABLE registration_schema_items_new RENAME TO registration_schema_items;
+ALTER TABLE registration_schema_select_options_new RENAME TO registration_schema_select_options;
+ALTER TABLE registration_items_new RENAME TO registration_items;
+
+UPDATE events SET dedup_item = (SELECT dedup_item FROM event_dedup_items WHERE event_dedup_items.id = events.id)
+	WHERE id IN (SELECT id FROM event_dedup_items);
+
+DROP TABLE event_dedup_items;
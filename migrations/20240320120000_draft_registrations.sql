@@ -0,0 +1,34 @@
+-- SQLite can't alter a CHECK constraint in place, so the registrations table is
+-- rebuilt to allow drafts. Its items point at it, so they're rebuilt with it.
+CREATE TABLE registrations_new
+(
+	id     TEXT                                                                        NOT NULL PRIMARY KEY,
+	event  TEXT                                                                        NOT NULL,
+	status TEXT CHECK( status IN ("CONFIRMED", "WAITLISTED", "CANCELLED", "DRAFT") ) NOT NULL DEFAULT "CONFIRMED",
+	user   TEXT,
+	FOREIGN KEY (event) REFERENCES events (id) ON DELETE CASCADE,
+	FOREIGN KEY (user) REFERENCES users (id) ON DELETE SET NULL
+);
+
+INSERT INTO registrations_new(id, event, status, user)
+	SELECT id, event, status, user FROM registrations;
+
+CREATE TABLE registration_items_new
+(
+	id            TEXT    NOT NULL PRIMARY KEY,
+	registration  TEXT    NOT NULL,
+	schema_item   TEXT    NOT NULL,
+	instance      INTEGER NOT NULL DEFAULT 0,
+	value         TEXT    NOT NULL,
+	FOREIGN KEY (registration) REFERENCES registrations_new (id) ON DELETE CASCADE,
+	FOREIGN KEY (schema_item) REFERENCES registration_schema_items (id) ON DELETE CASCADE
+);
+
+INSERT INTO registration_items_new(id, registration, schema_item, instance, value)
+	SELECT id, registration, schema_item, instance, value FROM registration_items;
+
+DROP TABLE registration_items;
+DROP TABLE registrations;
+
+ALTER TABLE registrations_new RENAME TO registrations;
+ALTER TABLE registration_items_new RENAME TO registration_items;
@@ -0,0 +1,27 @@
+-- One row per change to an item's name, type or options. Rows are only ever
+-- appended, and they outlive the items they describe, so there's no foreign key.
+CREATE TABLE registration_schema_item_history
+(
+	id          TEXT NOT NULL PRIMARY KEY,
+	schema_item TEXT NOT NULL,
+	changed_at  TEXT NOT NULL,
+	actor       TEXT,
+	old_name    TEXT NOT NULL,
+	new_name    TEXT NOT NULL,
+	old_type    TEXT NOT NULL,
+	new_type    TEXT NOT NULL,
+	old_options TEXT NOT NULL,
+	new_options TEXT NOT NULL
+);
+
+CREATE INDEX registration_schema_item_history_schema_item ON registration_schema_item_history (schema_item);
+
+CREATE TRIGGER registration_schema_item_history_no_update BEFORE UPDATE ON registration_schema_item_history
+BEGIN
+	SELECT RAISE(ABORT, 'schema item history is append-only');
+END;
+
+CREATE TRIGGER registration_schema_item_history_no_delete BEFORE DELETE ON registration_schema_item_history
+BEGIN
+	SELECT RAISE(ABORT, 'schema item history is append-only');
+END;
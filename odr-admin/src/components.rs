@@ -1,8 +1,11 @@
 pub mod breadcrumb;
+pub mod error_boundary;
 pub mod form;
 pub mod login_menu;
 pub mod menu;
 pub mod modal;
 pub mod page;
 pub mod table;
+#[cfg(test)]
+pub mod testing;
 pub mod with_toasts;
@@ -0,0 +1,88 @@
+use dioxus::{dioxus_core::CapturedError, prelude::*};
+
+use crate::{
+    components::form::{Button, ButtonFlavor},
+    hooks::toasts::use_toasts,
+};
+
+#[component]
+pub fn AppErrorBoundary(children: Element) -> Element {
+    rsx! {
+        ErrorBoundary {
+            handle_error: |error: CapturedError| rsx! {
+                ErrorFallback {
+                    error: error.to_string(),
+                }
+            },
+            { children }
+        }
+    }
+}
+
+#[component]
+fn ErrorFallback(error: String) -> Element {
+    let mut toaster = use_toasts();
+
+    // Logged once when the fallback mounts, rather than on every render.
+    use_hook(|| log::error!("Page failed to render: {}", error));
+    use_effect(move || toaster.write().new_error(error.clone()));
+
+    rsx! {
+        section {
+            class: "section",
+            div {
+                class: "notification is-danger",
+                h1 {
+                    class: "title is-4",
+                    "Something went wrong"
+                }
+                p {
+                    class: "block",
+                    "This page ran into a problem and couldn't be displayed."
+                }
+                Button {
+                    onclick: move |_| {
+                        let _ = gloo_utils::window().location().reload();
+                    },
+                    flavor: ButtonFlavor::Info,
+                    "Reload"
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dioxus::prelude::*;
+
+    use crate::{components::testing::render, hooks::toasts::use_toasts_provider};
+
+    use super::AppErrorBoundary;
+
+    #[component]
+    fn Broken() -> Element {
+        Err::<(), _>("broken component").throw()?;
+
+        rsx! {
+            p { "Rendered" }
+        }
+    }
+
+    #[test]
+    fn renders_fallback() {
+        fn app() -> Element {
+            use_toasts_provider();
+
+            rsx! {
+                AppErrorBoundary {
+                    Broken {}
+                }
+            }
+        }
+
+        let html = render(app);
+        assert!(html.contains("Something went wrong"), "{}", html);
+        assert!(!html.contains("Rendered"), "{}", html);
+    }
+}
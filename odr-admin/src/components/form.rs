@@ -7,6 +7,7 @@ pub enum TextInputType {
     Text(String),
     Password(String),
     Number(i32),
+    Date(String),
 }
 
 #[component]
@@ -16,20 +17,54 @@ pub fn TextInput(
     invalid: Option<Option<String>>,
     value: ReadOnlySignal<TextInputType>,
     is_expanded: Option<bool>,
+    placeholder: Option<String>,
+    prefix: Option<String>,
+    suffix: Option<String>,
 ) -> Element {
     let value_str = match &*value.read() {
         TextInputType::Text(text) => text.clone(),
         TextInputType::Password(text) => text.clone(),
         TextInputType::Number(number) => format!("{}", number),
+        TextInputType::Date(date) => date.clone(),
     };
 
     let typ = match &*value.read() {
         TextInputType::Text(_) => "text",
         TextInputType::Number(_) => "number",
         TextInputType::Password(_) => "password",
+        TextInputType::Date(_) => "date",
     };
 
+    // Adornments are drawn as static buttons on either side of the input.
+    let addon = |text: Option<String>| {
+        text.filter(|text| !text.is_empty()).map(|text| {
+            rsx! {
+                div {
+                    class: "control",
+                    span {
+                        class: "button is-static",
+                        "{text}"
+                    }
+                }
+            }
+        })
+    };
+    let prefix = addon(prefix);
+    let suffix = addon(suffix);
+
+    let has_addons = prefix.is_some() || suffix.is_some();
+
     let class = "field".to_owned();
+    let class = if has_addons {
+        format!("{} has-addons", class)
+    } else {
+        class
+    };
+    let control_class = if has_addons {
+        "control is-expanded"
+    } else {
+        "control"
+    };
     let class = if matches!(is_expanded, Some(true)) {
         format!("{} is-expanded", class)
     } else {
@@ -55,12 +90,14 @@ pub fn TextInput(
     rsx! {
         div {
             class: "{class}",
+            { prefix }
             div {
-                class: "control",
+                class: "{control_class}",
                 input {
                     class: "{input_class}",
                     value: "{value_str}",
                     "type": typ,
+                    placeholder: placeholder.unwrap_or_default(),
                     oninput: move |evt| oninput.call(evt),
                     onblur: move |evt| match onblur {
                         Some(onblur) => onblur.call(evt),
@@ -68,6 +105,7 @@ pub fn TextInput(
                     },
                 }
             }
+            { suffix }
             { invalid }
         }
     }
@@ -240,3 +278,103 @@ pub fn Field(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use dioxus::prelude::*;
+
+    use crate::components::testing::render;
+
+    use super::{Button, ButtonFlavor, CheckInput, CheckStyle, Field, TextInput, TextInputType};
+
+    #[test]
+    fn button() {
+        fn app() -> Element {
+            rsx! {
+                Button {
+                    flavor: ButtonFlavor::Info,
+                    onclick: move |_| {},
+                    "Save"
+                }
+            }
+        }
+
+        let html = render(app);
+        assert!(
+            html.contains("<button class=\"button is-info\" type=\"button\">Save</button>"),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn disabled_button() {
+        fn app() -> Element {
+            rsx! {
+                Button {
+                    disabled: true,
+                    onclick: move |_| {},
+                    "Save"
+                }
+            }
+        }
+
+        let html = render(app);
+        assert!(
+            html.contains("<button class=\"button\" disabled"),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn field() {
+        fn app() -> Element {
+            rsx! {
+                Field {
+                    label: "Email",
+                    TextInput {
+                        oninput: move |_| {},
+                        value: TextInputType::Text("alex@example.com".to_owned()),
+                        invalid: Some("is required".to_owned()),
+                    }
+                }
+            }
+        }
+
+        let html = render(app);
+        assert!(
+            html.contains("<label class=\"label\">Email</label>"),
+            "{}",
+            html
+        );
+        assert!(
+            html.contains("class=\"input is-danger\" value=\"alex@example.com\" type=\"text\""),
+            "{}",
+            html
+        );
+        assert!(
+            html.contains("<p class=\"help is-danger\">is required</p>"),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn check_input() {
+        fn app() -> Element {
+            rsx! {
+                CheckInput {
+                    style: CheckStyle::Checkbox,
+                    label: "Require account".to_owned(),
+                    value: true,
+                    onclick: move |_| {},
+                }
+            }
+        }
+
+        let html = render(app);
+        assert!(html.contains("type=\"checkbox\" checked"), "{}", html);
+        assert!(html.contains("Require account</label>"), "{}", html);
+    }
+}
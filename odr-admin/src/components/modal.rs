@@ -55,3 +55,47 @@ pub fn Modal(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use dioxus::prelude::*;
+
+    use crate::components::testing::render;
+
+    use super::Modal;
+
+    #[test]
+    fn modal() {
+        fn app() -> Element {
+            rsx! {
+                Modal {
+                    onsubmit: move |_| {},
+                    onclose: move |_| {},
+                    title: "Create new Event",
+                    success_text: "Create",
+                    disable_submit: true,
+                    p { "Body" }
+                }
+            }
+        }
+
+        let html = render(app);
+        assert!(
+            html.contains("<p class=\"modal-card-title\">Create new Event</p>"),
+            "{}",
+            html
+        );
+        assert!(
+            html.contains("<section class=\"modal-card-body\"><p>Body</p></section>"),
+            "{}",
+            html
+        );
+        assert!(
+            html.contains(
+                "<button class=\"button is-success\" disabled type=\"button\">Create</button>"
+            ),
+            "{}",
+            html
+        );
+    }
+}
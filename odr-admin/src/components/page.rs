@@ -58,3 +58,55 @@ pub fn Page(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use dioxus::prelude::*;
+
+    use crate::{
+        components::testing::render,
+        hooks::{login::use_login_provider, toasts::use_toasts_provider, use_grpc_client_provider},
+    };
+
+    use super::Page;
+
+    // The login menu and breadcrumb need the app's contexts and a router.
+    #[derive(Clone, PartialEq, Routable)]
+    enum TestRoutes {
+        #[route("/")]
+        Home,
+    }
+
+    #[component]
+    fn Home() -> Element {
+        rsx! {
+            Page {
+                title: "My Organizations".to_owned(),
+                breadcrumb: vec![("Home".to_owned(), None)],
+                p { "Body" }
+            }
+        }
+    }
+
+    #[test]
+    fn page() {
+        fn app() -> Element {
+            use_toasts_provider();
+            use_grpc_client_provider();
+            use_login_provider();
+
+            rsx! {
+                Router::<TestRoutes> {}
+            }
+        }
+
+        let html = render(app);
+        assert!(
+            html.contains("<h1 class=\"title is-1\">My Organizations</h1>"),
+            "{}",
+            html
+        );
+        assert!(html.contains("<nav class=\"breadcrumb\">"), "{}", html);
+        assert!(html.contains("<p>Body</p>"), "{}", html);
+    }
+}
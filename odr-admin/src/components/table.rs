@@ -33,3 +33,46 @@ pub fn Table(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use dioxus::prelude::*;
+
+    use crate::components::testing::render;
+
+    use super::Table;
+
+    #[test]
+    fn striped() {
+        fn app() -> Element {
+            rsx! {
+                Table {
+                    is_striped: true,
+                    tbody {
+                        tr { td { "Alex" } }
+                    }
+                }
+            }
+        }
+
+        let html = render(app);
+        assert!(
+            html.contains("<table class=\"table is-striped\""),
+            "{}",
+            html
+        );
+        assert!(html.contains("<td>Alex</td>"), "{}", html);
+    }
+
+    #[test]
+    fn plain() {
+        fn app() -> Element {
+            rsx! {
+                Table {}
+            }
+        }
+
+        let html = render(app);
+        assert!(html.contains("<table class=\"table\""), "{}", html);
+    }
+}
@@ -0,0 +1,96 @@
+use dioxus::{
+    dioxus_core::{
+        AttributeValue, DynamicNode, NoOpMutations, RenderReturn, TemplateAttribute, TemplateNode,
+        VNode,
+    },
+    prelude::*,
+};
+
+// Renders an app to HTML without a browser, so component tests can assert on the markup.
+// Listeners and attributes set to false or none are left out, as a browser would show them.
+pub fn render(app: fn() -> Element) -> String {
+    let mut dom = VirtualDom::new(app);
+    dom.rebuild_in_place();
+    // Scopes marked dirty while building, like an error boundary that caught an error, render
+    // again before the markup is read.
+    dom.render_immediate(&mut NoOpMutations);
+
+    let mut html = String::new();
+    render_return(&dom, dom.base_scope().root_node(), &mut html);
+    html
+}
+
+fn render_return(dom: &VirtualDom, node: &RenderReturn, html: &mut String) {
+    match node {
+        RenderReturn::Ready(vnode) | RenderReturn::Aborted(vnode) => render_vnode(dom, vnode, html),
+    }
+}
+
+fn render_vnode(dom: &VirtualDom, vnode: &VNode, html: &mut String) {
+    for root in vnode.template.get().roots {
+        render_template_node(dom, vnode, root, html);
+    }
+}
+
+fn render_template_node(dom: &VirtualDom, vnode: &VNode, node: &TemplateNode, html: &mut String) {
+    match node {
+        TemplateNode::Element {
+            tag,
+            attrs,
+            children,
+            ..
+        } => {
+            html.push_str(&format!("<{}", tag));
+            for attr in attrs.iter() {
+                match attr {
+                    TemplateAttribute::Static { name, value, .. } => {
+                        html.push_str(&format!(" {}=\"{}\"", name, value))
+                    }
+                    TemplateAttribute::Dynamic { id } => {
+                        for attr in vnode.dynamic_attrs[*id].iter() {
+                            match &attr.value {
+                                AttributeValue::Text(value) => {
+                                    html.push_str(&format!(" {}=\"{}\"", attr.name, value))
+                                }
+                                AttributeValue::Int(value) => {
+                                    html.push_str(&format!(" {}=\"{}\"", attr.name, value))
+                                }
+                                AttributeValue::Float(value) => {
+                                    html.push_str(&format!(" {}=\"{}\"", attr.name, value))
+                                }
+                                AttributeValue::Bool(true) => {
+                                    html.push_str(&format!(" {}", attr.name))
+                                }
+                                _ => (),
+                            }
+                        }
+                    }
+                }
+            }
+            html.push('>');
+
+            for child in children.iter() {
+                render_template_node(dom, vnode, child, html);
+            }
+
+            html.push_str(&format!("</{}>", tag));
+        }
+        TemplateNode::Text { text } => html.push_str(text),
+        TemplateNode::Dynamic { id } | TemplateNode::DynamicText { id } => {
+            match &vnode.dynamic_nodes[*id] {
+                DynamicNode::Text(text) => html.push_str(&text.value),
+                DynamicNode::Placeholder(_) => (),
+                DynamicNode::Fragment(vnodes) => {
+                    for child in vnodes {
+                        render_vnode(dom, child, html);
+                    }
+                }
+                DynamicNode::Component(component) => {
+                    if let Some(scope) = component.mounted_scope(*id, vnode, dom) {
+                        render_return(dom, scope.root_node(), html);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -8,8 +8,10 @@ use common::proto::{
 use dioxus::prelude::*;
 use tonic_web_wasm_client::options::{Credentials, FetchOptions};
 
+pub mod locale;
 pub mod login;
 pub mod toasts;
+pub mod unsaved_changes;
 
 #[derive(Clone)]
 pub struct GrpcContext {
@@ -0,0 +1,33 @@
+use dioxus::prelude::*;
+
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub enum Locale {
+    #[default]
+    English,
+    Spanish,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 2] = [Locale::English, Locale::Spanish];
+
+    /// The locale's name, in its own language.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Locale::English => "English",
+            Locale::Spanish => "Español",
+        }
+    }
+}
+
+/// Values with a translated label for each locale, for display in the admin UI.
+pub trait Localized {
+    fn label(&self, locale: Locale) -> &'static str;
+}
+
+pub fn use_locale_provider() {
+    use_context_provider(|| Signal::new(Locale::default()));
+}
+
+pub fn use_locale() -> Signal<Locale> {
+    use_context::<Signal<Locale>>()
+}
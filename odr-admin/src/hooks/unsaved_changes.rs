@@ -0,0 +1,27 @@
+use std::rc::Rc;
+
+use dioxus::prelude::*;
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+
+const MESSAGE: &str = "You have unsaved changes. Leave this page anyway?";
+
+/// Asks the user to confirm before the page is closed or reloaded while `dirty` is set.
+pub fn use_unsaved_changes_guard(dirty: Signal<bool>) {
+    // Returning a message from onbeforeunload is what makes the browser prompt.
+    let handler = use_hook(|| {
+        Rc::new(Closure::<dyn FnMut() -> JsValue>::new(|| {
+            JsValue::from_str(MESSAGE)
+        }))
+    });
+
+    use_effect(move || {
+        let window = gloo_utils::window();
+        if dirty() {
+            window.set_onbeforeunload(Some(handler.as_ref().as_ref().unchecked_ref()));
+        } else {
+            window.set_onbeforeunload(None);
+        }
+    });
+
+    use_drop(|| gloo_utils::window().set_onbeforeunload(None));
+}
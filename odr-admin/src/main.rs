@@ -1,7 +1,9 @@
 #![allow(non_snake_case)]
 use common::proto::ClaimsRequest;
 use dioxus::prelude::*;
+use gloo_timers::future::TimeoutFuture;
 use hooks::{
+    locale::use_locale_provider,
     login::{use_login, use_login_provider},
     toasts::{use_toasts, use_toasts_provider},
     use_grpc_client, use_grpc_client_provider,
@@ -9,7 +11,10 @@ use hooks::{
 use pages::Routes;
 use tonic::Code;
 
-use crate::{components::with_toasts::WithToasts, hooks::login::LoginState};
+use crate::{
+    components::{error_boundary::AppErrorBoundary, with_toasts::WithToasts},
+    hooks::login::LoginState,
+};
 
 pub mod components;
 pub mod hooks;
@@ -24,22 +29,30 @@ fn App() -> Element {
     use_toasts_provider();
     use_grpc_client_provider();
     use_login_provider();
+    use_locale_provider();
 
     use_check_login_state();
     rsx! {
         WithToasts {
-            Router::<Routes> {}
+            AppErrorBoundary {
+                Router::<Routes> {}
+            }
         }
     }
 }
 
+// Browsers fire longer timeouts immediately, so far-off expiries are waited out in steps.
+const MAX_TIMEOUT_MILLIS: f64 = i32::MAX as f64;
+
 fn use_check_login_state() {
     let mut is_logged_in = use_login();
     let grpc = use_grpc_client();
     let mut toaster = use_toasts();
+    let mut recheck = use_signal(|| 0);
 
     let _ = use_resource(move || {
         let mut grpc = grpc.clone();
+        let _ = recheck.read();
         async move {
             let res = grpc.authentication.claims(ClaimsRequest {}).await;
             match res {
@@ -57,4 +70,29 @@ fn use_check_login_state() {
             };
         }
     });
+
+    // Show the session as logged out once the token expires, rather than letting the next call
+    // fail. Restarts whenever the login state changes.
+    let _ = use_resource(move || {
+        let expires_at = match &*is_logged_in.read() {
+            LoginState::LoggedIn(claims) => claims.exp.as_ref().map(|exp| exp.seconds),
+            _ => None,
+        };
+
+        async move {
+            let Some(expires_at) = expires_at else {
+                return;
+            };
+
+            let remaining = expires_at as f64 * 1000.0 - js_sys::Date::now();
+            if remaining > MAX_TIMEOUT_MILLIS {
+                TimeoutFuture::new(MAX_TIMEOUT_MILLIS as u32).await;
+                *recheck.write() += 1;
+                return;
+            }
+
+            TimeoutFuture::new(remaining.max(0.0) as u32).await;
+            *is_logged_in.write() = LoginState::LoggedOut;
+        }
+    });
 }
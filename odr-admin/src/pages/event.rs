@@ -63,6 +63,7 @@ pub fn Page(id: ReadOnlySignal<String>) -> Element {
                             )),
                         })),
                     }),
+                    ..Default::default()
                 }))
                 .await;
 
@@ -6,7 +6,7 @@ use dioxus::prelude::*;
 
 use crate::{
     components::{
-        form::{Button, ButtonFlavor, Field, TextInput, TextInputType},
+        form::{Button, ButtonFlavor, CheckInput, CheckStyle, Field, TextInput, TextInputType},
         menu::organization::{Menu, MenuItem},
         modal::Modal,
         page::Page as GenericPage,
@@ -35,6 +35,7 @@ pub fn Page(org_id: ReadOnlySignal<String>) -> Element {
                             operator: Some(string_query::Operator::Equals(org_id())),
                         })),
                     }),
+                    ..Default::default()
                 }))
                 .await;
 
@@ -179,6 +180,14 @@ fn LoadedPage(
     }
 }
 
+pub fn date_to_timestamp(date: &str) -> String {
+    if date.is_empty() {
+        return String::new();
+    }
+
+    format!("{}T00:00:00Z", date)
+}
+
 #[component]
 fn EventModal(
     org_id: MappedSignal<String>,
@@ -186,6 +195,11 @@ fn EventModal(
     onclose: EventHandler<()>,
 ) -> Element {
     let mut event_name = use_signal(String::new);
+    let mut starts_on = use_signal(String::new);
+    let mut ends_on = use_signal(String::new);
+    let mut registration_opens_on = use_signal(String::new);
+    let mut registration_closes_on = use_signal(String::new);
+    let mut require_account = use_signal(|| false);
     let mut submitted = use_signal(|| false);
     let client = use_grpc_client();
     let mut toaster = use_toasts();
@@ -203,6 +217,14 @@ fn EventModal(
                             id: "".to_owned(),
                             organization_id: org_id().clone(),
                             name: event_name.read().clone(),
+                            starts_at: date_to_timestamp(&starts_on.read()),
+                            ends_at: date_to_timestamp(&ends_on.read()),
+                            registration_closes_at: date_to_timestamp(&registration_closes_on.read()),
+                            require_account: *require_account.read(),
+                            slug: "".to_owned(),
+                            dedup_item_id: "".to_owned(),
+                            block_duplicates: false,
+                            registration_opens_at: date_to_timestamp(&registration_opens_on.read()),
                         }],
                     })}.await;
 
@@ -228,6 +250,43 @@ fn EventModal(
                         value: TextInputType::Text(event_name.read().clone()),
                     }
                 }
+                Field {
+                    label: "Starts On",
+                    TextInput {
+                        oninput: move |evt: FormEvent| starts_on.set(evt.value()),
+                        value: TextInputType::Date(starts_on.read().clone()),
+                    }
+                }
+                Field {
+                    label: "Ends On",
+                    TextInput {
+                        oninput: move |evt: FormEvent| ends_on.set(evt.value()),
+                        value: TextInputType::Date(ends_on.read().clone()),
+                    }
+                }
+                Field {
+                    label: "Registration Opens On",
+                    TextInput {
+                        oninput: move |evt: FormEvent| registration_opens_on.set(evt.value()),
+                        value: TextInputType::Date(registration_opens_on.read().clone()),
+                    }
+                }
+                Field {
+                    label: "Registration Closes On",
+                    TextInput {
+                        oninput: move |evt: FormEvent| registration_closes_on.set(evt.value()),
+                        value: TextInputType::Date(registration_closes_on.read().clone()),
+                    }
+                }
+                Field {
+                    label: "Require Account",
+                    CheckInput{
+                        style: CheckStyle::Checkbox,
+                        label: "Registrants must sign in to register".to_owned(),
+                        value: *require_account.read(),
+                        onclick: move |_| require_account.with_mut(|v| *v = !*v),
+                    }
+                }
             }
         }
     }
@@ -1,4 +1,7 @@
-use common::proto::{Organization, QueryOrganizationsRequest, UpsertOrganizationsRequest};
+use common::proto::{
+    organization_query, Organization, OrganizationQuery, QueryOrganizationsRequest,
+    UpsertOrganizationsRequest,
+};
 use dioxus::prelude::*;
 
 use crate::{
@@ -13,6 +16,8 @@ use crate::{
     pages::Routes,
 };
 
+const PAGE_SIZE: u32 = 50;
+
 pub fn Page() -> Element {
     log::info!("rendering organizations page");
     let grpc_client = use_grpc_client();
@@ -20,30 +25,47 @@ pub fn Page() -> Element {
     let mut toaster = use_toasts();
 
     let mut orgs = use_signal(|| Vec::new());
+    let mut page = use_signal(|| 0u32);
+    let mut filter = use_signal(String::new);
+    let mut total_count = use_signal(|| 0u64);
 
     let orgs_success = use_resource(move || {
         let mut grpc_client = grpc_client.clone();
         async move {
+            let query = match filter.read().as_str() {
+                "" => None,
+                name => Some(OrganizationQuery {
+                    query: Some(organization_query::Query::NameContains(name.to_owned())),
+                }),
+            };
+
             let result = grpc_client
                 .organizations
                 .query_organizations(tonic::Request::new(QueryOrganizationsRequest {
-                    query: None,
+                    query,
+                    limit: PAGE_SIZE,
+                    offset: page() * PAGE_SIZE,
                 }))
                 .await;
 
             let response = match result {
-                Ok(rsp) => rsp,
+                Ok(rsp) => rsp.into_inner(),
                 Err(e) => {
                     toaster.write().new_error(e.to_string());
                     return false;
                 }
             };
 
-            *orgs.write() = response.into_inner().organizations;
+            *orgs.write() = response.organizations;
+            total_count.set(response.total_count);
             true
         }
     });
 
+    let page_count = (total_count() as u32).div_ceil(PAGE_SIZE).max(1);
+    let first_shown = (page() * PAGE_SIZE + 1).min(total_count() as u32);
+    let last_shown = page() * PAGE_SIZE + orgs.read().len() as u32;
+
     let mut show_org_modal = use_signal(|| false);
 
     let nav = use_navigator();
@@ -60,6 +82,7 @@ pub fn Page() -> Element {
                 onsubmit: move |organization| {
                     show_org_modal.set(false);
                     orgs.write().push(organization);
+                    total_count += 1;
                 },
                 onclose: move |_| show_org_modal.set(false),
             }
@@ -71,6 +94,16 @@ pub fn Page() -> Element {
     let page_body = match &*orgs_success.value().read() {
         Some(true) => {
             rsx! {
+                Field {
+                    label: "Filter by Name",
+                    TextInput {
+                        oninput: move |evt: FormEvent| {
+                            filter.set(evt.value());
+                            page.set(0);
+                        },
+                        value: TextInputType::Text(filter.read().clone()),
+                    }
+                }
                 Table {
                     is_striped: true,
                     is_fullwidth: true,
@@ -112,6 +145,33 @@ pub fn Page() -> Element {
                         }) }
                     }
                 }
+                nav {
+                    class: "level",
+                    div {
+                        class: "level-left",
+                        p {
+                            class: "level-item",
+                            "Showing {first_shown}-{last_shown} of {total_count}"
+                        }
+                    }
+                    div {
+                        class: "level-right",
+                        Button {
+                            disabled: page() == 0,
+                            onclick: move |_| page -= 1,
+                            "Previous",
+                        }
+                        p {
+                            class: "level-item",
+                            "Page {page() + 1} of {page_count}"
+                        }
+                        Button {
+                            disabled: page() + 1 >= page_count,
+                            onclick: move |_| page += 1,
+                            "Next",
+                        }
+                    }
+                }
                 Button {
                     flavor: ButtonFlavor::Info,
                     onclick: move |_| show_org_modal.set(true),
@@ -153,6 +213,7 @@ fn OrganizationModal(onsubmit: EventHandler<Organization>, onclose: EventHandler
                             organizations: vec![Organization{
                                 id: "".to_owned(),
                                 name: organization_name.read().clone(),
+                                slug: "".to_owned(),
                             }],
                         }).await;
 
@@ -7,11 +7,12 @@ use tonic::Request;
 
 use crate::{
     components::{
-        form::{Button, ButtonFlavor, Field, TextInput, TextInputType},
+        form::{Button, ButtonFlavor, Field, SelectInput, TextInput, TextInputType},
         menu::profile::{Menu, MenuItem},
         page::Page as GenericPage,
     },
     hooks::{
+        locale::{use_locale, Locale},
         login::{use_login, LoginState},
         toasts::use_toasts,
         use_grpc_client,
@@ -102,6 +103,7 @@ fn LoadedPage(user: ReadOnlySignal<User>) -> Element {
     let mut user_info = use_signal(move || user().clone());
 
     let mut profile_form: Signal<ProfileForm> = use_signal(|| user().clone().into());
+    let mut locale = use_locale();
     rsx! {
         GenericPage {
             title: "Profile".to_string(),
@@ -112,6 +114,18 @@ fn LoadedPage(user: ReadOnlySignal<User>) -> Element {
                 }
             },
             form {
+                Field {
+                    label: "Language",
+                    SelectInput {
+                        options: Locale::ALL.iter().map(|locale| locale.name().to_owned()).collect::<Vec<_>>(),
+                        value: Locale::ALL.iter().position(|l| *l == locale()).unwrap_or_default(),
+                        onchange: move |evt: FormEvent| {
+                            if let Some(selected) = evt.value().parse::<usize>().ok().and_then(|idx| Locale::ALL.get(idx)) {
+                                locale.set(*selected);
+                            }
+                        },
+                    }
+                }
                 Field {
                     label: "Display Name",
                     TextInput {
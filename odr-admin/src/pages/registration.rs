@@ -2,13 +2,14 @@ use std::collections::{BTreeSet, HashMap, HashSet};
 
 use common::proto::{
     self, event_query, organization_query, registration_query, registration_schema_item_type,
-    registration_schema_query, string_query, EventQuery, Organization, OrganizationQuery,
-    QueryEventsRequest, QueryOrganizationsRequest, QueryRegistrationSchemasRequest,
-    QueryRegistrationsRequest, Registration, RegistrationItem, RegistrationQuery,
-    RegistrationSchema, RegistrationSchemaQuery, StringQuery, UpsertRegistrationsRequest,
+    registration_schema_query, string_query, CopyRegistrationRequest, DeleteEventRegistrationsRequest, EventQuery, ExportRegistrationsCsvRequest,
+    GroupType, Organization, OrganizationQuery, QueryEventsRequest, QueryOrganizationsRequest,
+    QueryRegistrationSchemasRequest, QueryRegistrationsRequest, Registration, RegistrationItem,
+    RegistrationQuery, RegistrationSchema, RegistrationSchemaItem, RegistrationSchemaQuery,
+    StringQuery, TextType, UpsertRegistrationsRequest,
 };
 use dioxus::prelude::*;
-use futures::join;
+use wasm_bindgen::JsCast;
 
 use crate::{
     components::{
@@ -25,10 +26,13 @@ use crate::{
     pages::Routes,
 };
 
-#[derive(Default, Clone)]
+#[derive(Default, Clone, PartialEq)]
 struct TableRegistration {
     id: String,
-    items: HashMap<String, String>,
+    // Values keyed by schema item id and group instance.
+    items: HashMap<(String, u32), String>,
+    status: i32,
+    user_id: String,
 }
 
 impl From<Registration> for TableRegistration {
@@ -38,8 +42,10 @@ impl From<Registration> for TableRegistration {
             items: registration
                 .items
                 .into_iter()
-                .map(|item| (item.schema_item_id, item.value))
+                .map(|item| ((item.schema_item_id, item.instance), item.value))
                 .collect(),
+            status: registration.status,
+            user_id: registration.user_id,
         }
     }
 }
@@ -51,11 +57,123 @@ fn to_proto_registration(registration: TableRegistration, event_id: String) -> R
         items: registration
             .items
             .into_iter()
-            .map(|(schema_item_id, value)| RegistrationItem {
+            .map(|((schema_item_id, instance), value)| RegistrationItem {
                 schema_item_id,
                 value,
+                instance,
             })
             .collect(),
+        status: registration.status,
+        user_id: registration.user_id,
+    }
+}
+
+fn is_section(item: &RegistrationSchemaItem) -> bool {
+    matches!(
+        item.r#type.as_ref().and_then(|t| t.r#type.as_ref()),
+        Some(registration_schema_item_type::Type::Section(_))
+    )
+}
+
+fn group_type(item: &RegistrationSchemaItem) -> Option<&GroupType> {
+    match item.r#type.as_ref().and_then(|t| t.r#type.as_ref()) {
+        Some(registration_schema_item_type::Type::Group(group)) => Some(group),
+        _ => None,
+    }
+}
+
+fn find_schema_item<'a>(
+    schema: &'a RegistrationSchema,
+    id: &str,
+) -> Option<&'a RegistrationSchemaItem> {
+    schema
+        .items
+        .iter()
+        .flat_map(|item| {
+            std::iter::once(item).chain(group_type(item).into_iter().flat_map(|group| group.items.iter()))
+        })
+        .find(|item| item.id == id)
+}
+
+// The instances of a group that the registration has values for.
+fn group_instances(registration: &TableRegistration, group: &GroupType) -> BTreeSet<u32> {
+    registration
+        .items
+        .keys()
+        .filter(|(id, _)| group.items.iter().any(|group_item| group_item.id == *id))
+        .map(|(_, instance)| *instance)
+        .collect()
+}
+
+// Groups show how many times they were filled in rather than their values.
+fn table_value(item: &RegistrationSchemaItem, registration: &TableRegistration) -> String {
+    match group_type(item) {
+        Some(group) => group_instances(registration, group).len().to_string(),
+        None => registration
+            .items
+            .get(&(item.id.clone(), 0))
+            .map(|v| display_value(item, v))
+            .unwrap_or_default(),
+    }
+}
+
+fn group_item_name(group: &RegistrationSchemaItem, instance: u32, item: &RegistrationSchemaItem) -> String {
+    format!("{} {}: {}", group.name, instance + 1, item.name)
+}
+
+const PAGE_SIZE: u32 = 50;
+
+// Select values may be stored either as an option index or as the option name itself.
+fn display_option(options: &[proto::SelectOption], value: &str) -> String {
+    value
+        .parse::<usize>()
+        .ok()
+        .and_then(|idx| options.get(idx))
+        .map(|option| option.name.clone())
+        .unwrap_or_else(|| value.to_owned())
+}
+
+fn display_value(item: &RegistrationSchemaItem, value: &str) -> String {
+    match item.r#type.as_ref().and_then(|t| t.r#type.as_ref()) {
+        Some(registration_schema_item_type::Type::Select(select)) => {
+            display_option(&select.options, value)
+        }
+        Some(registration_schema_item_type::Type::MultiSelect(select)) => {
+            itertools::Itertools::intersperse(
+                value.split(',').map(|v| display_option(&select.options, v)),
+                ", ".to_owned(),
+            )
+            .collect()
+        }
+        Some(registration_schema_item_type::Type::Checkbox(_)) => match value {
+            "true" => "Yes".to_owned(),
+            "false" => "No".to_owned(),
+            _ => value.to_owned(),
+        },
+        Some(registration_schema_item_type::Type::Tags(_)) => {
+            match serde_json::from_str::<Vec<String>>(value) {
+                Ok(tags) => tags.join(", "),
+                Err(_) => value.to_owned(),
+            }
+        }
+        _ => value.to_owned(),
+    }
+}
+
+fn download_csv(filename: &str, csv: &str) {
+    let href = format!(
+        "data:text/csv;charset=utf-8,{}",
+        js_sys::encode_uri_component(csv)
+    );
+
+    let Ok(link) = gloo_utils::document().create_element("a") else {
+        return;
+    };
+
+    let _ = link.set_attribute("href", &href);
+    let _ = link.set_attribute("download", filename);
+    if let Ok(link) = link.dyn_into::<web_sys::HtmlElement>() {
+        link.click();
     }
 }
 
@@ -107,6 +225,7 @@ pub fn Page(event_id: ReadOnlySignal<String>) -> Element {
                             )),
                         })),
                     }),
+                    ..Default::default()
                 }))
                 .await;
 
@@ -151,30 +270,16 @@ fn LoadedPage(org: ReadOnlySignal<Organization>, event: ReadOnlySignal<proto::Ev
         use_resource(move || {
             let mut grpc_client = grpc_client.clone();
             async move {
-                let schema_fut = grpc_client.registration_schema.query_registration_schemas(
-                    tonic::Request::new(QueryRegistrationSchemasRequest {
+                let schema_result = grpc_client
+                    .registration_schema
+                    .query_registration_schemas(tonic::Request::new(QueryRegistrationSchemasRequest {
                         query: Some(RegistrationSchemaQuery {
                             query: Some(registration_schema_query::Query::EventId(StringQuery {
                                 operator: Some(string_query::Operator::Equals(event().id.clone())),
                             })),
                         }),
-                    }),
-                );
-
-                let registrations_fut =
-                    grpc_client
-                        .registration
-                        .query_registrations(tonic::Request::new(QueryRegistrationsRequest {
-                            query: Some(RegistrationQuery {
-                                query: Some(registration_query::Query::EventId(StringQuery {
-                                    operator: Some(string_query::Operator::Equals(
-                                        event().id.clone(),
-                                    )),
-                                })),
-                            }),
-                        }));
-
-                let (registrations_result, schema_result) = join!(registrations_fut, schema_fut);
+                    }))
+                    .await;
 
                 let response = match schema_result {
                     Ok(rsp) => rsp,
@@ -194,22 +299,11 @@ fn LoadedPage(org: ReadOnlySignal<Organization>, event: ReadOnlySignal<proto::Ev
                         schema
                     });
 
-                let response = match registrations_result {
-                    Ok(rsp) => rsp,
-                    Err(e) => {
-                        toaster.write().new_error(e.to_string());
-                        return None;
-                    }
-                };
-
-                let registrations = response.into_inner().registrations;
-
                 rsx! {
                     PageBody{
                         org: org,
                         event: event,
                         schema: schema,
-                        registrations: registrations,
                     }
                 }
             }
@@ -241,19 +335,55 @@ fn PageBody(
     org: ReadOnlySignal<Organization>,
     event: ReadOnlySignal<proto::Event>,
     schema: ReadOnlySignal<RegistrationSchema>,
-    registrations: ReadOnlySignal<Vec<Registration>>,
 ) -> Element {
     let grpc_client = use_grpc_client();
     let mut toaster = use_toasts();
 
-    let mut registrations = use_signal(move || {
-        registrations
-            .read()
-            .iter()
-            .map(|r| -> TableRegistration { r.clone().into() })
-            .collect::<Vec<_>>()
+    let mut page = use_signal(|| 0u32);
+    let mut registrations = use_signal(Vec::<TableRegistration>::new);
+    let mut total_count = use_signal(|| 0u64);
+
+    let page_grpc_client = grpc_client.clone();
+    let _ = use_resource(move || {
+        let mut grpc_client = page_grpc_client.clone();
+        async move {
+            let result = grpc_client
+                .registration
+                .query_registrations(tonic::Request::new(QueryRegistrationsRequest {
+                    query: Some(RegistrationQuery {
+                        query: Some(registration_query::Query::EventId(StringQuery {
+                            operator: Some(string_query::Operator::Equals(event().id.clone())),
+                        })),
+                    }),
+                    limit: PAGE_SIZE,
+                    offset: page() * PAGE_SIZE,
+                }))
+                .await;
+
+            let response = match result {
+                Ok(rsp) => rsp.into_inner(),
+                Err(e) => {
+                    toaster.write().new_error(e.to_string());
+                    return;
+                }
+            };
+
+            *registrations.write() = response
+                .registrations
+                .into_iter()
+                .map(|r| r.into())
+                .collect();
+            total_count.set(response.total_count);
+        }
     });
 
+    let page_count = (total_count() as u32).div_ceil(PAGE_SIZE).max(1);
+    let first_shown = (page() * PAGE_SIZE + 1).min(total_count() as u32);
+    let last_shown = page() * PAGE_SIZE + registrations.read().len() as u32;
+
+    let export_grpc_client = grpc_client.clone();
+    let copy_grpc_client = grpc_client.clone();
+
     let mut show_modal = use_signal(|| None);
     let registration_modal = show_modal.read().as_ref().map(move |modal_registration: &TableRegistration| {
             rsx!{
@@ -293,6 +423,7 @@ fn PageBody(
                                 }
                                 None => {
                                     registrations.write().push(response_registration.into());
+                                    total_count += 1;
                                 }
                             }
 
@@ -307,6 +438,24 @@ fn PageBody(
             }
         });
 
+    let mut show_clear_modal = use_signal(|| false);
+    let clear_modal = show_clear_modal().then(move || {
+        rsx! {
+            ClearRegistrationsModal {
+                event: event,
+                ondelete: move |_| {
+                    registrations.write().clear();
+                    total_count.set(0);
+                    page.set(0);
+                    show_clear_modal.set(false);
+                },
+                onclose: move |_| {
+                    show_clear_modal.set(false);
+                },
+            }
+        }
+    });
+
     rsx! {
         Button {
             flavor: ButtonFlavor::Info,
@@ -315,13 +464,105 @@ fn PageBody(
             },
             "Add Registration",
         }
+        Button {
+            flavor: ButtonFlavor::Danger,
+            disabled: registrations.read().is_empty(),
+            onclick: move |_| {
+                show_clear_modal.set(true);
+            },
+            "Clear All Registrations",
+        }
+        Button {
+            disabled: total_count() == 0,
+            onclick: move |_| {
+                let mut grpc_client = export_grpc_client.clone();
+                spawn(async move {
+                    let result = grpc_client
+                        .registration
+                        .export_registrations_csv(ExportRegistrationsCsvRequest {
+                            event_id: event().id.clone(),
+                        })
+                        .await;
+
+                    match result {
+                        Ok(rsp) => download_csv(&format!("{}.csv", event().name), &rsp.into_inner().csv),
+                        Err(e) => toaster.write().new_error(e.to_string()),
+                    }
+                });
+            },
+            "Export CSV",
+        }
+        RegistrationsTable {
+            schema: schema,
+            registrations: registrations.read().clone(),
+            onedit: move |registration| {
+                show_modal.set(Some(registration));
+            },
+            oncopy: move |source_id| {
+                let mut grpc_client = copy_grpc_client.clone();
+                spawn(async move {
+                    let result = grpc_client
+                        .registration
+                        .copy_registration(CopyRegistrationRequest { source_id })
+                        .await;
+
+                    match result.map(|rsp| rsp.into_inner().registration) {
+                        Ok(Some(registration)) => show_modal.set(Some(registration.into())),
+                        Ok(None) => toaster.write().new_error("No registration returned".to_owned()),
+                        Err(e) => toaster.write().new_error(e.to_string()),
+                    }
+                });
+            },
+        }
+        nav {
+            class: "level",
+            div {
+                class: "level-left",
+                p {
+                    class: "level-item",
+                    "Showing {first_shown}-{last_shown} of {total_count}"
+                }
+            }
+            div {
+                class: "level-right",
+                Button {
+                    disabled: page() == 0,
+                    onclick: move |_| page -= 1,
+                    "Previous",
+                }
+                p {
+                    class: "level-item",
+                    "Page {page() + 1} of {page_count}"
+                }
+                Button {
+                    disabled: page() + 1 >= page_count,
+                    onclick: move |_| page += 1,
+                    "Next",
+                }
+            }
+        }
+        {registration_modal}
+        {clear_modal}
+    }
+}
+
+// One row per registration, with a column for every schema item that holds a value.
+#[component]
+fn RegistrationsTable(
+    schema: ReadOnlySignal<RegistrationSchema>,
+    registrations: Vec<TableRegistration>,
+    onedit: EventHandler<TableRegistration>,
+    // Called with the id of the registration to copy.
+    oncopy: EventHandler<String>,
+) -> Element {
+    rsx! {
         Table {
             is_striped: true,
             is_fullwidth: true,
             thead {
                 tr {
                     th{}
-                    { schema.read().items.iter().map(|item| {
+                    { schema.read().items.iter().filter(|item| !is_section(item)).map(|item| {
                         rsx! {
                             th {
                                 key: "{item.id}",
@@ -332,8 +573,9 @@ fn PageBody(
                 }
             }
             tbody {
-                {registrations.read().iter().map(|registration| {
+                {registrations.iter().map(|registration| {
                     let button_registration = registration.clone();
+                    let source_id = registration.id.clone();
                     rsx! {
                         tr {
                             key: "{registration.id}",
@@ -341,16 +583,22 @@ fn PageBody(
                                 Button {
                                     flavor: ButtonFlavor::Info,
                                     onclick: move |_| {
-                                        show_modal.set(Some(button_registration.clone()));
+                                        onedit.call(button_registration.clone());
                                     },
                                     "Edit"
                                 }
+                                Button {
+                                    onclick: move |_| {
+                                        oncopy.call(source_id.clone());
+                                    },
+                                    "Copy"
+                                }
                             }
-                            { schema.read().items.iter().map(|item| {
+                            { schema.read().items.iter().filter(|item| !is_section(item)).map(|item| {
                                 rsx! {
                                     td {
                                         key: "{item.id}",
-                                        {registration.items.get(&item.id).map(|v| v.as_str()).unwrap_or_default()}
+                                        {table_value(item, registration)}
                                     }
                                 }
                             })}
@@ -359,7 +607,59 @@ fn PageBody(
                 })}
             }
         }
-        {registration_modal}
+    }
+}
+
+#[component]
+fn ClearRegistrationsModal(
+    event: ReadOnlySignal<proto::Event>,
+    ondelete: EventHandler<()>,
+    onclose: EventHandler<()>,
+) -> Element {
+    let grpc_client = use_grpc_client();
+    let mut toaster = use_toasts();
+    let mut confirmation = use_signal(String::new);
+    let mut submitted = use_signal(|| false);
+
+    rsx! {
+        Modal {
+            title: "Clear All Registrations",
+            onclose: onclose,
+            onsubmit: move |_| {
+                submitted.set(true);
+                let mut grpc_client = grpc_client.clone();
+                spawn(async move {
+                    let result = grpc_client
+                        .registration
+                        .delete_event_registrations(DeleteEventRegistrationsRequest{
+                            event_id: event().id.clone(),
+                        })
+                        .await;
+
+                    match result {
+                        Ok(_) => ondelete.call(()),
+                        Err(e) => {
+                            toaster.write().new_error(e.to_string());
+                            submitted.set(false);
+                        }
+                    }
+                });
+            },
+            disable_submit: *submitted.read() || *confirmation.read() != event.read().name,
+            success_text: "Delete Registrations",
+
+            p {
+                class: "block",
+                "This permanently deletes every registration for {event.read().name}. Type the event name to confirm."
+            }
+            Field {
+                label: "Event Name",
+                TextInput {
+                    oninput: move |evt: FormEvent| confirmation.set(evt.value()),
+                    value: TextInputType::Text(confirmation.read().clone()),
+                }
+            }
+        }
     }
 }
 
@@ -482,6 +782,10 @@ enum FormRegistrationItemValue {
     Checkbox(SelectOption),
     Select(SelectOption),
     MultiSelect(MultiSelectOption),
+    Section(String),
+    // The group's header, holding its items so that more instances can be added.
+    Group(Vec<RegistrationSchemaItem>),
+    Tags(Vec<String>),
 }
 
 impl From<FormRegistrationItemValue> for String {
@@ -491,6 +795,12 @@ impl From<FormRegistrationItemValue> for String {
             FormRegistrationItemValue::Checkbox(v) => v.into(),
             FormRegistrationItemValue::Select(v) => v.into(),
             FormRegistrationItemValue::MultiSelect(v) => v.into(),
+            // The server stores tags as a JSON array, and no tags as an empty value.
+            FormRegistrationItemValue::Tags(v) if v.is_empty() => String::new(),
+            FormRegistrationItemValue::Tags(v) => serde_json::to_string(&v).unwrap(),
+            FormRegistrationItemValue::Section(_) | FormRegistrationItemValue::Group(_) => {
+                String::new()
+            }
         }
     }
 }
@@ -520,10 +830,128 @@ impl FormRegistrationItemValue {
 
 struct FormRegistrationItem {
     schema_item_id: String,
+    instance: u32,
     name: String,
     value: FormRegistrationItemValue,
 }
 
+fn form_item(
+    item: &RegistrationSchemaItem,
+    existing: Option<String>,
+    instance: u32,
+    name: String,
+) -> FormRegistrationItem {
+    let value = match item
+        .r#type
+        .as_ref()
+        .and_then(|t| t.r#type.as_ref())
+        .unwrap()
+    {
+        registration_schema_item_type::Type::Text(_) => {
+            FormRegistrationItemValue::Text(existing.unwrap_or_default())
+        }
+
+        registration_schema_item_type::Type::Checkbox(checkbox) => {
+            let options = vec!["No".to_owned(), "Yes".to_owned()];
+            // The server stores checkboxes as "true" or "false".
+            let select_option = match existing.as_deref() {
+                Some("true") => SelectOption::new(options, 1),
+                Some("false") => SelectOption::new(options, 0),
+                Some(other) => SelectOption::from_existing(options, other.to_owned()),
+                None => {
+                    let selected = if checkbox.default { 1 } else { 0 };
+                    SelectOption::new(options, selected)
+                }
+            };
+
+            FormRegistrationItemValue::Checkbox(select_option)
+        }
+
+        registration_schema_item_type::Type::Select(select) => {
+            let options = select.options.iter().map(|o| o.name.clone()).collect();
+            let select_option = match existing {
+                Some(registration_item) => SelectOption::from_existing(options, registration_item),
+                None => {
+                    let selected = select.default as usize;
+                    SelectOption::new(options, selected)
+                }
+            };
+
+            FormRegistrationItemValue::Select(select_option)
+        }
+
+        registration_schema_item_type::Type::MultiSelect(select) => {
+            let options = select.options.iter().map(|o| o.name.clone()).collect();
+            let select_option = match existing {
+                Some(registration_item) => {
+                    MultiSelectOption::from_existing(options, registration_item)
+                }
+                None => {
+                    let selected = select
+                        .defaults
+                        .iter()
+                        .cloned()
+                        .map(|d| d as usize)
+                        .collect();
+                    MultiSelectOption::new(options, selected)
+                }
+            };
+
+            FormRegistrationItemValue::MultiSelect(select_option)
+        }
+
+        registration_schema_item_type::Type::Section(section) => {
+            FormRegistrationItemValue::Section(section.body.clone())
+        }
+
+        registration_schema_item_type::Type::Group(group) => {
+            FormRegistrationItemValue::Group(group.items.clone())
+        }
+
+        registration_schema_item_type::Type::Tags(_) => FormRegistrationItemValue::Tags(
+            existing
+                .and_then(|existing| serde_json::from_str(&existing).ok())
+                .unwrap_or_default(),
+        ),
+    };
+
+    FormRegistrationItem {
+        schema_item_id: item.id.clone(),
+        instance,
+        name,
+        value,
+    }
+}
+
+// Adds a blank instance of the group whose header is at header_idx, after its existing ones.
+fn add_group_instance(form: &mut Vec<FormRegistrationItem>, header_idx: usize) {
+    let FormRegistrationItemValue::Group(group_items) = form[header_idx].value.clone() else {
+        return;
+    };
+    let group = RegistrationSchemaItem {
+        name: form[header_idx].name.clone(),
+        ..Default::default()
+    };
+
+    let start = header_idx + 1;
+    let end = start
+        + form[start..]
+            .iter()
+            .take_while(|entry| group_items.iter().any(|item| item.id == entry.schema_item_id))
+            .count();
+    let instance = form[start..end]
+        .iter()
+        .map(|entry| entry.instance + 1)
+        .max()
+        .unwrap_or(0);
+
+    let entries = group_items
+        .iter()
+        .map(|item| form_item(item, None, instance, group_item_name(&group, instance, item)))
+        .collect::<Vec<_>>();
+    form.splice(end..end, entries);
+}
+
 #[component]
 fn RegistrationModal(
     schema: ReadOnlySignal<RegistrationSchema>,
@@ -532,86 +960,31 @@ fn RegistrationModal(
     onclose: EventHandler<()>,
 ) -> Element {
     let mut form = use_signal(move || {
-        schema
-            .read()
-            .items
-            .iter()
-            .map(|item| {
-                let value = match item
-                    .r#type
-                    .as_ref()
-                    .and_then(|t| t.r#type.as_ref())
-                    .unwrap()
-                {
-                    registration_schema_item_type::Type::Text(_) => {
-                        FormRegistrationItemValue::Text(
-                            registration
-                                .read()
-                                .items
-                                .get(&item.id)
-                                .cloned()
-                                .unwrap_or_default(),
+        let registration = registration.read();
+        let mut form = Vec::new();
+        for item in schema.read().items.iter() {
+            form.push(form_item(
+                item,
+                registration.items.get(&(item.id.clone(), 0)).cloned(),
+                0,
+                item.name.clone(),
+            ));
+
+            if let Some(group) = group_type(item) {
+                for instance in group_instances(&registration, group) {
+                    form.extend(group.items.iter().map(|group_item| {
+                        form_item(
+                            group_item,
+                            registration.items.get(&(group_item.id.clone(), instance)).cloned(),
+                            instance,
+                            group_item_name(item, instance, group_item),
                         )
-                    }
-
-                    registration_schema_item_type::Type::Checkbox(checkbox) => {
-                        let options = vec!["No".to_owned(), "Yes".to_owned()];
-                        let select_option = match registration.read().items.get(&item.id) {
-                            Some(registration_item) => {
-                                SelectOption::from_existing(options, registration_item.clone())
-                            }
-                            None => {
-                                let selected = if checkbox.default { 1 } else { 0 };
-                                SelectOption::new(options, selected)
-                            }
-                        };
-
-                        FormRegistrationItemValue::Checkbox(select_option)
-                    }
-
-                    registration_schema_item_type::Type::Select(select) => {
-                        let options = select.options.iter().map(|o| o.name.clone()).collect();
-                        let select_option = match registration.read().items.get(&item.id) {
-                            Some(registration_item) => {
-                                SelectOption::from_existing(options, registration_item.clone())
-                            }
-                            None => {
-                                let selected = select.default as usize;
-                                SelectOption::new(options, selected)
-                            }
-                        };
-
-                        FormRegistrationItemValue::Select(select_option)
-                    }
-
-                    registration_schema_item_type::Type::MultiSelect(select) => {
-                        let options = select.options.iter().map(|o| o.name.clone()).collect();
-                        let select_option = match registration.read().items.get(&item.id) {
-                            Some(registration_item) => {
-                                MultiSelectOption::from_existing(options, registration_item.clone())
-                            }
-                            None => {
-                                let selected = select
-                                    .defaults
-                                    .iter()
-                                    .cloned()
-                                    .map(|d| d as usize)
-                                    .collect();
-                                MultiSelectOption::new(options, selected)
-                            }
-                        };
-
-                        FormRegistrationItemValue::MultiSelect(select_option)
-                    }
-                };
-
-                FormRegistrationItem {
-                    schema_item_id: item.id.clone(),
-                    name: item.name.clone(),
-                    value,
+                    }));
                 }
-            })
-            .collect::<Vec<_>>()
+            }
+        }
+
+        form
     });
     let mut submitted = use_signal(|| false);
 
@@ -627,12 +1000,16 @@ fn RegistrationModal(
             onclose: onclose,
             onsubmit: move |_| {
                 submitted.set(true);
-                let items = form.read().iter().map(|item| {
-                    (item.schema_item_id.clone(), item.value.clone().into())
-                }).collect();
+                let items = form.read().iter()
+                    .filter(|item| !matches!(item.value, FormRegistrationItemValue::Section(_) | FormRegistrationItemValue::Group(_)))
+                    .map(|item| {
+                        ((item.schema_item_id.clone(), item.instance), item.value.clone().into())
+                    }).collect();
                 let r = TableRegistration {
                     id: registration.read().id.clone(),
                     items,
+                    status: registration.read().status,
+                    user_id: registration.read().user_id.clone(),
                 };
                 onsubmit.call(r)
             },
@@ -641,15 +1018,59 @@ fn RegistrationModal(
 
             form {
                 { form.read().iter().enumerate().map(|(idx, item)| {
+                    if let FormRegistrationItemValue::Section(body) = &item.value {
+                        return rsx! {
+                            div {
+                                key: "{item.schema_item_id}",
+                                class: "block",
+                                h2 {
+                                    class: "subtitle",
+                                    "{item.name}"
+                                }
+                                p {
+                                    style: "white-space: pre-wrap;",
+                                    "{body}"
+                                }
+                            }
+                        };
+                    }
+
+                    if let FormRegistrationItemValue::Group(_) = &item.value {
+                        return rsx! {
+                            div {
+                                key: "{item.schema_item_id}",
+                                class: "block",
+                                h2 {
+                                    class: "subtitle",
+                                    "{item.name}"
+                                }
+                                Button {
+                                    flavor: ButtonFlavor::Info,
+                                    onclick: move |_| form.with_mut(|form| add_group_instance(form, idx)),
+                                    "Add Another"
+                                }
+                            }
+                        };
+                    }
+
                     rsx! {
                         Field {
-                            key: "{item.schema_item_id}",
+                            key: "{item.schema_item_id}-{item.instance}",
                             label: "{item.name}",
                             match item.value.clone() {
                                 FormRegistrationItemValue::Text(value) => {
+                                    let text_type = find_schema_item(&schema.read(), &item.schema_item_id)
+                                        .and_then(|schema_item| schema_item.r#type.as_ref())
+                                        .and_then(|t| match &t.r#type {
+                                            Some(registration_schema_item_type::Type::Text(text)) => Some(text.clone()),
+                                            _ => None,
+                                        })
+                                        .unwrap_or_default();
+
                                     rsx! {
                                         TextRegistrationForm {
                                             value: value,
+                                            text_type: text_type,
                                             oninput: move |v| {
                                                 form.write()[idx].value = FormRegistrationItemValue::Text(v);
                                             },
@@ -712,6 +1133,18 @@ fn RegistrationModal(
                                         }
                                     }
                                 }
+                                FormRegistrationItemValue::Tags(tags) => {
+                                    rsx! {
+                                        TagsRegistrationForm {
+                                            tags: tags,
+                                            ontagsinput: move |v| {
+                                                form.write()[idx].value = FormRegistrationItemValue::Tags(v);
+                                            },
+                                        }
+                                    }
+                                }
+                                // Sections and groups are rendered above and never reach the field wrapper.
+                                FormRegistrationItemValue::Section(_) | FormRegistrationItemValue::Group(_) => None,
                             }
                         }
                     }
@@ -722,13 +1155,86 @@ fn RegistrationModal(
 }
 
 #[component]
-fn TextRegistrationForm(value: ReadOnlySignal<String>, oninput: EventHandler<String>) -> Element {
+fn TextRegistrationForm(
+    value: ReadOnlySignal<String>,
+    text_type: TextType,
+    oninput: EventHandler<String>,
+) -> Element {
     rsx! {
         TextInput {
             oninput: move |evt: FormEvent| {
                 oninput.call(evt.value());
             },
             value: TextInputType::Text(value.read().clone()),
+            placeholder: text_type.placeholder,
+            prefix: text_type.prefix,
+            suffix: text_type.suffix,
+        }
+    }
+}
+
+// Tags are shown as chips that can each be removed, with an input below for adding more. Blank
+// tags are never added.
+#[component]
+fn TagsRegistrationForm(tags: Vec<String>, ontagsinput: EventHandler<Vec<String>>) -> Element {
+    let mut draft = use_signal(String::new);
+
+    let add_tags = tags.clone();
+    let mut add = move || {
+        let tag = draft.read().trim().to_owned();
+        if tag.is_empty() {
+            return;
+        }
+
+        let mut tags = add_tags.clone();
+        tags.push(tag);
+        ontagsinput.call(tags);
+        draft.set(String::new());
+    };
+
+    rsx! {
+        div {
+            class: "tags",
+            { tags.iter().cloned().enumerate().map(|(tag_idx, tag)| {
+                let tags = tags.clone();
+                rsx! {
+                    span {
+                        key: "{tag_idx}",
+                        class: "tag is-info",
+                        "{tag}"
+                        button {
+                            class: "delete is-small",
+                            "type": "button",
+                            onclick: move |_| {
+                                let mut tags = tags.clone();
+                                tags.remove(tag_idx);
+                                ontagsinput.call(tags);
+                            },
+                        }
+                    }
+                }
+            })}
+        }
+        div {
+            class: "field has-addons",
+            div {
+                class: "control is-expanded",
+                input {
+                    class: "input",
+                    "type": "text",
+                    value: "{draft}",
+                    placeholder: "Add a tag",
+                    oninput: move |evt| draft.set(evt.value()),
+                }
+            }
+            div {
+                class: "control",
+                Button {
+                    flavor: ButtonFlavor::Info,
+                    onclick: move |_| add(),
+                    "Add"
+                }
+            }
         }
     }
 }
@@ -820,3 +1326,108 @@ fn MultiSelectRegistrationForm(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use common::proto::{
+        registration_schema_item_type::Type as ItemType, CheckboxType, RegistrationSchema,
+        RegistrationSchemaItem, RegistrationSchemaItemType, SectionType, SelectOption, SelectType,
+        TextType,
+    };
+    use dioxus::prelude::*;
+
+    use crate::components::testing::render;
+
+    use super::{RegistrationsTable, TableRegistration};
+
+    fn item(id: &str, name: &str, typ: ItemType) -> RegistrationSchemaItem {
+        RegistrationSchemaItem {
+            id: id.to_owned(),
+            name: name.to_owned(),
+            r#type: Some(RegistrationSchemaItemType { r#type: Some(typ) }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn table_columns_follow_schema() {
+        fn app() -> Element {
+            let schema = RegistrationSchema {
+                event_id: "event".to_owned(),
+                items: vec![
+                    item("name", "Name", ItemType::Text(TextType::default())),
+                    item(
+                        "intro",
+                        "Intro",
+                        ItemType::Section(SectionType {
+                            body: "Welcome".to_owned(),
+                        }),
+                    ),
+                    item(
+                        "role",
+                        "Role",
+                        ItemType::Select(SelectType {
+                            options: vec![
+                                SelectOption {
+                                    name: "Leader".to_owned(),
+                                    ..Default::default()
+                                },
+                                SelectOption {
+                                    name: "Follower".to_owned(),
+                                    ..Default::default()
+                                },
+                            ],
+                            ..Default::default()
+                        }),
+                    ),
+                    item("paid", "Paid", ItemType::Checkbox(CheckboxType::default())),
+                ],
+            };
+
+            let registrations = ["Alex", "Sam"]
+                .into_iter()
+                .map(|name| TableRegistration {
+                    id: name.to_lowercase(),
+                    items: HashMap::from([
+                        (("name".to_owned(), 0), name.to_owned()),
+                        (("role".to_owned(), 0), "1".to_owned()),
+                        (("paid".to_owned(), 0), "true".to_owned()),
+                    ]),
+                    ..Default::default()
+                })
+                .collect::<Vec<_>>();
+
+            rsx! {
+                RegistrationsTable {
+                    schema: schema,
+                    registrations: registrations,
+                    onedit: |_| {},
+                    oncopy: |_| {},
+                }
+            }
+        }
+
+        let html = render(app);
+
+        // Sections hold no values, so they get no column.
+        assert!(
+            html.contains("<th></th><th>Name</th><th>Role</th><th>Paid</th></tr>"),
+            "{}",
+            html
+        );
+        assert!(!html.contains("Intro"), "{}", html);
+
+        for name in ["Alex", "Sam"] {
+            assert!(
+                html.contains(&format!(
+                    "<td>{}</td><td>Follower</td><td>Yes</td></tr>",
+                    name
+                )),
+                "{}",
+                html
+            );
+        }
+    }
+}
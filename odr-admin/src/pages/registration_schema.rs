@@ -1,5 +1,5 @@
 use std::{
-    collections::{BTreeSet, HashMap},
+    collections::{BTreeSet, HashMap, VecDeque},
     rc::Rc,
 };
 
@@ -15,18 +15,22 @@ use crate::{
         table::Table,
     },
     hooks::{
+        locale::{use_locale, Locale, Localized},
         toasts::{use_toasts, ToastManager},
-        use_grpc_client,
+        unsaved_changes::use_unsaved_changes_guard,
+        use_grpc_client, GrpcContext,
     },
-    pages::Routes,
+    pages::{events::date_to_timestamp, Routes},
 };
 use common::proto::{
-    self, event_query, multi_select_type, organization_query, registration_schema_item_type::Type as ItemType, registration_schema_query, select_type, string_query, text_type, CheckboxType, EventQuery, MultiSelectType, Organization, OrganizationQuery, QueryEventsRequest, QueryOrganizationsRequest, QueryRegistrationSchemasRequest, RegistrationSchema, RegistrationSchemaItem, RegistrationSchemaItemType, RegistrationSchemaQuery, SelectOption, SelectType, StringQuery, TextType, UpsertRegistrationSchemasRequest
+    self, multi_select_type, AddRegistrationSchemaItemRequest, registration_schema_item_type::Type as ItemType, registration_schema_query, select_type, string_query, text_type, ChangeRegistrationSchemaItemTypeRequest, CheckboxType, DeleteRegistrationSchemaItemRequest, GetEventWithOrganizationRequest, GroupType, MultiSelectType, Organization, QueryRegistrationSchemasRequest, RegistrationSchema, RenameRegistrationSchemaItemRequest, RegistrationSchemaItem, RegistrationSchemaItemType, RegistrationSchemaQuery, SectionType, SelectOption, SelectType, StringQuery, TagsType, TextType, UpdateRegistrationSchemaItemsRequest, ReorderRegistrationSchemaItemsRequest
 };
 use dioxus::prelude::*;
 use futures::join;
+use gloo_timers::future::TimeoutFuture;
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
+use tonic::Code;
 use uuid::Uuid;
 
 fn default_registration_schema_item() -> RegistrationSchemaItem {
@@ -37,8 +41,13 @@ fn default_registration_schema_item() -> RegistrationSchemaItem {
             r#type: Some(ItemType::Text(TextType {
                 default: String::default(),
                 display: text_type::Display::Small as i32,
+                ..Default::default()
             })),
         }),
+        required: false,
+        required_after: String::default(),
+        blank_on_copy: false,
+        display_idx: None,
     }
 }
 
@@ -48,13 +57,256 @@ struct Schema {
     event_id: String,
 }
 
-impl From<Schema> for RegistrationSchema {
-    fn from(schema: Schema) -> Self {
-        RegistrationSchema {
-            event_id: schema.event_id,
-            items: schema.items.into_iter().map(|(_, i)| i).collect(),
+const AUTOSAVE_IDLE_MILLIS: u32 = 1000;
+
+const UNDO_HISTORY_LIMIT: usize = 5;
+
+// How to reverse one change. Undo sends it as a request for that item alone, so it can't
+// overwrite what other admins changed in the meantime.
+#[derive(Clone, Debug, PartialEq)]
+enum UndoAction {
+    // A deleted item is added again and moved back to where it was.
+    Restore { idx: usize, item: RegistrationSchemaItem },
+    Move { id: String, idx: usize },
+    Rename { id: String, name: String },
+    // A duplicated item is deleted again.
+    Delete { id: String },
+}
+
+impl UndoAction {
+    fn restore(idx: usize, mut item: RegistrationSchemaItem) -> Self {
+        clear_schema_item_ids(&mut item);
+        UndoAction::Restore { idx, item }
+    }
+}
+
+fn push_undo_history(history: &mut VecDeque<UndoAction>, action: UndoAction) {
+    history.push_back(action);
+    if history.len() > UNDO_HISTORY_LIMIT {
+        history.pop_front();
+    }
+}
+
+fn type_kind(item: &RegistrationSchemaItem) -> Option<std::mem::Discriminant<ItemType>> {
+    item.r#type.as_ref().and_then(|t| t.r#type.as_ref()).map(std::mem::discriminant)
+}
+
+// A type change is sent on its own first, so the server can check it against existing
+// registrations instead of rejecting the whole edit.
+async fn update_item(grpc_client: &mut GrpcContext, stored: &RegistrationSchemaItem, item: RegistrationSchemaItem) -> Result<(), tonic::Status> {
+    if type_kind(stored) != type_kind(&item) {
+        grpc_client.registration_schema.change_registration_schema_item_type(ChangeRegistrationSchemaItemTypeRequest{
+            id: item.id.clone(),
+            r#type: item.r#type.clone(),
+        }).await?;
+    }
+
+    grpc_client.registration_schema.update_registration_schema_items(UpdateRegistrationSchemaItemsRequest{
+        items: vec![item],
+    }).await?;
+
+    Ok(())
+}
+
+// Items that were deleted on the server have to be re-created, so their ids can't be reused.
+fn clear_schema_item_ids(item: &mut RegistrationSchemaItem) {
+    item.id = String::default();
+
+    let options = match item.r#type.as_mut().and_then(|t| t.r#type.as_mut()) {
+        Some(ItemType::Select(select)) => &mut select.options,
+        Some(ItemType::MultiSelect(select)) => &mut select.options,
+        Some(ItemType::Group(group)) => {
+            group.items.iter_mut().for_each(clear_schema_item_ids);
+            return;
+        }
+        _ => return,
+    };
+
+    for option in options.iter_mut() {
+        option.id = String::default();
+    }
+}
+
+// Moves the item at from to to, shifting the ones in between over by one.
+fn move_item(schema: &mut Schema, from: usize, to: usize) {
+    if from < to {
+        schema.items[from..=to].rotate_left(1);
+    } else {
+        schema.items[to..=from].rotate_right(1);
+    }
+}
+
+async fn save_order(grpc_client: &mut GrpcContext, schema: &Schema) -> Result<(), tonic::Status> {
+    grpc_client.registration_schema.reorder_registration_schema_items(ReorderRegistrationSchemaItemsRequest{
+        event_id: schema.event_id.clone(),
+        item_ids: schema.items.iter().map(|(_, i)| i.id.clone()).collect(),
+    }).await?;
+
+    Ok(())
+}
+
+// The server adds new items last, so the item is shown there until it has been moved to idx.
+async fn add_item_at(grpc_client: &mut GrpcContext, mut schema: Signal<Schema>, item: RegistrationSchemaItem, idx: usize) -> Result<String, tonic::Status> {
+    let event_id = schema.read().event_id.clone();
+    let rsp = grpc_client.registration_schema.add_registration_schema_item(AddRegistrationSchemaItemRequest{
+        event_id,
+        item: Some(item),
+    }).await?;
+
+    let Some(saved) = rsp.into_inner().item else {
+        return Err(tonic::Status::internal("Item missing from response"));
+    };
+    let id = saved.id.clone();
+
+    let mut placed = {
+        let mut schema = schema.write();
+        schema.items.push((Uuid::new_v4(), saved));
+        schema.clone()
+    };
+    let last = placed.items.len() - 1;
+    move_item(&mut placed, last, idx.min(last));
+    save_order(grpc_client, &placed).await?;
+    *schema.write() = placed;
+
+    Ok(id)
+}
+
+async fn undo(grpc_client: &mut GrpcContext, mut schema: Signal<Schema>, action: UndoAction) -> Result<(), tonic::Status> {
+    match action {
+        UndoAction::Restore { idx, item } => {
+            add_item_at(grpc_client, schema, item, idx).await?;
+        }
+        UndoAction::Move { id, idx } => {
+            let mut moved = schema.read().clone();
+            let Some(from) = moved.items.iter().position(|(_, i)| i.id == id) else {
+                return Err(tonic::Status::not_found("Item not found"));
+            };
+            let last = moved.items.len() - 1;
+            move_item(&mut moved, from, idx.min(last));
+            save_order(grpc_client, &moved).await?;
+            *schema.write() = moved;
+        }
+        UndoAction::Rename { id, name } => {
+            let rsp = grpc_client.registration_schema.rename_registration_schema_item(RenameRegistrationSchemaItemRequest{
+                id: id.clone(),
+                name,
+            }).await?;
+
+            let header = rsp.into_inner().header.unwrap_or_default();
+            if let Some((_, item)) = schema.write().items.iter_mut().find(|(_, i)| i.id == id) {
+                item.name = header.name;
+            }
+        }
+        UndoAction::Delete { id } => {
+            grpc_client.registration_schema.delete_registration_schema_item(DeleteRegistrationSchemaItemRequest{
+                id: id.clone(),
+            }).await?;
+
+            schema.write().items.retain(|(_, i)| i.id != id);
         }
     }
+
+    Ok(())
+}
+
+// Names are unique within a schema regardless of case, so later copies of an item are numbered.
+fn copy_name(schema: &Schema, name: &str) -> String {
+    let taken = schema.items.iter().map(|(_, i)| i.name.to_lowercase()).collect::<BTreeSet<_>>();
+
+    let mut copy = format!("{} (copy)", name);
+    let mut number = 1;
+    while taken.contains(&copy.to_lowercase()) {
+        number += 1;
+        copy = format!("{} (copy {})", name, number);
+    }
+
+    copy
+}
+
+// A copy of the item at idx, to be added as a new item, so it has no ids.
+fn duplicate_item(schema: &Schema, idx: usize) -> RegistrationSchemaItem {
+    let mut item = schema.items[idx].1.clone();
+    clear_schema_item_ids(&mut item);
+    item.name = copy_name(schema, &item.name);
+    item
+}
+
+// Each edit supersedes the ones before it, so only the last edit before the field goes idle is saved.
+#[derive(Default)]
+struct AutosaveDebounce {
+    latest: u64,
+}
+
+impl AutosaveDebounce {
+    // Records an edit, returning a ticket to check once the idle delay is up.
+    fn edit(&mut self) -> u64 {
+        self.latest += 1;
+        self.latest
+    }
+
+    fn is_latest(&self, ticket: u64) -> bool {
+        self.latest == ticket
+    }
+}
+
+// Edits that come in while a save is running are coalesced: only the newest is kept, and the running
+// save sends it once its current request finishes.
+#[derive(Default)]
+struct AutosaveQueue {
+    pending: Option<RegistrationSchemaItem>,
+    saving: bool,
+}
+
+impl AutosaveQueue {
+    // Queues the item, returning whether a save has to be started for it.
+    fn push(&mut self, item: RegistrationSchemaItem) -> bool {
+        self.pending = Some(item);
+        !std::mem::replace(&mut self.saving, true)
+    }
+
+    // The next item the running save should send, or None once it's caught up.
+    fn next(&mut self) -> Option<RegistrationSchemaItem> {
+        let item = self.pending.take();
+        self.saving = item.is_some();
+        item
+    }
+
+    // Drops whatever is queued after a failed save, so the next edit starts a new one.
+    fn abort(&mut self) {
+        self.pending = None;
+        self.saving = false;
+    }
+}
+
+// The first build of a form is its baseline; anything different from it hasn't been saved yet.
+struct ChangeTracker<T> {
+    baseline: Option<T>,
+}
+
+impl<T> Default for ChangeTracker<T> {
+    fn default() -> Self {
+        ChangeTracker { baseline: None }
+    }
+}
+
+impl<T: PartialEq> ChangeTracker<T> {
+    // Records a build of the form, returning whether it differs from the baseline.
+    fn changed(&mut self, build: T) -> bool {
+        match &self.baseline {
+            Some(baseline) => *baseline != build,
+            None => {
+                self.baseline = Some(build);
+                false
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum AutosaveState {
+    Idle,
+    Saving,
+    Saved,
 }
 
 #[derive(Clone)]
@@ -82,59 +334,27 @@ pub fn Page(id: ReadOnlySignal<String>) -> Element {
         async move {
             let result = grpc_client
                 .events
-                .query_events(tonic::Request::new(QueryEventsRequest {
-                    query: Some(EventQuery {
-                        query: Some(event_query::Query::Id(StringQuery {
-                            operator: Some(string_query::Operator::Equals(id().clone())),
-                        })),
-                    }),
+                .get_event_with_organization(tonic::Request::new(GetEventWithOrganizationRequest {
+                    event_id: id(),
+                    ..Default::default()
                 }))
                 .await;
 
             let response = match result {
-                Ok(rsp) => rsp,
-                Err(e) => {
-                    toaster.write().new_error(e.to_string());
-                    return None;
-                }
-            };
-
-
-            let event = match response.into_inner().events.pop() {
-                Some(event) => event,
-                None => {
+                Ok(rsp) => rsp.into_inner(),
+                Err(e) if e.code() == Code::NotFound => {
                     nav.push(Routes::NotFound);
                     return None;
                 }
-            };
-
-            let result = grpc_client
-                .organizations
-                .query_organizations(tonic::Request::new(QueryOrganizationsRequest {
-                    query: Some(OrganizationQuery {
-                        query: Some(organization_query::Query::Id(StringQuery {
-                            operator: Some(string_query::Operator::Equals(event.organization_id.clone())),
-                        })),
-                    }),
-                }))
-                .await;
-
-            let response = match result {
-                Ok(rsp) => rsp,
                 Err(e) => {
                     toaster.write().new_error(e.to_string());
                     return None;
                 }
             };
 
-            let org = match response.into_inner().organizations.pop(){
-                Some(org) => org,
-                None => {
-                    toaster
-                        .write()
-                        .new_error("Organization not found".to_string());
-                    return None;
-                }
+            let (Some(event), Some(org)) = (response.event, response.organization) else {
+                toaster.write().new_error("Event not found".to_string());
+                return None;
             };
 
             rsx!{
@@ -184,8 +404,9 @@ fn LoadedPage(org: ReadOnlySignal<Organization>, event: ReadOnlySignal<proto::Ev
                     }
                 };
 
+                let mut response = response.into_inner();
+                let max_options = response.max_options as usize;
                 let schema = response
-                    .into_inner()
                     .registration_schemas
                     .pop()
                     .unwrap_or_else(|| {
@@ -199,6 +420,7 @@ fn LoadedPage(org: ReadOnlySignal<Organization>, event: ReadOnlySignal<proto::Ev
                         org: org,
                         event: event,
                         schema: schema,
+                        max_options: max_options,
                         grabbing_cursor: grabbing_cursor,
                     }
                 }
@@ -231,6 +453,8 @@ fn PageBody(
     org: ReadOnlySignal<Organization>,
     event: ReadOnlySignal<proto::Event>,
     schema: ReadOnlySignal<RegistrationSchema>,
+    // The most options the server accepts on a select item.
+    max_options: usize,
     grabbing_cursor: Signal<bool>,
 ) -> Element {
     let grpc_client = use_grpc_client();
@@ -238,6 +462,11 @@ fn PageBody(
 
     let mut show_schema_item_modal = use_signal(|| None);
     let mut show_delete_item_modal = use_signal(|| None);
+    let mut autosave_state = use_signal(|| AutosaveState::Idle);
+    let mut autosave_queue = use_signal(AutosaveQueue::default);
+    let mut undo_history = use_signal(VecDeque::<UndoAction>::new);
+    let mut unsaved_changes = use_signal(|| false);
+    use_unsaved_changes_guard(unsaved_changes);
 
     let mut table_row_refs = use_signal(HashMap::default);
     let drag_data = use_signal(|| None);
@@ -264,19 +493,24 @@ fn PageBody(
         show_schema_item_modal.read().as_ref().map(move |(key, item): &(Uuid, RegistrationSchemaItem)| {
             let key = key.clone();
             let item = item.clone();
+            let autosave_grpc_client = grpc_client.clone();
             rsx!{
                 SchemaItemModal{
                     initial: item,
+                    taken_names: schema.read().items.iter().filter(|(k, _)| *k != key).map(|(_, i)| i.name.clone()).collect::<Vec<_>>(),
+                    max_options: max_options,
                     grabbing_cursor: grabbing_cursor.clone(),
+                    dirty: unsaved_changes,
                     onsubmit: move |item: RegistrationSchemaItem| {
                         let mut send_schema = schema.read().clone();
                         let is_new = item.id == "";
+                        let mut stored = None;
                         if is_new {
                             send_schema.items.push((key.clone(), item.clone()));
                         } else {
                             let idx = send_schema.items.iter().position(|(_, i)| i.id == item.id);
                             match idx {
-                                Some(idx) => send_schema.items[idx] = (key.clone(), item.clone()),
+                                Some(idx) => stored = Some(std::mem::replace(&mut send_schema.items[idx], (key, item.clone())).1),
                                 None => {
                                     toaster.write().new_error("Item not found".to_owned());
                                     return;
@@ -286,37 +520,90 @@ fn PageBody(
 
                         let mut grpc_client = grpc_client.clone();
                         spawn(async move {
-                            let registration_schema = RegistrationSchema {
-                                event_id: send_schema.event_id.clone(),
-                                items: send_schema.items.iter().cloned().map(|(_, i)| i).collect(),
-                            };
+                            if is_new {
+                                let rsp = grpc_client.registration_schema.add_registration_schema_item(AddRegistrationSchemaItemRequest{
+                                    event_id: send_schema.event_id.clone(),
+                                    item: Some(item),
+                                }).await;
+
+                                let rsp = match rsp {
+                                    Ok(rsp) => rsp,
+                                    Err(e) => {
+                                        toaster.write().new_error(e.to_string());
+                                        return
+                                    }
+                                };
 
-                            let rsp = grpc_client.registration_schema.upsert_registration_schemas(UpsertRegistrationSchemasRequest{
-                                registration_schemas: vec![registration_schema],
-                            }).await;
+                                send_schema.items.last_mut().unwrap().1 = rsp.into_inner().item.unwrap();
+                            } else if let Some(stored) = stored {
+                                let rsp = update_item(&mut grpc_client, &stored, item).await;
 
-                            let rsp = match rsp {
-                                Ok(rsp) => rsp,
-                                Err(e) => {
+                                if let Err(e) = rsp {
                                     toaster.write().new_error(e.to_string());
                                     return
                                 }
-                            };
-
-                            if is_new {
-                                send_schema.items.last_mut().unwrap().1.id = rsp.into_inner().registration_schemas[0].items.pop().unwrap().id;
                             }
 
                             *schema.write() = send_schema;
+                            unsaved_changes.set(false);
                             show_schema_item_modal.set(None);
                         });
                     },
-                    onclose: move |_| show_schema_item_modal.set(None),
+                    onautosave: move |item: RegistrationSchemaItem| {
+                        // A save is already running; it will pick up the latest item when it finishes.
+                        if !autosave_queue.write().push(item) {
+                            return;
+                        }
+
+                        autosave_state.set(AutosaveState::Saving);
+                        let mut grpc_client = autosave_grpc_client.clone();
+                        spawn(async move {
+                            loop {
+                                let next = autosave_queue.write().next();
+                                let Some(item) = next else {
+                                    break;
+                                };
+
+                                let mut send_schema = schema.read().clone();
+                                let idx = match send_schema.items.iter().position(|(_, i)| i.id == item.id) {
+                                    Some(idx) => idx,
+                                    None => {
+                                        toaster.write().new_error("Item not found".to_owned());
+                                        autosave_queue.write().abort();
+                                        autosave_state.set(AutosaveState::Idle);
+                                        return;
+                                    },
+                                };
+                                let stored = std::mem::replace(&mut send_schema.items[idx].1, item.clone());
+
+                                let rsp = update_item(&mut grpc_client, &stored, item).await;
+
+                                if let Err(e) = rsp {
+                                    toaster.write().new_error(e.to_string());
+                                    autosave_queue.write().abort();
+                                    autosave_state.set(AutosaveState::Idle);
+                                    return;
+                                }
+
+                                *schema.write() = send_schema;
+                            }
+
+                            unsaved_changes.set(false);
+                            autosave_state.set(AutosaveState::Saved);
+                        });
+                    },
+                    autosave_state: autosave_state,
+                    onclose: move |_| {
+                        unsaved_changes.set(false);
+                        show_schema_item_modal.set(None);
+                    },
                 }
             } 
         })
     };
 
+    let undo_grpc_client = grpc_client.clone();
+
     let delete_item_modal = {
         let grpc_client = grpc_client.clone();
         show_delete_item_modal.read().as_ref().map(move |idx: &usize| {
@@ -324,29 +611,32 @@ fn PageBody(
             rsx!{
                 DeleteItemModal{
                     onsubmit: move |_| {
-                        let registration_schema = RegistrationSchema {
-                            event_id: schema.read().event_id.clone(),
-                            items: schema.read().items.iter().enumerate().filter(|(i, _)| *i != idx).map(|(_, (_, i))| i).cloned().collect(),
-                        };
+                        let item_id = schema.read().items[idx].1.id.clone();
 
                         let mut grpc_client = grpc_client.clone();
 
+                        // The row stays until the server has deleted the item, so a refused delete
+                        // leaves the table and the undo history as they were.
                         spawn(async move {
-                            let rsp = grpc_client.registration_schema.upsert_registration_schemas(UpsertRegistrationSchemasRequest{
-                                registration_schemas: vec![registration_schema],
+                            let rsp = grpc_client.registration_schema.delete_registration_schema_item(DeleteRegistrationSchemaItemRequest{
+                                id: item_id.clone(),
                             }).await;
 
                             match rsp {
-                                Ok(_) => {},
+                                Ok(_) => {
+                                    let Some(idx) = schema.read().items.iter().position(|(_, i)| i.id == item_id) else {
+                                        return;
+                                    };
+                                    let (key, item) = schema.write().items.remove(idx);
+                                    table_row_refs.write().remove(&key);
+                                    push_undo_history(&mut undo_history.write(), UndoAction::restore(idx, item));
+                                },
                                 Err(e) => {
                                     toaster.write().new_error(e.to_string());
                                 }
                             }
-                       
                         });
 
-                        schema.write().items.remove(idx);
-                        table_row_refs.write().remove(&schema.read().items[idx].0);
                         show_delete_item_modal.set(None);
                     },
                     onclose: move |_| show_delete_item_modal.set(None),
@@ -374,6 +664,9 @@ fn PageBody(
                     th{
                         style: "width: 1px",
                     }
+                    th{
+                        style: "width: 1px",
+                    }
                 }
             }
             tbody {
@@ -381,6 +674,8 @@ fn PageBody(
                     let item = i.clone();
                     let key = key.clone();
                     let grpc_client = grpc_client.clone();
+                    let duplicate_grpc_client = grpc_client.clone();
+                    let rename_grpc_client = grpc_client.clone();
 
                     rsx!{
                         tr {
@@ -402,18 +697,15 @@ fn PageBody(
                                                 let mut grpc_client = grpc_client.clone();
                                                 spawn(async move {
                                                     let mut schema_copy = schema.read().clone();
-                                                    if data.dragged < data.new_location {
-                                                        schema_copy.items[data.dragged..=data.new_location].rotate_left(1);
-                                                    } else {
-                                                        schema_copy.items[data.new_location..=data.dragged].rotate_right(1);
-                                                    }
+                                                    push_undo_history(&mut undo_history.write(), UndoAction::Move{
+                                                        id: schema_copy.items[data.dragged].1.id.clone(),
+                                                        idx: data.dragged,
+                                                    });
+                                                    move_item(&mut schema_copy, data.dragged, data.new_location);
 
                                                     *schema.write() = schema_copy.clone();
 
-
-                                                    let res = grpc_client.registration_schema.upsert_registration_schemas(UpsertRegistrationSchemasRequest{
-                                                        registration_schemas: vec![schema_copy.into()],
-                                                    }).await;
+                                                    let res = save_order(&mut grpc_client, &schema_copy).await;
 
                                                     if let Err(e) = res {
                                                         toaster.write().new_error(e.to_string());
@@ -424,18 +716,61 @@ fn PageBody(
                                     }
                                 }
                             }
-                            td{
-                                class: "col-auto",
-                                "{item.name}"
+                            ItemNameCell {
+                                name: item.name.clone(),
+                                onrename: move |name: String| {
+                                    let mut grpc_client = rename_grpc_client.clone();
+                                    let id = schema.read().items[idx].1.id.clone();
+                                    spawn(async move {
+                                        let rsp = grpc_client.registration_schema.rename_registration_schema_item(RenameRegistrationSchemaItemRequest{
+                                            id: id.clone(),
+                                            name,
+                                        }).await;
+
+                                        let header = match rsp {
+                                            Ok(rsp) => rsp.into_inner().header.unwrap_or_default(),
+                                            Err(e) => {
+                                                toaster.write().new_error(e.to_string());
+                                                return;
+                                            }
+                                        };
+
+                                        if let Some((_, item)) = schema.write().items.iter_mut().find(|(_, i)| i.id == id) {
+                                            let name = std::mem::replace(&mut item.name, header.name);
+                                            push_undo_history(&mut undo_history.write(), UndoAction::Rename{ id, name });
+                                        }
+                                    });
+                                },
                             }
                             td{
                                 style: "width: 1px",
                                 Button {
                                     flavor: ButtonFlavor::Info,
-                                    onclick: move |_| show_schema_item_modal.set(Some((key.clone(), item.clone()))),
+                                    onclick: move |_| {
+                                        autosave_state.set(AutosaveState::Idle);
+                                        show_schema_item_modal.set(Some((key, item.clone())));
+                                    },
                                     "Edit"
                                 }
                             }
+                            td{
+                                style: "width: 1px",
+                                Button {
+                                    flavor: ButtonFlavor::Info,
+                                    onclick: move |_| {
+                                        let copy = duplicate_item(&schema.read(), idx);
+
+                                        let mut grpc_client = duplicate_grpc_client.clone();
+                                        spawn(async move {
+                                            match add_item_at(&mut grpc_client, schema, copy, idx + 1).await {
+                                                Ok(id) => push_undo_history(&mut undo_history.write(), UndoAction::Delete{ id }),
+                                                Err(e) => toaster.write().new_error(e.to_string()),
+                                            }
+                                        });
+                                    },
+                                    "Duplicate"
+                                }
+                            }
                             td{
                                 Button {
                                     flavor: ButtonFlavor::Danger,
@@ -450,41 +785,163 @@ fn PageBody(
         }
         Button {
             flavor: ButtonFlavor::Info,
-            onclick: move |_| show_schema_item_modal.set(Some((Uuid::new_v4(), default_registration_schema_item()))),
+            onclick: move |_| {
+                autosave_state.set(AutosaveState::Idle);
+                show_schema_item_modal.set(Some((Uuid::new_v4(), default_registration_schema_item())));
+            },
             "Add Field"
         }
+        Button {
+            disabled: undo_history.read().is_empty(),
+            onclick: move |_| {
+                let Some(action) = undo_history.write().pop_back() else {
+                    return;
+                };
+
+                let mut grpc_client = undo_grpc_client.clone();
+                spawn(async move {
+                    if let Err(e) = undo(&mut grpc_client, schema, action).await {
+                        toaster.write().new_error(e.to_string());
+                    }
+                });
+            },
+            "Undo"
+        }
         { drag_line }
         { schema_item_modal }
         { delete_item_modal }
     }
 }
 
-#[derive(EnumIter, PartialEq, strum_macros::Display)]
+// Renaming only touches the label, so it's done in place instead of through the full edit modal.
+#[component]
+fn ItemNameCell(name: ReadOnlySignal<String>, onrename: EventHandler<String>) -> Element {
+    let mut draft = use_signal(|| None::<String>);
+
+    let mut commit = move || {
+        let Some(new_name) = draft.write().take() else {
+            return;
+        };
+
+        let new_name = new_name.trim().to_owned();
+        if !new_name.is_empty() && new_name != *name.read() {
+            onrename.call(new_name);
+        }
+    };
+
+    let Some(value) = draft() else {
+        return rsx! {
+            td {
+                class: "col-auto",
+                title: "Double-click to rename",
+                ondoubleclick: move |_| draft.set(Some(name())),
+                "{name}"
+            }
+        };
+    };
+
+    rsx! {
+        td {
+            class: "col-auto",
+            onkeydown: move |evt: KeyboardEvent| match evt.key() {
+                Key::Enter => commit(),
+                Key::Escape => draft.set(None),
+                _ => {}
+            },
+            TextInput {
+                value: TextInputType::Text(value),
+                oninput: move |evt: FormEvent| draft.set(Some(evt.value())),
+                onblur: move |_| commit(),
+            }
+        }
+    }
+}
+
+#[derive(EnumIter, PartialEq)]
 enum ItemFieldsType {
     Text,
     Checkbox,
     Select,
     MultiSelect,
+    Section,
+    Group,
+    Tags,
 }
 
-#[derive(EnumIter, PartialEq, strum_macros::Display)]
+impl Localized for ItemFieldsType {
+    fn label(&self, locale: Locale) -> &'static str {
+        match (self, locale) {
+            (ItemFieldsType::Text, Locale::English) => "Text",
+            (ItemFieldsType::Text, Locale::Spanish) => "Texto",
+            (ItemFieldsType::Checkbox, Locale::English) => "Checkbox",
+            (ItemFieldsType::Checkbox, Locale::Spanish) => "Casilla",
+            (ItemFieldsType::Select, Locale::English) => "Select",
+            (ItemFieldsType::Select, Locale::Spanish) => "Selección",
+            (ItemFieldsType::MultiSelect, Locale::English) => "MultiSelect",
+            (ItemFieldsType::MultiSelect, Locale::Spanish) => "Selección múltiple",
+            (ItemFieldsType::Section, Locale::English) => "Section",
+            (ItemFieldsType::Section, Locale::Spanish) => "Sección",
+            (ItemFieldsType::Group, Locale::English) => "Group",
+            (ItemFieldsType::Group, Locale::Spanish) => "Grupo",
+            (ItemFieldsType::Tags, Locale::English) => "Tags",
+            (ItemFieldsType::Tags, Locale::Spanish) => "Etiquetas",
+        }
+    }
+}
+
+#[derive(EnumIter, PartialEq)]
 enum TextDisplayType {
     Small,
     Large,
 }
 
-#[derive(EnumIter, PartialEq, strum_macros::Display)]
+impl Localized for TextDisplayType {
+    fn label(&self, locale: Locale) -> &'static str {
+        match (self, locale) {
+            (TextDisplayType::Small, Locale::English) => "Small",
+            (TextDisplayType::Small, Locale::Spanish) => "Pequeño",
+            (TextDisplayType::Large, Locale::English) => "Large",
+            (TextDisplayType::Large, Locale::Spanish) => "Grande",
+        }
+    }
+}
+
+#[derive(EnumIter, PartialEq)]
 enum SelectDisplayType {
     Radio,
     Dropdown,
 }
 
-#[derive(EnumIter, PartialEq, strum_macros::Display)]
+impl Localized for SelectDisplayType {
+    fn label(&self, locale: Locale) -> &'static str {
+        match (self, locale) {
+            (SelectDisplayType::Radio, Locale::English) => "Radio",
+            (SelectDisplayType::Radio, Locale::Spanish) => "Botones de opción",
+            (SelectDisplayType::Dropdown, Locale::English) => "Dropdown",
+            (SelectDisplayType::Dropdown, Locale::Spanish) => "Desplegable",
+        }
+    }
+}
+
+#[derive(EnumIter, PartialEq)]
 enum MultiSelectDisplayType {
     Checkboxes,
     MultiselectBox,
 }
 
+impl Localized for MultiSelectDisplayType {
+    fn label(&self, locale: Locale) -> &'static str {
+        match (self, locale) {
+            (MultiSelectDisplayType::Checkboxes, Locale::English) => "Checkboxes",
+            (MultiSelectDisplayType::Checkboxes, Locale::Spanish) => "Casillas",
+            (MultiSelectDisplayType::MultiselectBox, Locale::English) => "MultiselectBox",
+            (MultiSelectDisplayType::MultiselectBox, Locale::Spanish) => {
+                "Cuadro de selección múltiple"
+            }
+        }
+    }
+}
+
 #[derive(Default, Clone)]
 struct FieldsSelect {
     display: usize,
@@ -499,6 +956,9 @@ struct FieldsMultiSelect {
 struct FieldsText {
     default: String,
     display: usize,
+    placeholder: String,
+    prefix: String,
+    suffix: String,
 }
 
 #[derive(Clone, Debug)]
@@ -521,6 +981,12 @@ struct ItemFields {
     id: String,
     name: String,
     name_touched: bool,
+    required: bool,
+    // Date part of required_after, as edited by the date input.
+    required_after: String,
+    blank_on_copy: bool,
+    // Position on the registrant form, empty to use schema order.
+    display_idx: String,
     typ: usize,
     text_type: FieldsText,
     checkbox_type: CheckboxType,
@@ -528,6 +994,9 @@ struct ItemFields {
     defaults: BTreeSet<usize>,
     multi_select_type: FieldsMultiSelect,
     options: Vec<FieldSelectOption>,
+    section_type: SectionType,
+    group_type: GroupType,
+    tags_type: TagsType,
     validation_error: Option<String>,
 }
 
@@ -537,6 +1006,10 @@ impl Default for ItemFields {
             id: String::default(),
             name: String::default(),
             name_touched: false,
+            required: false,
+            required_after: String::default(),
+            blank_on_copy: false,
+            display_idx: String::default(),
             typ: 0,
             text_type: FieldsText::default(),
             checkbox_type: CheckboxType::default(),
@@ -544,15 +1017,18 @@ impl Default for ItemFields {
             multi_select_type: FieldsMultiSelect::default(),
             options: Vec::default(),
             defaults: BTreeSet::default(),
+            section_type: SectionType::default(),
+            group_type: GroupType::default(),
+            tags_type: TagsType::default(),
             validation_error: None,
         }
     }
 }
 
-fn enum_selects<Enum: IntoEnumIterator + std::fmt::Display>() -> Vec<(Enum, String)> {
+fn enum_selects<Enum: IntoEnumIterator + Localized>(locale: Locale) -> Vec<(Enum, String)> {
     Enum::iter()
         .map(|e| {
-            let estr = format!("{}", e);
+            let estr = e.label(locale).to_owned();
             (e, estr)
         })
         .collect::<Vec<_>>()
@@ -561,35 +1037,56 @@ fn enum_selects<Enum: IntoEnumIterator + std::fmt::Display>() -> Vec<(Enum, Stri
 #[component]
 fn SchemaItemModal(
     initial: ReadOnlySignal<RegistrationSchemaItem>,
+    // Names of the schema's other items, which this one can't share.
+    taken_names: Vec<String>,
     onsubmit: EventHandler<RegistrationSchemaItem>,
+    onautosave: EventHandler<RegistrationSchemaItem>,
+    autosave_state: ReadOnlySignal<AutosaveState>,
     onclose: EventHandler<()>,
+    max_options: usize,
     grabbing_cursor: Signal<bool>,
+    dirty: Signal<bool>,
 ) -> Element {
     let mut toaster = use_toasts();
-    let type_selects = use_memo(|| enum_selects::<ItemFieldsType>());
-    let text_display_selects = use_memo(enum_selects::<TextDisplayType>);
-    let select_display_selects = use_memo(enum_selects::<SelectDisplayType>);
-    let multi_select_display_selects = use_memo(enum_selects::<MultiSelectDisplayType>);
+    let mut autosave_debounce = use_signal(AutosaveDebounce::default);
+    let locale = use_locale();
+    let type_selects = use_memo(move || enum_selects::<ItemFieldsType>(locale()));
+    let text_display_selects = use_memo(move || enum_selects::<TextDisplayType>(locale()));
+    let select_display_selects = use_memo(move || enum_selects::<SelectDisplayType>(locale()));
+    let multi_select_display_selects =
+        use_memo(move || enum_selects::<MultiSelectDisplayType>(locale()));
     let drag_data = use_signal(|| None);
     let mut field_refs = use_signal(HashMap::default);
     let success_text = use_memo(move || if initial().id == "" { "Create" } else { "Update" });
 
     let mut fields = use_signal(|| {
         let item = initial().clone();
-
-        let (typ, text_type, checkbox_type, defaults, select_type, multi_select_type, options) =
+        let group_type = match item.r#type.as_ref().and_then(|t| t.r#type.as_ref()) {
+            Some(ItemType::Group(group)) => group.clone(),
+            _ => GroupType::default(),
+        };
+        let tags_type = match item.r#type.as_ref().and_then(|t| t.r#type.as_ref()) {
+            Some(ItemType::Tags(tags)) => tags.clone(),
+            _ => TagsType::default(),
+        };
+
+        let (typ, text_type, checkbox_type, defaults, select_type, multi_select_type, options, section_type) =
             match item.r#type.unwrap().r#type.unwrap() {
                 ItemType::Text(text) => (
                     0,
                     FieldsText {
                         default: text.default,
                         display: text.display as usize,
+                        placeholder: text.placeholder,
+                        prefix: text.prefix,
+                        suffix: text.suffix,
                     },
                     CheckboxType::default(),
                     BTreeSet::default(),
                     FieldsSelect::default(),
                     FieldsMultiSelect::default(),
                     Vec::default(),
+                    SectionType::default(),
                 ),
                 ItemType::Checkbox(checkbox) => (
                     1,
@@ -599,6 +1096,7 @@ fn SchemaItemModal(
                     FieldsSelect::default(),
                     FieldsMultiSelect::default(),
                     Vec::default(),
+                    SectionType::default(),
                 ),
                 ItemType::Select(select) => {
                     let options = select
@@ -620,6 +1118,7 @@ fn SchemaItemModal(
                         },
                         FieldsMultiSelect::default(),
                         options,
+                        SectionType::default(),
                     )
                 }
                 ItemType::MultiSelect(multiselect) => {
@@ -645,14 +1144,49 @@ fn SchemaItemModal(
                             display: multiselect.display as usize,
                         },
                         options,
+                        SectionType::default(),
                     )
                 }
+                ItemType::Section(section) => (
+                    4,
+                    FieldsText::default(),
+                    CheckboxType::default(),
+                    BTreeSet::default(),
+                    FieldsSelect::default(),
+                    FieldsMultiSelect::default(),
+                    Vec::default(),
+                    section,
+                ),
+                ItemType::Group(_) => (
+                    5,
+                    FieldsText::default(),
+                    CheckboxType::default(),
+                    BTreeSet::default(),
+                    FieldsSelect::default(),
+                    FieldsMultiSelect::default(),
+                    Vec::default(),
+                    SectionType::default(),
+                ),
+                ItemType::Tags(_) => (
+                    6,
+                    FieldsText::default(),
+                    CheckboxType::default(),
+                    BTreeSet::default(),
+                    FieldsSelect::default(),
+                    FieldsMultiSelect::default(),
+                    Vec::default(),
+                    SectionType::default(),
+                ),
             };
 
         ItemFields {
             id: item.id,
             name: item.name,
             name_touched: false,
+            required: item.required,
+            required_after: item.required_after.get(..10).unwrap_or_default().to_owned(),
+            blank_on_copy: item.blank_on_copy,
+            display_idx: item.display_idx.map(|idx| idx.to_string()).unwrap_or_default(),
             typ,
             text_type,
             checkbox_type,
@@ -660,10 +1194,83 @@ fn SchemaItemModal(
             select_type,
             multi_select_type,
             options,
+            section_type,
+            group_type,
+            tags_type,
             validation_error: None,
         }
     });
 
+    let build_item = move || fields.with(|fields| {
+        RegistrationSchemaItem {
+            id: fields.id.clone(),
+            name: fields.name.clone(),
+            r#type: Some(RegistrationSchemaItemType{
+                r#type: Some(match type_selects.read()[fields.typ].0 {
+                    ItemFieldsType::Text => ItemType::Text(TextType {
+                        default: fields.text_type.default.clone(),
+                        display: match text_display_selects.read()[fields.text_type.display].0 {
+                            TextDisplayType::Small => text_type::Display::Small,
+                            TextDisplayType::Large => text_type::Display::Large,
+                        } as i32,
+                        placeholder: fields.text_type.placeholder.clone(),
+                        prefix: fields.text_type.prefix.clone(),
+                        suffix: fields.text_type.suffix.clone(),
+                    }),
+                    ItemFieldsType::Checkbox => ItemType::Checkbox(CheckboxType {
+                        default: fields.checkbox_type.default,
+                    }),
+                    ItemFieldsType::Select => ItemType::Select(SelectType{
+                        default: fields.defaults.first().copied().unwrap_or(0) as u32,
+                        display: match select_display_selects.read()[fields.select_type.display].0 {
+                            SelectDisplayType::Radio => select_type::Display::Radio,
+                            SelectDisplayType::Dropdown => select_type::Display::Dropdown,
+                        } as i32,
+                        options: fields.options.iter().map(|o| o.option.clone()).collect(),
+                    }),
+                    ItemFieldsType::MultiSelect => ItemType::MultiSelect(MultiSelectType{
+                        defaults: fields.defaults.iter().map(|idx| *idx as u32).collect(),
+                        display: match multi_select_display_selects.read()[fields.multi_select_type.display].0 {
+                            MultiSelectDisplayType::Checkboxes => multi_select_type::Display::Checkboxes,
+                            MultiSelectDisplayType::MultiselectBox => multi_select_type::Display::MultiselectBox,
+                        } as i32,
+                        options: fields.options.iter().map(|o| o.option.clone()).collect(),
+                    }),
+                    ItemFieldsType::Section => ItemType::Section(SectionType {
+                        body: fields.section_type.body.clone(),
+                    }),
+                    ItemFieldsType::Group => ItemType::Group(fields.group_type.clone()),
+                    ItemFieldsType::Tags => ItemType::Tags(fields.tags_type.clone()),
+                }),
+            }),
+            required: fields.required,
+            required_after: date_to_timestamp(&fields.required_after),
+            blank_on_copy: fields.blank_on_copy,
+            display_idx: fields.display_idx.trim().parse().ok(),
+        }
+    });
+
+    let mut change_tracker = use_signal(ChangeTracker::default);
+    use_effect(move || {
+        let item = build_item();
+        if change_tracker.write().changed(item) {
+            dirty.set(true);
+        }
+    });
+
+    let autosave_indicator = match *autosave_state.read() {
+        AutosaveState::Idle => None,
+        AutosaveState::Saving => Some("Saving..."),
+        AutosaveState::Saved => Some("Saved"),
+    }.map(|text| {
+        rsx!{
+            p {
+                class: "help has-text-grey",
+                "{text}"
+            }
+        }
+    });
+
     let validation_error = fields.read().validation_error.as_ref().map(|err| {
         rsx!{
             p {
@@ -680,10 +1287,15 @@ fn SchemaItemModal(
         }
     });
 
+    let duplicate_name = {
+        let name = fields.read().name.to_lowercase();
+        taken_names.iter().any(|taken| taken.to_lowercase() == name)
+    };
+
     rsx! { Modal {
         title: "New Field",
         onsubmit: move |_| {
-            if fields.read().name == "" {
+            if fields.read().name.is_empty() || duplicate_name {
                 fields.with_mut(|fields| {
                     fields.name_touched = true;
                     fields.validation_error = Some("One or more fields have errors".to_owned())
@@ -691,42 +1303,7 @@ fn SchemaItemModal(
                 return;
             }
 
-            let item = fields.with(|fields| {
-                RegistrationSchemaItem {
-                    id: fields.id.clone(),
-                    name: fields.name.clone(),
-                    r#type: Some(RegistrationSchemaItemType{
-                        r#type: Some(match type_selects.read()[fields.typ].0 {
-                            ItemFieldsType::Text => ItemType::Text(TextType {
-                                default: fields.text_type.default.clone(),
-                                display: match text_display_selects.read()[fields.text_type.display].0 {
-                                    TextDisplayType::Small => text_type::Display::Small,
-                                    TextDisplayType::Large => text_type::Display::Large,
-                                } as i32,
-                            }),
-                            ItemFieldsType::Checkbox => ItemType::Checkbox(CheckboxType {
-                                default: fields.checkbox_type.default,
-                            }),
-                            ItemFieldsType::Select => ItemType::Select(SelectType{
-                                default: fields.defaults.first().copied().unwrap_or(0) as u32,
-                                display: match select_display_selects.read()[fields.select_type.display].0 {
-                                    SelectDisplayType::Radio => select_type::Display::Radio,
-                                    SelectDisplayType::Dropdown => select_type::Display::Dropdown,
-                                } as i32,
-                                options: fields.options.iter().map(|o| o.option.clone()).collect(),
-                            }),
-                            ItemFieldsType::MultiSelect => ItemType::MultiSelect(MultiSelectType{
-                                defaults: fields.defaults.iter().map(|idx| *idx as u32).collect(),
-                                display: match multi_select_display_selects.read()[fields.multi_select_type.display].0 {
-                                    MultiSelectDisplayType::Checkboxes => multi_select_type::Display::Checkboxes,
-                                    MultiSelectDisplayType::MultiselectBox => multi_select_type::Display::MultiselectBox,
-                                } as i32,
-                                options: fields.options.iter().map(|o| o.option.clone()).collect(),
-                            }),
-                        }),
-                    }),
-                }
-            });
+            let item = build_item();
 
             onsubmit.call(item);
         },
@@ -743,15 +1320,33 @@ fn SchemaItemModal(
                             fields.name = evt.value();
                             fields.name_touched = false;
                             fields.validation_error = None;
-                        })
+                        });
+
+                        // Only existing items are autosaved; new items still need an explicit create.
+                        if fields.read().id.is_empty() || fields.read().name.is_empty() {
+                            return;
+                        }
+
+                        let ticket = autosave_debounce.write().edit();
+                        spawn(async move {
+                            TimeoutFuture::new(AUTOSAVE_IDLE_MILLIS).await;
+                            if !autosave_debounce.read().is_latest(ticket) {
+                                return;
+                            }
+
+                            onautosave.call(build_item());
+                        });
                     },
                     onblur: move |_| fields.write().name_touched = true,
                     invalid: if fields.read().name_touched && fields.read().name == "" {
                             Some("Name is required".to_owned())
+                        } else if duplicate_name {
+                            Some("Another field already has this name".to_owned())
                         } else {
                             None
                         },
                 }
+                { autosave_indicator }
             }
             Field {
                 label: "Type",
@@ -784,6 +1379,38 @@ fn SchemaItemModal(
                     value: fields.read().typ,
                 }
             }
+            Field {
+                label: "Form Position",
+                TextInput{
+                    value: TextInputType::Text(fields.read().display_idx.clone()),
+                    oninput: move |evt: FormEvent| fields.write().display_idx = evt.value(),
+                }
+            }
+            if !matches!(type_selects.read()[fields.read().typ].0, ItemFieldsType::Section) {
+                Field {
+                    label: "Required",
+                    CheckInput{
+                        style: CheckStyle::Checkbox,
+                        value: fields.read().required,
+                        onclick: move |_| fields.with_mut(|fields| fields.required = !fields.required),
+                    }
+                }
+                Field {
+                    label: "Required After",
+                    TextInput{
+                        value: TextInputType::Date(fields.read().required_after.clone()),
+                        oninput: move |evt: FormEvent| fields.write().required_after = evt.value(),
+                    }
+                }
+                Field {
+                    label: "Leave Blank When Copied",
+                    CheckInput{
+                        style: CheckStyle::Checkbox,
+                        value: fields.read().blank_on_copy,
+                        onclick: move |_| fields.with_mut(|fields| fields.blank_on_copy = !fields.blank_on_copy),
+                    }
+                }
+            }
             div {
                 class: "box",
 
@@ -815,6 +1442,27 @@ fn SchemaItemModal(
                                 value: fields.read().text_type.display,
                             }
                         }
+                        Field {
+                            label: "Placeholder",
+                            TextInput{
+                                value: TextInputType::Text(fields.read().text_type.placeholder.clone()),
+                                oninput: move |evt: FormEvent| fields.write().text_type.placeholder = evt.value(),
+                            }
+                        }
+                        Field {
+                            label: "Prefix",
+                            TextInput{
+                                value: TextInputType::Text(fields.read().text_type.prefix.clone()),
+                                oninput: move |evt: FormEvent| fields.write().text_type.prefix = evt.value(),
+                            }
+                        }
+                        Field {
+                            label: "Suffix",
+                            TextInput{
+                                value: TextInputType::Text(fields.read().text_type.suffix.clone()),
+                                oninput: move |evt: FormEvent| fields.write().text_type.suffix = evt.value(),
+                            }
+                        }
                     ),
 
                     ItemFieldsType::Checkbox => {
@@ -916,6 +1564,7 @@ fn SchemaItemModal(
                         }) }
                         Button {
                             flavor: ButtonFlavor::Info,
+                            disabled: fields.read().options.len() >= max_options,
                             onclick: move |_| fields.write().options.push(FieldSelectOption::default()),
                             "Add Option"
                         }
@@ -1006,10 +1655,74 @@ fn SchemaItemModal(
                         }) }
                         Button {
                             flavor: ButtonFlavor::Info,
+                            disabled: fields.read().options.len() >= max_options,
                             onclick: move |_| fields.write().options.push(FieldSelectOption::default()),
                             "Add Option"
                         }
                     },
+                    ItemFieldsType::Section => rsx!{
+                        Field {
+                            label: "Body",
+                            div {
+                                class: "control is-expanded",
+                                textarea {
+                                    class: "textarea",
+                                    value: "{fields.read().section_type.body}",
+                                    oninput: move |evt: FormEvent| fields.write().section_type.body = evt.value(),
+                                }
+                            }
+                        }
+                    },
+                    // Group items are added as text fields; they can't themselves be groups.
+                    ItemFieldsType::Group => rsx!{
+                        { fields.read().group_type.items.iter().enumerate().map(|(idx, group_item)| {
+                            rsx!{
+                                Field {
+                                    key: "{idx}",
+                                    label: "Name",
+                                    TextInput{
+                                        value: TextInputType::Text(group_item.name.clone()),
+                                        is_expanded: true,
+                                        oninput: move |evt: FormEvent| fields.write().group_type.items[idx].name = evt.value(),
+                                    }
+                                    CheckInput{
+                                        style: CheckStyle::Checkbox,
+                                        label: "Required",
+                                        value: group_item.required,
+                                        onclick: move |_| fields.with_mut(|fields| {
+                                            let group_item = &mut fields.group_type.items[idx];
+                                            group_item.required = !group_item.required;
+                                        }),
+                                    }
+                                    div {
+                                        class: "field",
+                                        button {
+                                            class: "delete",
+                                            "type": "button",
+                                            "aria-label": "close",
+                                            onclick: move |_| {
+                                                fields.write().group_type.items.remove(idx);
+                                            },
+                                        }
+                                    }
+                                }
+                            }
+                        }) }
+                        Button {
+                            flavor: ButtonFlavor::Info,
+                            onclick: move |_| fields.write().group_type.items.push(default_registration_schema_item()),
+                            "Add Item"
+                        }
+                    },
+                    ItemFieldsType::Tags => rsx!{
+                        Field {
+                            label: "Maximum Tags (0 for no limit)",
+                            TextInput{
+                                value: TextInputType::Number(fields.read().tags_type.max_tags as i32),
+                                oninput: move |evt: FormEvent| fields.write().tags_type.max_tags = evt.value().parse().unwrap_or(0),
+                            }
+                        }
+                    },
                 }
             }
             { validation_error }
@@ -1204,3 +1917,245 @@ fn OptionGrab(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use common::proto::{
+        registration_schema_item_type::Type as ItemType, CheckboxType, RegistrationSchemaItem,
+        RegistrationSchemaItemType, SelectOption, SelectType,
+    };
+    use dioxus::prelude::*;
+    use uuid::Uuid;
+
+    use crate::{
+        components::testing::render,
+        hooks::{locale::Locale, toasts::use_toasts_provider},
+    };
+
+    use super::{
+        default_registration_schema_item, duplicate_item, move_item, push_undo_history,
+        AutosaveDebounce, AutosaveQueue, AutosaveState, ChangeTracker, Schema, SchemaItemModal,
+        UndoAction, UNDO_HISTORY_LIMIT,
+    };
+
+    fn named_item(name: &str) -> RegistrationSchemaItem {
+        RegistrationSchemaItem {
+            id: "item".to_owned(),
+            name: name.to_owned(),
+            ..default_registration_schema_item()
+        }
+    }
+
+    #[test]
+    fn autosave_coalesces_edits() {
+        // Keystrokes within the idle window each start a timer, but only the last one saves.
+        let mut debounce = AutosaveDebounce::default();
+        let tickets = (0..3).map(|_| debounce.edit()).collect::<Vec<_>>();
+        assert_eq!(
+            tickets
+                .into_iter()
+                .filter(|ticket| debounce.is_latest(*ticket))
+                .count(),
+            1
+        );
+
+        let mut queue = AutosaveQueue::default();
+
+        // The first edit starts a save, which picks it up right away.
+        assert!(queue.push(named_item("S")));
+        assert_eq!(queue.next(), Some(named_item("S")));
+
+        // Edits made while that request is in flight don't start saves of their own, and only the
+        // last of them is sent.
+        assert!(!queue.push(named_item("Sh")));
+        assert!(!queue.push(named_item("Shi")));
+        assert!(!queue.push(named_item("Shirt")));
+        assert_eq!(queue.next(), Some(named_item("Shirt")));
+        assert_eq!(queue.next(), None);
+
+        // Once the save has caught up, the next edit starts a new one.
+        assert!(queue.push(named_item("Shirt size")));
+    }
+
+    fn saved_schema() -> Schema {
+        let select = RegistrationSchemaItem {
+            id: "role".to_owned(),
+            name: "Role".to_owned(),
+            r#type: Some(RegistrationSchemaItemType {
+                r#type: Some(ItemType::Select(SelectType {
+                    options: vec![SelectOption {
+                        id: "leader".to_owned(),
+                        name: "Leader".to_owned(),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                })),
+            }),
+            ..default_registration_schema_item()
+        };
+
+        Schema {
+            items: vec![
+                (Uuid::new_v4(), named_item("Name")),
+                (Uuid::new_v4(), select),
+            ],
+            event_id: "event".to_owned(),
+        }
+    }
+
+    #[test]
+    fn undo_restores_deleted_item() {
+        let schema = saved_schema();
+
+        let UndoAction::Restore { idx, item: restored_item } =
+            UndoAction::restore(1, schema.items[1].1.clone())
+        else {
+            panic!("not a restore");
+        };
+
+        // The deleted item goes back in its old place, without the ids the server forgot, so it
+        // can be added again.
+        assert_eq!(idx, 1);
+        assert_eq!(restored_item.id, "");
+        assert_eq!(restored_item.name, "Role");
+        match restored_item.r#type.as_ref().and_then(|t| t.r#type.as_ref()) {
+            Some(ItemType::Select(select)) => {
+                assert_eq!(select.options.len(), 1);
+                assert_eq!(select.options[0].id, "");
+                assert_eq!(select.options[0].name, "Leader");
+            }
+            typ => panic!("unexpected type: {:?}", typ),
+        }
+    }
+
+    #[test]
+    fn move_item_both_ways() {
+        let mut schema = saved_schema();
+        schema.items.push((Uuid::new_v4(), named_item("Email")));
+        let names = |schema: &Schema| {
+            schema
+                .items
+                .iter()
+                .map(|(_, i)| i.name.clone())
+                .collect::<Vec<_>>()
+        };
+
+        move_item(&mut schema, 0, 2);
+        assert_eq!(names(&schema), ["Role", "Email", "Name"]);
+
+        // Moving it back to where it was is what undo does.
+        move_item(&mut schema, 2, 0);
+        assert_eq!(names(&schema), ["Name", "Role", "Email"]);
+    }
+
+    #[test]
+    fn undo_history_is_capped() {
+        let mut history = VecDeque::new();
+        for idx in 0..UNDO_HISTORY_LIMIT + 2 {
+            push_undo_history(
+                &mut history,
+                UndoAction::Delete {
+                    id: idx.to_string(),
+                },
+            );
+        }
+
+        assert_eq!(history.len(), UNDO_HISTORY_LIMIT);
+        assert_eq!(
+            history.front(),
+            Some(&UndoAction::Delete {
+                id: "2".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn change_tracker_compares_to_first_build() {
+        let mut tracker = ChangeTracker::default();
+
+        assert!(!tracker.changed(named_item("Shirt")));
+        assert!(!tracker.changed(named_item("Shirt")));
+        assert!(tracker.changed(named_item("Shirt size")));
+
+        // Undoing the edit by hand leaves nothing to save.
+        assert!(!tracker.changed(named_item("Shirt")));
+    }
+
+    fn select_options(item: &RegistrationSchemaItem) -> &[SelectOption] {
+        match item.r#type.as_ref().and_then(|t| t.r#type.as_ref()) {
+            Some(ItemType::Select(select)) => &select.options,
+            _ => panic!("not a select item: {:?}", item),
+        }
+    }
+
+    #[test]
+    fn duplicate_item_copies_without_ids() {
+        let schema = saved_schema();
+
+        let copy = duplicate_item(&schema, 1);
+
+        assert_eq!(copy.name, "Role (copy)");
+        assert_eq!(copy.id, "");
+        assert_eq!(select_options(&copy)[0].id, "");
+        assert_eq!(select_options(&copy)[0].name, "Leader");
+    }
+
+    #[test]
+    fn duplicate_item_numbers_later_copies() {
+        let mut schema = saved_schema();
+        schema.items.push((Uuid::new_v4(), named_item("name (COPY)")));
+
+        assert_eq!(duplicate_item(&schema, 0).name, "Name (copy 2)");
+
+        schema.items.push((Uuid::new_v4(), named_item("Name (copy 2)")));
+        assert_eq!(duplicate_item(&schema, 0).name, "Name (copy 3)");
+    }
+
+    #[component]
+    fn CheckboxModal(locale: Locale) -> Element {
+        use_toasts_provider();
+        use_context_provider(|| Signal::new(locale));
+
+        let item = RegistrationSchemaItem {
+            r#type: Some(RegistrationSchemaItemType {
+                r#type: Some(ItemType::Checkbox(CheckboxType { default: false })),
+            }),
+            ..named_item("Waiver")
+        };
+
+        rsx! {
+            SchemaItemModal {
+                initial: item,
+                taken_names: Vec::new(),
+                onsubmit: move |_| {},
+                onautosave: move |_| {},
+                autosave_state: AutosaveState::Idle,
+                onclose: move |_| {},
+                max_options: 10,
+                grabbing_cursor: use_signal(|| false),
+                dirty: use_signal(|| false),
+            }
+        }
+    }
+
+    #[test]
+    fn locale_switch_keeps_selected_type() {
+        fn english() -> Element {
+            rsx! { CheckboxModal { locale: Locale::English } }
+        }
+        fn spanish() -> Element {
+            rsx! { CheckboxModal { locale: Locale::Spanish } }
+        }
+
+        let html = render(english);
+        assert!(html.contains("<option selected value=\"1\">Checkbox</option>"), "{}", html);
+        assert!(html.contains(">Text</option>"), "{}", html);
+
+        let html = render(spanish);
+        assert!(html.contains("<option selected value=\"1\">Casilla</option>"), "{}", html);
+        assert!(html.contains(">Texto</option>"), "{}", html);
+        assert!(!html.contains(">Checkbox</option>"), "{}", html);
+    }
+}
@@ -0,0 +1,80 @@
+//! Assembles a schema with 5,000 select options from its rows. To compare against another
+//! revision, run `cargo bench --bench registration_schema -- --save-baseline before` there and
+//! `cargo bench --bench registration_schema -- --baseline before` here.
+
+use common::proto::{
+    registration_schema_item_type::Type as ItemType, select_type, RegistrationSchemaItem,
+    RegistrationSchemaItemType, SelectOption, SelectType,
+};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use odr_server::store::registration_schema::items_to_schema;
+use uuid::Uuid;
+
+const ITEMS: usize = 50;
+const OPTIONS_PER_ITEM: usize = 100;
+
+type Rows = (
+    Vec<(String, usize, RegistrationSchemaItem)>,
+    Vec<(String, usize, SelectOption)>,
+);
+
+fn rows() -> Rows {
+    let event_id = Uuid::now_v7().to_string();
+    let mut items = Vec::with_capacity(ITEMS);
+    let mut options = Vec::with_capacity(ITEMS * OPTIONS_PER_ITEM);
+
+    for item_idx in 0..ITEMS {
+        let item_id = Uuid::now_v7().to_string();
+
+        // Rows come back from the database in no particular order.
+        for option_idx in (0..OPTIONS_PER_ITEM).rev() {
+            options.push((
+                item_id.clone(),
+                option_idx,
+                SelectOption {
+                    id: Uuid::now_v7().to_string(),
+                    name: format!("option {}", option_idx),
+                    product_id: "".to_owned(),
+                    archived: false,
+                },
+            ));
+        }
+
+        items.push((
+            event_id.clone(),
+            item_idx,
+            RegistrationSchemaItem {
+                id: item_id,
+                name: format!("item {}", item_idx),
+                r#type: Some(RegistrationSchemaItemType {
+                    r#type: Some(ItemType::Select(SelectType {
+                        default: 0,
+                        display: select_type::Display::Dropdown as i32,
+                        options: Vec::new(),
+                    })),
+                }),
+                required: false,
+                required_after: "".to_owned(),
+                blank_on_copy: false,
+                display_idx: None,
+            },
+        ));
+    }
+
+    (items, options)
+}
+
+fn bench_items_to_schema(c: &mut Criterion) {
+    let rows = rows();
+
+    c.bench_function("items_to_schema 5000 options", |b| {
+        b.iter_batched(
+            || rows.clone(),
+            |(items, options)| black_box(items_to_schema(items, options)),
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+criterion_group!(benches, bench_items_to_schema);
+criterion_main!(benches);
@@ -1,5 +1,7 @@
 use std::fmt::{Display, Formatter};
 
+use ::common::proto::{RegistrationFieldError, RegistrationValidationErrors, UpsertOutcome};
+use prost::Message as _;
 use tonic::{Code, Status};
 
 use crate::store;
@@ -8,15 +10,52 @@ use thiserror::Error as ThisError;
 pub mod authentication;
 mod common;
 pub mod event;
+pub mod maintenance;
+pub mod metrics;
 pub mod organization;
+pub mod public_registration;
 pub mod registration;
 pub mod registration_schema;
+pub mod schema_template;
 pub mod user;
 
+/// How many results a query that asks for no page returns before it's cut off, unless the server
+/// is configured otherwise.
+pub const DEFAULT_MAX_QUERY_RESULTS: u32 = 1000;
+
+impl From<store::Outcome> for UpsertOutcome {
+    fn from(outcome: store::Outcome) -> Self {
+        UpsertOutcome {
+            id: outcome.id,
+            created: outcome.created,
+        }
+    }
+}
+
 impl From<store::Error> for Status {
     fn from(err: store::Error) -> Self {
         let code = match err {
-            store::Error::IdDoesNotExist(_) => Code::NotFound,
+            store::Error::IdDoesNotExist(_) | store::Error::SlugDoesNotExist(_) => Code::NotFound,
+            store::Error::InvalidEventDates(_, _)
+            | store::Error::TooManyOptions { .. }
+            | store::Error::DuplicateOptionName { .. }
+            | store::Error::DuplicateItemName { .. }
+            | store::Error::InvalidTimestamp(_)
+            | store::Error::EmptyUpdateMask
+            | store::Error::InconsistentItemType { .. }
+            | store::Error::InvalidMerge { .. }
+            | store::Error::ValueTooLong { .. }
+            | store::Error::InvalidRegistrationValue { .. }
+            | store::Error::UseMoveEvent(_)
+            | store::Error::InvalidStatus(_)
+            | store::Error::RegistrationValidation(_) => Code::InvalidArgument,
+            store::Error::IncompatibleSchema(_)
+            | store::Error::RegistrationNotOpen(_)
+            | store::Error::RegistrationClosed(_)
+            | store::Error::SchemaLocked(_)
+            | store::Error::TypeChangeUnsafe { .. } => Code::FailedPrecondition,
+            store::Error::AccountRequired(_) => Code::Unauthenticated,
+            store::Error::DuplicateRegistration { .. } => Code::AlreadyExists,
             store::Error::InsertionError(_)
             | store::Error::FetchError(_)
             | store::Error::UpdateError(_)
@@ -24,10 +63,26 @@ impl From<store::Error> for Status {
             | store::Error::CheckExistsError(_)
             | store::Error::TransactionStartError(_)
             | store::Error::TransactionFailed(_)
-            | store::Error::ColumnParseError(_) => Code::Internal,
+            | store::Error::ColumnParseError(_)
+            | store::Error::UnknownEnumValue { .. } => Code::Internal,
+        };
+
+        let details = match &err {
+            store::Error::RegistrationValidation(errors) => RegistrationValidationErrors {
+                errors: errors
+                    .iter()
+                    .map(|e| RegistrationFieldError {
+                        item_id: e.item_id.clone(),
+                        reason: e.reason.to_string(),
+                        instance: e.instance,
+                    })
+                    .collect(),
+            }
+            .encode_to_vec(),
+            _ => Vec::new(),
         };
 
-        Status::new(code, format!("{}", err))
+        Status::with_details(code, format!("{}", err), details.into())
     }
 }
 
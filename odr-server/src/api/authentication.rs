@@ -1,7 +1,15 @@
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 use argon2::{Argon2, PasswordVerifier};
-use common::proto::{self, ClaimsRequest, ClaimsResponse, LoginRequest, LoginResponse};
+use common::proto::{
+    self, user_access, ClaimsRequest, ClaimsResponse, LoginRequest, LoginResponse,
+    PermissionSummary, RegistrationAccessRequest, RegistrationAccessResponse, WhoamiRequest,
+    WhoamiResponse,
+};
 use cookie::{Cookie, CookieBuilder, Expiration, SameSite};
 use ed25519_dalek::pkcs8::EncodePrivateKey;
 use http::header::{HeaderMap, COOKIE, SET_COOKIE};
@@ -15,14 +23,19 @@ use crate::{
     keys::KeyManager,
     store::{
         self,
+        ids::{EventId, RegistrationId, UserId},
         keys::Store as KeyStore,
-        user::{EmailQuery, PasswordType, Query, Store as UserStore},
-        CompoundOperator, CompoundQuery,
+        permission::{
+            Access, EventRole, Permission, PermissionRole, Query as PermissionQuery,
+            Store as PermissionStore, UserAccess, UserIdQuery,
+        },
+        user::{EmailQuery, IdQuery, PasswordType, Query, Store as UserStore},
+        Compound as _,
     },
 };
 
-#[derive(Debug)]
-struct Claims {
+#[derive(Clone, Debug)]
+pub(super) struct Claims {
     iss: String,
     sub: String,
     aud: Audience,
@@ -102,37 +115,200 @@ impl<'de> Deserialize<'de> for Claims {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, strum::Display)]
+#[derive(Clone, Debug, Serialize, Deserialize, strum::Display)]
 enum Audience {
     Access,
+    PublicRegistration,
+    RegistrationEdit,
+    Readiness,
 }
 
 impl From<Audience> for proto::Audience {
     fn from(aud: Audience) -> Self {
         match aud {
             Audience::Access => proto::Audience::Access,
+            Audience::PublicRegistration => proto::Audience::PublicRegistration,
+            Audience::RegistrationEdit => proto::Audience::RegistrationEdit,
+            Audience::Readiness => proto::Audience::Readiness,
         }
     }
 }
 
 const ISSUER: &str = "https://auth.example.com";
-const ACCESS_TOKEN_EXPIRATION_SECONDS: i64 = 60 * 60 * 24 * 30 * 6;
+pub const DEFAULT_ACCESS_TOKEN_TTL_SECONDS: u64 = 60 * 60 * 24 * 30 * 6;
 const ACCESS_TOKEN_COOKIE: &str = "authorization";
 
-pub struct Service<KStore: KeyStore, UStore: UserStore> {
+const CLAIMS_CACHE_CAPACITY: usize = 1024;
+const CLAIMS_CACHE_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Debug)]
+struct CachedClaims {
+    token: String,
+    kid: String,
+    claims: Claims,
+    verified_at: Instant,
+}
+
+// Access tokens whose signatures were recently verified, keyed by signature. A hit skips only the
+// signature check: expiry is checked again, and the caller still looks up the signing key so a
+// revoked key takes effect immediately.
+#[derive(Debug, Default)]
+struct ClaimsCache {
+    entries: HashMap<String, CachedClaims>,
+    order: VecDeque<String>,
+}
+
+fn token_signature(token: &str) -> Option<&str> {
+    token.rsplit_once('.').map(|(_, signature)| signature)
+}
+
+impl ClaimsCache {
+    // Returns the signing key id and claims of a fresh, unexpired entry for exactly this token.
+    fn get(&self, token: &str, now: Instant) -> Option<(String, Claims)> {
+        let entry = self.entries.get(token_signature(token)?)?;
+
+        (entry.token == token
+            && now.duration_since(entry.verified_at) < CLAIMS_CACHE_TTL
+            && entry.claims.exp > chrono::Utc::now())
+        .then(|| (entry.kid.clone(), entry.claims.clone()))
+    }
+
+    fn insert(&mut self, token: &str, kid: String, claims: Claims, now: Instant) {
+        // Verification allows some leeway past expiry; such tokens are never remembered.
+        if claims.exp <= chrono::Utc::now() {
+            return;
+        }
+
+        let Some(signature) = token_signature(token) else {
+            return;
+        };
+
+        let entry = CachedClaims {
+            token: token.to_owned(),
+            kid,
+            claims,
+            verified_at: now,
+        };
+        if self.entries.insert(signature.to_owned(), entry).is_none() {
+            self.order.push_back(signature.to_owned());
+        }
+
+        while self.order.len() > CLAIMS_CACHE_CAPACITY {
+            if let Some(signature) = self.order.pop_front() {
+                self.entries.remove(&signature);
+            }
+        }
+    }
+
+    fn remove(&mut self, token: &str) {
+        let Some(signature) = token_signature(token) else {
+            return;
+        };
+
+        if self.entries.remove(signature).is_some() {
+            self.order.retain(|s| s != signature);
+        }
+    }
+}
+
+pub struct Service<KStore: KeyStore, UStore: UserStore, PStore: PermissionStore> {
     km: Arc<KeyManager<KStore>>,
     user_store: Arc<UStore>,
+    permission_store: Arc<PStore>,
+    access_token_ttl: chrono::Duration,
+    claims_cache: Mutex<ClaimsCache>,
 }
 
-impl<KStore: KeyStore, UStore: UserStore> Service<KStore, UStore> {
-    pub fn new(km: Arc<KeyManager<KStore>>, user_store: Arc<UStore>) -> Self {
-        Self { km, user_store }
+impl<KStore: KeyStore, UStore: UserStore, PStore: PermissionStore> Service<KStore, UStore, PStore> {
+    pub fn new(
+        km: Arc<KeyManager<KStore>>,
+        user_store: Arc<UStore>,
+        permission_store: Arc<PStore>,
+        access_token_ttl: chrono::Duration,
+    ) -> Self {
+        Self {
+            km,
+            user_store,
+            permission_store,
+            access_token_ttl,
+            claims_cache: Mutex::new(ClaimsCache::default()),
+        }
+    }
+
+    // Validates the request's access token like validate_request, reusing a recent verification
+    // of the same token when there is one.
+    async fn validate_cached(&self, metadata: &MetadataMap) -> Result<Claims, ValidationError> {
+        let token = access_token(metadata)?;
+
+        let cached = self
+            .claims_cache
+            .lock()
+            .unwrap()
+            .get(&token, Instant::now());
+        if let Some((kid, claims)) = cached {
+            return match self.km.get_verifying_key(&kid).await {
+                Ok(_) => Ok(claims),
+                Err(store::Error::IdDoesNotExist(_)) => {
+                    self.claims_cache.lock().unwrap().remove(&token);
+                    Err(ValidationError::Unauthenticated)
+                }
+                Err(e) => Err(ValidationError::StoreError(e)),
+            };
+        }
+
+        let (kid, claims) = verify_token(&self.km, &token, Audience::Access).await?;
+        self.claims_cache
+            .lock()
+            .unwrap()
+            .insert(&token, kid, claims.clone(), Instant::now());
+
+        Ok(claims)
+    }
+}
+
+fn permission_summary(permissions: Vec<Permission>) -> PermissionSummary {
+    permissions
+        .into_iter()
+        .fold(PermissionSummary::default(), |mut summary, permission| {
+            match permission.role {
+                PermissionRole::ServerAdmin => summary.server_admin = true,
+                PermissionRole::OrganizationAdmin(id) => summary.organization_admin_ids.push(id),
+                PermissionRole::OrganizationViewer(id) => summary.organization_viewer_ids.push(id),
+                PermissionRole::EventAdmin(id) => summary.event_admin_ids.push(id),
+                PermissionRole::EventEditor(id) => summary.event_editor_ids.push(id),
+                PermissionRole::EventViewer(id) => summary.event_viewer_ids.push(id),
+            };
+
+            summary
+        })
+}
+
+impl From<UserAccess> for proto::UserAccess {
+    fn from(user_access: UserAccess) -> Self {
+        let access = match user_access.access {
+            Access::View => user_access::Access::View,
+            Access::Edit => user_access::Access::Edit,
+        };
+        let role = &user_access.permission.role;
+
+        proto::UserAccess {
+            user_id: user_access.user_id,
+            access: access as i32,
+            role: role.as_str_name().to_owned(),
+            scope_id: role
+                .organization()
+                .or(role.event())
+                .unwrap_or_default()
+                .to_owned(),
+            permission_id: user_access.permission.id,
+        }
     }
 }
 
 #[tonic::async_trait]
-impl<KStore: KeyStore, UStore: UserStore>
-    proto::authentication_service_server::AuthenticationService for Service<KStore, UStore>
+impl<KStore: KeyStore, UStore: UserStore, PStore: PermissionStore>
+    proto::authentication_service_server::AuthenticationService
+    for Service<KStore, UStore, PStore>
 {
     async fn login(
         &self,
@@ -146,13 +322,10 @@ impl<KStore: KeyStore, UStore: UserStore>
         let mut users = self
             .user_store
             .query(
-                Some(Query::CompoundQuery(CompoundQuery {
-                    operator: CompoundOperator::And,
-                    queries: vec![
-                        Query::Email(EmailQuery::Equals(credentials.email)),
-                        Query::PasswordIsSet(true),
-                    ],
-                }))
+                Some(Query::all_of(vec![
+                    Query::Email(EmailQuery::Equals(credentials.email)),
+                    Query::PasswordIsSet(true),
+                ]))
                 .as_ref(),
             )
             .await
@@ -180,36 +353,16 @@ impl<KStore: KeyStore, UStore: UserStore>
             )
             .map_err(|_| invalid_email_or_password())?;
 
-        let (kid, key) = self
-            .km
-            .get_signing_key()
-            .await
-            .map_err(|e| -> Status { e.into() })?;
-
-        let encoding_key = EncodingKey::from_ed_der(
-            key.to_pkcs8_der()
-                .map_err(|e| {
-                    Status::new(
-                        Code::Internal,
-                        format!("error generating signing key {}", e),
-                    )
-                })?
-                .as_bytes(),
-        );
-
-        let mut header = jsonwebtoken::Header::new(Algorithm::EdDSA);
-        header.kid = Some(kid);
-
+        let now = chrono::Utc::now();
         let access_claims = Claims {
             iss: ISSUER.to_string(),
             sub: user.id,
             aud: Audience::Access,
-            iat: chrono::Utc::now(),
-            exp: chrono::Utc::now() + chrono::Duration::seconds(ACCESS_TOKEN_EXPIRATION_SECONDS),
+            iat: now,
+            exp: now + self.access_token_ttl,
         };
 
-        let access_jwt = jsonwebtoken::encode(&header, &access_claims, &encoding_key)
-            .map_err(|e| Status::new(Code::Internal, format!("error signing token: {}", e)))?;
+        let access_jwt = sign_claims(&self.km, &access_claims).await?;
 
         let claims_cookie = Cookie::build((ACCESS_TOKEN_COOKIE, access_jwt.clone()))
             .expires(Expiration::DateTime(
@@ -219,8 +372,14 @@ impl<KStore: KeyStore, UStore: UserStore>
             .http_only(true)
             .same_site(SameSite::Strict);
 
+        // The token only carries whole seconds, so the expiry is reported the same way.
+        let expires_at = access_claims
+            .exp
+            .to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+
         let mut response = Response::new(LoginResponse {
             claims: Some(access_claims.into()),
+            expires_at,
         });
         let mut metadata = HeaderMap::new();
         metadata.insert(SET_COOKIE, claims_cookie.to_string().parse().unwrap());
@@ -231,34 +390,94 @@ impl<KStore: KeyStore, UStore: UserStore>
 
     async fn claims(
         &self,
-        mut request: Request<ClaimsRequest>,
+        request: Request<ClaimsRequest>,
     ) -> Result<Response<ClaimsResponse>, Status> {
-        let metadata = std::mem::take(request.metadata_mut());
-        let headers = metadata.into_headers();
-        let cookies = headers.get_all(COOKIE);
-        let auth_cookie = cookies.iter().find_map(|cookie_header_value| {
-            let parsed = Cookie::parse(cookie_header_value.to_str().ok()?).ok()?;
+        let token = self
+            .validate_cached(request.metadata())
+            .await
+            .map_err(|e| -> Status { e.into() })?;
 
-            if parsed.name() != ACCESS_TOKEN_COOKIE {
-                return None;
-            }
+        Ok(Response::new(ClaimsResponse {
+            claims: Some(token.into()),
+        }))
+    }
 
-            Some(parsed)
-        });
+    async fn whoami(
+        &self,
+        request: Request<WhoamiRequest>,
+    ) -> Result<Response<WhoamiResponse>, Status> {
+        let token = self
+            .validate_cached(request.metadata())
+            .await
+            .map_err(|e| -> Status { e.into() })?;
 
-        let auth_cookie = match auth_cookie {
-            Some(cookie) => cookie,
-            None => {
-                return Err(ValidationError::Unauthenticated.into());
-            }
-        };
+        let mut users = self
+            .user_store
+            .query(Some(&Query::Id(IdQuery::Equals(token.sub.clone()))))
+            .await
+            .map_err(|e| -> Status { e.into() })?;
 
-        let token = validate_token(&self.km, auth_cookie.value())
+        if users.is_empty() {
+            return Err(ValidationError::Unauthenticated.into());
+        }
+        let user = users.remove(0);
+
+        let permissions = self
+            .permission_store
+            .query(Some(&PermissionQuery::UserId(UserIdQuery::Equals(
+                user.id.clone(),
+            ))))
             .await
             .map_err(|e| -> Status { e.into() })?;
 
-        Ok(Response::new(ClaimsResponse {
-            claims: Some(token.into()),
+        Ok(Response::new(WhoamiResponse {
+            user_id: user.id,
+            email: user.email,
+            display_name: user.display_name,
+            permissions: Some(permission_summary(permissions)),
+        }))
+    }
+
+    async fn registration_access(
+        &self,
+        request: Request<RegistrationAccessRequest>,
+    ) -> Result<Response<RegistrationAccessResponse>, Status> {
+        let token = self
+            .validate_cached(request.metadata())
+            .await
+            .map_err(|e| -> Status { e.into() })?;
+
+        let permissions = self
+            .permission_store
+            .query(Some(&PermissionQuery::UserId(UserIdQuery::Equals(
+                token.sub,
+            ))))
+            .await
+            .map_err(|e| -> Status { e.into() })?;
+
+        if !permissions
+            .iter()
+            .any(|permission| permission.role == PermissionRole::ServerAdmin)
+        {
+            return Err(Status::new(
+                Code::PermissionDenied,
+                "registration access is only available to server admins",
+            ));
+        }
+
+        let registration_id = request.into_inner().registration_id;
+        if registration_id.is_empty() {
+            return Err(super::ValidationError::new_empty("registration_id").into());
+        }
+
+        let access = self
+            .permission_store
+            .registration_access(&RegistrationId::from(&registration_id))
+            .await
+            .map_err(|e| -> Status { e.into() })?;
+
+        Ok(Response::new(RegistrationAccessResponse {
+            access: access.into_iter().map(|access| access.into()).collect(),
         }))
     }
 
@@ -275,6 +494,111 @@ impl<KStore: KeyStore, UStore: UserStore>
     }
 }
 
+async fn sign_claims<KStore: KeyStore>(
+    km: &KeyManager<KStore>,
+    claims: &Claims,
+) -> Result<String, Status> {
+    let (kid, key) = km
+        .get_signing_key()
+        .await
+        .map_err(|e| -> Status { e.into() })?;
+
+    let encoding_key = EncodingKey::from_ed_der(
+        key.to_pkcs8_der()
+            .map_err(|e| {
+                Status::new(
+                    Code::Internal,
+                    format!("error generating signing key {}", e),
+                )
+            })?
+            .as_bytes(),
+    );
+
+    let mut header = jsonwebtoken::Header::new(Algorithm::EdDSA);
+    header.kid = Some(kid);
+
+    jsonwebtoken::encode(&header, claims, &encoding_key)
+        .map_err(|e| Status::new(Code::Internal, format!("error signing token: {}", e)))
+}
+
+// Public registration tokens are scoped to a single event; they are only ever accepted by
+// validate_public_registration_token, never as an access token.
+pub(super) async fn create_public_registration_token<KStore: KeyStore>(
+    km: &KeyManager<KStore>,
+    event_id: &str,
+    ttl: chrono::Duration,
+) -> Result<String, Status> {
+    let now = chrono::Utc::now();
+    let claims = Claims {
+        iss: ISSUER.to_string(),
+        sub: event_id.to_owned(),
+        aud: Audience::PublicRegistration,
+        iat: now,
+        exp: now + ttl,
+    };
+
+    sign_claims(km, &claims).await
+}
+
+pub(super) async fn validate_public_registration_token<KStore: KeyStore>(
+    km: &KeyManager<KStore>,
+    token: &str,
+) -> Result<String, Status> {
+    let claims = validate_token(km, token, Audience::PublicRegistration).await?;
+
+    Ok(claims.sub)
+}
+
+// Edit tokens are scoped to a single registration, letting a registrant fix their own answers
+// without an account.
+pub(super) async fn create_registration_edit_token<KStore: KeyStore>(
+    km: &KeyManager<KStore>,
+    registration_id: &str,
+    ttl: chrono::Duration,
+) -> Result<String, Status> {
+    let now = chrono::Utc::now();
+    let claims = Claims {
+        iss: ISSUER.to_string(),
+        sub: registration_id.to_owned(),
+        aud: Audience::RegistrationEdit,
+        iat: now,
+        exp: now + ttl,
+    };
+
+    sign_claims(km, &claims).await
+}
+
+pub(super) async fn validate_registration_edit_token<KStore: KeyStore>(
+    km: &KeyManager<KStore>,
+    token: &str,
+) -> Result<String, Status> {
+    let claims = validate_token(km, token, Audience::RegistrationEdit).await?;
+
+    Ok(claims.sub)
+}
+
+// Signs a throwaway token with the newest key and verifies it again, since no one can sign in
+// if the keys can't be loaded. The token is never handed out.
+pub(super) async fn check_signing_key<KStore: KeyStore>(
+    km: &KeyManager<KStore>,
+) -> Result<(), Status> {
+    let now = chrono::Utc::now();
+    let claims = Claims {
+        iss: ISSUER.to_string(),
+        sub: "readiness".to_owned(),
+        aud: Audience::Readiness,
+        iat: now,
+        exp: now + chrono::Duration::minutes(1),
+    };
+
+    let token = sign_claims(km, &claims).await?;
+    validate_token(km, &token, Audience::Readiness)
+        .await
+        .map_err(|e| -> Status { e.into() })?;
+
+    Ok(())
+}
+
 fn delete_cookie() -> CookieBuilder<'static> {
     Cookie::build((ACCESS_TOKEN_COOKIE, ""))
         .expires(Expiration::DateTime(OffsetDateTime::UNIX_EPOCH))
@@ -284,7 +608,7 @@ fn delete_cookie() -> CookieBuilder<'static> {
 }
 
 #[derive(thiserror::Error, Debug)]
-enum ValidationError {
+pub(super) enum ValidationError {
     #[error("unauthenticated")]
     Unauthenticated,
 
@@ -303,10 +627,137 @@ impl From<ValidationError> for Status {
     }
 }
 
+fn access_token(metadata: &MetadataMap) -> Result<String, ValidationError> {
+    let auth_cookie = metadata
+        .get_all(COOKIE.as_str())
+        .iter()
+        .find_map(|cookie_header_value| {
+            let parsed = Cookie::parse(cookie_header_value.to_str().ok()?).ok()?;
+
+            if parsed.name() != ACCESS_TOKEN_COOKIE {
+                return None;
+            }
+
+            Some(parsed)
+        })
+        .ok_or(ValidationError::Unauthenticated)?;
+
+    Ok(auth_cookie.value().to_owned())
+}
+
+pub(super) async fn validate_request<KStore: KeyStore>(
+    km: &KeyManager<KStore>,
+    metadata: &MetadataMap,
+) -> Result<Claims, ValidationError> {
+    validate_token(km, &access_token(metadata)?, Audience::Access).await
+}
+
+// Fails unless the request is signed in as a server admin, for tools that reach across
+// organizations.
+pub(super) async fn require_server_admin<KStore: KeyStore, PStore: PermissionStore>(
+    km: &KeyManager<KStore>,
+    permission_store: &PStore,
+    metadata: &MetadataMap,
+) -> Result<Claims, Status> {
+    let claims = validate_request(km, metadata)
+        .await
+        .map_err(|e| -> Status { e.into() })?;
+
+    let permissions = permission_store
+        .query(Some(&PermissionQuery::UserId(UserIdQuery::Equals(
+            claims.sub.clone(),
+        ))))
+        .await
+        .map_err(|e| -> Status { e.into() })?;
+
+    if !permissions
+        .iter()
+        .any(|permission| permission.role == PermissionRole::ServerAdmin)
+    {
+        return Err(Status::new(
+            Code::PermissionDenied,
+            "only server admins can do this",
+        ));
+    }
+
+    Ok(claims)
+}
+
+// Fails unless the request is signed in as a user holding at least min_role on the event, whether
+// directly, through its organization or as a server admin.
+pub(super) async fn require_event_role<KStore: KeyStore, PStore: PermissionStore>(
+    km: &KeyManager<KStore>,
+    permission_store: &PStore,
+    metadata: &MetadataMap,
+    event_id: &EventId,
+    min_role: EventRole,
+) -> Result<Claims, Status> {
+    let claims = validate_request(km, metadata)
+        .await
+        .map_err(|e| -> Status { e.into() })?;
+
+    let role = permission_store
+        .effective_event_role(&UserId::from(&claims.sub), event_id)
+        .await
+        .map_err(|e| -> Status { e.into() })?;
+
+    if role < Some(min_role) {
+        return Err(Status::new(
+            Code::PermissionDenied,
+            "you don't have permission to do this on this event",
+        ));
+    }
+
+    Ok(claims)
+}
+
+// The user signed in on the request, if any. Unlike validate_request, a missing or invalid access
+// token is not an error.
+pub(super) async fn request_user_id<KStore: KeyStore>(
+    km: &KeyManager<KStore>,
+    metadata: &MetadataMap,
+) -> Result<Option<String>, ValidationError> {
+    match validate_request(km, metadata).await {
+        Ok(claims) => Ok(Some(claims.sub)),
+        Err(ValidationError::Unauthenticated) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+pub(super) async fn create_access_token<KStore: KeyStore>(
+    km: &KeyManager<KStore>,
+    user_id: &str,
+    ttl: chrono::Duration,
+) -> Result<String, Status> {
+    let now = chrono::Utc::now();
+    let claims = Claims {
+        iss: ISSUER.to_string(),
+        sub: user_id.to_owned(),
+        aud: Audience::Access,
+        iat: now,
+        exp: now + ttl,
+    };
+
+    sign_claims(km, &claims).await
+}
+
 async fn validate_token<KStore: KeyStore>(
     km: &KeyManager<KStore>,
     token: &str,
+    audience: Audience,
 ) -> Result<Claims, ValidationError> {
+    let (_, claims) = verify_token(km, token, audience).await?;
+
+    Ok(claims)
+}
+
+// Returns the id of the key that signed the token along with its claims.
+async fn verify_token<KStore: KeyStore>(
+    km: &KeyManager<KStore>,
+    token: &str,
+    audience: Audience,
+) -> Result<(String, Claims), ValidationError> {
     let header = decode_header(token).map_err(|_| ValidationError::Unauthenticated)?;
 
     let kid = header.kid.ok_or(ValidationError::Unauthenticated)?;
@@ -324,12 +775,254 @@ async fn validate_token<KStore: KeyStore>(
     let decoding_key = DecodingKey::from_ed_der(key.to_bytes().as_slice());
 
     let mut validation = Validation::new(Algorithm::EdDSA);
-    validation.set_audience(&[Audience::Access]);
+    validation.set_audience(&[audience]);
     validation.set_issuer(&[ISSUER]);
     validation.validate_exp = true;
 
     let claims = decode::<Claims>(token, &decoding_key, &validation)
         .map_err(|_| ValidationError::Unauthenticated)?;
 
-    Ok(claims.claims)
+    Ok((kid, claims.claims))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Instant};
+
+    use argon2::{password_hash::SaltString, Argon2, PasswordHasher};
+    use common::proto::{
+        authentication_service_server::AuthenticationService, ClaimsRequest, LoginRequest,
+        PermissionSummary,
+    };
+    use http::header::COOKIE;
+    use rand::rngs::OsRng;
+    use sqlx::{migrate::MigrateDatabase, Sqlite, SqlitePool};
+    use tonic::{metadata::MetadataMap, Code, Request};
+    use uuid::Uuid;
+
+    use crate::{
+        keys::KeyManager,
+        store::{
+            keys::SqliteStore as KeyStore,
+            permission::{Permission, PermissionRole, SqliteStore as PermissionStore},
+            user::SqliteStore as UserStore,
+        },
+    };
+
+    use super::{permission_summary, sign_claims, Audience, Claims, Service, ISSUER};
+
+    #[tokio::test]
+    async fn login_expires_at() {
+        let db_url = "sqlite://:memory:";
+        Sqlite::create_database(db_url).await.unwrap();
+
+        let db = SqlitePool::connect(db_url).await.unwrap();
+        sqlx::migrate!("../migrations").run(&db).await.unwrap();
+
+        let password = Argon2::default()
+            .hash_password(b"password", &SaltString::generate(&mut OsRng))
+            .unwrap()
+            .serialize();
+        sqlx::query("INSERT INTO users(id, email, password, display_name) VALUES (?, ?, ?, ?);")
+            .bind(Uuid::now_v7().to_string())
+            .bind("a@example.com")
+            .bind(password.as_str())
+            .bind("a")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let db = Arc::new(db);
+        let km = Arc::new(KeyManager::new(Arc::new(KeyStore::new(db.clone()))));
+        km.rotate_key(false).await.unwrap();
+
+        let ttl = chrono::Duration::minutes(15);
+        let service = Service::new(
+            km,
+            Arc::new(UserStore::new(db.clone())),
+            Arc::new(PermissionStore::new(db)),
+            ttl,
+        );
+
+        let issued_at = chrono::Utc::now();
+        let response = service
+            .login(Request::new(LoginRequest {
+                email: "a@example.com".to_owned(),
+                password: "password".to_owned(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let expires_at = chrono::DateTime::parse_from_rfc3339(&response.expires_at).unwrap();
+        let drift = expires_at.signed_duration_since(issued_at + ttl);
+        assert!(drift.num_seconds().abs() <= 5, "{}", response.expires_at);
+        assert_eq!(
+            response.claims.unwrap().exp.unwrap().seconds,
+            expires_at.timestamp()
+        );
+    }
+
+    async fn claims_service() -> Service<KeyStore, UserStore, PermissionStore> {
+        let db = Arc::new(SqlitePool::connect("sqlite::memory:").await.unwrap());
+        sqlx::migrate!("../migrations").run(&*db).await.unwrap();
+
+        let km = Arc::new(KeyManager::new(Arc::new(KeyStore::new(db.clone()))));
+        km.rotate_key(false).await.unwrap();
+
+        Service::new(
+            km,
+            Arc::new(UserStore::new(db.clone())),
+            Arc::new(PermissionStore::new(db)),
+            chrono::Duration::minutes(15),
+        )
+    }
+
+    async fn access_token(
+        service: &Service<KeyStore, UserStore, PermissionStore>,
+        exp: chrono::DateTime<chrono::Utc>,
+    ) -> String {
+        let claims = Claims {
+            iss: ISSUER.to_owned(),
+            sub: "user".to_owned(),
+            aud: Audience::Access,
+            iat: exp - chrono::Duration::hours(1),
+            exp,
+        };
+
+        sign_claims(&service.km, &claims).await.unwrap()
+    }
+
+    fn claims_request(token: &str) -> Request<ClaimsRequest> {
+        let mut metadata = MetadataMap::new();
+        metadata.insert(
+            COOKIE.as_str(),
+            format!("authorization={}", token).parse().unwrap(),
+        );
+
+        let mut request = Request::new(ClaimsRequest {});
+        *request.metadata_mut() = metadata;
+        request
+    }
+
+    fn is_cached(service: &Service<KeyStore, UserStore, PermissionStore>, token: &str) -> bool {
+        service
+            .claims_cache
+            .lock()
+            .unwrap()
+            .get(token, Instant::now())
+            .is_some()
+    }
+
+    #[tokio::test]
+    async fn claims_cached() {
+        let service = claims_service().await;
+        let token = access_token(&service, chrono::Utc::now() + chrono::Duration::hours(1)).await;
+
+        let claims = service
+            .claims(claims_request(&token))
+            .await
+            .unwrap()
+            .into_inner()
+            .claims
+            .unwrap();
+        assert_eq!(claims.sub, "user");
+        assert!(is_cached(&service, &token));
+
+        // Change the cached claims; a second call that re-verified the token wouldn't see this.
+        for entry in service.claims_cache.lock().unwrap().entries.values_mut() {
+            entry.claims.sub = "cached".to_owned();
+        }
+
+        let claims = service
+            .claims(claims_request(&token))
+            .await
+            .unwrap()
+            .into_inner()
+            .claims
+            .unwrap();
+        assert_eq!(claims.sub, "cached");
+    }
+
+    #[tokio::test]
+    async fn expired_claims_not_cached() {
+        let service = claims_service().await;
+        let token = access_token(&service, chrono::Utc::now() - chrono::Duration::hours(1)).await;
+
+        let status = service.claims(claims_request(&token)).await.unwrap_err();
+
+        assert_eq!(status.code(), Code::Unauthenticated);
+        assert!(!is_cached(&service, &token));
+        assert!(service.claims_cache.lock().unwrap().entries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn revoked_claims_not_cached() {
+        let service = claims_service().await;
+        let token = access_token(&service, chrono::Utc::now() + chrono::Duration::hours(1)).await;
+
+        service.claims(claims_request(&token)).await.unwrap();
+        assert!(is_cached(&service, &token));
+
+        // Clearing old keys revokes every token they signed.
+        service.km.rotate_key(true).await.unwrap();
+
+        let status = service.claims(claims_request(&token)).await.unwrap_err();
+
+        assert_eq!(status.code(), Code::Unauthenticated);
+        assert!(service.claims_cache.lock().unwrap().entries.is_empty());
+    }
+
+    fn permission(role: PermissionRole) -> Permission {
+        Permission {
+            id: "permission".to_owned(),
+            user_id: "user".to_owned(),
+            role,
+        }
+    }
+
+    #[test]
+    fn server_admin_summary() {
+        let summary = permission_summary(vec![permission(PermissionRole::ServerAdmin)]);
+
+        assert_eq!(
+            summary,
+            PermissionSummary {
+                server_admin: true,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn organization_admin_summary() {
+        let summary = permission_summary(vec![
+            permission(PermissionRole::OrganizationAdmin("org 1".to_owned())),
+            permission(PermissionRole::OrganizationViewer("org 2".to_owned())),
+        ]);
+
+        assert_eq!(
+            summary,
+            PermissionSummary {
+                organization_admin_ids: vec!["org 1".to_owned()],
+                organization_viewer_ids: vec!["org 2".to_owned()],
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn event_viewer_summary() {
+        let summary = permission_summary(vec![permission(PermissionRole::EventViewer(
+            "event 1".to_owned(),
+        ))]);
+
+        assert_eq!(
+            summary,
+            PermissionSummary {
+                event_viewer_ids: vec!["event 1".to_owned()],
+                ..Default::default()
+            }
+        );
+    }
 }
@@ -12,6 +12,7 @@ pub fn try_logical_string_query<F: store::Field<Item = String>>(
         Some(string_query::Operator::NotEquals(not_equals)) => {
             Ok(store::LogicalQuery::NotEquals(not_equals))
         }
+        Some(string_query::Operator::In(list)) => Ok(store::LogicalQuery::In(list.values)),
         None => Err(ValidationError::new_empty("operator")),
     }
 }
@@ -3,24 +3,90 @@ use std::sync::Arc;
 use tonic::{Request, Response, Status};
 
 use crate::store::{
-    event::{Query, Store},
-    CompoundOperator, CompoundQuery,
+    self,
+    common::{format_timestamp, parse_timestamp},
+    event::{Query, Store, UpdateField},
+    ids::{EventId, OrgId, TemplateId},
+    registration_schema::Store as SchemaStore,
+    schema_template::Store as TemplateStore,
+    CompoundOperator, CompoundQuery, TimeRangeQuery,
 };
 use common::proto::{
-    self, compound_event_query, event_query, DeleteEventsResponse, EventQuery, QueryEventsRequest,
-    QueryEventsResponse, UpsertEventsRequest, UpsertEventsResponse,
+    self, compound_event_query, event_query, CreateEventSeriesRequest, CreateEventSeriesResponse,
+    DeleteEventsResponse, Event, EventQuery, GetEventWithOrganizationRequest,
+    GetEventWithOrganizationResponse, QueryEventsRequest, QueryEventsResponse, UpdateEventRequest,
+    UpdateEventResponse, UpsertEventsRequest, UpsertEventsResponse, UpsertOutcome,
 };
+use prost_types::FieldMask;
 
-use super::{common::try_logical_string_query, ValidationError};
+use super::{common::try_logical_string_query, registration_schema::SchemaCache, ValidationError};
 
 #[derive(Debug)]
-pub struct Service<StoreType: Store> {
+pub struct Service<StoreType: Store, SStore: SchemaStore, TStore: TemplateStore> {
     store: Arc<StoreType>,
+    schema_store: Arc<SStore>,
+    template_store: Arc<TStore>,
+    schema_cache: Arc<SchemaCache>,
+    max_query_results: u32,
 }
 
-impl<StoreType: Store> Service<StoreType> {
-    pub fn new(store: Arc<StoreType>) -> Self {
-        Service { store }
+impl<StoreType: Store, SStore: SchemaStore, TStore: TemplateStore>
+    Service<StoreType, SStore, TStore>
+{
+    pub fn new(
+        store: Arc<StoreType>,
+        schema_store: Arc<SStore>,
+        template_store: Arc<TStore>,
+    ) -> Self {
+        Service {
+            store,
+            schema_store,
+            template_store,
+            schema_cache: Arc::new(SchemaCache::default()),
+            max_query_results: super::DEFAULT_MAX_QUERY_RESULTS,
+        }
+    }
+
+    pub fn with_max_query_results(mut self, max_query_results: u32) -> Self {
+        self.max_query_results = max_query_results;
+        self
+    }
+
+    /// Shares the schema service's cache, so that schemas written or deleted along with events
+    /// aren't served stale.
+    pub fn with_schema_cache(mut self, schema_cache: Arc<SchemaCache>) -> Self {
+        self.schema_cache = schema_cache;
+        self
+    }
+
+    fn invalidate_schemas(&self, event_ids: &[String]) {
+        self.schema_cache
+            .invalidate(&event_ids.iter().map(|id| id.as_str()).collect::<Vec<_>>());
+    }
+
+    // Gives each event its own copy of the template's schema.
+    async fn instantiate_schemas(
+        &self,
+        template_id: &TemplateId,
+        events: &[Event],
+    ) -> store::StoreResult<()> {
+        let mut schemas = Vec::with_capacity(events.len());
+        for event in events {
+            schemas.push(
+                self.template_store
+                    .instantiate_template(template_id, &EventId::from(&event.id))
+                    .await?,
+            );
+        }
+
+        let event_ids = schemas
+            .iter()
+            .map(|schema| schema.event_id.clone())
+            .collect::<Vec<_>>();
+        self.schema_store.upsert(schemas).await?;
+        self.invalidate_schemas(&event_ids);
+
+        Ok(())
     }
 }
 
@@ -38,6 +104,18 @@ impl TryFrom<EventQuery> for Query {
                     .map_err(|e| e.with_context("query.organization_id"))?,
             )),
 
+            Some(event_query::Query::StartsAt(query)) => {
+                let start = parse_timestamp(&query.start)
+                    .map_err(|_| ValidationError::new_invalid_value("query.starts_at.start"))?;
+                let end = parse_timestamp(&query.end)
+                    .map_err(|_| ValidationError::new_invalid_value("query.starts_at.end"))?;
+
+                Ok(Query::StartsAt(TimeRangeQuery {
+                    start: start.as_ref().map(format_timestamp),
+                    end: end.as_ref().map(format_timestamp),
+                }))
+            }
+
             Some(event_query::Query::Compound(compound_query)) => {
                 let operator =
                     match compound_event_query::Operator::try_from(compound_query.operator) {
@@ -68,8 +146,51 @@ impl TryFrom<EventQuery> for Query {
     }
 }
 
+fn update_fields(event: &Event, mask: FieldMask) -> Result<Vec<UpdateField>, ValidationError> {
+    if mask.paths.is_empty() {
+        return Err(ValidationError::new_empty("update_mask.paths"));
+    }
+
+    mask.paths
+        .iter()
+        .enumerate()
+        .map(|(idx, path)| {
+            let (field, timestamp) = match path.as_str() {
+                "name" => (UpdateField::Name, None),
+                "starts_at" => (UpdateField::StartsAt, Some(&event.starts_at)),
+                "ends_at" => (UpdateField::EndsAt, Some(&event.ends_at)),
+                "registration_opens_at" => (
+                    UpdateField::RegistrationOpensAt,
+                    Some(&event.registration_opens_at),
+                ),
+                "registration_closes_at" => (
+                    UpdateField::RegistrationClosesAt,
+                    Some(&event.registration_closes_at),
+                ),
+                _ => {
+                    return Err(ValidationError::new_invalid_value(&format!(
+                        "update_mask.paths[{}]",
+                        idx
+                    )))
+                }
+            };
+
+            if timestamp.is_some_and(|timestamp| parse_timestamp(timestamp).is_err()) {
+                return Err(ValidationError::new_invalid_value(&format!(
+                    "event.{}",
+                    path
+                )));
+            }
+
+            Ok(field)
+        })
+        .collect()
+}
+
 #[tonic::async_trait]
-impl<StoreType: Store> proto::event_service_server::EventService for Service<StoreType> {
+impl<StoreType: Store, SStore: SchemaStore, TStore: TemplateStore>
+    proto::event_service_server::EventService for Service<StoreType, SStore, TStore>
+{
     async fn upsert_events(
         &self,
         request: Request<UpsertEventsRequest>,
@@ -83,14 +204,49 @@ impl<StoreType: Store> proto::event_service_server::EventService for Service<Sto
                 ))
                 .into());
             }
+
+            if parse_timestamp(&event.starts_at).is_err() {
+                return Err(ValidationError::new_invalid_value(&format!(
+                    "events[{}].starts_at",
+                    idx
+                ))
+                .into());
+            }
+
+            if parse_timestamp(&event.ends_at).is_err() {
+                return Err(ValidationError::new_invalid_value(&format!(
+                    "events[{}].ends_at",
+                    idx
+                ))
+                .into());
+            }
+
+            if parse_timestamp(&event.registration_opens_at).is_err() {
+                return Err(ValidationError::new_invalid_value(&format!(
+                    "events[{}].registration_opens_at",
+                    idx
+                ))
+                .into());
+            }
+
+            if parse_timestamp(&event.registration_closes_at).is_err() {
+                return Err(ValidationError::new_invalid_value(&format!(
+                    "events[{}].registration_closes_at",
+                    idx
+                ))
+                .into());
+            }
         }
 
-        let events = self
+        let (events, outcomes) = self
             .store
-            .upsert(events)
+            .upsert_with_outcomes(events)
             .await
             .map_err(|e| -> Status { e.into() })?;
-        Ok(Response::new(UpsertEventsResponse { events }))
+        Ok(Response::new(UpsertEventsResponse {
+            events,
+            outcomes: outcomes.into_iter().map(UpsertOutcome::from).collect(),
+        }))
     }
 
     async fn query_events(
@@ -102,21 +258,364 @@ impl<StoreType: Store> proto::event_service_server::EventService for Service<Sto
 
         let events = self
             .store
-            .query(query.as_ref())
+            .query_capped(query.as_ref(), self.max_query_results)
             .await
             .map_err(|e| -> Status { e.into() })?;
-        Ok(Response::new(QueryEventsResponse { events }))
+        Ok(Response::new(QueryEventsResponse {
+            events: events.rows,
+            truncated: events.truncated,
+        }))
     }
 
     async fn delete_events(
         &self,
         request: Request<proto::DeleteEventsRequest>,
     ) -> Result<Response<DeleteEventsResponse>, Status> {
+        let ids = request.into_inner().ids;
         self.store
-            .delete(&request.into_inner().ids)
+            .delete(&ids)
             .await
             .map_err(|e| -> Status { e.into() })?;
 
+        // Deleting an event deletes its schema with it.
+        self.invalidate_schemas(&ids);
+
         Ok(Response::new(DeleteEventsResponse {}))
     }
+
+    async fn update_event(
+        &self,
+        request: Request<UpdateEventRequest>,
+    ) -> Result<Response<UpdateEventResponse>, Status> {
+        let request = request.into_inner();
+        let event = request
+            .event
+            .ok_or_else(|| ValidationError::new_empty("event"))?;
+        if event.id.is_empty() {
+            return Err(ValidationError::new_empty("event.id").into());
+        }
+
+        let fields = update_fields(
+            &event,
+            request
+                .update_mask
+                .ok_or_else(|| ValidationError::new_empty("update_mask"))?,
+        )?;
+
+        let event = self
+            .store
+            .update(event, &fields)
+            .await
+            .map_err(|e| -> Status { e.into() })?;
+
+        Ok(Response::new(UpdateEventResponse { event: Some(event) }))
+    }
+
+    async fn get_event_with_organization(
+        &self,
+        request: Request<GetEventWithOrganizationRequest>,
+    ) -> Result<Response<GetEventWithOrganizationResponse>, Status> {
+        let request = request.into_inner();
+        let event_id = request.event_id;
+        if event_id.is_empty() {
+            return Err(ValidationError::new_empty("event_id").into());
+        }
+
+        // A URL can pair an event with any organization, so don't trust that they match.
+        if !request.organization_id.is_empty()
+            && !self
+                .store
+                .event_belongs_to_org(
+                    &EventId::from(&event_id),
+                    &OrgId::from(request.organization_id),
+                )
+                .await
+                .map_err(|e| -> Status { e.into() })?
+        {
+            return Err(store::Error::IdDoesNotExist(event_id).into());
+        }
+
+        let (event, organization) = self
+            .store
+            .get_event_with_organization(&EventId::from(event_id))
+            .await
+            .map_err(|e| -> Status { e.into() })?;
+
+        Ok(Response::new(GetEventWithOrganizationResponse {
+            event: Some(event),
+            organization: Some(organization),
+        }))
+    }
+
+    async fn create_event_series(
+        &self,
+        request: Request<CreateEventSeriesRequest>,
+    ) -> Result<Response<CreateEventSeriesResponse>, Status> {
+        let request = request.into_inner();
+        if request.organization_id.is_empty() {
+            return Err(ValidationError::new_empty("organization_id").into());
+        }
+
+        let template = request
+            .template
+            .ok_or_else(|| ValidationError::new_empty("template"))?;
+
+        let dates = request
+            .dates
+            .into_iter()
+            .enumerate()
+            .map(|(idx, dates)| {
+                if !matches!(parse_timestamp(&dates.starts_at), Ok(Some(_))) {
+                    return Err(ValidationError::new_invalid_value(&format!(
+                        "dates[{}].starts_at",
+                        idx
+                    )));
+                }
+
+                if parse_timestamp(&dates.ends_at).is_err() {
+                    return Err(ValidationError::new_invalid_value(&format!(
+                        "dates[{}].ends_at",
+                        idx
+                    )));
+                }
+
+                Ok((dates.starts_at, dates.ends_at))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let events = self
+            .store
+            .create_series(&OrgId::from(request.organization_id), template, &dates)
+            .await
+            .map_err(|e| -> Status { e.into() })?;
+
+        if !request.schema_template_id.is_empty() {
+            if let Err(e) = self
+                .instantiate_schemas(&TemplateId::from(request.schema_template_id), &events)
+                .await
+            {
+                // The events were committed on their own, so take them back out rather than
+                // leave a series without its schemas.
+                let ids = events.into_iter().map(|event| event.id).collect::<Vec<_>>();
+                if let Err(delete_err) = self.store.delete(&ids).await {
+                    log::error!("unable to remove events {:?}: {}", ids, delete_err);
+                }
+                self.invalidate_schemas(&ids);
+
+                return Err(e.into());
+            }
+        }
+
+        Ok(Response::new(CreateEventSeriesResponse { events }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use common::proto::{
+        event_service_server::EventService as _, registration_schema_item_type::Type as ItemType,
+        registration_schema_service_server::RegistrationSchemaService as _, CheckboxType,
+        CreateEventSeriesRequest, DeleteEventsRequest, Event, EventDates,
+        QueryRegistrationSchemasRequest, RegistrationSchemaItem, RegistrationSchemaItemType,
+        SchemaTemplate,
+    };
+    use sqlx::{migrate::MigrateDatabase, Sqlite, SqlitePool};
+    use tonic::{Code, Request};
+
+    use crate::{
+        api::registration_schema::{SchemaCache, Service as SchemaService},
+        keys::KeyManager,
+        store::{
+            common::new_id,
+            event::{SqliteStore, Store as _},
+            keys::SqliteStore as KeyStore,
+            registration_schema::{SqliteStore as SchemaStore, Store as _},
+            schema_template::{SqliteStore as TemplateStore, Store as _},
+        },
+    };
+
+    use super::Service;
+
+    struct Init {
+        org: String,
+        template_id: String,
+        service: Service<SqliteStore, SchemaStore, TemplateStore>,
+        db: Arc<SqlitePool>,
+    }
+
+    async fn init() -> Init {
+        let db_url = "sqlite://:memory:";
+        Sqlite::create_database(db_url).await.unwrap();
+
+        let db = SqlitePool::connect(db_url).await.unwrap();
+        sqlx::migrate!("../migrations").run(&db).await.unwrap();
+
+        let org = new_id();
+        sqlx::query("INSERT INTO organizations(id, name) VALUES (?, ?);")
+            .bind(&org)
+            .bind("Organization 1")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let db = Arc::new(db);
+        let template_store = Arc::new(TemplateStore::new(db.clone()));
+        let template_id = template_store
+            .upsert(vec![SchemaTemplate {
+                id: "".to_owned(),
+                organization_id: org.clone(),
+                name: "Class".to_owned(),
+                items: vec![RegistrationSchemaItem {
+                    name: "Attending".to_owned(),
+                    r#type: Some(RegistrationSchemaItemType {
+                        r#type: Some(ItemType::Checkbox(CheckboxType { default: false })),
+                    }),
+                    ..Default::default()
+                }],
+            }])
+            .await
+            .unwrap()
+            .remove(0)
+            .id;
+
+        let service = Service::new(
+            Arc::new(SqliteStore::new(db.clone())),
+            Arc::new(SchemaStore::new(db.clone())),
+            template_store,
+        );
+
+        Init {
+            org,
+            template_id,
+            service,
+            db,
+        }
+    }
+
+    fn series_request(org: &str, template_id: &str) -> Request<CreateEventSeriesRequest> {
+        Request::new(CreateEventSeriesRequest {
+            organization_id: org.to_owned(),
+            template: Some(Event {
+                name: "Blues Class".to_owned(),
+                ..Default::default()
+            }),
+            dates: [1, 8, 15, 22]
+                .into_iter()
+                .map(|day| EventDates {
+                    starts_at: format!("2024-07-{:02}T19:00:00Z", day),
+                    ends_at: format!("2024-07-{:02}T21:00:00Z", day),
+                })
+                .collect(),
+            schema_template_id: template_id.to_owned(),
+        })
+    }
+
+    #[tokio::test]
+    async fn create_event_series() {
+        let init = init().await;
+
+        let events = init
+            .service
+            .create_event_series(series_request(&init.org, &init.template_id))
+            .await
+            .unwrap()
+            .into_inner()
+            .events;
+
+        assert_eq!(
+            events
+                .iter()
+                .map(|event| (event.name.as_str(), event.starts_at.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("Blues Class 2024-07-01", "2024-07-01T19:00:00Z"),
+                ("Blues Class 2024-07-08", "2024-07-08T19:00:00Z"),
+                ("Blues Class 2024-07-15", "2024-07-15T19:00:00Z"),
+                ("Blues Class 2024-07-22", "2024-07-22T19:00:00Z"),
+            ]
+        );
+
+        let schemas = SchemaStore::new(init.db.clone()).query(None).await.unwrap();
+        assert_eq!(schemas.len(), 4);
+        for event in events.iter() {
+            let schema = schemas
+                .iter()
+                .find(|schema| schema.event_id == event.id)
+                .unwrap();
+            assert_eq!(
+                schema
+                    .items
+                    .iter()
+                    .map(|item| item.name.as_str())
+                    .collect::<Vec<_>>(),
+                vec!["Attending"]
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn schema_cache_shared() {
+        let init = init().await;
+
+        let schema_cache = Arc::new(SchemaCache::default());
+        let service = Service::new(
+            Arc::new(SqliteStore::new(init.db.clone())),
+            Arc::new(SchemaStore::new(init.db.clone())),
+            Arc::new(TemplateStore::new(init.db.clone())),
+        )
+        .with_schema_cache(schema_cache.clone());
+        let schema_service = SchemaService::new(
+            Arc::new(SchemaStore::new(init.db.clone())),
+            Arc::new(TemplateStore::new(init.db.clone())),
+            Arc::new(KeyManager::new(Arc::new(KeyStore::new(init.db.clone())))),
+        )
+        .with_schema_cache(schema_cache);
+
+        let query_schemas = || async {
+            schema_service
+                .query_registration_schemas(Request::new(QueryRegistrationSchemasRequest {
+                    query: None,
+                }))
+                .await
+                .unwrap()
+                .into_inner()
+                .registration_schemas
+        };
+
+        // Cache an empty result before any schemas exist.
+        assert!(query_schemas().await.is_empty());
+
+        let events = service
+            .create_event_series(series_request(&init.org, &init.template_id))
+            .await
+            .unwrap()
+            .into_inner()
+            .events;
+        assert_eq!(query_schemas().await.len(), 4);
+
+        service
+            .delete_events(Request::new(DeleteEventsRequest {
+                ids: events.into_iter().map(|event| event.id).collect(),
+            }))
+            .await
+            .unwrap();
+        assert!(query_schemas().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn create_event_series_unknown_template() {
+        let init = init().await;
+
+        let result = init
+            .service
+            .create_event_series(series_request(&init.org, &new_id()))
+            .await;
+        assert_eq!(result.unwrap_err().code(), Code::NotFound);
+
+        // The events go away with the schemas that couldn't be made for them.
+        let events = SqliteStore::new(init.db.clone()).query(None).await.unwrap();
+        assert!(events.is_empty());
+    }
 }
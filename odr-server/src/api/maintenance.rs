@@ -0,0 +1,157 @@
+use std::sync::Arc;
+
+use common::proto::{
+    self, CheckIntegrityRequest, CheckIntegrityResponse, ReadyRequest, ReadyResponse,
+};
+use sqlx::SqlitePool;
+use tonic::{Code, Request, Response, Status};
+
+use crate::{
+    keys::KeyManager,
+    store::{
+        integrity::{check_integrity, IntegrityReport},
+        keys::Store as KeyStore,
+        permission::Store as PermissionStore,
+    },
+};
+
+use super::authentication::{check_signing_key, require_server_admin};
+
+pub struct Service<KStore: KeyStore, PStore: PermissionStore> {
+    db: Arc<SqlitePool>,
+    km: Arc<KeyManager<KStore>>,
+    permission_store: Arc<PStore>,
+}
+
+impl<KStore: KeyStore, PStore: PermissionStore> Service<KStore, PStore> {
+    pub fn new(
+        db: Arc<SqlitePool>,
+        km: Arc<KeyManager<KStore>>,
+        permission_store: Arc<PStore>,
+    ) -> Self {
+        Service {
+            db,
+            km,
+            permission_store,
+        }
+    }
+}
+
+impl From<IntegrityReport> for CheckIntegrityResponse {
+    fn from(report: IntegrityReport) -> Self {
+        CheckIntegrityResponse {
+            orphaned_registration_item_ids: report.orphaned_registration_items,
+            orphaned_select_option_ids: report.orphaned_select_options,
+            dangling_permission_ids: report.dangling_permissions,
+            orphaned_registration_ids: report.orphaned_registrations,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl<KStore: KeyStore, PStore: PermissionStore>
+    proto::maintenance_service_server::MaintenanceService for Service<KStore, PStore>
+{
+    async fn check_integrity(
+        &self,
+        request: Request<CheckIntegrityRequest>,
+    ) -> Result<Response<CheckIntegrityResponse>, Status> {
+        require_server_admin(&self.km, &*self.permission_store, request.metadata()).await?;
+
+        let report = check_integrity(&self.db)
+            .await
+            .map_err(|e| -> Status { e.into() })?;
+
+        Ok(Response::new(report.into()))
+    }
+
+    async fn ready(&self, _: Request<ReadyRequest>) -> Result<Response<ReadyResponse>, Status> {
+        sqlx::query("SELECT 1")
+            .execute(&*self.db)
+            .await
+            .map_err(|e| Status::new(Code::Unavailable, format!("database unreachable: {}", e)))?;
+
+        check_signing_key(&self.km).await.map_err(|e| {
+            Status::new(
+                Code::Unavailable,
+                format!("signing keys unusable: {}", e.message()),
+            )
+        })?;
+
+        Ok(Response::new(ReadyResponse {}))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use common::proto::{
+        maintenance_service_server::MaintenanceService, CheckIntegrityRequest, ReadyRequest,
+    };
+    use sqlx::{migrate::MigrateDatabase, Sqlite, SqlitePool};
+    use test_case::test_case;
+    use tonic::{Code, Request};
+
+    use crate::{
+        keys::KeyManager,
+        store::{keys::SqliteStore as KeyStore, permission::SqliteStore as PermissionStore},
+    };
+
+    use super::Service;
+
+    enum Keys {
+        Valid,
+        Missing,
+        Corrupt,
+    }
+
+    #[test_case(Keys::Valid, None ; "valid")]
+    #[test_case(Keys::Missing, Some(Code::Unavailable) ; "missing")]
+    #[test_case(Keys::Corrupt, Some(Code::Unavailable) ; "corrupt")]
+    #[tokio::test]
+    async fn ready(keys: Keys, expected: Option<Code>) {
+        let db_url = "sqlite://:memory:";
+        Sqlite::create_database(db_url).await.unwrap();
+
+        let db = Arc::new(SqlitePool::connect(db_url).await.unwrap());
+        sqlx::migrate!("../migrations").run(&*db).await.unwrap();
+
+        let km = Arc::new(KeyManager::new(Arc::new(KeyStore::new(db.clone()))));
+        match keys {
+            Keys::Valid => km.rotate_key(false).await.unwrap(),
+            Keys::Missing => (),
+            Keys::Corrupt => {
+                km.rotate_key(false).await.unwrap();
+                sqlx::query("UPDATE keys SET eddsa_key = X'00'")
+                    .execute(&*db)
+                    .await
+                    .unwrap();
+            }
+        }
+
+        let service = Service::new(db.clone(), km, Arc::new(PermissionStore::new(db)));
+        let result = service.ready(Request::new(ReadyRequest {})).await;
+
+        assert_eq!(result.err().map(|status| status.code()), expected);
+    }
+
+    #[tokio::test]
+    async fn check_integrity_requires_authentication() {
+        let db_url = "sqlite://:memory:";
+        Sqlite::create_database(db_url).await.unwrap();
+
+        let db = Arc::new(SqlitePool::connect(db_url).await.unwrap());
+        sqlx::migrate!("../migrations").run(&*db).await.unwrap();
+
+        let km = Arc::new(KeyManager::new(Arc::new(KeyStore::new(db.clone()))));
+        km.rotate_key(false).await.unwrap();
+
+        let service = Service::new(db.clone(), km, Arc::new(PermissionStore::new(db)));
+        let result = service
+            .check_integrity(Request::new(CheckIntegrityRequest {}))
+            .await;
+
+        assert_eq!(result.unwrap_err().code(), Code::Unauthenticated);
+    }
+}
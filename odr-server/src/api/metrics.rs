@@ -0,0 +1,124 @@
+use std::sync::Arc;
+
+use common::proto::{self, GetMetricsRequest, GetMetricsResponse};
+use tonic::{Request, Response, Status};
+
+use crate::metrics::Registry;
+
+pub struct Service {
+    registry: Arc<Registry>,
+}
+
+impl Service {
+    pub fn new(registry: Arc<Registry>) -> Self {
+        Service { registry }
+    }
+}
+
+#[tonic::async_trait]
+impl proto::metrics_service_server::MetricsService for Service {
+    async fn get_metrics(
+        &self,
+        _: Request<GetMetricsRequest>,
+    ) -> Result<Response<GetMetricsResponse>, Status> {
+        Ok(Response::new(GetMetricsResponse {
+            text: self.registry.render(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use common::proto::{event_service_server::EventServiceServer, EventQuery, QueryEventsRequest};
+    use prost::Message;
+    use sqlx::{migrate::MigrateDatabase, Sqlite, SqlitePool};
+    use tower::{Layer, ServiceExt};
+
+    use crate::{
+        api::event::Service as EventService,
+        metrics::{MetricsLayer, Registry},
+        store::{
+            event::SqliteStore, registration_schema::SqliteStore as SchemaStore,
+            schema_template::SqliteStore as TemplateStore,
+        },
+    };
+
+    const QUERY_EVENTS: &str = "/proto.EventService/QueryEvents";
+
+    async fn init_db() -> SqlitePool {
+        let db_url = "sqlite://:memory:";
+        Sqlite::create_database(db_url).await.unwrap();
+
+        let db = SqlitePool::connect(db_url).await.unwrap();
+        sqlx::migrate!("../migrations").run(&db).await.unwrap();
+
+        db
+    }
+
+    fn grpc_request(path: &str, message: impl Message) -> http::Request<hyper::Body> {
+        let encoded = message.encode_to_vec();
+        let mut body = vec![0];
+        body.extend_from_slice(&(encoded.len() as u32).to_be_bytes());
+        body.extend(encoded);
+
+        http::Request::post(path)
+            .header("content-type", "application/grpc")
+            .header("te", "trailers")
+            .body(hyper::Body::from(body))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn counts_requests_and_errors() {
+        let db = init_db().await;
+        let registry = Arc::new(Registry::new());
+        let db = Arc::new(db);
+        let service =
+            MetricsLayer::new(registry.clone()).layer(EventServiceServer::new(EventService::new(
+                Arc::new(SqliteStore::new(db.clone())),
+                Arc::new(SchemaStore::new(db.clone())),
+                Arc::new(TemplateStore::new(db)),
+            )));
+
+        for _ in 0..2 {
+            service
+                .clone()
+                .oneshot(grpc_request(
+                    QUERY_EVENTS,
+                    QueryEventsRequest { query: None },
+                ))
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(registry.requests(QUERY_EVENTS), 2);
+        assert_eq!(registry.errors(QUERY_EVENTS), 0);
+
+        // A query with no contents fails validation.
+        service
+            .clone()
+            .oneshot(grpc_request(
+                QUERY_EVENTS,
+                QueryEventsRequest {
+                    query: Some(EventQuery { query: None }),
+                },
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(registry.requests(QUERY_EVENTS), 3);
+        assert_eq!(registry.errors(QUERY_EVENTS), 1);
+
+        let text = registry.render();
+        assert!(text.contains(&format!(
+            "odr_requests_total{{method=\"{}\"}} 3",
+            QUERY_EVENTS
+        )));
+        assert!(text.contains(&format!(
+            "odr_request_errors_total{{method=\"{}\"}} 1",
+            QUERY_EVENTS
+        )));
+    }
+}
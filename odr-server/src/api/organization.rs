@@ -1,26 +1,59 @@
 use std::sync::Arc;
 
 use common::proto::{
-    self, compound_organization_query, organization_query, DeleteOrganizationsRequest,
-    DeleteOrganizationsResponse, OrganizationQuery, QueryOrganizationsRequest,
-    QueryOrganizationsResponse, UpsertOrganizationsRequest, UpsertOrganizationsResponse,
+    self, compound_organization_query, organization_query, CloneOrganizationRequest,
+    CloneOrganizationResponse, DeleteOrganizationsRequest, DeleteOrganizationsResponse,
+    OrganizationQuery, QueryOrganizationsRequest, QueryOrganizationsResponse,
+    UpsertOrganizationsRequest, UpsertOrganizationsResponse,
 };
 use tonic::{Request, Response, Status};
 
-use crate::store::{
-    organization::{Query, Store},
-    CompoundOperator, CompoundQuery,
+use crate::{
+    keys::KeyManager,
+    store::{
+        ids::OrgId,
+        keys::Store as KeyStore,
+        organization::{Query, Store},
+        permission::Store as PermissionStore,
+        CompoundOperator, CompoundQuery, Pagination,
+    },
 };
 
-use super::{common::try_logical_string_query, ValidationError};
+use super::{
+    authentication::require_server_admin, common::try_logical_string_query,
+    registration_schema::SchemaCache, ValidationError,
+};
+
+const MAX_QUERY_LIMIT: u32 = 500;
 
-pub struct Service<StoreType: Store> {
+pub struct Service<StoreType: Store, KStore: KeyStore, PStore: PermissionStore> {
     store: Arc<StoreType>,
+    km: Arc<KeyManager<KStore>>,
+    permission_store: Arc<PStore>,
+    schema_cache: Arc<SchemaCache>,
 }
 
-impl<StoreType: Store> Service<StoreType> {
-    pub fn new(store: Arc<StoreType>) -> Self {
-        Service { store }
+impl<StoreType: Store, KStore: KeyStore, PStore: PermissionStore>
+    Service<StoreType, KStore, PStore>
+{
+    pub fn new(
+        store: Arc<StoreType>,
+        km: Arc<KeyManager<KStore>>,
+        permission_store: Arc<PStore>,
+    ) -> Self {
+        Service {
+            store,
+            km,
+            permission_store,
+            schema_cache: Arc::new(SchemaCache::default()),
+        }
+    }
+
+    /// Shares the schema service's cache, so that schemas deleted or copied along with an
+    /// organization's events aren't served stale.
+    pub fn with_schema_cache(mut self, schema_cache: Arc<SchemaCache>) -> Self {
+        self.schema_cache = schema_cache;
+        self
     }
 }
 
@@ -33,6 +66,8 @@ impl TryFrom<OrganizationQuery> for Query {
                 try_logical_string_query(query).map_err(|e| e.with_context("query.id"))?,
             )),
 
+            Some(organization_query::Query::NameContains(name)) => Ok(Query::NameContains(name)),
+
             Some(organization_query::Query::Compound(compound_query)) => {
                 let operator = match compound_organization_query::Operator::try_from(
                     compound_query.operator,
@@ -63,8 +98,8 @@ impl TryFrom<OrganizationQuery> for Query {
 }
 
 #[tonic::async_trait]
-impl<StoreType: Store> proto::organization_service_server::OrganizationService
-    for Service<StoreType>
+impl<StoreType: Store, KStore: KeyStore, PStore: PermissionStore>
+    proto::organization_service_server::OrganizationService for Service<StoreType, KStore, PStore>
 {
     async fn upsert_organizations(
         &self,
@@ -85,16 +120,48 @@ impl<StoreType: Store> proto::organization_service_server::OrganizationService
         &self,
         request: Request<QueryOrganizationsRequest>,
     ) -> Result<Response<QueryOrganizationsResponse>, Status> {
-        let query = request.into_inner().query;
-        let query = query.map(|query| query.try_into()).transpose()?;
+        let request = request.into_inner();
 
-        let organizations = self
-            .store
-            .query(query.as_ref())
-            .await
-            .map_err(|e| -> Status { e.into() })?;
+        let query = request.query.map(|query| query.try_into()).transpose()?;
+
+        let (organizations, total_count) = match request.limit {
+            0 => {
+                let organizations = self
+                    .store
+                    .query(query.as_ref())
+                    .await
+                    .map_err(|e| -> Status { e.into() })?;
+
+                let total_count = organizations.len() as u64;
+                (organizations, total_count)
+            }
+            limit => {
+                let page = Pagination {
+                    limit,
+                    offset: request.offset,
+                }
+                .clamp(MAX_QUERY_LIMIT);
+
+                let organizations = self
+                    .store
+                    .query_page(query.as_ref(), page)
+                    .await
+                    .map_err(|e| -> Status { e.into() })?;
 
-        Ok(Response::new(QueryOrganizationsResponse { organizations }))
+                let total_count = self
+                    .store
+                    .count(query.as_ref())
+                    .await
+                    .map_err(|e| -> Status { e.into() })?;
+
+                (organizations, total_count)
+            }
+        };
+
+        Ok(Response::new(QueryOrganizationsResponse {
+            organizations,
+            total_count,
+        }))
     }
 
     async fn delete_organizations(
@@ -106,6 +173,142 @@ impl<StoreType: Store> proto::organization_service_server::OrganizationService
             .await
             .map_err(|e| -> Status { e.into() })?;
 
+        // The organizations' events and their schemas go with them, and their ids aren't at hand.
+        self.schema_cache.invalidate_all();
+
         Ok(Response::new(DeleteOrganizationsResponse {}))
     }
+
+    async fn clone_organization(
+        &self,
+        request: Request<CloneOrganizationRequest>,
+    ) -> Result<Response<CloneOrganizationResponse>, Status> {
+        require_server_admin(&self.km, &*self.permission_store, request.metadata()).await?;
+
+        let request = request.into_inner();
+        if request.source_id.is_empty() {
+            return Err(ValidationError::new_empty("source_id").into());
+        }
+        if request.name.trim().is_empty() {
+            return Err(ValidationError::new_empty("name").into());
+        }
+
+        let organization = self
+            .store
+            .clone_organization(&OrgId::from(request.source_id), request.name.trim())
+            .await
+            .map_err(|e| -> Status { e.into() })?;
+
+        // The copied events come with copies of their schemas, which unfiltered queries include.
+        self.schema_cache.invalidate_all();
+
+        Ok(Response::new(CloneOrganizationResponse {
+            organization: Some(organization),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use common::proto::{
+        organization_service_server::OrganizationService, CloneOrganizationRequest,
+    };
+    use http::header::COOKIE;
+    use sqlx::{migrate::MigrateDatabase, Sqlite, SqlitePool};
+    use test_case::test_case;
+    use tonic::{Code, Request};
+    use uuid::Uuid;
+
+    use crate::{
+        api::authentication::create_access_token,
+        keys::KeyManager,
+        store::{
+            keys::SqliteStore as KeyStore, organization::SqliteStore as OrganizationStore,
+            permission::SqliteStore as PermissionStore,
+        },
+    };
+
+    use super::Service;
+
+    #[test_case(None, Some(Code::Unauthenticated) ; "signed out")]
+    #[test_case(Some("ORGANIZATION_ADMIN"), Some(Code::PermissionDenied) ; "organization admin")]
+    #[test_case(Some("SERVER_ADMIN"), None ; "server admin")]
+    #[tokio::test]
+    async fn clone_organization_requires_server_admin(role: Option<&str>, expected: Option<Code>) {
+        let db_url = "sqlite://:memory:";
+        Sqlite::create_database(db_url).await.unwrap();
+
+        let db = SqlitePool::connect(db_url).await.unwrap();
+        sqlx::migrate!("../migrations").run(&db).await.unwrap();
+
+        let org = Uuid::now_v7().to_string();
+        sqlx::query("INSERT INTO organizations(id, name) VALUES (?, ?);")
+            .bind(&org)
+            .bind("Organization 1")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let user = Uuid::now_v7().to_string();
+        sqlx::query("INSERT INTO users(id, email, password, display_name) VALUES (?, ?, ?, ?);")
+            .bind(&user)
+            .bind("user@example.com")
+            .bind("")
+            .bind("User")
+            .execute(&db)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO permissions(id, user, role, organization) VALUES (?, ?, ?, ?);")
+            .bind(Uuid::now_v7().to_string())
+            .bind(&user)
+            .bind("ORGANIZATION_ADMIN")
+            .bind(&org)
+            .execute(&db)
+            .await
+            .unwrap();
+        if role == Some("SERVER_ADMIN") {
+            sqlx::query("INSERT INTO permissions(id, user, role) VALUES (?, ?, ?);")
+                .bind(Uuid::now_v7().to_string())
+                .bind(&user)
+                .bind("SERVER_ADMIN")
+                .execute(&db)
+                .await
+                .unwrap();
+        }
+
+        let db = Arc::new(db);
+        let km = Arc::new(KeyManager::new(Arc::new(KeyStore::new(db.clone()))));
+        km.rotate_key(false).await.unwrap();
+        let service = Service::new(
+            Arc::new(OrganizationStore::new(db.clone())),
+            km.clone(),
+            Arc::new(PermissionStore::new(db)),
+        );
+
+        let mut request = Request::new(CloneOrganizationRequest {
+            source_id: org.clone(),
+            name: "Staging".to_owned(),
+        });
+        if role.is_some() {
+            let token = create_access_token(&km, &user, chrono::Duration::hours(1))
+                .await
+                .unwrap();
+            request.metadata_mut().insert(
+                COOKIE.as_str(),
+                format!("authorization={}", token).parse().unwrap(),
+            );
+        }
+
+        let result = service.clone_organization(request).await;
+        match expected {
+            Some(code) => assert_eq!(result.unwrap_err().code(), code),
+            None => {
+                let organization = result.unwrap().into_inner().organization.unwrap();
+                assert_ne!(organization.id, org);
+                assert_eq!(organization.name, "Staging");
+            }
+        }
+    }
 }
@@ -0,0 +1,1772 @@
+use std::sync::Arc;
+
+use common::proto::{
+    self, registration, CreatePublicLinkRequest, CreatePublicLinkResponse,
+    CreatePublicRegistrationRequest, CreatePublicRegistrationResponse, DuplicateWarning,
+    GetDraftRegistrationRequest, GetDraftRegistrationResponse, GetEditableRegistrationRequest,
+    GetEditableRegistrationResponse, GetPublicRegistrationSchemaRequest,
+    GetPublicRegistrationSchemaResponse, Registration, RegistrationSchema, RequestEditLinkRequest,
+    RequestEditLinkResponse, SaveDraftRegistrationRequest, SaveDraftRegistrationResponse,
+    SubmitDraftRegistrationRequest, SubmitDraftRegistrationResponse,
+    UpdateEditableRegistrationRequest, UpdateEditableRegistrationResponse, ValidateFieldRequest,
+    ValidateFieldResponse,
+};
+use tonic::{metadata::MetadataMap, Code, Request, Response, Status};
+
+use crate::{
+    keys::KeyManager,
+    notifier::Notifier,
+    store::{
+        event::{self, Store as EventStore},
+        ids::{EventId, UserId},
+        keys::Store as KeyStore,
+        permission::{EventRole, Store as PermissionStore},
+        registration::{
+            self as registration_store, validate_draft_items, validate_items, validate_value,
+            Editor, Store as RegistrationStore,
+        },
+        registration_schema::{self, Store as SchemaStore},
+    },
+};
+
+use super::{
+    authentication::{
+        create_public_registration_token, create_registration_edit_token, request_user_id,
+        require_event_role, validate_public_registration_token, validate_registration_edit_token,
+        validate_request,
+    },
+    ValidationError,
+};
+
+const PUBLIC_REGISTRATION_TOKEN_HEADER: &str = "x-public-registration-token";
+const REGISTRATION_EDIT_TOKEN_HEADER: &str = "x-registration-edit-token";
+const MAX_PUBLIC_LINK_TTL_SECONDS: u64 = 60 * 60 * 24 * 365;
+const DRAFT_TOKEN_TTL_SECONDS: i64 = 60 * 60 * 24 * 30;
+
+fn link_ttl(ttl_seconds: u64) -> Result<chrono::Duration, ValidationError> {
+    if ttl_seconds == 0 {
+        return Err(ValidationError::new_empty("ttl_seconds"));
+    }
+
+    if ttl_seconds > MAX_PUBLIC_LINK_TTL_SECONDS {
+        return Err(ValidationError::new_invalid_value("ttl_seconds"));
+    }
+
+    Ok(chrono::Duration::seconds(ttl_seconds as i64))
+}
+
+fn token_from_header<'a>(metadata: &'a MetadataMap, header: &str) -> Option<&'a str> {
+    metadata.get(header).and_then(|value| value.to_str().ok())
+}
+
+pub struct Service<
+    KStore: KeyStore,
+    PStore: PermissionStore,
+    EStore: EventStore,
+    SStore: SchemaStore,
+    RStore: RegistrationStore,
+    N: Notifier,
+> {
+    km: Arc<KeyManager<KStore>>,
+    permission_store: Arc<PStore>,
+    event_store: Arc<EStore>,
+    schema_store: Arc<SStore>,
+    registration_store: Arc<RStore>,
+    notifier: Arc<N>,
+}
+
+impl<
+        KStore: KeyStore,
+        PStore: PermissionStore,
+        EStore: EventStore,
+        SStore: SchemaStore,
+        RStore: RegistrationStore,
+        N: Notifier,
+    > Service<KStore, PStore, EStore, SStore, RStore, N>
+{
+    pub fn new(
+        km: Arc<KeyManager<KStore>>,
+        permission_store: Arc<PStore>,
+        event_store: Arc<EStore>,
+        schema_store: Arc<SStore>,
+        registration_store: Arc<RStore>,
+        notifier: Arc<N>,
+    ) -> Self {
+        Service {
+            km,
+            permission_store,
+            event_store,
+            schema_store,
+            registration_store,
+            notifier,
+        }
+    }
+
+    // The public token is the only credential accepted by the registrant endpoints, and it only
+    // ever grants access to the event it was issued for.
+    async fn authorized_event_id(&self, metadata: &MetadataMap) -> Result<String, Status> {
+        let token = token_from_header(metadata, PUBLIC_REGISTRATION_TOKEN_HEADER)
+            .ok_or_else(|| Status::new(Code::Unauthenticated, "unauthenticated"))?;
+
+        validate_public_registration_token(&self.km, token).await
+    }
+
+    // Edit tokens name the registration they were issued for, so registrants can never reach
+    // any other registration through them.
+    async fn authorized_registration(
+        &self,
+        metadata: &MetadataMap,
+    ) -> Result<Registration, Status> {
+        let token = token_from_header(metadata, REGISTRATION_EDIT_TOKEN_HEADER)
+            .ok_or_else(|| Status::new(Code::Unauthenticated, "unauthenticated"))?;
+        let registration_id = validate_registration_edit_token(&self.km, token).await?;
+
+        self.registration(&registration_id).await
+    }
+
+    async fn registration(&self, registration_id: &str) -> Result<Registration, Status> {
+        self.registration_store
+            .query(Some(&registration_store::Query::Id(
+                registration_store::IdQuery::Equals(registration_id.to_owned()),
+            )))
+            .await
+            .map_err(|e| -> Status { e.into() })?
+            .pop()
+            .ok_or_else(|| {
+                Status::new(
+                    Code::NotFound,
+                    format!("registration {} not found", registration_id),
+                )
+            })
+    }
+
+    // A draft is found by its draft token, which is an edit token for it, or else by the signed
+    // in user. The token has to be for a draft of the same event.
+    async fn draft(
+        &self,
+        metadata: &MetadataMap,
+        event_id: &str,
+        user_id: &str,
+    ) -> Result<Option<Registration>, Status> {
+        if token_from_header(metadata, REGISTRATION_EDIT_TOKEN_HEADER).is_some() {
+            let registration = self.authorized_registration(metadata).await?;
+            if registration.event_id != event_id {
+                return Err(Status::new(
+                    Code::PermissionDenied,
+                    format!(
+                        "registration {} is not for event {}",
+                        registration.id, event_id
+                    ),
+                ));
+            }
+
+            if registration.status != registration::Status::Draft as i32 {
+                return Err(Status::new(
+                    Code::FailedPrecondition,
+                    format!("registration {} is not a draft", registration.id),
+                ));
+            }
+
+            return Ok(Some(registration));
+        }
+
+        if user_id.is_empty() {
+            return Ok(None);
+        }
+
+        let draft_id = self
+            .registration_store
+            .find_draft(&EventId::from(event_id), &UserId::from(user_id))
+            .await
+            .map_err(|e| -> Status { e.into() })?;
+
+        match draft_id {
+            Some(draft_id) => self.registration(&draft_id).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    // The event's schema in registrant order.
+    async fn schema(&self, event_id: &str) -> Result<RegistrationSchema, Status> {
+        let mut schema = self
+            .schema_store
+            .query(Some(&registration_schema::Query::EventId(
+                registration_schema::EventIdQuery::Equals(event_id.to_owned()),
+            )))
+            .await
+            .map_err(|e| -> Status { e.into() })?
+            .pop()
+            .unwrap_or_else(|| RegistrationSchema {
+                event_id: event_id.to_owned(),
+                items: Vec::new(),
+            });
+        registration_schema::sort_for_display(&mut schema.items);
+
+        Ok(schema)
+    }
+
+    // The registration is already saved by now, so problems are only reported back as a
+    // warning.
+    async fn send_confirmation(
+        &self,
+        to: &str,
+        registration: &Registration,
+        schema: &RegistrationSchema,
+    ) -> Option<String> {
+        let event = self
+            .event_store
+            .query(Some(&event::Query::Id(event::IdQuery::Equals(
+                registration.event_id.clone(),
+            ))))
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|mut events| events.pop().ok_or_else(|| "event not found".to_owned()));
+
+        let result = match event {
+            Ok(event) => self
+                .notifier
+                .send_confirmation(to, registration, &event, schema)
+                .await
+                .map_err(|e| e.to_string()),
+            Err(e) => Err(e),
+        };
+
+        let e = result.err()?;
+        log::warn!(
+            "confirmation email for registration {} failed: {}",
+            registration.id,
+            e
+        );
+
+        Some("the confirmation email could not be sent".to_owned())
+    }
+
+    // What to tell the registrant once a registration has been submitted.
+    async fn submitted(
+        &self,
+        registration: Option<&Registration>,
+        confirmation_email: &str,
+        schema: &RegistrationSchema,
+    ) -> (Vec<String>, Option<DuplicateWarning>) {
+        let Some(registration) = registration else {
+            return (Vec::new(), None);
+        };
+
+        let mut warnings = Vec::new();
+        if !confirmation_email.is_empty() {
+            warnings.extend(
+                self.send_confirmation(confirmation_email, registration, schema)
+                    .await,
+            );
+        }
+
+        (warnings, self.duplicate_warning(registration).await)
+    }
+
+    // Like the confirmation, the lookup happens after the registration is saved, so a failure is
+    // only logged.
+    async fn duplicate_warning(&self, registration: &Registration) -> Option<DuplicateWarning> {
+        match self.registration_store.find_duplicate(registration).await {
+            Ok(existing_registration_id) => {
+                existing_registration_id.map(|existing_registration_id| DuplicateWarning {
+                    existing_registration_id,
+                })
+            }
+            Err(e) => {
+                log::warn!(
+                    "duplicate check for registration {} failed: {}",
+                    registration.id,
+                    e
+                );
+                None
+            }
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl<
+        KStore: KeyStore,
+        PStore: PermissionStore,
+        EStore: EventStore,
+        SStore: SchemaStore,
+        RStore: RegistrationStore,
+        N: Notifier,
+    > proto::public_registration_service_server::PublicRegistrationService
+    for Service<KStore, PStore, EStore, SStore, RStore, N>
+{
+    async fn create_public_link(
+        &self,
+        request: Request<CreatePublicLinkRequest>,
+    ) -> Result<Response<CreatePublicLinkResponse>, Status> {
+        let (metadata, _, request) = request.into_parts();
+        if request.event_id.is_empty() {
+            return Err(ValidationError::new_empty("event_id").into());
+        }
+
+        let ttl = link_ttl(request.ttl_seconds)?;
+
+        // Anyone holding the link can register, so only those who can edit the event's
+        // registrations may hand one out.
+        require_event_role(
+            &self.km,
+            &*self.permission_store,
+            &metadata,
+            &EventId::from(&request.event_id),
+            EventRole::Editor,
+        )
+        .await?;
+
+        let token = create_public_registration_token(&self.km, &request.event_id, ttl).await?;
+
+        Ok(Response::new(CreatePublicLinkResponse { token }))
+    }
+
+    async fn get_public_registration_schema(
+        &self,
+        request: Request<GetPublicRegistrationSchemaRequest>,
+    ) -> Result<Response<GetPublicRegistrationSchemaResponse>, Status> {
+        let event_id = self.authorized_event_id(request.metadata()).await?;
+
+        let registration_schema = self.schema(&event_id).await?;
+
+        Ok(Response::new(GetPublicRegistrationSchemaResponse {
+            registration_schema: Some(registration_schema),
+        }))
+    }
+
+    async fn create_public_registration(
+        &self,
+        request: Request<CreatePublicRegistrationRequest>,
+    ) -> Result<Response<CreatePublicRegistrationResponse>, Status> {
+        let event_id = self.authorized_event_id(request.metadata()).await?;
+        let user_id = request_user_id(&self.km, request.metadata())
+            .await
+            .map_err(|e| -> Status { e.into() })?
+            .unwrap_or_default();
+        let CreatePublicRegistrationRequest {
+            items,
+            confirmation_email,
+        } = request.into_inner();
+
+        let schema = self.schema(&event_id).await?;
+        validate_items(&schema, &items, chrono::Utc::now()).map_err(|e| -> Status { e.into() })?;
+
+        let registration = self
+            .registration_store
+            .upsert(
+                vec![Registration {
+                    id: "".to_owned(),
+                    event_id,
+                    items,
+                    status: registration::Status::Confirmed as i32,
+                    user_id,
+                }],
+                Editor::Registrant,
+            )
+            .await
+            .map_err(|e| -> Status { e.into() })?
+            .pop();
+
+        let (warnings, duplicate_warning) = self
+            .submitted(registration.as_ref(), &confirmation_email, &schema)
+            .await;
+
+        Ok(Response::new(CreatePublicRegistrationResponse {
+            registration,
+            warnings,
+            duplicate_warning,
+        }))
+    }
+
+    async fn request_edit_link(
+        &self,
+        request: Request<RequestEditLinkRequest>,
+    ) -> Result<Response<RequestEditLinkResponse>, Status> {
+        validate_request(&self.km, request.metadata())
+            .await
+            .map_err(|e| -> Status { e.into() })?;
+
+        let request = request.into_inner();
+        if request.registration_id.is_empty() {
+            return Err(ValidationError::new_empty("registration_id").into());
+        }
+
+        let ttl = link_ttl(request.ttl_seconds)?;
+
+        self.registration(&request.registration_id).await?;
+
+        let token = create_registration_edit_token(&self.km, &request.registration_id, ttl).await?;
+
+        Ok(Response::new(RequestEditLinkResponse { token }))
+    }
+
+    async fn get_editable_registration(
+        &self,
+        request: Request<GetEditableRegistrationRequest>,
+    ) -> Result<Response<GetEditableRegistrationResponse>, Status> {
+        let registration = self.authorized_registration(request.metadata()).await?;
+        let registration_schema = self.schema(&registration.event_id).await?;
+
+        Ok(Response::new(GetEditableRegistrationResponse {
+            registration: Some(registration),
+            registration_schema: Some(registration_schema),
+        }))
+    }
+
+    async fn update_editable_registration(
+        &self,
+        request: Request<UpdateEditableRegistrationRequest>,
+    ) -> Result<Response<UpdateEditableRegistrationResponse>, Status> {
+        let registration = self.authorized_registration(request.metadata()).await?;
+        let items = request.into_inner().items;
+
+        let schema = self.schema(&registration.event_id).await?;
+        validate_items(&schema, &items, chrono::Utc::now()).map_err(|e| -> Status { e.into() })?;
+
+        let registration = self
+            .registration_store
+            .upsert(
+                vec![Registration {
+                    items,
+                    ..registration
+                }],
+                Editor::Registrant,
+            )
+            .await
+            .map_err(|e| -> Status { e.into() })?
+            .pop();
+
+        Ok(Response::new(UpdateEditableRegistrationResponse {
+            registration,
+        }))
+    }
+
+    async fn validate_field(
+        &self,
+        request: Request<ValidateFieldRequest>,
+    ) -> Result<Response<ValidateFieldResponse>, Status> {
+        let event_id = self.authorized_event_id(request.metadata()).await?;
+        let request = request.into_inner();
+        if request.schema_item_id.is_empty() {
+            return Err(ValidationError::new_empty("schema_item_id").into());
+        }
+
+        let schema = self.schema(&event_id).await?;
+        let response = match validate_value(
+            &schema,
+            &request.schema_item_id,
+            &request.value,
+            chrono::Utc::now(),
+        ) {
+            Ok(()) => ValidateFieldResponse {
+                valid: true,
+                reason: "".to_owned(),
+            },
+            Err(reason) => ValidateFieldResponse {
+                valid: false,
+                reason: reason.to_string(),
+            },
+        };
+
+        Ok(Response::new(response))
+    }
+
+    async fn save_draft_registration(
+        &self,
+        request: Request<SaveDraftRegistrationRequest>,
+    ) -> Result<Response<SaveDraftRegistrationResponse>, Status> {
+        let event_id = self.authorized_event_id(request.metadata()).await?;
+        let user_id = request_user_id(&self.km, request.metadata())
+            .await
+            .map_err(|e| -> Status { e.into() })?
+            .unwrap_or_default();
+        let draft = self.draft(request.metadata(), &event_id, &user_id).await?;
+        let items = request.into_inner().items;
+
+        let schema = self.schema(&event_id).await?;
+        validate_draft_items(&schema, &items, chrono::Utc::now())
+            .map_err(|e| -> Status { e.into() })?;
+
+        let draft = draft.unwrap_or_else(|| Registration {
+            id: "".to_owned(),
+            event_id,
+            items: Vec::new(),
+            status: registration::Status::Draft as i32,
+            user_id,
+        });
+
+        let registration = self
+            .registration_store
+            .upsert(vec![Registration { items, ..draft }], Editor::Registrant)
+            .await
+            .map_err(|e| -> Status { e.into() })?
+            .pop()
+            .ok_or_else(|| Status::new(Code::Internal, "draft was not saved"))?;
+
+        let draft_token = create_registration_edit_token(
+            &self.km,
+            &registration.id,
+            chrono::Duration::seconds(DRAFT_TOKEN_TTL_SECONDS),
+        )
+        .await?;
+
+        Ok(Response::new(SaveDraftRegistrationResponse {
+            registration: Some(registration),
+            draft_token,
+        }))
+    }
+
+    async fn get_draft_registration(
+        &self,
+        request: Request<GetDraftRegistrationRequest>,
+    ) -> Result<Response<GetDraftRegistrationResponse>, Status> {
+        let event_id = self.authorized_event_id(request.metadata()).await?;
+        let user_id = request_user_id(&self.km, request.metadata())
+            .await
+            .map_err(|e| -> Status { e.into() })?
+            .unwrap_or_default();
+
+        let registration = self.draft(request.metadata(), &event_id, &user_id).await?;
+
+        Ok(Response::new(GetDraftRegistrationResponse { registration }))
+    }
+
+    async fn submit_draft_registration(
+        &self,
+        request: Request<SubmitDraftRegistrationRequest>,
+    ) -> Result<Response<SubmitDraftRegistrationResponse>, Status> {
+        let event_id = self.authorized_event_id(request.metadata()).await?;
+        let user_id = request_user_id(&self.km, request.metadata())
+            .await
+            .map_err(|e| -> Status { e.into() })?
+            .unwrap_or_default();
+        let draft = self
+            .draft(request.metadata(), &event_id, &user_id)
+            .await?
+            .ok_or_else(|| Status::new(Code::NotFound, "no draft registration to submit"))?;
+        let SubmitDraftRegistrationRequest {
+            items,
+            confirmation_email,
+        } = request.into_inner();
+
+        let schema = self.schema(&event_id).await?;
+        validate_items(&schema, &items, chrono::Utc::now()).map_err(|e| -> Status { e.into() })?;
+
+        let registration = self
+            .registration_store
+            .upsert(
+                vec![Registration {
+                    items,
+                    status: registration::Status::Confirmed as i32,
+                    ..draft
+                }],
+                Editor::Registrant,
+            )
+            .await
+            .map_err(|e| -> Status { e.into() })?
+            .pop();
+
+        let (warnings, duplicate_warning) = self
+            .submitted(registration.as_ref(), &confirmation_email, &schema)
+            .await;
+
+        Ok(Response::new(SubmitDraftRegistrationResponse {
+            registration,
+            warnings,
+            duplicate_warning,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    };
+
+    use common::proto::{
+        public_registration_service_server::PublicRegistrationService, registration,
+        CreatePublicLinkRequest, CreatePublicRegistrationRequest, CreatePublicRegistrationResponse,
+        DuplicateWarning, Event, GetDraftRegistrationRequest, GetPublicRegistrationSchemaRequest,
+        Registration, RegistrationFieldError, RegistrationItem, RegistrationSchema,
+        RegistrationValidationErrors, SaveDraftRegistrationRequest, SubmitDraftRegistrationRequest,
+        UpdateEditableRegistrationRequest, ValidateFieldRequest,
+    };
+    use http::header::COOKIE;
+    use prost::Message as _;
+    use sqlx::{migrate::MigrateDatabase, Sqlite, SqlitePool};
+    use test_case::test_case;
+    use tonic::{metadata::MetadataMap, Code, Request, Status};
+    use uuid::Uuid;
+
+    use crate::{
+        api::authentication::{
+            create_access_token, create_public_registration_token, create_registration_edit_token,
+            validate_public_registration_token, validate_request,
+        },
+        keys::KeyManager,
+        notifier::{self, Notifier},
+        store::{
+            event::SqliteStore as EventStore,
+            keys::SqliteStore as KeyStore,
+            permission::SqliteStore as PermissionStore,
+            registration::{Editor, SqliteStore as RegistrationStore, Store as _},
+            registration_schema::{SqliteStore as SchemaStore, Store as _},
+        },
+    };
+
+    use super::{Service, PUBLIC_REGISTRATION_TOKEN_HEADER, REGISTRATION_EDIT_TOKEN_HEADER};
+
+    // Records each confirmation, along with whether the registration had already been committed
+    // when it was sent.
+    struct RecordingNotifier {
+        db: Arc<SqlitePool>,
+        fail: AtomicBool,
+        sent: Mutex<Vec<(String, bool)>>,
+    }
+
+    #[tonic::async_trait]
+    impl Notifier for RecordingNotifier {
+        async fn send_confirmation(
+            &self,
+            to: &str,
+            registration: &Registration,
+            _event: &Event,
+            _schema: &RegistrationSchema,
+        ) -> Result<(), notifier::Error> {
+            let (count,): (i64,) =
+                sqlx::query_as("SELECT COUNT(*) FROM registrations WHERE id = ?")
+                    .bind(&registration.id)
+                    .fetch_one(&*self.db)
+                    .await
+                    .unwrap();
+            self.sent.lock().unwrap().push((to.to_owned(), count == 1));
+
+            if self.fail.load(Ordering::SeqCst) {
+                return Err(notifier::Error::InvalidAddress(to.to_owned()));
+            }
+
+            Ok(())
+        }
+    }
+
+    struct Init {
+        event: String,
+        schema_item: String,
+        db: Arc<SqlitePool>,
+        km: Arc<KeyManager<KeyStore>>,
+        notifier: Arc<RecordingNotifier>,
+        service: Service<
+            KeyStore,
+            PermissionStore,
+            EventStore,
+            SchemaStore,
+            RegistrationStore,
+            RecordingNotifier,
+        >,
+    }
+
+    async fn init() -> Init {
+        let db_url = "sqlite://:memory:";
+        Sqlite::create_database(db_url).await.unwrap();
+
+        let db = SqlitePool::connect(db_url).await.unwrap();
+        sqlx::migrate!("../migrations").run(&db).await.unwrap();
+
+        let org = Uuid::now_v7().to_string();
+        sqlx::query("INSERT INTO organizations(id, name) VALUES (?, ?);")
+            .bind(&org)
+            .bind("Organization 1")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let event = Uuid::now_v7().to_string();
+        sqlx::query("INSERT INTO events(id, organization, name) VALUES (?, ?, ?);")
+            .bind(&event)
+            .bind(&org)
+            .bind("Event 1")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let schema_item = Uuid::now_v7().to_string();
+        sqlx::query(
+            "INSERT INTO registration_schema_items(id, event, idx, name, item_type, checkbox_type_default) VALUES (?, ?, ?, ?, ?, ?);",
+        )
+        .bind(&schema_item)
+        .bind(&event)
+        .bind(0)
+        .bind("Housing")
+        .bind("CheckboxType")
+        .bind(0)
+        .execute(&db)
+        .await
+        .unwrap();
+
+        let db = Arc::new(db);
+        let km = Arc::new(KeyManager::new(Arc::new(KeyStore::new(db.clone()))));
+        km.rotate_key(false).await.unwrap();
+
+        let notifier = Arc::new(RecordingNotifier {
+            db: db.clone(),
+            fail: AtomicBool::new(false),
+            sent: Mutex::new(Vec::new()),
+        });
+
+        let service = Service::new(
+            km.clone(),
+            Arc::new(PermissionStore::new(db.clone())),
+            Arc::new(EventStore::new(db.clone())),
+            Arc::new(SchemaStore::new(db.clone())),
+            Arc::new(RegistrationStore::new(db.clone())),
+            notifier.clone(),
+        );
+
+        Init {
+            event,
+            schema_item,
+            db,
+            km,
+            notifier,
+            service,
+        }
+    }
+
+    fn registration_request(
+        token: &str,
+        schema_item: &str,
+    ) -> Request<CreatePublicRegistrationRequest> {
+        let mut request = Request::new(CreatePublicRegistrationRequest {
+            items: vec![RegistrationItem {
+                schema_item_id: schema_item.to_owned(),
+                value: "true".to_owned(),
+                instance: 0,
+            }],
+            confirmation_email: "".to_owned(),
+        });
+        request
+            .metadata_mut()
+            .insert(PUBLIC_REGISTRATION_TOKEN_HEADER, token.parse().unwrap());
+
+        request
+    }
+
+    #[tokio::test]
+    async fn valid_token_registers() {
+        let init = init().await;
+        let token =
+            create_public_registration_token(&init.km, &init.event, chrono::Duration::hours(1))
+                .await
+                .unwrap();
+
+        let registration = init
+            .service
+            .create_public_registration(registration_request(&token, &init.schema_item))
+            .await
+            .unwrap()
+            .into_inner()
+            .registration
+            .unwrap();
+
+        assert_eq!(registration.event_id, init.event);
+        assert_eq!(registration.items[0].schema_item_id, init.schema_item);
+    }
+
+    #[tokio::test]
+    async fn expired_token_rejected() {
+        let init = init().await;
+        let token =
+            create_public_registration_token(&init.km, &init.event, chrono::Duration::hours(-1))
+                .await
+                .unwrap();
+
+        let status = init
+            .service
+            .create_public_registration(registration_request(&token, &init.schema_item))
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), Code::Unauthenticated);
+    }
+
+    #[test_case(None, Some(Code::Unauthenticated) ; "anonymous")]
+    #[test_case(Some("EVENT_VIEWER"), Some(Code::PermissionDenied) ; "event viewer")]
+    #[test_case(Some("EVENT_EDITOR"), None ; "event editor")]
+    #[test_case(Some("SERVER_ADMIN"), None ; "server admin")]
+    #[tokio::test]
+    async fn create_public_link_requires_editor(role: Option<&str>, expected: Option<Code>) {
+        let init = init().await;
+
+        let user_id = Uuid::now_v7().to_string();
+        sqlx::query("INSERT INTO users(id, email, password, display_name) VALUES (?, ?, ?, ?);")
+            .bind(&user_id)
+            .bind("user@example.com")
+            .bind("")
+            .bind("User")
+            .execute(&*init.db)
+            .await
+            .unwrap();
+
+        let mut request = Request::new(CreatePublicLinkRequest {
+            event_id: init.event.clone(),
+            ttl_seconds: 60 * 60,
+        });
+        if let Some(role) = role {
+            let event = (role != "SERVER_ADMIN").then_some(&init.event);
+            sqlx::query("INSERT INTO permissions(id, user, role, event) VALUES (?, ?, ?, ?);")
+                .bind(Uuid::now_v7().to_string())
+                .bind(&user_id)
+                .bind(role)
+                .bind(event)
+                .execute(&*init.db)
+                .await
+                .unwrap();
+
+            let access_token = create_access_token(&init.km, &user_id, chrono::Duration::hours(1))
+                .await
+                .unwrap();
+            request.metadata_mut().insert(
+                COOKIE.as_str(),
+                format!("authorization={}", access_token).parse().unwrap(),
+            );
+        }
+
+        let result = init.service.create_public_link(request).await;
+
+        match expected {
+            Some(code) => assert_eq!(result.unwrap_err().code(), code),
+            None => {
+                let token = result.unwrap().into_inner().token;
+                assert_eq!(
+                    validate_public_registration_token(&init.km, &token)
+                        .await
+                        .unwrap(),
+                    init.event
+                );
+            }
+        }
+    }
+
+    #[test_case(false, false, None ; "open event anonymous")]
+    #[test_case(true, false, Some(Code::Unauthenticated) ; "require account anonymous")]
+    #[test_case(true, true, None ; "require account signed in")]
+    #[tokio::test]
+    async fn require_account(require_account: bool, signed_in: bool, expected: Option<Code>) {
+        let init = init().await;
+
+        sqlx::query("UPDATE events SET require_account = ? WHERE id = ?")
+            .bind(require_account)
+            .bind(&init.event)
+            .execute(&*init.db)
+            .await
+            .unwrap();
+
+        let user_id = Uuid::now_v7().to_string();
+        sqlx::query("INSERT INTO users(id, email, password, display_name) VALUES (?, ?, ?, ?);")
+            .bind(&user_id)
+            .bind("user@example.com")
+            .bind("")
+            .bind("User")
+            .execute(&*init.db)
+            .await
+            .unwrap();
+
+        let token =
+            create_public_registration_token(&init.km, &init.event, chrono::Duration::hours(1))
+                .await
+                .unwrap();
+        let mut request = registration_request(&token, &init.schema_item);
+        if signed_in {
+            let access_token = create_access_token(&init.km, &user_id, chrono::Duration::hours(1))
+                .await
+                .unwrap();
+            request.metadata_mut().insert(
+                COOKIE.as_str(),
+                format!("authorization={}", access_token).parse().unwrap(),
+            );
+        }
+
+        let result = init.service.create_public_registration(request).await;
+
+        match expected {
+            Some(code) => assert_eq!(result.unwrap_err().code(), code),
+            None => {
+                let registration = result.unwrap().into_inner().registration.unwrap();
+                let expected_user = if signed_in { user_id } else { String::new() };
+                assert_eq!(registration.user_id, expected_user);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn schema_in_display_order() {
+        let init = init().await;
+        for (idx, name) in [(1, "Name"), (2, "Email")] {
+            sqlx::query(
+                "INSERT INTO registration_schema_items(id, event, idx, name, item_type, text_type_default, text_type_display) VALUES (?, ?, ?, ?, ?, ?, ?);",
+            )
+            .bind(Uuid::now_v7().to_string())
+            .bind(&init.event)
+            .bind(idx)
+            .bind(name)
+            .bind("TextType")
+            .bind("")
+            .bind("SMALL")
+            .execute(&*init.db)
+            .await
+            .unwrap();
+        }
+        sqlx::query("UPDATE registration_schema_items SET display_idx = ? WHERE id = ?")
+            .bind(2)
+            .bind(&init.schema_item)
+            .execute(&*init.db)
+            .await
+            .unwrap();
+
+        let token =
+            create_public_registration_token(&init.km, &init.event, chrono::Duration::hours(1))
+                .await
+                .unwrap();
+        let mut request = Request::new(GetPublicRegistrationSchemaRequest {});
+        request
+            .metadata_mut()
+            .insert(PUBLIC_REGISTRATION_TOKEN_HEADER, token.parse().unwrap());
+
+        let schema = init
+            .service
+            .get_public_registration_schema(request)
+            .await
+            .unwrap()
+            .into_inner()
+            .registration_schema
+            .unwrap();
+        let names = |schema: &RegistrationSchema| {
+            schema
+                .items
+                .iter()
+                .map(|item| item.name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&schema), vec!["Name", "Housing", "Email"]);
+
+        // The admin view keeps the stored order.
+        let stored = SchemaStore::new(init.db.clone())
+            .query(None)
+            .await
+            .unwrap()
+            .pop()
+            .unwrap();
+        assert_eq!(names(&stored), vec!["Housing", "Name", "Email"]);
+        assert_eq!(stored.items[0].display_idx, Some(2));
+    }
+
+    #[test_case("sam@example.com", false, Ok(false) ; "different value")]
+    #[test_case("alex@example.com", false, Ok(true) ; "matching value")]
+    #[test_case("ALEX@example.com", false, Ok(true) ; "matching value ignoring case")]
+    #[test_case("", false, Ok(false) ; "blank value")]
+    #[test_case("sam@example.com", true, Ok(false) ; "blocking different value")]
+    #[test_case("alex@example.com", true, Err(Code::AlreadyExists) ; "blocking matching value")]
+    #[tokio::test]
+    async fn duplicate_registration(email: &str, block: bool, expected: Result<bool, Code>) {
+        let init = init().await;
+        let email_item = Uuid::now_v7().to_string();
+        sqlx::query(
+            "INSERT INTO registration_schema_items(id, event, idx, name, item_type, text_type_default, text_type_display) VALUES (?, ?, ?, ?, ?, ?, ?);",
+        )
+        .bind(&email_item)
+        .bind(&init.event)
+        .bind(1)
+        .bind("Email")
+        .bind("TextType")
+        .bind("")
+        .bind("SMALL")
+        .execute(&*init.db)
+        .await
+        .unwrap();
+        sqlx::query("UPDATE events SET dedup_item = ?, block_duplicates = ? WHERE id = ?")
+            .bind(&email_item)
+            .bind(block)
+            .bind(&init.event)
+            .execute(&*init.db)
+            .await
+            .unwrap();
+
+        let token =
+            create_public_registration_token(&init.km, &init.event, chrono::Duration::hours(1))
+                .await
+                .unwrap();
+        let request = |email: &str| {
+            let mut request = registration_request(&token, &init.schema_item);
+            request.get_mut().items.push(RegistrationItem {
+                schema_item_id: email_item.clone(),
+                value: email.to_owned(),
+                instance: 0,
+            });
+            request
+        };
+
+        let existing = init
+            .service
+            .create_public_registration(request("alex@example.com"))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(existing.duplicate_warning, None);
+        let existing_id = existing.registration.unwrap().id;
+
+        let result = init
+            .service
+            .create_public_registration(request(email))
+            .await;
+
+        match expected {
+            Ok(warned) => {
+                let expected_warning = warned.then_some(DuplicateWarning {
+                    existing_registration_id: existing_id,
+                });
+                assert_eq!(
+                    result.unwrap().into_inner().duplicate_warning,
+                    expected_warning
+                );
+            }
+            Err(code) => {
+                let status = result.unwrap_err();
+                assert_eq!(status.code(), code);
+                assert!(status.message().contains(&existing_id));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn token_grants_no_other_access() {
+        let init = init().await;
+        let token =
+            create_public_registration_token(&init.km, &init.event, chrono::Duration::hours(1))
+                .await
+                .unwrap();
+
+        // Not usable as an access token.
+        let mut metadata = MetadataMap::new();
+        metadata.insert(
+            COOKIE.as_str(),
+            format!("authorization={}", token).parse().unwrap(),
+        );
+        assert!(validate_request(&init.km, &metadata).await.is_err());
+
+        // Not usable for schema items outside the token's event.
+        let status = init
+            .service
+            .create_public_registration(registration_request(&token, &Uuid::now_v7().to_string()))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+    }
+
+    enum FieldItem {
+        Select,
+        RequiredText,
+        Tags,
+        Unknown,
+    }
+
+    #[test_case(FieldItem::Select, "1", None ; "select index")]
+    #[test_case(FieldItem::Select, "Follow", None ; "select name")]
+    #[test_case(FieldItem::Select, "2", Some("names an option that doesn't exist") ; "select index out of range")]
+    #[test_case(FieldItem::Select, "banana", Some("names an option that doesn't exist") ; "select unknown name")]
+    #[test_case(FieldItem::RequiredText, "Sam", None ; "required filled in")]
+    #[test_case(FieldItem::RequiredText, " ", Some("is required") ; "required empty")]
+    #[test_case(FieldItem::Tags, r#"["Vegan","Nut allergy"]"#, None ; "tags")]
+    #[test_case(FieldItem::Tags, "", None ; "no tags")]
+    #[test_case(FieldItem::Tags, r#"["a","b","c"]"#, Some("has more tags than allowed") ; "too many tags")]
+    #[test_case(FieldItem::Tags, r#"["Vegan"," "]"#, Some("has a blank tag") ; "blank tag")]
+    #[test_case(FieldItem::Tags, "Vegan", Some("isn't a list of tags") ; "tags not a list")]
+    #[test_case(FieldItem::Unknown, "true", Some("is not in the registration schema") ; "unknown item")]
+    #[tokio::test]
+    async fn validate_field(item: FieldItem, value: &str, reason: Option<&str>) {
+        let init = init().await;
+
+        let select_item = Uuid::now_v7().to_string();
+        sqlx::query(
+            "INSERT INTO registration_schema_items(id, event, idx, name, item_type, select_type_default, select_type_display) VALUES (?, ?, ?, ?, ?, ?, ?);",
+        )
+        .bind(&select_item)
+        .bind(&init.event)
+        .bind(1)
+        .bind("Role")
+        .bind("SelectType")
+        .bind(0)
+        .bind("RADIO")
+        .execute(&*init.db)
+        .await
+        .unwrap();
+        for (idx, name) in ["Lead", "Follow"].into_iter().enumerate() {
+            sqlx::query(
+                "INSERT INTO registration_schema_select_options(id, schema_item, idx, name, product_id) VALUES (?, ?, ?, ?, ?);",
+            )
+            .bind(Uuid::now_v7().to_string())
+            .bind(&select_item)
+            .bind(idx as i32)
+            .bind(name)
+            .bind("")
+            .execute(&*init.db)
+            .await
+            .unwrap();
+        }
+
+        let text_item = Uuid::now_v7().to_string();
+        sqlx::query(
+            "INSERT INTO registration_schema_items(id, event, idx, name, item_type, text_type_default, text_type_display, required) VALUES (?, ?, ?, ?, ?, ?, ?, ?);",
+        )
+        .bind(&text_item)
+        .bind(&init.event)
+        .bind(2)
+        .bind("Name")
+        .bind("TextType")
+        .bind("")
+        .bind("SMALL")
+        .bind(true)
+        .execute(&*init.db)
+        .await
+        .unwrap();
+
+        let tags_item = Uuid::now_v7().to_string();
+        sqlx::query(
+            "INSERT INTO registration_schema_items(id, event, idx, name, item_type, tags_type_max_tags) VALUES (?, ?, ?, ?, ?, ?);",
+        )
+        .bind(&tags_item)
+        .bind(&init.event)
+        .bind(3)
+        .bind("Dietary needs")
+        .bind("TagsType")
+        .bind(2)
+        .execute(&*init.db)
+        .await
+        .unwrap();
+
+        let token =
+            create_public_registration_token(&init.km, &init.event, chrono::Duration::hours(1))
+                .await
+                .unwrap();
+        let schema_item_id = match item {
+            FieldItem::Select => select_item,
+            FieldItem::RequiredText => text_item,
+            FieldItem::Tags => tags_item,
+            FieldItem::Unknown => Uuid::now_v7().to_string(),
+        };
+
+        let mut request = Request::new(ValidateFieldRequest {
+            schema_item_id,
+            value: value.to_owned(),
+        });
+        request
+            .metadata_mut()
+            .insert(PUBLIC_REGISTRATION_TOKEN_HEADER, token.parse().unwrap());
+
+        let response = init
+            .service
+            .validate_field(request)
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(response.valid, reason.is_none());
+        assert_eq!(response.reason, reason.unwrap_or_default());
+    }
+
+    #[tokio::test]
+    async fn all_field_errors_returned() {
+        let init = init().await;
+        let required_item = Uuid::now_v7().to_string();
+        sqlx::query(
+            "INSERT INTO registration_schema_items(id, event, idx, name, item_type, checkbox_type_default, required) VALUES (?, ?, ?, ?, ?, ?, ?);",
+        )
+        .bind(&required_item)
+        .bind(&init.event)
+        .bind(1)
+        .bind("Waiver")
+        .bind("CheckboxType")
+        .bind(0)
+        .bind(true)
+        .execute(&*init.db)
+        .await
+        .unwrap();
+
+        let token =
+            create_public_registration_token(&init.km, &init.event, chrono::Duration::hours(1))
+                .await
+                .unwrap();
+        let unknown_item = Uuid::now_v7().to_string();
+
+        let mut request = registration_request(&token, &init.schema_item);
+        request.get_mut().items.extend([
+            RegistrationItem {
+                schema_item_id: init.schema_item.clone(),
+                value: "false".to_owned(),
+                instance: 0,
+            },
+            RegistrationItem {
+                schema_item_id: unknown_item.clone(),
+                value: "true".to_owned(),
+                instance: 0,
+            },
+        ]);
+
+        let status = init
+            .service
+            .create_public_registration(request)
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), Code::InvalidArgument);
+        let details = RegistrationValidationErrors::decode(status.details()).unwrap();
+        assert_eq!(
+            details.errors,
+            vec![
+                RegistrationFieldError {
+                    item_id: init.schema_item.clone(),
+                    reason: "is answered more than once".to_owned(),
+                    instance: 0,
+                },
+                RegistrationFieldError {
+                    item_id: unknown_item,
+                    reason: "is not in the registration schema".to_owned(),
+                    instance: 0,
+                },
+                RegistrationFieldError {
+                    item_id: required_item,
+                    reason: "is required".to_owned(),
+                    instance: 0,
+                },
+            ]
+        );
+    }
+
+    enum GroupTest {
+        EveryInstanceComplete,
+        SecondInstanceMissingRequired,
+        NoInstances,
+    }
+
+    #[test_case(GroupTest::EveryInstanceComplete ; "every instance complete")]
+    #[test_case(GroupTest::SecondInstanceMissingRequired ; "second instance missing required")]
+    #[test_case(GroupTest::NoInstances ; "no instances")]
+    #[tokio::test]
+    async fn group_instances_validated(test_name: GroupTest) {
+        let init = init().await;
+        let group = Uuid::now_v7().to_string();
+        sqlx::query(
+            "INSERT INTO registration_schema_items(id, event, idx, name, item_type, required) VALUES (?, ?, ?, ?, ?, ?);",
+        )
+        .bind(&group)
+        .bind(&init.event)
+        .bind(1)
+        .bind("Partners")
+        .bind("GroupType")
+        .bind(true)
+        .execute(&*init.db)
+        .await
+        .unwrap();
+
+        let name_item = Uuid::now_v7().to_string();
+        let lunch_item = Uuid::now_v7().to_string();
+        sqlx::query(
+            "INSERT INTO registration_schema_items(id, parent, idx, name, item_type, text_type_default, text_type_display, required) VALUES (?, ?, ?, ?, ?, ?, ?, ?);",
+        )
+        .bind(&name_item)
+        .bind(&group)
+        .bind(0)
+        .bind("Name")
+        .bind("TextType")
+        .bind("")
+        .bind("SMALL")
+        .bind(true)
+        .execute(&*init.db)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO registration_schema_items(id, parent, idx, name, item_type, checkbox_type_default) VALUES (?, ?, ?, ?, ?, ?);",
+        )
+        .bind(&lunch_item)
+        .bind(&group)
+        .bind(1)
+        .bind("Lunch")
+        .bind("CheckboxType")
+        .bind(0)
+        .execute(&*init.db)
+        .await
+        .unwrap();
+
+        let item = |schema_item_id: &str, value: &str, instance: u32| RegistrationItem {
+            schema_item_id: schema_item_id.to_owned(),
+            value: value.to_owned(),
+            instance,
+        };
+
+        let (group_items, expected_errors) = match test_name {
+            GroupTest::EveryInstanceComplete => (
+                vec![
+                    item(&name_item, "Alex", 0),
+                    item(&lunch_item, "true", 0),
+                    item(&name_item, "Sam", 1),
+                ],
+                Vec::new(),
+            ),
+            GroupTest::SecondInstanceMissingRequired => (
+                vec![
+                    item(&name_item, "Alex", 0),
+                    item(&name_item, "", 1),
+                    item(&lunch_item, "true", 1),
+                ],
+                vec![RegistrationFieldError {
+                    item_id: name_item.clone(),
+                    reason: "is required".to_owned(),
+                    instance: 1,
+                }],
+            ),
+            GroupTest::NoInstances => (
+                vec![item(&name_item, "", 0)],
+                vec![RegistrationFieldError {
+                    item_id: group.clone(),
+                    reason: "is required".to_owned(),
+                    instance: 0,
+                }],
+            ),
+        };
+
+        let token =
+            create_public_registration_token(&init.km, &init.event, chrono::Duration::hours(1))
+                .await
+                .unwrap();
+        let mut request = registration_request(&token, &init.schema_item);
+        request.get_mut().items.extend(group_items.clone());
+
+        let result = init.service.create_public_registration(request).await;
+
+        if expected_errors.is_empty() {
+            let registration = result.unwrap().into_inner().registration.unwrap();
+            let mut stored = registration
+                .items
+                .into_iter()
+                .filter(|item| item.schema_item_id != init.schema_item)
+                .collect::<Vec<_>>();
+            stored.sort_by(|l, r| {
+                (l.instance, &l.schema_item_id).cmp(&(r.instance, &r.schema_item_id))
+            });
+            let mut expected = group_items;
+            expected.sort_by(|l, r| {
+                (l.instance, &l.schema_item_id).cmp(&(r.instance, &r.schema_item_id))
+            });
+            assert_eq!(stored, expected);
+        } else {
+            let status = result.unwrap_err();
+            assert_eq!(status.code(), Code::InvalidArgument);
+            let details = RegistrationValidationErrors::decode(status.details()).unwrap();
+            assert_eq!(details.errors, expected_errors);
+        }
+    }
+
+    async fn register_with_confirmation(
+        init: &Init,
+    ) -> Result<CreatePublicRegistrationResponse, Status> {
+        let token =
+            create_public_registration_token(&init.km, &init.event, chrono::Duration::hours(1))
+                .await
+                .unwrap();
+
+        let mut request = registration_request(&token, &init.schema_item);
+        request.get_mut().confirmation_email = "registrant@example.com".to_owned();
+
+        init.service
+            .create_public_registration(request)
+            .await
+            .map(|response| response.into_inner())
+    }
+
+    #[tokio::test]
+    async fn confirmation_sent_after_commit() {
+        let init = init().await;
+
+        let response = register_with_confirmation(&init).await.unwrap();
+
+        assert!(response.registration.is_some());
+        assert!(response.warnings.is_empty());
+        assert_eq!(
+            *init.notifier.sent.lock().unwrap(),
+            vec![("registrant@example.com".to_owned(), true)]
+        );
+    }
+
+    #[tokio::test]
+    async fn confirmation_not_sent_without_email() {
+        let init = init().await;
+        let token =
+            create_public_registration_token(&init.km, &init.event, chrono::Duration::hours(1))
+                .await
+                .unwrap();
+
+        init.service
+            .create_public_registration(registration_request(&token, &init.schema_item))
+            .await
+            .unwrap();
+
+        assert!(init.notifier.sent.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn confirmation_not_sent_on_rollback() {
+        let init = init().await;
+        sqlx::query("UPDATE events SET registration_closes_at = ? WHERE id = ?")
+            .bind("2000-01-01T00:00:00Z")
+            .bind(&init.event)
+            .execute(&*init.db)
+            .await
+            .unwrap();
+
+        let status = register_with_confirmation(&init).await.unwrap_err();
+
+        assert_eq!(status.code(), Code::FailedPrecondition);
+        assert!(init.notifier.sent.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn confirmation_failure_is_warning() {
+        let init = init().await;
+        init.notifier.fail.store(true, Ordering::SeqCst);
+
+        let response = register_with_confirmation(&init).await.unwrap();
+
+        assert!(response.registration.is_some());
+        assert_eq!(response.warnings.len(), 1);
+        assert_eq!(init.notifier.sent.lock().unwrap().len(), 1);
+    }
+
+    async fn registration(init: &Init, value: &str) -> Registration {
+        init.service
+            .registration_store
+            .upsert(
+                vec![Registration {
+                    id: "".to_owned(),
+                    event_id: init.event.clone(),
+                    items: vec![RegistrationItem {
+                        schema_item_id: init.schema_item.clone(),
+                        value: value.to_owned(),
+                        instance: 0,
+                    }],
+                    status: registration::Status::Confirmed as i32,
+                    user_id: String::default(),
+                }],
+                Editor::Staff,
+            )
+            .await
+            .unwrap()
+            .remove(0)
+    }
+
+    fn edit_request(token: &str, schema_item: &str) -> Request<UpdateEditableRegistrationRequest> {
+        let mut request = Request::new(UpdateEditableRegistrationRequest {
+            items: vec![RegistrationItem {
+                schema_item_id: schema_item.to_owned(),
+                value: "false".to_owned(),
+                instance: 0,
+            }],
+        });
+        request
+            .metadata_mut()
+            .insert(REGISTRATION_EDIT_TOKEN_HEADER, token.parse().unwrap());
+
+        request
+    }
+
+    #[tokio::test]
+    async fn edit_within_validity() {
+        let init = init().await;
+        let original = registration(&init, "true").await;
+        let token =
+            create_registration_edit_token(&init.km, &original.id, chrono::Duration::hours(1))
+                .await
+                .unwrap();
+
+        let edited = init
+            .service
+            .update_editable_registration(edit_request(&token, &init.schema_item))
+            .await
+            .unwrap()
+            .into_inner()
+            .registration
+            .unwrap();
+
+        assert_eq!(edited.id, original.id);
+        assert_eq!(edited.event_id, original.event_id);
+        assert_eq!(edited.items[0].value, "false");
+    }
+
+    #[tokio::test]
+    async fn expired_edit_token_rejected() {
+        let init = init().await;
+        let original = registration(&init, "true").await;
+        let token =
+            create_registration_edit_token(&init.km, &original.id, chrono::Duration::hours(-1))
+                .await
+                .unwrap();
+
+        let status = init
+            .service
+            .update_editable_registration(edit_request(&token, &init.schema_item))
+            .await
+            .unwrap_err();
+
+        assert_eq!(status.code(), Code::Unauthenticated);
+    }
+
+    #[tokio::test]
+    async fn edit_token_scoped_to_registration() {
+        let init = init().await;
+        let original = registration(&init, "true").await;
+        let other = registration(&init, "true").await;
+        let token =
+            create_registration_edit_token(&init.km, &original.id, chrono::Duration::hours(1))
+                .await
+                .unwrap();
+
+        init.service
+            .update_editable_registration(edit_request(&token, &init.schema_item))
+            .await
+            .unwrap();
+
+        let stored = init.service.registration(&other.id).await.unwrap();
+        assert_eq!(stored, other);
+
+        // Not usable as a public registration token.
+        let status = init
+            .service
+            .create_public_registration(registration_request(&token, &init.schema_item))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+    }
+
+    // Adds a required text item to the event and returns its id.
+    async fn required_name_item(init: &Init) -> String {
+        let name_item = Uuid::now_v7().to_string();
+        sqlx::query(
+            "INSERT INTO registration_schema_items(id, event, idx, name, item_type, text_type_default, text_type_display, required) VALUES (?, ?, ?, ?, ?, ?, ?, ?);",
+        )
+        .bind(&name_item)
+        .bind(&init.event)
+        .bind(1)
+        .bind("Name")
+        .bind("TextType")
+        .bind("")
+        .bind("SMALL")
+        .bind(true)
+        .execute(&*init.db)
+        .await
+        .unwrap();
+
+        name_item
+    }
+
+    fn draft_request<T>(message: T, token: &str, draft_token: Option<&str>) -> Request<T> {
+        let mut request = Request::new(message);
+        request
+            .metadata_mut()
+            .insert(PUBLIC_REGISTRATION_TOKEN_HEADER, token.parse().unwrap());
+        if let Some(draft_token) = draft_token {
+            request
+                .metadata_mut()
+                .insert(REGISTRATION_EDIT_TOKEN_HEADER, draft_token.parse().unwrap());
+        }
+
+        request
+    }
+
+    fn signed_in<T>(access_token: &str, mut request: Request<T>) -> Request<T> {
+        request.metadata_mut().insert(
+            COOKIE.as_str(),
+            format!("authorization={}", access_token).parse().unwrap(),
+        );
+
+        request
+    }
+
+    #[tokio::test]
+    async fn draft_promoted_once_complete() {
+        let init = init().await;
+        let name_item = required_name_item(&init).await;
+        let token =
+            create_public_registration_token(&init.km, &init.event, chrono::Duration::hours(1))
+                .await
+                .unwrap();
+        let partial = vec![RegistrationItem {
+            schema_item_id: init.schema_item.clone(),
+            value: "true".to_owned(),
+            instance: 0,
+        }];
+
+        let saved = init
+            .service
+            .save_draft_registration(draft_request(
+                SaveDraftRegistrationRequest {
+                    items: partial.clone(),
+                },
+                &token,
+                None,
+            ))
+            .await
+            .unwrap()
+            .into_inner();
+        let draft = saved.registration.unwrap();
+        assert_eq!(draft.status, registration::Status::Draft as i32);
+
+        let status = init
+            .service
+            .submit_draft_registration(draft_request(
+                SubmitDraftRegistrationRequest {
+                    items: partial.clone(),
+                    confirmation_email: "".to_owned(),
+                },
+                &token,
+                Some(&saved.draft_token),
+            ))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        let details = RegistrationValidationErrors::decode(status.details()).unwrap();
+        assert_eq!(
+            details.errors,
+            vec![RegistrationFieldError {
+                item_id: name_item.clone(),
+                reason: "is required".to_owned(),
+                instance: 0,
+            }]
+        );
+        let stored = init.service.registration(&draft.id).await.unwrap();
+        assert_eq!(stored.status, registration::Status::Draft as i32);
+
+        let mut complete = partial;
+        complete.push(RegistrationItem {
+            schema_item_id: name_item,
+            value: "Sam".to_owned(),
+            instance: 0,
+        });
+        let submitted = init
+            .service
+            .submit_draft_registration(draft_request(
+                SubmitDraftRegistrationRequest {
+                    items: complete,
+                    confirmation_email: "".to_owned(),
+                },
+                &token,
+                Some(&saved.draft_token),
+            ))
+            .await
+            .unwrap()
+            .into_inner()
+            .registration
+            .unwrap();
+        assert_eq!(submitted.id, draft.id);
+        assert_eq!(submitted.status, registration::Status::Confirmed as i32);
+        assert_eq!(submitted.items.len(), 2);
+
+        // Once submitted, it's no longer a draft.
+        let status = init
+            .service
+            .save_draft_registration(draft_request(
+                SaveDraftRegistrationRequest { items: Vec::new() },
+                &token,
+                Some(&saved.draft_token),
+            ))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::FailedPrecondition);
+    }
+
+    #[tokio::test]
+    async fn draft_resumed_by_user() {
+        let init = init().await;
+        required_name_item(&init).await;
+
+        let user_id = Uuid::now_v7().to_string();
+        sqlx::query("INSERT INTO users(id, email, password, display_name) VALUES (?, ?, ?, ?);")
+            .bind(&user_id)
+            .bind("user@example.com")
+            .bind("")
+            .bind("User")
+            .execute(&*init.db)
+            .await
+            .unwrap();
+        let access_token = create_access_token(&init.km, &user_id, chrono::Duration::hours(1))
+            .await
+            .unwrap();
+        let token =
+            create_public_registration_token(&init.km, &init.event, chrono::Duration::hours(1))
+                .await
+                .unwrap();
+
+        let draft = init
+            .service
+            .save_draft_registration(signed_in(
+                &access_token,
+                draft_request(
+                    SaveDraftRegistrationRequest { items: Vec::new() },
+                    &token,
+                    None,
+                ),
+            ))
+            .await
+            .unwrap()
+            .into_inner()
+            .registration
+            .unwrap();
+        assert_eq!(draft.user_id, user_id);
+
+        // Saving again updates the same draft.
+        let resaved = init
+            .service
+            .save_draft_registration(signed_in(
+                &access_token,
+                draft_request(
+                    SaveDraftRegistrationRequest {
+                        items: vec![RegistrationItem {
+                            schema_item_id: init.schema_item.clone(),
+                            value: "true".to_owned(),
+                            instance: 0,
+                        }],
+                    },
+                    &token,
+                    None,
+                ),
+            ))
+            .await
+            .unwrap()
+            .into_inner()
+            .registration
+            .unwrap();
+        assert_eq!(resaved.id, draft.id);
+
+        let resumed = init
+            .service
+            .get_draft_registration(signed_in(
+                &access_token,
+                draft_request(GetDraftRegistrationRequest {}, &token, None),
+            ))
+            .await
+            .unwrap()
+            .into_inner()
+            .registration;
+        assert_eq!(resumed, Some(resaved));
+
+        // Anonymous registrants have nothing to resume without a draft token.
+        let anonymous = init
+            .service
+            .get_draft_registration(draft_request(GetDraftRegistrationRequest {}, &token, None))
+            .await
+            .unwrap()
+            .into_inner()
+            .registration;
+        assert_eq!(anonymous, None);
+    }
+}
@@ -1,26 +1,67 @@
 use std::sync::Arc;
 
 use common::proto::{
-    self, compound_registration_query, registration_query, DeleteRegistrationsRequest,
-    DeleteRegistrationsResponse, QueryRegistrationsRequest, QueryRegistrationsResponse,
-    Registration, RegistrationQuery, UpsertRegistrationsRequest, UpsertRegistrationsResponse,
+    self, compound_registration_query, registration, registration_query, CopyRegistrationRequest,
+    CopyRegistrationResponse, DeleteEventRegistrationsRequest, DeleteEventRegistrationsResponse,
+    DeleteRegistrationsRequest, DeleteRegistrationsResponse, ExportRegistrationsCsvRequest,
+    ExportRegistrationsCsvResponse, MoveRegistrationRequest, MoveRegistrationResponse,
+    QueryRegistrationsRequest, QueryRegistrationsResponse, Registration, RegistrationQuery,
+    RegistrationSchema, RenderRegistrationReceiptRequest, RenderRegistrationReceiptResponse,
+    ResolveRegistrationValuesRequest, ResolveRegistrationValuesResponse, ResolvedRegistrationValue,
+    SeedEventRequest, SeedEventResponse, SetRegistrationStatusesRequest,
+    SetRegistrationStatusesResponse, UpsertRegistrationsRequest, UpsertRegistrationsResponse,
 };
-use tonic::{Request, Response, Status};
+use tonic::{Code, Request, Response, Status};
 
-use crate::store::{
-    registration::{Query, Store},
-    CompoundOperator, CompoundQuery,
+use crate::{
+    csv_export::export_registrations_csv,
+    labels::resolve_values,
+    receipt::render_registration_receipt,
+    seed::generate_registrations,
+    store::{
+        event::{self, Store as EventStore},
+        ids::{EventId, RegistrationId},
+        registration::{
+            copy_registration, Editor, EventIdQuery, IdQuery, Query, StatusQuery, Store,
+        },
+        registration_schema::{self, Store as SchemaStore},
+        CompoundOperator, CompoundQuery, Pagination,
+    },
 };
 
 use super::{common::try_logical_string_query, ValidationError};
 
-pub struct Service<StoreType: Store> {
+const MAX_QUERY_LIMIT: u32 = 500;
+const MAX_SEED_COUNT: u32 = 10000;
+
+pub struct Service<StoreType: Store, EStore: EventStore, SStore: SchemaStore> {
     store: Arc<StoreType>,
+    event_store: Arc<EStore>,
+    schema_store: Arc<SStore>,
+    // Seeding writes made-up registrations, so it's only turned on for non-production servers.
+    allow_seeding: bool,
+    max_query_results: u32,
 }
 
-impl<StoreType: Store> Service<StoreType> {
-    pub fn new(store: Arc<StoreType>) -> Self {
-        Service { store }
+impl<StoreType: Store, EStore: EventStore, SStore: SchemaStore> Service<StoreType, EStore, SStore> {
+    pub fn new(
+        store: Arc<StoreType>,
+        event_store: Arc<EStore>,
+        schema_store: Arc<SStore>,
+        allow_seeding: bool,
+    ) -> Self {
+        Service {
+            store,
+            event_store,
+            schema_store,
+            allow_seeding,
+            max_query_results: super::DEFAULT_MAX_QUERY_RESULTS,
+        }
+    }
+
+    pub fn with_max_query_results(mut self, max_query_results: u32) -> Self {
+        self.max_query_results = max_query_results;
+        self
     }
 }
 
@@ -29,6 +70,10 @@ fn validate_registration(registration: &Registration) -> Result<(), ValidationEr
         return Err(ValidationError::new_empty("event_id"));
     }
 
+    if registration::Status::try_from(registration.status).is_err() {
+        return Err(ValidationError::new_invalid_enum("status"));
+    }
+
     Ok(())
 }
 
@@ -46,6 +91,15 @@ impl TryFrom<RegistrationQuery> for Query {
                 try_logical_string_query(id_query).map_err(|e| e.with_context("query.id"))?,
             )),
 
+            Some(registration_query::Query::Status(status)) => {
+                let status = registration::Status::try_from(status)
+                    .map_err(|_| ValidationError::new_invalid_enum("query.status"))?;
+
+                Ok(Query::Status(StatusQuery::Equals(
+                    status.as_str_name().to_owned(),
+                )))
+            }
+
             Some(registration_query::Query::Compound(compound_query)) => {
                 let operator = match compound_registration_query::Operator::try_from(
                     compound_query.operator,
@@ -76,8 +130,8 @@ impl TryFrom<RegistrationQuery> for Query {
 }
 
 #[tonic::async_trait]
-impl<StoreType: Store> proto::registration_service_server::RegistrationService
-    for Service<StoreType>
+impl<StoreType: Store, EStore: EventStore, SStore: SchemaStore>
+    proto::registration_service_server::RegistrationService for Service<StoreType, EStore, SStore>
 {
     async fn upsert_registrations(
         &self,
@@ -91,9 +145,10 @@ impl<StoreType: Store> proto::registration_service_server::RegistrationService
             })?;
         }
 
+        // This service backs the admin UI, so changes are made as staff.
         let registrations = self
             .store
-            .upsert(request_registrations)
+            .upsert(request_registrations, Editor::Staff)
             .await
             .map_err(|e| -> Status { e.into() })?;
 
@@ -104,17 +159,41 @@ impl<StoreType: Store> proto::registration_service_server::RegistrationService
         &self,
         request: Request<QueryRegistrationsRequest>,
     ) -> Result<Response<QueryRegistrationsResponse>, Status> {
-        let query = request.into_inner().query;
+        let request = request.into_inner();
 
-        let query = query.map(|query| query.try_into()).transpose()?;
+        let query = request.query.map(|query| query.try_into()).transpose()?;
 
-        let registrations = self
-            .store
-            .query(query.as_ref())
-            .await
-            .map_err(|e| -> Status { e.into() })?;
+        let (registrations, total_count) = match request.limit {
+            0 => self
+                .store
+                .query_page(
+                    query.as_ref(),
+                    Pagination {
+                        limit: self.max_query_results,
+                        offset: 0,
+                    },
+                )
+                .await
+                .map_err(|e| -> Status { e.into() })?,
+            limit => self
+                .store
+                .query_page(
+                    query.as_ref(),
+                    Pagination {
+                        limit,
+                        offset: request.offset,
+                    }
+                    .clamp(MAX_QUERY_LIMIT),
+                )
+                .await
+                .map_err(|e| -> Status { e.into() })?,
+        };
 
-        Ok(Response::new(QueryRegistrationsResponse { registrations }))
+        Ok(Response::new(QueryRegistrationsResponse {
+            truncated: request.limit == 0 && (registrations.len() as u64) < total_count,
+            registrations,
+            total_count,
+        }))
     }
 
     async fn delete_registrations(
@@ -128,4 +207,387 @@ impl<StoreType: Store> proto::registration_service_server::RegistrationService
 
         Ok(Response::new(DeleteRegistrationsResponse {}))
     }
+
+    async fn delete_event_registrations(
+        &self,
+        request: Request<DeleteEventRegistrationsRequest>,
+    ) -> Result<Response<DeleteEventRegistrationsResponse>, Status> {
+        let event_id = request.into_inner().event_id;
+        if event_id.is_empty() {
+            return Err(ValidationError::new_empty("event_id").into());
+        }
+
+        let deleted_count = self
+            .store
+            .delete_all_for_event(&EventId::from(&event_id))
+            .await
+            .map_err(|e| -> Status { e.into() })?;
+
+        Ok(Response::new(DeleteEventRegistrationsResponse {
+            deleted_count,
+        }))
+    }
+
+    async fn render_registration_receipt(
+        &self,
+        request: Request<RenderRegistrationReceiptRequest>,
+    ) -> Result<Response<RenderRegistrationReceiptResponse>, Status> {
+        let registration_id = request.into_inner().registration_id;
+        if registration_id.is_empty() {
+            return Err(ValidationError::new_empty("registration_id").into());
+        }
+
+        let registration = self
+            .store
+            .query(Some(&Query::Id(IdQuery::Equals(registration_id.clone()))))
+            .await
+            .map_err(|e| -> Status { e.into() })?
+            .pop()
+            .ok_or_else(|| {
+                Status::new(
+                    Code::NotFound,
+                    format!("registration {} not found", registration_id),
+                )
+            })?;
+
+        let event = self
+            .event_store
+            .query(Some(&event::Query::Id(event::IdQuery::Equals(
+                registration.event_id.clone(),
+            ))))
+            .await
+            .map_err(|e| -> Status { e.into() })?
+            .pop()
+            .ok_or_else(|| {
+                Status::new(
+                    Code::NotFound,
+                    format!("event {} not found", registration.event_id),
+                )
+            })?;
+
+        let schema = self
+            .schema_store
+            .query(Some(&registration_schema::Query::EventId(
+                registration_schema::EventIdQuery::Equals(registration.event_id.clone()),
+            )))
+            .await
+            .map_err(|e| -> Status { e.into() })?
+            .pop()
+            .unwrap_or_else(|| RegistrationSchema {
+                event_id: registration.event_id.clone(),
+                items: Vec::new(),
+            });
+
+        Ok(Response::new(RenderRegistrationReceiptResponse {
+            html: render_registration_receipt(&event, &schema, &registration),
+        }))
+    }
+
+    async fn export_registrations_csv(
+        &self,
+        request: Request<ExportRegistrationsCsvRequest>,
+    ) -> Result<Response<ExportRegistrationsCsvResponse>, Status> {
+        let event_id = request.into_inner().event_id;
+        if event_id.is_empty() {
+            return Err(ValidationError::new_empty("event_id").into());
+        }
+
+        let schema = self
+            .schema_store
+            .query(Some(&registration_schema::Query::EventId(
+                registration_schema::EventIdQuery::Equals(event_id.clone()),
+            )))
+            .await
+            .map_err(|e| -> Status { e.into() })?
+            .pop()
+            .unwrap_or_else(|| RegistrationSchema {
+                event_id: event_id.clone(),
+                items: Vec::new(),
+            });
+
+        // Drafts haven't been submitted, so they aren't exported.
+        let registrations = self
+            .store
+            .query(Some(&Query::Compound(CompoundQuery {
+                operator: CompoundOperator::And,
+                queries: vec![
+                    Query::EventId(EventIdQuery::Equals(event_id)),
+                    Query::Status(StatusQuery::NotEquals(
+                        registration::Status::Draft.as_str_name().to_owned(),
+                    )),
+                ],
+            })))
+            .await
+            .map_err(|e| -> Status { e.into() })?;
+
+        let csv = export_registrations_csv(&schema, &registrations)
+            .map_err(|e| Status::new(Code::Internal, e.to_string()))?;
+
+        Ok(Response::new(ExportRegistrationsCsvResponse { csv }))
+    }
+
+    async fn resolve_registration_values(
+        &self,
+        request: Request<ResolveRegistrationValuesRequest>,
+    ) -> Result<Response<ResolveRegistrationValuesResponse>, Status> {
+        let request = request.into_inner();
+        if request.event_id.is_empty() {
+            return Err(ValidationError::new_empty("event_id").into());
+        }
+
+        let schema = self
+            .schema_store
+            .query(Some(&registration_schema::Query::EventId(
+                registration_schema::EventIdQuery::Equals(request.event_id.clone()),
+            )))
+            .await
+            .map_err(|e| -> Status { e.into() })?
+            .pop()
+            .unwrap_or_else(|| RegistrationSchema {
+                event_id: request.event_id,
+                items: Vec::new(),
+            });
+
+        let values = resolve_values(
+            &schema,
+            request
+                .values
+                .into_iter()
+                .map(|item| (item.schema_item_id, item.value))
+                .collect(),
+        )
+        .into_iter()
+        .map(|resolved| ResolvedRegistrationValue {
+            schema_item_id: resolved.schema_item_id,
+            value: resolved.value,
+            label: resolved.label,
+            resolved: resolved.resolved,
+        })
+        .collect();
+
+        Ok(Response::new(ResolveRegistrationValuesResponse { values }))
+    }
+
+    async fn seed_event(
+        &self,
+        request: Request<SeedEventRequest>,
+    ) -> Result<Response<SeedEventResponse>, Status> {
+        if !self.allow_seeding {
+            return Err(Status::new(
+                Code::PermissionDenied,
+                "seeding is disabled on this server",
+            ));
+        }
+
+        let request = request.into_inner();
+        if request.event_id.is_empty() {
+            return Err(ValidationError::new_empty("event_id").into());
+        }
+
+        if request.count > MAX_SEED_COUNT {
+            return Err(ValidationError::new_invalid_value("count").into());
+        }
+
+        let schema = self
+            .schema_store
+            .query(Some(&registration_schema::Query::EventId(
+                registration_schema::EventIdQuery::Equals(request.event_id.clone()),
+            )))
+            .await
+            .map_err(|e| -> Status { e.into() })?
+            .pop()
+            .unwrap_or_else(|| RegistrationSchema {
+                event_id: request.event_id.clone(),
+                items: Vec::new(),
+            });
+
+        let registrations = generate_registrations(
+            &mut rand::thread_rng(),
+            &schema,
+            request.count as usize,
+            chrono::Utc::now(),
+        );
+
+        let created = self
+            .store
+            .upsert(registrations, Editor::Staff)
+            .await
+            .map_err(|e| -> Status { e.into() })?;
+
+        Ok(Response::new(SeedEventResponse {
+            created_count: created.len() as u32,
+        }))
+    }
+
+    async fn move_registration(
+        &self,
+        request: Request<MoveRegistrationRequest>,
+    ) -> Result<Response<MoveRegistrationResponse>, Status> {
+        let request = request.into_inner();
+        if request.registration_id.is_empty() {
+            return Err(ValidationError::new_empty("registration_id").into());
+        }
+
+        if request.target_event_id.is_empty() {
+            return Err(ValidationError::new_empty("target_event_id").into());
+        }
+
+        let registration = self
+            .store
+            .move_to_event(
+                &RegistrationId::from(request.registration_id),
+                &EventId::from(request.target_event_id),
+            )
+            .await
+            .map_err(|e| -> Status { e.into() })?;
+
+        Ok(Response::new(MoveRegistrationResponse {
+            registration: Some(registration),
+        }))
+    }
+
+    async fn copy_registration(
+        &self,
+        request: Request<CopyRegistrationRequest>,
+    ) -> Result<Response<CopyRegistrationResponse>, Status> {
+        let source_id = request.into_inner().source_id;
+        if source_id.is_empty() {
+            return Err(ValidationError::new_empty("source_id").into());
+        }
+
+        let source = self
+            .store
+            .query(Some(&Query::Id(IdQuery::Equals(source_id.clone()))))
+            .await
+            .map_err(|e| -> Status { e.into() })?
+            .pop()
+            .ok_or_else(|| {
+                Status::new(
+                    Code::NotFound,
+                    format!("registration {} not found", source_id),
+                )
+            })?;
+
+        let schema = self
+            .schema_store
+            .query(Some(&registration_schema::Query::EventId(
+                registration_schema::EventIdQuery::Equals(source.event_id.clone()),
+            )))
+            .await
+            .map_err(|e| -> Status { e.into() })?
+            .pop()
+            .unwrap_or_else(|| RegistrationSchema {
+                event_id: source.event_id.clone(),
+                items: Vec::new(),
+            });
+
+        Ok(Response::new(CopyRegistrationResponse {
+            registration: Some(copy_registration(&source, &schema)),
+        }))
+    }
+
+    async fn set_registration_statuses(
+        &self,
+        request: Request<SetRegistrationStatusesRequest>,
+    ) -> Result<Response<SetRegistrationStatusesResponse>, Status> {
+        let request = request.into_inner();
+        let status = registration::Status::try_from(request.status)
+            .map_err(|_| -> Status { ValidationError::new_invalid_enum("status").into() })?;
+
+        let registrations = self
+            .store
+            .set_status_many(&request.ids, status)
+            .await
+            .map_err(|e| -> Status { e.into() })?;
+
+        Ok(Response::new(SetRegistrationStatusesResponse {
+            registrations,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use sqlx::{migrate::MigrateDatabase, Sqlite, SqlitePool};
+    use tonic::Request;
+    use uuid::Uuid;
+
+    use common::proto::{
+        registration, registration_service_server::RegistrationService,
+        ExportRegistrationsCsvRequest, Registration,
+    };
+
+    use crate::store::{
+        event::SqliteStore as EventStore,
+        registration::{Editor, SqliteStore, Store as _},
+        registration_schema::SqliteStore as SchemaStore,
+    };
+
+    use super::Service;
+
+    #[tokio::test]
+    async fn export_leaves_out_drafts() {
+        let db_url = "sqlite://:memory:";
+        Sqlite::create_database(db_url).await.unwrap();
+
+        let db = SqlitePool::connect(db_url).await.unwrap();
+        sqlx::migrate!("../migrations").run(&db).await.unwrap();
+
+        let org = Uuid::now_v7().to_string();
+        sqlx::query("INSERT INTO organizations(id, name) VALUES (?, ?);")
+            .bind(&org)
+            .bind("Organization 1")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let event = Uuid::now_v7().to_string();
+        sqlx::query("INSERT INTO events(id, organization, name) VALUES (?, ?, ?);")
+            .bind(&event)
+            .bind(&org)
+            .bind("Event 1")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let db = Arc::new(db);
+        let store = Arc::new(SqliteStore::new(db.clone()));
+        let registrations = store
+            .upsert(
+                [registration::Status::Confirmed, registration::Status::Draft]
+                    .into_iter()
+                    .map(|status| Registration {
+                        event_id: event.clone(),
+                        status: status as i32,
+                        ..Default::default()
+                    })
+                    .collect(),
+                Editor::Staff,
+            )
+            .await
+            .unwrap();
+
+        let service = Service::new(
+            store,
+            Arc::new(EventStore::new(db.clone())),
+            Arc::new(SchemaStore::new(db)),
+            false,
+        );
+
+        let csv = service
+            .export_registrations_csv(Request::new(ExportRegistrationsCsvRequest {
+                event_id: event,
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .csv;
+
+        assert_eq!(
+            csv,
+            format!("id,status\n{},CONFIRMED\n", registrations[0].id)
+        );
+    }
 }
@@ -1,38 +1,268 @@
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{Arc, Mutex},
+};
 
-use tonic::{Request, Response, Status};
+use prost::Message;
+use tonic::{metadata::MetadataMap, Request, Response, Status};
 
-use crate::store::{
-    registration_schema::{Query, Store},
-    CompoundOperator, CompoundQuery,
+use crate::{
+    json_schema::export_json_schema,
+    keys::KeyManager,
+    store::{
+        ids::{ids, EventId, SchemaItemId, TemplateId, UserId},
+        keys::Store as KeyStore,
+        registration_schema::{EventIdQuery, Query, Store, DEFAULT_MAX_OPTIONS},
+        schema_template::Store as TemplateStore,
+        Capped, CompoundOperator, CompoundQuery,
+    },
 };
 use common::proto::{
     self, compound_registration_schema_query, multi_select_type, registration_schema_item_type,
-    registration_schema_query, select_type, text_type, DeleteRegistrationSchemasResponse,
+    registration_schema_query, select_type, string_query, text_type,
+    AddRegistrationSchemaItemRequest, AddRegistrationSchemaItemResponse,
+    ChangeRegistrationSchemaItemTypeRequest, ChangeRegistrationSchemaItemTypeResponse,
+    DeleteRegistrationSchemaItemRequest, DeleteRegistrationSchemaItemResponse,
+    DeleteRegistrationSchemasResponse, ExportJsonSchemaRequest, ExportJsonSchemaResponse,
+    GetSchemaFullRequest, GetSchemaFullResponse, InstantiateSchemaTemplateRequest,
+    InstantiateSchemaTemplateResponse, ListItemHeadersRequest, ListItemHeadersResponse,
     QueryRegistrationSchemasRequest, QueryRegistrationSchemasResponse, RegistrationSchema,
-    RegistrationSchemaItem, RegistrationSchemaQuery, UpsertRegistrationSchemasRequest,
-    UpsertRegistrationSchemasResponse,
+    RegistrationSchemaItem, RegistrationSchemaItemType, RegistrationSchemaQuery,
+    RenameRegistrationSchemaItemRequest, RenameRegistrationSchemaItemResponse,
+    ReorderRegistrationSchemaItemsRequest, ReorderRegistrationSchemaItemsResponse, SelectOption,
+    UpdateRegistrationSchemaItemsRequest, UpdateRegistrationSchemaItemsResponse, UpsertOutcome,
+    UpsertRegistrationSchemasRequest, UpsertRegistrationSchemasResponse,
 };
 
-use super::{common::try_logical_string_query, ValidationError};
+use super::{authentication::request_user_id, common::try_logical_string_query, ValidationError};
+
+const SCHEMA_CACHE_CAPACITY: usize = 64;
 
 #[derive(Debug)]
-pub struct Service<StoreType: Store> {
+enum CacheDependency {
+    Events(HashSet<String>),
+    All,
+}
+
+impl CacheDependency {
+    fn from_query(query: Option<&RegistrationSchemaQuery>) -> Self {
+        fn collect(query: &RegistrationSchemaQuery, ids: &mut HashSet<String>) -> bool {
+            match &query.query {
+                Some(registration_schema_query::Query::EventId(event_id_query)) => {
+                    match &event_id_query.operator {
+                        Some(string_query::Operator::Equals(id)) => {
+                            ids.insert(id.clone());
+                            true
+                        }
+                        Some(string_query::Operator::In(list)) => {
+                            ids.extend(list.values.iter().cloned());
+                            true
+                        }
+                        _ => false,
+                    }
+                }
+                Some(registration_schema_query::Query::Compound(compound_query)) => compound_query
+                    .queries
+                    .iter()
+                    .all(|query| collect(query, ids)),
+                None => false,
+            }
+        }
+
+        let mut ids = HashSet::new();
+        match query {
+            Some(query) if collect(query, &mut ids) => CacheDependency::Events(ids),
+            _ => CacheDependency::All,
+        }
+    }
+
+    fn depends_on(&self, event_ids: &[&str]) -> bool {
+        match self {
+            CacheDependency::Events(ids) => event_ids.iter().any(|id| ids.contains(*id)),
+            CacheDependency::All => true,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    dependency: CacheDependency,
+    registration_schemas: Capped<RegistrationSchema>,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<Vec<u8>, CacheEntry>,
+    order: VecDeque<Vec<u8>>,
+    generation: u64,
+}
+
+/// Recent schema query results. Schemas are written by more than this service, so the cache is
+/// shared with every service that writes them, and each evicts what its writes make stale before
+/// responding.
+#[derive(Debug, Default)]
+pub struct SchemaCache {
+    state: Mutex<CacheState>,
+}
+
+impl SchemaCache {
+    // A miss returns the generation to insert the result under once it has been read.
+    fn get(&self, key: &[u8]) -> Result<Capped<RegistrationSchema>, u64> {
+        let state = self.state.lock().unwrap();
+        state
+            .entries
+            .get(key)
+            .map(|entry| entry.registration_schemas.clone())
+            .ok_or(state.generation)
+    }
+
+    // Results read before an invalidation may already be stale, so they are dropped instead of cached.
+    fn insert(&self, key: Vec<u8>, generation: u64, entry: CacheEntry) {
+        let mut state = self.state.lock().unwrap();
+        if generation != state.generation {
+            return;
+        }
+
+        if state.entries.insert(key.clone(), entry).is_none() {
+            state.order.push_back(key);
+        }
+
+        while state.order.len() > SCHEMA_CACHE_CAPACITY {
+            if let Some(key) = state.order.pop_front() {
+                state.entries.remove(&key);
+            }
+        }
+    }
+
+    /// Evicts every result that may include a schema for one of the events.
+    pub fn invalidate(&self, event_ids: &[&str]) {
+        let mut state = self.state.lock().unwrap();
+        state.generation += 1;
+        state
+            .entries
+            .retain(|_, entry| !entry.dependency.depends_on(event_ids));
+
+        let CacheState { entries, order, .. } = &mut *state;
+        order.retain(|key| entries.contains_key(key));
+    }
+
+    /// Evicts everything, for writes that touch events without naming them.
+    pub fn invalidate_all(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.generation += 1;
+        state.entries.clear();
+        state.order.clear();
+    }
+}
+
+pub struct Service<StoreType: Store, TStore: TemplateStore, KStore: KeyStore> {
     store: Arc<StoreType>,
+    template_store: Arc<TStore>,
+    km: Arc<KeyManager<KStore>>,
+    cache: Arc<SchemaCache>,
+    max_query_results: u32,
+    max_options: usize,
 }
 
-impl<StoreType: Store> Service<StoreType> {
-    pub fn new(store: Arc<StoreType>) -> Self {
-        Service { store }
+impl<StoreType: Store, TStore: TemplateStore, KStore: KeyStore> Service<StoreType, TStore, KStore> {
+    pub fn new(
+        store: Arc<StoreType>,
+        template_store: Arc<TStore>,
+        km: Arc<KeyManager<KStore>>,
+    ) -> Self {
+        Service {
+            store,
+            template_store,
+            km,
+            cache: Arc::new(SchemaCache::default()),
+            max_query_results: super::DEFAULT_MAX_QUERY_RESULTS,
+            max_options: DEFAULT_MAX_OPTIONS,
+        }
+    }
+
+    pub fn with_max_query_results(mut self, max_query_results: u32) -> Self {
+        self.max_query_results = max_query_results;
+        self
+    }
+
+    // Only reported to clients so they can stop adding options early. The store enforces the limit
+    // it was built with, so both should be given the same value.
+    pub fn with_max_options(mut self, max_options: usize) -> Self {
+        self.max_options = max_options;
+        self
+    }
+
+    pub fn with_schema_cache(mut self, cache: Arc<SchemaCache>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    fn invalidate_cache(&self, event_ids: &[&str]) {
+        self.cache.invalidate(event_ids);
+    }
+
+    // Item changes are credited to the signed in user in the item's history. Anyone else's are
+    // recorded without an actor.
+    async fn actor(&self, metadata: &MetadataMap) -> Result<Option<UserId>, Status> {
+        request_user_id(&self.km, metadata)
+            .await
+            .map(|user_id| user_id.map(UserId::from))
+            .map_err(|e| -> Status { e.into() })
+    }
+}
+
+// Maps the index of each unarchived option to its index once archived options are dropped.
+fn unarchived_indices(options: &[SelectOption]) -> HashMap<u32, u32> {
+    options
+        .iter()
+        .enumerate()
+        .filter(|(_, option)| !option.archived)
+        .enumerate()
+        .map(|(new_idx, (idx, _))| (idx as u32, new_idx as u32))
+        .collect()
+}
+
+// Drops archived options while keeping defaults on the same options. A select whose default was
+// archived falls back to its first remaining option.
+fn remove_archived_options(items: &mut [RegistrationSchemaItem]) {
+    for item in items {
+        match item.r#type.as_mut().and_then(|t| t.r#type.as_mut()) {
+            Some(registration_schema_item_type::Type::Select(select)) => {
+                let indices = unarchived_indices(&select.options);
+                select.default = indices.get(&select.default).copied().unwrap_or(0);
+                select.options.retain(|option| !option.archived);
+            }
+            Some(registration_schema_item_type::Type::MultiSelect(multi_select)) => {
+                let indices = unarchived_indices(&multi_select.options);
+                multi_select.defaults = multi_select
+                    .defaults
+                    .iter()
+                    .filter_map(|default| indices.get(default).copied())
+                    .collect();
+                multi_select.options.retain(|option| !option.archived);
+            }
+            Some(registration_schema_item_type::Type::Group(group)) => {
+                remove_archived_options(&mut group.items)
+            }
+            _ => (),
+        }
     }
 }
 
-fn validate_registration_schema_item(item: &RegistrationSchemaItem) -> Result<(), ValidationError> {
+pub(super) fn validate_registration_schema_item(
+    item: &RegistrationSchemaItem,
+) -> Result<(), ValidationError> {
     if item.name.is_empty() {
         return Err(ValidationError::new_empty("name"));
     }
 
-    let outer_type = match &item.r#type {
+    validate_registration_schema_item_type(item.r#type.as_ref())
+}
+
+fn validate_registration_schema_item_type(
+    outer_type: Option<&RegistrationSchemaItemType>,
+) -> Result<(), ValidationError> {
+    let outer_type = match outer_type {
         Some(t) => t,
         None => return Err(ValidationError::new_empty("type")),
     };
@@ -74,6 +304,21 @@ fn validate_registration_schema_item(item: &RegistrationSchemaItem) -> Result<()
                 ));
             }
         }
+
+        registration_schema_item_type::Type::Section(_) => (),
+
+        registration_schema_item_type::Type::Tags(_) => (),
+
+        registration_schema_item_type::Type::Group(group) => {
+            if group.items.len() > i32::MAX as usize {
+                return Err(ValidationError::new_too_many_items("type.group.items"));
+            }
+
+            for (idx, group_item) in group.items.iter().enumerate() {
+                validate_registration_schema_item(group_item)
+                    .map_err(|e| e.with_context(&format!("type.group.items[{}]", idx)))?;
+            }
+        }
     };
 
     Ok(())
@@ -138,14 +383,17 @@ impl TryFrom<RegistrationSchemaQuery> for Query {
 }
 
 #[tonic::async_trait]
-impl<StoreType: Store> proto::registration_schema_service_server::RegistrationSchemaService
-    for Service<StoreType>
+impl<StoreType: Store, TStore: TemplateStore, KStore: KeyStore>
+    proto::registration_schema_service_server::RegistrationSchemaService
+    for Service<StoreType, TStore, KStore>
 {
     async fn upsert_registration_schemas(
         &self,
         request: Request<UpsertRegistrationSchemasRequest>,
     ) -> Result<Response<UpsertRegistrationSchemasResponse>, Status> {
-        let request_schemas = request.into_inner().registration_schemas;
+        let actor = self.actor(request.metadata()).await?;
+        let request = request.into_inner();
+        let request_schemas = request.registration_schemas;
 
         for (idx, schema) in request_schemas.iter().enumerate() {
             validate_registration_schema(schema).map_err(|e| -> Status {
@@ -154,14 +402,29 @@ impl<StoreType: Store> proto::registration_schema_service_server::RegistrationSc
             })?;
         }
 
-        let registration_schemas = self
-            .store
-            .upsert(request_schemas)
-            .await
-            .map_err(|e| -> Status { e.into() })?;
+        let event_ids = request_schemas
+            .iter()
+            .map(|schema| schema.event_id.clone())
+            .collect::<Vec<_>>();
+
+        let result = if request.changed_only {
+            self.store
+                .upsert_changed(request_schemas, actor.as_ref())
+                .await
+                .map(|schemas| (schemas, Vec::new()))
+        } else {
+            self.store
+                .upsert_with_outcomes(request_schemas, actor.as_ref())
+                .await
+        };
+
+        self.invalidate_cache(&event_ids.iter().map(|id| id.as_str()).collect::<Vec<_>>());
+
+        let (registration_schemas, outcomes) = result.map_err(|e| -> Status { e.into() })?;
 
         Ok(Response::new(UpsertRegistrationSchemasResponse {
             registration_schemas,
+            outcomes: outcomes.into_iter().map(UpsertOutcome::from).collect(),
         }))
     }
 
@@ -169,19 +432,47 @@ impl<StoreType: Store> proto::registration_schema_service_server::RegistrationSc
         &self,
         request: Request<QueryRegistrationSchemasRequest>,
     ) -> Result<Response<QueryRegistrationSchemasResponse>, Status> {
-        let query = request
-            .into_inner()
-            .query
+        let request_query = request.into_inner().query;
+        let cache_key = request_query
+            .as_ref()
+            .map(|q| q.encode_to_vec())
+            .unwrap_or_default();
+        let dependency = CacheDependency::from_query(request_query.as_ref());
+
+        let query = request_query
             .map(|q| -> Result<_, ValidationError> { q.try_into() })
             .transpose()?;
 
+        let generation = match self.cache.get(&cache_key) {
+            Ok(registration_schemas) => {
+                return Ok(Response::new(QueryRegistrationSchemasResponse {
+                    registration_schemas: registration_schemas.rows,
+                    truncated: registration_schemas.truncated,
+                    max_options: self.max_options as u32,
+                }))
+            }
+            Err(generation) => generation,
+        };
+
         let registration_schemas = self
             .store
-            .query(query.as_ref())
+            .query_capped(query.as_ref(), self.max_query_results)
             .await
             .map_err(|e| -> Status { e.into() })?;
+
+        self.cache.insert(
+            cache_key,
+            generation,
+            CacheEntry {
+                dependency,
+                registration_schemas: registration_schemas.clone(),
+            },
+        );
+
         Ok(Response::new(QueryRegistrationSchemasResponse {
-            registration_schemas,
+            registration_schemas: registration_schemas.rows,
+            truncated: registration_schemas.truncated,
+            max_options: self.max_options as u32,
         }))
     }
 
@@ -189,11 +480,717 @@ impl<StoreType: Store> proto::registration_schema_service_server::RegistrationSc
         &self,
         request: Request<proto::DeleteRegistrationSchemasRequest>,
     ) -> Result<Response<DeleteRegistrationSchemasResponse>, Status> {
-        self.store
-            .delete(&request.into_inner().ids)
+        let ids = request.into_inner().ids;
+        let result = self.store.delete(&ids).await;
+
+        self.invalidate_cache(&ids.iter().map(|id| id.as_str()).collect::<Vec<_>>());
+
+        result.map_err(|e| -> Status { e.into() })?;
+
+        Ok(Response::new(DeleteRegistrationSchemasResponse {}))
+    }
+
+    async fn list_item_headers(
+        &self,
+        request: Request<ListItemHeadersRequest>,
+    ) -> Result<Response<ListItemHeadersResponse>, Status> {
+        let event_id = request.into_inner().event_id;
+        if event_id.is_empty() {
+            return Err(ValidationError::new_empty("event_id").into());
+        }
+
+        let headers = self
+            .store
+            .list_item_headers(&EventId::from(&event_id))
             .await
             .map_err(|e| -> Status { e.into() })?;
 
-        Ok(Response::new(DeleteRegistrationSchemasResponse {}))
+        Ok(Response::new(ListItemHeadersResponse { headers }))
+    }
+
+    async fn instantiate_schema_template(
+        &self,
+        request: Request<InstantiateSchemaTemplateRequest>,
+    ) -> Result<Response<InstantiateSchemaTemplateResponse>, Status> {
+        let actor = self.actor(request.metadata()).await?;
+        let request = request.into_inner();
+        if request.template_id.is_empty() {
+            return Err(ValidationError::new_empty("template_id").into());
+        }
+
+        if request.event_id.is_empty() {
+            return Err(ValidationError::new_empty("event_id").into());
+        }
+
+        let template_schema = self
+            .template_store
+            .instantiate_template(
+                &TemplateId::from(&request.template_id),
+                &EventId::from(&request.event_id),
+            )
+            .await
+            .map_err(|e| -> Status { e.into() })?;
+
+        // The template's items are added after the stored ones, leaving those alone, so a
+        // concurrent edit isn't overwritten.
+        let result = self
+            .store
+            .add_items(
+                &EventId::from(&request.event_id),
+                template_schema.items,
+                actor.as_ref(),
+            )
+            .await;
+
+        self.invalidate_cache(&[request.event_id.as_str()]);
+        result.map_err(|e| -> Status { e.into() })?;
+
+        let registration_schema = self
+            .store
+            .query(Some(&Query::EventId(EventIdQuery::Equals(
+                request.event_id.clone(),
+            ))))
+            .await
+            .map_err(|e| -> Status { e.into() })?
+            .pop();
+
+        Ok(Response::new(InstantiateSchemaTemplateResponse {
+            registration_schema,
+        }))
+    }
+
+    async fn export_json_schema(
+        &self,
+        request: Request<ExportJsonSchemaRequest>,
+    ) -> Result<Response<ExportJsonSchemaResponse>, Status> {
+        let event_id = request.into_inner().event_id;
+        if event_id.is_empty() {
+            return Err(ValidationError::new_empty("event_id").into());
+        }
+
+        let registration_schema = self
+            .store
+            .query(Some(&Query::EventId(EventIdQuery::Equals(
+                event_id.clone(),
+            ))))
+            .await
+            .map_err(|e| -> Status { e.into() })?
+            .pop()
+            .unwrap_or_else(|| RegistrationSchema {
+                event_id,
+                items: Vec::new(),
+            });
+
+        let json_schema = export_json_schema(&registration_schema, chrono::Utc::now());
+
+        Ok(Response::new(ExportJsonSchemaResponse {
+            json_schema: json_schema.to_string(),
+        }))
+    }
+
+    async fn get_schema_full(
+        &self,
+        request: Request<GetSchemaFullRequest>,
+    ) -> Result<Response<GetSchemaFullResponse>, Status> {
+        let request = request.into_inner();
+        if request.event_id.is_empty() {
+            return Err(ValidationError::new_empty("event_id").into());
+        }
+
+        let mut registration_schema = self
+            .store
+            .query(Some(&Query::EventId(EventIdQuery::Equals(
+                request.event_id.clone(),
+            ))))
+            .await
+            .map_err(|e| -> Status { e.into() })?
+            .pop()
+            .unwrap_or_else(|| RegistrationSchema {
+                event_id: request.event_id,
+                items: Vec::new(),
+            });
+
+        if !request.include_archived {
+            remove_archived_options(&mut registration_schema.items);
+        }
+
+        Ok(Response::new(GetSchemaFullResponse {
+            registration_schema: Some(registration_schema),
+        }))
+    }
+
+    async fn add_registration_schema_item(
+        &self,
+        request: Request<AddRegistrationSchemaItemRequest>,
+    ) -> Result<Response<AddRegistrationSchemaItemResponse>, Status> {
+        let actor = self.actor(request.metadata()).await?;
+        let request = request.into_inner();
+        if request.event_id.is_empty() {
+            return Err(ValidationError::new_empty("event_id").into());
+        }
+
+        let item = request
+            .item
+            .ok_or_else(|| -> Status { ValidationError::new_empty("item").into() })?;
+        if !item.id.is_empty() {
+            return Err(ValidationError::new_invalid_value("item.id").into());
+        }
+
+        validate_registration_schema_item(&item)
+            .map_err(|e| -> Status { e.with_context("item").into() })?;
+
+        let result = self
+            .store
+            .add_items(
+                &EventId::from(&request.event_id),
+                vec![item],
+                actor.as_ref(),
+            )
+            .await;
+
+        self.invalidate_cache(&[request.event_id.as_str()]);
+
+        let item = result.map_err(|e| -> Status { e.into() })?.pop();
+
+        Ok(Response::new(AddRegistrationSchemaItemResponse { item }))
+    }
+
+    async fn update_registration_schema_items(
+        &self,
+        request: Request<UpdateRegistrationSchemaItemsRequest>,
+    ) -> Result<Response<UpdateRegistrationSchemaItemsResponse>, Status> {
+        let actor = self.actor(request.metadata()).await?;
+        let items = request.into_inner().items;
+
+        for (idx, item) in items.iter().enumerate() {
+            let validation = if item.id.is_empty() {
+                Err(ValidationError::new_empty("id"))
+            } else {
+                validate_registration_schema_item(item)
+            };
+
+            validation
+                .map_err(|e| -> Status { e.with_context(&format!("items[{}]", idx)).into() })?;
+        }
+
+        // The store only commits a successful edit, so there's nothing to invalidate otherwise.
+        let registration_schemas = self
+            .store
+            .update_items(items, actor.as_ref())
+            .await
+            .map_err(|e| -> Status { e.into() })?;
+
+        self.invalidate_cache(
+            &registration_schemas
+                .iter()
+                .map(|schema| schema.event_id.as_str())
+                .collect::<Vec<_>>(),
+        );
+
+        Ok(Response::new(UpdateRegistrationSchemaItemsResponse {
+            registration_schemas,
+        }))
+    }
+
+    async fn reorder_registration_schema_items(
+        &self,
+        request: Request<ReorderRegistrationSchemaItemsRequest>,
+    ) -> Result<Response<ReorderRegistrationSchemaItemsResponse>, Status> {
+        let request = request.into_inner();
+        if request.event_id.is_empty() {
+            return Err(ValidationError::new_empty("event_id").into());
+        }
+
+        let result = self
+            .store
+            .reorder_items(&EventId::from(&request.event_id), &ids(request.item_ids))
+            .await;
+
+        self.invalidate_cache(&[request.event_id.as_str()]);
+
+        let headers = result.map_err(|e| -> Status { e.into() })?;
+
+        Ok(Response::new(ReorderRegistrationSchemaItemsResponse {
+            headers,
+        }))
+    }
+
+    async fn rename_registration_schema_item(
+        &self,
+        request: Request<RenameRegistrationSchemaItemRequest>,
+    ) -> Result<Response<RenameRegistrationSchemaItemResponse>, Status> {
+        let actor = self.actor(request.metadata()).await?;
+        let request = request.into_inner();
+        if request.id.is_empty() {
+            return Err(ValidationError::new_empty("id").into());
+        }
+
+        if request.name.is_empty() {
+            return Err(ValidationError::new_empty("name").into());
+        }
+
+        let header = self
+            .store
+            .rename_item(
+                &SchemaItemId::from(request.id),
+                &request.name,
+                actor.as_ref(),
+            )
+            .await
+            .map_err(|e| -> Status { e.into() })?;
+
+        self.invalidate_cache(&[header.event_id.as_str()]);
+
+        Ok(Response::new(RenameRegistrationSchemaItemResponse {
+            header: Some(header),
+        }))
+    }
+
+    async fn delete_registration_schema_item(
+        &self,
+        request: Request<DeleteRegistrationSchemaItemRequest>,
+    ) -> Result<Response<DeleteRegistrationSchemaItemResponse>, Status> {
+        let request = request.into_inner();
+        if request.id.is_empty() {
+            return Err(ValidationError::new_empty("id").into());
+        }
+
+        let header = self
+            .store
+            .delete_item(&SchemaItemId::from(request.id))
+            .await
+            .map_err(|e| -> Status { e.into() })?;
+
+        self.invalidate_cache(&[header.event_id.as_str()]);
+
+        Ok(Response::new(DeleteRegistrationSchemaItemResponse {
+            header: Some(header),
+        }))
+    }
+
+    async fn change_registration_schema_item_type(
+        &self,
+        request: Request<ChangeRegistrationSchemaItemTypeRequest>,
+    ) -> Result<Response<ChangeRegistrationSchemaItemTypeResponse>, Status> {
+        let actor = self.actor(request.metadata()).await?;
+        let request = request.into_inner();
+        if request.id.is_empty() {
+            return Err(ValidationError::new_empty("id").into());
+        }
+
+        validate_registration_schema_item_type(request.r#type.as_ref())?;
+
+        let registration_schema = self
+            .store
+            .change_item_type(
+                &SchemaItemId::from(request.id),
+                request.r#type.unwrap_or_default(),
+                actor.as_ref(),
+            )
+            .await
+            .map_err(|e| -> Status { e.into() })?;
+
+        self.invalidate_cache(&[registration_schema.event_id.as_str()]);
+
+        Ok(Response::new(ChangeRegistrationSchemaItemTypeResponse {
+            registration_schema: Some(registration_schema),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use sqlx::{migrate::MigrateDatabase, Sqlite, SqlitePool};
+    use test_case::test_case;
+    use tonic::Request;
+    use uuid::Uuid;
+
+    use common::proto::{
+        registration_schema_item_type, registration_schema_query,
+        registration_schema_service_server::RegistrationSchemaService, string_query, CheckboxType,
+        GetSchemaFullRequest, InstantiateSchemaTemplateRequest, QueryRegistrationSchemasRequest,
+        RegistrationSchema, RegistrationSchemaItem, RegistrationSchemaItemType,
+        RegistrationSchemaQuery, RenameRegistrationSchemaItemRequest, SchemaTemplate, SelectOption,
+        SelectType, StringQuery, UpsertRegistrationSchemasRequest,
+    };
+
+    use crate::{
+        keys::KeyManager,
+        store::{
+            keys::SqliteStore as KeyStore,
+            registration_schema::SqliteStore,
+            schema_template::{SqliteStore as TemplateStore, Store as _},
+        },
+    };
+
+    use super::Service;
+
+    struct Init {
+        org: String,
+        event: String,
+        db: Arc<SqlitePool>,
+    }
+
+    async fn init_db() -> Init {
+        let db_url = "sqlite://:memory:";
+        Sqlite::create_database(db_url).await.unwrap();
+
+        let db = SqlitePool::connect(db_url).await.unwrap();
+        sqlx::migrate!("../migrations").run(&db).await.unwrap();
+
+        let org = Uuid::now_v7().to_string();
+        sqlx::query("INSERT INTO organizations(id, name) VALUES (?, ?);")
+            .bind(&org)
+            .bind("Organization 1")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let event = Uuid::now_v7().to_string();
+        sqlx::query("INSERT INTO events(id, organization, name) VALUES (?, ?, ?);")
+            .bind(&event)
+            .bind(&org)
+            .bind("Event 1")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        Init {
+            org,
+            event,
+            db: Arc::new(db),
+        }
+    }
+
+    fn service(init: &Init) -> Service<SqliteStore, TemplateStore, KeyStore> {
+        Service::new(
+            Arc::new(SqliteStore::new(init.db.clone())),
+            Arc::new(TemplateStore::new(init.db.clone())),
+            Arc::new(KeyManager::new(Arc::new(KeyStore::new(init.db.clone())))),
+        )
+    }
+
+    fn schema(event_id: &str, item_name: &str) -> RegistrationSchema {
+        RegistrationSchema {
+            event_id: event_id.to_owned(),
+            items: vec![RegistrationSchemaItem {
+                id: "".to_owned(),
+                name: item_name.to_owned(),
+                r#type: Some(RegistrationSchemaItemType {
+                    r#type: Some(registration_schema_item_type::Type::Checkbox(
+                        CheckboxType { default: false },
+                    )),
+                }),
+                required: false,
+                required_after: "".to_owned(),
+                blank_on_copy: false,
+                display_idx: None,
+            }],
+        }
+    }
+
+    async fn query_item_name(
+        service: &Service<SqliteStore, TemplateStore, KeyStore>,
+        event_id: &str,
+    ) -> String {
+        let mut response = service
+            .query_registration_schemas(Request::new(QueryRegistrationSchemasRequest {
+                query: Some(RegistrationSchemaQuery {
+                    query: Some(registration_schema_query::Query::EventId(StringQuery {
+                        operator: Some(string_query::Operator::Equals(event_id.to_owned())),
+                    })),
+                }),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        response.registration_schemas[0].items.remove(0).name
+    }
+
+    #[tokio::test]
+    async fn query_is_cached() {
+        let init = init_db().await;
+        let service = service(&init);
+
+        service
+            .upsert_registration_schemas(Request::new(UpsertRegistrationSchemasRequest {
+                registration_schemas: vec![schema(&init.event, "Item 1")],
+                changed_only: false,
+            }))
+            .await
+            .unwrap();
+
+        assert_eq!(query_item_name(&service, &init.event).await, "Item 1");
+
+        // Change the row behind the service's back; a cached query won't see it.
+        sqlx::query("UPDATE registration_schema_items SET name = ?")
+            .bind("Changed")
+            .execute(&*init.db)
+            .await
+            .unwrap();
+
+        assert_eq!(query_item_name(&service, &init.event).await, "Item 1");
+    }
+
+    #[tokio::test]
+    async fn query_reports_max_options() {
+        let init = init_db().await;
+        let service = service(&init).with_max_options(5);
+
+        // The second query is answered from the cache, which has to report the limit too.
+        for _ in 0..2 {
+            let response = service
+                .query_registration_schemas(Request::new(QueryRegistrationSchemasRequest {
+                    query: None,
+                }))
+                .await
+                .unwrap()
+                .into_inner();
+
+            assert_eq!(response.max_options, 5);
+        }
+    }
+
+    #[tokio::test]
+    async fn upsert_invalidates_cache() {
+        let init = init_db().await;
+        let service = service(&init);
+
+        service
+            .upsert_registration_schemas(Request::new(UpsertRegistrationSchemasRequest {
+                registration_schemas: vec![schema(&init.event, "Item 1")],
+                changed_only: false,
+            }))
+            .await
+            .unwrap();
+
+        assert_eq!(query_item_name(&service, &init.event).await, "Item 1");
+
+        service
+            .upsert_registration_schemas(Request::new(UpsertRegistrationSchemasRequest {
+                registration_schemas: vec![schema(&init.event, "Item 2")],
+                changed_only: false,
+            }))
+            .await
+            .unwrap();
+
+        assert_eq!(query_item_name(&service, &init.event).await, "Item 2");
+    }
+
+    #[tokio::test]
+    async fn rename_invalidates_cache() {
+        let init = init_db().await;
+        let service = service(&init);
+
+        let item_id = service
+            .upsert_registration_schemas(Request::new(UpsertRegistrationSchemasRequest {
+                registration_schemas: vec![schema(&init.event, "Item 1")],
+                changed_only: false,
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .registration_schemas[0]
+            .items[0]
+            .id
+            .clone();
+
+        assert_eq!(query_item_name(&service, &init.event).await, "Item 1");
+
+        service
+            .rename_registration_schema_item(Request::new(RenameRegistrationSchemaItemRequest {
+                id: item_id,
+                name: "Renamed".to_owned(),
+            }))
+            .await
+            .unwrap();
+
+        assert_eq!(query_item_name(&service, &init.event).await, "Renamed");
+    }
+
+    fn select_option(name: &str, archived: bool) -> SelectOption {
+        SelectOption {
+            id: "".to_owned(),
+            name: name.to_owned(),
+            product_id: "".to_owned(),
+            archived,
+        }
+    }
+
+    #[test_case(false, &[("Beginner", false), ("Advanced", false)], 1 ; "default")]
+    #[test_case(true, &[("Beginner", false), ("Intermediate", true), ("Advanced", false)], 2 ; "include archived")]
+    #[tokio::test]
+    async fn get_schema_full(include_archived: bool, expected: &[(&str, bool)], default: u32) {
+        let init = init_db().await;
+        let service = service(&init);
+
+        let mut registration_schema = schema(&init.event, "Level");
+        registration_schema.items[0].r#type = Some(RegistrationSchemaItemType {
+            r#type: Some(registration_schema_item_type::Type::Select(SelectType {
+                default: 2,
+                display: 0,
+                options: vec![
+                    select_option("Beginner", false),
+                    select_option("Intermediate", true),
+                    select_option("Advanced", false),
+                ],
+            })),
+        });
+
+        service
+            .upsert_registration_schemas(Request::new(UpsertRegistrationSchemasRequest {
+                registration_schemas: vec![registration_schema],
+                changed_only: false,
+            }))
+            .await
+            .unwrap();
+
+        let registration_schema = service
+            .get_schema_full(Request::new(GetSchemaFullRequest {
+                event_id: init.event.clone(),
+                include_archived,
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .registration_schema
+            .unwrap();
+
+        let Some(registration_schema_item_type::Type::Select(select)) = registration_schema.items
+            [0]
+        .r#type
+        .as_ref()
+        .and_then(|t| t.r#type.as_ref()) else {
+            panic!("expected a select item");
+        };
+
+        let options = select
+            .options
+            .iter()
+            .map(|option| (option.name.as_str(), option.archived))
+            .collect::<Vec<_>>();
+        assert_eq!(options, expected);
+
+        // The default stays on "Advanced" whether or not the archived option is dropped.
+        assert_eq!(select.default, default);
+    }
+
+    #[tokio::test]
+    async fn instantiate_template_into_empty_event() {
+        let init = init_db().await;
+        let service = service(&init);
+
+        let template_items = vec![
+            RegistrationSchemaItem {
+                id: "".to_owned(),
+                name: "Level".to_owned(),
+                r#type: Some(RegistrationSchemaItemType {
+                    r#type: Some(registration_schema_item_type::Type::Select(SelectType {
+                        default: 0,
+                        display: 0,
+                        options: vec![SelectOption {
+                            id: "".to_owned(),
+                            name: "Beginner".to_owned(),
+                            product_id: "".to_owned(),
+                            archived: false,
+                        }],
+                    })),
+                }),
+                required: false,
+                required_after: "".to_owned(),
+                blank_on_copy: false,
+                display_idx: None,
+            },
+            schema(&init.event, "Needs Shoes").items.remove(0),
+        ];
+
+        let template = TemplateStore::new(init.db.clone())
+            .upsert(vec![SchemaTemplate {
+                id: "".to_owned(),
+                organization_id: init.org.clone(),
+                name: "Beginner Class Signup".to_owned(),
+                items: template_items.clone(),
+            }])
+            .await
+            .unwrap()
+            .remove(0);
+
+        let registration_schema = service
+            .instantiate_schema_template(Request::new(InstantiateSchemaTemplateRequest {
+                template_id: template.id,
+                event_id: init.event.clone(),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .registration_schema
+            .unwrap();
+
+        let mut items = registration_schema.items;
+        for item in items.iter_mut() {
+            assert!(!item.id.is_empty());
+            item.id = "".to_owned();
+
+            if let Some(registration_schema_item_type::Type::Select(select)) =
+                item.r#type.as_mut().and_then(|t| t.r#type.as_mut())
+            {
+                for option in select.options.iter_mut() {
+                    assert!(!option.id.is_empty());
+                    option.id = "".to_owned();
+                }
+            }
+        }
+
+        assert_eq!(registration_schema.event_id, init.event);
+        assert_eq!(items, template_items);
+        assert_eq!(query_item_name(&service, &init.event).await, "Level");
+    }
+
+    #[tokio::test]
+    async fn instantiate_template_keeps_existing_items() {
+        let init = init_db().await;
+        let service = service(&init);
+
+        let existing = service
+            .upsert_registration_schemas(Request::new(UpsertRegistrationSchemasRequest {
+                registration_schemas: vec![schema(&init.event, "Name")],
+                changed_only: false,
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .registration_schemas
+            .remove(0);
+
+        let template = TemplateStore::new(init.db.clone())
+            .upsert(vec![SchemaTemplate {
+                id: "".to_owned(),
+                organization_id: init.org.clone(),
+                name: "Waivers".to_owned(),
+                items: schema(&init.event, "Waiver").items,
+            }])
+            .await
+            .unwrap()
+            .remove(0);
+
+        let registration_schema = service
+            .instantiate_schema_template(Request::new(InstantiateSchemaTemplateRequest {
+                template_id: template.id,
+                event_id: init.event.clone(),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .registration_schema
+            .unwrap();
+
+        // The stored item is left as it was and the template's item goes after it.
+        assert_eq!(registration_schema.items.len(), 2);
+        assert_eq!(registration_schema.items[0], existing.items[0]);
+        assert_eq!(registration_schema.items[1].name, "Waiver");
+        assert!(!registration_schema.items[1].id.is_empty());
     }
 }
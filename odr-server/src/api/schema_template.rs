@@ -0,0 +1,151 @@
+use std::sync::Arc;
+
+use common::proto::{
+    self, compound_schema_template_query, schema_template_query, DeleteSchemaTemplatesRequest,
+    DeleteSchemaTemplatesResponse, QuerySchemaTemplatesRequest, QuerySchemaTemplatesResponse,
+    SchemaTemplate, SchemaTemplateQuery, UpsertSchemaTemplatesRequest,
+    UpsertSchemaTemplatesResponse,
+};
+use tonic::{Request, Response, Status};
+
+use crate::store::{
+    schema_template::{Query, Store},
+    CompoundOperator, CompoundQuery,
+};
+
+use super::{
+    common::try_logical_string_query, registration_schema::validate_registration_schema_item,
+    ValidationError,
+};
+
+pub struct Service<StoreType: Store> {
+    store: Arc<StoreType>,
+}
+
+impl<StoreType: Store> Service<StoreType> {
+    pub fn new(store: Arc<StoreType>) -> Self {
+        Service { store }
+    }
+}
+
+fn validate_schema_template(template: &SchemaTemplate) -> Result<(), ValidationError> {
+    if template.organization_id.is_empty() {
+        return Err(ValidationError::new_empty("organization_id"));
+    }
+
+    if template.name.is_empty() {
+        return Err(ValidationError::new_empty("name"));
+    }
+
+    if template.items.len() > i32::MAX as usize {
+        return Err(ValidationError::new_too_many_items("items"));
+    }
+
+    for (idx, item) in template.items.iter().enumerate() {
+        validate_registration_schema_item(item)
+            .map_err(|e| e.with_context(&format!("items[{}]", idx)))?;
+    }
+
+    Ok(())
+}
+
+impl TryFrom<SchemaTemplateQuery> for Query {
+    type Error = ValidationError;
+
+    fn try_from(query: SchemaTemplateQuery) -> Result<Self, Self::Error> {
+        match query.query {
+            Some(schema_template_query::Query::Id(query)) => Ok(Query::Id(
+                try_logical_string_query(query).map_err(|e| e.with_context("query.id"))?,
+            )),
+
+            Some(schema_template_query::Query::OrganizationId(query)) => Ok(Query::Organization(
+                try_logical_string_query(query)
+                    .map_err(|e| e.with_context("query.organization_id"))?,
+            )),
+
+            Some(schema_template_query::Query::Compound(compound_query)) => {
+                let operator = match compound_schema_template_query::Operator::try_from(
+                    compound_query.operator,
+                ) {
+                    Ok(compound_schema_template_query::Operator::And) => CompoundOperator::And,
+                    Ok(compound_schema_template_query::Operator::Or) => CompoundOperator::Or,
+                    Err(_) => {
+                        return Err(ValidationError::new_invalid_enum("query.compound.operator"))
+                    }
+                };
+
+                let queries = compound_query
+                    .queries
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, query)| {
+                        query.try_into().map_err(|e: Self::Error| {
+                            e.with_context(&format!("query.compound.queries[{}]", idx))
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Ok(Query::CompoundQuery(CompoundQuery { operator, queries }))
+            }
+            None => Err(ValidationError::new_empty("query")),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl<StoreType: Store> proto::schema_template_service_server::SchemaTemplateService
+    for Service<StoreType>
+{
+    async fn upsert_schema_templates(
+        &self,
+        request: Request<UpsertSchemaTemplatesRequest>,
+    ) -> Result<Response<UpsertSchemaTemplatesResponse>, Status> {
+        let request_templates = request.into_inner().schema_templates;
+
+        for (idx, template) in request_templates.iter().enumerate() {
+            validate_schema_template(template).map_err(|e| -> Status {
+                e.with_context(&format!("schema_templates[{}]", idx)).into()
+            })?;
+        }
+
+        let schema_templates = self
+            .store
+            .upsert(request_templates)
+            .await
+            .map_err(|e| -> Status { e.into() })?;
+
+        Ok(Response::new(UpsertSchemaTemplatesResponse {
+            schema_templates,
+        }))
+    }
+
+    async fn query_schema_templates(
+        &self,
+        request: Request<QuerySchemaTemplatesRequest>,
+    ) -> Result<Response<QuerySchemaTemplatesResponse>, Status> {
+        let query = request.into_inner().query;
+        let query = query.map(|query| query.try_into()).transpose()?;
+
+        let schema_templates = self
+            .store
+            .query(query.as_ref())
+            .await
+            .map_err(|e| -> Status { e.into() })?;
+
+        Ok(Response::new(QuerySchemaTemplatesResponse {
+            schema_templates,
+        }))
+    }
+
+    async fn delete_schema_templates(
+        &self,
+        request: Request<DeleteSchemaTemplatesRequest>,
+    ) -> Result<Response<DeleteSchemaTemplatesResponse>, Status> {
+        self.store
+            .delete(&request.into_inner().ids)
+            .await
+            .map_err(|e| -> Status { e.into() })?;
+
+        Ok(Response::new(DeleteSchemaTemplatesResponse {}))
+    }
+}
@@ -4,8 +4,8 @@ use common::{
     password,
     proto::{
         self, compound_user_query, user::Password, user_query, DeleteUsersRequest,
-        DeleteUsersResponse, QueryUsersRequest, QueryUsersResponse, UpsertUsersRequest,
-        UpsertUsersResponse, UserQuery,
+        DeleteUsersResponse, QueryUsersRequest, QueryUsersResponse, SearchUsersRequest,
+        SearchUsersResponse, UpsertUsersRequest, UpsertUsersResponse, UserQuery,
     },
 };
 use tonic::{Request, Response, Status};
@@ -20,6 +20,9 @@ use crate::{
 
 use super::{common::try_logical_string_query, ValidationError};
 
+const DEFAULT_SEARCH_LIMIT: u32 = 10;
+const MAX_SEARCH_LIMIT: u32 = 50;
+
 pub struct Service<StoreType: Store> {
     store: Arc<StoreType>,
 }
@@ -191,4 +194,29 @@ impl<StoreType: Store> proto::user_service_server::UserService for Service<Store
 
         Ok(Response::new(DeleteUsersResponse {}))
     }
+
+    async fn search_users(
+        &self,
+        request: Request<SearchUsersRequest>,
+    ) -> Result<Response<SearchUsersResponse>, Status> {
+        let request = request.into_inner();
+        if request.prefix.is_empty() {
+            return Err(ValidationError::new_empty("prefix").into());
+        }
+
+        let limit = match request.limit {
+            0 => DEFAULT_SEARCH_LIMIT,
+            limit => limit.min(MAX_SEARCH_LIMIT),
+        };
+
+        let users = self
+            .store
+            .search(&request.prefix, limit)
+            .await
+            .map_err(|e| -> Status { e.into() })?;
+
+        Ok(Response::new(SearchUsersResponse {
+            users: users.into_iter().map(user_to_proto).collect(),
+        }))
+    }
 }
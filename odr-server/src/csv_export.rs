@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use common::proto::{
+    registration, registration_schema_item_type::Type as ItemType, Registration,
+    RegistrationSchema, RegistrationSchemaItem,
+};
+
+use crate::receipt::resolve_value;
+
+fn item_type(item: &RegistrationSchemaItem) -> Option<&ItemType> {
+    item.r#type.as_ref().and_then(|t| t.r#type.as_ref())
+}
+
+fn has_value(item: &RegistrationSchemaItem) -> bool {
+    !matches!(
+        item_type(item),
+        Some(ItemType::Section(_)) | Some(ItemType::Group(_))
+    )
+}
+
+/// Writes one row per registration and one column per answerable item. A group gets a column per
+/// item in it, with the values from each of its repeats joined by "; ".
+pub fn export_registrations_csv(
+    schema: &RegistrationSchema,
+    registrations: &[Registration],
+) -> Result<String, csv::Error> {
+    let columns = schema
+        .items
+        .iter()
+        .flat_map(|item| match item_type(item) {
+            Some(ItemType::Group(group)) => group
+                .items
+                .iter()
+                .map(|group_item| (format!("{}: {}", item.name, group_item.name), group_item))
+                .collect(),
+            _ => vec![(item.name.clone(), item)],
+        })
+        .filter(|(_, item)| has_value(item))
+        .collect::<Vec<_>>();
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    writer.write_record(
+        ["id", "status"]
+            .into_iter()
+            .chain(columns.iter().map(|(name, _)| name.as_str())),
+    )?;
+
+    for registration in registrations {
+        let mut values: HashMap<&str, Vec<(u32, &str)>> = HashMap::new();
+        for item in registration.items.iter() {
+            values
+                .entry(item.schema_item_id.as_str())
+                .or_default()
+                .push((item.instance, item.value.as_str()));
+        }
+
+        let status = registration::Status::try_from(registration.status)
+            .map(|status| status.as_str_name())
+            .unwrap_or_default();
+
+        let cells = columns.iter().map(|(_, item)| {
+            let mut item_values = values.get(item.id.as_str()).cloned().unwrap_or_default();
+            item_values.sort_unstable();
+
+            itertools::join(
+                item_values
+                    .into_iter()
+                    .map(|(_, value)| resolve_value(item_type(item), value)),
+                "; ",
+            )
+        });
+
+        writer.write_record(
+            [registration.id.clone(), status.to_owned()]
+                .into_iter()
+                .chain(cells),
+        )?;
+    }
+
+    let bytes = writer.into_inner().map_err(|e| e.into_error())?;
+
+    // Every field written above is a String, so the output is valid UTF-8.
+    Ok(String::from_utf8(bytes).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use common::proto::{registration, Registration, RegistrationItem, RegistrationSchema};
+
+    use super::export_registrations_csv;
+    use crate::testing::{
+        checkbox_item, multi_select_item, section_item, select_item, text_item, with_id,
+    };
+
+    fn registration_item(schema_item_id: &str, value: &str) -> RegistrationItem {
+        RegistrationItem {
+            schema_item_id: schema_item_id.to_owned(),
+            value: value.to_owned(),
+            instance: 0,
+        }
+    }
+
+    #[test]
+    fn export_csv() {
+        let schema = RegistrationSchema {
+            event_id: "event".to_owned(),
+            items: vec![
+                with_id("name", text_item("Name")),
+                with_id("about", section_item("About You", "")),
+                with_id(
+                    "pass",
+                    select_item("Pass", &["Full Weekend", "Saturday Only"]),
+                ),
+                with_id(
+                    "classes",
+                    multi_select_item("Classes", &["Lindy", "Balboa", "Blues"]),
+                ),
+                with_id("housing", checkbox_item("Housing")),
+            ],
+        };
+
+        let registrations = vec![
+            Registration {
+                id: "A".to_owned(),
+                event_id: "event".to_owned(),
+                items: vec![
+                    registration_item("name", "Smith, Jo"),
+                    registration_item("pass", "1"),
+                    registration_item("classes", "0,2"),
+                    registration_item("housing", "true"),
+                ],
+                status: registration::Status::Confirmed as i32,
+                user_id: String::default(),
+            },
+            Registration {
+                id: "B".to_owned(),
+                event_id: "event".to_owned(),
+                items: vec![registration_item("name", "Sam \"Swing\" Lee")],
+                status: registration::Status::Waitlisted as i32,
+                user_id: String::default(),
+            },
+        ];
+
+        let csv = export_registrations_csv(&schema, &registrations).unwrap();
+
+        assert_eq!(
+            csv,
+            "id,status,Name,Pass,Classes,Housing\n\
+            A,CONFIRMED,\"Smith, Jo\",Saturday Only,\"Lindy, Blues\",Yes\n\
+            B,WAITLISTED,\"Sam \"\"Swing\"\" Lee\",,,\n"
+        );
+    }
+}
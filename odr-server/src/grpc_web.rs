@@ -0,0 +1,180 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use tonic::{body::BoxBody, server::NamedService};
+use tonic_web::CorsGrpcWeb;
+use tower::{BoxError, Service};
+
+/// Makes a service reachable from browsers over gRPC-Web, answering CORS preflights too. When
+/// `enabled` is false the service is left as it is, so only plain gRPC reaches it. Plain gRPC
+/// calls pass through unchanged either way.
+pub fn enable<S>(service: S, enabled: bool) -> GrpcWeb<S>
+where
+    S: Service<http::Request<hyper::Body>, Response = http::Response<BoxBody>>,
+    S: Clone + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Into<BoxError> + Send,
+{
+    if enabled {
+        GrpcWeb::Enabled(Box::new(tonic_web::enable(service)))
+    } else {
+        GrpcWeb::Disabled(service)
+    }
+}
+
+#[derive(Clone)]
+pub enum GrpcWeb<S> {
+    Enabled(Box<CorsGrpcWeb<S>>),
+    Disabled(S),
+}
+
+impl<S> Service<http::Request<hyper::Body>> for GrpcWeb<S>
+where
+    S: Service<http::Request<hyper::Body>, Response = http::Response<BoxBody>>,
+    S: Clone + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Into<BoxError> + Send,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self {
+            GrpcWeb::Enabled(service) => service.poll_ready(cx),
+            GrpcWeb::Disabled(service) => service.poll_ready(cx),
+        }
+    }
+
+    fn call(&mut self, request: http::Request<hyper::Body>) -> Self::Future {
+        match self {
+            GrpcWeb::Enabled(service) => Box::pin(service.call(request)),
+            GrpcWeb::Disabled(service) => Box::pin(service.call(request)),
+        }
+    }
+}
+
+impl<S: NamedService> NamedService for GrpcWeb<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use common::proto::{
+        event_service_server::EventServiceServer, Event, QueryEventsRequest, QueryEventsResponse,
+    };
+    use prost::Message as _;
+    use sqlx::{migrate::MigrateDatabase, Sqlite, SqlitePool};
+    use tower::ServiceExt as _;
+    use uuid::Uuid;
+
+    use crate::{
+        api::event::Service as EventService,
+        store::{
+            event::{SqliteStore as EventStore, Store as _},
+            registration_schema::SqliteStore as SchemaStore,
+            schema_template::SqliteStore as TemplateStore,
+        },
+    };
+
+    use super::enable;
+
+    const GRPC_WEB: &str = "application/grpc-web+proto";
+
+    async fn event_service() -> (
+        EventServiceServer<EventService<EventStore, SchemaStore, TemplateStore>>,
+        Event,
+    ) {
+        let db_url = "sqlite://:memory:";
+        Sqlite::create_database(db_url).await.unwrap();
+
+        let db = SqlitePool::connect(db_url).await.unwrap();
+        sqlx::migrate!("../migrations").run(&db).await.unwrap();
+
+        let org = Uuid::now_v7().to_string();
+        sqlx::query("INSERT INTO organizations(id, name) VALUES (?, ?);")
+            .bind(&org)
+            .bind("Organization 1")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let db = Arc::new(db);
+        let event_store = Arc::new(EventStore::new(db.clone()));
+        let event = event_store
+            .upsert(vec![Event {
+                organization_id: org,
+                name: "Event 1".to_owned(),
+                ..Default::default()
+            }])
+            .await
+            .unwrap()
+            .remove(0);
+
+        let service = EventServiceServer::new(EventService::new(
+            event_store,
+            Arc::new(SchemaStore::new(db.clone())),
+            Arc::new(TemplateStore::new(db)),
+        ));
+
+        (service, event)
+    }
+
+    // A gRPC-Web call carries the message in a length-prefixed frame, and the response adds a
+    // frame of trailers after the message's.
+    fn request(content_type: &str) -> http::Request<hyper::Body> {
+        let message = QueryEventsRequest { query: None }.encode_to_vec();
+        let mut body = vec![0];
+        body.extend((message.len() as u32).to_be_bytes());
+        body.extend(message);
+
+        http::Request::post("/proto.EventService/QueryEvents")
+            .header(http::header::CONTENT_TYPE, content_type)
+            .body(hyper::Body::from(body))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn grpc_web_query_events() {
+        let (service, event) = event_service().await;
+
+        let response = enable(service, true)
+            .oneshot(request(GRPC_WEB))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), http::StatusCode::OK);
+        assert_eq!(response.headers()[http::header::CONTENT_TYPE], GRPC_WEB);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(body[0], 0);
+        let len = u32::from_be_bytes(body[1..5].try_into().unwrap()) as usize;
+        let message = QueryEventsResponse::decode(&body[5..5 + len]).unwrap();
+        assert_eq!(message.events, vec![event]);
+
+        let trailers = &body[5 + len..];
+        assert_eq!(trailers[0], 0x80);
+        let trailers = String::from_utf8_lossy(&trailers[5..]);
+        assert!(trailers.contains("grpc-status:0"), "{}", trailers);
+    }
+
+    #[tokio::test]
+    async fn grpc_web_disabled() {
+        let (service, _) = event_service().await;
+
+        let response = enable(service, false)
+            .oneshot(request(GRPC_WEB))
+            .await
+            .unwrap();
+
+        assert_ne!(
+            response.headers().get(http::header::CONTENT_TYPE),
+            Some(&http::HeaderValue::from_static(GRPC_WEB))
+        );
+    }
+}
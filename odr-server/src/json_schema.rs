@@ -0,0 +1,251 @@
+use chrono::{DateTime, Utc};
+use common::proto::{
+    registration_schema_item_type::Type as ItemType, RegistrationSchema, RegistrationSchemaItem,
+    SelectOption,
+};
+use serde_json::{json, Map, Value};
+
+use crate::store::registration::is_required;
+
+const DRAFT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+fn option_names(options: &[SelectOption]) -> Vec<&str> {
+    options.iter().map(|option| option.name.as_str()).collect()
+}
+
+fn property(item: &RegistrationSchemaItem, now: DateTime<Utc>) -> Option<Value> {
+    let mut property = match item.r#type.as_ref()?.r#type.as_ref()? {
+        ItemType::Text(text) => json!({
+            "type": "string",
+            "default": text.default,
+        }),
+        ItemType::Checkbox(checkbox) => json!({
+            "type": "boolean",
+            "default": checkbox.default,
+        }),
+        ItemType::Select(select) => {
+            let mut property = json!({
+                "type": "string",
+                "enum": option_names(&select.options),
+            });
+            if let Some(default) = select.options.get(select.default as usize) {
+                property["default"] = json!(default.name);
+            }
+            property
+        }
+        ItemType::MultiSelect(multi_select) => json!({
+            "type": "array",
+            "items": {
+                "type": "string",
+                "enum": option_names(&multi_select.options),
+            },
+            "uniqueItems": true,
+            "default": multi_select
+                .defaults
+                .iter()
+                .filter_map(|idx| multi_select.options.get(*idx as usize))
+                .map(|option| option.name.as_str())
+                .collect::<Vec<_>>(),
+        }),
+        ItemType::Tags(tags) => {
+            let mut property = json!({
+                "type": "array",
+                "items": {
+                    "type": "string",
+                    "pattern": "\\S",
+                },
+            });
+            if tags.max_tags != 0 {
+                property["maxItems"] = json!(tags.max_tags);
+            }
+            property
+        }
+        // Sections only carry text for the form; they have no value.
+        ItemType::Section(_) => return None,
+        // Each repeat of a group is an object of its own items' values.
+        ItemType::Group(group) => {
+            let mut property = json!({
+                "type": "array",
+                "items": object(&group.items, now),
+            });
+            if is_required(item, now) {
+                property["minItems"] = json!(1);
+            }
+            property
+        }
+    };
+
+    property["title"] = json!(item.name);
+    Some(property)
+}
+
+fn object(items: &[RegistrationSchemaItem], now: DateTime<Utc>) -> Value {
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+
+    for item in items.iter() {
+        let Some(property) = property(item, now) else {
+            continue;
+        };
+
+        if is_required(item, now) {
+            required.push(item.id.clone());
+        }
+        properties.insert(item.id.clone(), property);
+    }
+
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+/// Describes the values a registration for the schema may hold as a JSON Schema object, keyed by
+/// schema item id, for integrators building their own registration forms. Groups are arrays with
+/// an object per repeat.
+pub fn export_json_schema(schema: &RegistrationSchema, now: DateTime<Utc>) -> Value {
+    let mut schema_object = object(&schema.items, now);
+    schema_object["$schema"] = json!(DRAFT);
+    schema_object
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use common::proto::{
+        multi_select_type, registration_schema_item_type::Type as ItemType, select_type, text_type,
+        CheckboxType, GroupType, MultiSelectType, RegistrationSchema, RegistrationSchemaItem,
+        SelectType, TextType,
+    };
+    use serde_json::json;
+    use test_case::test_case;
+
+    use super::export_json_schema;
+    use crate::{
+        store::common::format_timestamp,
+        testing::{checkbox_item, item, option, section_item, with_id},
+    };
+
+    #[test_case(
+        ItemType::Text(TextType {
+            default: "none".to_owned(),
+            display: text_type::Display::Small as i32,
+            ..Default::default()
+        }),
+        json!({"type": "string", "default": "none", "title": "item name"})
+        ; "text"
+    )]
+    #[test_case(
+        ItemType::Checkbox(CheckboxType { default: true }),
+        json!({"type": "boolean", "default": true, "title": "item name"})
+        ; "checkbox"
+    )]
+    #[test_case(
+        ItemType::Select(SelectType {
+            default: 1,
+            display: select_type::Display::Dropdown as i32,
+            options: vec![option("Lead"), option("Follow")],
+        }),
+        json!({"type": "string", "enum": ["Lead", "Follow"], "default": "Follow", "title": "item name"})
+        ; "select"
+    )]
+    #[test_case(
+        ItemType::MultiSelect(MultiSelectType {
+            defaults: vec![0, 2],
+            display: multi_select_type::Display::Checkboxes as i32,
+            options: vec![option("Fri"), option("Sat"), option("Sun")],
+        }),
+        json!({
+            "type": "array",
+            "items": {"type": "string", "enum": ["Fri", "Sat", "Sun"]},
+            "uniqueItems": true,
+            "default": ["Fri", "Sun"],
+            "title": "item name",
+        })
+        ; "multi select"
+    )]
+    #[test_case(
+        ItemType::Group(GroupType {
+            items: vec![with_id("child", checkbox_item("child name"))],
+        }),
+        json!({
+            "type": "array",
+            "items": {
+                "type": "object",
+                "properties": {
+                    "child": {"type": "boolean", "default": false, "title": "child name"},
+                },
+                "required": [],
+                "additionalProperties": false,
+            },
+            "title": "item name",
+        })
+        ; "group"
+    )]
+    fn item_property(typ: ItemType, expected: serde_json::Value) {
+        let schema = RegistrationSchema {
+            event_id: "event".to_owned(),
+            items: vec![with_id("item", item("item name", typ))],
+        };
+
+        let json_schema = export_json_schema(&schema, Utc::now());
+
+        assert_eq!(json_schema["type"], "object");
+        assert_eq!(json_schema["additionalProperties"], false);
+        assert_eq!(json_schema["properties"]["item"], expected);
+    }
+
+    #[test]
+    fn sections_skipped() {
+        let schema = RegistrationSchema {
+            event_id: "event".to_owned(),
+            items: vec![with_id("section", section_item("section name", "Welcome"))],
+        };
+
+        let json_schema = export_json_schema(&schema, Utc::now());
+        assert_eq!(json_schema["properties"], json!({}));
+        assert_eq!(json_schema["required"], json!([]));
+    }
+
+    #[test]
+    fn required_items() {
+        let now = Utc::now();
+        let checkbox = |id: &str| with_id(id, checkbox_item(&format!("{} name", id)));
+
+        let required = RegistrationSchemaItem {
+            required: true,
+            ..checkbox("required")
+        };
+        let required_after_past = RegistrationSchemaItem {
+            required_after: format_timestamp(&(now - Duration::days(1))),
+            ..checkbox("past")
+        };
+        let required_after_future = RegistrationSchemaItem {
+            required_after: format_timestamp(&(now + Duration::days(1))),
+            ..checkbox("future")
+        };
+
+        let schema = RegistrationSchema {
+            event_id: "event".to_owned(),
+            items: vec![
+                required,
+                checkbox("optional"),
+                required_after_past,
+                required_after_future,
+            ],
+        };
+
+        let json_schema = export_json_schema(&schema, now);
+        assert_eq!(json_schema["required"], json!(["required", "past"]));
+        assert_eq!(
+            json_schema["properties"]
+                .as_object()
+                .unwrap()
+                .keys()
+                .collect::<Vec<_>>(),
+            vec!["future", "optional", "past", "required"]
+        );
+    }
+}
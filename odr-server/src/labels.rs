@@ -0,0 +1,157 @@
+use std::{collections::HashMap, iter};
+
+use common::proto::{
+    registration_schema_item_type::Type as ItemType, RegistrationSchema, RegistrationSchemaItem,
+    SelectOption,
+};
+
+use crate::receipt::resolve_value;
+
+/// A stored value along with the label a person would read for it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResolvedValue {
+    pub schema_item_id: String,
+    pub value: String,
+    pub label: String,
+    /// False when the value points at something the schema doesn't have, such as an option index
+    /// past the end or an unknown item. The label is then the value itself.
+    pub resolved: bool,
+}
+
+fn group_items(item: &RegistrationSchemaItem) -> &[RegistrationSchemaItem] {
+    match item.r#type.as_ref().and_then(|t| t.r#type.as_ref()) {
+        Some(ItemType::Group(group)) => &group.items,
+        _ => &[],
+    }
+}
+
+// Select values may be an option index, the option name itself or a free-form "other" answer.
+// Only an index with no option behind it can't be resolved.
+fn resolve_option(options: &[SelectOption], value: &str) -> Option<String> {
+    match value.parse::<usize>() {
+        Ok(idx) => options.get(idx).map(|option| option.name.clone()),
+        Err(_) => Some(value.to_owned()),
+    }
+}
+
+fn resolve(item: &RegistrationSchemaItem, value: &str) -> Option<String> {
+    let typ = item.r#type.as_ref().and_then(|t| t.r#type.as_ref());
+    match typ {
+        Some(ItemType::Select(_)) | Some(ItemType::MultiSelect(_)) if value.is_empty() => {
+            Some(String::new())
+        }
+        Some(ItemType::Select(select)) => resolve_option(&select.options, value),
+        Some(ItemType::MultiSelect(multi_select)) => value
+            .split(',')
+            .map(|v| resolve_option(&multi_select.options, v))
+            .collect::<Option<Vec<_>>>()
+            .map(|names| names.join(", ")),
+        _ => Some(resolve_value(typ, value)),
+    }
+}
+
+/// Labels many values against one schema, the way receipts and exports show them: select and
+/// multi-select indices become option names, checkboxes become "Yes" or "No", and anything else is
+/// passed through. Values are returned in the order they were given.
+pub fn resolve_values(
+    schema: &RegistrationSchema,
+    values: Vec<(String, String)>,
+) -> Vec<ResolvedValue> {
+    let items = schema
+        .items
+        .iter()
+        .flat_map(|item| iter::once(item).chain(group_items(item)))
+        .map(|item| (item.id.as_str(), item))
+        .collect::<HashMap<_, _>>();
+
+    values
+        .into_iter()
+        .map(|(schema_item_id, value)| {
+            let label = items
+                .get(schema_item_id.as_str())
+                .and_then(|item| resolve(item, &value));
+
+            ResolvedValue {
+                resolved: label.is_some(),
+                label: label.unwrap_or_else(|| value.clone()),
+                schema_item_id,
+                value,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use common::proto::RegistrationSchema;
+
+    use super::{resolve_values, ResolvedValue};
+    use crate::testing::{
+        checkbox_item, group_item, multi_select_item, select_item, text_item, with_id,
+    };
+
+    #[test]
+    fn resolve_mixed_values() {
+        let schema = RegistrationSchema {
+            event_id: "event".to_owned(),
+            items: vec![
+                with_id("role", select_item("role", &["Lead", "Follow"])),
+                with_id(
+                    "classes",
+                    multi_select_item("classes", &["Beginner", "Advanced", "Solo"]),
+                ),
+                with_id("waiver", checkbox_item("waiver")),
+                with_id(
+                    "partner",
+                    group_item(
+                        "partner",
+                        vec![with_id("partner_name", text_item("partner_name"))],
+                    ),
+                ),
+            ],
+        };
+
+        let values = [
+            ("role", "1"),
+            ("role", "Lead"),
+            ("role", "5"),
+            ("classes", "0,2"),
+            ("classes", "0,7"),
+            ("classes", ""),
+            ("waiver", "true"),
+            ("partner_name", "Sam"),
+            ("missing", "3"),
+        ];
+        let resolved = resolve_values(
+            &schema,
+            values
+                .iter()
+                .map(|(id, value)| (id.to_string(), value.to_string()))
+                .collect(),
+        );
+
+        let expected = [
+            ("role", "1", "Follow", true),
+            ("role", "Lead", "Lead", true),
+            ("role", "5", "5", false),
+            ("classes", "0,2", "Beginner, Solo", true),
+            ("classes", "0,7", "0,7", false),
+            ("classes", "", "", true),
+            ("waiver", "true", "Yes", true),
+            ("partner_name", "Sam", "Sam", true),
+            ("missing", "3", "3", false),
+        ];
+        assert_eq!(
+            resolved,
+            expected
+                .iter()
+                .map(|(id, value, label, ok)| ResolvedValue {
+                    schema_item_id: id.to_string(),
+                    value: value.to_string(),
+                    label: label.to_string(),
+                    resolved: *ok,
+                })
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -1,3 +1,13 @@
+pub mod csv_export;
+pub mod json_schema;
 pub mod keys;
+pub mod labels;
+pub mod metrics;
+pub mod notifier;
+pub mod receipt;
+pub mod request_id;
+pub mod seed;
 pub mod store;
+#[cfg(test)]
+pub mod testing;
 pub mod user;
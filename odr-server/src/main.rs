@@ -1,83 +1,277 @@
-use std::{env, sync::Arc};
+use std::{env, str::FromStr, sync::Arc, time::Duration};
 
 use api::{
-    authentication::Service as AuthenticationService, event::Service as EventService,
-    organization::Service as OrganizationService, registration::Service as RegistrationService,
-    registration_schema::Service as SchemaService, user::Service as UserService,
+    authentication::Service as AuthenticationService,
+    event::Service as EventService,
+    maintenance::Service as MaintenanceService,
+    metrics::Service as MetricsService,
+    organization::Service as OrganizationService,
+    public_registration::Service as PublicRegistrationService,
+    registration::Service as RegistrationService,
+    registration_schema::{SchemaCache, Service as SchemaService},
+    schema_template::Service as SchemaTemplateService,
+    user::Service as UserService,
 };
 use common::proto;
-use sqlx::SqlitePool;
+use metrics::{MetricsLayer, Registry};
+use notifier::SmtpNotifier;
+use request_id::RequestIdLayer;
 use store::{
-    event::SqliteStore as EventStore, keys::SqliteStore as KeyStore,
-    organization::SqliteStore as OrganizationStore, registration::SqliteStore as RegistrationStore,
-    registration_schema::SqliteStore as SchemaStore, user::SqliteStore as UserStore,
+    common::SlugPolicy,
+    event::SqliteStore as EventStore,
+    keys::SqliteStore as KeyStore,
+    organization::SqliteStore as OrganizationStore,
+    permission::SqliteStore as PermissionStore,
+    registration::SqliteStore as RegistrationStore,
+    registration_schema::{EnumPolicy, SqliteStore as SchemaStore},
+    schema_template::SqliteStore as SchemaTemplateStore,
+    user::SqliteStore as UserStore,
 };
 use thiserror::Error;
 use tonic::transport::{self, Server};
 
 pub mod api;
+pub mod csv_export;
+pub mod grpc_web;
+pub mod json_schema;
 pub mod keys;
+pub mod labels;
+pub mod metrics;
+pub mod notifier;
+pub mod receipt;
+pub mod request_id;
+pub mod seed;
 pub mod store;
+#[cfg(test)]
+pub mod testing;
 pub mod user;
 
+const BUSY_TIMEOUT_ENV: &str = "ODR_SQLITE_BUSY_TIMEOUT_MS";
+const MAX_CONNECTIONS_ENV: &str = "ODR_SQLITE_MAX_CONNECTIONS";
+const MIN_CONNECTIONS_ENV: &str = "ODR_SQLITE_MIN_CONNECTIONS";
+const ACQUIRE_TIMEOUT_ENV: &str = "ODR_SQLITE_ACQUIRE_TIMEOUT_MS";
+const ACCESS_TOKEN_TTL_ENV: &str = "ODR_ACCESS_TOKEN_TTL_SECONDS";
+const SMTP_URL_ENV: &str = "ODR_SMTP_URL";
+const SMTP_FROM_ENV: &str = "ODR_SMTP_FROM";
+const ALLOW_SEEDING_ENV: &str = "ODR_ALLOW_SEEDING";
+const MAX_VALUE_LENGTH_ENV: &str = "ODR_MAX_REGISTRATION_VALUE_LENGTH";
+const LENIENT_ENUMS_ENV: &str = "ODR_LENIENT_ENUMS";
+const REGENERATE_SLUGS_ENV: &str = "ODR_REGENERATE_SLUGS_ON_RENAME";
+const READ_REPLICA_URL_ENV: &str = "ODR_READ_REPLICA_URL";
+const GRPC_WEB_ENV: &str = "ODR_GRPC_WEB";
+const MAX_QUERY_RESULTS_ENV: &str = "ODR_MAX_QUERY_RESULTS";
+const MAX_SELECT_OPTIONS_ENV: &str = "ODR_MAX_SELECT_OPTIONS";
+
 fn db_url() -> String {
     format!("sqlite://{}/odr-sqlite.db", env::temp_dir().display())
 }
 
+fn env_var<T: FromStr>(name: &'static str, default: T) -> Result<T, ServerError> {
+    match env::var(name) {
+        Ok(value) => value
+            .parse()
+            .map_err(|_| ServerError::InvalidEnvVar(name, value)),
+        Err(_) => Ok(default),
+    }
+}
+
+fn env_millis(name: &'static str, default: Duration) -> Result<Duration, ServerError> {
+    env_var(name, default.as_millis() as u64).map(Duration::from_millis)
+}
+
+// Confirmation emails are only sent when an SMTP server is configured.
+fn notifier() -> Result<Option<SmtpNotifier>, Box<dyn std::error::Error>> {
+    let Ok(url) = env::var(SMTP_URL_ENV) else {
+        return Ok(None);
+    };
+    let from = env::var(SMTP_FROM_ENV).map_err(|_| ServerError::MissingEnvVar(SMTP_FROM_ENV))?;
+
+    Ok(Some(SmtpNotifier::new(&url, &from)?))
+}
+
+fn pool_config() -> Result<store::PoolConfig, ServerError> {
+    Ok(store::PoolConfig {
+        max_connections: env_var(MAX_CONNECTIONS_ENV, store::DEFAULT_MAX_CONNECTIONS)?,
+        min_connections: env_var(MIN_CONNECTIONS_ENV, store::DEFAULT_MIN_CONNECTIONS)?,
+        acquire_timeout: env_millis(ACQUIRE_TIMEOUT_ENV, store::DEFAULT_ACQUIRE_TIMEOUT)?,
+    })
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let db_url = db_url();
 
-    let db = Arc::new(SqlitePool::connect(&db_url).await?);
+    let busy_timeout = env_millis(BUSY_TIMEOUT_ENV, store::DEFAULT_BUSY_TIMEOUT)?;
+    let access_token_ttl = chrono::Duration::seconds(env_var(
+        ACCESS_TOKEN_TTL_ENV,
+        api::authentication::DEFAULT_ACCESS_TOKEN_TTL_SECONDS,
+    )? as i64);
+
+    let db = Arc::new(
+        store::pool_options(&pool_config()?)
+            .connect_with(store::connect_options(&db_url, busy_timeout)?)
+            .await?,
+    );
+
+    store::organization::adopt_orphaned_events(&db).await?;
+
+    // Reporting reads go to the replica when one is configured; otherwise they share the primary.
+    let read_db = match env::var(READ_REPLICA_URL_ENV) {
+        Ok(read_db_url) => Arc::new(
+            store::pool_options(&pool_config()?)
+                .connect_with(store::connect_options(&read_db_url, busy_timeout)?.read_only(true))
+                .await?,
+        ),
+        Err(_) => db.clone(),
+    };
 
-    let event_store = Arc::new(EventStore::new(db.clone()));
-    let schema_store = Arc::new(SchemaStore::new(db.clone()));
-    let registration_store = Arc::new(RegistrationStore::new(db.clone()));
-    let organization_store = Arc::new(OrganizationStore::new(db.clone()));
+    let slug_policy = if env_var(REGENERATE_SLUGS_ENV, false)? {
+        SlugPolicy::Regenerate
+    } else {
+        SlugPolicy::Stable
+    };
+    let event_store = Arc::new(EventStore::new(db.clone()).with_slug_policy(slug_policy));
+    let enum_policy = if env_var(LENIENT_ENUMS_ENV, false)? {
+        EnumPolicy::Lenient
+    } else {
+        EnumPolicy::Strict
+    };
+    let max_options = env_var(
+        MAX_SELECT_OPTIONS_ENV,
+        store::registration_schema::DEFAULT_MAX_OPTIONS,
+    )?;
+    let schema_store = Arc::new(
+        SchemaStore::new(db.clone())
+            .with_enum_policy(enum_policy)
+            .with_max_options(max_options),
+    );
+    let schema_template_store = Arc::new(SchemaTemplateStore::new(db.clone()));
+    let registration_store = Arc::new(
+        RegistrationStore::new(db.clone())
+            .with_read_pool(read_db)
+            .with_max_value_length(env_var(
+                MAX_VALUE_LENGTH_ENV,
+                store::registration::DEFAULT_MAX_VALUE_LENGTH,
+            )?),
+    );
+    let organization_store =
+        Arc::new(OrganizationStore::new(db.clone()).with_slug_policy(slug_policy));
     let user_store = Arc::new(UserStore::new(db.clone()));
     let key_store = Arc::new(KeyStore::new(db.clone()));
+    let permission_store = Arc::new(PermissionStore::new(db.clone()));
 
     let key_manager = Arc::new(keys::KeyManager::new(key_store));
 
-    let event_service =
-        proto::event_service_server::EventServiceServer::new(EventService::new(event_store));
+    let metrics_registry = Arc::new(Registry::new());
+
+    let max_query_results = env_var(MAX_QUERY_RESULTS_ENV, api::DEFAULT_MAX_QUERY_RESULTS)?;
+
+    // Every service that writes schemas evicts the schema service's cached queries.
+    let schema_cache = Arc::new(SchemaCache::default());
+
+    let event_service = proto::event_service_server::EventServiceServer::new(
+        EventService::new(
+            event_store.clone(),
+            schema_store.clone(),
+            schema_template_store.clone(),
+        )
+        .with_max_query_results(max_query_results)
+        .with_schema_cache(schema_cache.clone()),
+    );
 
     let schema_service =
         proto::registration_schema_service_server::RegistrationSchemaServiceServer::new(
-            SchemaService::new(schema_store),
+            SchemaService::new(
+                schema_store.clone(),
+                schema_template_store.clone(),
+                key_manager.clone(),
+            )
+            .with_max_query_results(max_query_results)
+            .with_max_options(max_options)
+            .with_schema_cache(schema_cache.clone()),
+        );
+
+    let schema_template_service =
+        proto::schema_template_service_server::SchemaTemplateServiceServer::new(
+            SchemaTemplateService::new(schema_template_store),
+        );
+
+    let public_registration_service =
+        proto::public_registration_service_server::PublicRegistrationServiceServer::new(
+            PublicRegistrationService::new(
+                key_manager.clone(),
+                permission_store.clone(),
+                event_store.clone(),
+                schema_store.clone(),
+                registration_store.clone(),
+                Arc::new(notifier()?),
+            ),
         );
 
     let registration_service = proto::registration_service_server::RegistrationServiceServer::new(
-        RegistrationService::new(registration_store),
+        RegistrationService::new(
+            registration_store,
+            event_store.clone(),
+            schema_store.clone(),
+            env_var(ALLOW_SEEDING_ENV, false)?,
+        )
+        .with_max_query_results(max_query_results),
     );
 
     let organization_service = proto::organization_service_server::OrganizationServiceServer::new(
-        OrganizationService::new(organization_store),
+        OrganizationService::new(
+            organization_store,
+            key_manager.clone(),
+            permission_store.clone(),
+        )
+        .with_schema_cache(schema_cache),
     );
 
     let authentication_service =
         proto::authentication_service_server::AuthenticationServiceServer::new(
-            AuthenticationService::new(key_manager, user_store.clone()),
+            AuthenticationService::new(
+                key_manager.clone(),
+                user_store.clone(),
+                permission_store.clone(),
+                access_token_ttl,
+            ),
         );
 
     let user_service =
         proto::user_service_server::UserServiceServer::new(UserService::new(user_store));
 
+    let metrics_service = proto::metrics_service_server::MetricsServiceServer::new(
+        MetricsService::new(metrics_registry.clone()),
+    );
+
+    let maintenance_service = proto::maintenance_service_server::MaintenanceServiceServer::new(
+        MaintenanceService::new(db.clone(), key_manager, permission_store),
+    );
+
     let reflection_service = tonic_reflection::server::Builder::configure()
         .register_encoded_file_descriptor_set(proto::FILE_DESCRIPTOR_SET)
         .build()?;
 
     let grpc_addr = "[::1]:50051".parse()?;
 
+    // Browsers, the admin included, can only reach the services over gRPC-Web, which needs HTTP/1.
+    let grpc_web = env_var(GRPC_WEB_ENV, true)?;
+
     Server::builder()
-        .accept_http1(true)
-        .add_service(tonic_web::enable(event_service))
-        .add_service(tonic_web::enable(schema_service))
-        .add_service(tonic_web::enable(registration_service))
-        .add_service(tonic_web::enable(organization_service))
-        .add_service(tonic_web::enable(user_service))
-        .add_service(tonic_web::enable(authentication_service))
-        .add_service(tonic_web::enable(reflection_service))
+        .accept_http1(grpc_web)
+        .layer(RequestIdLayer::new())
+        .layer(MetricsLayer::new(metrics_registry))
+        .add_service(grpc_web::enable(event_service, grpc_web))
+        .add_service(grpc_web::enable(schema_service, grpc_web))
+        .add_service(grpc_web::enable(schema_template_service, grpc_web))
+        .add_service(grpc_web::enable(registration_service, grpc_web))
+        .add_service(grpc_web::enable(public_registration_service, grpc_web))
+        .add_service(grpc_web::enable(organization_service, grpc_web))
+        .add_service(grpc_web::enable(user_service, grpc_web))
+        .add_service(grpc_web::enable(authentication_service, grpc_web))
+        .add_service(grpc_web::enable(metrics_service, grpc_web))
+        .add_service(grpc_web::enable(maintenance_service, grpc_web))
+        .add_service(grpc_web::enable(reflection_service, grpc_web))
         .serve(grpc_addr)
         .await
         .map_err(|e| ServerError::GrpcError(e))?;
@@ -89,4 +283,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 enum ServerError {
     #[error("failed to start grpc server: {0}")]
     GrpcError(transport::Error),
+
+    #[error("invalid {0} value {1:?}")]
+    InvalidEnvVar(&'static str, String),
+
+    #[error("{0} must be set")]
+    MissingEnvVar(&'static str),
 }
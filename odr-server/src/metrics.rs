@@ -0,0 +1,170 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use tower::{Layer, Service};
+
+#[derive(Default)]
+struct MethodMetrics {
+    requests: AtomicU64,
+    errors: AtomicU64,
+    duration_micros: AtomicU64,
+}
+
+/// Process-wide counters, keyed by gRPC method path (e.g. `/proto.EventService/QueryEvents`).
+#[derive(Default)]
+pub struct Registry {
+    methods: RwLock<BTreeMap<String, Arc<MethodMetrics>>>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn method(&self, method: &str) -> Arc<MethodMetrics> {
+        if let Some(metrics) = self.methods.read().unwrap().get(method) {
+            return metrics.clone();
+        }
+
+        self.methods
+            .write()
+            .unwrap()
+            .entry(method.to_owned())
+            .or_default()
+            .clone()
+    }
+
+    pub fn record_request(&self, method: &str, duration: Duration, failed: bool) {
+        let metrics = self.method(method);
+        metrics.requests.fetch_add(1, Ordering::Relaxed);
+        if failed {
+            metrics.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        metrics
+            .duration_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    pub fn requests(&self, method: &str) -> u64 {
+        self.methods
+            .read()
+            .unwrap()
+            .get(method)
+            .map(|metrics| metrics.requests.load(Ordering::Relaxed))
+            .unwrap_or(0)
+    }
+
+    pub fn errors(&self, method: &str) -> u64 {
+        self.methods
+            .read()
+            .unwrap()
+            .get(method)
+            .map(|metrics| metrics.errors.load(Ordering::Relaxed))
+            .unwrap_or(0)
+    }
+
+    /// Renders all counters in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let methods = self.methods.read().unwrap();
+        let mut text = String::new();
+
+        let mut family = |name: &str, value: &dyn Fn(&MethodMetrics) -> String| {
+            writeln!(text, "# TYPE {} counter", name).unwrap();
+            for (method, metrics) in methods.iter() {
+                writeln!(text, "{}{{method=\"{}\"}} {}", name, method, value(metrics)).unwrap();
+            }
+        };
+
+        family("odr_requests_total", &|m| {
+            m.requests.load(Ordering::Relaxed).to_string()
+        });
+        family("odr_request_errors_total", &|m| {
+            m.errors.load(Ordering::Relaxed).to_string()
+        });
+        family("odr_request_duration_seconds_sum", &|m| {
+            (m.duration_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0).to_string()
+        });
+        family("odr_request_duration_seconds_count", &|m| {
+            m.requests.load(Ordering::Relaxed).to_string()
+        });
+
+        text
+    }
+}
+
+/// Tower layer recording a request, its duration, and whether it failed for every gRPC call.
+#[derive(Clone)]
+pub struct MetricsLayer {
+    registry: Arc<Registry>,
+}
+
+impl MetricsLayer {
+    pub fn new(registry: Arc<Registry>) -> Self {
+        MetricsLayer { registry }
+    }
+}
+
+impl<S> Layer<S> for MetricsLayer {
+    type Service = Metered<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Metered {
+            inner,
+            registry: self.registry.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Metered<S> {
+    inner: S,
+    registry: Arc<Registry>,
+}
+
+impl<S, ReqBody, ResBody> Service<http::Request<ReqBody>> for Metered<S>
+where
+    S: Service<http::Request<ReqBody>, Response = http::Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<ReqBody>) -> Self::Future {
+        let method = request.uri().path().to_owned();
+        let registry = self.registry.clone();
+        let start = Instant::now();
+        let future = self.inner.call(request);
+
+        Box::pin(async move {
+            let response = future.await;
+
+            // Failed calls are returned "trailers only", so the status is already in the headers.
+            // Successful calls send their status in trailers, which we treat as success.
+            let failed = match &response {
+                Ok(response) => response
+                    .headers()
+                    .get("grpc-status")
+                    .is_some_and(|status| status != "0"),
+                Err(_) => true,
+            };
+            registry.record_request(&method, start.elapsed(), failed);
+
+            response
+        })
+    }
+}
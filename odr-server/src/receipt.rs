@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+
+use common::proto::{
+    registration_schema_item_type::Type as ItemType, Event, Registration, RegistrationSchema,
+    RegistrationSchemaItem, SelectOption,
+};
+
+use crate::store::registration::parse_tags;
+
+fn escape_html(s: &str) -> String {
+    s.chars()
+        .fold(String::with_capacity(s.len()), |mut out, c| {
+            match c {
+                '&' => out.push_str("&amp;"),
+                '<' => out.push_str("&lt;"),
+                '>' => out.push_str("&gt;"),
+                '"' => out.push_str("&quot;"),
+                '\'' => out.push_str("&#39;"),
+                c => out.push(c),
+            };
+            out
+        })
+}
+
+// Select values may be stored either as an option index or as the option name itself.
+fn resolve_option(options: &[SelectOption], value: &str) -> String {
+    value
+        .parse::<usize>()
+        .ok()
+        .and_then(|idx| options.get(idx))
+        .map(|option| option.name.clone())
+        .unwrap_or_else(|| value.to_owned())
+}
+
+pub(crate) fn resolve_value(typ: Option<&ItemType>, value: &str) -> String {
+    match typ {
+        Some(ItemType::Checkbox(_)) => match value {
+            "true" | "1" => "Yes".to_owned(),
+            "false" | "0" => "No".to_owned(),
+            _ => value.to_owned(),
+        },
+        Some(ItemType::Select(select)) => resolve_option(&select.options, value),
+        Some(ItemType::MultiSelect(select)) => itertools::Itertools::intersperse(
+            value.split(',').map(|v| resolve_option(&select.options, v)),
+            ", ".to_owned(),
+        )
+        .collect(),
+        Some(ItemType::Tags(_)) => match parse_tags(value) {
+            Some(tags) => tags.join(", "),
+            None => value.to_owned(),
+        },
+        _ => value.to_owned(),
+    }
+}
+
+pub fn render_registration_receipt(
+    event: &Event,
+    schema: &RegistrationSchema,
+    registration: &Registration,
+) -> String {
+    let values = registration
+        .items
+        .iter()
+        .map(|item| {
+            (
+                (item.schema_item_id.as_str(), item.instance),
+                item.value.as_str(),
+            )
+        })
+        .collect::<HashMap<_, _>>();
+
+    let row = |item: &RegistrationSchemaItem, instance: u32, label: &str| {
+        let typ = item.r#type.as_ref().and_then(|t| t.r#type.as_ref());
+        if let Some(ItemType::Section(_)) = typ {
+            return None;
+        }
+
+        let value = values.get(&(item.id.as_str(), instance))?;
+
+        Some(format!(
+            "<tr><th>{}</th><td>{}</td></tr>",
+            escape_html(label),
+            escape_html(&resolve_value(typ, value)),
+        ))
+    };
+
+    // Each repeat of a group is listed in turn, numbered from one.
+    let rows = schema
+        .items
+        .iter()
+        .flat_map(
+            |item| match item.r#type.as_ref().and_then(|t| t.r#type.as_ref()) {
+                Some(ItemType::Group(group)) => {
+                    let mut instances = registration
+                        .items
+                        .iter()
+                        .filter(|value| {
+                            group
+                                .items
+                                .iter()
+                                .any(|group_item| group_item.id == value.schema_item_id)
+                        })
+                        .map(|value| value.instance)
+                        .collect::<Vec<_>>();
+                    instances.sort_unstable();
+                    instances.dedup();
+
+                    instances
+                        .into_iter()
+                        .enumerate()
+                        .flat_map(|(number, instance)| {
+                            group.items.iter().filter_map(move |group_item| {
+                                row(
+                                    group_item,
+                                    instance,
+                                    &format!("{} {}: {}", item.name, number + 1, group_item.name),
+                                )
+                            })
+                        })
+                        .collect::<Vec<_>>()
+                }
+                _ => row(item, 0, &item.name).into_iter().collect(),
+            },
+        )
+        .collect::<String>();
+
+    let event_name = escape_html(&event.name);
+
+    format!(
+        "<!DOCTYPE html>\
+        <html>\
+        <head><meta charset=\"utf-8\"><title>Registration Receipt - {0}</title></head>\
+        <body>\
+        <h1>{0}</h1>\
+        <p>Confirmation code: <strong>{1}</strong></p>\
+        <table>{2}</table>\
+        </body>\
+        </html>",
+        event_name,
+        escape_html(&registration.id),
+        rows,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use common::proto::{registration, Event, Registration, RegistrationItem, RegistrationSchema};
+
+    use super::render_registration_receipt;
+    use crate::testing::{checkbox_item, multi_select_item, select_item, with_id};
+
+    #[test]
+    fn render_receipt() {
+        let event = Event {
+            id: "event".to_owned(),
+            organization_id: "org".to_owned(),
+            name: "Swing & Blues Weekend".to_owned(),
+            starts_at: "".to_owned(),
+            ends_at: "".to_owned(),
+            registration_closes_at: "".to_owned(),
+            require_account: false,
+            slug: String::default(),
+            dedup_item_id: String::default(),
+            block_duplicates: false,
+            registration_opens_at: String::default(),
+        };
+
+        let schema = RegistrationSchema {
+            event_id: event.id.clone(),
+            items: vec![
+                with_id(
+                    "pass",
+                    select_item("Pass", &["Full Weekend", "Saturday Only"]),
+                ),
+                with_id(
+                    "classes",
+                    multi_select_item("Classes", &["Lindy", "Balboa", "Blues"]),
+                ),
+                with_id("housing", checkbox_item("Housing")),
+            ],
+        };
+
+        let registration = Registration {
+            id: "ABC123".to_owned(),
+            event_id: event.id.clone(),
+            items: vec![
+                RegistrationItem {
+                    schema_item_id: "pass".to_owned(),
+                    value: "1".to_owned(),
+                    instance: 0,
+                },
+                RegistrationItem {
+                    schema_item_id: "classes".to_owned(),
+                    value: "0,Blues".to_owned(),
+                    instance: 0,
+                },
+                RegistrationItem {
+                    schema_item_id: "housing".to_owned(),
+                    value: "true".to_owned(),
+                    instance: 0,
+                },
+            ],
+            status: registration::Status::Confirmed as i32,
+            user_id: String::default(),
+        };
+
+        let html = render_registration_receipt(&event, &schema, &registration);
+
+        assert!(html.contains("<h1>Swing &amp; Blues Weekend</h1>"));
+        assert!(html.contains("<strong>ABC123</strong>"));
+        assert!(html.contains("<tr><th>Pass</th><td>Saturday Only</td></tr>"));
+        assert!(html.contains("<tr><th>Classes</th><td>Lindy, Blues</td></tr>"));
+        assert!(html.contains("<tr><th>Housing</th><td>Yes</td></tr>"));
+    }
+}
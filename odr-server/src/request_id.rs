@@ -0,0 +1,144 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use http::HeaderValue;
+use tower::{Layer, Service};
+use uuid::Uuid;
+
+pub const HEADER: &str = "x-request-id";
+
+// Longer client-provided ids are replaced rather than copied into every log line.
+const MAX_LEN: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// The id of the request being handled by the current task, if there is one.
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Tower layer giving every call a request id, for correlating support requests with server
+/// logs. The id is taken from the `x-request-id` header when the client sends one and generated
+/// otherwise. Handlers can read it with [`current`], and it is echoed back in the response
+/// headers.
+#[derive(Clone, Default)]
+pub struct RequestIdLayer;
+
+impl RequestIdLayer {
+    pub fn new() -> Self {
+        RequestIdLayer
+    }
+}
+
+impl<S> Layer<S> for RequestIdLayer {
+    type Service = RequestId<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequestId { inner }
+    }
+}
+
+#[derive(Clone)]
+pub struct RequestId<S> {
+    inner: S,
+}
+
+impl<S, ReqBody, ResBody> Service<http::Request<ReqBody>> for RequestId<S>
+where
+    S: Service<http::Request<ReqBody>, Response = http::Response<ResBody>>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: http::Request<ReqBody>) -> Self::Future {
+        let provided = request
+            .headers()
+            .get(HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|id| !id.is_empty() && id.len() <= MAX_LEN)
+            .map(str::to_owned);
+
+        let id = provided.unwrap_or_else(|| Uuid::now_v7().to_string());
+        // The id is either a valid header value already or a generated uuid.
+        let header = HeaderValue::from_str(&id).unwrap();
+
+        // Overwrite the request header too, so handlers reading metadata see the same id.
+        request.headers_mut().insert(HEADER, header.clone());
+
+        // Services may do some of their work when called rather than when polled.
+        let future = REQUEST_ID.sync_scope(id.clone(), || self.inner.call(request));
+        let future = REQUEST_ID.scope(id, future);
+
+        Box::pin(async move {
+            let mut response = future.await?;
+            response.headers_mut().insert(HEADER, header);
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use tower::{service_fn, Layer, ServiceExt};
+    use uuid::Uuid;
+
+    use super::{current, RequestIdLayer, HEADER};
+
+    async fn call(request_id: Option<&str>) -> (String, String) {
+        let service = RequestIdLayer::new().layer(service_fn(|_: http::Request<()>| async {
+            Ok::<_, Infallible>(http::Response::new(current().unwrap()))
+        }));
+
+        let mut request = http::Request::builder().uri("/proto.EventService/QueryEvents");
+        if let Some(request_id) = request_id {
+            request = request.header(HEADER, request_id);
+        }
+
+        let response = service.oneshot(request.body(()).unwrap()).await.unwrap();
+        let header = response.headers()[HEADER].to_str().unwrap().to_owned();
+
+        (header, response.into_body())
+    }
+
+    #[tokio::test]
+    async fn provided_request_id() {
+        let (header, seen_by_handler) = call(Some("support-1234")).await;
+        assert_eq!(header, "support-1234");
+        assert_eq!(seen_by_handler, "support-1234");
+    }
+
+    #[tokio::test]
+    async fn generated_request_id() {
+        let (header, seen_by_handler) = call(None).await;
+        assert!(Uuid::parse_str(&header).is_ok(), "{}", header);
+        assert_eq!(seen_by_handler, header);
+
+        let (other, _) = call(None).await;
+        assert_ne!(other, header);
+    }
+
+    #[tokio::test]
+    async fn oversized_request_id_replaced() {
+        let oversized = "a".repeat(200);
+        let (header, _) = call(Some(&oversized)).await;
+        assert!(Uuid::parse_str(&header).is_ok(), "{}", header);
+    }
+
+    #[test]
+    fn no_current_outside_request() {
+        assert_eq!(current(), None);
+    }
+}
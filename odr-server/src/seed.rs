@@ -0,0 +1,304 @@
+use chrono::{DateTime, Utc};
+use common::proto::{
+    registration, registration_schema_item_type::Type as ItemType, Registration, RegistrationItem,
+    RegistrationSchema, RegistrationSchemaItem, SelectOption,
+};
+use rand::{seq::SliceRandom, Rng};
+
+use crate::store::registration::is_required;
+
+const FIRST_NAMES: &[&str] = &[
+    "Alex", "Sam", "Jordan", "Taylor", "Morgan", "Casey", "Riley", "Jamie", "Avery", "Quinn",
+];
+
+const LAST_NAMES: &[&str] = &[
+    "Rivera", "Nguyen", "Smith", "Okafor", "Larsen", "Kowalski", "Tanaka", "Silva", "Haddad",
+    "Murphy",
+];
+
+// Optional items are left blank this often, so seeded data has some gaps like real data does.
+const OPTIONAL_SKIP_PROBABILITY: f64 = 0.2;
+
+const MAX_GROUP_INSTANCES: u32 = 2;
+
+const TAGS: &[&str] = &[
+    "Vegetarian",
+    "Vegan",
+    "Gluten free",
+    "Nut allergy",
+    "Lactose free",
+];
+
+fn random_text<R: Rng>(rng: &mut R) -> String {
+    format!(
+        "{} {}",
+        FIRST_NAMES.choose(rng).unwrap(),
+        LAST_NAMES.choose(rng).unwrap()
+    )
+}
+
+// Multi-select values are comma separated, so an option whose name has a comma in it is picked by
+// index instead.
+fn option_value(idx: usize, option: &SelectOption) -> String {
+    if option.name.contains(',') {
+        idx.to_string()
+    } else {
+        option.name.clone()
+    }
+}
+
+fn random_value<R: Rng>(rng: &mut R, typ: &ItemType) -> Option<String> {
+    match typ {
+        ItemType::Text(_) => Some(random_text(rng)),
+        ItemType::Checkbox(_) => Some(rng.gen_bool(0.5).to_string()),
+        // With no options to pick from, there's no valid answer, so the item is left blank.
+        ItemType::Select(select) => select.options.choose(rng).map(|option| option.name.clone()),
+        ItemType::MultiSelect(select) => {
+            if select.options.is_empty() {
+                return None;
+            }
+
+            let count = rng.gen_range(1..=select.options.len());
+            let mut picked = rand::seq::index::sample(rng, select.options.len(), count).into_vec();
+            picked.sort_unstable();
+
+            Some(itertools::join(
+                picked
+                    .into_iter()
+                    .map(|idx| option_value(idx, &select.options[idx])),
+                ",",
+            ))
+        }
+        ItemType::Tags(tags) => {
+            let max = match tags.max_tags {
+                0 => TAGS.len(),
+                max => (max as usize).min(TAGS.len()),
+            };
+
+            let count = rng.gen_range(1..=max);
+            let picked = TAGS.choose_multiple(rng, count).collect::<Vec<_>>();
+
+            Some(serde_json::to_string(&picked).unwrap())
+        }
+        ItemType::Section(_) | ItemType::Group(_) => None,
+    }
+}
+
+// A group that isn't skipped is filled in once or twice, with every item in it answered.
+fn random_items<R: Rng>(
+    rng: &mut R,
+    item: &RegistrationSchemaItem,
+    now: DateTime<Utc>,
+) -> Vec<RegistrationItem> {
+    let Some(typ) = item.r#type.as_ref().and_then(|t| t.r#type.as_ref()) else {
+        return Vec::new();
+    };
+
+    if !is_required(item, now) && rng.gen_bool(OPTIONAL_SKIP_PROBABILITY) {
+        return Vec::new();
+    }
+
+    let ItemType::Group(group) = typ else {
+        return random_value(rng, typ)
+            .map(|value| RegistrationItem {
+                schema_item_id: item.id.clone(),
+                value,
+                instance: 0,
+            })
+            .into_iter()
+            .collect();
+    };
+
+    let mut items = Vec::new();
+    for instance in 0..rng.gen_range(1..=MAX_GROUP_INSTANCES) {
+        for group_item in group.items.iter() {
+            let Some(typ) = group_item.r#type.as_ref().and_then(|t| t.r#type.as_ref()) else {
+                continue;
+            };
+
+            if let Some(value) = random_value(rng, typ) {
+                items.push(RegistrationItem {
+                    schema_item_id: group_item.id.clone(),
+                    value,
+                    instance,
+                });
+            }
+        }
+    }
+
+    items
+}
+
+/// Generates `count` new confirmed registrations for the schema's event, each with a random
+/// answer to every required item and most optional ones. The answers always pass
+/// [`validate_items`](crate::store::registration::validate_items), unless a required select has
+/// no options to pick from.
+pub fn generate_registrations<R: Rng>(
+    rng: &mut R,
+    schema: &RegistrationSchema,
+    count: usize,
+    now: DateTime<Utc>,
+) -> Vec<Registration> {
+    (0..count)
+        .map(|_| Registration {
+            id: String::default(),
+            event_id: schema.event_id.clone(),
+            items: schema
+                .items
+                .iter()
+                .flat_map(|item| random_items(rng, item, now))
+                .collect(),
+            status: registration::Status::Confirmed as i32,
+            user_id: String::default(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use common::proto::{
+        registration_schema_item_type::Type as ItemType, CheckboxType, GroupType, MultiSelectType,
+        RegistrationSchema, RegistrationSchemaItem, RegistrationSchemaItemType, SectionType,
+        SelectOption, SelectType, TextType,
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+    use test_case::test_case;
+
+    use crate::store::registration::validate_items;
+
+    use super::generate_registrations;
+
+    fn options(names: &[&str]) -> Vec<SelectOption> {
+        names
+            .iter()
+            .map(|name| SelectOption {
+                id: name.to_string(),
+                name: name.to_string(),
+                product_id: String::default(),
+                archived: false,
+            })
+            .collect()
+    }
+
+    enum SeedTest {
+        Text,
+        Checkbox,
+        Select,
+        MultiSelect,
+        MultiSelectCommaOption,
+        Section,
+        Group,
+    }
+
+    #[test_case(SeedTest::Text ; "text")]
+    #[test_case(SeedTest::Checkbox ; "checkbox")]
+    #[test_case(SeedTest::Select ; "select")]
+    #[test_case(SeedTest::MultiSelect ; "multi select")]
+    #[test_case(SeedTest::MultiSelectCommaOption ; "multi select comma option")]
+    #[test_case(SeedTest::Section ; "section")]
+    #[test_case(SeedTest::Group ; "group")]
+    fn seeded_registrations_validate(test_name: SeedTest) {
+        let typ = match test_name {
+            SeedTest::Text => ItemType::Text(TextType {
+                default: String::default(),
+                display: 0,
+                ..Default::default()
+            }),
+            SeedTest::Checkbox => ItemType::Checkbox(CheckboxType { default: false }),
+            SeedTest::Select => ItemType::Select(SelectType {
+                default: 0,
+                display: 0,
+                options: options(&["Leader", "Follower", "Switch"]),
+            }),
+            SeedTest::MultiSelect => ItemType::MultiSelect(MultiSelectType {
+                defaults: Vec::new(),
+                display: 0,
+                options: options(&["Lindy", "Balboa", "Blues"]),
+            }),
+            SeedTest::MultiSelectCommaOption => ItemType::MultiSelect(MultiSelectType {
+                defaults: Vec::new(),
+                display: 0,
+                options: options(&["Lindy", "9,000 feet", "Blues"]),
+            }),
+            SeedTest::Section => ItemType::Section(SectionType {
+                body: String::default(),
+            }),
+            SeedTest::Group => ItemType::Group(GroupType {
+                items: vec![RegistrationSchemaItem {
+                    id: "group item".to_owned(),
+                    name: "group item".to_owned(),
+                    r#type: Some(RegistrationSchemaItemType {
+                        r#type: Some(ItemType::Checkbox(CheckboxType { default: false })),
+                    }),
+                    required: true,
+                    required_after: String::default(),
+                    blank_on_copy: false,
+                    display_idx: None,
+                }],
+            }),
+        };
+
+        let item = |id: &str, required: bool| {
+            // Item ids are unique within a schema, including those inside groups.
+            let mut typ = typ.clone();
+            if let ItemType::Group(group) = &mut typ {
+                for group_item in group.items.iter_mut() {
+                    group_item.id = format!("{} {}", id, group_item.id);
+                }
+            }
+
+            RegistrationSchemaItem {
+                id: id.to_owned(),
+                name: id.to_owned(),
+                r#type: Some(RegistrationSchemaItemType { r#type: Some(typ) }),
+                required,
+                required_after: String::default(),
+                blank_on_copy: false,
+                display_idx: None,
+            }
+        };
+
+        let schema = RegistrationSchema {
+            event_id: "event".to_owned(),
+            items: vec![item("required", true), item("optional", false)],
+        };
+
+        let now = Utc::now();
+        let mut rng = StdRng::seed_from_u64(0);
+        let registrations = generate_registrations(&mut rng, &schema, 100, now);
+
+        assert_eq!(registrations.len(), 100);
+        for registration in registrations {
+            assert_eq!(registration.event_id, "event");
+            validate_items(&schema, &registration.items, now).unwrap();
+        }
+    }
+
+    #[test]
+    fn select_without_options_left_blank() {
+        let schema = RegistrationSchema {
+            event_id: "event".to_owned(),
+            items: vec![RegistrationSchemaItem {
+                id: "role".to_owned(),
+                name: "role".to_owned(),
+                r#type: Some(RegistrationSchemaItemType {
+                    r#type: Some(ItemType::Select(SelectType {
+                        default: 0,
+                        display: 0,
+                        options: Vec::new(),
+                    })),
+                }),
+                required: true,
+                required_after: String::default(),
+                blank_on_copy: false,
+                display_idx: None,
+            }],
+        };
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let registrations = generate_registrations(&mut rng, &schema, 10, Utc::now());
+
+        assert!(registrations.iter().all(|r| r.items.is_empty()));
+    }
+}
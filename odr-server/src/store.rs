@@ -1,39 +1,214 @@
-mod common;
+use std::{str::FromStr, time::Duration};
+
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions};
+
+pub mod common;
 pub mod event;
+pub mod ids;
+pub mod integrity;
 pub mod keys;
 pub mod organization;
+pub mod permission;
 pub mod registration;
 pub mod registration_schema;
+pub mod schema_template;
 pub mod user;
 
+/// Store errors. Their messages are returned to API clients, so errors from the database are
+/// only described in general terms; the underlying sqlx error is available through `source()`.
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("id {0} does not exist")]
     IdDoesNotExist(String),
 
-    #[error("error inserting new event into data store: {0}")]
+    #[error("slug {0} does not exist")]
+    SlugDoesNotExist(String),
+
+    #[error("unable to save to the data store")]
     InsertionError(#[source] sqlx::Error),
 
-    #[error("error fetching event from database: {0}")]
+    #[error("unable to read from the data store")]
     FetchError(#[source] sqlx::Error),
 
-    #[error("error deleting event from database: {0}")]
+    #[error("unable to delete from the data store")]
     DeleteError(#[source] sqlx::Error),
 
-    #[error("error checking event existance in database: {0}")]
+    #[error("unable to check whether ids exist in the data store")]
     CheckExistsError(#[source] sqlx::Error),
 
-    #[error("error updating event: {0}")]
+    #[error("unable to update the data store")]
     UpdateError(#[source] sqlx::Error),
 
-    #[error("transaction failed to commit: {0}")]
+    #[error("unable to commit data store transaction")]
     TransactionFailed(#[source] sqlx::Error),
 
-    #[error("transaction failed to start: {0}")]
+    #[error("unable to start data store transaction")]
     TransactionStartError(#[source] sqlx::Error),
 
-    #[error("unable to parse column {0}")]
+    #[error("unable to parse stored column {0}")]
     ColumnParseError(&'static str),
+
+    #[error("unknown value {value:?} stored in column {column}")]
+    UnknownEnumValue { column: &'static str, value: String },
+
+    #[error("invalid event dates: starts at {0:?}, ends at {1:?}")]
+    InvalidEventDates(String, String),
+
+    #[error("select items may have at most {max} options")]
+    TooManyOptions { max: usize },
+
+    #[error("registration item {0} has no compatible item in the target schema")]
+    IncompatibleSchema(String),
+
+    #[error("option name {name} is used more than once in item {item_id:?}")]
+    DuplicateOptionName { item_id: String, name: String },
+
+    #[error("invalid timestamp {0:?}")]
+    InvalidTimestamp(String),
+
+    #[error("registration for event {0} is not open yet")]
+    RegistrationNotOpen(String),
+
+    #[error("registration for event {0} is closed")]
+    RegistrationClosed(String),
+
+    #[error("registration for event {0} requires an account")]
+    AccountRequired(String),
+
+    #[error("registration matches existing registration {existing_registration_id}")]
+    DuplicateRegistration { existing_registration_id: String },
+
+    #[error("update mask must name at least one field")]
+    EmptyUpdateMask,
+
+    #[error("more than one item is named {name:?}")]
+    DuplicateItemName { name: String },
+
+    #[error("item {item_id:?} has settings that don't match its type")]
+    InconsistentItemType { item_id: String },
+
+    #[error("registration {drop_id} can't be merged into registration {keep_id}")]
+    InvalidMerge { keep_id: String, drop_id: String },
+
+    #[error("value for item {item_id:?} is longer than {max} characters")]
+    ValueTooLong { item_id: String, max: usize },
+
+    #[error("invalid value {value:?} for item {item_id:?}")]
+    InvalidRegistrationValue { item_id: String, value: String },
+
+    #[error("schema for event {0} is locked because it has registrations")]
+    SchemaLocked(String),
+
+    #[error("changing the type of item {item_id:?} would invalidate its registration values")]
+    TypeChangeUnsafe { item_id: String },
+
+    #[error("event {0} can't change organization through upsert; use move_event")]
+    UseMoveEvent(String),
+
+    #[error("unknown registration status {0}")]
+    InvalidStatus(i32),
+
+    #[error("invalid registration items: {}", itertools::join(.0, ", "))]
+    RegistrationValidation(Vec<registration::FieldError>),
+}
+
+pub type StoreResult<T> = Result<T, Error>;
+
+/// What an upsert did with one entity: `created` is set when it was inserted rather than updated.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Outcome {
+    pub id: String,
+    pub created: bool,
+}
+
+/// Maps a database error onto the store error for the operation that failed, so call sites name
+/// the operation instead of picking an error variant by hand.
+pub trait IntoStoreError<T> {
+    fn fetch_err(self) -> StoreResult<T>;
+    fn insert_err(self) -> StoreResult<T>;
+    fn update_err(self) -> StoreResult<T>;
+    fn delete_err(self) -> StoreResult<T>;
+    fn check_exists_err(self) -> StoreResult<T>;
+    fn begin_err(self) -> StoreResult<T>;
+    fn commit_err(self) -> StoreResult<T>;
+}
+
+impl<T> IntoStoreError<T> for Result<T, sqlx::Error> {
+    fn fetch_err(self) -> StoreResult<T> {
+        self.map_err(Error::FetchError)
+    }
+
+    fn insert_err(self) -> StoreResult<T> {
+        self.map_err(Error::InsertionError)
+    }
+
+    fn update_err(self) -> StoreResult<T> {
+        self.map_err(Error::UpdateError)
+    }
+
+    fn delete_err(self) -> StoreResult<T> {
+        self.map_err(Error::DeleteError)
+    }
+
+    fn check_exists_err(self) -> StoreResult<T> {
+        self.map_err(Error::CheckExistsError)
+    }
+
+    fn begin_err(self) -> StoreResult<T> {
+        self.map_err(Error::TransactionStartError)
+    }
+
+    fn commit_err(self) -> StoreResult<T> {
+        self.map_err(Error::TransactionFailed)
+    }
+}
+
+pub const DEFAULT_BUSY_TIMEOUT: Duration = Duration::from_millis(5000);
+
+/// Connection options for the server database. They are applied to every connection the pool
+/// opens.
+///
+/// WAL mode lets reads continue while a write is in progress, and the busy timeout makes a
+/// connection wait for a competing transaction to finish instead of failing with "database is
+/// locked".
+pub fn connect_options(
+    db_url: &str,
+    busy_timeout: Duration,
+) -> Result<SqliteConnectOptions, sqlx::Error> {
+    Ok(SqliteConnectOptions::from_str(db_url)?
+        .journal_mode(SqliteJournalMode::Wal)
+        .busy_timeout(busy_timeout)
+        .foreign_keys(true))
+}
+
+pub const DEFAULT_MAX_CONNECTIONS: u32 = 10;
+pub const DEFAULT_MIN_CONNECTIONS: u32 = 0;
+pub const DEFAULT_ACQUIRE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Sizing for the server's connection pool.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PoolConfig {
+    pub max_connections: u32,
+    pub min_connections: u32,
+    /// How long a caller waits for a free connection before giving up.
+    pub acquire_timeout: Duration,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        PoolConfig {
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            min_connections: DEFAULT_MIN_CONNECTIONS,
+            acquire_timeout: DEFAULT_ACQUIRE_TIMEOUT,
+        }
+    }
+}
+
+pub fn pool_options(config: &PoolConfig) -> SqlitePoolOptions {
+    SqlitePoolOptions::new()
+        .max_connections(config.max_connections)
+        .min_connections(config.min_connections)
+        .acquire_timeout(config.acquire_timeout)
 }
 
 pub trait Queryable {
@@ -52,6 +227,77 @@ pub trait Bindable<'q, DB: sqlx::Database> {
     ) -> sqlx::query::QueryAs<'q, DB, O, <DB as sqlx::database::HasArguments<'q>>::Arguments>;
 }
 
+/// A page of query results: at most `limit` rows after skipping `offset` of them. Every paginated
+/// store pages this way so limits and offsets mean the same thing everywhere.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pagination {
+    pub limit: u32,
+    pub offset: u32,
+}
+
+impl Pagination {
+    /// Caps the limit at `max_limit`, keeping the offset.
+    pub fn clamp(self, max_limit: u32) -> Self {
+        Pagination {
+            limit: self.limit.min(max_limit),
+            offset: self.offset,
+        }
+    }
+
+    /// The first page of a query capped at `cap` rows, reaching one row past the cap so
+    /// `Capped::new` can tell whether anything was left out.
+    pub fn over_cap(cap: u32) -> Self {
+        Pagination {
+            limit: cap.saturating_add(1),
+            offset: 0,
+        }
+    }
+}
+
+/// Results of a query that asked for no page, cut off at the server's result size cap so a
+/// huge table can't be loaded whole.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Capped<T> {
+    pub rows: Vec<T>,
+    /// Set when more rows matched than were returned; callers should paginate to see the rest.
+    pub truncated: bool,
+}
+
+impl<T> Capped<T> {
+    /// Cuts rows fetched with `Pagination::over_cap(cap)` down to the cap.
+    pub fn new(mut rows: Vec<T>, cap: u32) -> Self {
+        let truncated = rows.len() > cap as usize;
+        rows.truncate(cap as usize);
+        Capped { rows, truncated }
+    }
+}
+
+/// Appends the LIMIT and OFFSET clause for `page` to an ordered query. The page's own `bind`
+/// supplies the values, after any bound by the rest of the query.
+pub fn append_pagination(sql: String, page: Option<&Pagination>) -> String {
+    match page {
+        Some(_) => format!("{} LIMIT ? OFFSET ?", sql),
+        None => sql,
+    }
+}
+
+impl<'q, DB: sqlx::Database> Bindable<'q, DB> for Pagination
+where
+    u32: sqlx::Encode<'q, DB> + sqlx::Type<DB>,
+{
+    fn bind<O>(
+        &'q self,
+        query_builder: sqlx::query::QueryAs<
+            'q,
+            DB,
+            O,
+            <DB as sqlx::database::HasArguments<'q>>::Arguments,
+        >,
+    ) -> sqlx::query::QueryAs<'q, DB, O, <DB as sqlx::database::HasArguments<'q>>::Arguments> {
+        query_builder.bind(self.limit).bind(self.offset)
+    }
+}
+
 pub enum CompoundOperator {
     And,
     Or,
@@ -64,6 +310,15 @@ pub struct CompoundQuery<Q: Queryable> {
 
 impl<Q: Queryable> Queryable for CompoundQuery<Q> {
     fn where_clause(&self) -> String {
+        if self.queries.is_empty() {
+            // An empty AND matches everything, an empty OR matches nothing.
+            return match self.operator {
+                CompoundOperator::And => "(TRUE)",
+                CompoundOperator::Or => "(FALSE)",
+            }
+            .to_owned();
+        }
+
         let operator = match self.operator {
             CompoundOperator::And => " AND ",
             CompoundOperator::Or => " OR ",
@@ -79,6 +334,27 @@ impl<Q: Queryable> Queryable for CompoundQuery<Q> {
     }
 }
 
+/// Shorthand for building a store's compound query variant.
+pub trait Compound: Queryable + Sized {
+    fn compound(query: CompoundQuery<Self>) -> Self;
+
+    /// Matches when any of `queries` match.
+    fn any_of(queries: Vec<Self>) -> Self {
+        Self::compound(CompoundQuery {
+            operator: CompoundOperator::Or,
+            queries,
+        })
+    }
+
+    /// Matches when all of `queries` match.
+    fn all_of(queries: Vec<Self>) -> Self {
+        Self::compound(CompoundQuery {
+            operator: CompoundOperator::And,
+            queries,
+        })
+    }
+}
+
 impl<'q, DB: sqlx::Database, Q: Queryable + Bindable<'q, DB>> Bindable<'q, DB>
     for CompoundQuery<Q>
 {
@@ -107,6 +383,7 @@ pub trait Field {
 pub enum LogicalQuery<F: Field> {
     Equals(F::Item),
     NotEquals(F::Item),
+    In(Vec<F::Item>),
 }
 
 impl<F: Field> Queryable for LogicalQuery<F> {
@@ -114,6 +391,11 @@ impl<F: Field> Queryable for LogicalQuery<F> {
         match self {
             LogicalQuery::Equals(_) => format!("{} = ?", F::field()),
             LogicalQuery::NotEquals(_) => format!("{} != ?", F::field()),
+            // SQLite accepts an empty IN list, but spell it out like an empty OR for clarity.
+            LogicalQuery::In(values) if values.is_empty() => "(FALSE)".to_owned(),
+            LogicalQuery::In(values) => {
+                format!("{} IN ({})", F::field(), vec!["?"; values.len()].join(", "))
+            }
         }
     }
 }
@@ -134,6 +416,587 @@ where
         match self {
             LogicalQuery::Equals(value) => query_builder.bind(value),
             LogicalQuery::NotEquals(value) => query_builder.bind(value),
+            LogicalQuery::In(values) => {
+                values.iter().fold(query_builder, |query_builder, value| {
+                    query_builder.bind(value)
+                })
+            }
+        }
+    }
+}
+
+pub struct TimeRangeQuery<F: Field> {
+    pub start: Option<F::Item>,
+    pub end: Option<F::Item>,
+}
+
+impl<F: Field> Queryable for TimeRangeQuery<F> {
+    fn where_clause(&self) -> String {
+        let mut clauses = Vec::new();
+        if self.start.is_some() {
+            clauses.push(format!("{} >= ?", F::field()));
+        }
+        if self.end.is_some() {
+            clauses.push(format!("{} < ?", F::field()));
+        }
+        if clauses.is_empty() {
+            clauses.push(format!("{} IS NOT NULL", F::field()));
+        }
+
+        format!("({})", clauses.join(" AND "))
+    }
+}
+
+impl<'q, DB: sqlx::Database, F: Field> Bindable<'q, DB> for TimeRangeQuery<F>
+where
+    F::Item: sqlx::Encode<'q, DB> + sqlx::Type<DB> + Sync,
+{
+    fn bind<O>(
+        &'q self,
+        query_builder: sqlx::query::QueryAs<
+            'q,
+            DB,
+            O,
+            <DB as sqlx::database::HasArguments<'q>>::Arguments,
+        >,
+    ) -> sqlx::query::QueryAs<'q, DB, O, <DB as sqlx::database::HasArguments<'q>>::Arguments> {
+        [&self.start, &self.end]
+            .into_iter()
+            .flatten()
+            .fold(query_builder, |query_builder, value| {
+                query_builder.bind(value)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{borrow::Cow, env, error::Error as _, sync::Arc, time::Duration};
+
+    use chrono::{TimeZone, Utc};
+    use common::proto::Event;
+    use ed25519_dalek::SigningKey;
+    use rand::rngs::OsRng;
+    use sqlx::{migrate::Migrator, SqlitePool};
+    use test_case::test_case;
+
+    use super::{
+        append_pagination,
+        common::{format_timestamp, new_id},
+        connect_options,
+        event::{SqliteStore, Store},
+        keys::{self, Key, Store as _},
+        pool_options, user, Bindable as _, Capped, Compound, CompoundOperator, CompoundQuery,
+        Error, IntoStoreError, LogicalQuery, Pagination, PoolConfig, Queryable, StoreResult,
+        DEFAULT_BUSY_TIMEOUT,
+    };
+
+    #[test]
+    fn pagination_clamp() {
+        let page = Pagination {
+            limit: 1000,
+            offset: 20,
+        };
+        assert_eq!(
+            page.clamp(500),
+            Pagination {
+                limit: 500,
+                offset: 20
+            }
+        );
+
+        let page = Pagination {
+            limit: 10,
+            offset: 20,
+        };
+        assert_eq!(page.clamp(500), page);
+    }
+
+    #[test]
+    fn capped_rows() {
+        assert_eq!(Pagination::over_cap(3).limit, 4);
+
+        let capped = Capped::new(vec![1, 2, 3, 4], 3);
+        assert_eq!(capped.rows, vec![1, 2, 3]);
+        assert!(capped.truncated);
+
+        let capped = Capped::new(vec![1, 2, 3], 3);
+        assert_eq!(capped.rows, vec![1, 2, 3]);
+        assert!(!capped.truncated);
+    }
+
+    #[tokio::test]
+    async fn pagination_sql() {
+        let sql = "SELECT column1 FROM (VALUES (1), (2), (3), (4)) ORDER BY column1".to_owned();
+        assert_eq!(append_pagination(sql.clone(), None), sql);
+
+        let page = Pagination {
+            limit: 2,
+            offset: 1,
+        };
+        let paged_sql = append_pagination(sql.clone(), Some(&page));
+        assert_eq!(paged_sql, format!("{} LIMIT ? OFFSET ?", sql));
+
+        let db = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        let rows: Vec<(i64,)> = page
+            .bind(sqlx::query_as(&paged_sql))
+            .fetch_all(&db)
+            .await
+            .unwrap();
+        assert_eq!(rows, vec![(2,), (3,)]);
+    }
+
+    #[tokio::test]
+    async fn pool_waits_for_free_connection() {
+        let config = PoolConfig {
+            max_connections: 1,
+            acquire_timeout: Duration::from_secs(10),
+            ..Default::default()
+        };
+        let pool = pool_options(&config)
+            .connect_with(connect_options("sqlite::memory:", DEFAULT_BUSY_TIMEOUT).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(pool.options().get_max_connections(), 1);
+
+        let held = pool.acquire().await.unwrap();
+
+        let waiting = tokio::spawn({
+            let pool = pool.clone();
+            async move { pool.acquire().await.map(|_| ()) }
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!waiting.is_finished());
+
+        drop(held);
+        tokio::time::timeout(Duration::from_secs(5), waiting)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+    }
+
+    #[test_case(Error::IdDoesNotExist("abc".to_owned()), "id abc does not exist" ; "id does not exist")]
+    #[test_case(Error::ColumnParseError("status"), "unable to parse stored column status" ; "column parse error")]
+    #[test_case(Error::UnknownEnumValue { column: "text_type_display", value: "MARQUEE".to_owned() }, "unknown value \"MARQUEE\" stored in column text_type_display" ; "unknown enum value")]
+    #[test_case(Error::FetchError(sqlx::Error::RowNotFound), "unable to read from the data store" ; "fetch error")]
+    #[test_case(Error::TransactionFailed(sqlx::Error::PoolClosed), "unable to commit data store transaction" ; "transaction failed")]
+    #[test_case(Error::InvalidTimestamp("soon".to_owned()), "invalid timestamp \"soon\"" ; "invalid timestamp")]
+    fn error_display(error: Error, expected: &str) {
+        assert_eq!(error.to_string(), expected);
+    }
+
+    #[test_case(IntoStoreError::fetch_err, "unable to read from the data store" ; "fetch")]
+    #[test_case(IntoStoreError::insert_err, "unable to save to the data store" ; "insert")]
+    #[test_case(IntoStoreError::update_err, "unable to update the data store" ; "update")]
+    #[test_case(IntoStoreError::delete_err, "unable to delete from the data store" ; "delete")]
+    #[test_case(IntoStoreError::check_exists_err, "unable to check whether ids exist in the data store" ; "check exists")]
+    #[test_case(IntoStoreError::begin_err, "unable to start data store transaction" ; "begin")]
+    #[test_case(IntoStoreError::commit_err, "unable to commit data store transaction" ; "commit")]
+    fn into_store_error(map: fn(Result<(), sqlx::Error>) -> StoreResult<()>, expected: &str) {
+        let error = map(Err(sqlx::Error::RowNotFound)).unwrap_err();
+        assert_eq!(error.to_string(), expected);
+        assert!(matches!(
+            error.source().unwrap().downcast_ref::<sqlx::Error>(),
+            Some(sqlx::Error::RowNotFound)
+        ));
+
+        assert!(map(Ok(())).is_ok());
+    }
+
+    #[test]
+    fn error_source() {
+        let error = Error::InsertionError(sqlx::Error::Protocol(
+            "INSERT INTO events failed".to_owned(),
+        ));
+
+        // The sqlx details stay out of the message but remain available to callers.
+        assert!(!error.to_string().contains("INSERT"));
+        let source = error.source().unwrap();
+        assert!(source.to_string().contains("INSERT INTO events failed"));
+
+        assert!(Error::ColumnParseError("status").source().is_none());
+    }
+
+    fn user_queries() -> Vec<user::Query> {
+        vec![
+            user::Query::Id(LogicalQuery::Equals("user".to_owned())),
+            user::Query::PasswordIsSet(true),
+        ]
+    }
+
+    #[test_case(CompoundOperator::Or ; "any of")]
+    #[test_case(CompoundOperator::And ; "all of")]
+    fn compound_helpers(operator: CompoundOperator) {
+        let helper = match operator {
+            CompoundOperator::Or => user::Query::any_of(user_queries()),
+            CompoundOperator::And => user::Query::all_of(user_queries()),
+        };
+        let manual = user::Query::CompoundQuery(CompoundQuery {
+            operator,
+            queries: user_queries(),
+        });
+
+        assert_eq!(helper.where_clause(), manual.where_clause());
+    }
+
+    #[test]
+    fn empty_compound_where_clause() {
+        assert_eq!(user::Query::all_of(Vec::new()).where_clause(), "(TRUE)");
+        assert_eq!(
+            user::Query::any_of(Vec::new()).where_clause(),
+            user::Query::Id(LogicalQuery::In(Vec::new())).where_clause()
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn concurrent_upserts() {
+        // WAL mode is not available for in-memory databases, so this test needs a real file.
+        let path = env::temp_dir().join(format!("odr-concurrent-{}.db", new_id()));
+        let db_url = format!("sqlite://{}", path.display());
+
+        let db = SqlitePool::connect_with(
+            connect_options(&db_url, DEFAULT_BUSY_TIMEOUT)
+                .unwrap()
+                .create_if_missing(true),
+        )
+        .await
+        .unwrap();
+        sqlx::migrate!("../migrations").run(&db).await.unwrap();
+
+        let org = new_id();
+        sqlx::query("INSERT INTO organizations(id, name) VALUES (?, ?);")
+            .bind(&org)
+            .bind("Organization 1")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let store = Arc::new(SqliteStore::new(Arc::new(db)));
+        let event = store
+            .upsert(vec![Event {
+                organization_id: org,
+                name: "Event".to_owned(),
+                ..Default::default()
+            }])
+            .await
+            .unwrap()
+            .remove(0);
+
+        let tasks = ["Event 1", "Event 2"].map(|name| {
+            let store = store.clone();
+            let event = Event {
+                name: name.to_owned(),
+                ..event.clone()
+            };
+            tokio::spawn(async move { store.upsert(vec![event]).await })
+        });
+
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        drop(store);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+
+    #[tokio::test]
+    async fn timestamps_match_across_stores() {
+        let db = Arc::new(
+            SqlitePool::connect_with(
+                connect_options("sqlite::memory:", DEFAULT_BUSY_TIMEOUT).unwrap(),
+            )
+            .await
+            .unwrap(),
+        );
+        sqlx::migrate!("../migrations").run(&*db).await.unwrap();
+
+        let org = new_id();
+        sqlx::query("INSERT INTO organizations(id, name) VALUES (?, ?);")
+            .bind(&org)
+            .bind("Organization 1")
+            .execute(&*db)
+            .await
+            .unwrap();
+
+        let instant = Utc.with_ymd_and_hms(2024, 3, 9, 12, 30, 5).unwrap();
+
+        // The same instant, given with an offset, must still be stored as UTC.
+        SqliteStore::new(db.clone())
+            .upsert(vec![Event {
+                organization_id: org,
+                name: "Event".to_owned(),
+                registration_closes_at: "2024-03-09T14:30:05+02:00".to_owned(),
+                ..Default::default()
+            }])
+            .await
+            .unwrap();
+
+        keys::SqliteStore::new(db.clone())
+            .insert(Key {
+                id: String::default(),
+                key: SigningKey::generate(&mut OsRng),
+                created_at: instant,
+            })
+            .await
+            .unwrap();
+
+        let (event_timestamp,): (String,) =
+            sqlx::query_as("SELECT registration_closes_at FROM events")
+                .fetch_one(&*db)
+                .await
+                .unwrap();
+        let (key_timestamp,): (String,) = sqlx::query_as("SELECT created_at FROM keys")
+            .fetch_one(&*db)
+            .await
+            .unwrap();
+
+        assert_eq!(event_timestamp, format_timestamp(&instant));
+        assert_eq!(key_timestamp, event_timestamp);
+    }
+
+    // Rebuilding the schema item table for tags must keep existing items, their options and
+    // answers, and the events' dedup items pointing at them.
+    #[tokio::test]
+    async fn tags_migration_keeps_rows() {
+        let db = SqlitePool::connect_with(
+            connect_options("sqlite::memory:", DEFAULT_BUSY_TIMEOUT).unwrap(),
+        )
+        .await
+        .unwrap();
+
+        let migrator = sqlx::migrate!("../migrations");
+        let before_tags = Migrator {
+            migrations: Cow::Owned(
+                migrator
+                    .migrations
+                    .iter()
+                    .filter(|migration| migration.version < 20240319120000)
+                    .cloned()
+                    .collect(),
+            ),
+            ..sqlx::migrate!("../migrations")
+        };
+        before_tags.run(&db).await.unwrap();
+
+        let org = new_id();
+        let event = new_id();
+        let item = new_id();
+        let registration = new_id();
+        let option = new_id();
+        let answer = new_id();
+        for (query, binds) in [
+            (
+                "INSERT INTO organizations(id, name) VALUES (?, ?)",
+                vec![org.as_str(), "Org"],
+            ),
+            (
+                "INSERT INTO events(id, organization, name) VALUES (?, ?, ?)",
+                vec![event.as_str(), org.as_str(), "Event"],
+            ),
+            (
+                "INSERT INTO registration_schema_items(id, event, idx, name, item_type, select_type_default, select_type_display) VALUES (?, ?, 0, ?, 'SelectType', 0, 'RADIO')",
+                vec![item.as_str(), event.as_str(), "Role"],
+            ),
+            (
+                "INSERT INTO registration_schema_select_options(id, schema_item, idx, name, product_id) VALUES (?, ?, 0, ?, '')",
+                vec![option.as_str(), item.as_str(), "Lead"],
+            ),
+            (
+                "INSERT INTO registrations(id, event) VALUES (?, ?)",
+                vec![registration.as_str(), event.as_str()],
+            ),
+            (
+                "INSERT INTO registration_items(id, registration, schema_item, value) VALUES (?, ?, ?, ?)",
+                vec![answer.as_str(), registration.as_str(), item.as_str(), "0"],
+            ),
+            (
+                "UPDATE events SET dedup_item = ? WHERE id = ?",
+                vec![item.as_str(), event.as_str()],
+            ),
+        ] {
+            binds
+                .into_iter()
+                .fold(sqlx::query(query), |query, bind| query.bind(bind))
+                .execute(&db)
+                .await
+                .unwrap();
+        }
+
+        migrator.run(&db).await.unwrap();
+
+        for table in [
+            "registration_schema_items",
+            "registration_schema_select_options",
+            "registration_items",
+        ] {
+            let (count,): (i64,) = sqlx::query_as(&format!("SELECT COUNT(*) FROM {}", table))
+                .fetch_one(&db)
+                .await
+                .unwrap();
+            assert_eq!(count, 1, "{} lost rows", table);
+        }
+
+        let (dedup_item,): (Option<String>,) =
+            sqlx::query_as("SELECT dedup_item FROM events WHERE id = ?")
+                .bind(&event)
+                .fetch_one(&db)
+                .await
+                .unwrap();
+        assert_eq!(dedup_item, Some(item));
+    }
+
+    #[tokio::test]
+    async fn draft_registrations_migration_keeps_rows() {
+        let db = SqlitePool::connect_with(
+            connect_options("sqlite::memory:", DEFAULT_BUSY_TIMEOUT).unwrap(),
+        )
+        .await
+        .unwrap();
+
+        let migrator = sqlx::migrate!("../migrations");
+        let before_drafts = Migrator {
+            migrations: Cow::Owned(
+                migrator
+                    .migrations
+                    .iter()
+                    .filter(|migration| migration.version < 20240320120000)
+                    .cloned()
+                    .collect(),
+            ),
+            ..sqlx::migrate!("../migrations")
+        };
+        before_drafts.run(&db).await.unwrap();
+
+        let org = new_id();
+        let event = new_id();
+        let item = new_id();
+        let confirmed = new_id();
+        let waitlisted = new_id();
+        let answer = new_id();
+        for (query, binds) in [
+            (
+                "INSERT INTO organizations(id, name) VALUES (?, ?)",
+                vec![org.as_str(), "Org"],
+            ),
+            (
+                "INSERT INTO events(id, organization, name) VALUES (?, ?, ?)",
+                vec![event.as_str(), org.as_str(), "Event"],
+            ),
+            (
+                "INSERT INTO registration_schema_items(id, event, idx, name, item_type) VALUES (?, ?, 0, ?, 'TextType')",
+                vec![item.as_str(), event.as_str(), "Name"],
+            ),
+            (
+                "INSERT INTO registrations(id, event, status) VALUES (?, ?, 'CONFIRMED')",
+                vec![confirmed.as_str(), event.as_str()],
+            ),
+            (
+                "INSERT INTO registrations(id, event, status) VALUES (?, ?, 'WAITLISTED')",
+                vec![waitlisted.as_str(), event.as_str()],
+            ),
+            (
+                "INSERT INTO registration_items(id, registration, schema_item, value) VALUES (?, ?, ?, ?)",
+                vec![answer.as_str(), confirmed.as_str(), item.as_str(), "Alice"],
+            ),
+        ] {
+            binds
+                .into_iter()
+                .fold(sqlx::query(query), |query, bind| query.bind(bind))
+                .execute(&db)
+                .await
+                .unwrap();
         }
+
+        migrator.run(&db).await.unwrap();
+
+        let statuses: Vec<(String, String)> =
+            sqlx::query_as("SELECT id, status FROM registrations ORDER BY status")
+                .fetch_all(&db)
+                .await
+                .unwrap();
+        assert_eq!(
+            statuses,
+            vec![
+                (confirmed.clone(), "CONFIRMED".to_owned()),
+                (waitlisted, "WAITLISTED".to_owned()),
+            ]
+        );
+
+        let items: Vec<(String, String, String)> =
+            sqlx::query_as("SELECT id, registration, value FROM registration_items")
+                .fetch_all(&db)
+                .await
+                .unwrap();
+        assert_eq!(items, vec![(answer, confirmed, "Alice".to_owned())]);
+    }
+
+    // The columns each store reads or writes. Running every migration from scratch must leave
+    // all of them in place, so a migration that drops or renames one fails here rather than at
+    // query time.
+    #[test_case("organizations", &["id", "name", "slug"] ; "organizations")]
+    #[test_case("events", &["id", "organization", "name", "starts_at", "ends_at", "registration_closes_at", "require_account", "slug", "dedup_item", "block_duplicates", "registration_opens_at"] ; "events")]
+    #[test_case("registration_schema_items", &[
+        "id",
+        "event",
+        "parent",
+        "idx",
+        "name",
+        "required",
+        "required_after",
+        "blank_on_copy",
+        "display_idx",
+        "item_type",
+        "text_type_default",
+        "text_type_display",
+        "text_type_placeholder",
+        "text_type_prefix",
+        "text_type_suffix",
+        "checkbox_type_default",
+        "select_type_default",
+        "select_type_display",
+        "multi_select_type_defaults",
+        "multi_select_type_display",
+        "section_type_body",
+        "tags_type_max_tags",
+    ] ; "registration schema items")]
+    #[test_case("registration_schema_select_options", &["id", "schema_item", "idx", "name", "product_id", "archived"] ; "registration schema select options")]
+    #[test_case("registration_schema_item_history", &["id", "schema_item", "changed_at", "actor", "old_name", "new_name", "old_type", "new_type", "old_options", "new_options"] ; "registration schema item history")]
+    #[test_case("registrations", &["id", "event", "status", "user"] ; "registrations")]
+    #[test_case("registration_items", &["id", "registration", "schema_item", "instance", "value"] ; "registration items")]
+    #[test_case("schema_templates", &["id", "organization", "name", "items"] ; "schema templates")]
+    #[test_case("users", &["id", "email", "password", "display_name"] ; "users")]
+    #[test_case("permissions", &["id", "user", "role", "organization", "event"] ; "permissions")]
+    #[test_case("keys", &["id", "eddsa_key", "created_at"] ; "keys")]
+    #[tokio::test]
+    async fn migrations_match_stores(table: &str, columns: &[&str]) {
+        let db = SqlitePool::connect_with(
+            connect_options("sqlite::memory:", DEFAULT_BUSY_TIMEOUT).unwrap(),
+        )
+        .await
+        .unwrap();
+        sqlx::migrate!("../migrations").run(&db).await.unwrap();
+
+        let rows: Vec<(String,)> = sqlx::query_as("SELECT name FROM pragma_table_info(?)")
+            .bind(table)
+            .fetch_all(&db)
+            .await
+            .unwrap();
+        let existing = rows.into_iter().map(|(name,)| name).collect::<Vec<_>>();
+        assert!(!existing.is_empty(), "table {} does not exist", table);
+
+        let missing = columns
+            .iter()
+            .filter(|column| !existing.iter().any(|name| name == *column))
+            .collect::<Vec<_>>();
+        assert!(
+            missing.is_empty(),
+            "table {} is missing columns {:?}",
+            table,
+            missing
+        );
     }
 }
@@ -1,7 +1,17 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, SecondsFormat, Utc};
 use sqlx::SqlitePool;
 use uuid::Uuid;
 
-use super::Error;
+use super::{Error, IntoStoreError as _, StoreResult};
+
+// Store methods given nothing to write return an empty result without touching the database,
+// so callers don't have to check first. Used as
+// `if let Some(result) = nothing_to_do(&input) { return result; }`.
+pub fn nothing_to_do<T, R: Default>(input: &[T]) -> Option<StoreResult<R>> {
+    input.is_empty().then(|| Ok(R::default()))
+}
 
 pub fn new_id() -> String {
     Uuid::now_v7()
@@ -10,14 +20,128 @@ pub fn new_id() -> String {
         .to_owned()
 }
 
+// Timestamps are stored as UTC RFC3339 strings with whole seconds so that they sort lexically. All
+// stores go through these helpers so the format can't drift between tables.
+pub fn parse_timestamp(timestamp: &str) -> Result<Option<DateTime<Utc>>, chrono::ParseError> {
+    if timestamp.is_empty() {
+        return Ok(None);
+    }
+
+    DateTime::parse_from_rfc3339(timestamp).map(|t| Some(t.with_timezone(&Utc)))
+}
+
+pub fn format_timestamp(timestamp: &DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+pub fn now_rfc3339() -> String {
+    format_timestamp(&Utc::now())
+}
+
+/// How user-entered strings are cleaned up before they are stored.
+///
+/// Event names and select option names are collapsed, registration item values are only
+/// trimmed so that multi-line answers keep their line breaks.
+pub enum Normalization {
+    Trim,
+    Collapse,
+}
+
+pub fn normalize(value: &str, normalization: Normalization) -> String {
+    match normalization {
+        Normalization::Trim => value.trim().to_owned(),
+        Normalization::Collapse => {
+            itertools::Itertools::intersperse(value.split_whitespace(), " ").collect()
+        }
+    }
+}
+
+/// Whether an event or organization keeps its slug when it is renamed. Keeping it means links
+/// already handed out keep working.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SlugPolicy {
+    #[default]
+    Stable,
+    Regenerate,
+}
+
+/// A lowercase, hyphenated form of `name` for use in URLs. Names with no ASCII letters or digits
+/// fall back to `fallback`.
+pub fn slugify(name: &str, fallback: &str) -> String {
+    let slug: String = itertools::Itertools::intersperse(
+        name.split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|word| !word.is_empty()),
+        "-",
+    )
+    .collect::<String>()
+    .to_ascii_lowercase();
+
+    if slug.is_empty() {
+        fallback.to_owned()
+    } else {
+        slug
+    }
+}
+
+/// `base`, or `base` with the smallest numeric suffix from 2 up that isn't already taken.
+pub fn unique_slug(base: &str, taken: &HashSet<String>) -> String {
+    if !taken.contains(base) {
+        return base.to_owned();
+    }
+
+    (2..)
+        .map(|n| format!("{}-{}", base, n))
+        .find(|slug| !taken.contains(slug))
+        .unwrap()
+}
+
+/// Slugs in `table` that `unique_slug` could collide with when deriving one from `base`, ignoring
+/// the row `id` itself. `scope` limits the search to rows with the same value in that column.
+pub async fn taken_slugs(
+    pool: &SqlitePool,
+    table: &'static str,
+    scope: Option<(&'static str, &str)>,
+    id: &str,
+    base: &str,
+) -> StoreResult<HashSet<String>> {
+    let scope_clause = match scope {
+        Some((column, _)) => format!(" AND {} = ?", column),
+        None => String::new(),
+    };
+
+    let query = format!(
+        "SELECT slug FROM {} WHERE (slug = ? OR slug LIKE ?) AND id != ?{}",
+        table, scope_clause
+    );
+
+    // Slugs only contain lowercase letters, digits and hyphens, so there are no LIKE wildcards to
+    // escape.
+    let query_builder = sqlx::query_as(&query)
+        .bind(base)
+        .bind(format!("{}-%", base))
+        .bind(id);
+    let query_builder = match scope {
+        Some((_, value)) => query_builder.bind(value),
+        None => query_builder,
+    };
+
+    let rows: Vec<(String,)> = query_builder.fetch_all(pool).await.fetch_err()?;
+
+    Ok(rows.into_iter().map(|(slug,)| slug).collect())
+}
+
 pub async fn ids_in_table<'a, Iter>(
     pool: &SqlitePool,
     table: &'static str,
     ids: Iter,
-) -> Result<(), Error>
+) -> StoreResult<()>
 where
     Iter: IntoIterator<Item = &'a str> + Clone,
 {
+    if ids.clone().into_iter().next().is_none() {
+        return Ok(());
+    }
+
     let select_where_clause: String =
         itertools::Itertools::intersperse(ids.clone().into_iter().map(|_| "(?)"), ",").collect();
 
@@ -32,7 +156,7 @@ where
     let missing_ids: Vec<(String,)> = select_query_builder
         .fetch_all(pool)
         .await
-        .map_err(|e| Error::CheckExistsError(e))?;
+        .check_exists_err()?;
 
     if !missing_ids.is_empty() {
         return Err(Error::IdDoesNotExist(missing_ids[0].0.clone()));
@@ -40,3 +164,63 @@ where
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use chrono::{DateTime, TimeZone, Utc};
+
+    use std::collections::HashSet;
+
+    use super::{
+        format_timestamp, normalize, now_rfc3339, parse_timestamp, slugify, unique_slug,
+        Normalization,
+    };
+
+    #[test_case("  Salsa  ", Normalization::Trim, "Salsa" ; "trim")]
+    #[test_case(" Salsa \n Night ", Normalization::Trim, "Salsa \n Night" ; "trim keeps inner whitespace")]
+    #[test_case("  Salsa \t  Night ", Normalization::Collapse, "Salsa Night" ; "collapse")]
+    fn normalize_value(value: &str, normalization: Normalization, expected: &str) {
+        assert_eq!(normalize(value, normalization), expected);
+    }
+
+    #[test_case("Salsa Night", "salsa-night" ; "lowercase hyphenated")]
+    #[test_case("  Rock & Roll -- 2024! ", "rock-roll-2024" ; "punctuation collapsed")]
+    #[test_case("Café Tango", "caf-tango" ; "non ascii dropped")]
+    #[test_case("!!!", "event" ; "fallback")]
+    fn slugify_name(name: &str, expected: &str) {
+        assert_eq!(slugify(name, "event"), expected);
+    }
+
+    #[test_case(&[], "salsa-night" ; "free")]
+    #[test_case(&["salsa-night"], "salsa-night-2" ; "taken")]
+    #[test_case(&["salsa-night", "salsa-night-2"], "salsa-night-3" ; "suffix taken")]
+    fn unique_slug_suffix(taken: &[&str], expected: &str) {
+        let taken = taken.iter().map(|s| s.to_string()).collect::<HashSet<_>>();
+        assert_eq!(unique_slug("salsa-night", &taken), expected);
+    }
+
+    #[test]
+    fn now_rfc3339_parses() {
+        let before = Utc::now().timestamp();
+        let now = now_rfc3339();
+        let after = Utc::now().timestamp();
+
+        let parsed = DateTime::parse_from_rfc3339(&now).unwrap();
+        assert_eq!(parsed.offset().local_minus_utc(), 0);
+        assert!((before..=after).contains(&parsed.timestamp()));
+        assert_eq!(
+            parse_timestamp(&now).unwrap(),
+            Some(parsed.with_timezone(&Utc))
+        );
+    }
+
+    #[test]
+    fn format_timestamp_is_utc_seconds() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 3, 9, 12, 30, 5).unwrap()
+            + chrono::Duration::milliseconds(250);
+
+        assert_eq!(format_timestamp(&timestamp), "2024-03-09T12:30:05Z");
+    }
+}
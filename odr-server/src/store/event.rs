@@ -1,19 +1,37 @@
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
+use chrono::{DateTime, Utc};
 use sqlx::SqlitePool;
 
-use common::proto::Event;
+use common::proto::{Event, Organization};
 
 use super::{
-    common::{ids_in_table, new_id},
-    Bindable as _, Error, Queryable as _,
+    append_pagination,
+    common::{
+        format_timestamp, ids_in_table, new_id, normalize, nothing_to_do, parse_timestamp, slugify,
+        taken_slugs, unique_slug, Normalization, SlugPolicy,
+    },
+    ids::{EventId, OrgId},
+    Bindable as _, Capped, Error, IntoStoreError as _, Outcome, Pagination, Queryable as _,
+    StoreResult,
 };
 
 #[derive(sqlx::FromRow)]
-struct EventRow {
+pub(super) struct EventRow {
     id: String,
     name: String,
     organization: String,
+    starts_at: Option<String>,
+    ends_at: Option<String>,
+    registration_closes_at: Option<String>,
+    require_account: bool,
+    slug: Option<String>,
+    dedup_item: Option<String>,
+    block_duplicates: bool,
+    registration_opens_at: Option<String>,
 }
 
 impl From<EventRow> for Event {
@@ -22,6 +40,140 @@ impl From<EventRow> for Event {
             id: row.id,
             name: row.name,
             organization_id: row.organization,
+            starts_at: row.starts_at.unwrap_or_default(),
+            ends_at: row.ends_at.unwrap_or_default(),
+            registration_closes_at: row.registration_closes_at.unwrap_or_default(),
+            require_account: row.require_account,
+            slug: row.slug.unwrap_or_default(),
+            dedup_item_id: row.dedup_item.unwrap_or_default(),
+            block_duplicates: row.block_duplicates,
+            registration_opens_at: row.registration_opens_at.unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct EventWithOrganizationRow {
+    #[sqlx(flatten)]
+    event: EventRow,
+    organization_name: String,
+    organization_slug: Option<String>,
+}
+
+fn normalize_dates(event: &mut Event) -> StoreResult<()> {
+    let invalid =
+        |event: &Event| Error::InvalidEventDates(event.starts_at.clone(), event.ends_at.clone());
+
+    let starts_at = parse_timestamp(&event.starts_at).map_err(|_| invalid(event))?;
+    let ends_at = parse_timestamp(&event.ends_at).map_err(|_| invalid(event))?;
+
+    if let (Some(starts_at), Some(ends_at)) = (&starts_at, &ends_at) {
+        if starts_at > ends_at {
+            return Err(invalid(event));
+        }
+    }
+
+    let registration_opens_at = parse_timestamp(&event.registration_opens_at)
+        .map_err(|_| Error::InvalidTimestamp(event.registration_opens_at.clone()))?;
+    let registration_closes_at = parse_timestamp(&event.registration_closes_at)
+        .map_err(|_| Error::InvalidTimestamp(event.registration_closes_at.clone()))?;
+
+    event.starts_at = starts_at.as_ref().map(format_timestamp).unwrap_or_default();
+    event.ends_at = ends_at.as_ref().map(format_timestamp).unwrap_or_default();
+    event.registration_opens_at = registration_opens_at
+        .as_ref()
+        .map(format_timestamp)
+        .unwrap_or_default();
+    event.registration_closes_at = registration_closes_at
+        .as_ref()
+        .map(format_timestamp)
+        .unwrap_or_default();
+
+    Ok(())
+}
+
+/// Where an event is in its registration schedule, for showing registrants whether they can
+/// register yet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegistrationState {
+    NotYetOpen,
+    Open,
+    Closed,
+}
+
+/// Registration opens at `registration_opens_at` and closes at `registration_closes_at`; either
+/// may be empty, leaving that side of the schedule unbounded.
+pub fn registration_state(event: &Event) -> RegistrationState {
+    registration_state_at(
+        &event.registration_opens_at,
+        &event.registration_closes_at,
+        &Utc::now(),
+    )
+}
+
+pub(super) fn registration_state_at(
+    opens_at: &str,
+    closes_at: &str,
+    now: &DateTime<Utc>,
+) -> RegistrationState {
+    // Stored timestamps have already been validated.
+    let bound = |timestamp: &str| parse_timestamp(timestamp).ok().flatten();
+
+    if bound(closes_at).is_some_and(|closes_at| closes_at <= *now) {
+        RegistrationState::Closed
+    } else if bound(opens_at).is_some_and(|opens_at| opens_at > *now) {
+        RegistrationState::NotYetOpen
+    } else {
+        RegistrationState::Open
+    }
+}
+
+pub fn nullable(value: &str) -> Option<&str> {
+    (!value.is_empty()).then_some(value)
+}
+
+/// An event field that can be updated on its own by `Store::update`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UpdateField {
+    Name,
+    StartsAt,
+    EndsAt,
+    RegistrationOpensAt,
+    RegistrationClosesAt,
+}
+
+impl UpdateField {
+    fn column(&self) -> &'static str {
+        match self {
+            UpdateField::Name => "name",
+            UpdateField::StartsAt => "starts_at",
+            UpdateField::EndsAt => "ends_at",
+            UpdateField::RegistrationOpensAt => "registration_opens_at",
+            UpdateField::RegistrationClosesAt => "registration_closes_at",
+        }
+    }
+
+    fn copy(&self, from: &Event, to: &mut Event) {
+        match self {
+            UpdateField::Name => to.name = normalize(&from.name, Normalization::Collapse),
+            UpdateField::StartsAt => to.starts_at = from.starts_at.clone(),
+            UpdateField::EndsAt => to.ends_at = from.ends_at.clone(),
+            UpdateField::RegistrationOpensAt => {
+                to.registration_opens_at = from.registration_opens_at.clone()
+            }
+            UpdateField::RegistrationClosesAt => {
+                to.registration_closes_at = from.registration_closes_at.clone()
+            }
+        }
+    }
+
+    fn value<'a>(&self, event: &'a Event) -> Option<&'a str> {
+        match self {
+            UpdateField::Name => Some(&event.name),
+            UpdateField::StartsAt => nullable(&event.starts_at),
+            UpdateField::EndsAt => nullable(&event.ends_at),
+            UpdateField::RegistrationOpensAt => nullable(&event.registration_opens_at),
+            UpdateField::RegistrationClosesAt => nullable(&event.registration_closes_at),
         }
     }
 }
@@ -50,9 +202,22 @@ impl super::Field for OrganizationField {
 
 pub type OrganizationQuery = super::LogicalQuery<OrganizationField>;
 
+pub struct StartsAtField;
+
+impl super::Field for StartsAtField {
+    type Item = String;
+
+    fn field() -> &'static str {
+        "starts_at"
+    }
+}
+
+pub type StartsAtQuery = super::TimeRangeQuery<StartsAtField>;
+
 pub enum Query {
     Id(IdQuery),
     Organization(OrganizationQuery),
+    StartsAt(StartsAtQuery),
     CompoundQuery(super::CompoundQuery<Query>),
 }
 
@@ -61,15 +226,23 @@ impl super::Queryable for Query {
         match self {
             Query::Id(q) => q.where_clause(),
             Query::Organization(q) => q.where_clause(),
+            Query::StartsAt(q) => q.where_clause(),
             Query::CompoundQuery(compound_query) => compound_query.where_clause(),
         }
     }
 }
 
+impl super::Compound for Query {
+    fn compound(query: super::CompoundQuery<Query>) -> Self {
+        Query::CompoundQuery(query)
+    }
+}
+
 impl<'q, DB: sqlx::Database> super::Bindable<'q, DB> for Query
 where
     <IdField as super::Field>::Item: sqlx::Type<DB> + sqlx::Encode<'q, DB>,
     <OrganizationField as super::Field>::Item: sqlx::Type<DB> + sqlx::Encode<'q, DB>,
+    <StartsAtField as super::Field>::Item: sqlx::Type<DB> + sqlx::Encode<'q, DB>,
 {
     fn bind<O>(
         &'q self,
@@ -83,6 +256,7 @@ where
         match self {
             Query::Id(q) => q.bind(query_builder),
             Query::Organization(q) => q.bind(query_builder),
+            Query::StartsAt(q) => q.bind(query_builder),
             Query::CompoundQuery(compound_query) => compound_query.bind(query_builder),
         }
     }
@@ -90,25 +264,177 @@ where
 
 #[tonic::async_trait]
 pub trait Store: Send + Sync + 'static {
-    async fn upsert(&self, events: Vec<Event>) -> Result<Vec<Event>, Error>;
-    async fn query(&self, query: Option<&Query>) -> Result<Vec<Event>, Error>;
-    async fn delete(&self, event_ids: &Vec<String>) -> Result<(), Error>;
+    /// Updated events must stay in their organization; moving one is left to `move_event`.
+    async fn upsert(&self, events: Vec<Event>) -> StoreResult<Vec<Event>>;
+    /// Upserts like `upsert`, also returning an outcome per event in the same order.
+    async fn upsert_with_outcomes(
+        &self,
+        events: Vec<Event>,
+    ) -> StoreResult<(Vec<Event>, Vec<Outcome>)>;
+    /// Events are returned in id order.
+    async fn query(&self, query: Option<&Query>) -> StoreResult<Vec<Event>>;
+    /// Like query, but returns at most the first `cap` events.
+    async fn query_capped(&self, query: Option<&Query>, cap: u32) -> StoreResult<Capped<Event>>;
+    async fn delete(&self, event_ids: &[String]) -> StoreResult<()>;
+    /// Updates only the given fields of an existing event, leaving the rest untouched, and
+    /// returns the whole updated event.
+    async fn update(&self, event: Event, fields: &[UpdateField]) -> StoreResult<Event>;
+    async fn get_event_with_organization(
+        &self,
+        event_id: &EventId,
+    ) -> StoreResult<(Event, Organization)>;
+    /// Moves an event to another organization, keeping its slug unless that is taken there.
+    /// Permissions on the event itself go with it, since they name the event rather than the
+    /// organization.
+    async fn move_event(&self, event_id: &EventId, organization_id: &OrgId) -> StoreResult<Event>;
+    async fn get_by_slug(&self, organization_id: &OrgId, slug: &str) -> StoreResult<Event>;
+    /// Whether the event exists and belongs to the organization.
+    async fn event_belongs_to_org(
+        &self,
+        event_id: &EventId,
+        organization_id: &OrgId,
+    ) -> StoreResult<bool>;
+    /// Creates one event per pair of start and end timestamps, named after the template with the
+    /// start date added, and returns them in the same order. Either every event is created or
+    /// none are.
+    async fn create_series(
+        &self,
+        organization_id: &OrgId,
+        template: Event,
+        dates: &[(String, String)],
+    ) -> StoreResult<Vec<Event>>;
 }
 
 #[derive(Debug)]
 pub struct SqliteStore {
     pool: Arc<SqlitePool>,
+    slug_policy: SlugPolicy,
 }
 
 impl SqliteStore {
     pub fn new(pool: Arc<SqlitePool>) -> Self {
-        SqliteStore { pool }
+        SqliteStore {
+            pool,
+            slug_policy: SlugPolicy::default(),
+        }
+    }
+
+    pub fn with_slug_policy(mut self, slug_policy: SlugPolicy) -> Self {
+        self.slug_policy = slug_policy;
+        self
+    }
+
+    // Slugs are unique within an organization. `assigned` holds slugs already given out in the
+    // same batch, keyed by organization.
+    async fn assign_slug(
+        &self,
+        event: &mut Event,
+        base: &str,
+        assigned: &mut HashMap<String, HashSet<String>>,
+    ) -> StoreResult<()> {
+        let assigned = assigned.entry(event.organization_id.clone()).or_default();
+
+        let mut taken = taken_slugs(
+            &self.pool,
+            "events",
+            Some(("organization", &event.organization_id)),
+            &event.id,
+            base,
+        )
+        .await?;
+        taken.extend(assigned.iter().cloned());
+
+        event.slug = unique_slug(base, &taken);
+        assigned.insert(event.slug.clone());
+
+        Ok(())
+    }
+
+    // A dedup item has to be in the event's own schema, so an event being inserted can't have
+    // one yet.
+    async fn ensure_dedup_items(&self, events: &[Event]) -> StoreResult<()> {
+        for event in events.iter().filter(|e| !e.dedup_item_id.is_empty()) {
+            let item: Option<(String,)> = sqlx::query_as(
+                "SELECT id FROM registration_schema_items WHERE id = ? AND event = ?",
+            )
+            .bind(&event.dedup_item_id)
+            .bind(&event.id)
+            .fetch_optional(&*self.pool)
+            .await
+            .fetch_err()?;
+
+            if item.is_none() {
+                return Err(Error::IdDoesNotExist(event.dedup_item_id.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Events matching the query in id order.
+    async fn fetch_events(
+        &self,
+        query: Option<&Query>,
+        page: Option<Pagination>,
+    ) -> StoreResult<Vec<Event>> {
+        let base_query =
+            "SELECT id, organization, name, starts_at, ends_at, registration_closes_at, require_account, slug, dedup_item, block_duplicates, registration_opens_at FROM events";
+        let query_string = match query {
+            Some(query) => format!("{} WHERE {} ORDER BY id", base_query, query.where_clause()),
+            None => format!("{} ORDER BY id", base_query),
+        };
+
+        let query_string = append_pagination(query_string, page.as_ref());
+
+        let query_builder = sqlx::query_as(&query_string);
+        let query_builder = match query {
+            Some(query) => query.bind(query_builder),
+            None => query_builder,
+        };
+
+        let query_builder = match &page {
+            Some(page) => page.bind(query_builder),
+            None => query_builder,
+        };
+
+        let rows: Vec<EventRow> = query_builder.fetch_all(&*self.pool).await.fetch_err()?;
+
+        let output_events = rows.into_iter().map(|row| row.into()).collect();
+
+        Ok(output_events)
     }
 }
 
 #[tonic::async_trait]
 impl Store for SqliteStore {
-    async fn upsert(&self, events: Vec<Event>) -> Result<Vec<Event>, Error> {
+    async fn upsert(&self, events: Vec<Event>) -> StoreResult<Vec<Event>> {
+        self.upsert_with_outcomes(events)
+            .await
+            .map(|(events, _)| events)
+    }
+
+    async fn upsert_with_outcomes(
+        &self,
+        mut events: Vec<Event>,
+    ) -> StoreResult<(Vec<Event>, Vec<Outcome>)> {
+        if let Some(result) = nothing_to_do(&events) {
+            return result;
+        }
+
+        for event in events.iter_mut() {
+            event.name = normalize(&event.name, Normalization::Collapse);
+            normalize_dates(event)?;
+        }
+
+        ids_in_table(
+            &self.pool,
+            "organizations",
+            events.iter().map(|e| e.organization_id.as_str()),
+        )
+        .await?;
+
+        self.ensure_dedup_items(&events).await?;
+
         let (insert_events, mut update_events): (Vec<_>, Vec<_>) =
             events.into_iter().partition(|e| e.id == "");
 
@@ -122,30 +448,76 @@ impl Store for SqliteStore {
             .await?;
         }
 
-        let mut tx = self
-            .pool
-            .begin()
-            .await
-            .map_err(|e| Error::TransactionStartError(e))?;
-
-        let mut output_events = Vec::new();
-        if !insert_events.is_empty() {
-            let mut events_with_ids = insert_events
-                .into_iter()
-                .map(|mut e| {
-                    e.id = new_id();
-                    e
+        let existing: HashMap<String, EventRow> = if update_events.is_empty() {
+            HashMap::new()
+        } else {
+            let in_clause = vec!["?"; update_events.len()].join(", ");
+            let query = format!(
+                "SELECT id, organization, name, starts_at, ends_at, registration_closes_at, require_account, slug, dedup_item, block_duplicates, registration_opens_at FROM events WHERE id IN ({})",
+                in_clause
+            );
+            let rows: Vec<EventRow> = update_events
+                .iter()
+                .fold(sqlx::query_as(&query), |query_builder, event| {
+                    query_builder.bind(&event.id)
                 })
-                .collect::<Vec<_>>();
+                .fetch_all(&*self.pool)
+                .await
+                .fetch_err()?;
+
+            rows.into_iter().map(|row| (row.id.clone(), row)).collect()
+        };
+
+        // Moving an event through upsert would skip the checks that come with `move_event`.
+        if let Some(moved) = update_events.iter().find(|event| {
+            existing
+                .get(&event.id)
+                .is_some_and(|row| row.organization != event.organization_id)
+        }) {
+            return Err(Error::UseMoveEvent(moved.id.clone()));
+        }
+
+        let mut events_with_ids = insert_events
+            .into_iter()
+            .map(|mut e| {
+                e.id = new_id();
+                e
+            })
+            .collect::<Vec<_>>();
+
+        // Slugs are picked before the transaction starts, since reading first would keep it from
+        // taking the write lock while another upsert holds it. The unique index still guards
+        // against a concurrent upsert picking the same slug.
+        let mut assigned = HashMap::new();
+        for event in events_with_ids.iter_mut().chain(update_events.iter_mut()) {
+            let base = match existing.get(&event.id) {
+                Some(EventRow {
+                    name,
+                    slug: Some(slug),
+                    ..
+                }) if self.slug_policy == SlugPolicy::Stable || *name == event.name => slug.clone(),
+                _ => slugify(&event.name, "event"),
+            };
+
+            self.assign_slug(event, &base, &mut assigned).await?;
+        }
+
+        let mut tx = self.pool.begin().await.begin_err()?;
 
+        // Inserted events come out first.
+        let created = events_with_ids.len();
+        let mut output_events = Vec::new();
+        if !events_with_ids.is_empty() {
             let values_clause: String = itertools::Itertools::intersperse(
-                events_with_ids.iter().map(|_| "(?, ?, ?)"),
+                events_with_ids
+                    .iter()
+                    .map(|_| "(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"),
                 ", ",
             )
             .collect();
 
             let query = format!(
-                "INSERT INTO events(id, organization, name) VALUES {}",
+                "INSERT INTO events(id, organization, name, starts_at, ends_at, registration_closes_at, require_account, slug, dedup_item, block_duplicates, registration_opens_at) VALUES {}",
                 values_clause
             );
             let query_builder = sqlx::query(&query);
@@ -157,25 +529,42 @@ impl Store for SqliteStore {
                             .bind(&event.id)
                             .bind(&event.organization_id)
                             .bind(&event.name)
+                            .bind(nullable(&event.starts_at))
+                            .bind(nullable(&event.ends_at))
+                            .bind(nullable(&event.registration_closes_at))
+                            .bind(event.require_account)
+                            .bind(&event.slug)
+                            .bind(nullable(&event.dedup_item_id))
+                            .bind(event.block_duplicates)
+                            .bind(nullable(&event.registration_opens_at))
                     });
 
-            query_builder
-                .execute(&mut *tx)
-                .await
-                .map_err(|e| Error::InsertionError(e))?;
+            query_builder.execute(&mut *tx).await.insert_err()?;
             output_events.append(&mut events_with_ids);
         }
 
         if !update_events.is_empty() {
-            let values_clause: String =
-                itertools::Itertools::intersperse(update_events.iter().map(|_| "(?, ?, ?)"), ", ")
-                    .collect();
+            let values_clause: String = itertools::Itertools::intersperse(
+                update_events
+                    .iter()
+                    .map(|_| "(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"),
+                ", ",
+            )
+            .collect();
 
             let query = format!(
-                "WITH mydata(id, organization, name) AS (VALUES {}) 
+                "WITH mydata(id, organization, name, starts_at, ends_at, registration_closes_at, require_account, slug, dedup_item, block_duplicates, registration_opens_at) AS (VALUES {}) 
                 UPDATE events 
                 SET name = mydata.name,
-                organization = mydata.organization
+                organization = mydata.organization,
+                starts_at = mydata.starts_at,
+                ends_at = mydata.ends_at,
+                registration_closes_at = mydata.registration_closes_at,
+                require_account = mydata.require_account,
+                slug = mydata.slug,
+                dedup_item = mydata.dedup_item,
+                block_duplicates = mydata.block_duplicates,
+                registration_opens_at = mydata.registration_opens_at
                 FROM mydata WHERE events.id = mydata.id",
                 values_clause
             );
@@ -187,47 +576,50 @@ impl Store for SqliteStore {
                         .bind(&event.id)
                         .bind(&event.organization_id)
                         .bind(&event.name)
+                        .bind(nullable(&event.starts_at))
+                        .bind(nullable(&event.ends_at))
+                        .bind(nullable(&event.registration_closes_at))
+                        .bind(event.require_account)
+                        .bind(&event.slug)
+                        .bind(nullable(&event.dedup_item_id))
+                        .bind(event.block_duplicates)
+                        .bind(nullable(&event.registration_opens_at))
                 });
 
-            query_builder
-                .execute(&mut *tx)
-                .await
-                .map_err(|e| Error::UpdateError(e))?;
+            query_builder.execute(&mut *tx).await.update_err()?;
 
             output_events.append(&mut update_events);
         }
 
-        tx.commit().await.map_err(|e| Error::TransactionFailed(e))?;
-
-        Ok(output_events)
-    }
+        tx.commit().await.commit_err()?;
 
-    async fn query(&self, query: Option<&Query>) -> Result<Vec<Event>, Error> {
-        let base_query = "SELECT id, organization, name FROM events";
-        let query_string = match query {
-            Some(query) => format!("{} WHERE {}", base_query, query.where_clause()),
-            None => base_query.to_owned(),
-        };
+        let outcomes = output_events
+            .iter()
+            .enumerate()
+            .map(|(idx, event)| Outcome {
+                id: event.id.clone(),
+                created: idx < created,
+            })
+            .collect();
 
-        let query_builder = sqlx::query_as(&query_string);
-        let query_builder = match query {
-            Some(query) => query.bind(query_builder),
-            None => query_builder,
-        };
+        Ok((output_events, outcomes))
+    }
 
-        let rows: Vec<EventRow> = query_builder
-            .fetch_all(&*self.pool)
-            .await
-            .map_err(|e| Error::FetchError(e))?;
+    async fn query(&self, query: Option<&Query>) -> StoreResult<Vec<Event>> {
+        self.fetch_events(query, None).await
+    }
 
-        let output_events = rows.into_iter().map(|row| row.into()).collect();
+    async fn query_capped(&self, query: Option<&Query>, cap: u32) -> StoreResult<Capped<Event>> {
+        let events = self
+            .fetch_events(query, Some(Pagination::over_cap(cap)))
+            .await?;
 
-        Ok(output_events)
+        Ok(Capped::new(events, cap))
     }
 
-    async fn delete(&self, event_ids: &Vec<String>) -> Result<(), Error> {
-        if event_ids.is_empty() {
-            return Ok(());
+    async fn delete(&self, event_ids: &[String]) -> StoreResult<()> {
+        if let Some(result) = nothing_to_do(event_ids) {
+            return result;
         }
 
         ids_in_table(
@@ -246,12 +638,197 @@ impl Store for SqliteStore {
             .iter()
             .fold(query_builder, |query_builder, id| query_builder.bind(id));
 
+        query_builder.execute(&*self.pool).await.delete_err()?;
+
+        Ok(())
+    }
+
+    async fn update(&self, event: Event, fields: &[UpdateField]) -> StoreResult<Event> {
+        if fields.is_empty() {
+            return Err(Error::EmptyUpdateMask);
+        }
+
+        let mut tx = self.pool.begin().await.begin_err()?;
+
+        let row: Option<EventRow> = sqlx::query_as(
+            "SELECT id, organization, name, starts_at, ends_at, registration_closes_at, require_account, slug, dedup_item, block_duplicates, registration_opens_at FROM events WHERE id = ?",
+        )
+        .bind(&event.id)
+        .fetch_optional(&mut *tx)
+        .await
+        .fetch_err()?;
+
+        let mut updated: Event = row
+            .ok_or_else(|| Error::IdDoesNotExist(event.id.clone()))?
+            .into();
+
+        let previous_name = updated.name.clone();
+
+        // Dates are validated together, so merge the changes before checking them.
+        for field in fields {
+            field.copy(&event, &mut updated);
+        }
+        normalize_dates(&mut updated)?;
+
+        if self.slug_policy == SlugPolicy::Regenerate && updated.name != previous_name {
+            let base = slugify(&updated.name, "event");
+            self.assign_slug(&mut updated, &base, &mut HashMap::new())
+                .await?;
+
+            sqlx::query("UPDATE events SET slug = ? WHERE id = ?")
+                .bind(&updated.slug)
+                .bind(&updated.id)
+                .execute(&mut *tx)
+                .await
+                .update_err()?;
+        }
+
+        let set_clause: String = itertools::Itertools::intersperse(
+            fields.iter().map(|field| format!("{} = ?", field.column())),
+            ", ".to_owned(),
+        )
+        .collect();
+
+        let query = format!("UPDATE events SET {} WHERE id = ?", set_clause);
+        let query_builder = fields
+            .iter()
+            .fold(sqlx::query(&query), |query_builder, field| {
+                query_builder.bind(field.value(&updated))
+            });
+
         query_builder
+            .bind(&updated.id)
+            .execute(&mut *tx)
+            .await
+            .update_err()?;
+
+        tx.commit().await.commit_err()?;
+
+        Ok(updated)
+    }
+
+    async fn move_event(&self, event_id: &EventId, organization_id: &OrgId) -> StoreResult<Event> {
+        ids_in_table(&self.pool, "organizations", [organization_id.as_str()]).await?;
+
+        let row: Option<EventRow> = sqlx::query_as(
+            "SELECT id, organization, name, starts_at, ends_at, registration_closes_at, require_account, slug, dedup_item, block_duplicates, registration_opens_at FROM events WHERE id = ?",
+        )
+        .bind(event_id)
+        .fetch_optional(&*self.pool)
+        .await
+        .fetch_err()?;
+
+        let mut event: Event = row
+            .ok_or_else(|| Error::IdDoesNotExist(event_id.to_string()))?
+            .into();
+        if event.organization_id == organization_id.as_str() {
+            return Ok(event);
+        }
+
+        event.organization_id = organization_id.to_string();
+        let base = event.slug.clone();
+        self.assign_slug(&mut event, &base, &mut HashMap::new())
+            .await?;
+
+        sqlx::query("UPDATE events SET organization = ?, slug = ? WHERE id = ?")
+            .bind(&event.organization_id)
+            .bind(&event.slug)
+            .bind(&event.id)
             .execute(&*self.pool)
             .await
-            .map_err(|e| Error::DeleteError(e))?;
+            .update_err()?;
 
-        Ok(())
+        Ok(event)
+    }
+
+    async fn get_event_with_organization(
+        &self,
+        event_id: &EventId,
+    ) -> StoreResult<(Event, Organization)> {
+        let row: Option<EventWithOrganizationRow> = sqlx::query_as(
+            "SELECT events.id, events.organization, events.name, events.starts_at, events.ends_at, events.registration_closes_at, events.require_account, events.slug, events.dedup_item, events.block_duplicates, events.registration_opens_at, organizations.name AS organization_name, organizations.slug AS organization_slug
+            FROM events JOIN organizations ON events.organization = organizations.id
+            WHERE events.id = ?",
+        )
+        .bind(event_id)
+        .fetch_optional(&*self.pool)
+        .await
+        .fetch_err()?;
+
+        let row = row.ok_or_else(|| Error::IdDoesNotExist(event_id.to_string()))?;
+        let organization = Organization {
+            id: row.event.organization.clone(),
+            name: row.organization_name,
+            slug: row.organization_slug.unwrap_or_default(),
+        };
+
+        Ok((row.event.into(), organization))
+    }
+
+    async fn get_by_slug(&self, organization_id: &OrgId, slug: &str) -> StoreResult<Event> {
+        let row: Option<EventRow> = sqlx::query_as(
+            "SELECT id, organization, name, starts_at, ends_at, registration_closes_at, require_account, slug, dedup_item, block_duplicates, registration_opens_at FROM events WHERE organization = ? AND slug = ?",
+        )
+        .bind(organization_id)
+        .bind(slug)
+        .fetch_optional(&*self.pool)
+        .await
+        .fetch_err()?;
+
+        row.map(|row| row.into())
+            .ok_or_else(|| Error::SlugDoesNotExist(slug.to_owned()))
+    }
+
+    async fn event_belongs_to_org(
+        &self,
+        event_id: &EventId,
+        organization_id: &OrgId,
+    ) -> StoreResult<bool> {
+        let (belongs,): (bool,) = sqlx::query_as(
+            "SELECT EXISTS (SELECT 1 FROM events WHERE id = ? AND organization = ?)",
+        )
+        .bind(event_id)
+        .bind(organization_id)
+        .fetch_one(&*self.pool)
+        .await
+        .fetch_err()?;
+
+        Ok(belongs)
+    }
+
+    async fn create_series(
+        &self,
+        organization_id: &OrgId,
+        template: Event,
+        dates: &[(String, String)],
+    ) -> StoreResult<Vec<Event>> {
+        let events = dates
+            .iter()
+            .map(|(starts_at, ends_at)| {
+                // Named for the date where the event happens rather than the date in UTC.
+                let date = DateTime::parse_from_rfc3339(starts_at)
+                    .map_err(|_| Error::InvalidEventDates(starts_at.clone(), ends_at.clone()))?
+                    .date_naive();
+
+                Ok(Event {
+                    id: String::default(),
+                    organization_id: organization_id.to_string(),
+                    name: format!("{} {}", template.name, date.format("%Y-%m-%d")),
+                    starts_at: starts_at.clone(),
+                    ends_at: ends_at.clone(),
+                    registration_closes_at: String::default(),
+                    require_account: template.require_account,
+                    registration_opens_at: String::default(),
+                    slug: String::default(),
+                    // The new events have no schema yet to pick a dedup item from.
+                    dedup_item_id: String::default(),
+                    block_duplicates: template.block_duplicates,
+                })
+            })
+            .collect::<StoreResult<Vec<_>>>()?;
+
+        // Inserts all happen in the one upsert transaction.
+        self.upsert(events).await
     }
 }
 
@@ -261,11 +838,19 @@ mod tests {
 
     use sqlx::{migrate::MigrateDatabase, Sqlite, SqlitePool};
 
-    use common::proto::Event;
+    use common::proto::{Event, Organization};
 
-    use crate::store::{common::new_id, CompoundOperator, CompoundQuery, LogicalQuery};
+    use crate::store::{
+        common::new_id,
+        ids::{EventId, OrgId},
+        CompoundOperator, CompoundQuery, LogicalQuery, Outcome, TimeRangeQuery,
+    };
+    use crate::testing::closed_pool;
 
-    use super::{Error, EventRow, Query, SqliteStore, Store};
+    use super::{
+        registration_state_at, Error, EventRow, Query, RegistrationState, SlugPolicy, SqliteStore,
+        Store, UpdateField,
+    };
 
     struct Init {
         org: String,
@@ -306,7 +891,15 @@ mod tests {
         let event = Event {
             name: "Event 1".to_owned(),
             organization_id: init.org,
+            starts_at: "".to_owned(),
+            ends_at: "".to_owned(),
+            registration_closes_at: "".to_owned(),
             id: "".to_owned(),
+            require_account: false,
+            slug: String::default(),
+            dedup_item_id: String::default(),
+            block_duplicates: false,
+            registration_opens_at: String::default(),
         };
 
         let returned_events = store.upsert(vec![event.clone()]).await.unwrap();
@@ -314,11 +907,12 @@ mod tests {
         assert_eq!(returned_events.len(), 1);
         assert_eq!(event.name, returned_events[0].name);
 
-        let mut store_row: Vec<EventRow> =
-            sqlx::query_as("SELECT id, organization, name FROM events")
-                .fetch_all(&*db)
-                .await
-                .unwrap();
+        let mut store_row: Vec<EventRow> = sqlx::query_as(
+            "SELECT id, organization, name, starts_at, ends_at, registration_closes_at, require_account, slug, dedup_item, block_duplicates, registration_opens_at FROM events",
+        )
+        .fetch_all(&*db)
+        .await
+        .unwrap();
 
         assert_eq!(store_row.len(), 1);
 
@@ -354,7 +948,15 @@ mod tests {
         let event = Event {
             name: "Event 3".to_owned(),
             organization_id: init.org,
+            starts_at: "".to_owned(),
+            ends_at: "".to_owned(),
+            registration_closes_at: "".to_owned(),
             id: id_1,
+            require_account: false,
+            slug: String::default(),
+            dedup_item_id: String::default(),
+            block_duplicates: false,
+            registration_opens_at: String::default(),
         };
 
         let returned_events = store.upsert(vec![event.clone()]).await.unwrap();
@@ -363,23 +965,25 @@ mod tests {
         assert_eq!(event.name, returned_events[0].name);
         assert_eq!(event.id, returned_events[0].id);
 
-        let changed_store_row: Vec<EventRow> =
-            sqlx::query_as("SELECT id, organization, name FROM events WHERE id = ?")
-                .bind(&event.id)
-                .fetch_all(&*db)
-                .await
-                .unwrap();
+        let changed_store_row: Vec<EventRow> = sqlx::query_as(
+            "SELECT id, organization, name, starts_at, ends_at, registration_closes_at, require_account, slug, dedup_item, block_duplicates, registration_opens_at FROM events WHERE id = ?",
+        )
+        .bind(&event.id)
+        .fetch_all(&*db)
+        .await
+        .unwrap();
 
         assert_eq!(changed_store_row.len(), 1);
         assert_eq!(changed_store_row[0].name, event.name);
         assert_eq!(changed_store_row[0].id, event.id);
 
-        let unchanged_store_row: Vec<EventRow> =
-            sqlx::query_as("SELECT id, organization, name FROM events WHERE id = ?")
-                .bind(&id_2)
-                .fetch_all(&*db)
-                .await
-                .unwrap();
+        let unchanged_store_row: Vec<EventRow> = sqlx::query_as(
+            "SELECT id, organization, name, starts_at, ends_at, registration_closes_at, require_account, slug, dedup_item, block_duplicates, registration_opens_at FROM events WHERE id = ?",
+        )
+        .bind(&id_2)
+        .fetch_all(&*db)
+        .await
+        .unwrap();
 
         assert_eq!(unchanged_store_row.len(), 1);
         assert_eq!(unchanged_store_row[0].name, name_2);
@@ -399,7 +1003,15 @@ mod tests {
         let event = Event {
             name: "Event 1".to_owned(),
             organization_id: init.org,
+            starts_at: "".to_owned(),
+            ends_at: "".to_owned(),
+            registration_closes_at: "".to_owned(),
             id: new_id(),
+            require_account: false,
+            slug: String::default(),
+            dedup_item_id: String::default(),
+            block_duplicates: false,
+            registration_opens_at: String::default(),
         };
 
         let result = store.upsert(vec![event.clone()]).await;
@@ -416,12 +1028,16 @@ mod tests {
         Organization,
         CompoundQuery,
         NoResults,
+        EmptyAnd,
+        EmptyOr,
     }
     #[test_case(QueryTest::All ; "all")]
     #[test_case(QueryTest::Id ; "id")]
     #[test_case(QueryTest::Organization ; "organization")]
     #[test_case(QueryTest::CompoundQuery ; "compound query")]
     #[test_case(QueryTest::NoResults ; "no results")]
+    #[test_case(QueryTest::EmptyAnd ; "empty and")]
+    #[test_case(QueryTest::EmptyOr ; "empty or")]
     #[tokio::test]
     async fn query(test_name: QueryTest) {
         let init = init_db().await;
@@ -445,12 +1061,28 @@ mod tests {
             Event {
                 name: name_1.to_owned(),
                 organization_id: init.org.clone(),
+                starts_at: "".to_owned(),
+                ends_at: "".to_owned(),
+                registration_closes_at: "".to_owned(),
                 id: id_1.clone(),
+                require_account: false,
+                slug: String::default(),
+                dedup_item_id: String::default(),
+                block_duplicates: false,
+                registration_opens_at: String::default(),
             },
             Event {
                 name: name_2.to_owned(),
                 organization_id: init.org.clone(),
+                starts_at: "".to_owned(),
+                ends_at: "".to_owned(),
+                registration_closes_at: "".to_owned(),
                 id: id_2.clone(),
+                require_account: false,
+                slug: String::default(),
+                dedup_item_id: String::default(),
+                block_duplicates: false,
+                registration_opens_at: String::default(),
             },
         ];
 
@@ -491,14 +1123,33 @@ mod tests {
                 query: Some(Query::Id(LogicalQuery::Equals(new_id()))),
                 expected: vec![],
             },
+            QueryTest::EmptyAnd => TestCase {
+                query: Some(Query::CompoundQuery(CompoundQuery {
+                    operator: CompoundOperator::And,
+                    queries: Vec::new(),
+                })),
+                expected: events,
+            },
+            QueryTest::EmptyOr => TestCase {
+                query: Some(Query::CompoundQuery(CompoundQuery {
+                    operator: CompoundOperator::Or,
+                    queries: Vec::new(),
+                })),
+                expected: vec![],
+            },
         };
 
-        let mut returned_events = store.query(tc.query.as_ref()).await.unwrap();
-        returned_events.sort_by(|a, b| a.id.cmp(&b.id));
-        let mut expected_events = tc.expected;
-        expected_events.sort_by(|a, b| a.id.cmp(&b.id));
+        let returned_events = store.query(tc.query.as_ref()).await.unwrap();
+        let mut expected_ids = tc.expected.into_iter().map(|e| e.id).collect::<Vec<_>>();
+        expected_ids.sort();
 
-        assert_eq!(returned_events.len(), expected_events.len());
+        assert_eq!(
+            returned_events
+                .into_iter()
+                .map(|e| e.id)
+                .collect::<Vec<_>>(),
+            expected_ids
+        );
     }
 
     #[tokio::test]
@@ -525,13 +1176,14 @@ mod tests {
             SqliteStore::new(db)
         };
 
-        store.delete(&vec![id_1]).await.unwrap();
+        store.delete(&[id_1]).await.unwrap();
 
-        let mut store_row: Vec<EventRow> =
-            sqlx::query_as("SELECT id, organization, name FROM events")
-                .fetch_all(&*db)
-                .await
-                .unwrap();
+        let mut store_row: Vec<EventRow> = sqlx::query_as(
+            "SELECT id, organization, name, starts_at, ends_at, registration_closes_at, require_account, slug, dedup_item, block_duplicates, registration_opens_at FROM events",
+        )
+        .fetch_all(&*db)
+        .await
+        .unwrap();
 
         assert_eq!(store_row.len(), 1);
 
@@ -549,11 +1201,836 @@ mod tests {
         };
 
         let id = new_id();
-        let result = store.delete(&vec![id.clone()]).await;
+        let result = store.delete(std::slice::from_ref(&id)).await;
         match result {
             Ok(_) => panic!("no error returned"),
             Err(Error::IdDoesNotExist(err_id)) => assert_eq!(err_id, id),
             _ => panic!("incorrect error type: {:?}", result),
         }
     }
+
+    #[tokio::test]
+    async fn insert_normalizes_name() {
+        let init = init_db().await;
+        let db = Arc::new(init.db);
+        let store = SqliteStore::new(db.clone());
+
+        let event = Event {
+            name: "  Salsa  ".to_owned(),
+            organization_id: init.org,
+            starts_at: "".to_owned(),
+            ends_at: "".to_owned(),
+            registration_closes_at: "".to_owned(),
+            id: "".to_owned(),
+            require_account: false,
+            slug: String::default(),
+            dedup_item_id: String::default(),
+            block_duplicates: false,
+            registration_opens_at: String::default(),
+        };
+
+        let returned_events = store.upsert(vec![event]).await.unwrap();
+        assert_eq!(returned_events[0].name, "Salsa");
+
+        let store_rows: Vec<EventRow> = sqlx::query_as(
+            "SELECT id, organization, name, starts_at, ends_at, registration_closes_at, require_account, slug, dedup_item, block_duplicates, registration_opens_at FROM events WHERE name = ?",
+        )
+        .bind("Salsa")
+        .fetch_all(&*db)
+        .await
+        .unwrap();
+
+        assert_eq!(store_rows.len(), 1);
+        assert_eq!(store_rows[0].id, returned_events[0].id);
+    }
+
+    #[tokio::test]
+    async fn insert_with_dates() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let event = Event {
+            name: "Event 1".to_owned(),
+            organization_id: init.org,
+            starts_at: "2024-05-03T18:00:00-05:00".to_owned(),
+            ends_at: "2024-05-05T22:00:00-05:00".to_owned(),
+            registration_closes_at: "".to_owned(),
+            id: "".to_owned(),
+            require_account: false,
+            slug: String::default(),
+            dedup_item_id: String::default(),
+            block_duplicates: false,
+            registration_opens_at: String::default(),
+        };
+
+        let returned_events = store.upsert(vec![event]).await.unwrap();
+        assert_eq!(returned_events[0].starts_at, "2024-05-03T23:00:00Z");
+        assert_eq!(returned_events[0].ends_at, "2024-05-06T03:00:00Z");
+
+        let queried_events = store
+            .query(Some(&Query::Id(LogicalQuery::Equals(
+                returned_events[0].id.clone(),
+            ))))
+            .await
+            .unwrap();
+        assert_eq!(queried_events, returned_events);
+    }
+
+    #[test_case("2024-05-05T00:00:00Z", "2024-05-03T00:00:00Z" ; "inverted")]
+    #[test_case("not a date", "" ; "invalid format")]
+    #[tokio::test]
+    async fn insert_invalid_dates(starts_at: &str, ends_at: &str) {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let event = Event {
+            name: "Event 1".to_owned(),
+            organization_id: init.org,
+            starts_at: starts_at.to_owned(),
+            ends_at: ends_at.to_owned(),
+            registration_closes_at: "".to_owned(),
+            id: "".to_owned(),
+            require_account: false,
+            slug: String::default(),
+            dedup_item_id: String::default(),
+            block_duplicates: false,
+            registration_opens_at: String::default(),
+        };
+
+        let result = store.upsert(vec![event]).await;
+        match result {
+            Ok(_) => panic!("no error returned"),
+            Err(Error::InvalidEventDates(_, _)) => (),
+            _ => panic!("incorrect error type: {:?}", result),
+        }
+    }
+
+    #[test_case("", "", "2024-05-05T12:00:00Z", RegistrationState::Open ; "unbounded")]
+    #[test_case("2024-05-05T12:00:00Z", "", "2024-05-05T11:59:59Z", RegistrationState::NotYetOpen ; "before open")]
+    #[test_case("2024-05-05T12:00:00Z", "", "2024-05-05T12:00:00Z", RegistrationState::Open ; "at open")]
+    #[test_case("", "2024-05-05T12:00:00Z", "2024-05-05T11:59:59Z", RegistrationState::Open ; "before close")]
+    #[test_case("", "2024-05-05T12:00:00Z", "2024-05-05T12:00:00Z", RegistrationState::Closed ; "at close")]
+    #[test_case("2024-05-01T00:00:00Z", "2024-05-05T12:00:00Z", "2024-05-03T00:00:00Z", RegistrationState::Open ; "between open and close")]
+    #[test_case("2024-05-01T00:00:00Z", "2024-05-05T12:00:00Z", "2024-05-06T00:00:00Z", RegistrationState::Closed ; "after close")]
+    fn registration_state(opens_at: &str, closes_at: &str, now: &str, expected: RegistrationState) {
+        let now = now.parse().unwrap();
+        assert_eq!(registration_state_at(opens_at, closes_at, &now), expected);
+    }
+
+    enum DedupItemTest {
+        OwnItem,
+        OtherEventItem,
+        Unknown,
+    }
+
+    #[test_case(DedupItemTest::OwnItem ; "own item")]
+    #[test_case(DedupItemTest::OtherEventItem ; "other event item")]
+    #[test_case(DedupItemTest::Unknown ; "unknown")]
+    #[tokio::test]
+    async fn dedup_item(test_name: DedupItemTest) {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let mut events = store
+            .upsert(vec![
+                Event {
+                    name: "Event 1".to_owned(),
+                    organization_id: init.org.clone(),
+                    ..Default::default()
+                },
+                Event {
+                    name: "Event 2".to_owned(),
+                    organization_id: init.org.clone(),
+                    ..Default::default()
+                },
+            ])
+            .await
+            .unwrap();
+
+        let mut items = Vec::new();
+        for event in events.iter() {
+            let item = new_id();
+            sqlx::query(
+                "INSERT INTO registration_schema_items(id, event, idx, name, item_type, text_type_default, text_type_display) VALUES (?, ?, ?, ?, ?, ?, ?);",
+            )
+            .bind(&item)
+            .bind(&event.id)
+            .bind(0)
+            .bind("Email")
+            .bind("TextType")
+            .bind("")
+            .bind("SMALL")
+            .execute(&*store.pool)
+            .await
+            .unwrap();
+            items.push(item);
+        }
+
+        let mut event = events.remove(0);
+        event.dedup_item_id = match test_name {
+            DedupItemTest::OwnItem => items[0].clone(),
+            DedupItemTest::OtherEventItem => items[1].clone(),
+            DedupItemTest::Unknown => new_id(),
+        };
+        event.block_duplicates = true;
+
+        let result = store.upsert(vec![event.clone()]).await;
+        match test_name {
+            DedupItemTest::OwnItem => {
+                let stored = store
+                    .query(Some(&Query::Id(LogicalQuery::Equals(event.id.clone()))))
+                    .await
+                    .unwrap();
+                assert_eq!(stored, vec![event]);
+            }
+            DedupItemTest::OtherEventItem | DedupItemTest::Unknown => match result {
+                Err(Error::IdDoesNotExist(id)) => assert_eq!(id, event.dedup_item_id),
+                _ => panic!("expected IdDoesNotExist, got {:?}", result),
+            },
+        }
+    }
+
+    fn weekly_dates(weeks: usize) -> Vec<(String, String)> {
+        (0..weeks)
+            .map(|week| {
+                let day = 7 + 7 * week;
+                (
+                    format!("2024-05-{:02}T19:00:00-05:00", day),
+                    format!("2024-05-{:02}T21:00:00-05:00", day),
+                )
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn create_series() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let template = Event {
+            name: "Tuesday Class".to_owned(),
+            require_account: true,
+            ..Default::default()
+        };
+        let events = store
+            .create_series(&OrgId::from(&init.org), template, &weekly_dates(4))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            events
+                .iter()
+                .map(|event| (
+                    event.name.as_str(),
+                    event.starts_at.as_str(),
+                    event.ends_at.as_str()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "Tuesday Class 2024-05-07",
+                    "2024-05-08T00:00:00Z",
+                    "2024-05-08T02:00:00Z"
+                ),
+                (
+                    "Tuesday Class 2024-05-14",
+                    "2024-05-15T00:00:00Z",
+                    "2024-05-15T02:00:00Z"
+                ),
+                (
+                    "Tuesday Class 2024-05-21",
+                    "2024-05-22T00:00:00Z",
+                    "2024-05-22T02:00:00Z"
+                ),
+                (
+                    "Tuesday Class 2024-05-28",
+                    "2024-05-29T00:00:00Z",
+                    "2024-05-29T02:00:00Z"
+                ),
+            ]
+        );
+        assert!(events
+            .iter()
+            .all(|event| event.organization_id == init.org && event.require_account));
+
+        let mut stored = store.query(None).await.unwrap();
+        stored.sort_by(|a, b| a.starts_at.cmp(&b.starts_at));
+        assert_eq!(stored, events);
+    }
+
+    #[tokio::test]
+    async fn create_series_invalid_date() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let mut dates = weekly_dates(2);
+        dates[1].0 = "".to_owned();
+
+        let result = store
+            .create_series(&OrgId::from(&init.org), Event::default(), &dates)
+            .await;
+        match result {
+            Ok(_) => panic!("no error returned"),
+            Err(Error::InvalidEventDates(_, _)) => (),
+            _ => panic!("incorrect error type: {:?}", result),
+        }
+        assert!(store.query(None).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn query_upcoming() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let events = ["2024-01-01T00:00:00Z", "2024-06-01T00:00:00Z", ""]
+            .into_iter()
+            .enumerate()
+            .map(|(idx, starts_at)| Event {
+                name: format!("Event {}", idx),
+                organization_id: init.org.clone(),
+                starts_at: starts_at.to_owned(),
+                ends_at: "".to_owned(),
+                registration_closes_at: "".to_owned(),
+                id: "".to_owned(),
+                require_account: false,
+                slug: String::default(),
+                dedup_item_id: String::default(),
+                block_duplicates: false,
+                registration_opens_at: String::default(),
+            })
+            .collect();
+
+        let events = store.upsert(events).await.unwrap();
+
+        let returned_events = store
+            .query(Some(&Query::StartsAt(TimeRangeQuery {
+                start: Some("2024-03-01T00:00:00Z".to_owned()),
+                end: None,
+            })))
+            .await
+            .unwrap();
+
+        assert_eq!(returned_events, vec![events[1].clone()]);
+    }
+
+    #[test_case(2, true ; "over cap")]
+    #[test_case(3, false ; "at cap")]
+    #[tokio::test]
+    async fn query_capped(cap: u32, truncated: bool) {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let events = (0..3)
+            .map(|idx| Event {
+                name: format!("Event {}", idx),
+                organization_id: init.org.clone(),
+                starts_at: "".to_owned(),
+                ends_at: "".to_owned(),
+                registration_closes_at: "".to_owned(),
+                id: "".to_owned(),
+                require_account: false,
+                slug: String::default(),
+                dedup_item_id: String::default(),
+                block_duplicates: false,
+                registration_opens_at: String::default(),
+            })
+            .collect();
+
+        let mut events = store.upsert(events).await.unwrap();
+        events.sort_by(|a, b| a.id.cmp(&b.id));
+        events.truncate(cap as usize);
+
+        let capped = store.query_capped(None, cap).await.unwrap();
+        assert_eq!(capped.rows, events);
+        assert_eq!(capped.truncated, truncated);
+    }
+
+    #[tokio::test]
+    async fn insert_organization_does_not_exist() {
+        let init = init_db().await;
+        let db = Arc::new(init.db);
+        let store = SqliteStore::new(db.clone());
+
+        let org = new_id();
+        let result = store
+            .upsert(vec![Event {
+                organization_id: org.clone(),
+                name: "Event 1".to_owned(),
+                ..Default::default()
+            }])
+            .await;
+        match result {
+            Err(Error::IdDoesNotExist(id)) => assert_eq!(id, org),
+            _ => panic!("unexpected result: {:?}", result),
+        }
+
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM events")
+            .fetch_one(&*db)
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
+    async fn dated_event(store: &SqliteStore, org: &str) -> Event {
+        store
+            .upsert(vec![Event {
+                organization_id: org.to_owned(),
+                name: "Event 1".to_owned(),
+                starts_at: "2024-05-01T18:00:00Z".to_owned(),
+                ends_at: "2024-05-03T22:00:00Z".to_owned(),
+                registration_closes_at: "2024-04-25T00:00:00Z".to_owned(),
+                ..Default::default()
+            }])
+            .await
+            .unwrap()
+            .remove(0)
+    }
+
+    #[tokio::test]
+    async fn update_name_only() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+        let event = dated_event(&store, &init.org).await;
+
+        // Unmasked fields are ignored even when the client sends them blank.
+        let updated = store
+            .update(
+                Event {
+                    id: event.id.clone(),
+                    name: "Renamed".to_owned(),
+                    ..Default::default()
+                },
+                &[UpdateField::Name],
+            )
+            .await
+            .unwrap();
+
+        let expected = Event {
+            name: "Renamed".to_owned(),
+            ..event
+        };
+        assert_eq!(updated, expected);
+        assert_eq!(store.query(None).await.unwrap(), vec![expected]);
+    }
+
+    #[tokio::test]
+    async fn update_dates_only() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+        let event = dated_event(&store, &init.org).await;
+
+        let updated = store
+            .update(
+                Event {
+                    id: event.id.clone(),
+                    starts_at: "2024-06-01T18:00:00+02:00".to_owned(),
+                    ends_at: "2024-06-02T22:00:00Z".to_owned(),
+                    ..Default::default()
+                },
+                &[UpdateField::StartsAt, UpdateField::EndsAt],
+            )
+            .await
+            .unwrap();
+
+        let expected = Event {
+            starts_at: "2024-06-01T16:00:00Z".to_owned(),
+            ends_at: "2024-06-02T22:00:00Z".to_owned(),
+            ..event
+        };
+        assert_eq!(updated, expected);
+        assert_eq!(store.query(None).await.unwrap(), vec![expected]);
+    }
+
+    #[tokio::test]
+    async fn update_checks_merged_dates() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+        let event = dated_event(&store, &init.org).await;
+
+        // Moving only the end before the stored start is still invalid.
+        let result = store
+            .update(
+                Event {
+                    id: event.id.clone(),
+                    ends_at: "2024-04-01T00:00:00Z".to_owned(),
+                    ..Default::default()
+                },
+                &[UpdateField::EndsAt],
+            )
+            .await;
+
+        match result {
+            Err(Error::InvalidEventDates(_, _)) => {}
+            _ => panic!("unexpected result: {:?}", result),
+        }
+        assert_eq!(store.query(None).await.unwrap(), vec![event]);
+    }
+
+    #[tokio::test]
+    async fn update_empty_mask() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+        let event = dated_event(&store, &init.org).await;
+
+        let result = store.update(event, &[]).await;
+        match result {
+            Err(Error::EmptyUpdateMask) => {}
+            _ => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[tokio::test]
+    async fn update_mask_does_not_exist() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let id = new_id();
+        let result = store
+            .update(
+                Event {
+                    id: id.clone(),
+                    name: "Event 1".to_owned(),
+                    ..Default::default()
+                },
+                &[UpdateField::Name],
+            )
+            .await;
+
+        match result {
+            Err(Error::IdDoesNotExist(err_id)) => assert_eq!(err_id, id),
+            _ => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[tokio::test]
+    async fn empty_input() {
+        let store = SqliteStore::new(closed_pool().await);
+
+        assert!(store.upsert(Vec::new()).await.unwrap().is_empty());
+        store.delete(&Vec::new()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn get_event_with_organization() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+        let event = dated_event(&store, &init.org).await;
+
+        let (found_event, organization) = store
+            .get_event_with_organization(&EventId::from(&event.id))
+            .await
+            .unwrap();
+
+        assert_eq!(found_event, event);
+        assert_eq!(
+            organization,
+            Organization {
+                id: init.org,
+                name: "Organization 1".to_owned(),
+                slug: String::default(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn get_event_with_organization_does_not_exist() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+        let id = new_id();
+
+        let result = store.get_event_with_organization(&EventId::from(&id)).await;
+        match result {
+            Err(Error::IdDoesNotExist(missing)) => assert_eq!(missing, id),
+            _ => panic!("incorrect result: {:?}", result),
+        }
+    }
+
+    enum BelongsToOrgTest {
+        Matching,
+        Mismatched,
+        MissingEvent,
+    }
+
+    #[test_case(BelongsToOrgTest::Matching ; "matching")]
+    #[test_case(BelongsToOrgTest::Mismatched ; "mismatched")]
+    #[test_case(BelongsToOrgTest::MissingEvent ; "missing event")]
+    #[tokio::test]
+    async fn event_belongs_to_org(test_name: BelongsToOrgTest) {
+        let init = init_db().await;
+        let other_org = new_id();
+        sqlx::query("INSERT INTO organizations(id, name) VALUES (?, ?);")
+            .bind(&other_org)
+            .bind("Organization 2")
+            .execute(&init.db)
+            .await
+            .unwrap();
+
+        let store = SqliteStore::new(Arc::new(init.db));
+        let event = dated_event(&store, &init.org).await;
+
+        let (event_id, org_id, expected) = match test_name {
+            BelongsToOrgTest::Matching => (event.id, init.org, true),
+            BelongsToOrgTest::Mismatched => (event.id, other_org, false),
+            BelongsToOrgTest::MissingEvent => (new_id(), init.org, false),
+        };
+
+        assert_eq!(
+            store
+                .event_belongs_to_org(&EventId::from(&event_id), &OrgId::from(&org_id))
+                .await
+                .unwrap(),
+            expected
+        );
+    }
+
+    fn named_event(org: &str, name: &str) -> Event {
+        Event {
+            organization_id: org.to_owned(),
+            name: name.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn slugs_deduplicated() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let first = store
+            .upsert(vec![
+                named_event(&init.org, "Salsa Night"),
+                named_event(&init.org, "Salsa Night"),
+            ])
+            .await
+            .unwrap();
+        let third = store
+            .upsert(vec![named_event(&init.org, "Salsa  Night!")])
+            .await
+            .unwrap();
+
+        assert_eq!(first[0].slug, "salsa-night");
+        assert_eq!(first[1].slug, "salsa-night-2");
+        assert_eq!(third[0].slug, "salsa-night-3");
+    }
+
+    #[tokio::test]
+    async fn slugs_scoped_to_organization() {
+        let init = init_db().await;
+        let other_org = new_id();
+        sqlx::query("INSERT INTO organizations(id, name) VALUES (?, ?);")
+            .bind(&other_org)
+            .bind("Organization 2")
+            .execute(&init.db)
+            .await
+            .unwrap();
+
+        let store = SqliteStore::new(Arc::new(init.db));
+        let events = store
+            .upsert(vec![
+                named_event(&init.org, "Salsa Night"),
+                named_event(&other_org, "Salsa Night"),
+            ])
+            .await
+            .unwrap();
+
+        assert_eq!(events[0].slug, "salsa-night");
+        assert_eq!(events[1].slug, "salsa-night");
+    }
+
+    #[tokio::test]
+    async fn upsert_rejects_org_change() {
+        let init = init_db().await;
+        let other_org = new_id();
+        sqlx::query("INSERT INTO organizations(id, name) VALUES (?, ?);")
+            .bind(&other_org)
+            .bind("Organization 2")
+            .execute(&init.db)
+            .await
+            .unwrap();
+
+        let store = SqliteStore::new(Arc::new(init.db));
+        let event = store
+            .upsert(vec![named_event(&init.org, "Salsa Night")])
+            .await
+            .unwrap()
+            .remove(0);
+
+        let result = store
+            .upsert(vec![Event {
+                organization_id: other_org,
+                ..event.clone()
+            }])
+            .await;
+        match result {
+            Err(Error::UseMoveEvent(id)) => assert_eq!(id, event.id),
+            _ => panic!("incorrect result: {:?}", result),
+        }
+
+        let renamed = store
+            .upsert(vec![Event {
+                name: "Bachata Night".to_owned(),
+                require_account: true,
+                ..event.clone()
+            }])
+            .await
+            .unwrap()
+            .remove(0);
+        assert_eq!(renamed.organization_id, event.organization_id);
+        assert_eq!(renamed.name, "Bachata Night");
+        assert!(renamed.require_account);
+    }
+
+    #[tokio::test]
+    async fn upsert_outcomes() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+        let existing = store
+            .upsert(vec![named_event(&init.org, "Salsa Night")])
+            .await
+            .unwrap()
+            .remove(0);
+
+        let (events, outcomes) = store
+            .upsert_with_outcomes(vec![
+                Event {
+                    name: "Bachata Night".to_owned(),
+                    ..existing.clone()
+                },
+                named_event(&init.org, "Swing Night"),
+            ])
+            .await
+            .unwrap();
+
+        let new = events.iter().find(|e| e.name == "Swing Night").unwrap();
+        assert_eq!(outcomes.len(), 2);
+        assert!(outcomes.contains(&Outcome {
+            id: new.id.clone(),
+            created: true,
+        }));
+        assert!(outcomes.contains(&Outcome {
+            id: existing.id.clone(),
+            created: false,
+        }));
+    }
+
+    #[tokio::test]
+    async fn move_event() {
+        let init = init_db().await;
+        let other_org = new_id();
+        sqlx::query("INSERT INTO organizations(id, name) VALUES (?, ?);")
+            .bind(&other_org)
+            .bind("Organization 2")
+            .execute(&init.db)
+            .await
+            .unwrap();
+
+        let store = SqliteStore::new(Arc::new(init.db));
+        let events = store
+            .upsert(vec![
+                named_event(&init.org, "Salsa Night"),
+                named_event(&init.org, "Bachata Night"),
+                named_event(&other_org, "Bachata Night"),
+            ])
+            .await
+            .unwrap();
+
+        let moved = store
+            .move_event(&EventId::from(&events[0].id), &OrgId::from(&other_org))
+            .await
+            .unwrap();
+        assert_eq!(moved.organization_id, other_org);
+        assert_eq!(moved.slug, "salsa-night");
+
+        // The slug is already used in the new organization.
+        let moved = store
+            .move_event(&EventId::from(&events[1].id), &OrgId::from(&other_org))
+            .await
+            .unwrap();
+        assert_eq!(moved.slug, "bachata-night-2");
+
+        let queried = store
+            .query(Some(&Query::Organization(LogicalQuery::Equals(
+                other_org.clone(),
+            ))))
+            .await
+            .unwrap();
+        assert_eq!(queried.len(), 3);
+
+        let result = store
+            .move_event(&EventId::from(&events[2].id), &OrgId::from(&new_id()))
+            .await;
+        assert!(matches!(result, Err(Error::IdDoesNotExist(_))));
+    }
+
+    #[tokio::test]
+    async fn get_by_slug() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let events = store
+            .upsert(vec![
+                named_event(&init.org, "Salsa Night"),
+                named_event(&init.org, "Salsa Night"),
+            ])
+            .await
+            .unwrap();
+
+        let found = store
+            .get_by_slug(&OrgId::from(&init.org), "salsa-night-2")
+            .await
+            .unwrap();
+        assert_eq!(found, events[1]);
+
+        let result = store
+            .get_by_slug(&OrgId::from(&new_id()), "salsa-night")
+            .await;
+        match result {
+            Err(Error::SlugDoesNotExist(slug)) => assert_eq!(slug, "salsa-night"),
+            _ => panic!("incorrect result: {:?}", result),
+        }
+    }
+
+    #[test_case(SlugPolicy::Stable, "salsa-night", "salsa-night" ; "stable")]
+    #[test_case(SlugPolicy::Regenerate, "bachata-night", "kizomba-night" ; "regenerate")]
+    #[tokio::test]
+    async fn slug_on_rename(slug_policy: SlugPolicy, after_upsert: &str, after_update: &str) {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db)).with_slug_policy(slug_policy);
+
+        let event = store
+            .upsert(vec![named_event(&init.org, "Salsa Night")])
+            .await
+            .unwrap()
+            .remove(0);
+
+        let upserted = store
+            .upsert(vec![Event {
+                name: "Bachata Night".to_owned(),
+                ..event.clone()
+            }])
+            .await
+            .unwrap()
+            .remove(0);
+        assert_eq!(upserted.slug, after_upsert);
+
+        // Partial updates follow the same policy.
+        let updated = store
+            .update(
+                Event {
+                    name: "Kizomba Night".to_owned(),
+                    ..event
+                },
+                &[UpdateField::Name],
+            )
+            .await
+            .unwrap();
+        assert_eq!(updated.slug, after_update);
+        assert_eq!(
+            store
+                .get_by_slug(&OrgId::from(&init.org), after_update)
+                .await
+                .unwrap(),
+            updated
+        );
+    }
 }
@@ -0,0 +1,94 @@
+//! Ids taken by store methods, one type per kind of entity, so an id for one kind can't be passed
+//! where another is expected:
+//!
+//! ```compile_fail
+//! use odr_server::store::ids::{EventId, OrgId};
+//!
+//! fn event_only(_: &EventId) {}
+//!
+//! event_only(&OrgId::from("org"));
+//! ```
+//!
+//! Ids still travel as plain strings in protos, and bind to queries as the strings they wrap.
+
+use std::fmt::{self, Display, Formatter};
+
+macro_rules! id_type {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord, sqlx::Type)]
+        #[sqlx(transparent)]
+        pub struct $name(String);
+
+        impl $name {
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(id: String) -> Self {
+                $name(id)
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(id: &str) -> Self {
+                $name(id.to_owned())
+            }
+        }
+
+        impl From<&String> for $name {
+            fn from(id: &String) -> Self {
+                $name(id.clone())
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+    };
+}
+
+id_type!(EventId);
+id_type!(OrgId);
+id_type!(UserId);
+id_type!(RegistrationId);
+id_type!(
+    /// A registration schema item, at the top level or inside a group.
+    SchemaItemId
+);
+id_type!(TemplateId);
+
+/// Wraps each id in a list, for store methods that take several ids of one kind.
+pub fn ids<T: From<String>>(ids: impl IntoIterator<Item = String>) -> Vec<T> {
+    ids.into_iter().map(T::from).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ids, EventId, RegistrationId};
+
+    #[test]
+    fn round_trip() {
+        let id = EventId::from("abc");
+        assert_eq!(id.as_str(), "abc");
+        assert_eq!(id.to_string(), "abc");
+        assert_eq!(String::from(id.clone()), "abc");
+        assert_eq!(EventId::from("abc".to_owned()), id);
+
+        let wrapped: Vec<RegistrationId> = ids(vec!["a".to_owned(), "b".to_owned()]);
+        assert_eq!(
+            wrapped,
+            vec![RegistrationId::from("a"), RegistrationId::from("b")]
+        );
+    }
+}
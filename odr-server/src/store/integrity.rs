@@ -0,0 +1,249 @@
+use sqlx::SqlitePool;
+
+use super::{IntoStoreError as _, StoreResult};
+
+/// Rows whose references point at rows that no longer exist, by id. Foreign keys keep these from
+/// being written, but databases edited with foreign keys off can still hold them.
+#[derive(Debug, Default, PartialEq)]
+pub struct IntegrityReport {
+    /// Registration items whose registration is missing.
+    pub orphaned_registration_items: Vec<String>,
+    /// Select options whose schema item is missing.
+    pub orphaned_select_options: Vec<String>,
+    /// Permissions whose user, organization, or event is missing.
+    pub dangling_permissions: Vec<String>,
+    /// Registrations whose event is missing.
+    pub orphaned_registrations: Vec<String>,
+}
+
+impl IntegrityReport {
+    pub fn is_clean(&self) -> bool {
+        self.orphaned_registration_items.is_empty()
+            && self.orphaned_select_options.is_empty()
+            && self.dangling_permissions.is_empty()
+            && self.orphaned_registrations.is_empty()
+    }
+}
+
+async fn ids(pool: &SqlitePool, query: &str) -> StoreResult<Vec<String>> {
+    let rows: Vec<(String,)> = sqlx::query_as(query).fetch_all(pool).await.fetch_err()?;
+
+    Ok(rows.into_iter().map(|(id,)| id).collect())
+}
+
+/// Scans the database for orphaned rows. Nothing is changed; the report is left for an
+/// administrator to act on.
+pub async fn check_integrity(pool: &SqlitePool) -> StoreResult<IntegrityReport> {
+    let report = IntegrityReport {
+        orphaned_registration_items: ids(
+            pool,
+            "SELECT id FROM registration_items
+            WHERE registration NOT IN (SELECT id FROM registrations)
+            ORDER BY id",
+        )
+        .await?,
+        orphaned_select_options: ids(
+            pool,
+            "SELECT id FROM registration_schema_select_options
+            WHERE schema_item NOT IN (SELECT id FROM registration_schema_items)
+            ORDER BY id",
+        )
+        .await?,
+        dangling_permissions: ids(
+            pool,
+            "SELECT id FROM permissions
+            WHERE user NOT IN (SELECT id FROM users)
+                OR (organization IS NOT NULL AND organization NOT IN (SELECT id FROM organizations))
+                OR (event IS NOT NULL AND event NOT IN (SELECT id FROM events))
+            ORDER BY id",
+        )
+        .await?,
+        orphaned_registrations: ids(
+            pool,
+            "SELECT id FROM registrations
+            WHERE event NOT IN (SELECT id FROM events)
+            ORDER BY id",
+        )
+        .await?,
+    };
+
+    if !report.is_clean() {
+        log::warn!("integrity check found orphaned rows: {:?}", report);
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use sqlx::{
+        migrate::MigrateDatabase, sqlite::SqliteConnectOptions, ConnectOptions, Sqlite, SqlitePool,
+    };
+
+    use super::{check_integrity, IntegrityReport};
+    use crate::store::common::new_id;
+
+    async fn init_db() -> SqlitePool {
+        let db_url = "sqlite://:memory:";
+        Sqlite::create_database(db_url).await.unwrap();
+
+        let db = SqlitePool::connect_with(
+            SqliteConnectOptions::from_str(db_url)
+                .unwrap()
+                .log_statements(log::LevelFilter::Trace),
+        )
+        .await
+        .unwrap();
+        sqlx::migrate!("../migrations").run(&db).await.unwrap();
+
+        db
+    }
+
+    // Inserts one row of each kind the check looks at, all with their references intact, and
+    // returns the user's id.
+    async fn valid_rows(db: &SqlitePool) -> String {
+        let org = new_id();
+        let event = new_id();
+        let user = new_id();
+        let registration = new_id();
+        let schema_item = new_id();
+
+        sqlx::query("INSERT INTO organizations(id, name) VALUES (?, ?)")
+            .bind(&org)
+            .bind("Org")
+            .execute(db)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO events(id, organization, name) VALUES (?, ?, ?)")
+            .bind(&event)
+            .bind(&org)
+            .bind("Event")
+            .execute(db)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO users(id, email, display_name) VALUES (?, ?, ?)")
+            .bind(&user)
+            .bind("user@example.com")
+            .bind("User")
+            .execute(db)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO permissions(id, user, role, event) VALUES (?, ?, ?, ?)")
+            .bind(new_id())
+            .bind(&user)
+            .bind("EVENT_ADMIN")
+            .bind(&event)
+            .execute(db)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO registration_schema_items(id, event, idx, name, item_type, text_type_default, text_type_display) VALUES (?, ?, ?, ?, ?, ?, ?)")
+            .bind(&schema_item)
+            .bind(&event)
+            .bind(0)
+            .bind("Name")
+            .bind("TextType")
+            .bind("")
+            .bind("SMALL")
+            .execute(db)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO registrations(id, event) VALUES (?, ?)")
+            .bind(&registration)
+            .bind(&event)
+            .execute(db)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO registration_items(id, registration, schema_item, value) VALUES (?, ?, ?, ?)",
+        )
+        .bind(new_id())
+        .bind(&registration)
+        .bind(&schema_item)
+        .bind("Alex")
+        .execute(db)
+        .await
+        .unwrap();
+
+        user
+    }
+
+    #[tokio::test]
+    async fn clean() {
+        let db = init_db().await;
+        valid_rows(&db).await;
+
+        let report = check_integrity(&db).await.unwrap();
+        assert!(report.is_clean());
+        assert_eq!(report, IntegrityReport::default());
+    }
+
+    #[tokio::test]
+    async fn reports_orphans() {
+        let db = init_db().await;
+        let user = valid_rows(&db).await;
+
+        let registration_item = new_id();
+        let select_option = new_id();
+        let permission = new_id();
+        let registration = new_id();
+
+        // Orphans can only be written with foreign keys off.
+        let mut conn = db.acquire().await.unwrap();
+        sqlx::query("PRAGMA foreign_keys = OFF")
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO registration_items(id, registration, schema_item, value) VALUES (?, ?, ?, ?)",
+        )
+        .bind(&registration_item)
+        .bind(new_id())
+        .bind(new_id())
+        .bind("Sam")
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO registration_schema_select_options(id, schema_item, idx, name, product_id) VALUES (?, ?, ?, ?, ?)")
+            .bind(&select_option)
+            .bind(new_id())
+            .bind(0)
+            .bind("Lead")
+            .bind("")
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO permissions(id, user, role, event) VALUES (?, ?, ?, ?)")
+            .bind(&permission)
+            .bind(&user)
+            .bind("EVENT_VIEWER")
+            .bind(new_id())
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO registrations(id, event) VALUES (?, ?)")
+            .bind(&registration)
+            .bind(new_id())
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        sqlx::query("PRAGMA foreign_keys = ON")
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        drop(conn);
+
+        let report = check_integrity(&db).await.unwrap();
+        assert!(!report.is_clean());
+        assert_eq!(
+            report,
+            IntegrityReport {
+                orphaned_registration_items: vec![registration_item],
+                orphaned_select_options: vec![select_option],
+                dangling_permissions: vec![permission],
+                orphaned_registrations: vec![registration],
+            }
+        );
+    }
+}
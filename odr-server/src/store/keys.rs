@@ -4,7 +4,10 @@ use chrono::{DateTime, Utc};
 use ed25519_dalek::{SecretKey, SigningKey};
 use sqlx::SqlitePool;
 
-use super::{common::new_id, Error};
+use super::{
+    common::{format_timestamp, new_id, nothing_to_do, parse_timestamp},
+    Error, IntoStoreError as _, StoreResult,
+};
 
 #[derive(Debug, PartialEq)]
 pub struct Key {
@@ -15,11 +18,11 @@ pub struct Key {
 
 #[tonic::async_trait]
 pub trait Store: Send + Sync + 'static {
-    async fn insert(&self, mut key: Key) -> Result<Key, Error>;
-    async fn list(&self, ids: Vec<&str>) -> Result<Vec<Key>, Error>;
-    async fn get_newest(&self) -> Result<Key, Error>;
-    async fn has(&self) -> Result<bool, Error>;
-    async fn delete(&self, ids: Vec<String>) -> Result<(), Error>;
+    async fn insert(&self, mut key: Key) -> StoreResult<Key>;
+    async fn list(&self, ids: Vec<&str>) -> StoreResult<Vec<Key>>;
+    async fn get_newest(&self) -> StoreResult<Key>;
+    async fn has(&self) -> StoreResult<bool>;
+    async fn delete(&self, ids: Vec<String>) -> StoreResult<()>;
 }
 
 pub struct SqliteStore {
@@ -36,7 +39,7 @@ impl SqliteStore {
 struct KeyRow {
     id: String,
     eddsa_key: Vec<u8>,
-    created_at: i64,
+    created_at: String,
 }
 
 impl TryFrom<KeyRow> for Key {
@@ -51,15 +54,17 @@ impl TryFrom<KeyRow> for Key {
         Ok(Key {
             id: row.id,
             key: SigningKey::from_bytes(&key_bytes),
-            created_at: DateTime::<Utc>::from_timestamp(row.created_at, 0)
-                .ok_or_else(|| Error::ColumnParseError("created_at"))?,
+            created_at: parse_timestamp(&row.created_at)
+                .ok()
+                .flatten()
+                .ok_or(Error::ColumnParseError("created_at"))?,
         })
     }
 }
 
 #[tonic::async_trait]
 impl Store for SqliteStore {
-    async fn insert(&self, mut key: Key) -> Result<Key, Error> {
+    async fn insert(&self, mut key: Key) -> StoreResult<Key> {
         key.id = new_id();
 
         sqlx::query(
@@ -70,15 +75,15 @@ impl Store for SqliteStore {
         )
         .bind(&key.id)
         .bind(key.key.to_bytes().as_slice())
-        .bind(key.created_at.timestamp())
+        .bind(format_timestamp(&key.created_at))
         .execute(&*self.pool)
         .await
-        .map_err(|e| Error::InsertionError(e))?;
+        .insert_err()?;
 
         Ok(key)
     }
 
-    async fn get_newest(&self) -> Result<Key, Error> {
+    async fn get_newest(&self) -> StoreResult<Key> {
         let row: KeyRow = sqlx::query_as(
             r#"
             SELECT id, eddsa_key, MAX(created_at) AS created_at
@@ -87,12 +92,12 @@ impl Store for SqliteStore {
         )
         .fetch_one(&*self.pool)
         .await
-        .map_err(|e| Error::FetchError(e))?;
+        .fetch_err()?;
 
         row.try_into()
     }
 
-    async fn has(&self) -> Result<bool, Error> {
+    async fn has(&self) -> StoreResult<bool> {
         let row: (bool,) = sqlx::query_as(
             r#"
             SELECT EXISTS(SELECT 1 FROM keys)
@@ -100,18 +105,18 @@ impl Store for SqliteStore {
         )
         .fetch_one(&*self.pool)
         .await
-        .map_err(|e| Error::FetchError(e))?;
+        .fetch_err()?;
 
         Ok(row.0)
     }
 
-    async fn list(&self, ids: Vec<&str>) -> Result<Vec<Key>, Error> {
+    async fn list(&self, ids: Vec<&str>) -> StoreResult<Vec<Key>> {
         let base_query = "SELECT id, eddsa_key, created_at FROM keys";
         if ids.is_empty() {
             let rows: Vec<KeyRow> = sqlx::query_as(base_query)
                 .fetch_all(&*self.pool)
                 .await
-                .map_err(|e| Error::FetchError(e))?;
+                .fetch_err()?;
 
             let keys = rows
                 .into_iter()
@@ -129,10 +134,7 @@ impl Store for SqliteStore {
 
         let query_builder = sqlx::query_as(&query);
         let query_builder = ids.iter().fold(query_builder, |qb, id| qb.bind(id));
-        let rows: Vec<KeyRow> = query_builder
-            .fetch_all(&*self.pool)
-            .await
-            .map_err(|e| Error::FetchError(e))?;
+        let rows: Vec<KeyRow> = query_builder.fetch_all(&*self.pool).await.fetch_err()?;
 
         Ok(rows
             .into_iter()
@@ -140,7 +142,11 @@ impl Store for SqliteStore {
             .collect::<Result<Vec<_>, _>>()?)
     }
 
-    async fn delete(&self, ids: Vec<String>) -> Result<(), Error> {
+    async fn delete(&self, ids: Vec<String>) -> StoreResult<()> {
+        if let Some(result) = nothing_to_do(&ids) {
+            return result;
+        }
+
         let where_clause =
             itertools::Itertools::intersperse(std::iter::repeat("id = ?").take(ids.len()), " OR ")
                 .collect::<String>();
@@ -148,10 +154,7 @@ impl Store for SqliteStore {
         let query = format!("DELETE FROM keys WHERE {}", where_clause);
         let query_builder = sqlx::query(&query);
         let query_builder = ids.iter().fold(query_builder, |qb, id| qb.bind(id));
-        query_builder
-            .execute(&*self.pool)
-            .await
-            .map_err(|e| Error::DeleteError(e))?;
+        query_builder.execute(&*self.pool).await.delete_err()?;
 
         Ok(())
     }
@@ -169,7 +172,8 @@ mod tests {
     };
 
     use super::{Key, SqliteStore, Store};
-    use crate::store::common::new_id;
+    use crate::store::common::{format_timestamp, new_id};
+    use crate::testing::closed_pool;
 
     struct Init {
         db: SqlitePool,
@@ -215,10 +219,10 @@ mod tests {
         )
         .bind(&old_key.id)
         .bind(old_key.key.to_bytes().as_slice())
-        .bind(old_key.created_at.timestamp())
+        .bind(format_timestamp(&old_key.created_at))
         .bind(&new_key.id)
         .bind(new_key.key.to_bytes().as_slice())
-        .bind(new_key.created_at.timestamp())
+        .bind(format_timestamp(&new_key.created_at))
         .execute(&init.db)
         .await
         .unwrap();
@@ -229,4 +233,11 @@ mod tests {
 
         assert_eq!(returned_key, new_key);
     }
+
+    #[tokio::test]
+    async fn empty_input() {
+        let store = SqliteStore::new(closed_pool().await);
+
+        store.delete(Vec::new()).await.unwrap();
+    }
 }
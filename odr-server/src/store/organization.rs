@@ -1,8 +1,13 @@
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
 use super::{
-    common::{ids_in_table, new_id},
-    Bindable as _, Error, Queryable as _,
+    append_pagination,
+    common::{ids_in_table, new_id, nothing_to_do, slugify, taken_slugs, unique_slug, SlugPolicy},
+    ids::OrgId,
+    Bindable as _, Error, IntoStoreError as _, Pagination, Queryable as _, StoreResult,
 };
 use common::proto::Organization;
 use sqlx::SqlitePool;
@@ -11,6 +16,7 @@ use sqlx::SqlitePool;
 struct OrganizationRow {
     id: String,
     name: String,
+    slug: Option<String>,
 }
 
 impl From<OrganizationRow> for Organization {
@@ -18,6 +24,7 @@ impl From<OrganizationRow> for Organization {
         Organization {
             id: row.id,
             name: row.name,
+            slug: row.slug.unwrap_or_default(),
         }
     }
 }
@@ -36,6 +43,8 @@ pub type IdQuery = super::LogicalQuery<IdField>;
 
 pub enum Query {
     Id(IdQuery),
+    /// Organizations whose name contains the string. Case-insensitive for ASCII letters.
+    NameContains(String),
     CompoundQuery(super::CompoundQuery<Query>),
 }
 
@@ -43,11 +52,18 @@ impl super::Queryable for Query {
     fn where_clause(&self) -> String {
         match self {
             Query::Id(q) => q.where_clause(),
+            Query::NameContains(_) => "name LIKE ? ESCAPE '\\'".to_owned(),
             Query::CompoundQuery(compound_query) => compound_query.where_clause(),
         }
     }
 }
 
+impl super::Compound for Query {
+    fn compound(query: super::CompoundQuery<Query>) -> Self {
+        Query::CompoundQuery(query)
+    }
+}
+
 impl<'q, DB: sqlx::Database> super::Bindable<'q, DB> for Query
 where
     <IdField as super::Field>::Item: sqlx::Type<DB> + sqlx::Encode<'q, DB>,
@@ -63,6 +79,13 @@ where
     ) -> sqlx::query::QueryAs<'q, DB, O, <DB as sqlx::database::HasArguments<'q>>::Arguments> {
         match self {
             Query::Id(q) => q.bind(query_builder),
+            Query::NameContains(substring) => query_builder.bind(format!(
+                "%{}%",
+                substring
+                    .replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_")
+            )),
             Query::CompoundQuery(compound_query) => compound_query.bind(query_builder),
         }
     }
@@ -70,26 +93,83 @@ where
 
 #[tonic::async_trait]
 pub trait Store: Send + Sync + 'static {
-    async fn upsert(&self, organizations: Vec<Organization>) -> Result<Vec<Organization>, Error>;
-    async fn query(&self, query: Option<&Query>) -> Result<Vec<Organization>, Error>;
-    async fn delete(&self, ids: &Vec<String>) -> Result<(), Error>;
+    async fn upsert(&self, organizations: Vec<Organization>) -> StoreResult<Vec<Organization>>;
+    /// Organizations are returned in id order.
+    async fn query(&self, query: Option<&Query>) -> StoreResult<Vec<Organization>>;
+    /// Like query, but returns at most `page.limit` organizations after skipping `page.offset`
+    /// of them.
+    async fn query_page(
+        &self,
+        query: Option<&Query>,
+        page: Pagination,
+    ) -> StoreResult<Vec<Organization>>;
+    /// The number of organizations the query matches.
+    async fn count(&self, query: Option<&Query>) -> StoreResult<u64>;
+    async fn get_by_slug(&self, slug: &str) -> StoreResult<Organization>;
+    async fn delete(&self, ids: &[String]) -> StoreResult<()>;
+    /// Copies the organization under a new name, along with its events and their schemas, giving
+    /// everything fresh ids. Registrations and permissions stay with the source. Either the whole
+    /// copy is made or none of it is.
+    async fn clone_organization(&self, source_id: &OrgId, name: &str) -> StoreResult<Organization>;
 }
 
 #[derive(Debug)]
 pub struct SqliteStore {
     pool: Arc<SqlitePool>,
+    slug_policy: SlugPolicy,
 }
 
 impl SqliteStore {
     pub fn new(pool: Arc<SqlitePool>) -> Self {
-        SqliteStore { pool }
+        SqliteStore {
+            pool,
+            slug_policy: SlugPolicy::default(),
+        }
+    }
+
+    pub fn with_slug_policy(mut self, slug_policy: SlugPolicy) -> Self {
+        self.slug_policy = slug_policy;
+        self
+    }
+
+    async fn fetch(
+        &self,
+        query: Option<&Query>,
+        page: Option<Pagination>,
+    ) -> StoreResult<Vec<Organization>> {
+        let base_query = "SELECT id, name, slug FROM organizations";
+        let query_string = match query {
+            Some(query) => format!("{} WHERE {} ORDER BY id", base_query, query.where_clause()),
+            None => format!("{} ORDER BY id", base_query),
+        };
+
+        let query_string = append_pagination(query_string, page.as_ref());
+
+        let query_builder = sqlx::query_as(&query_string);
+        let query_builder = match query {
+            Some(query) => query.bind(query_builder),
+            None => query_builder,
+        };
+
+        let query_builder = match &page {
+            Some(page) => page.bind(query_builder),
+            None => query_builder,
+        };
+
+        let rows: Vec<OrganizationRow> = query_builder.fetch_all(&*self.pool).await.fetch_err()?;
+
+        Ok(rows.into_iter().map(|row| row.into()).collect())
     }
 }
 
 #[tonic::async_trait]
 impl Store for SqliteStore {
-    async fn upsert(&self, organizations: Vec<Organization>) -> Result<Vec<Organization>, Error> {
-        let (inserts, updates): (Vec<_>, Vec<_>) = organizations
+    async fn upsert(&self, organizations: Vec<Organization>) -> StoreResult<Vec<Organization>> {
+        if let Some(result) = nothing_to_do(&organizations) {
+            return result;
+        }
+
+        let (inserts, mut updates): (Vec<_>, Vec<_>) = organizations
             .into_iter()
             .enumerate()
             .partition(|(_, org)| org.id == "");
@@ -104,7 +184,7 @@ impl Store for SqliteStore {
             .await?;
         }
 
-        let inserts = inserts
+        let mut inserts = inserts
             .into_iter()
             .map(|(idx, mut org)| {
                 org.id = new_id();
@@ -112,21 +192,59 @@ impl Store for SqliteStore {
             })
             .collect::<Vec<_>>();
 
-        let mut tx = self
-            .pool
-            .begin()
-            .await
-            .map_err(|e| Error::TransactionStartError(e))?;
+        let existing: HashMap<String, (String, Option<String>)> = if updates.is_empty() {
+            HashMap::new()
+        } else {
+            let in_clause = vec!["?"; updates.len()].join(", ");
+            let query = format!(
+                "SELECT id, name, slug FROM organizations WHERE id IN ({})",
+                in_clause
+            );
+            let rows: Vec<OrganizationRow> = updates
+                .iter()
+                .fold(sqlx::query_as(&query), |query_builder, (_, org)| {
+                    query_builder.bind(&org.id)
+                })
+                .fetch_all(&*self.pool)
+                .await
+                .fetch_err()?;
+
+            rows.into_iter()
+                .map(|row| (row.id, (row.name, row.slug)))
+                .collect()
+        };
+
+        // Slugs are picked before the transaction starts, since reading first would keep it from
+        // taking the write lock while another upsert holds it.
+        let mut assigned = HashSet::new();
+        for (_, org) in inserts.iter_mut().chain(updates.iter_mut()) {
+            let base = match existing.get(&org.id) {
+                Some((name, Some(slug)))
+                    if self.slug_policy == SlugPolicy::Stable || *name == org.name =>
+                {
+                    slug.clone()
+                }
+                _ => slugify(&org.name, "organization"),
+            };
+
+            let mut taken = taken_slugs(&self.pool, "organizations", None, &org.id, &base).await?;
+            taken.extend(assigned.iter().cloned());
+
+            org.slug = unique_slug(&base, &taken);
+            assigned.insert(org.slug.clone());
+        }
+
+        let mut tx = self.pool.begin().await.begin_err()?;
 
         if !inserts.is_empty() {
             let values_clause = itertools::Itertools::intersperse(
-                std::iter::repeat("(?, ?)").take(inserts.len()),
+                std::iter::repeat_n("(?, ?, ?)", inserts.len()),
                 " , ",
             )
             .collect::<String>();
 
             let query = format!(
-                "INSERT INTO organizations (id, name) VALUES {}",
+                "INSERT INTO organizations (id, name, slug) VALUES {}",
                 values_clause
             );
 
@@ -134,26 +252,23 @@ impl Store for SqliteStore {
             let query_builder = inserts
                 .iter()
                 .fold(query_builder, |query_builder, (_, org)| {
-                    query_builder.bind(&org.id).bind(&org.name)
+                    query_builder.bind(&org.id).bind(&org.name).bind(&org.slug)
                 });
 
-            query_builder
-                .execute(&mut *tx)
-                .await
-                .map_err(|e| Error::InsertionError(e))?;
+            query_builder.execute(&mut *tx).await.insert_err()?;
         };
 
         if !updates.is_empty() {
             let values_clause = itertools::Itertools::intersperse(
-                std::iter::repeat("(?, ?)").take(updates.len()),
+                std::iter::repeat_n("(?, ?, ?)", updates.len()),
                 " , ",
             )
             .collect::<String>();
 
             let query = format!(
-                "WITH mydata(id, name) AS (VALUES {}) 
+                "WITH mydata(id, name, slug) AS (VALUES {}) 
                 UPDATE organizations 
-                SET name = mydata.name 
+                SET name = mydata.name, slug = mydata.slug 
                 FROM mydata 
                 WHERE organizations.id = mydata.id",
                 values_clause
@@ -163,16 +278,13 @@ impl Store for SqliteStore {
             let query_builder = updates
                 .iter()
                 .fold(query_builder, |query_builder, (_, org)| {
-                    query_builder.bind(&org.id).bind(&org.name)
+                    query_builder.bind(&org.id).bind(&org.name).bind(&org.slug)
                 });
 
-            query_builder
-                .execute(&mut *tx)
-                .await
-                .map_err(|e| Error::UpdateError(e))?;
+            query_builder.execute(&mut *tx).await.update_err()?;
         };
 
-        tx.commit().await.map_err(|e| Error::TransactionFailed(e))?;
+        tx.commit().await.commit_err()?;
 
         let mut outputs = Vec::new();
         outputs.resize(inserts.len() + updates.len(), Organization::default());
@@ -185,11 +297,26 @@ impl Store for SqliteStore {
 
         Ok(outputs)
     }
-    async fn query(&self, query: Option<&Query>) -> Result<Vec<Organization>, Error> {
-        let base_query = "SELECT id, name FROM organizations";
+
+    async fn query(&self, query: Option<&Query>) -> StoreResult<Vec<Organization>> {
+        self.fetch(query, None).await
+    }
+
+    async fn query_page(
+        &self,
+        query: Option<&Query>,
+        page: Pagination,
+    ) -> StoreResult<Vec<Organization>> {
+        self.fetch(query, Some(page)).await
+    }
+
+    async fn count(&self, query: Option<&Query>) -> StoreResult<u64> {
         let query_string = match query {
-            Some(query) => format!("{} WHERE {}", base_query, query.where_clause()),
-            None => base_query.to_string(),
+            Some(query) => format!(
+                "SELECT COUNT(*) FROM organizations WHERE {}",
+                query.where_clause()
+            ),
+            None => "SELECT COUNT(*) FROM organizations".to_owned(),
         };
 
         let query_builder = sqlx::query_as(&query_string);
@@ -198,17 +325,145 @@ impl Store for SqliteStore {
             None => query_builder,
         };
 
-        let rows: Vec<OrganizationRow> = query_builder
-            .fetch_all(&*self.pool)
+        let (count,): (i64,) = query_builder.fetch_one(&*self.pool).await.fetch_err()?;
+
+        Ok(count as u64)
+    }
+
+    async fn get_by_slug(&self, slug: &str) -> StoreResult<Organization> {
+        let row: Option<OrganizationRow> =
+            sqlx::query_as("SELECT id, name, slug FROM organizations WHERE slug = ?")
+                .bind(slug)
+                .fetch_optional(&*self.pool)
+                .await
+                .fetch_err()?;
+
+        row.map(|row| row.into())
+            .ok_or_else(|| Error::SlugDoesNotExist(slug.to_owned()))
+    }
+
+    async fn clone_organization(&self, source_id: &OrgId, name: &str) -> StoreResult<Organization> {
+        ids_in_table(&self.pool, "organizations", [source_id.as_str()]).await?;
+
+        let id = new_id();
+        let base = slugify(name, "organization");
+        let taken = taken_slugs(&self.pool, "organizations", None, &id, &base).await?;
+        let organization = Organization {
+            id,
+            name: name.to_owned(),
+            slug: unique_slug(&base, &taken),
+        };
+
+        let mut tx = self.pool.begin().await.begin_err()?;
+
+        sqlx::query("INSERT INTO organizations(id, name, slug) VALUES (?, ?, ?)")
+            .bind(&organization.id)
+            .bind(&organization.name)
+            .bind(&organization.slug)
+            .execute(&mut *tx)
             .await
-            .map_err(|e| Error::FetchError(e))?;
+            .insert_err()?;
+
+        // Every copied row gets a fresh id, kept alongside the source's so references between
+        // copied rows can be pointed at the copies.
+        let source_ids: Vec<(String,)> = sqlx::query_as(
+            "SELECT id FROM events WHERE organization = ?1
+            UNION ALL
+            SELECT id FROM registration_schema_items
+            WHERE event IN (SELECT id FROM events WHERE organization = ?1)
+                OR parent IN (SELECT id FROM registration_schema_items
+                    WHERE event IN (SELECT id FROM events WHERE organization = ?1))
+            UNION ALL
+            SELECT options.id FROM registration_schema_select_options AS options
+            JOIN registration_schema_items AS items ON options.schema_item = items.id
+            LEFT JOIN registration_schema_items AS groups ON items.parent = groups.id
+            WHERE COALESCE(items.event, groups.event) IN (SELECT id FROM events WHERE organization = ?1)",
+        )
+        .bind(source_id)
+        .fetch_all(&mut *tx)
+        .await
+        .fetch_err()?;
 
-        Ok(rows.into_iter().map(|row| row.into()).collect())
+        sqlx::query(
+            "CREATE TEMP TABLE cloned_ids(source TEXT NOT NULL PRIMARY KEY, copy TEXT NOT NULL)",
+        )
+        .execute(&mut *tx)
+        .await
+        .insert_err()?;
+
+        for (source,) in source_ids.iter() {
+            sqlx::query("INSERT INTO cloned_ids(source, copy) VALUES (?, ?)")
+                .bind(source)
+                .bind(new_id())
+                .execute(&mut *tx)
+                .await
+                .insert_err()?;
+        }
+
+        // Dedup items point into the events' schemas, which don't exist yet, so they're set once
+        // the items are copied.
+        sqlx::query(&format!(
+            "INSERT INTO events(id, organization, {columns})
+            SELECT cloned_ids.copy, ?, {columns} FROM events
+            JOIN cloned_ids ON cloned_ids.source = events.id",
+            columns = CLONED_EVENT_COLUMNS
+        ))
+        .bind(&organization.id)
+        .execute(&mut *tx)
+        .await
+        .insert_err()?;
+
+        // Groups are copied before their items, so the items have a parent to point to.
+        sqlx::query(&format!(
+            "INSERT INTO registration_schema_items(id, event, parent, {columns})
+            SELECT cloned_ids.copy, cloned_events.copy, cloned_parents.copy, {columns}
+            FROM registration_schema_items
+            JOIN cloned_ids ON cloned_ids.source = registration_schema_items.id
+            LEFT JOIN cloned_ids AS cloned_events ON cloned_events.source = registration_schema_items.event
+            LEFT JOIN cloned_ids AS cloned_parents ON cloned_parents.source = registration_schema_items.parent
+            ORDER BY registration_schema_items.parent IS NOT NULL",
+            columns = CLONED_SCHEMA_ITEM_COLUMNS
+        ))
+        .execute(&mut *tx)
+        .await
+        .insert_err()?;
+
+        sqlx::query(&format!(
+            "INSERT INTO registration_schema_select_options(id, schema_item, {columns})
+            SELECT cloned_ids.copy, cloned_items.copy, {columns}
+            FROM registration_schema_select_options
+            JOIN cloned_ids ON cloned_ids.source = registration_schema_select_options.id
+            JOIN cloned_ids AS cloned_items ON cloned_items.source = registration_schema_select_options.schema_item",
+            columns = CLONED_SELECT_OPTION_COLUMNS
+        ))
+        .execute(&mut *tx)
+        .await
+        .insert_err()?;
+
+        sqlx::query(
+            "UPDATE events SET dedup_item = cloned_items.copy
+            FROM cloned_ids, events AS sources, cloned_ids AS cloned_items
+            WHERE cloned_ids.copy = events.id
+                AND sources.id = cloned_ids.source
+                AND cloned_items.source = sources.dedup_item",
+        )
+        .execute(&mut *tx)
+        .await
+        .update_err()?;
+
+        sqlx::query("DROP TABLE cloned_ids")
+            .execute(&mut *tx)
+            .await
+            .delete_err()?;
+
+        tx.commit().await.commit_err()?;
+
+        Ok(organization)
     }
 
-    async fn delete(&self, ids: &Vec<String>) -> Result<(), Error> {
-        if ids.is_empty() {
-            return Ok(());
+    async fn delete(&self, ids: &[String]) -> StoreResult<()> {
+        if let Some(result) = nothing_to_do(ids) {
+            return result;
         }
 
         ids_in_table(
@@ -228,27 +483,128 @@ impl Store for SqliteStore {
         let query_builder = ids
             .iter()
             .fold(query_builder, |query_builder, id| query_builder.bind(id));
-        query_builder
-            .execute(&*self.pool)
-            .await
-            .map_err(|e| Error::DeleteError(e))?;
+        query_builder.execute(&*self.pool).await.delete_err()?;
 
         Ok(())
     }
 }
 
+// Columns copied as they are when an organization is cloned. Ids, and the columns pointing at
+// other copied rows, are mapped to the copies' ids instead.
+const CLONED_EVENT_COLUMNS: &str = "name, starts_at, ends_at, registration_closes_at, require_account, slug, block_duplicates, registration_opens_at";
+const CLONED_SCHEMA_ITEM_COLUMNS: &str = "idx, name, required, required_after, item_type, text_type_default, text_type_display, text_type_placeholder, text_type_prefix, text_type_suffix, checkbox_type_default, select_type_default, select_type_display, multi_select_type_defaults, multi_select_type_display, section_type_body, blank_on_copy, display_idx, tags_type_max_tags";
+const CLONED_SELECT_OPTION_COLUMNS: &str = "idx, name, product_id, archived";
+
+pub const UNASSIGNED_ORGANIZATION_NAME: &str = "Unassigned";
+
+/// Moves events whose organization is missing into an "Unassigned" organization, creating it if
+/// needed, and returns how many were moved. Databases written with foreign keys disabled can hold
+/// such events. Running it again is a no-op once every event has an organization.
+pub async fn adopt_orphaned_events(pool: &SqlitePool) -> StoreResult<u64> {
+    let mut tx = pool.begin().await.begin_err()?;
+
+    let orphans: Vec<(String, Option<String>)> = sqlx::query_as(
+        "SELECT id, slug FROM events
+        WHERE organization IS NULL OR organization NOT IN (SELECT id FROM organizations)
+        ORDER BY id",
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .fetch_err()?;
+
+    if orphans.is_empty() {
+        return Ok(0);
+    }
+
+    let existing: Option<(String,)> =
+        sqlx::query_as("SELECT id FROM organizations WHERE name = ? ORDER BY id LIMIT 1")
+            .bind(UNASSIGNED_ORGANIZATION_NAME)
+            .fetch_optional(&mut *tx)
+            .await
+            .fetch_err()?;
+
+    let organization_id = match existing {
+        Some((id,)) => id,
+        None => {
+            let id = new_id();
+            let taken: Vec<(String,)> = sqlx::query_as("SELECT slug FROM organizations")
+                .fetch_all(&mut *tx)
+                .await
+                .fetch_err()?;
+            let slug = unique_slug(
+                &slugify(UNASSIGNED_ORGANIZATION_NAME, &id),
+                &taken.into_iter().map(|(slug,)| slug).collect(),
+            );
+
+            sqlx::query("INSERT INTO organizations(id, name, slug) VALUES (?, ?, ?)")
+                .bind(&id)
+                .bind(UNASSIGNED_ORGANIZATION_NAME)
+                .bind(slug)
+                .execute(&mut *tx)
+                .await
+                .insert_err()?;
+
+            id
+        }
+    };
+
+    // Orphans may have come from different organizations, so their slugs can clash once they
+    // share one.
+    let taken: Vec<(String,)> =
+        sqlx::query_as("SELECT slug FROM events WHERE organization = ? AND slug IS NOT NULL")
+            .bind(&organization_id)
+            .fetch_all(&mut *tx)
+            .await
+            .fetch_err()?;
+    let mut taken: HashSet<String> = taken.into_iter().map(|(slug,)| slug).collect();
+
+    for (id, slug) in orphans.iter() {
+        let slug = unique_slug(slug.as_deref().unwrap_or(id), &taken);
+        taken.insert(slug.clone());
+
+        sqlx::query("UPDATE events SET organization = ?, slug = ? WHERE id = ?")
+            .bind(&organization_id)
+            .bind(slug)
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .update_err()?;
+    }
+
+    tx.commit().await.commit_err()?;
+
+    log::info!(
+        "moved {} orphaned events to organization {}",
+        orphans.len(),
+        organization_id
+    );
+
+    Ok(orphans.len() as u64)
+}
+
 #[cfg(test)]
 mod tests {
     use std::{str::FromStr, sync::Arc};
 
-    use common::proto::Organization;
+    use common::proto::{
+        registration_schema_item_type::Type as ItemType, select_type, text_type, Event, GroupType,
+        Organization, RegistrationSchema, RegistrationSchemaItem, RegistrationSchemaItemType,
+        SelectOption, SelectType, TextType,
+    };
     use sqlx::{
         migrate::MigrateDatabase, sqlite::SqliteConnectOptions, ConnectOptions, Sqlite, SqlitePool,
     };
 
-    use crate::store::{common::new_id, CompoundOperator, CompoundQuery, Error, LogicalQuery};
+    use crate::store::{
+        common::new_id,
+        event::{self, SqliteStore as EventStore, Store as _},
+        ids::OrgId,
+        registration_schema::{self, SqliteStore as SchemaStore, Store as _},
+        CompoundOperator, CompoundQuery, Error, LogicalQuery,
+    };
+    use crate::testing::closed_pool;
 
-    use super::{OrganizationRow, Query, SqliteStore, Store};
+    use super::{OrganizationRow, Pagination, Query, SqliteStore, Store};
 
     use test_case::test_case;
 
@@ -278,12 +634,14 @@ mod tests {
         let org2_id = new_id();
         let org2_name = "org2";
 
-        let query = "INSERT INTO organizations (id, name) VALUES (?, ?), (?, ?)";
+        let query = "INSERT INTO organizations (id, name, slug) VALUES (?, ?, ?), (?, ?, ?)";
         sqlx::query(query)
             .bind(&org1_id)
             .bind(org1_name)
+            .bind(org1_name)
             .bind(&org2_id)
             .bind(org2_name)
+            .bind(org2_name)
             .execute(&init.db)
             .await
             .unwrap();
@@ -292,10 +650,12 @@ mod tests {
             Organization {
                 id: org1_id,
                 name: org1_name.to_string(),
+                slug: org1_name.to_string(),
             },
             Organization {
                 id: org2_id,
                 name: org2_name.to_string(),
+                slug: org2_name.to_string(),
             },
         ];
 
@@ -312,10 +672,12 @@ mod tests {
             Organization {
                 id: "".to_string(),
                 name: "org1".to_string(),
+                slug: String::default(),
             },
             Organization {
                 id: "".to_string(),
                 name: "org2".to_string(),
+                slug: String::default(),
             },
         ];
 
@@ -326,6 +688,7 @@ mod tests {
             .zip(returned_orgs.iter())
             .map(|(mut org, store_org)| {
                 org.id = store_org.id.clone();
+                org.slug = org.name.clone();
                 org
             })
             .collect::<Vec<_>>();
@@ -333,7 +696,7 @@ mod tests {
         assert_eq!(orgs, returned_orgs);
 
         let store_org_rows: Vec<OrganizationRow> =
-            sqlx::query_as("SELECT id, name FROM organizations")
+            sqlx::query_as("SELECT id, name, slug FROM organizations")
                 .fetch_all(&*db)
                 .await
                 .unwrap();
@@ -364,7 +727,7 @@ mod tests {
         assert_eq!(orgs[0], returned_orgs[0]);
 
         let store_org_rows: Vec<OrganizationRow> =
-            sqlx::query_as("SELECT id, name FROM organizations")
+            sqlx::query_as("SELECT id, name, slug FROM organizations")
                 .fetch_all(&*db)
                 .await
                 .unwrap();
@@ -392,6 +755,7 @@ mod tests {
             .upsert(vec![Organization {
                 id: id.clone(),
                 name: "whatever".to_string(),
+                slug: String::default(),
             }])
             .await;
 
@@ -451,15 +815,121 @@ mod tests {
         let db = Arc::new(init.db);
         let store = SqliteStore::new(db.clone());
 
-        let mut returned_orgs = store.query(tc.query.as_ref()).await.unwrap();
+        let returned_orgs = store.query(tc.query.as_ref()).await.unwrap();
 
         let mut expected_orgs = tc.expected;
         expected_orgs.sort_by(|a, b| a.id.cmp(&b.id));
-        returned_orgs.sort_by(|a, b| a.id.cmp(&b.id));
 
         assert_eq!(expected_orgs, returned_orgs);
     }
 
+    async fn named_orgs(init: &Init, names: &[&str]) -> Vec<Organization> {
+        let store = SqliteStore::new(Arc::new(init.db.clone()));
+        let mut orgs = store
+            .upsert(
+                names
+                    .iter()
+                    .map(|name| Organization {
+                        id: "".to_owned(),
+                        name: name.to_string(),
+                        slug: String::default(),
+                    })
+                    .collect(),
+            )
+            .await
+            .unwrap();
+        orgs.sort_by(|a, b| a.id.cmp(&b.id));
+        orgs
+    }
+
+    #[test_case("dance", &["Swing Dance Club", "Blues Dance"] ; "substring")]
+    #[test_case("DANCE", &["Swing Dance Club", "Blues Dance"] ; "case insensitive")]
+    #[test_case("100%", &["100% Swing"] ; "escapes wildcards")]
+    #[test_case("tango", &[] ; "no match")]
+    #[tokio::test]
+    async fn name_contains(substring: &str, expected: &[&str]) {
+        let init = init().await;
+        named_orgs(
+            &init,
+            &[
+                "Swing Dance Club",
+                "Blues Dance",
+                "100% Swing",
+                "1000 Swing",
+            ],
+        )
+        .await;
+
+        let store = SqliteStore::new(Arc::new(init.db));
+        let returned = store
+            .query(Some(&Query::NameContains(substring.to_owned())))
+            .await
+            .unwrap();
+
+        let mut names = returned.into_iter().map(|org| org.name).collect::<Vec<_>>();
+        names.sort();
+        let mut expected = expected.to_vec();
+        expected.sort();
+        assert_eq!(names, expected);
+    }
+
+    #[test_case(2, 0 ; "first page")]
+    #[test_case(2, 2 ; "second page")]
+    #[test_case(2, 4 ; "partial page")]
+    #[test_case(10, 6 ; "past the end")]
+    #[tokio::test]
+    async fn query_page(limit: u32, offset: u32) {
+        let init = init().await;
+        let orgs = named_orgs(&init, &["a", "b", "c", "d", "e"]).await;
+
+        let store = SqliteStore::new(Arc::new(init.db));
+        let returned = store
+            .query_page(None, Pagination { limit, offset })
+            .await
+            .unwrap();
+
+        let expected = orgs
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect::<Vec<_>>();
+        assert_eq!(returned, expected);
+    }
+
+    #[tokio::test]
+    async fn slugs() {
+        let init = init().await;
+        let orgs = named_orgs(&init, &["Swing Dance Club", "Swing Dance Club"]).await;
+
+        let mut slugs = orgs.iter().map(|org| org.slug.as_str()).collect::<Vec<_>>();
+        slugs.sort();
+        assert_eq!(slugs, vec!["swing-dance-club", "swing-dance-club-2"]);
+
+        let store = SqliteStore::new(Arc::new(init.db));
+        for org in orgs.iter() {
+            assert_eq!(&store.get_by_slug(&org.slug).await.unwrap(), org);
+        }
+
+        match store.get_by_slug("blues").await {
+            Err(Error::SlugDoesNotExist(slug)) => assert_eq!(slug, "blues"),
+            result => panic!("incorrect result: {:?}", result),
+        }
+    }
+
+    #[test_case(None ; "all")]
+    #[test_case(Some(Query::NameContains("swing".to_owned())) ; "filtered")]
+    #[tokio::test]
+    async fn count(query: Option<Query>) {
+        let init = init().await;
+        named_orgs(&init, &["Swing Dance Club", "Blues Dance", "100% Swing"]).await;
+
+        let store = SqliteStore::new(Arc::new(init.db));
+        let unpaginated = store.query(query.as_ref()).await.unwrap();
+        let count = store.count(query.as_ref()).await.unwrap();
+
+        assert_eq!(count, unpaginated.len() as u64);
+    }
+
     #[tokio::test]
     async fn delete() {
         let init = init().await;
@@ -468,10 +938,10 @@ mod tests {
         let db = Arc::new(init.db);
         let store = SqliteStore::new(db.clone());
 
-        store.delete(&vec![orgs[0].id.clone()]).await.unwrap();
+        store.delete(&[orgs[0].id.clone()]).await.unwrap();
 
         let store_org_rows: Vec<OrganizationRow> =
-            sqlx::query_as("SELECT id, name FROM organizations")
+            sqlx::query_as("SELECT id, name, slug FROM organizations")
                 .fetch_all(&*db)
                 .await
                 .unwrap();
@@ -485,4 +955,297 @@ mod tests {
 
         assert_eq!(orgs[1], store_orgs[0]);
     }
+
+    #[tokio::test]
+    async fn empty_input() {
+        let store = SqliteStore::new(closed_pool().await);
+
+        assert!(store.upsert(Vec::new()).await.unwrap().is_empty());
+        store.delete(&Vec::new()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn adopt_orphaned_events() {
+        let init = init().await;
+        let orgs = test_data(&init).await;
+
+        // Orphans can only be written with foreign keys off.
+        let mut conn = init.db.acquire().await.unwrap();
+        sqlx::query("PRAGMA foreign_keys = OFF")
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        // Orphans are adopted in id order, so the first keeps its slug.
+        let mut orphans = [new_id(), new_id()];
+        orphans.sort();
+        let kept = new_id();
+        sqlx::query(
+            "INSERT INTO events(id, organization, name, slug) VALUES (?, ?, ?, ?), (?, ?, ?, ?), (?, ?, ?, ?)",
+        )
+        .bind(&orphans[0])
+        .bind(new_id())
+        .bind("Salsa Night")
+        .bind("salsa-night")
+        .bind(&orphans[1])
+        .bind(new_id())
+        .bind("Salsa Night")
+        .bind("salsa-night")
+        .bind(&kept)
+        .bind(&orgs[0].id)
+        .bind("Salsa Night")
+        .bind("salsa-night")
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+        sqlx::query("PRAGMA foreign_keys = ON")
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        drop(conn);
+
+        assert_eq!(super::adopt_orphaned_events(&init.db).await.unwrap(), 2);
+        assert_eq!(super::adopt_orphaned_events(&init.db).await.unwrap(), 0);
+
+        let unassigned: Vec<(String, String)> =
+            sqlx::query_as("SELECT id, slug FROM organizations WHERE name = ?")
+                .bind(super::UNASSIGNED_ORGANIZATION_NAME)
+                .fetch_all(&init.db)
+                .await
+                .unwrap();
+        assert_eq!(unassigned.len(), 1);
+        let (unassigned_id, unassigned_slug) = &unassigned[0];
+        assert_eq!(unassigned_slug, "unassigned");
+
+        let mut events: Vec<(String, String, String)> =
+            sqlx::query_as("SELECT id, organization, slug FROM events")
+                .fetch_all(&init.db)
+                .await
+                .unwrap();
+        events.sort();
+
+        let mut expected = vec![
+            (
+                orphans[0].clone(),
+                unassigned_id.clone(),
+                "salsa-night".to_owned(),
+            ),
+            (
+                orphans[1].clone(),
+                unassigned_id.clone(),
+                "salsa-night-2".to_owned(),
+            ),
+            (kept, orgs[0].id.clone(), "salsa-night".to_owned()),
+        ];
+        expected.sort();
+
+        assert_eq!(events, expected);
+    }
+
+    #[tokio::test]
+    async fn clone_organization() {
+        let init = init().await;
+        let orgs = test_data(&init).await;
+        let db = Arc::new(init.db);
+
+        let event_store = EventStore::new(db.clone());
+        let schema_store = SchemaStore::new(db.clone());
+        let events = event_store
+            .upsert(vec![
+                Event {
+                    organization_id: orgs[0].id.clone(),
+                    name: "Salsa Night".to_owned(),
+                    ..Default::default()
+                },
+                Event {
+                    organization_id: orgs[0].id.clone(),
+                    name: "Bachata Night".to_owned(),
+                    ..Default::default()
+                },
+                Event {
+                    organization_id: orgs[1].id.clone(),
+                    name: "Kizomba Night".to_owned(),
+                    ..Default::default()
+                },
+            ])
+            .await
+            .unwrap();
+
+        let item = |name: &str, r#type| RegistrationSchemaItem {
+            name: name.to_owned(),
+            r#type: Some(RegistrationSchemaItemType {
+                r#type: Some(r#type),
+            }),
+            ..Default::default()
+        };
+        let text = || {
+            ItemType::Text(TextType {
+                display: text_type::Display::Small as i32,
+                ..Default::default()
+            })
+        };
+        let schemas = schema_store
+            .upsert(vec![
+                RegistrationSchema {
+                    event_id: events[0].id.clone(),
+                    items: vec![
+                        item("Email", text()),
+                        item(
+                            "Role",
+                            ItemType::Select(SelectType {
+                                display: select_type::Display::Radio as i32,
+                                options: ["Lead", "Follow"]
+                                    .into_iter()
+                                    .map(|name| SelectOption {
+                                        name: name.to_owned(),
+                                        ..Default::default()
+                                    })
+                                    .collect(),
+                                ..Default::default()
+                            }),
+                        ),
+                        item(
+                            "Guests",
+                            ItemType::Group(GroupType {
+                                items: vec![item("Guest name", text())],
+                            }),
+                        ),
+                    ],
+                },
+                RegistrationSchema {
+                    event_id: events[1].id.clone(),
+                    items: vec![item("Name", text())],
+                },
+            ])
+            .await
+            .unwrap();
+        event_store
+            .upsert(vec![Event {
+                dedup_item_id: schemas
+                    .iter()
+                    .find(|schema| schema.event_id == events[0].id)
+                    .unwrap()
+                    .items[0]
+                    .id
+                    .clone(),
+                ..events[0].clone()
+            }])
+            .await
+            .unwrap();
+
+        sqlx::query("INSERT INTO registrations(id, event) VALUES (?, ?)")
+            .bind(new_id())
+            .bind(&events[0].id)
+            .execute(&*db)
+            .await
+            .unwrap();
+
+        let store = SqliteStore::new(db.clone());
+        let clone = store
+            .clone_organization(&OrgId::from(&orgs[0].id), "org1 staging")
+            .await
+            .unwrap();
+        assert_ne!(clone.id, orgs[0].id);
+        assert_eq!(clone.name, "org1 staging");
+        assert_eq!(clone.slug, "org1-staging");
+
+        let org_events = |org: &str| {
+            let query =
+                event::Query::Organization(event::OrganizationQuery::Equals(org.to_owned()));
+            let event_store = &event_store;
+            async move { event_store.query(Some(&query)).await }
+        };
+        let source_events = org_events(&orgs[0].id).await.unwrap();
+        let mut cloned_events = org_events(&clone.id).await.unwrap();
+        assert_eq!(cloned_events.len(), source_events.len());
+        cloned_events.sort_by(|a, b| a.slug.cmp(&b.slug));
+
+        let schema_of = |event_id: &str| {
+            let query =
+                registration_schema::Query::EventId(LogicalQuery::Equals(event_id.to_owned()));
+            let schema_store = &schema_store;
+            async move { schema_store.query(Some(&query)).await }
+        };
+        for source in source_events.iter() {
+            let copy = cloned_events
+                .iter()
+                .find(|copy| copy.slug == source.slug)
+                .unwrap();
+            assert_ne!(copy.id, source.id);
+            assert_eq!(copy.name, source.name);
+
+            let source_schema = schema_of(&source.id).await.unwrap().remove(0);
+            let copy_schema = schema_of(&copy.id).await.unwrap().remove(0);
+            assert_eq!(
+                strip_ids(copy_schema.items.clone()),
+                strip_ids(source_schema.items.clone())
+            );
+
+            let source_ids = schema_ids(&source_schema.items);
+            let copy_ids = schema_ids(&copy_schema.items);
+            assert_eq!(copy_ids.len(), source_ids.len());
+            assert!(copy_ids.iter().all(|id| !source_ids.contains(id)));
+
+            // The dedup item is the copy's own, not the source's.
+            match source_schema
+                .items
+                .iter()
+                .position(|item| item.id == source.dedup_item_id)
+            {
+                Some(idx) => assert_eq!(copy.dedup_item_id, copy_schema.items[idx].id),
+                None => assert_eq!(copy.dedup_item_id, ""),
+            }
+        }
+
+        let (registrations,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM registrations WHERE event IN (SELECT id FROM events WHERE organization = ?)",
+        )
+        .bind(&clone.id)
+        .fetch_one(&*db)
+        .await
+        .unwrap();
+        assert_eq!(registrations, 0);
+
+        // The other organization is left alone.
+        assert_eq!(org_events(&orgs[1].id).await.unwrap().len(), 1);
+
+        let result = store
+            .clone_organization(&OrgId::from(&new_id()), "missing")
+            .await;
+        assert!(matches!(result, Err(Error::IdDoesNotExist(_))));
+    }
+
+    fn strip_ids(items: Vec<RegistrationSchemaItem>) -> Vec<RegistrationSchemaItem> {
+        items
+            .into_iter()
+            .map(|mut item| {
+                item.id = String::new();
+                match item.r#type.as_mut().and_then(|t| t.r#type.as_mut()) {
+                    Some(ItemType::Select(select)) => {
+                        select.options.iter_mut().for_each(|o| o.id = String::new())
+                    }
+                    Some(ItemType::Group(group)) => {
+                        group.items = strip_ids(std::mem::take(&mut group.items))
+                    }
+                    _ => (),
+                }
+                item
+            })
+            .collect()
+    }
+
+    fn schema_ids(items: &[RegistrationSchemaItem]) -> Vec<String> {
+        items
+            .iter()
+            .flat_map(|item| {
+                let nested = match item.r#type.as_ref().and_then(|t| t.r#type.as_ref()) {
+                    Some(ItemType::Select(select)) => {
+                        select.options.iter().map(|o| o.id.clone()).collect()
+                    }
+                    Some(ItemType::Group(group)) => schema_ids(&group.items),
+                    _ => Vec::new(),
+                };
+                std::iter::once(item.id.clone()).chain(nested)
+            })
+            .collect()
+    }
 }
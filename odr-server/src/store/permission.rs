@@ -0,0 +1,1078 @@
+use std::{iter, sync::Arc};
+
+use common::proto::Event;
+use sqlx::SqlitePool;
+
+use super::{
+    append_pagination,
+    common::{ids_in_table, new_id, nothing_to_do},
+    event::EventRow,
+    ids::{EventId, RegistrationId, UserId},
+    Bindable as _, Capped, Error, IntoStoreError as _, Pagination, Queryable as _, StoreResult,
+};
+
+// Joins each event to the permissions that apply to it, whether directly, through the event's
+// organization, or as a server admin.
+const PERMISSION_APPLIES_TO_EVENT: &str = "permissions.role = 'SERVER_ADMIN'
+    OR permissions.organization = events.organization
+    OR permissions.event = events.id";
+
+#[derive(sqlx::FromRow)]
+struct PermissionRow {
+    id: String,
+    user: String,
+    role: String,
+    organization: Option<String>,
+    event: Option<String>,
+}
+
+impl TryFrom<PermissionRow> for Permission {
+    type Error = Error;
+
+    fn try_from(row: PermissionRow) -> StoreResult<Self> {
+        let organization = || {
+            row.organization
+                .clone()
+                .ok_or(Error::ColumnParseError("organization"))
+        };
+        let event = || row.event.clone().ok_or(Error::ColumnParseError("event"));
+
+        let role = match row.role.as_str() {
+            "SERVER_ADMIN" => PermissionRole::ServerAdmin,
+            "ORGANIZATION_ADMIN" => PermissionRole::OrganizationAdmin(organization()?),
+            "ORGANIZATION_VIEWER" => PermissionRole::OrganizationViewer(organization()?),
+            "EVENT_ADMIN" => PermissionRole::EventAdmin(event()?),
+            "EVENT_EDITOR" => PermissionRole::EventEditor(event()?),
+            "EVENT_VIEWER" => PermissionRole::EventViewer(event()?),
+            _ => return Err(Error::ColumnParseError("role")),
+        };
+
+        Ok(Permission {
+            id: row.id,
+            user_id: row.user,
+            role,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PermissionRole {
+    ServerAdmin,
+    OrganizationAdmin(String),
+    OrganizationViewer(String),
+    EventAdmin(String),
+    EventEditor(String),
+    EventViewer(String),
+}
+
+impl PermissionRole {
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            PermissionRole::ServerAdmin => "SERVER_ADMIN",
+            PermissionRole::OrganizationAdmin(_) => "ORGANIZATION_ADMIN",
+            PermissionRole::OrganizationViewer(_) => "ORGANIZATION_VIEWER",
+            PermissionRole::EventAdmin(_) => "EVENT_ADMIN",
+            PermissionRole::EventEditor(_) => "EVENT_EDITOR",
+            PermissionRole::EventViewer(_) => "EVENT_VIEWER",
+        }
+    }
+
+    pub fn organization(&self) -> Option<&str> {
+        match self {
+            PermissionRole::OrganizationAdmin(id) | PermissionRole::OrganizationViewer(id) => {
+                Some(id)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn event(&self) -> Option<&str> {
+        match self {
+            PermissionRole::EventAdmin(id)
+            | PermissionRole::EventEditor(id)
+            | PermissionRole::EventViewer(id) => Some(id),
+            _ => None,
+        }
+    }
+
+    /// The role this grants on each event within its scope.
+    pub fn event_role(&self) -> EventRole {
+        match self {
+            PermissionRole::ServerAdmin
+            | PermissionRole::OrganizationAdmin(_)
+            | PermissionRole::EventAdmin(_) => EventRole::Admin,
+            PermissionRole::EventEditor(_) => EventRole::Editor,
+            PermissionRole::OrganizationViewer(_) | PermissionRole::EventViewer(_) => {
+                EventRole::Viewer
+            }
+        }
+    }
+
+    /// The access this role grants to registrations within its scope.
+    pub fn registration_access(&self) -> Access {
+        match self {
+            PermissionRole::ServerAdmin
+            | PermissionRole::OrganizationAdmin(_)
+            | PermissionRole::EventAdmin(_)
+            | PermissionRole::EventEditor(_) => Access::Edit,
+            PermissionRole::OrganizationViewer(_) | PermissionRole::EventViewer(_) => Access::View,
+        }
+    }
+}
+
+/// The role a permission grants on each event within its scope, from least to most access.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EventRole {
+    Viewer,
+    Editor,
+    Admin,
+}
+
+impl EventRole {
+    // Permission role names granting at least this role on the events in their scope.
+    fn granted_by(self) -> Vec<&'static str> {
+        [
+            ("SERVER_ADMIN", EventRole::Admin),
+            ("ORGANIZATION_ADMIN", EventRole::Admin),
+            ("ORGANIZATION_VIEWER", EventRole::Viewer),
+            ("EVENT_ADMIN", EventRole::Admin),
+            ("EVENT_EDITOR", EventRole::Editor),
+            ("EVENT_VIEWER", EventRole::Viewer),
+        ]
+        .into_iter()
+        .filter(|(_, role)| *role >= self)
+        .map(|(name, _)| name)
+        .collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Permission {
+    pub id: String,
+    pub user_id: String,
+    pub role: PermissionRole,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Access {
+    View,
+    Edit,
+}
+
+/// A user's access to a registration, along with the permission that grants it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UserAccess {
+    pub user_id: String,
+    pub access: Access,
+    pub permission: Permission,
+}
+
+pub struct IdField;
+
+impl super::Field for IdField {
+    type Item = String;
+
+    fn field() -> &'static str {
+        "id"
+    }
+}
+
+pub type IdQuery = super::LogicalQuery<IdField>;
+
+pub struct UserIdField;
+
+impl super::Field for UserIdField {
+    type Item = String;
+
+    fn field() -> &'static str {
+        "user"
+    }
+}
+
+pub type UserIdQuery = super::LogicalQuery<UserIdField>;
+
+pub enum Query {
+    Id(IdQuery),
+    UserId(UserIdQuery),
+    CompoundQuery(super::CompoundQuery<Query>),
+}
+
+impl super::Queryable for Query {
+    fn where_clause(&self) -> String {
+        match self {
+            Query::Id(q) => q.where_clause(),
+            Query::UserId(q) => q.where_clause(),
+            Query::CompoundQuery(compound_query) => compound_query.where_clause(),
+        }
+    }
+}
+
+impl super::Compound for Query {
+    fn compound(query: super::CompoundQuery<Query>) -> Self {
+        Query::CompoundQuery(query)
+    }
+}
+
+impl<'q, DB: sqlx::Database> super::Bindable<'q, DB> for Query
+where
+    String: sqlx::Type<DB> + sqlx::Encode<'q, DB>,
+{
+    fn bind<O>(
+        &'q self,
+        query_builder: sqlx::query::QueryAs<
+            'q,
+            DB,
+            O,
+            <DB as sqlx::database::HasArguments<'q>>::Arguments,
+        >,
+    ) -> sqlx::query::QueryAs<'q, DB, O, <DB as sqlx::database::HasArguments<'q>>::Arguments> {
+        match self {
+            Query::Id(q) => q.bind(query_builder),
+            Query::UserId(q) => q.bind(query_builder),
+            Query::CompoundQuery(compound_query) => compound_query.bind(query_builder),
+        }
+    }
+}
+
+type QueryBuilder<'q> = sqlx::query::Query<
+    'q,
+    sqlx::Sqlite,
+    <sqlx::Sqlite as sqlx::database::HasArguments<'q>>::Arguments,
+>;
+
+fn bind_permission<'q>(
+    query_builder: QueryBuilder<'q>,
+    permission: &'q Permission,
+) -> QueryBuilder<'q> {
+    query_builder
+        .bind(&permission.id)
+        .bind(&permission.user_id)
+        .bind(permission.role.as_str_name())
+        .bind(permission.role.organization())
+        .bind(permission.role.event())
+}
+
+#[tonic::async_trait]
+pub trait Store: Send + Sync + 'static {
+    async fn upsert(&self, permissions: Vec<Permission>) -> StoreResult<Vec<Permission>>;
+    /// Permissions are returned in id order.
+    async fn query(&self, query: Option<&Query>) -> StoreResult<Vec<Permission>>;
+    /// Like query, but returns at most the first `cap` permissions.
+    async fn query_capped(
+        &self,
+        query: Option<&Query>,
+        cap: u32,
+    ) -> StoreResult<Capped<Permission>>;
+    async fn delete(&self, ids: &[String]) -> StoreResult<()>;
+    /// Lists every permission granting access to the registration, whether directly through its
+    /// event, through the event's organization, or as a server admin. Results are ordered by user,
+    /// then permission id.
+    async fn registration_access(
+        &self,
+        registration_id: &RegistrationId,
+    ) -> StoreResult<Vec<UserAccess>>;
+    /// Lists the events on which the user holds at least `min_role`, including through an
+    /// organization permission or as a server admin. Events are returned in id order.
+    async fn events_by_role(
+        &self,
+        user_id: &UserId,
+        min_role: EventRole,
+    ) -> StoreResult<Vec<Event>>;
+    /// The strongest role the user holds on the event, whether directly, through the event's
+    /// organization, or as a server admin. Returns `None` if the user has no access to it.
+    async fn effective_event_role(
+        &self,
+        user_id: &UserId,
+        event_id: &EventId,
+    ) -> StoreResult<Option<EventRole>>;
+}
+
+#[derive(Debug)]
+pub struct SqliteStore {
+    pool: Arc<SqlitePool>,
+}
+
+impl SqliteStore {
+    pub fn new(pool: Arc<SqlitePool>) -> Self {
+        SqliteStore { pool }
+    }
+
+    // Permissions matching the query in id order.
+    async fn fetch_permissions(
+        &self,
+        query: Option<&Query>,
+        page: Option<Pagination>,
+    ) -> StoreResult<Vec<Permission>> {
+        let base_query_string = "SELECT id, user, role, organization, event FROM permissions";
+        let query_string = match query {
+            Some(query) => format!(
+                "{} WHERE {} ORDER BY id",
+                base_query_string,
+                query.where_clause()
+            ),
+            None => format!("{} ORDER BY id", base_query_string),
+        };
+
+        let query_string = append_pagination(query_string, page.as_ref());
+
+        let query_builder = sqlx::query_as(&query_string);
+        let query_builder = match query {
+            Some(query) => query.bind(query_builder),
+            None => query_builder,
+        };
+
+        let query_builder = match &page {
+            Some(page) => page.bind(query_builder),
+            None => query_builder,
+        };
+
+        let rows: Vec<PermissionRow> = query_builder.fetch_all(&*self.pool).await.fetch_err()?;
+
+        rows.into_iter()
+            .map(|row| row.try_into())
+            .collect::<Result<Vec<_>, _>>()
+    }
+}
+
+#[tonic::async_trait]
+impl Store for SqliteStore {
+    async fn upsert(&self, permissions: Vec<Permission>) -> StoreResult<Vec<Permission>> {
+        if let Some(result) = nothing_to_do(&permissions) {
+            return result;
+        }
+
+        let (inserts, updates): (Vec<_>, Vec<_>) = permissions
+            .into_iter()
+            .enumerate()
+            .partition(|(_, permission)| permission.id.is_empty());
+
+        if !updates.is_empty() {
+            ids_in_table(
+                &self.pool,
+                "permissions",
+                updates.iter().map(|(_, permission)| permission.id.as_str()),
+            )
+            .await?;
+        }
+
+        let inserts = inserts
+            .into_iter()
+            .map(|(idx, mut permission)| {
+                permission.id = new_id();
+                (idx, permission)
+            })
+            .collect::<Vec<_>>();
+
+        let mut tx = self.pool.begin().await.begin_err()?;
+
+        if !inserts.is_empty() {
+            let values_clause = itertools::Itertools::intersperse(
+                std::iter::repeat_n("(?, ?, ?, ?, ?)", inserts.len()),
+                ", ",
+            )
+            .collect::<String>();
+
+            let query = format!(
+                "INSERT INTO permissions (id, user, role, organization, event) VALUES {}",
+                values_clause
+            );
+
+            let query_builder = inserts
+                .iter()
+                .fold(sqlx::query(&query), |query_builder, (_, permission)| {
+                    bind_permission(query_builder, permission)
+                });
+
+            query_builder.execute(&mut *tx).await.insert_err()?;
+        }
+
+        if !updates.is_empty() {
+            let values_clause = itertools::Itertools::intersperse(
+                std::iter::repeat_n("(?, ?, ?, ?, ?)", updates.len()),
+                ", ",
+            )
+            .collect::<String>();
+
+            let query = format!(
+                "WITH mydata(id, user, role, organization, event) AS (VALUES {})
+                UPDATE permissions
+                SET user = mydata.user,
+                    role = mydata.role,
+                    organization = mydata.organization,
+                    event = mydata.event
+                FROM mydata
+                WHERE permissions.id = mydata.id",
+                values_clause
+            );
+
+            let query_builder = updates
+                .iter()
+                .fold(sqlx::query(&query), |query_builder, (_, permission)| {
+                    bind_permission(query_builder, permission)
+                });
+
+            query_builder.execute(&mut *tx).await.update_err()?;
+        }
+
+        tx.commit().await.commit_err()?;
+
+        let mut outputs = inserts.into_iter().chain(updates).collect::<Vec<_>>();
+        outputs.sort_by_key(|(idx, _)| *idx);
+
+        Ok(outputs
+            .into_iter()
+            .map(|(_, permission)| permission)
+            .collect())
+    }
+
+    async fn query(&self, query: Option<&Query>) -> StoreResult<Vec<Permission>> {
+        self.fetch_permissions(query, None).await
+    }
+
+    async fn query_capped(
+        &self,
+        query: Option<&Query>,
+        cap: u32,
+    ) -> StoreResult<Capped<Permission>> {
+        let permissions = self
+            .fetch_permissions(query, Some(Pagination::over_cap(cap)))
+            .await?;
+
+        Ok(Capped::new(permissions, cap))
+    }
+
+    async fn delete(&self, ids: &[String]) -> StoreResult<()> {
+        if let Some(result) = nothing_to_do(ids) {
+            return result;
+        }
+
+        ids_in_table(&self.pool, "permissions", ids.iter().map(|id| id.as_str())).await?;
+
+        let where_clause =
+            itertools::Itertools::intersperse(std::iter::repeat_n("id = ?", ids.len()), " OR ")
+                .collect::<String>();
+
+        let query = format!("DELETE FROM permissions WHERE {}", where_clause);
+
+        let query_builder = ids.iter().fold(sqlx::query(&query), |query_builder, id| {
+            query_builder.bind(id)
+        });
+
+        query_builder.execute(&*self.pool).await.delete_err()?;
+
+        Ok(())
+    }
+
+    async fn registration_access(
+        &self,
+        registration_id: &RegistrationId,
+    ) -> StoreResult<Vec<UserAccess>> {
+        ids_in_table(&self.pool, "registrations", [registration_id.as_str()]).await?;
+
+        let query = format!(
+            "SELECT permissions.id, permissions.user, permissions.role, permissions.organization, permissions.event
+            FROM registrations
+            JOIN events ON events.id = registrations.event
+            JOIN permissions ON {}
+            WHERE registrations.id = ?
+            ORDER BY permissions.user, permissions.id",
+            PERMISSION_APPLIES_TO_EVENT
+        );
+
+        let rows: Vec<PermissionRow> = sqlx::query_as(&query)
+            .bind(registration_id)
+            .fetch_all(&*self.pool)
+            .await
+            .fetch_err()?;
+
+        rows.into_iter()
+            .map(|row| {
+                let permission = Permission::try_from(row)?;
+                Ok(UserAccess {
+                    user_id: permission.user_id.clone(),
+                    access: permission.role.registration_access(),
+                    permission,
+                })
+            })
+            .collect()
+    }
+
+    async fn events_by_role(
+        &self,
+        user_id: &UserId,
+        min_role: EventRole,
+    ) -> StoreResult<Vec<Event>> {
+        ids_in_table(&self.pool, "users", [user_id.as_str()]).await?;
+
+        let roles = min_role.granted_by();
+        let roles_clause: String =
+            itertools::Itertools::intersperse(iter::repeat_n("?", roles.len()), ", ").collect();
+
+        let query = format!(
+            "SELECT DISTINCT events.id, events.organization, events.name, events.starts_at, events.ends_at, events.registration_closes_at, events.require_account, events.slug, events.dedup_item, events.block_duplicates, events.registration_opens_at
+            FROM events
+            JOIN permissions ON {}
+            WHERE permissions.user = ? AND permissions.role IN ({})
+            ORDER BY events.id",
+            PERMISSION_APPLIES_TO_EVENT, roles_clause
+        );
+
+        let query_builder = sqlx::query_as(&query).bind(user_id);
+        let rows: Vec<EventRow> = roles
+            .into_iter()
+            .fold(query_builder, |query_builder, role| {
+                query_builder.bind(role)
+            })
+            .fetch_all(&*self.pool)
+            .await
+            .fetch_err()?;
+
+        Ok(rows.into_iter().map(|row| row.into()).collect())
+    }
+
+    async fn effective_event_role(
+        &self,
+        user_id: &UserId,
+        event_id: &EventId,
+    ) -> StoreResult<Option<EventRole>> {
+        ids_in_table(&self.pool, "users", [user_id.as_str()]).await?;
+        ids_in_table(&self.pool, "events", [event_id.as_str()]).await?;
+
+        let query = format!(
+            "SELECT permissions.id, permissions.user, permissions.role, permissions.organization, permissions.event
+            FROM events
+            JOIN permissions ON {}
+            WHERE permissions.user = ? AND events.id = ?",
+            PERMISSION_APPLIES_TO_EVENT
+        );
+
+        let rows: Vec<PermissionRow> = sqlx::query_as(&query)
+            .bind(user_id)
+            .bind(event_id)
+            .fetch_all(&*self.pool)
+            .await
+            .fetch_err()?;
+
+        rows.into_iter()
+            .map(|row| Permission::try_from(row).map(|permission| permission.role.event_role()))
+            .try_fold(None, |strongest, role| Ok(strongest.max(Some(role?))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, sync::Arc};
+
+    use sqlx::{
+        migrate::MigrateDatabase, sqlite::SqliteConnectOptions, ConnectOptions, Sqlite, SqlitePool,
+    };
+    use test_case::test_case;
+
+    use crate::store::{
+        common::new_id,
+        ids::{EventId, RegistrationId, UserId},
+        CompoundOperator, CompoundQuery, Error, LogicalQuery,
+    };
+    use crate::testing::closed_pool;
+
+    use super::{
+        Access, EventRole, Permission, PermissionRole, Query, SqliteStore, Store, UserAccess,
+    };
+
+    struct Init {
+        user: String,
+        organization: String,
+        event: String,
+        db: SqlitePool,
+    }
+
+    async fn init() -> Init {
+        let db_url = "sqlite://:memory:";
+        Sqlite::create_database(db_url).await.unwrap();
+
+        let db = SqlitePool::connect_with(
+            SqliteConnectOptions::from_str(db_url)
+                .unwrap()
+                .log_statements(log::LevelFilter::Trace),
+        )
+        .await
+        .unwrap();
+        sqlx::migrate!("../migrations").run(&db).await.unwrap();
+
+        let user = new_id();
+        sqlx::query("INSERT INTO users (id, email, password, display_name) VALUES (?, ?, ?, ?)")
+            .bind(&user)
+            .bind("a@gmail.com")
+            .bind(None as Option<&str>)
+            .bind("a")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let organization = new_id();
+        sqlx::query("INSERT INTO organizations (id, name) VALUES (?, ?)")
+            .bind(&organization)
+            .bind("Org 1")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let event = new_id();
+        sqlx::query("INSERT INTO events (id, organization, name) VALUES (?, ?, ?)")
+            .bind(&event)
+            .bind(&organization)
+            .bind("Event 1")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        Init {
+            user,
+            organization,
+            event,
+            db,
+        }
+    }
+
+    #[tokio::test]
+    async fn upsert_and_query() {
+        let init = init().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let permissions = vec![
+            Permission {
+                id: "".to_owned(),
+                user_id: init.user.clone(),
+                role: PermissionRole::ServerAdmin,
+            },
+            Permission {
+                id: "".to_owned(),
+                user_id: init.user.clone(),
+                role: PermissionRole::OrganizationViewer(init.organization.clone()),
+            },
+            Permission {
+                id: "".to_owned(),
+                user_id: init.user.clone(),
+                role: PermissionRole::EventEditor(init.event.clone()),
+            },
+        ];
+
+        let mut returned = store.upsert(permissions.clone()).await.unwrap();
+        assert_eq!(returned.len(), permissions.len());
+        for (permission, returned) in permissions.iter().zip(returned.iter()) {
+            assert!(!returned.id.is_empty());
+            assert_eq!(permission.role, returned.role);
+        }
+
+        returned[1].role = PermissionRole::OrganizationAdmin(init.organization.clone());
+        let updated = store.upsert(vec![returned[1].clone()]).await.unwrap();
+        assert_eq!(updated, vec![returned[1].clone()]);
+
+        let queried = store
+            .query(Some(&Query::UserId(LogicalQuery::Equals(
+                init.user.clone(),
+            ))))
+            .await
+            .unwrap();
+        returned.sort_by(|a, b| a.id.cmp(&b.id));
+        assert_eq!(queried, returned);
+    }
+
+    #[test_case(2, true ; "over cap")]
+    #[test_case(3, false ; "at cap")]
+    #[tokio::test]
+    async fn query_capped(cap: u32, truncated: bool) {
+        let init = init().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let mut permissions = store
+            .upsert(vec![
+                Permission {
+                    id: "".to_owned(),
+                    user_id: init.user.clone(),
+                    role: PermissionRole::ServerAdmin,
+                },
+                Permission {
+                    id: "".to_owned(),
+                    user_id: init.user.clone(),
+                    role: PermissionRole::OrganizationViewer(init.organization.clone()),
+                },
+                Permission {
+                    id: "".to_owned(),
+                    user_id: init.user.clone(),
+                    role: PermissionRole::EventViewer(init.event.clone()),
+                },
+            ])
+            .await
+            .unwrap();
+        permissions.sort_by(|a, b| a.id.cmp(&b.id));
+        permissions.truncate(cap as usize);
+
+        let capped = store.query_capped(None, cap).await.unwrap();
+        assert_eq!(capped.rows, permissions);
+        assert_eq!(capped.truncated, truncated);
+    }
+
+    #[test_case(CompoundOperator::And, true ; "and matches all")]
+    #[test_case(CompoundOperator::Or, false ; "or matches none")]
+    #[tokio::test]
+    async fn empty_compound_query(operator: CompoundOperator, matches_all: bool) {
+        let init = init().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let permissions = store
+            .upsert(vec![
+                Permission {
+                    id: "".to_owned(),
+                    user_id: init.user.clone(),
+                    role: PermissionRole::ServerAdmin,
+                },
+                Permission {
+                    id: "".to_owned(),
+                    user_id: init.user.clone(),
+                    role: PermissionRole::EventViewer(init.event.clone()),
+                },
+            ])
+            .await
+            .unwrap();
+
+        let mut queried = store
+            .query(Some(&Query::CompoundQuery(CompoundQuery {
+                operator,
+                queries: Vec::new(),
+            })))
+            .await
+            .unwrap();
+        queried.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let mut expected = if matches_all { permissions } else { Vec::new() };
+        expected.sort_by(|a, b| a.id.cmp(&b.id));
+        assert_eq!(queried, expected);
+    }
+
+    #[tokio::test]
+    async fn update_does_not_exist() {
+        let init = init().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let id = new_id();
+        let result = store
+            .upsert(vec![Permission {
+                id: id.clone(),
+                user_id: init.user,
+                role: PermissionRole::ServerAdmin,
+            }])
+            .await;
+
+        match result {
+            Err(Error::IdDoesNotExist(err_id)) => assert_eq!(err_id, id),
+            _ => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[tokio::test]
+    async fn delete() {
+        let init = init().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let permissions = store
+            .upsert(vec![
+                Permission {
+                    id: "".to_owned(),
+                    user_id: init.user.clone(),
+                    role: PermissionRole::ServerAdmin,
+                },
+                Permission {
+                    id: "".to_owned(),
+                    user_id: init.user.clone(),
+                    role: PermissionRole::EventViewer(init.event.clone()),
+                },
+            ])
+            .await
+            .unwrap();
+
+        store.delete(&[permissions[0].id.clone()]).await.unwrap();
+
+        let remaining = store.query(None).await.unwrap();
+        assert_eq!(remaining, vec![permissions[1].clone()]);
+    }
+
+    #[tokio::test]
+    async fn registration_access() {
+        let init = init().await;
+
+        let mut users = vec![init.user.clone()];
+        for email in ["b@gmail.com", "c@gmail.com", "d@gmail.com"] {
+            let user = new_id();
+            sqlx::query(
+                "INSERT INTO users (id, email, password, display_name) VALUES (?, ?, ?, ?)",
+            )
+            .bind(&user)
+            .bind(email)
+            .bind(None as Option<&str>)
+            .bind(email)
+            .execute(&init.db)
+            .await
+            .unwrap();
+            users.push(user);
+        }
+        users.sort();
+
+        let other_organization = new_id();
+        sqlx::query("INSERT INTO organizations (id, name) VALUES (?, ?)")
+            .bind(&other_organization)
+            .bind("Org 2")
+            .execute(&init.db)
+            .await
+            .unwrap();
+
+        let registration = new_id();
+        sqlx::query("INSERT INTO registrations (id, event) VALUES (?, ?)")
+            .bind(&registration)
+            .bind(&init.event)
+            .execute(&init.db)
+            .await
+            .unwrap();
+
+        let store = SqliteStore::new(Arc::new(init.db));
+        let permissions = store
+            .upsert(vec![
+                Permission {
+                    id: "".to_owned(),
+                    user_id: users[0].clone(),
+                    role: PermissionRole::ServerAdmin,
+                },
+                Permission {
+                    id: "".to_owned(),
+                    user_id: users[1].clone(),
+                    role: PermissionRole::OrganizationViewer(init.organization.clone()),
+                },
+                Permission {
+                    id: "".to_owned(),
+                    user_id: users[2].clone(),
+                    role: PermissionRole::EventEditor(init.event.clone()),
+                },
+                // Grants nothing on this registration.
+                Permission {
+                    id: "".to_owned(),
+                    user_id: users[3].clone(),
+                    role: PermissionRole::OrganizationAdmin(other_organization),
+                },
+            ])
+            .await
+            .unwrap();
+
+        let access = store
+            .registration_access(&RegistrationId::from(&registration))
+            .await
+            .unwrap();
+
+        let expected = permissions[..3]
+            .iter()
+            .zip([Access::Edit, Access::View, Access::Edit])
+            .map(|(permission, access)| UserAccess {
+                user_id: permission.user_id.clone(),
+                access,
+                permission: permission.clone(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(access, expected);
+    }
+
+    #[tokio::test]
+    async fn registration_access_does_not_exist() {
+        let init = init().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let id = new_id();
+        let result = store.registration_access(&RegistrationId::from(&id)).await;
+
+        match result {
+            Err(Error::IdDoesNotExist(err_id)) => assert_eq!(err_id, id),
+            _ => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test_case(EventRole::Viewer, true ; "viewer")]
+    #[test_case(EventRole::Editor, true ; "editor")]
+    #[test_case(EventRole::Admin, false ; "admin")]
+    #[tokio::test]
+    async fn events_by_role_event_editor(min_role: EventRole, expected: bool) {
+        let init = init().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+        store
+            .upsert(vec![Permission {
+                id: "".to_owned(),
+                user_id: init.user.clone(),
+                role: PermissionRole::EventEditor(init.event.clone()),
+            }])
+            .await
+            .unwrap();
+
+        let events = store
+            .events_by_role(&UserId::from(&init.user), min_role)
+            .await
+            .unwrap();
+
+        let event_ids = events.into_iter().map(|e| e.id).collect::<Vec<_>>();
+        if expected {
+            assert_eq!(event_ids, vec![init.event]);
+        } else {
+            assert!(event_ids.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn events_by_role_inherited() {
+        let init = init().await;
+
+        let other_event = new_id();
+        sqlx::query("INSERT INTO events (id, organization, name) VALUES (?, ?, ?)")
+            .bind(&other_event)
+            .bind(&init.organization)
+            .bind("Event 2")
+            .execute(&init.db)
+            .await
+            .unwrap();
+
+        let store = SqliteStore::new(Arc::new(init.db));
+        store
+            .upsert(vec![
+                Permission {
+                    id: "".to_owned(),
+                    user_id: init.user.clone(),
+                    role: PermissionRole::OrganizationViewer(init.organization.clone()),
+                },
+                Permission {
+                    id: "".to_owned(),
+                    user_id: init.user.clone(),
+                    role: PermissionRole::EventAdmin(init.event.clone()),
+                },
+            ])
+            .await
+            .unwrap();
+
+        let mut expected = vec![init.event.clone(), other_event];
+        expected.sort();
+        let viewable = store
+            .events_by_role(&UserId::from(&init.user), EventRole::Viewer)
+            .await
+            .unwrap();
+        assert_eq!(
+            viewable.into_iter().map(|e| e.id).collect::<Vec<_>>(),
+            expected
+        );
+
+        let administered = store
+            .events_by_role(&UserId::from(&init.user), EventRole::Admin)
+            .await
+            .unwrap();
+        assert_eq!(
+            administered.into_iter().map(|e| e.id).collect::<Vec<_>>(),
+            vec![init.event]
+        );
+    }
+
+    #[tokio::test]
+    async fn events_by_role_does_not_exist() {
+        let init = init().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let id = new_id();
+        let result = store
+            .events_by_role(&UserId::from(&id), EventRole::Viewer)
+            .await;
+
+        match result {
+            Err(Error::IdDoesNotExist(err_id)) => assert_eq!(err_id, id),
+            _ => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    enum EffectiveRoleTest {
+        ServerAdmin,
+        OrganizationViewer,
+        Strongest,
+        OtherEvent,
+        NoPermissions,
+    }
+
+    #[test_case(EffectiveRoleTest::ServerAdmin, Some(EventRole::Admin) ; "server admin")]
+    #[test_case(EffectiveRoleTest::OrganizationViewer, Some(EventRole::Viewer) ; "organization viewer")]
+    #[test_case(EffectiveRoleTest::Strongest, Some(EventRole::Editor) ; "strongest")]
+    #[test_case(EffectiveRoleTest::OtherEvent, None ; "other event")]
+    #[test_case(EffectiveRoleTest::NoPermissions, None ; "no permissions")]
+    #[tokio::test]
+    async fn effective_event_role(test_name: EffectiveRoleTest, expected: Option<EventRole>) {
+        let init = init().await;
+
+        let other_event = new_id();
+        sqlx::query("INSERT INTO events (id, organization, name) VALUES (?, ?, ?)")
+            .bind(&other_event)
+            .bind(&init.organization)
+            .bind("Event 2")
+            .execute(&init.db)
+            .await
+            .unwrap();
+
+        let roles = match test_name {
+            EffectiveRoleTest::ServerAdmin => vec![PermissionRole::ServerAdmin],
+            EffectiveRoleTest::OrganizationViewer => {
+                vec![PermissionRole::OrganizationViewer(
+                    init.organization.clone(),
+                )]
+            }
+            EffectiveRoleTest::Strongest => vec![
+                PermissionRole::OrganizationViewer(init.organization.clone()),
+                PermissionRole::EventEditor(init.event.clone()),
+            ],
+            EffectiveRoleTest::OtherEvent => vec![PermissionRole::EventAdmin(other_event)],
+            EffectiveRoleTest::NoPermissions => vec![],
+        };
+
+        let store = SqliteStore::new(Arc::new(init.db));
+        store
+            .upsert(
+                roles
+                    .into_iter()
+                    .map(|role| Permission {
+                        id: "".to_owned(),
+                        user_id: init.user.clone(),
+                        role,
+                    })
+                    .collect(),
+            )
+            .await
+            .unwrap();
+
+        let role = store
+            .effective_event_role(&UserId::from(&init.user), &EventId::from(&init.event))
+            .await
+            .unwrap();
+        assert_eq!(role, expected);
+    }
+
+    #[tokio::test]
+    async fn effective_event_role_does_not_exist() {
+        let init = init().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let id = new_id();
+        let result = store
+            .effective_event_role(&UserId::from(&init.user), &EventId::from(&id))
+            .await;
+
+        match result {
+            Err(Error::IdDoesNotExist(err_id)) => assert_eq!(err_id, id),
+            _ => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[tokio::test]
+    async fn empty_input() {
+        let store = SqliteStore::new(closed_pool().await);
+
+        assert!(store.upsert(Vec::new()).await.unwrap().is_empty());
+        store.delete(&[]).await.unwrap();
+    }
+}
@@ -1,43 +1,446 @@
-use std::{collections::HashMap, iter, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{Display, Formatter},
+    iter,
+    sync::Arc,
+};
 
-use common::proto::{Registration, RegistrationItem};
+use chrono::{DateTime, Utc};
+use common::proto::{
+    registration, registration_schema_item_type::Type as ItemType, Registration, RegistrationItem,
+    RegistrationSchema, RegistrationSchemaItem, SelectOption, TagsType,
+};
 use sqlx::SqlitePool;
 
 use super::{
-    common::{ids_in_table, new_id},
-    Bindable as _, Error, Queryable as _,
+    append_pagination,
+    common::{
+        ids_in_table, new_id, normalize, nothing_to_do, now_rfc3339, parse_timestamp, Normalization,
+    },
+    event::{nullable, registration_state_at, RegistrationState},
+    ids::{EventId, RegistrationId, UserId},
+    Bindable as _, Compound as _, Error, IntoStoreError as _, Pagination, Queryable as _,
+    StoreResult,
 };
 
+/// Who is changing a registration. Once an event's registration closes, only staff may still
+/// make changes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Editor {
+    Registrant,
+    Staff,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FieldErrorReason {
+    UnknownItem,
+    DuplicateItem,
+    MissingRequired,
+    InvalidOption,
+    UnexpectedInstance,
+    InvalidTags,
+    EmptyTag,
+    TooManyTags,
+}
+
+impl Display for FieldErrorReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldErrorReason::UnknownItem => write!(f, "is not in the registration schema"),
+            FieldErrorReason::DuplicateItem => write!(f, "is answered more than once"),
+            FieldErrorReason::MissingRequired => write!(f, "is required"),
+            FieldErrorReason::InvalidOption => write!(f, "names an option that doesn't exist"),
+            FieldErrorReason::UnexpectedInstance => {
+                write!(f, "is answered more than once, but isn't in a group")
+            }
+            FieldErrorReason::InvalidTags => write!(f, "isn't a list of tags"),
+            FieldErrorReason::EmptyTag => write!(f, "has a blank tag"),
+            FieldErrorReason::TooManyTags => write!(f, "has more tags than allowed"),
+        }
+    }
+}
+
+/// A problem with a registration's answer to a single schema item.
+#[derive(thiserror::Error, Clone, Debug, PartialEq, Eq)]
+#[error("item {item_id:?} {reason}")]
+pub struct FieldError {
+    pub item_id: String,
+    /// Which repeat of the item's group the problem is in. Always 0 outside of groups.
+    pub instance: u32,
+    pub reason: FieldErrorReason,
+}
+
+impl FieldError {
+    fn new(item_id: &str, instance: u32, reason: FieldErrorReason) -> Self {
+        FieldError {
+            item_id: item_id.to_owned(),
+            instance,
+            reason,
+        }
+    }
+}
+
+/// Whether a schema item must be filled in at `now`: either its required flag is set or its
+/// required_after time has passed.
+pub fn is_required(item: &RegistrationSchemaItem, now: DateTime<Utc>) -> bool {
+    item.required
+        || parse_timestamp(&item.required_after)
+            .ok()
+            .flatten()
+            .is_some_and(|required_after| required_after <= now)
+}
+
+// Select values may be an option index or the option name itself.
+fn option_fits(options: &[SelectOption], value: &str) -> bool {
+    options.iter().any(|option| option.name == value)
+        || matches!(value.parse::<usize>(), Ok(idx) if idx < options.len())
+}
+
+/// The tags in a tags item's value, which is a JSON array of strings. An empty value has no tags,
+/// and anything else that isn't such an array has none either.
+pub fn parse_tags(value: &str) -> Option<Vec<String>> {
+    if value.is_empty() {
+        return Some(Vec::new());
+    }
+
+    serde_json::from_str(value).ok()
+}
+
+fn check_tags(tags: &TagsType, value: &str) -> Result<(), FieldErrorReason> {
+    let values = parse_tags(value).ok_or(FieldErrorReason::InvalidTags)?;
+
+    if values.iter().any(|tag| tag.trim().is_empty()) {
+        Err(FieldErrorReason::EmptyTag)
+    } else if tags.max_tags != 0 && values.len() > tags.max_tags as usize {
+        Err(FieldErrorReason::TooManyTags)
+    } else {
+        Ok(())
+    }
+}
+
+fn check_value(item: &RegistrationSchemaItem, value: &str) -> Result<(), FieldErrorReason> {
+    let fits = match item.r#type.as_ref().and_then(|t| t.r#type.as_ref()) {
+        Some(ItemType::Tags(tags)) => return check_tags(tags, value),
+        Some(ItemType::Select(select)) => value.is_empty() || option_fits(&select.options, value),
+        Some(ItemType::MultiSelect(multi_select)) => {
+            value.is_empty()
+                || value
+                    .split(',')
+                    .all(|v| option_fits(&multi_select.options, v))
+        }
+        _ => true,
+    };
+
+    if fits {
+        Ok(())
+    } else {
+        Err(FieldErrorReason::InvalidOption)
+    }
+}
+
+fn is_answerable(item: &RegistrationSchemaItem) -> bool {
+    !matches!(
+        item.r#type.as_ref().and_then(|t| t.r#type.as_ref()),
+        Some(ItemType::Section(_)) | Some(ItemType::Group(_))
+    )
+}
+
+fn group_items(item: &RegistrationSchemaItem) -> Option<&[RegistrationSchemaItem]> {
+    match item.r#type.as_ref().and_then(|t| t.r#type.as_ref()) {
+        Some(ItemType::Group(group)) => Some(&group.items),
+        _ => None,
+    }
+}
+
+/// The canonical form of a checkbox value: "true" or "false" for any of the spellings clients
+/// send, case-insensitively. An empty value stays empty so an unanswered checkbox remains
+/// unanswered.
+pub fn normalize_checkbox_value(value: &str) -> Option<&'static str> {
+    match value.to_lowercase().as_str() {
+        "" => Some(""),
+        "true" | "1" | "yes" => Some("true"),
+        "false" | "0" | "no" => Some("false"),
+        _ => None,
+    }
+}
+
+/// A new, unsaved registration for the source's event, pre-filled with the source's values. Items
+/// marked blank_on_copy are left out, as is everything in a group marked blank_on_copy.
+pub fn copy_registration(source: &Registration, schema: &RegistrationSchema) -> Registration {
+    let blanked = schema
+        .items
+        .iter()
+        .filter(|item| item.blank_on_copy)
+        .flat_map(|item| iter::once(item).chain(group_items(item).into_iter().flatten()))
+        .chain(
+            schema
+                .items
+                .iter()
+                .filter_map(group_items)
+                .flatten()
+                .filter(|item| item.blank_on_copy),
+        )
+        .map(|item| item.id.as_str())
+        .collect::<HashSet<_>>();
+
+    Registration {
+        event_id: source.event_id.clone(),
+        items: source
+            .items
+            .iter()
+            .filter(|item| !blanked.contains(item.schema_item_id.as_str()))
+            .cloned()
+            .collect(),
+        ..Default::default()
+    }
+}
+
+// Registrants may only answer the event's own schema items, including those inside groups, but
+// not sections or the groups themselves. Items inside a group are returned with their group.
+fn answerable_items(
+    schema: &RegistrationSchema,
+) -> HashMap<&str, (&RegistrationSchemaItem, Option<&RegistrationSchemaItem>)> {
+    schema
+        .items
+        .iter()
+        .flat_map(|item| {
+            iter::once((item, None)).chain(
+                group_items(item)
+                    .into_iter()
+                    .flatten()
+                    .map(move |group_item| (group_item, Some(item))),
+            )
+        })
+        .filter(|(item, _)| is_answerable(item))
+        .map(|(item, group)| (item.id.as_str(), (item, group)))
+        .collect()
+}
+
+/// Checks a single answer on its own, as a registrant fills in the form, using the same rules as
+/// [`validate_items`].
+pub fn validate_value(
+    schema: &RegistrationSchema,
+    schema_item_id: &str,
+    value: &str,
+    now: DateTime<Utc>,
+) -> Result<(), FieldErrorReason> {
+    let (item, _) = answerable_items(schema)
+        .remove(schema_item_id)
+        .ok_or(FieldErrorReason::UnknownItem)?;
+
+    if value.trim().is_empty() && is_required(item, now) {
+        return Err(FieldErrorReason::MissingRequired);
+    }
+
+    check_value(item, value)
+}
+
+/// Checks a draft's answers the way [`validate_items`] does, except that required items may still
+/// be left blank.
+pub fn validate_draft_items(
+    schema: &RegistrationSchema,
+    items: &[RegistrationItem],
+    now: DateTime<Utc>,
+) -> StoreResult<()> {
+    match validate_items(schema, items, now) {
+        Err(Error::RegistrationValidation(errors)) => {
+            let errors = errors
+                .into_iter()
+                .filter(|error| error.reason != FieldErrorReason::MissingRequired)
+                .collect::<Vec<_>>();
+
+            if errors.is_empty() {
+                Ok(())
+            } else {
+                Err(Error::RegistrationValidation(errors))
+            }
+        }
+        result => result,
+    }
+}
+
+/// Checks a registrant's answers against the event's schema. Each schema item may be answered at
+/// most once, or once per repeat for items inside a group, with a value that fits its type, and
+/// every item that is currently required must be filled in. A required group must be filled in at
+/// least once, and each repeat that is filled in must have its own required items. All problems
+/// are returned together so they can be fixed in one go.
+pub fn validate_items(
+    schema: &RegistrationSchema,
+    items: &[RegistrationItem],
+    now: DateTime<Utc>,
+) -> StoreResult<()> {
+    let schema_items = answerable_items(schema);
+
+    let mut errors = Vec::new();
+    let mut answered = HashSet::new();
+    let mut duplicated = HashSet::new();
+    for item in items {
+        let item_id = item.schema_item_id.as_str();
+        let Some((schema_item, group)) = schema_items.get(item_id) else {
+            errors.push(FieldError::new(
+                item_id,
+                item.instance,
+                FieldErrorReason::UnknownItem,
+            ));
+            continue;
+        };
+
+        if group.is_none() && item.instance != 0 {
+            errors.push(FieldError::new(
+                item_id,
+                item.instance,
+                FieldErrorReason::UnexpectedInstance,
+            ));
+        } else if !answered.insert((item_id, item.instance)) {
+            if duplicated.insert((item_id, item.instance)) {
+                errors.push(FieldError::new(
+                    item_id,
+                    item.instance,
+                    FieldErrorReason::DuplicateItem,
+                ));
+            }
+        } else if let Err(reason) = check_value(schema_item, &item.value) {
+            errors.push(FieldError::new(item_id, item.instance, reason));
+        }
+    }
+
+    // As with incomplete registrations, an empty value doesn't count as filled in.
+    let filled = items
+        .iter()
+        .filter(|item| !item.value.trim().is_empty())
+        .map(|item| (item.schema_item_id.as_str(), item.instance))
+        .collect::<HashSet<_>>();
+
+    for item in schema.items.iter() {
+        let Some(group_items) = group_items(item) else {
+            if schema_items.contains_key(item.id.as_str())
+                && is_required(item, now)
+                && !filled.contains(&(item.id.as_str(), 0))
+            {
+                errors.push(FieldError::new(
+                    &item.id,
+                    0,
+                    FieldErrorReason::MissingRequired,
+                ));
+            }
+            continue;
+        };
+
+        // A repeat only exists once something in it is filled in.
+        let mut instances = filled
+            .iter()
+            .filter(|(item_id, _)| {
+                group_items
+                    .iter()
+                    .any(|group_item| group_item.id == *item_id)
+            })
+            .map(|(_, instance)| *instance)
+            .collect::<Vec<_>>();
+        instances.sort_unstable();
+        instances.dedup();
+
+        if instances.is_empty() && is_required(item, now) {
+            errors.push(FieldError::new(
+                &item.id,
+                0,
+                FieldErrorReason::MissingRequired,
+            ));
+        }
+
+        for instance in instances {
+            errors.extend(
+                group_items
+                    .iter()
+                    .filter(|group_item| {
+                        is_answerable(group_item)
+                            && is_required(group_item, now)
+                            && !filled.contains(&(group_item.id.as_str(), instance))
+                    })
+                    .map(|group_item| {
+                        FieldError::new(&group_item.id, instance, FieldErrorReason::MissingRequired)
+                    }),
+            );
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::RegistrationValidation(errors))
+    }
+}
+
 #[derive(sqlx::FromRow)]
 struct RegistrationRow {
     id: String,
     event: String,
+    status: String,
+    user: Option<String>,
 }
 
 impl RegistrationRow {
-    fn to_registration(self) -> Registration {
-        Registration {
+    fn into_registration(self) -> StoreResult<Registration> {
+        Ok(Registration {
             id: self.id,
             event_id: self.event,
             items: Vec::new(),
-        }
+            status: registration::Status::from_str_name(&self.status)
+                .ok_or(Error::ColumnParseError("status"))? as i32,
+            user_id: self.user.unwrap_or_default(),
+        })
     }
 }
 
+fn status_name(status: i32) -> StoreResult<&'static str> {
+    registration::Status::try_from(status)
+        .map(|status| status.as_str_name())
+        .map_err(|_| Error::InvalidStatus(status))
+}
+
+#[derive(sqlx::FromRow)]
+struct MoveItemRow {
+    id: String,
+    schema_item: String,
+    instance: u32,
+    value: String,
+    name: String,
+    item_type: String,
+    grouped: bool,
+}
+
+#[derive(sqlx::FromRow)]
+struct TargetItemRow {
+    id: String,
+    name: String,
+    item_type: String,
+    grouped: bool,
+}
+
+#[derive(sqlx::FromRow)]
+struct MergeItemRow {
+    id: String,
+    registration: String,
+    schema_item: String,
+    instance: u32,
+    value: String,
+}
+
 #[derive(sqlx::FromRow)]
 struct RegistrationItemRow {
     registration: String,
     schema_item: String,
+    instance: u32,
     value: String,
 }
 
 impl RegistrationItemRow {
-    fn to_registration_item(self) -> Result<(String, RegistrationItem), Error> {
+    fn into_registration_item(self) -> StoreResult<(String, RegistrationItem)> {
         Ok((
             self.registration,
             RegistrationItem {
                 schema_item_id: self.schema_item,
                 value: self.value,
+                instance: self.instance,
             },
         ))
     }
@@ -67,9 +470,23 @@ impl super::Field for EventIdField {
 
 pub type EventIdQuery = super::LogicalQuery<EventIdField>;
 
+/// Matches the stored status name, such as "WAITLISTED".
+pub struct StatusField;
+
+impl super::Field for StatusField {
+    type Item = String;
+
+    fn field() -> &'static str {
+        "status"
+    }
+}
+
+pub type StatusQuery = super::LogicalQuery<StatusField>;
+
 pub enum Query {
     Id(IdQuery),
     EventId(EventIdQuery),
+    Status(StatusQuery),
     Compound(super::CompoundQuery<Query>),
 }
 
@@ -78,15 +495,23 @@ impl super::Queryable for Query {
         match self {
             Query::Id(query) => query.where_clause(),
             Query::EventId(query) => query.where_clause(),
+            Query::Status(query) => query.where_clause(),
             Query::Compound(query) => query.where_clause(),
         }
     }
 }
 
+impl super::Compound for Query {
+    fn compound(query: super::CompoundQuery<Query>) -> Self {
+        Query::Compound(query)
+    }
+}
+
 impl<'q, DB: sqlx::Database> super::Bindable<'q, DB> for Query
 where
     <IdField as super::Field>::Item: sqlx::Encode<'q, DB> + sqlx::Type<DB> + Sync,
     <EventIdField as super::Field>::Item: sqlx::Encode<'q, DB> + sqlx::Type<DB> + Sync,
+    <StatusField as super::Field>::Item: sqlx::Encode<'q, DB> + sqlx::Type<DB> + Sync,
 {
     fn bind<O>(
         &'q self,
@@ -100,11 +525,43 @@ where
         match self {
             Query::Id(query) => query.bind(query_builder),
             Query::EventId(query) => query.bind(query_builder),
+            Query::Status(query) => query.bind(query_builder),
             Query::Compound(query) => query.bind(query_builder),
         }
     }
 }
 
+// Drafts haven't been submitted yet, so listings leave them out unless the query picks
+// registrations by status.
+fn selects_status(query: &Query) -> bool {
+    match query {
+        Query::Status(_) => true,
+        Query::Compound(compound) => compound.queries.iter().any(selects_status),
+        Query::Id(_) | Query::EventId(_) => false,
+    }
+}
+
+fn where_clause(query: Option<&Query>, hide_drafts: bool) -> Option<String> {
+    let clauses = query
+        .map(|query| format!("({})", query.where_clause()))
+        .into_iter()
+        .chain(hide_drafts.then(|| "status != 'DRAFT'".to_owned()))
+        .collect::<Vec<_>>();
+
+    (!clauses.is_empty()).then(|| clauses.join(" AND "))
+}
+
+// Values for items inside a group sort with their group, one repeat at a time.
+const ITEMS_IN_SCHEMA_ORDER_JOIN: &str = "registration_items
+    JOIN registration_schema_items ON registration_items.schema_item = registration_schema_items.id
+    LEFT JOIN registration_schema_items AS groups ON registration_schema_items.parent = groups.id";
+
+const ITEMS_IN_SCHEMA_ORDER: &str = "COALESCE(groups.idx, registration_schema_items.idx),
+    registration_items.instance,
+    registration_schema_items.parent IS NOT NULL,
+    registration_schema_items.idx,
+    registration_items.id";
+
 fn attach_items(
     registrations: impl IntoIterator<Item = Registration>,
     registration_items: impl IntoIterator<Item = (String, RegistrationItem)>,
@@ -123,127 +580,552 @@ fn attach_items(
         .collect()
 }
 
+pub const DEFAULT_MAX_VALUE_LENGTH: usize = 10_000;
+
 #[derive(Debug)]
 pub struct SqliteStore {
     pool: Arc<SqlitePool>,
+    read_pool: Arc<SqlitePool>,
+    max_value_length: usize,
 }
 
 impl SqliteStore {
     pub fn new(pool: Arc<SqlitePool>) -> Self {
-        SqliteStore { pool }
+        SqliteStore {
+            read_pool: pool.clone(),
+            pool,
+            max_value_length: DEFAULT_MAX_VALUE_LENGTH,
+        }
     }
-}
 
-type QueryBuilder<'q> = sqlx::query::Query<
-    'q,
-    sqlx::Sqlite,
-    <sqlx::Sqlite as sqlx::database::HasArguments<'q>>::Arguments,
->;
+    /// Sends the reporting reads (`query`, `query_page` and `incomplete_registrations`) to a
+    /// separate, typically read-only, pool so they don't contend with writes. Without one they
+    /// use the primary pool.
+    pub fn with_read_pool(mut self, read_pool: Arc<SqlitePool>) -> Self {
+        self.read_pool = read_pool;
+        self
+    }
 
-#[tonic::async_trait]
-pub trait Store: Send + Sync + 'static {
-    async fn upsert(&self, registrations: Vec<Registration>) -> Result<Vec<Registration>, Error>;
-    async fn query(&self, query: Option<&Query>) -> Result<Vec<Registration>, Error>;
-    async fn delete(&self, ids: &Vec<String>) -> Result<(), Error>;
-}
+    /// Limits how many characters any single item value may have, whatever the item's type.
+    pub fn with_max_value_length(mut self, max_value_length: usize) -> Self {
+        self.max_value_length = max_value_length;
+        self
+    }
 
-fn bind_item<'q>(
-    query_builder: QueryBuilder<'q>,
-    registration_id: &'q str,
-    item_id: &'q str,
-    item: &'q RegistrationItem,
-) -> QueryBuilder<'q> {
-    query_builder
-        .bind(item_id)
-        .bind(registration_id)
-        .bind(&item.schema_item_id)
-        .bind(&item.value)
-}
+    async fn ensure_registration_open(&self, event_ids: &[&str]) -> StoreResult<()> {
+        if event_ids.is_empty() {
+            return Ok(());
+        }
 
-#[tonic::async_trait]
-impl Store for SqliteStore {
-    async fn upsert(&self, registrations: Vec<Registration>) -> Result<Vec<Registration>, Error> {
-        ids_in_table(
-            &*self.pool,
-            "events",
-            registrations
-                .iter()
-                .map(|registration| registration.event_id.as_str()),
-        )
-        .await?;
+        let where_clause: String =
+            itertools::Itertools::intersperse(iter::repeat_n("id = ?", event_ids.len()), " OR ")
+                .collect();
 
-        let registrations_and_items = registrations.into_iter().enumerate().map(|(idx, mut r)| {
-            let items = std::mem::take(&mut r.items);
-            ((idx, r), items)
-        });
+        let query = format!(
+            "SELECT id, registration_opens_at, registration_closes_at FROM events WHERE {} ORDER BY id",
+            where_clause
+        );
 
-        let (inserts_and_items, updates_and_items): (Vec<_>, Vec<_>) =
-            registrations_and_items.partition(|((_, r), _)| r.id == "");
+        let schedules: Vec<(String, Option<String>, Option<String>)> = event_ids
+            .iter()
+            .fold(sqlx::query_as(&query), |query_builder, id| {
+                query_builder.bind(id)
+            })
+            .fetch_all(&*self.pool)
+            .await
+            .fetch_err()?;
+
+        let now = Utc::now();
+        for (event_id, opens_at, closes_at) in schedules {
+            match registration_state_at(
+                opens_at.as_deref().unwrap_or_default(),
+                closes_at.as_deref().unwrap_or_default(),
+                &now,
+            ) {
+                RegistrationState::NotYetOpen => return Err(Error::RegistrationNotOpen(event_id)),
+                RegistrationState::Closed => return Err(Error::RegistrationClosed(event_id)),
+                RegistrationState::Open => (),
+            }
+        }
 
-        let (updates, insert_items, update_items) = if !updates_and_items.is_empty() {
-            ids_in_table(
-                &*self.pool,
-                "registrations",
-                updates_and_items.iter().map(|((_, r), _)| r.id.as_str()),
-            )
-            .await?;
+        Ok(())
+    }
 
-            let where_clause = itertools::Itertools::intersperse(
-                updates_and_items.iter().map(|(_, items)| {
-                    let item_clause = itertools::Itertools::intersperse(
-                        iter::repeat("schema_item = ?").take(items.len()),
-                        " OR ",
-                    )
-                    .collect::<String>();
+    async fn ensure_account(&self, registrations: &[Registration]) -> StoreResult<()> {
+        let event_ids = registrations
+            .iter()
+            .filter(|registration| registration.user_id.is_empty())
+            .map(|registration| registration.event_id.as_str())
+            .collect::<HashSet<_>>();
 
-                    format!("registration = ? AND ({})", item_clause)
-                }),
-                " OR ".to_owned(),
-            )
-            .collect::<String>();
+        if event_ids.is_empty() {
+            return Ok(());
+        }
 
-            let query = format!(
-                "SELECT registration, schema_item, id FROM registration_items WHERE {}",
-                where_clause
-            );
+        let where_clause: String =
+            itertools::Itertools::intersperse(iter::repeat_n("id = ?", event_ids.len()), " OR ")
+                .collect();
 
-            let query_builder = sqlx::query_as(&query);
-            let query_builder =
-                updates_and_items
-                    .iter()
-                    .fold(query_builder, |query_builder, ((_, r), items)| {
-                        let query_builder = query_builder.bind(&r.id);
-                        items.iter().fold(query_builder, |query_builder, item| {
-                            query_builder.bind(&item.schema_item_id)
-                        })
-                    });
+        let query = format!(
+            "SELECT id FROM events WHERE ({}) AND require_account = TRUE LIMIT 1",
+            where_clause
+        );
+
+        let query_builder = event_ids
+            .iter()
+            .fold(sqlx::query_as(&query), |query_builder, id| {
+                query_builder.bind(id)
+            });
+
+        let restricted: Option<(String,)> = query_builder
+            .fetch_optional(&*self.pool)
+            .await
+            .fetch_err()?;
+
+        match restricted {
+            Some((event_id,)) => Err(Error::AccountRequired(event_id)),
+            None => Ok(()),
+        }
+    }
 
-            let rows: Vec<(String, String, String)> = query_builder
-                .fetch_all(&*self.pool)
+    // The event's dedup item, and whether registrants are kept from saving a duplicate.
+    async fn dedup_item(&self, event_id: &str) -> StoreResult<Option<(String, bool)>> {
+        let row: Option<(Option<String>, bool)> =
+            sqlx::query_as("SELECT dedup_item, block_duplicates FROM events WHERE id = ?")
+                .bind(event_id)
+                .fetch_optional(&*self.pool)
                 .await
-                .map_err(|e| Error::FetchError(e))?;
+                .fetch_err()?;
 
-            let mut id_map = rows
-                .into_iter()
-                .map(|(registration, schema_item, id)| ((registration, schema_item), id))
-                .collect::<HashMap<_, _>>();
+        Ok(row.and_then(|(dedup_item, block)| Some((dedup_item?, block))))
+    }
 
-            let (updates, items_from_updates): (Vec<_>, Vec<_>) =
-                updates_and_items.into_iter().unzip();
+    async fn duplicate_of(
+        &self,
+        registration: &Registration,
+        dedup_item: &str,
+    ) -> StoreResult<Option<String>> {
+        let Some(value) = registration
+            .items
+            .iter()
+            .find(|item| item.schema_item_id == dedup_item && !item.value.is_empty())
+            .map(|item| item.value.as_str())
+        else {
+            return Ok(None);
+        };
 
-            let mut insert_items = Vec::new();
-            let mut update_items = Vec::new();
+        let duplicate: Option<(String,)> = sqlx::query_as(
+            "SELECT registrations.id FROM registrations
+            JOIN registration_items ON registration_items.registration = registrations.id
+            WHERE registrations.event = ? AND registrations.id != ? AND registrations.status != 'DRAFT'
+                AND registration_items.schema_item = ? AND registration_items.value = ? COLLATE NOCASE
+            ORDER BY registrations.id LIMIT 1",
+        )
+        .bind(&registration.event_id)
+        .bind(&registration.id)
+        .bind(dedup_item)
+        .bind(value)
+        .fetch_optional(&*self.pool)
+        .await
+        .fetch_err()?;
 
-            updates
-                .iter()
-                .map(|(idx, r)| (idx, &r.id))
-                .zip(items_from_updates.into_iter())
-                .for_each(|((registration_idx, registration_id), items)| {
-                    for (item_idx, item) in items.into_iter().enumerate() {
-                        let item_id =
-                            id_map.remove(&(registration_id.clone(), item.schema_item_id.clone()));
+        Ok(duplicate.map(|(id,)| id))
+    }
 
-                        match item_id {
+    // Drafts are neither checked nor matched, since they haven't been submitted yet.
+    async fn ensure_not_duplicate(&self, registrations: &[Registration]) -> StoreResult<()> {
+        for registration in registrations
+            .iter()
+            .filter(|registration| registration.status != registration::Status::Draft as i32)
+        {
+            let Some((dedup_item, true)) = self.dedup_item(&registration.event_id).await? else {
+                continue;
+            };
+
+            if let Some(existing_registration_id) =
+                self.duplicate_of(registration, &dedup_item).await?
+            {
+                return Err(Error::DuplicateRegistration {
+                    existing_registration_id,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    // Rewrites checkbox values to "true" or "false", whichever spelling the client sent.
+    async fn normalize_checkbox_values(
+        &self,
+        registrations: &mut [Registration],
+    ) -> StoreResult<()> {
+        let schema_item_ids = registrations
+            .iter()
+            .flat_map(|registration| registration.items.iter())
+            .map(|item| item.schema_item_id.as_str())
+            .collect::<HashSet<_>>();
+
+        if schema_item_ids.is_empty() {
+            return Ok(());
+        }
+
+        let where_clause: String = itertools::Itertools::intersperse(
+            iter::repeat_n("id = ?", schema_item_ids.len()),
+            " OR ",
+        )
+        .collect();
+
+        let query = format!(
+            "SELECT id FROM registration_schema_items WHERE item_type = 'CheckboxType' AND ({})",
+            where_clause
+        );
+
+        let query_builder = schema_item_ids
+            .iter()
+            .fold(sqlx::query_as(&query), |query_builder, id| {
+                query_builder.bind(id)
+            });
+
+        let rows: Vec<(String,)> = query_builder.fetch_all(&*self.pool).await.fetch_err()?;
+        let checkboxes = rows.into_iter().map(|(id,)| id).collect::<HashSet<_>>();
+
+        for item in registrations
+            .iter_mut()
+            .flat_map(|registration| registration.items.iter_mut())
+            .filter(|item| checkboxes.contains(&item.schema_item_id))
+        {
+            match normalize_checkbox_value(&item.value) {
+                Some(value) => item.value = value.to_owned(),
+                None => {
+                    return Err(Error::InvalidRegistrationValue {
+                        item_id: item.schema_item_id.clone(),
+                        value: item.value.clone(),
+                    })
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Registrations matching the query in id order, without their items.
+    async fn fetch_registrations(
+        &self,
+        pool: &SqlitePool,
+        query: Option<&Query>,
+        page: Option<Pagination>,
+        hide_drafts: bool,
+    ) -> StoreResult<Vec<Registration>> {
+        let base_query_string = "SELECT id, event, status, user FROM registrations";
+        let query_string = match where_clause(query, hide_drafts) {
+            Some(where_clause) => {
+                format!("{} WHERE {} ORDER BY id", base_query_string, where_clause)
+            }
+            None => format!("{} ORDER BY id", base_query_string),
+        };
+
+        let query_string = append_pagination(query_string, page.as_ref());
+
+        let query_builder = sqlx::query_as(&query_string);
+        let query_builder = match query {
+            Some(query) => query.bind(query_builder),
+            None => query_builder,
+        };
+
+        let query_builder = match &page {
+            Some(page) => page.bind(query_builder),
+            None => query_builder,
+        };
+
+        let rows: Vec<RegistrationRow> = query_builder.fetch_all(pool).await.fetch_err()?;
+
+        rows.into_iter()
+            .map(|row| row.into_registration())
+            .collect::<Result<Vec<_>, _>>()
+    }
+
+    async fn attach_stored_items(
+        &self,
+        pool: &SqlitePool,
+        registrations: Vec<Registration>,
+    ) -> StoreResult<Vec<Registration>> {
+        if registrations.is_empty() {
+            return Ok(registrations);
+        }
+
+        let items = {
+            let where_clause: String = itertools::Itertools::intersperse(
+                std::iter::repeat_n("registration = ?", registrations.len()),
+                " OR ",
+            )
+            .collect();
+
+            let query = format!(
+                "SELECT registration_items.id, registration, schema_item, instance, value
+                FROM {}
+                WHERE {}
+                ORDER BY {}",
+                ITEMS_IN_SCHEMA_ORDER_JOIN, where_clause, ITEMS_IN_SCHEMA_ORDER
+            );
+
+            let query_builder = sqlx::query_as(&query);
+            let query_builder = registrations
+                .iter()
+                .fold(query_builder, |query_builder, r| query_builder.bind(&r.id));
+
+            let rows: Vec<RegistrationItemRow> = query_builder.fetch_all(pool).await.fetch_err()?;
+
+            rows.into_iter()
+                .map(|row| row.into_registration_item())
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        let registrations = attach_items(registrations, items);
+
+        Ok(registrations)
+    }
+
+    // Writes read their results back through the primary pool so a lagging replica can't hand
+    // back stale rows.
+    async fn query_in(
+        &self,
+        pool: &SqlitePool,
+        query: Option<&Query>,
+    ) -> StoreResult<Vec<Registration>> {
+        let registrations = self.fetch_registrations(pool, query, None, false).await?;
+
+        self.attach_stored_items(pool, registrations).await
+    }
+}
+
+type QueryBuilder<'q> = sqlx::query::Query<
+    'q,
+    sqlx::Sqlite,
+    <sqlx::Sqlite as sqlx::database::HasArguments<'q>>::Arguments,
+>;
+
+#[tonic::async_trait]
+pub trait Store: Send + Sync + 'static {
+    async fn upsert(
+        &self,
+        registrations: Vec<Registration>,
+        editor: Editor,
+    ) -> StoreResult<Vec<Registration>>;
+    /// Registrations are returned in id order, with their items in schema order.
+    async fn query(&self, query: Option<&Query>) -> StoreResult<Vec<Registration>>;
+    /// Like query, but returns at most `page.limit` registrations after skipping `page.offset`
+    /// of them, along with the total number of registrations the query matches. Drafts are left
+    /// out unless the query selects by status.
+    async fn query_page(
+        &self,
+        query: Option<&Query>,
+        page: Pagination,
+    ) -> StoreResult<(Vec<Registration>, u64)>;
+    async fn delete(&self, ids: &[String]) -> StoreResult<()>;
+    /// Deletes every registration for the event, returning how many were removed.
+    async fn delete_all_for_event(&self, event_id: &EventId) -> StoreResult<u64>;
+    /// Submitted registrations for the event without a value for at least one required schema
+    /// item, in id order. An item is required if its required flag is set or its required_after
+    /// time has passed.
+    async fn incomplete_registrations(&self, event_id: &EventId) -> StoreResult<Vec<Registration>>;
+    async fn move_to_event(
+        &self,
+        registration_id: &RegistrationId,
+        event_id: &EventId,
+    ) -> StoreResult<Registration>;
+    async fn set_status_many(
+        &self,
+        ids: &[String],
+        status: registration::Status,
+    ) -> StoreResult<Vec<Registration>>;
+    /// Folds duplicate registrations into the kept one and deletes them. Item values the kept
+    /// registration is missing are taken from the dropped registrations, earlier ones first; the
+    /// kept registration's own values always win.
+    async fn merge_registrations(
+        &self,
+        keep_id: &RegistrationId,
+        drop_ids: &[RegistrationId],
+    ) -> StoreResult<Registration>;
+    /// The earliest other registration for the same event whose value for the event's dedup
+    /// item matches this one's, ignoring case. None if the event has no dedup item or the
+    /// registration left it blank.
+    async fn find_duplicate(&self, registration: &Registration) -> StoreResult<Option<String>>;
+    /// The user's draft registration for the event, if they have one. Should there be several,
+    /// the earliest is returned.
+    async fn find_draft(&self, event_id: &EventId, user_id: &UserId)
+        -> StoreResult<Option<String>>;
+}
+
+// Schema item ids are unique per event, so items are matched across events by name and type.
+// Select values may be an option index or the option name itself. An index is resolved against
+// the source item's options and rewritten as the index of the same option in the target, while a
+// name is kept as is. None if any option has no match in the target.
+fn move_option_value(
+    item_type: &str,
+    value: &str,
+    source: &[String],
+    target: &[String],
+) -> Option<String> {
+    let values = match item_type {
+        "SelectType" => vec![value],
+        "MultiSelectType" => value.split(',').collect(),
+        _ => return Some(value.to_owned()),
+    };
+
+    if value.is_empty() {
+        return Some(value.to_owned());
+    }
+
+    let move_option = |v: &str| match v.parse::<usize>().ok().and_then(|idx| source.get(idx)) {
+        Some(name) => target
+            .iter()
+            .position(|o| o == name)
+            .map(|idx| idx.to_string()),
+        None => target.iter().any(|o| o == v).then(|| v.to_owned()),
+    };
+
+    values
+        .into_iter()
+        .map(move_option)
+        .collect::<Option<Vec<_>>>()
+        .map(|values| values.join(","))
+}
+
+fn bind_item<'q>(
+    query_builder: QueryBuilder<'q>,
+    registration_id: &'q str,
+    item_id: &'q str,
+    item: &'q RegistrationItem,
+) -> QueryBuilder<'q> {
+    query_builder
+        .bind(item_id)
+        .bind(registration_id)
+        .bind(&item.schema_item_id)
+        .bind(item.instance)
+        .bind(&item.value)
+}
+
+#[tonic::async_trait]
+impl Store for SqliteStore {
+    async fn upsert(
+        &self,
+        mut registrations: Vec<Registration>,
+        editor: Editor,
+    ) -> StoreResult<Vec<Registration>> {
+        if let Some(result) = nothing_to_do(&registrations) {
+            return result;
+        }
+
+        for item in registrations.iter_mut().flat_map(|r| r.items.iter_mut()) {
+            item.value = normalize(&item.value, Normalization::Trim);
+
+            if item.value.chars().count() > self.max_value_length {
+                return Err(Error::ValueTooLong {
+                    item_id: item.schema_item_id.clone(),
+                    max: self.max_value_length,
+                });
+            }
+        }
+
+        self.normalize_checkbox_values(&mut registrations).await?;
+
+        if editor == Editor::Registrant {
+            self.ensure_registration_open(
+                &registrations
+                    .iter()
+                    .map(|registration| registration.event_id.as_str())
+                    .collect::<Vec<_>>(),
+            )
+            .await?;
+
+            self.ensure_account(&registrations).await?;
+            self.ensure_not_duplicate(&registrations).await?;
+        }
+
+        ids_in_table(
+            &*self.pool,
+            "events",
+            registrations
+                .iter()
+                .map(|registration| registration.event_id.as_str()),
+        )
+        .await?;
+
+        let registrations_and_items = registrations.into_iter().enumerate().map(|(idx, mut r)| {
+            let items = std::mem::take(&mut r.items);
+            ((idx, r), items)
+        });
+
+        let (inserts_and_items, updates_and_items): (Vec<_>, Vec<_>) =
+            registrations_and_items.partition(|((_, r), _)| r.id == "");
+
+        let (updates, insert_items, update_items) = if !updates_and_items.is_empty() {
+            ids_in_table(
+                &*self.pool,
+                "registrations",
+                updates_and_items.iter().map(|((_, r), _)| r.id.as_str()),
+            )
+            .await?;
+
+            let where_clause = itertools::Itertools::intersperse(
+                updates_and_items.iter().map(|(_, items)| {
+                    let item_clause = itertools::Itertools::intersperse(
+                        iter::repeat("schema_item = ?").take(items.len()),
+                        " OR ",
+                    )
+                    .collect::<String>();
+
+                    format!("registration = ? AND ({})", item_clause)
+                }),
+                " OR ".to_owned(),
+            )
+            .collect::<String>();
+
+            let query = format!(
+                "SELECT registration, schema_item, instance, id FROM registration_items WHERE {}",
+                where_clause
+            );
+
+            let query_builder = sqlx::query_as(&query);
+            let query_builder =
+                updates_and_items
+                    .iter()
+                    .fold(query_builder, |query_builder, ((_, r), items)| {
+                        let query_builder = query_builder.bind(&r.id);
+                        items.iter().fold(query_builder, |query_builder, item| {
+                            query_builder.bind(&item.schema_item_id)
+                        })
+                    });
+
+            let rows: Vec<(String, String, u32, String)> =
+                query_builder.fetch_all(&*self.pool).await.fetch_err()?;
+
+            let mut id_map = rows
+                .into_iter()
+                .map(|(registration, schema_item, instance, id)| {
+                    ((registration, schema_item, instance), id)
+                })
+                .collect::<HashMap<_, _>>();
+
+            let (updates, items_from_updates): (Vec<_>, Vec<_>) =
+                updates_and_items.into_iter().unzip();
+
+            let mut insert_items = Vec::new();
+            let mut update_items = Vec::new();
+
+            updates
+                .iter()
+                .map(|(idx, r)| (idx, &r.id))
+                .zip(items_from_updates.into_iter())
+                .for_each(|((registration_idx, registration_id), items)| {
+                    for (item_idx, item) in items.into_iter().enumerate() {
+                        let item_id = id_map.remove(&(
+                            registration_id.clone(),
+                            item.schema_item_id.clone(),
+                            item.instance,
+                        ));
+
+                        match item_id {
                             Some(item_id) => {
                                 update_items.push((*registration_idx, item_idx, item, item_id))
                             }
@@ -291,60 +1173,68 @@ impl Store for SqliteStore {
             .map(|(registration_idx, item_idx, item)| (registration_idx, item_idx, item, new_id()))
             .collect::<Vec<_>>();
 
-        let mut tx = self
-            .pool
-            .begin()
-            .await
-            .map_err(|e| Error::TransactionStartError(e))?;
+        let mut tx = self.pool.begin().await.begin_err()?;
 
         if !inserts.is_empty() {
             let values_clause: String = itertools::Itertools::intersperse(
-                std::iter::repeat("(?, ?)").take(inserts.len()),
+                std::iter::repeat_n("(?, ?, ?, ?)", inserts.len()),
                 ", ",
             )
             .collect();
 
             let query = format!(
-                "INSERT INTO registrations(id, event) VALUES {}",
+                "INSERT INTO registrations(id, event, status, user) VALUES {}",
                 values_clause
             );
 
             let query_builder = sqlx::query(&query);
-            let query_builder = inserts.iter().fold(query_builder, |query_builder, (_, r)| {
-                query_builder.bind(&r.id).bind(&r.event_id)
-            });
+            let query_builder =
+                inserts
+                    .iter()
+                    .try_fold(query_builder, |query_builder, (_, r)| {
+                        Ok::<_, Error>(
+                            query_builder
+                                .bind(&r.id)
+                                .bind(&r.event_id)
+                                .bind(status_name(r.status)?)
+                                .bind(nullable(&r.user_id)),
+                        )
+                    })?;
 
-            query_builder
-                .execute(&mut *tx)
-                .await
-                .map_err(|e| Error::InsertionError(e))?;
+            query_builder.execute(&mut *tx).await.insert_err()?;
         }
 
         if !updates.is_empty() {
             let values_clause: String = itertools::Itertools::intersperse(
-                std::iter::repeat("(?, ?)").take(updates.len()),
+                std::iter::repeat_n("(?, ?, ?, ?)", updates.len()),
                 ", ",
             )
             .collect();
 
             let query = format!(
-                "WITH mydata(id, event) AS (VALUES {}) 
+                "WITH mydata(id, event, status, user) AS (VALUES {}) 
                 UPDATE registrations 
-                SET event = mydata.event 
+                SET event = mydata.event, status = mydata.status, user = mydata.user 
                 FROM mydata 
                 WHERE registrations.id = mydata.id",
                 values_clause
             );
 
             let query_builder = sqlx::query(&query);
-            let query_builder = updates.iter().fold(query_builder, |query_builder, (_, r)| {
-                query_builder.bind(&r.id).bind(&r.event_id)
-            });
+            let query_builder =
+                updates
+                    .iter()
+                    .try_fold(query_builder, |query_builder, (_, r)| {
+                        Ok::<_, Error>(
+                            query_builder
+                                .bind(&r.id)
+                                .bind(&r.event_id)
+                                .bind(status_name(r.status)?)
+                                .bind(nullable(&r.user_id)),
+                        )
+                    })?;
 
-            query_builder
-                .execute(&mut *tx)
-                .await
-                .map_err(|e| Error::UpdateError(e))?;
+            query_builder.execute(&mut *tx).await.update_err()?;
         };
 
         let mut outputs = Vec::new();
@@ -366,13 +1256,13 @@ impl Store for SqliteStore {
 
         if !insert_items.is_empty() {
             let values_clause: String = itertools::Itertools::intersperse(
-                std::iter::repeat("(?, ?, ?, ?)").take(insert_items.len()),
+                std::iter::repeat_n("(?, ?, ?, ?, ?)", insert_items.len()),
                 ", ",
             )
             .collect();
 
             let query = format!(
-                "INSERT INTO registration_items(id, registration, schema_item, value) VALUES {}",
+                "INSERT INTO registration_items(id, registration, schema_item, instance, value) VALUES {}",
                 values_clause
             );
 
@@ -388,26 +1278,24 @@ impl Store for SqliteStore {
                     )
                 },
             );
-            query_builder
-                .execute(&mut *tx)
-                .await
-                .map_err(|e| Error::InsertionError(e))?;
+            query_builder.execute(&mut *tx).await.insert_err()?;
         }
 
         if !update_items.is_empty() {
             let values_clause: String = itertools::Itertools::intersperse(
-                std::iter::repeat("(?, ?, ?, ?)").take(update_items.len()),
+                std::iter::repeat_n("(?, ?, ?, ?, ?)", update_items.len()),
                 ", ",
             )
             .collect();
 
             let query = format!(
-                "WITH mydata(id, registration, schema_item, value) 
+                "WITH mydata(id, registration, schema_item, instance, value) 
                 AS (VALUES {})
                 UPDATE registration_items
                 SET
                     registration = mydata.registration,
                     schema_item = mydata.schema_item,
+                    instance = mydata.instance,
                     value = mydata.value
                 FROM mydata
                 WHERE registration_items.id = mydata.id",
@@ -427,10 +1315,7 @@ impl Store for SqliteStore {
                 },
             );
 
-            query_builder
-                .execute(&mut *tx)
-                .await
-                .map_err(|e| Error::UpdateError(e))?;
+            query_builder.execute(&mut *tx).await.update_err()?;
         }
 
         let mut items_by_registration = iter::repeat(Vec::new())
@@ -482,13 +1367,10 @@ impl Store for SqliteStore {
                         })
                 },
             );
-            query_builder
-                .execute(&mut *tx)
-                .await
-                .map_err(|e| Error::DeleteError(e))?;
+            query_builder.execute(&mut *tx).await.delete_err()?;
         }
 
-        tx.commit().await.map_err(|e| Error::TransactionFailed(e))?;
+        tx.commit().await.commit_err()?;
 
         let outputs = outputs
             .into_iter()
@@ -502,69 +1384,48 @@ impl Store for SqliteStore {
         Ok(outputs)
     }
 
-    async fn query(&self, query: Option<&Query>) -> Result<Vec<Registration>, Error> {
-        let registrations = {
-            let base_query_string = "SELECT id, event FROM registrations";
-            let query_string = match query {
-                Some(query) => format!("{} WHERE {}", base_query_string, query.where_clause()),
-                None => base_query_string.to_owned(),
-            };
-
-            let query_builder = sqlx::query_as(&query_string);
-            let query_builder = match query {
-                Some(query) => query.bind(query_builder),
-                None => query_builder,
-            };
-
-            let rows: Vec<RegistrationRow> = query_builder
-                .fetch_all(&*self.pool)
-                .await
-                .map_err(|e| Error::FetchError(e))?;
+    async fn query(&self, query: Option<&Query>) -> StoreResult<Vec<Registration>> {
+        self.query_in(&self.read_pool, query).await
+    }
 
-            rows.into_iter()
-                .map(|row| row.to_registration())
-                .collect::<Vec<_>>()
+    async fn query_page(
+        &self,
+        query: Option<&Query>,
+        page: Pagination,
+    ) -> StoreResult<(Vec<Registration>, u64)> {
+        let hide_drafts = !query.is_some_and(selects_status);
+        let count_query_string = match where_clause(query, hide_drafts) {
+            Some(where_clause) => {
+                format!("SELECT COUNT(*) FROM registrations WHERE {}", where_clause)
+            }
+            None => "SELECT COUNT(*) FROM registrations".to_owned(),
         };
 
-        if registrations.is_empty() {
-            return Ok(registrations);
-        }
-
-        let items = {
-            let where_clause: String = itertools::Itertools::intersperse(
-                std::iter::repeat("registration = ?").take(registrations.len()),
-                " OR ",
-            )
-            .collect();
-
-            let query = format!(
-                "SELECT id, registration, schema_item, value FROM registration_items WHERE {}",
-                where_clause
-            );
-
-            let query_builder = sqlx::query_as(&query);
-            let query_builder = registrations
-                .iter()
-                .fold(query_builder, |query_builder, r| query_builder.bind(&r.id));
+        let count_query_builder = sqlx::query_as(&count_query_string);
+        let count_query_builder = match query {
+            Some(query) => query.bind(count_query_builder),
+            None => count_query_builder,
+        };
 
-            let rows: Vec<RegistrationItemRow> = query_builder
-                .fetch_all(&*self.pool)
-                .await
-                .map_err(|e| Error::FetchError(e))?;
+        let (total_count,): (i64,) = count_query_builder
+            .fetch_one(&*self.read_pool)
+            .await
+            .fetch_err()?;
 
-            rows.into_iter()
-                .map(|row| row.to_registration_item())
-                .collect::<Result<Vec<_>, _>>()?
-        };
+        let registrations = self
+            .fetch_registrations(&self.read_pool, query, Some(page), hide_drafts)
+            .await?;
 
-        let registrations = attach_items(registrations.into_iter(), items.into_iter());
+        let registrations = self
+            .attach_stored_items(&self.read_pool, registrations)
+            .await?;
 
-        Ok(registrations)
+        Ok((registrations, total_count as u64))
     }
 
-    async fn delete(&self, ids: &Vec<String>) -> Result<(), Error> {
-        if ids.is_empty() {
-            return Ok(());
+    async fn delete(&self, ids: &[String]) -> StoreResult<()> {
+        if let Some(result) = nothing_to_do(ids) {
+            return result;
         }
 
         ids_in_table(
@@ -583,28 +1444,481 @@ impl Store for SqliteStore {
         let query_builder = ids
             .iter()
             .fold(query_builder, |query_builder, id| query_builder.bind(id));
-        query_builder
-            .execute(&*self.pool)
-            .await
-            .map_err(|e| Error::DeleteError(e))?;
+        query_builder.execute(&*self.pool).await.delete_err()?;
 
         Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::{str::FromStr, sync::Arc};
+    async fn delete_all_for_event(&self, event_id: &EventId) -> StoreResult<u64> {
+        ids_in_table(&self.pool, "events", iter::once(event_id.as_str())).await?;
 
-    use common::proto::{Registration, RegistrationItem};
+        let mut tx = self.pool.begin().await.begin_err()?;
+
+        sqlx::query(
+            "DELETE FROM registration_items WHERE registration IN (SELECT id FROM registrations WHERE event = ?)",
+        )
+        .bind(event_id)
+        .execute(&mut *tx)
+        .await
+        .delete_err()?;
+
+        let deleted = sqlx::query("DELETE FROM registrations WHERE event = ?")
+            .bind(event_id)
+            .execute(&mut *tx)
+            .await
+            .delete_err()?
+            .rows_affected();
+
+        tx.commit().await.commit_err()?;
+
+        Ok(deleted)
+    }
+
+    async fn incomplete_registrations(&self, event_id: &EventId) -> StoreResult<Vec<Registration>> {
+        ids_in_table(&self.read_pool, "events", iter::once(event_id.as_str())).await?;
+
+        // An empty value is what the registration form submits for an untouched field, so it
+        // doesn't count as filled in. A group is filled in by a value for any of its items, and
+        // each of its repeats that has one must also have the group's required items.
+        let now = now_rfc3339();
+        let ids: Vec<(String,)> = sqlx::query_as(
+            "SELECT id FROM registrations WHERE event = ? AND status != 'DRAFT' AND (EXISTS (
+                SELECT 1 FROM registration_schema_items
+                WHERE registration_schema_items.event = registrations.event
+                    AND (
+                        registration_schema_items.required
+                        OR registration_schema_items.required_after <= ?
+                    )
+                    AND registration_schema_items.item_type != 'SectionType'
+                    AND NOT EXISTS (
+                        SELECT 1 FROM registration_items
+                        JOIN registration_schema_items AS answered_items ON registration_items.schema_item = answered_items.id
+                        WHERE registration_items.registration = registrations.id
+                            AND (
+                                answered_items.id = registration_schema_items.id
+                                OR answered_items.parent = registration_schema_items.id
+                            )
+                            AND registration_items.value != ''
+                    )
+            ) OR EXISTS (
+                SELECT 1 FROM registration_items AS answered
+                JOIN registration_schema_items AS answered_items ON answered.schema_item = answered_items.id
+                JOIN registration_schema_items AS required_items ON required_items.parent = answered_items.parent
+                WHERE answered.registration = registrations.id
+                    AND answered.value != ''
+                    AND (
+                        required_items.required
+                        OR required_items.required_after <= ?
+                    )
+                    AND required_items.item_type != 'SectionType'
+                    AND NOT EXISTS (
+                        SELECT 1 FROM registration_items
+                        WHERE registration_items.registration = registrations.id
+                            AND registration_items.schema_item = required_items.id
+                            AND registration_items.instance = answered.instance
+                            AND registration_items.value != ''
+                    )
+            ))",
+        )
+        .bind(event_id)
+        .bind(&now)
+        .bind(&now)
+        .fetch_all(&*self.read_pool)
+        .await
+        .fetch_err()?;
+
+        let query = Query::any_of(
+            ids.into_iter()
+                .map(|(id,)| Query::Id(IdQuery::Equals(id)))
+                .collect(),
+        );
+
+        self.query_in(&self.read_pool, Some(&query)).await
+    }
+
+    async fn move_to_event(
+        &self,
+        registration_id: &RegistrationId,
+        event_id: &EventId,
+    ) -> StoreResult<Registration> {
+        ids_in_table(
+            &self.pool,
+            "registrations",
+            iter::once(registration_id.as_str()),
+        )
+        .await?;
+        ids_in_table(&self.pool, "events", iter::once(event_id.as_str())).await?;
+
+        let mut tx = self.pool.begin().await.begin_err()?;
+
+        let (status, user): (String, Option<String>) =
+            sqlx::query_as("SELECT status, user FROM registrations WHERE id = ?")
+                .bind(registration_id)
+                .fetch_one(&mut *tx)
+                .await
+                .fetch_err()?;
+
+        let items: Vec<MoveItemRow> = sqlx::query_as(&format!(
+            "SELECT registration_items.id, registration_items.schema_item, registration_items.instance, registration_items.value, registration_schema_items.name, registration_schema_items.item_type, registration_schema_items.parent IS NOT NULL AS grouped
+            FROM {}
+            WHERE registration_items.registration = ?
+            ORDER BY {}",
+            ITEMS_IN_SCHEMA_ORDER_JOIN, ITEMS_IN_SCHEMA_ORDER
+        ))
+        .bind(registration_id)
+        .fetch_all(&mut *tx)
+        .await
+        .fetch_err()?;
+
+        // Items inside a group have no event of their own, so they're found through their group.
+        let target_items: Vec<TargetItemRow> = sqlx::query_as(
+            "SELECT id, name, item_type, parent IS NOT NULL AS grouped FROM registration_schema_items
+            WHERE event = ? OR parent IN (SELECT id FROM registration_schema_items WHERE event = ?)",
+        )
+        .bind(event_id)
+        .bind(event_id)
+        .fetch_all(&mut *tx)
+        .await
+        .fetch_err()?;
+
+        let target_options: Vec<(String, String)> = sqlx::query_as(
+            "SELECT registration_schema_select_options.schema_item, registration_schema_select_options.name
+            FROM registration_schema_select_options JOIN registration_schema_items ON registration_schema_select_options.schema_item = registration_schema_items.id
+            WHERE registration_schema_items.event = ?
+                OR registration_schema_items.parent IN (SELECT id FROM registration_schema_items WHERE event = ?)
+            ORDER BY registration_schema_select_options.idx",
+        )
+        .bind(event_id)
+        .bind(event_id)
+        .fetch_all(&mut *tx)
+        .await
+        .fetch_err()?;
+
+        let source_options: Vec<(String, String)> = sqlx::query_as(
+            "SELECT schema_item, name FROM registration_schema_select_options
+            WHERE schema_item IN (SELECT schema_item FROM registration_items WHERE registration = ?)
+            ORDER BY idx",
+        )
+        .bind(registration_id)
+        .fetch_all(&mut *tx)
+        .await
+        .fetch_err()?;
+
+        let mut options_map = HashMap::new();
+        for (schema_item, name) in target_options.into_iter().chain(source_options) {
+            options_map
+                .entry(schema_item)
+                .or_insert(Vec::new())
+                .push(name);
+        }
+        let options_of = |schema_item: &str| {
+            options_map
+                .get(schema_item)
+                .map(|o: &Vec<String>| o.as_slice())
+                .unwrap_or_default()
+        };
+
+        let moved_items = items
+            .into_iter()
+            .map(|item| {
+                let (target, value) = target_items
+                    .iter()
+                    .find(|t| {
+                        t.name == item.name
+                            && t.item_type == item.item_type
+                            && t.grouped == item.grouped
+                    })
+                    .and_then(|t| {
+                        move_option_value(
+                            &item.item_type,
+                            &item.value,
+                            options_of(&item.schema_item),
+                            options_of(&t.id),
+                        )
+                        .map(|value| (t, value))
+                    })
+                    .ok_or_else(|| Error::IncompatibleSchema(item.name.clone()))?;
+
+                Ok((item.id, target.id.clone(), item.instance, value))
+            })
+            .collect::<StoreResult<Vec<_>>>()?;
+
+        sqlx::query("UPDATE registrations SET event = ? WHERE id = ?")
+            .bind(event_id)
+            .bind(registration_id)
+            .execute(&mut *tx)
+            .await
+            .update_err()?;
+
+        if !moved_items.is_empty() {
+            let values_clause: String = itertools::Itertools::intersperse(
+                iter::repeat_n("(?, ?, ?)", moved_items.len()),
+                ", ",
+            )
+            .collect();
+
+            // Values that pick options by index are rewritten for the target's options.
+            let query = format!(
+                "WITH mydata(id, schema_item, value) AS (VALUES {})
+                UPDATE registration_items
+                SET schema_item = mydata.schema_item, value = mydata.value
+                FROM mydata WHERE registration_items.id = mydata.id",
+                values_clause
+            );
+
+            let query_builder = moved_items.iter().fold(
+                sqlx::query(&query),
+                |query_builder, (id, schema_item, _, value)| {
+                    query_builder.bind(id).bind(schema_item).bind(value)
+                },
+            );
+
+            query_builder.execute(&mut *tx).await.update_err()?;
+        }
+
+        tx.commit().await.commit_err()?;
+
+        Ok(Registration {
+            id: registration_id.to_string(),
+            event_id: event_id.to_string(),
+            items: moved_items
+                .into_iter()
+                .map(|(_, schema_item_id, instance, value)| RegistrationItem {
+                    schema_item_id,
+                    value,
+                    instance,
+                })
+                .collect(),
+            status: registration::Status::from_str_name(&status)
+                .ok_or(Error::ColumnParseError("status"))? as i32,
+            user_id: user.unwrap_or_default(),
+        })
+    }
+
+    async fn set_status_many(
+        &self,
+        ids: &[String],
+        status: registration::Status,
+    ) -> StoreResult<Vec<Registration>> {
+        if let Some(result) = nothing_to_do(ids) {
+            return result;
+        }
+
+        ids_in_table(
+            &self.pool,
+            "registrations",
+            ids.iter().map(|id| id.as_str()),
+        )
+        .await?;
+
+        let mut tx = self.pool.begin().await.begin_err()?;
+
+        let where_clause: String =
+            itertools::Itertools::intersperse(iter::repeat_n("id = ?", ids.len()), " OR ")
+                .collect();
+
+        let query = format!("UPDATE registrations SET status = ? WHERE {}", where_clause);
+        let query_builder = sqlx::query(&query).bind(status.as_str_name());
+        let query_builder = ids
+            .iter()
+            .fold(query_builder, |query_builder, id| query_builder.bind(id));
+        query_builder.execute(&mut *tx).await.update_err()?;
+
+        tx.commit().await.commit_err()?;
+
+        self.query_in(
+            &self.pool,
+            Some(&Query::any_of(
+                ids.iter()
+                    .map(|id| Query::Id(IdQuery::Equals(id.clone())))
+                    .collect(),
+            )),
+        )
+        .await
+    }
+
+    async fn merge_registrations(
+        &self,
+        keep_id: &RegistrationId,
+        drop_ids: &[RegistrationId],
+    ) -> StoreResult<Registration> {
+        ids_in_table(
+            &self.pool,
+            "registrations",
+            iter::once(keep_id.as_str()).chain(drop_ids.iter().map(RegistrationId::as_str)),
+        )
+        .await?;
+
+        let invalid_merge = |drop_id: &RegistrationId| Error::InvalidMerge {
+            keep_id: keep_id.to_string(),
+            drop_id: drop_id.to_string(),
+        };
+
+        if let Some(drop_id) = drop_ids.iter().find(|id| *id == keep_id) {
+            return Err(invalid_merge(drop_id));
+        }
+
+        let mut tx = self.pool.begin().await.begin_err()?;
+
+        let registration_ids: Vec<&str> = iter::once(keep_id.as_str())
+            .chain(drop_ids.iter().map(RegistrationId::as_str))
+            .collect();
+        let in_clause: String =
+            itertools::Itertools::intersperse(iter::repeat_n("?", registration_ids.len()), ", ")
+                .collect();
+
+        // Schema item ids belong to a single event, so values only carry over within one.
+        let query = format!(
+            "SELECT id, event FROM registrations WHERE id IN ({})",
+            in_clause
+        );
+        let events: HashMap<String, String> = registration_ids
+            .iter()
+            .fold(sqlx::query_as(&query), |query_builder, id| {
+                query_builder.bind(*id)
+            })
+            .fetch_all(&mut *tx)
+            .await
+            .fetch_err()?
+            .into_iter()
+            .collect();
+
+        if let Some(drop_id) = drop_ids
+            .iter()
+            .find(|id| events[id.as_str()] != events[keep_id.as_str()])
+        {
+            return Err(invalid_merge(drop_id));
+        }
+
+        let query = format!(
+            "SELECT id, registration, schema_item, instance, value FROM registration_items WHERE registration IN ({})",
+            in_clause
+        );
+        let items: Vec<MergeItemRow> = registration_ids
+            .iter()
+            .fold(sqlx::query_as(&query), |query_builder, id| {
+                query_builder.bind(*id)
+            })
+            .fetch_all(&mut *tx)
+            .await
+            .fetch_err()?;
+
+        // An empty value is an untouched field, so it can be filled from a duplicate.
+        let mut filled: HashSet<(&str, u32)> = items
+            .iter()
+            .filter(|item| item.registration == keep_id.as_str() && !item.value.is_empty())
+            .map(|item| (item.schema_item.as_str(), item.instance))
+            .collect();
+
+        let mut moved = Vec::new();
+        for drop_id in drop_ids {
+            for item in items
+                .iter()
+                .filter(|item| item.registration == drop_id.as_str() && !item.value.is_empty())
+            {
+                if filled.insert((&item.schema_item, item.instance)) {
+                    moved.push(item);
+                }
+            }
+        }
+
+        for item in moved {
+            sqlx::query(
+                "DELETE FROM registration_items WHERE registration = ? AND schema_item = ? AND instance = ?",
+            )
+            .bind(keep_id)
+            .bind(&item.schema_item)
+            .bind(item.instance)
+            .execute(&mut *tx)
+            .await
+            .delete_err()?;
+
+            sqlx::query("UPDATE registration_items SET registration = ? WHERE id = ?")
+                .bind(keep_id)
+                .bind(&item.id)
+                .execute(&mut *tx)
+                .await
+                .update_err()?;
+        }
+
+        let in_clause: String =
+            itertools::Itertools::intersperse(iter::repeat_n("?", drop_ids.len()), ", ").collect();
+        for table_query in [
+            format!(
+                "DELETE FROM registration_items WHERE registration IN ({})",
+                in_clause
+            ),
+            format!("DELETE FROM registrations WHERE id IN ({})", in_clause),
+        ] {
+            drop_ids
+                .iter()
+                .fold(sqlx::query(&table_query), |query_builder, id| {
+                    query_builder.bind(id)
+                })
+                .execute(&mut *tx)
+                .await
+                .delete_err()?;
+        }
+
+        tx.commit().await.commit_err()?;
+
+        self.query_in(
+            &self.pool,
+            Some(&Query::Id(IdQuery::Equals(keep_id.to_string()))),
+        )
+        .await?
+        .pop()
+        .ok_or_else(|| Error::IdDoesNotExist(keep_id.to_string()))
+    }
+
+    async fn find_duplicate(&self, registration: &Registration) -> StoreResult<Option<String>> {
+        match self.dedup_item(&registration.event_id).await? {
+            Some((dedup_item, _)) => self.duplicate_of(registration, &dedup_item).await,
+            None => Ok(None),
+        }
+    }
+
+    async fn find_draft(
+        &self,
+        event_id: &EventId,
+        user_id: &UserId,
+    ) -> StoreResult<Option<String>> {
+        let draft: Option<(String,)> = sqlx::query_as(
+            "SELECT id FROM registrations WHERE event = ? AND user = ? AND status = 'DRAFT' ORDER BY id LIMIT 1",
+        )
+        .bind(event_id.as_str())
+        .bind(user_id.as_str())
+        .fetch_optional(&*self.pool)
+        .await
+        .fetch_err()?;
+
+        Ok(draft.map(|(id,)| id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{iter, str::FromStr, sync::Arc};
+
+    use common::proto::{
+        registration, registration_schema_item_type, GroupType, Registration, RegistrationItem,
+        RegistrationSchema, RegistrationSchemaItem, RegistrationSchemaItemType, TextType,
+    };
     use sqlx::{
         migrate::MigrateDatabase, sqlite::SqliteConnectOptions, ConnectOptions, Sqlite, SqlitePool,
     };
 
-    use super::{attach_items, RegistrationItemRow, RegistrationRow, SqliteStore, Store};
+    use super::{
+        attach_items, copy_registration, Editor, RegistrationItemRow, RegistrationRow, SqliteStore,
+        Store,
+    };
     use crate::store::{
-        common::new_id, registration::Query, CompoundOperator, CompoundQuery, Error, LogicalQuery,
+        common::{format_timestamp, new_id},
+        ids::{EventId, RegistrationId},
+        registration::Query,
+        Compound as _, CompoundOperator, CompoundQuery, Error, LogicalQuery, Pagination,
     };
+    use crate::testing::closed_pool;
     use test_case::test_case;
 
     struct Init {
@@ -712,7 +2026,7 @@ mod tests {
         let query_builder = query_builder
             .bind(&schema_id_3)
             .bind(&id_2)
-            .bind(1)
+            .bind(0)
             .bind("schema 3 name")
             .bind("SelectType")
             .bind::<Option<String>>(None)
@@ -740,7 +2054,7 @@ mod tests {
         let query_builder = query_builder
             .bind(&schema_id_5)
             .bind(&id_1)
-            .bind(1)
+            .bind(2)
             .bind("schema 5 name")
             .bind("CheckboxType")
             .bind::<Option<String>>(None)
@@ -771,7 +2085,7 @@ mod tests {
         let item1_value = "value";
         let item2_id = new_id();
         let item2_schema_item_id = &init.schema_id_2;
-        let item2_value = "yes";
+        let item2_value = "true";
         let registration2_id = new_id();
         let item3_id = new_id();
         let item3_schema_item_id = &init.schema_id_3;
@@ -822,30 +2136,38 @@ mod tests {
             Registration {
                 id: registration1_id,
                 event_id: init.event_1.clone(),
+                status: registration::Status::Confirmed as i32,
                 items: vec![
                     RegistrationItem {
                         schema_item_id: item1_schema_item_id.clone(),
                         value: item1_value.to_owned(),
+                        instance: 0,
                     },
                     RegistrationItem {
                         schema_item_id: item2_schema_item_id.clone(),
                         value: item2_value.to_owned(),
+                        instance: 0,
                     },
                 ],
+                user_id: String::default(),
             },
             Registration {
                 id: registration2_id,
                 event_id: init.event_2.clone(),
+                status: registration::Status::Confirmed as i32,
                 items: vec![
                     RegistrationItem {
                         schema_item_id: item3_schema_item_id.clone(),
                         value: item3_value.to_owned(),
+                        instance: 0,
                     },
                     RegistrationItem {
                         schema_item_id: item4_schema_item_id.clone(),
                         value: item4_value.to_owned(),
+                        instance: 0,
                     },
                 ],
+                user_id: String::default(),
             },
         ];
 
@@ -869,30 +2191,38 @@ mod tests {
             Registration {
                 id: "".to_owned(),
                 event_id: init.event_1,
+                status: registration::Status::Confirmed as i32,
                 items: vec![
                     RegistrationItem {
                         schema_item_id: init.schema_id_1.clone(),
                         value: "value".to_owned(),
+                        instance: 0,
                     },
                     RegistrationItem {
                         schema_item_id: init.schema_id_2.clone(),
-                        value: "yes".to_owned(),
+                        value: "true".to_owned(),
+                        instance: 0,
                     },
                 ],
+                user_id: String::default(),
             },
             Registration {
                 id: "".to_owned(),
                 event_id: init.event_2,
+                status: registration::Status::Confirmed as i32,
                 items: vec![
                     RegistrationItem {
                         schema_item_id: init.schema_id_3.clone(),
                         value: "1".to_owned(),
+                        instance: 0,
                     },
                     RegistrationItem {
                         schema_item_id: init.schema_id_4.clone(),
                         value: "1,2,3".to_owned(),
+                        instance: 0,
                     },
                 ],
+                user_id: String::default(),
             },
         ];
 
@@ -900,7 +2230,10 @@ mod tests {
 
         let store = SqliteStore::new(db.clone());
 
-        let returned_registrations = store.upsert(registrations.clone()).await.unwrap();
+        let returned_registrations = store
+            .upsert(registrations.clone(), Editor::Staff)
+            .await
+            .unwrap();
 
         let registrations = registrations
             .into_iter()
@@ -926,10 +2259,12 @@ mod tests {
                 .unwrap();
 
         let store_registrations = attach_items(
-            store_row.into_iter().map(|row| row.to_registration()),
+            store_row
+                .into_iter()
+                .map(|row| row.into_registration().unwrap()),
             store_item_row
                 .into_iter()
-                .map(|row| row.to_registration_item().unwrap()),
+                .map(|row| row.into_registration_item().unwrap()),
         );
 
         let registrations = sort_registrations(registrations);
@@ -945,14 +2280,18 @@ mod tests {
         registrations[0].items[0].value = "updated value".to_owned();
         registrations[1].items[1] = RegistrationItem {
             schema_item_id: init.schema_id_5.clone(),
-            value: "2".to_owned(),
+            value: "true".to_owned(),
+            instance: 0,
         };
 
         let db = Arc::new(init.db);
 
         let store = SqliteStore::new(db.clone());
 
-        let returned_registrations = store.upsert(registrations.clone()).await.unwrap();
+        let returned_registrations = store
+            .upsert(registrations.clone(), Editor::Staff)
+            .await
+            .unwrap();
 
         assert_eq!(registrations, returned_registrations);
 
@@ -968,10 +2307,12 @@ mod tests {
                 .unwrap();
 
         let store_registrations = attach_items(
-            store_row.into_iter().map(|row| row.to_registration()),
+            store_row
+                .into_iter()
+                .map(|row| row.into_registration().unwrap()),
             store_item_row
                 .into_iter()
-                .map(|row| row.to_registration_item().unwrap()),
+                .map(|row| row.into_registration_item().unwrap()),
         );
 
         let registrations = sort_registrations(registrations);
@@ -1003,7 +2344,9 @@ mod tests {
                     registration: Registration {
                         id,
                         event_id: init.event_1,
+                        status: registration::Status::Confirmed as i32,
                         items: Vec::new(),
+                        user_id: String::default(),
                     },
                 }
             }
@@ -1017,7 +2360,7 @@ mod tests {
         };
 
         let store = SqliteStore::new(Arc::new(init.db));
-        let result = store.upsert(vec![tc.registration]).await;
+        let result = store.upsert(vec![tc.registration], Editor::Staff).await;
         match result {
             Ok(_) => panic!("Expected error"),
             Err(Error::IdDoesNotExist(id)) => assert_eq!(id, tc.id),
@@ -1030,6 +2373,7 @@ mod tests {
         Id,
         EventId,
         CompoundQuery,
+        AnyOf,
         NoResults,
     }
 
@@ -1037,6 +2381,7 @@ mod tests {
     #[test_case(QueryTest::Id ; "id")]
     #[test_case(QueryTest::EventId ; "event id")]
     #[test_case(QueryTest::CompoundQuery ; "compound query")]
+    #[test_case(QueryTest::AnyOf ; "any of")]
     #[test_case(QueryTest::NoResults ; "no results")]
     #[tokio::test]
     async fn query(test_name: QueryTest) {
@@ -1071,6 +2416,15 @@ mod tests {
                 })),
                 expected: registrations,
             },
+            QueryTest::AnyOf => TestCase {
+                query: Some(Query::any_of(
+                    registrations
+                        .iter()
+                        .map(|r| Query::Id(LogicalQuery::Equals(r.id.clone())))
+                        .collect(),
+                )),
+                expected: registrations,
+            },
             QueryTest::NoResults => TestCase {
                 query: Some(Query::Id(LogicalQuery::Equals(new_id()))),
                 expected: Vec::new(),
@@ -1080,12 +2434,157 @@ mod tests {
         let store = SqliteStore::new(Arc::new(init.db));
         let returned_registrations = store.query(tc.query.as_ref()).await.unwrap();
 
-        let returned_registrations = sort_registrations(returned_registrations);
-        let expected = sort_registrations(tc.expected);
+        let mut expected = tc.expected;
+        expected.sort_by(|l, r| l.id.cmp(&r.id));
 
         assert_eq!(expected, returned_registrations);
     }
 
+    #[test_case(&[registration::Status::Confirmed], &[0] ; "confirmed")]
+    #[test_case(&[registration::Status::Waitlisted], &[1] ; "waitlisted")]
+    #[test_case(&[registration::Status::Confirmed, registration::Status::Waitlisted], &[0, 1] ; "confirmed or waitlisted")]
+    #[test_case(&[registration::Status::Cancelled], &[] ; "none cancelled")]
+    #[tokio::test]
+    async fn query_status(statuses: &[registration::Status], expected: &[usize]) {
+        let init = init_db().await;
+        let mut registrations = sort_registrations(test_data(&init).await);
+
+        let store = SqliteStore::new(Arc::new(init.db));
+        store
+            .set_status_many(
+                &[registrations[1].id.clone()],
+                registration::Status::Waitlisted,
+            )
+            .await
+            .unwrap();
+        registrations[1].status = registration::Status::Waitlisted as i32;
+
+        let query = Query::Compound(CompoundQuery {
+            operator: CompoundOperator::Or,
+            queries: statuses
+                .iter()
+                .map(|status| Query::Status(LogicalQuery::Equals(status.as_str_name().to_owned())))
+                .collect(),
+        });
+
+        let returned = store.query(Some(&query)).await.unwrap();
+        assert_eq!(
+            sort_registrations(returned),
+            expected
+                .iter()
+                .map(|idx| registrations[*idx].clone())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    enum QueryPageTest {
+        FirstPage,
+        SecondPage,
+        PastEnd,
+        Filtered,
+    }
+
+    #[test_case(QueryPageTest::FirstPage ; "first page")]
+    #[test_case(QueryPageTest::SecondPage ; "second page")]
+    #[test_case(QueryPageTest::PastEnd ; "past end")]
+    #[test_case(QueryPageTest::Filtered ; "filtered")]
+    #[tokio::test]
+    async fn query_page(test_name: QueryPageTest) {
+        let init = init_db().await;
+        let mut registrations = test_data(&init).await;
+        registrations.sort_by(|l, r| l.id.cmp(&r.id));
+
+        struct TestCase {
+            query: Option<Query>,
+            limit: u32,
+            offset: u32,
+            expected: Vec<Registration>,
+            expected_total: u64,
+        }
+
+        let tc = match test_name {
+            QueryPageTest::FirstPage => TestCase {
+                query: None,
+                limit: 1,
+                offset: 0,
+                expected: vec![registrations[0].clone()],
+                expected_total: 2,
+            },
+            QueryPageTest::SecondPage => TestCase {
+                query: None,
+                limit: 1,
+                offset: 1,
+                expected: vec![registrations[1].clone()],
+                expected_total: 2,
+            },
+            QueryPageTest::PastEnd => TestCase {
+                query: None,
+                limit: 10,
+                offset: 2,
+                expected: Vec::new(),
+                expected_total: 2,
+            },
+            QueryPageTest::Filtered => TestCase {
+                query: Some(Query::EventId(LogicalQuery::Equals(init.event_1.clone()))),
+                limit: 10,
+                offset: 0,
+                expected: registrations
+                    .iter()
+                    .filter(|r| r.event_id == init.event_1)
+                    .cloned()
+                    .collect(),
+                expected_total: 1,
+            },
+        };
+
+        let store = SqliteStore::new(Arc::new(init.db));
+        let (returned_registrations, total) = store
+            .query_page(
+                tc.query.as_ref(),
+                Pagination {
+                    limit: tc.limit,
+                    offset: tc.offset,
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(tc.expected, returned_registrations);
+        assert_eq!(tc.expected_total, total);
+    }
+
+    #[tokio::test]
+    async fn query_page_hides_drafts() {
+        let init = init_db().await;
+        let mut registrations = test_data(&init).await;
+        registrations.sort_by(|l, r| l.id.cmp(&r.id));
+
+        sqlx::query("UPDATE registrations SET status = 'DRAFT' WHERE id = ?")
+            .bind(&registrations[0].id)
+            .execute(&init.db)
+            .await
+            .unwrap();
+        registrations[0].status = registration::Status::Draft as i32;
+
+        let store = SqliteStore::new(Arc::new(init.db));
+        let page = Pagination {
+            limit: 10,
+            offset: 0,
+        };
+
+        let (returned, total) = store.query_page(None, page).await.unwrap();
+        assert_eq!(returned, vec![registrations[1].clone()]);
+        assert_eq!(total, 1);
+
+        // Asking for drafts by status still finds them.
+        let query = Query::Status(LogicalQuery::Equals(
+            registration::Status::Draft.as_str_name().to_owned(),
+        ));
+        let (returned, total) = store.query_page(Some(&query), page).await.unwrap();
+        assert_eq!(returned, vec![registrations[0].clone()]);
+        assert_eq!(total, 1);
+    }
+
     #[tokio::test]
     async fn delete_one() {
         let init = init_db().await;
@@ -1093,10 +2592,7 @@ mod tests {
 
         let db = Arc::new(init.db);
         let store = SqliteStore::new(db.clone());
-        store
-            .delete(&vec![registrations[0].id.clone()])
-            .await
-            .unwrap();
+        store.delete(&[registrations[0].id.clone()]).await.unwrap();
 
         registrations.remove(0);
 
@@ -1112,10 +2608,12 @@ mod tests {
                 .unwrap();
 
         let store_registrations = attach_items(
-            store_row.into_iter().map(|row| row.to_registration()),
+            store_row
+                .into_iter()
+                .map(|row| row.into_registration().unwrap()),
             store_item_row
                 .into_iter()
-                .map(|row| row.to_registration_item().unwrap()),
+                .map(|row| row.into_registration_item().unwrap()),
         );
 
         let registrations = sort_registrations(registrations);
@@ -1123,4 +2621,1135 @@ mod tests {
 
         assert_eq!(registrations, store_registrations);
     }
+
+    #[tokio::test]
+    async fn delete_all_for_event() {
+        let init = init_db().await;
+        let mut registrations = test_data(&init).await;
+        let event_1 = init.event_1.clone();
+
+        let db = Arc::new(init.db);
+        let store = SqliteStore::new(db.clone());
+        assert_eq!(
+            store
+                .delete_all_for_event(&EventId::from(&event_1))
+                .await
+                .unwrap(),
+            1
+        );
+
+        registrations.remove(0);
+        assert_eq!(store.query(None).await.unwrap(), registrations);
+
+        let (item_count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM registration_items")
+            .fetch_one(&*db)
+            .await
+            .unwrap();
+        assert_eq!(item_count as usize, registrations[0].items.len());
+
+        assert_eq!(
+            store
+                .delete_all_for_event(&EventId::from(&event_1))
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn delete_all_for_event_does_not_exist() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let id = new_id();
+        let result = store.delete_all_for_event(&EventId::from(&id)).await;
+        match result {
+            Err(Error::IdDoesNotExist(err_id)) => assert_eq!(err_id, id),
+            _ => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    enum ReadPoolTest {
+        Configured,
+        NotConfigured,
+    }
+
+    #[test_case(ReadPoolTest::Configured ; "configured")]
+    #[test_case(ReadPoolTest::NotConfigured ; "not configured")]
+    #[tokio::test]
+    async fn read_pool(test_name: ReadPoolTest) {
+        let primary = init_db().await;
+        let replica = init_db().await;
+        let primary_registrations = test_data(&primary).await;
+        let replica_registrations = test_data(&replica).await;
+
+        let store = SqliteStore::new(Arc::new(primary.db.clone()));
+        let (store, expected, expected_init) = match test_name {
+            ReadPoolTest::Configured => (
+                store.with_read_pool(Arc::new(replica.db.clone())),
+                replica_registrations,
+                &replica,
+            ),
+            ReadPoolTest::NotConfigured => (store, primary_registrations, &primary),
+        };
+
+        let expected = sort_registrations(expected);
+        assert_eq!(
+            sort_registrations(store.query(None).await.unwrap()),
+            expected
+        );
+        let (page, total_count) = store
+            .query_page(
+                None,
+                Pagination {
+                    limit: 10,
+                    offset: 0,
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(sort_registrations(page), expected);
+        assert_eq!(total_count, expected.len() as u64);
+        store
+            .incomplete_registrations(&EventId::from(&expected_init.event_1))
+            .await
+            .unwrap();
+
+        // Writes always go to the primary.
+        let mut registration = expected[0].clone();
+        registration.id = String::default();
+        registration.event_id = primary.event_1.clone();
+        registration.items = Vec::new();
+        store
+            .upsert(vec![registration], Editor::Staff)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn incomplete_registrations() {
+        let init = init_db().await;
+        let event_1 = init.event_1.clone();
+        let schema_id_1 = init.schema_id_1.clone();
+        let schema_id_2 = init.schema_id_2.clone();
+        let schema_id_5 = init.schema_id_5.clone();
+
+        let db = Arc::new(init.db);
+        let store = SqliteStore::new(db.clone());
+
+        let registration = |items: Vec<(&String, &str)>| Registration {
+            id: "".to_owned(),
+            event_id: event_1.clone(),
+            status: registration::Status::Confirmed as i32,
+            items: items
+                .into_iter()
+                .map(|(schema_item_id, value)| RegistrationItem {
+                    schema_item_id: schema_item_id.clone(),
+                    value: value.to_owned(),
+                    instance: 0,
+                })
+                .collect(),
+            user_id: String::default(),
+        };
+
+        let registrations = store
+            .upsert(
+                vec![
+                    // Complete
+                    registration(vec![
+                        (&schema_id_1, "hello"),
+                        (&schema_id_2, "true"),
+                        (&schema_id_5, "false"),
+                    ]),
+                    // Missing only the optional item 5
+                    registration(vec![(&schema_id_1, "hello"), (&schema_id_2, "true")]),
+                    // Missing the required item 1
+                    registration(vec![(&schema_id_2, "true"), (&schema_id_5, "true")]),
+                    // Required item 1 left empty
+                    registration(vec![(&schema_id_1, ""), (&schema_id_2, "true")]),
+                    // Missing the required item 1, but still a draft
+                    Registration {
+                        status: registration::Status::Draft as i32,
+                        ..registration(vec![(&schema_id_2, "true")])
+                    },
+                ],
+                Editor::Staff,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store
+                .incomplete_registrations(&EventId::from(&event_1))
+                .await
+                .unwrap(),
+            Vec::new()
+        );
+
+        sqlx::query("UPDATE registration_schema_items SET required = TRUE WHERE id = ?")
+            .bind(&schema_id_1)
+            .execute(&*db)
+            .await
+            .unwrap();
+
+        let mut expected = registrations[2..4].to_vec();
+        expected.sort_by(|a, b| a.id.cmp(&b.id));
+        assert_eq!(
+            store
+                .incomplete_registrations(&EventId::from(&event_1))
+                .await
+                .unwrap(),
+            expected
+        );
+    }
+
+    #[derive(Debug)]
+    enum RequiredAfterTest {
+        Before,
+        After,
+        RequiredOverrides,
+    }
+
+    #[test_case(RequiredAfterTest::Before ; "before")]
+    #[test_case(RequiredAfterTest::After ; "after")]
+    #[test_case(RequiredAfterTest::RequiredOverrides ; "required overrides")]
+    #[tokio::test]
+    async fn incomplete_registrations_required_after(test_name: RequiredAfterTest) {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db.clone()));
+
+        // Leaves schema item 5 unset.
+        let registration = registration_in_event_1(&store, &init).await;
+
+        let now = chrono::Utc::now();
+        let day = chrono::Duration::days(1);
+        let (required, required_after, expected) = match test_name {
+            RequiredAfterTest::Before => (false, now + day, Vec::new()),
+            RequiredAfterTest::After => (false, now - day, vec![registration]),
+            RequiredAfterTest::RequiredOverrides => (true, now + day, vec![registration]),
+        };
+
+        sqlx::query(
+            "UPDATE registration_schema_items SET required = ?, required_after = ? WHERE id = ?",
+        )
+        .bind(required)
+        .bind(format_timestamp(&required_after))
+        .bind(&init.schema_id_5)
+        .execute(&init.db)
+        .await
+        .unwrap();
+
+        assert_eq!(
+            store
+                .incomplete_registrations(&EventId::from(&init.event_1))
+                .await
+                .unwrap(),
+            expected
+        );
+    }
+
+    #[tokio::test]
+    async fn incomplete_registrations_does_not_exist() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let id = new_id();
+        let result = store.incomplete_registrations(&EventId::from(&id)).await;
+        match result {
+            Err(Error::IdDoesNotExist(err_id)) => assert_eq!(err_id, id),
+            _ => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    async fn registration_in_event_1(store: &SqliteStore, init: &Init) -> Registration {
+        store
+            .upsert(
+                vec![Registration {
+                    id: "".to_owned(),
+                    event_id: init.event_1.clone(),
+                    status: registration::Status::Confirmed as i32,
+                    items: vec![
+                        RegistrationItem {
+                            schema_item_id: init.schema_id_1.clone(),
+                            value: "hello".to_owned(),
+                            instance: 0,
+                        },
+                        RegistrationItem {
+                            schema_item_id: init.schema_id_2.clone(),
+                            value: "true".to_owned(),
+                            instance: 0,
+                        },
+                    ],
+                    user_id: String::default(),
+                }],
+                Editor::Staff,
+            )
+            .await
+            .unwrap()
+            .remove(0)
+    }
+
+    #[tokio::test]
+    async fn move_to_event() {
+        let init = init_db().await;
+        let target_id_1 = new_id();
+        let target_id_2 = new_id();
+        sqlx::query(
+            "INSERT INTO registration_schema_items(id, event, idx, name, item_type) VALUES (?, ?, ?, ?, ?), (?, ?, ?, ?, ?)",
+        )
+        .bind(&target_id_1)
+        .bind(&init.event_2)
+        .bind(2)
+        .bind("schema 1 name")
+        .bind("TextType")
+        .bind(&target_id_2)
+        .bind(&init.event_2)
+        .bind(3)
+        .bind("schema 2 name")
+        .bind("CheckboxType")
+        .execute(&init.db)
+        .await
+        .unwrap();
+
+        let store = SqliteStore::new(Arc::new(init.db.clone()));
+        let registration = registration_in_event_1(&store, &init).await;
+
+        let moved = store
+            .move_to_event(
+                &RegistrationId::from(&registration.id),
+                &EventId::from(&init.event_2),
+            )
+            .await
+            .unwrap();
+
+        let expected = Registration {
+            id: registration.id.clone(),
+            event_id: init.event_2.clone(),
+            status: registration::Status::Confirmed as i32,
+            items: vec![
+                RegistrationItem {
+                    schema_item_id: target_id_1,
+                    value: "hello".to_owned(),
+                    instance: 0,
+                },
+                RegistrationItem {
+                    schema_item_id: target_id_2,
+                    value: "true".to_owned(),
+                    instance: 0,
+                },
+            ],
+            user_id: String::default(),
+        };
+
+        assert_eq!(moved, expected.clone());
+
+        let stored = store
+            .query(Some(&Query::Id(LogicalQuery::Equals(registration.id))))
+            .await
+            .unwrap();
+        assert_eq!(stored, vec![expected]);
+    }
+
+    #[tokio::test]
+    async fn move_to_event_incompatible() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db.clone()));
+        let registration = registration_in_event_1(&store, &init).await;
+
+        let result = store
+            .move_to_event(
+                &RegistrationId::from(&registration.id),
+                &EventId::from(&init.event_2),
+            )
+            .await;
+        match result {
+            Ok(_) => panic!("no error returned"),
+            Err(Error::IncompatibleSchema(_)) => (),
+            _ => panic!("incorrect error type: {:?}", result),
+        }
+
+        let stored = store
+            .query(Some(&Query::Id(LogicalQuery::Equals(registration.id))))
+            .await
+            .unwrap();
+        assert_eq!(stored[0].event_id, init.event_1);
+    }
+
+    async fn insert_select_item(init: &Init, event: &str, options: &[&str]) -> String {
+        let id = new_id();
+        sqlx::query(
+            "INSERT INTO registration_schema_items(id, event, idx, name, item_type, select_type_default, select_type_display) VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&id)
+        .bind(event)
+        .bind(3)
+        .bind("role")
+        .bind("SelectType")
+        .bind(0)
+        .bind("RADIO")
+        .execute(&init.db)
+        .await
+        .unwrap();
+
+        for (idx, name) in options.iter().enumerate() {
+            sqlx::query(
+                "INSERT INTO registration_schema_select_options(id, schema_item, idx, name, product_id) VALUES (?, ?, ?, ?, ?)",
+            )
+            .bind(new_id())
+            .bind(&id)
+            .bind(idx as i64)
+            .bind(name)
+            .bind("")
+            .execute(&init.db)
+            .await
+            .unwrap();
+        }
+
+        id
+    }
+
+    #[test_case("1", Some("0") ; "index rewritten")]
+    #[test_case("leader", Some("leader") ; "name kept")]
+    #[test_case("", Some("") ; "blank")]
+    #[test_case("2", None ; "index missing from target")]
+    #[test_case("banana", None ; "name missing from target")]
+    #[tokio::test]
+    async fn move_to_event_select_value(value: &str, expected: Option<&str>) {
+        let init = init_db().await;
+        let source =
+            insert_select_item(&init, &init.event_1, &["leader", "follower", "switch"]).await;
+        let target = insert_select_item(&init, &init.event_2, &["follower", "leader"]).await;
+
+        let registration_id = new_id();
+        sqlx::query("INSERT INTO registrations(id, event) VALUES (?, ?)")
+            .bind(&registration_id)
+            .bind(&init.event_1)
+            .execute(&init.db)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO registration_items(id, registration, schema_item, value) VALUES (?, ?, ?, ?)",
+        )
+        .bind(new_id())
+        .bind(&registration_id)
+        .bind(&source)
+        .bind(value)
+        .execute(&init.db)
+        .await
+        .unwrap();
+
+        let store = SqliteStore::new(Arc::new(init.db.clone()));
+        let result = store
+            .move_to_event(
+                &RegistrationId::from(&registration_id),
+                &EventId::from(&init.event_2),
+            )
+            .await;
+
+        match (expected, result) {
+            (Some(expected), Ok(moved)) => {
+                let expected_items = vec![RegistrationItem {
+                    schema_item_id: target,
+                    value: expected.to_owned(),
+                    instance: 0,
+                }];
+                assert_eq!(moved.items, expected_items);
+
+                let stored = store
+                    .query(Some(&Query::Id(LogicalQuery::Equals(registration_id))))
+                    .await
+                    .unwrap();
+                assert_eq!(stored[0].items, expected_items);
+            }
+            (None, Err(Error::IncompatibleSchema(name))) => assert_eq!(name, "role"),
+            (_, result) => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    async fn waitlisted_registrations(store: &SqliteStore, init: &Init) -> Vec<Registration> {
+        store
+            .upsert(
+                iter::repeat_n(
+                    Registration {
+                        id: "".to_owned(),
+                        event_id: init.event_1.clone(),
+                        status: registration::Status::Waitlisted as i32,
+                        items: Vec::new(),
+                        user_id: String::default(),
+                    },
+                    3,
+                )
+                .collect(),
+                Editor::Staff,
+            )
+            .await
+            .unwrap()
+    }
+
+    async fn registration_with_items(
+        store: &SqliteStore,
+        init: &Init,
+        values: &[(&str, &str)],
+    ) -> Registration {
+        store
+            .upsert(
+                vec![Registration {
+                    id: "".to_owned(),
+                    event_id: init.event_1.clone(),
+                    status: registration::Status::Confirmed as i32,
+                    items: values
+                        .iter()
+                        .map(|(schema_item_id, value)| RegistrationItem {
+                            schema_item_id: schema_item_id.to_string(),
+                            value: value.to_string(),
+                            instance: 0,
+                        })
+                        .collect(),
+                    user_id: String::default(),
+                }],
+                Editor::Staff,
+            )
+            .await
+            .unwrap()
+            .remove(0)
+    }
+
+    #[tokio::test]
+    async fn group_instances() {
+        let init = init_db().await;
+        let group_id = new_id();
+        let group_item_id = new_id();
+        sqlx::query(
+            "INSERT INTO registration_schema_items(id, event, idx, name, item_type, required) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&group_id)
+        .bind(&init.event_1)
+        .bind(2)
+        .bind("partners")
+        .bind("GroupType")
+        .bind(true)
+        .execute(&init.db)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO registration_schema_items(id, parent, idx, name, item_type, text_type_default, text_type_display) VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&group_item_id)
+        .bind(&group_id)
+        .bind(0)
+        .bind("partner name")
+        .bind("TextType")
+        .bind("")
+        .bind("SMALL")
+        .execute(&init.db)
+        .await
+        .unwrap();
+
+        let db = Arc::new(init.db);
+        let store = SqliteStore::new(db.clone());
+
+        let item = |value: &str, instance: u32| RegistrationItem {
+            schema_item_id: group_item_id.clone(),
+            value: value.to_owned(),
+            instance,
+        };
+
+        let registration = store
+            .upsert(
+                vec![Registration {
+                    id: "".to_owned(),
+                    event_id: init.event_1.clone(),
+                    status: registration::Status::Confirmed as i32,
+                    items: vec![item("Alex", 0), item("Sam", 1)],
+                    user_id: String::default(),
+                }],
+                Editor::Staff,
+            )
+            .await
+            .unwrap()
+            .remove(0);
+
+        assert_eq!(store.query(None).await.unwrap(), vec![registration.clone()]);
+        assert!(store
+            .incomplete_registrations(&EventId::from(&init.event_1))
+            .await
+            .unwrap()
+            .is_empty());
+
+        // Each instance keeps its own row, so changing one leaves the other alone.
+        let updated = Registration {
+            items: vec![item("Alex", 0), item("Jo", 1)],
+            ..registration
+        };
+        store
+            .upsert(vec![updated.clone()], Editor::Staff)
+            .await
+            .unwrap();
+        assert_eq!(store.query(None).await.unwrap(), vec![updated]);
+
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM registration_items")
+            .fetch_one(&*db)
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+    }
+
+    enum MergeTest {
+        Complementary,
+        Conflicting,
+        EmptyKeptValue,
+        EarlierDropWins,
+    }
+
+    #[test_case(MergeTest::Complementary ; "complementary")]
+    #[test_case(MergeTest::Conflicting ; "conflicting")]
+    #[test_case(MergeTest::EmptyKeptValue ; "empty kept value")]
+    #[test_case(MergeTest::EarlierDropWins ; "earlier drop wins")]
+    #[tokio::test]
+    async fn merge_registrations(test_name: MergeTest) {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db.clone()));
+
+        struct TestCase<'a> {
+            keep: Vec<(&'a str, &'a str)>,
+            drops: Vec<Vec<(&'a str, &'a str)>>,
+            expected: Vec<(&'a str, &'a str)>,
+        }
+
+        let (text, checkbox, other_checkbox) = (
+            init.schema_id_1.as_str(),
+            init.schema_id_2.as_str(),
+            init.schema_id_5.as_str(),
+        );
+
+        let tc = match test_name {
+            MergeTest::Complementary => TestCase {
+                keep: vec![(text, "kept")],
+                drops: vec![vec![(checkbox, "true")], vec![(other_checkbox, "false")]],
+                expected: vec![
+                    (text, "kept"),
+                    (checkbox, "true"),
+                    (other_checkbox, "false"),
+                ],
+            },
+            MergeTest::Conflicting => TestCase {
+                keep: vec![(text, "kept"), (checkbox, "true")],
+                drops: vec![vec![(text, "dropped"), (checkbox, "false")]],
+                expected: vec![(text, "kept"), (checkbox, "true")],
+            },
+            MergeTest::EmptyKeptValue => TestCase {
+                keep: vec![(text, ""), (checkbox, "true")],
+                drops: vec![vec![(text, "dropped")]],
+                expected: vec![(text, "dropped"), (checkbox, "true")],
+            },
+            MergeTest::EarlierDropWins => TestCase {
+                keep: vec![],
+                drops: vec![vec![(text, "first")], vec![(text, "second")]],
+                expected: vec![(text, "first")],
+            },
+        };
+
+        let keep = registration_with_items(&store, &init, &tc.keep).await;
+        let mut drop_ids = Vec::new();
+        for values in tc.drops.iter() {
+            drop_ids.push(RegistrationId::from(
+                registration_with_items(&store, &init, values).await.id,
+            ));
+        }
+
+        let merged = store
+            .merge_registrations(&RegistrationId::from(&keep.id), &drop_ids)
+            .await
+            .unwrap();
+
+        let expected = Registration {
+            items: tc
+                .expected
+                .iter()
+                .map(|(schema_item_id, value)| RegistrationItem {
+                    schema_item_id: schema_item_id.to_string(),
+                    value: value.to_string(),
+                    instance: 0,
+                })
+                .collect(),
+            ..keep.clone()
+        };
+        assert_eq!(
+            sort_registrations(vec![merged]),
+            sort_registrations(vec![expected.clone()])
+        );
+
+        let stored = store.query(None).await.unwrap();
+        assert_eq!(
+            sort_registrations(stored),
+            sort_registrations(vec![expected])
+        );
+
+        let (leftover_items,): (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM registration_items WHERE registration != ?")
+                .bind(&keep.id)
+                .fetch_one(&init.db)
+                .await
+                .unwrap();
+        assert_eq!(leftover_items, 0);
+    }
+
+    #[tokio::test]
+    async fn merge_registrations_invalid() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db.clone()));
+        let keep = registration_with_items(&store, &init, &[]).await;
+        let other_event = store
+            .upsert(
+                vec![Registration {
+                    id: "".to_owned(),
+                    event_id: init.event_2.clone(),
+                    status: registration::Status::Confirmed as i32,
+                    items: Vec::new(),
+                    user_id: String::default(),
+                }],
+                Editor::Staff,
+            )
+            .await
+            .unwrap()
+            .remove(0);
+
+        for drop_id in [keep.id.clone(), other_event.id.clone()] {
+            let result = store
+                .merge_registrations(
+                    &RegistrationId::from(&keep.id),
+                    &[RegistrationId::from(drop_id)],
+                )
+                .await;
+            match result {
+                Err(Error::InvalidMerge { .. }) => (),
+                _ => panic!("incorrect result: {:?}", result),
+            }
+        }
+
+        let result = store
+            .merge_registrations(
+                &RegistrationId::from(&keep.id),
+                &[RegistrationId::from(new_id())],
+            )
+            .await;
+        match result {
+            Err(Error::IdDoesNotExist(_)) => (),
+            _ => panic!("incorrect result: {:?}", result),
+        }
+
+        assert_eq!(store.query(None).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn set_status_many() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db.clone()));
+        let registrations = waitlisted_registrations(&store, &init).await;
+        let ids = registrations
+            .iter()
+            .map(|r| r.id.clone())
+            .collect::<Vec<_>>();
+
+        let updated = store
+            .set_status_many(&ids, registration::Status::Confirmed)
+            .await
+            .unwrap();
+
+        let expected = registrations
+            .into_iter()
+            .map(|mut r| {
+                r.status = registration::Status::Confirmed as i32;
+                r
+            })
+            .collect::<Vec<_>>();
+
+        let expected = sort_registrations(expected);
+        assert_eq!(updated, expected);
+
+        let stored = store.query(None).await.unwrap();
+        assert_eq!(stored, expected);
+    }
+
+    #[tokio::test]
+    async fn set_status_many_missing_id() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db.clone()));
+        let registrations = waitlisted_registrations(&store, &init).await;
+        let missing_id = new_id();
+        let ids = registrations
+            .iter()
+            .map(|r| r.id.clone())
+            .chain(iter::once(missing_id.clone()))
+            .collect::<Vec<_>>();
+
+        let result = store
+            .set_status_many(&ids, registration::Status::Confirmed)
+            .await;
+        match result {
+            Ok(_) => panic!("no error returned"),
+            Err(Error::IdDoesNotExist(id)) => assert_eq!(id, missing_id),
+            _ => panic!("incorrect error type: {:?}", result),
+        }
+
+        let stored = store.query(None).await.unwrap();
+        assert_eq!(stored, sort_registrations(registrations));
+    }
+
+    enum DeadlineTest {
+        Open,
+        Unbounded,
+        NotYetOpenRegistrant,
+        NotYetOpenStaff,
+        ClosedRegistrant,
+        ClosedStaff,
+    }
+
+    #[test_case(DeadlineTest::Open ; "between open and deadline")]
+    #[test_case(DeadlineTest::Unbounded ; "no open or deadline")]
+    #[test_case(DeadlineTest::NotYetOpenRegistrant ; "before open registrant")]
+    #[test_case(DeadlineTest::NotYetOpenStaff ; "before open staff")]
+    #[test_case(DeadlineTest::ClosedRegistrant ; "after deadline registrant")]
+    #[test_case(DeadlineTest::ClosedStaff ; "after deadline staff")]
+    #[tokio::test]
+    async fn registration_deadline(test_name: DeadlineTest) {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db.clone()));
+
+        let past = Some("2000-01-01T00:00:00Z");
+        let future = Some("2999-01-01T00:00:00Z");
+        let (opens_at, closes_at, editor) = match test_name {
+            DeadlineTest::Open => (past, future, Editor::Registrant),
+            DeadlineTest::Unbounded => (None, None, Editor::Registrant),
+            DeadlineTest::NotYetOpenRegistrant => (future, None, Editor::Registrant),
+            DeadlineTest::NotYetOpenStaff => (future, None, Editor::Staff),
+            DeadlineTest::ClosedRegistrant => (None, past, Editor::Registrant),
+            DeadlineTest::ClosedStaff => (None, past, Editor::Staff),
+        };
+
+        sqlx::query(
+            "UPDATE events SET registration_opens_at = ?, registration_closes_at = ? WHERE id = ?",
+        )
+        .bind(opens_at)
+        .bind(closes_at)
+        .bind(&init.event_1)
+        .execute(&init.db)
+        .await
+        .unwrap();
+
+        let result = store
+            .upsert(
+                vec![Registration {
+                    id: "".to_owned(),
+                    event_id: init.event_1.clone(),
+                    status: registration::Status::Confirmed as i32,
+                    items: Vec::new(),
+                    user_id: String::default(),
+                }],
+                editor,
+            )
+            .await;
+
+        match (test_name, result) {
+            (DeadlineTest::NotYetOpenRegistrant, Err(Error::RegistrationNotOpen(event_id))) => {
+                assert_eq!(event_id, init.event_1)
+            }
+            (DeadlineTest::NotYetOpenRegistrant, result) => {
+                panic!("expected RegistrationNotOpen, got {:?}", result)
+            }
+            (DeadlineTest::ClosedRegistrant, Err(Error::RegistrationClosed(event_id))) => {
+                assert_eq!(event_id, init.event_1)
+            }
+            (DeadlineTest::ClosedRegistrant, result) => {
+                panic!("expected RegistrationClosed, got {:?}", result)
+            }
+            (_, result) => assert_eq!(result.unwrap().len(), 1),
+        }
+    }
+
+    enum AccountTest {
+        OpenAnonymous,
+        RequiredAnonymous,
+        RequiredSignedIn,
+    }
+
+    #[test_case(AccountTest::OpenAnonymous ; "open event anonymous")]
+    #[test_case(AccountTest::RequiredAnonymous ; "require account anonymous")]
+    #[test_case(AccountTest::RequiredSignedIn ; "require account signed in")]
+    #[tokio::test]
+    async fn require_account(test_name: AccountTest) {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db.clone()));
+
+        let user_id = new_id();
+        sqlx::query("INSERT INTO users(id, email, password, display_name) VALUES (?, ?, ?, ?)")
+            .bind(&user_id)
+            .bind("user@example.com")
+            .bind("")
+            .bind("User")
+            .execute(&init.db)
+            .await
+            .unwrap();
+
+        let (require_account, user_id) = match test_name {
+            AccountTest::OpenAnonymous => (false, String::default()),
+            AccountTest::RequiredAnonymous => (true, String::default()),
+            AccountTest::RequiredSignedIn => (true, user_id),
+        };
+
+        sqlx::query("UPDATE events SET require_account = ? WHERE id = ?")
+            .bind(require_account)
+            .bind(&init.event_1)
+            .execute(&init.db)
+            .await
+            .unwrap();
+
+        let result = store
+            .upsert(
+                vec![Registration {
+                    id: "".to_owned(),
+                    event_id: init.event_1.clone(),
+                    status: registration::Status::Confirmed as i32,
+                    items: Vec::new(),
+                    user_id: user_id.clone(),
+                }],
+                Editor::Registrant,
+            )
+            .await;
+
+        match (test_name, result) {
+            (AccountTest::RequiredAnonymous, Err(Error::AccountRequired(event_id))) => {
+                assert_eq!(event_id, init.event_1)
+            }
+            (AccountTest::RequiredAnonymous, result) => {
+                panic!("expected AccountRequired, got {:?}", result)
+            }
+            (_, result) => {
+                let registrations = result.unwrap();
+                assert_eq!(registrations[0].user_id, user_id);
+
+                let stored = store.query(None).await.unwrap();
+                assert_eq!(stored, registrations);
+            }
+        }
+    }
+
+    #[test_case(10, false ; "at limit")]
+    #[test_case(11, true ; "over limit")]
+    #[tokio::test]
+    async fn max_value_length(length: usize, expect_error: bool) {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db)).with_max_value_length(10);
+
+        // Length is counted in characters, not bytes.
+        let value = "é".repeat(length);
+        let result = store
+            .upsert(
+                vec![Registration {
+                    id: "".to_owned(),
+                    event_id: init.event_1.clone(),
+                    status: registration::Status::Confirmed as i32,
+                    items: vec![RegistrationItem {
+                        schema_item_id: init.schema_id_1.clone(),
+                        value: value.clone(),
+                        instance: 0,
+                    }],
+                    user_id: String::default(),
+                }],
+                Editor::Staff,
+            )
+            .await;
+
+        match (expect_error, result) {
+            (false, Ok(registrations)) => assert_eq!(registrations[0].items[0].value, value),
+            (true, Err(Error::ValueTooLong { item_id, max })) => {
+                assert_eq!(item_id, init.schema_id_1);
+                assert_eq!(max, 10);
+                assert!(store.query(None).await.unwrap().is_empty());
+            }
+            (_, result) => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[tokio::test]
+    async fn unknown_status() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let result = store
+            .upsert(
+                vec![Registration {
+                    id: "".to_owned(),
+                    event_id: init.event_1.clone(),
+                    status: 99,
+                    items: Vec::new(),
+                    user_id: String::default(),
+                }],
+                Editor::Staff,
+            )
+            .await;
+
+        match result {
+            Err(Error::InvalidStatus(99)) => (),
+            _ => panic!("unexpected result: {:?}", result),
+        }
+        assert!(store.query(None).await.unwrap().is_empty());
+    }
+
+    #[test_case("true", Some("true") ; "true lowercase")]
+    #[test_case("TRUE", Some("true") ; "true uppercase")]
+    #[test_case("1", Some("true") ; "one")]
+    #[test_case("yes", Some("true") ; "yes")]
+    #[test_case("Yes", Some("true") ; "yes mixed case")]
+    #[test_case("false", Some("false") ; "false lowercase")]
+    #[test_case("False", Some("false") ; "false mixed case")]
+    #[test_case("0", Some("false") ; "zero")]
+    #[test_case("no", Some("false") ; "no")]
+    #[test_case("NO", Some("false") ; "no uppercase")]
+    #[test_case(" yes ", Some("true") ; "padded")]
+    #[test_case("", Some("") ; "empty")]
+    #[test_case("maybe", None ; "rejected")]
+    #[tokio::test]
+    async fn checkbox_value(value: &str, expected: Option<&str>) {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let result = store
+            .upsert(
+                vec![Registration {
+                    id: "".to_owned(),
+                    event_id: init.event_1.clone(),
+                    status: registration::Status::Confirmed as i32,
+                    items: vec![
+                        RegistrationItem {
+                            schema_item_id: init.schema_id_1.clone(),
+                            value: "yes".to_owned(),
+                            instance: 0,
+                        },
+                        RegistrationItem {
+                            schema_item_id: init.schema_id_2.clone(),
+                            value: value.to_owned(),
+                            instance: 0,
+                        },
+                    ],
+                    user_id: String::default(),
+                }],
+                Editor::Staff,
+            )
+            .await;
+
+        match (expected, result) {
+            (Some(expected), Ok(registrations)) => {
+                // Only checkbox items are rewritten.
+                assert_eq!(registrations[0].items[0].value, "yes");
+                assert_eq!(registrations[0].items[1].value, expected);
+
+                let stored = store.query(None).await.unwrap();
+                assert_eq!(stored, registrations);
+            }
+            (None, Err(Error::InvalidRegistrationValue { item_id, value: v })) => {
+                assert_eq!(item_id, init.schema_id_2);
+                assert_eq!(v, value);
+                assert!(store.query(None).await.unwrap().is_empty());
+            }
+            (_, result) => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[tokio::test]
+    async fn empty_input() {
+        let store = SqliteStore::new(closed_pool().await);
+
+        assert!(store
+            .upsert(Vec::new(), Editor::Staff)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(store
+            .set_status_many(&[], registration::Status::Cancelled)
+            .await
+            .unwrap()
+            .is_empty());
+        store.delete(&Vec::new()).await.unwrap();
+    }
+
+    fn copy_item(
+        id: &str,
+        blank_on_copy: bool,
+        group: Option<Vec<RegistrationSchemaItem>>,
+    ) -> RegistrationSchemaItem {
+        let typ = match group {
+            Some(items) => registration_schema_item_type::Type::Group(GroupType { items }),
+            None => registration_schema_item_type::Type::Text(TextType::default()),
+        };
+
+        RegistrationSchemaItem {
+            id: id.to_owned(),
+            r#type: Some(RegistrationSchemaItemType { r#type: Some(typ) }),
+            blank_on_copy,
+            ..Default::default()
+        }
+    }
+
+    fn copy_value(schema_item_id: &str, instance: u32) -> RegistrationItem {
+        RegistrationItem {
+            schema_item_id: schema_item_id.to_owned(),
+            value: format!("{} {}", schema_item_id, instance),
+            instance,
+        }
+    }
+
+    #[test]
+    fn copy() {
+        let schema = RegistrationSchema {
+            event_id: "event".to_owned(),
+            items: vec![
+                copy_item("name", false, None),
+                copy_item("email", true, None),
+                copy_item(
+                    "partners",
+                    false,
+                    Some(vec![
+                        copy_item("partner_name", false, None),
+                        copy_item("partner_email", true, None),
+                    ]),
+                ),
+                copy_item(
+                    "emergency_contacts",
+                    true,
+                    Some(vec![copy_item("contact_name", false, None)]),
+                ),
+            ],
+        };
+
+        let source = Registration {
+            id: "source".to_owned(),
+            event_id: "event".to_owned(),
+            items: vec![
+                copy_value("name", 0),
+                copy_value("email", 0),
+                copy_value("partner_name", 0),
+                copy_value("partner_email", 0),
+                copy_value("partner_name", 1),
+                copy_value("partner_email", 1),
+                copy_value("contact_name", 0),
+            ],
+            status: registration::Status::Waitlisted as i32,
+            user_id: "user".to_owned(),
+        };
+
+        let copy = copy_registration(&source, &schema);
+        assert_eq!(
+            copy,
+            Registration {
+                event_id: "event".to_owned(),
+                items: vec![
+                    copy_value("name", 0),
+                    copy_value("partner_name", 0),
+                    copy_value("partner_name", 1),
+                ],
+                ..Default::default()
+            }
+        );
+    }
 }
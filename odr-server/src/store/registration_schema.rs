@@ -1,21 +1,77 @@
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, HashMap, HashSet},
     sync::Arc,
 };
 
-use sqlx::SqlitePool;
+use sqlx::{SqliteConnection, SqlitePool};
 
 use common::proto::{
-    multi_select_type, registration_schema_item_type::Type as ItemType, select_type, text_type,
-    CheckboxType, MultiSelectType, RegistrationSchema, RegistrationSchemaItem,
-    RegistrationSchemaItemType, SelectOption, SelectType, TextType,
+    item_header, multi_select_type, registration_schema_item_type::Type as ItemType, select_type,
+    text_type, CheckboxType, GroupType, ItemHeader, MultiSelectType, RegistrationSchema,
+    RegistrationSchemaItem, RegistrationSchemaItemType, SectionType, SelectOption, SelectType,
+    TagsType, TextType,
 };
 
 use super::{
-    common::{ids_in_table, new_id},
-    Bindable as _, Error, Queryable as _,
+    append_pagination,
+    common::{
+        format_timestamp, ids_in_table, new_id, normalize, nothing_to_do, now_rfc3339,
+        parse_timestamp, Normalization,
+    },
+    event::nullable,
+    ids::{EventId, SchemaItemId, UserId},
+    Bindable as _, Capped, Error, IntoStoreError as _, Outcome, Pagination, Queryable as _,
+    StoreResult,
 };
 
+/// How to read a stored display enum this version doesn't recognize, such as one written by a
+/// newer server.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EnumPolicy {
+    /// Fail the read with `Error::UnknownEnumValue`.
+    #[default]
+    Strict,
+    /// Log a warning and fall back to the default display.
+    Lenient,
+}
+
+/// One change to an item's name, type or options. Types are stored type names such as
+/// "TextType", and options are the names of the unarchived options, comma separated.
+#[derive(Clone, Debug, PartialEq, Eq, sqlx::FromRow)]
+pub struct ItemHistoryEntry {
+    pub changed_at: String,
+    /// The user who made the change, if it was made on someone's behalf.
+    pub actor: Option<String>,
+    pub old_name: String,
+    pub new_name: String,
+    pub old_type: String,
+    pub new_type: String,
+    pub old_options: String,
+    pub new_options: String,
+}
+
+fn parse_display<T: Default>(
+    column: &'static str,
+    value: Option<String>,
+    from_str_name: fn(&str) -> Option<T>,
+    policy: EnumPolicy,
+) -> StoreResult<T> {
+    let value = value.ok_or(Error::ColumnParseError(column))?;
+
+    match (from_str_name(&value), policy) {
+        (Some(display), _) => Ok(display),
+        (None, EnumPolicy::Strict) => Err(Error::UnknownEnumValue { column, value }),
+        (None, EnumPolicy::Lenient) => {
+            log::warn!(
+                "unknown value {:?} stored in column {}, using the default",
+                value,
+                column
+            );
+            Ok(T::default())
+        }
+    }
+}
+
 #[derive(sqlx::FromRow)]
 struct OptionRow {
     id: String,
@@ -23,10 +79,11 @@ struct OptionRow {
     idx: i32,
     name: String,
     product_id: String,
+    archived: bool,
 }
 
 impl OptionRow {
-    fn to_option(self) -> Result<(String, usize, SelectOption), Error> {
+    fn into_option(self) -> StoreResult<(String, usize, SelectOption)> {
         Ok((
             self.schema_item,
             usize::try_from(self.idx).map_err(|_| Error::ColumnParseError("idx"))?,
@@ -34,6 +91,7 @@ impl OptionRow {
                 id: self.id,
                 name: self.name,
                 product_id: self.product_id,
+                archived: self.archived,
             },
         ))
     }
@@ -42,33 +100,90 @@ impl OptionRow {
 #[derive(sqlx::FromRow)]
 struct ItemRow {
     id: String,
-    event: String,
+    event: Option<String>,
+    parent: Option<String>,
     idx: i32,
     name: String,
     item_type: String,
+    required: bool,
+    required_after: Option<String>,
+    blank_on_copy: bool,
+    display_idx: Option<u32>,
     text_type_default: Option<String>,
     text_type_display: Option<String>,
+    text_type_placeholder: Option<String>,
+    text_type_prefix: Option<String>,
+    text_type_suffix: Option<String>,
     checkbox_type_default: Option<i32>,
     select_type_default: Option<i32>,
     select_type_display: Option<String>,
     multi_select_type_defaults: Option<String>,
     multi_select_type_display: Option<String>,
+    section_type_body: Option<String>,
+    tags_type_max_tags: Option<u32>,
+}
+
+#[derive(sqlx::FromRow)]
+struct ItemHeaderRow {
+    id: String,
+    event: String,
+    parent: Option<String>,
+    idx: i32,
+    name: String,
+    item_type: String,
+}
+
+// Items inside a group have no event of their own, so headers take it from the group.
+const HEADER_COLUMNS: &str = "id,
+    COALESCE(event, (SELECT groups.event FROM registration_schema_items AS groups WHERE groups.id = registration_schema_items.parent)) AS event,
+    parent,
+    idx,
+    name,
+    item_type";
+
+impl ItemHeaderRow {
+    fn into_header(self) -> StoreResult<ItemHeader> {
+        let type_kind = match self.item_type.as_str() {
+            "TextType" => item_header::TypeKind::Text,
+            "CheckboxType" => item_header::TypeKind::Checkbox,
+            "SelectType" => item_header::TypeKind::Select,
+            "MultiSelectType" => item_header::TypeKind::MultiSelect,
+            "SectionType" => item_header::TypeKind::Section,
+            "GroupType" => item_header::TypeKind::Group,
+            "TagsType" => item_header::TypeKind::Tags,
+            _ => return Err(Error::ColumnParseError("item_type")),
+        };
+
+        Ok(ItemHeader {
+            id: self.id,
+            name: self.name,
+            type_kind: type_kind as i32,
+            idx: u32::try_from(self.idx).map_err(|_| Error::ColumnParseError("idx"))?,
+            event_id: self.event,
+        })
+    }
 }
 
 impl ItemRow {
-    fn to_item(self) -> Result<(String, usize, RegistrationSchemaItem), Error> {
+    fn into_item(
+        self,
+        enum_policy: EnumPolicy,
+    ) -> StoreResult<(String, usize, RegistrationSchemaItem)> {
         let typ = match self.item_type.as_str() {
             "TextType" => Some(ItemType::Text(TextType {
                 default: self
                     .text_type_default
                     .ok_or(Error::ColumnParseError("text_type_default"))?,
-                display: text_type::Display::from_str_name(
-                    &self
-                        .text_type_display
-                        .ok_or(Error::ColumnParseError("text_type_display"))?,
-                )
-                .ok_or(Error::ColumnParseError("text_type_display"))?
-                    as i32,
+                display: parse_display(
+                    "text_type_display",
+                    self.text_type_display,
+                    text_type::Display::from_str_name,
+                    enum_policy,
+                )? as i32,
+                // Items saved before adornments existed have none.
+                placeholder: self.text_type_placeholder.unwrap_or_default(),
+                prefix: self.text_type_prefix.unwrap_or_default(),
+                suffix: self.text_type_suffix.unwrap_or_default(),
             })),
             "CheckboxType" => Some(ItemType::Checkbox(CheckboxType {
                 default: self
@@ -82,61 +197,100 @@ impl ItemRow {
                         .ok_or(Error::ColumnParseError("select_type_default"))?,
                 )
                 .map_err(|_| Error::ColumnParseError("select_type_default"))?,
-                display: select_type::Display::from_str_name(
-                    &self
-                        .select_type_display
-                        .ok_or(Error::ColumnParseError("select_type_display"))?,
-                )
-                .ok_or(Error::ColumnParseError("select_type_display"))?
-                    as i32,
+                display: parse_display(
+                    "select_type_display",
+                    self.select_type_display,
+                    select_type::Display::from_str_name,
+                    enum_policy,
+                )? as i32,
                 options: Vec::new(),
             })),
             "MultiSelectType" => {
-                let defaults = self
+                let mut defaults = self
                     .multi_select_type_defaults
                     .ok_or(Error::ColumnParseError("multi_select_type_defaults"))?
                     .split(',')
+                    .filter(|s| !s.is_empty())
                     .map(|s| {
                         s.parse::<u32>()
                             .map_err(|_| Error::ColumnParseError("multi_select_type_defaults"))
                     })
                     .collect::<Result<Vec<_>, _>>()?;
 
+                // Rows written before defaults were normalized may still be out of order.
+                defaults.sort_unstable();
+                defaults.dedup();
+
                 Some(ItemType::MultiSelect(MultiSelectType {
                     defaults,
-                    display: multi_select_type::Display::from_str_name(
-                        &self
-                            .multi_select_type_display
-                            .ok_or(Error::ColumnParseError("multi_select_type_defaults"))?,
-                    )
-                    .ok_or(Error::ColumnParseError("multi_select_type_defaults"))?
-                        as i32,
+                    display: parse_display(
+                        "multi_select_type_display",
+                        self.multi_select_type_display,
+                        multi_select_type::Display::from_str_name,
+                        enum_policy,
+                    )? as i32,
                     options: Vec::new(),
                 }))
             }
+            "SectionType" => Some(ItemType::Section(SectionType {
+                body: self
+                    .section_type_body
+                    .ok_or(Error::ColumnParseError("section_type_body"))?,
+            })),
+            // A group's items are stored as rows of their own and attached by build_items_map.
+            "GroupType" => Some(ItemType::Group(GroupType { items: Vec::new() })),
+            "TagsType" => Some(ItemType::Tags(TagsType {
+                max_tags: self
+                    .tags_type_max_tags
+                    .ok_or(Error::ColumnParseError("tags_type_max_tags"))?,
+            })),
             _ => None,
         };
 
         Ok((
-            self.event,
+            self.parent
+                .or(self.event)
+                .ok_or(Error::ColumnParseError("event"))?,
             usize::try_from(self.idx).map_err(|_| Error::ColumnParseError("idx"))?,
             RegistrationSchemaItem {
                 id: self.id,
                 name: self.name,
                 r#type: Some(RegistrationSchemaItemType { r#type: typ }),
+                required: self.required,
+                required_after: self.required_after.unwrap_or_default(),
+                blank_on_copy: self.blank_on_copy,
+                display_idx: self.display_idx,
             },
         ))
     }
 }
 
+pub const DEFAULT_MAX_OPTIONS: usize = 200;
+
 #[derive(Debug)]
 pub struct SqliteStore {
     pool: Arc<SqlitePool>,
+    max_options: usize,
+    enum_policy: EnumPolicy,
 }
 
 impl SqliteStore {
     pub fn new(pool: Arc<SqlitePool>) -> Self {
-        SqliteStore { pool }
+        SqliteStore {
+            pool,
+            max_options: DEFAULT_MAX_OPTIONS,
+            enum_policy: EnumPolicy::default(),
+        }
+    }
+
+    pub fn with_max_options(mut self, max_options: usize) -> Self {
+        self.max_options = max_options;
+        self
+    }
+
+    pub fn with_enum_policy(mut self, enum_policy: EnumPolicy) -> Self {
+        self.enum_policy = enum_policy;
+        self
     }
 }
 
@@ -146,14 +300,86 @@ type QueryBuilder<'q> = sqlx::query::Query<
     <sqlx::Sqlite as sqlx::database::HasArguments<'q>>::Arguments,
 >;
 
+/// Every change to an existing item's name, type or options is added to the item's history. The
+/// methods that take an actor credit the change to them; `upsert` records it without one.
 #[tonic::async_trait]
 pub trait Store: Send + Sync + 'static {
     async fn upsert(
         &self,
         schemas: Vec<RegistrationSchema>,
-    ) -> Result<Vec<RegistrationSchema>, Error>;
-    async fn query(&self, query: Option<&Query>) -> Result<Vec<RegistrationSchema>, Error>;
-    async fn delete(&self, ids: &Vec<String>) -> Result<(), Error>;
+    ) -> StoreResult<Vec<RegistrationSchema>>;
+    /// Upserts like `upsert`, also returning an outcome for every returned item, group items
+    /// included, in the order `with_group_items` walks them.
+    async fn upsert_with_outcomes(
+        &self,
+        schemas: Vec<RegistrationSchema>,
+        actor: Option<&UserId>,
+    ) -> StoreResult<(Vec<RegistrationSchema>, Vec<Outcome>)>;
+    /// Upserts like `upsert`, but returns only the items that were inserted or whose stored
+    /// contents or position changed. Schemas with no such items are left out.
+    async fn upsert_changed(
+        &self,
+        schemas: Vec<RegistrationSchema>,
+        actor: Option<&UserId>,
+    ) -> StoreResult<Vec<RegistrationSchema>>;
+    /// Schemas are returned in event id order, with their items in index order.
+    async fn query(&self, query: Option<&Query>) -> StoreResult<Vec<RegistrationSchema>>;
+    /// Like query, but returns at most the first `cap` schemas.
+    async fn query_capped(
+        &self,
+        query: Option<&Query>,
+        cap: u32,
+    ) -> StoreResult<Capped<RegistrationSchema>>;
+    async fn delete(&self, ids: &[String]) -> StoreResult<()>;
+    async fn list_item_headers(&self, event_id: &EventId) -> StoreResult<Vec<ItemHeader>>;
+    /// Adds new items after the event's stored ones, all in one transaction, without touching the
+    /// rest of its schema, so an addition can't undo a concurrent edit or reorder. The items are
+    /// returned in order with their new ids.
+    async fn add_items(
+        &self,
+        event_id: &EventId,
+        items: Vec<RegistrationSchemaItem>,
+        actor: Option<&UserId>,
+    ) -> StoreResult<Vec<RegistrationSchemaItem>>;
+    /// Changes the contents of existing items without touching their position or the rest of
+    /// their schema, so an edit can't undo a concurrent reorder. The edited items are returned
+    /// grouped into a schema per event. Items inside a group are edited through their group.
+    async fn update_items(
+        &self,
+        items: Vec<RegistrationSchemaItem>,
+        actor: Option<&UserId>,
+    ) -> StoreResult<Vec<RegistrationSchema>>;
+    /// Moves the event's items into the given order without touching their contents. Ids that are
+    /// no longer in the schema are skipped, and items that aren't listed keep their relative order
+    /// after the listed ones.
+    async fn reorder_items(
+        &self,
+        event_id: &EventId,
+        item_ids: &[SchemaItemId],
+    ) -> StoreResult<Vec<ItemHeader>>;
+    /// Changes only the item's name, so a relabel can't disturb its type or options.
+    async fn rename_item(
+        &self,
+        item_id: &SchemaItemId,
+        name: &str,
+        actor: Option<&UserId>,
+    ) -> StoreResult<ItemHeader>;
+    /// Deletes one item and its options, closing the gap it leaves in its schema's order, or in its
+    /// group's if it's inside one. The deleted item's header is returned.
+    async fn delete_item(&self, item_id: &SchemaItemId) -> StoreResult<ItemHeader>;
+    /// Changes an item's type, leaving its name and position alone. The changed item, or the group
+    /// it's in, is returned in its event's schema. Once registrations hold values for the item, the change has to keep them readable: select
+    /// answers are rewritten to option names when a select becomes text, select options carry over
+    /// from the stored item, and changes that would lose values fail with `TypeChangeUnsafe`.
+    async fn change_item_type(
+        &self,
+        item_id: &SchemaItemId,
+        new_type: RegistrationSchemaItemType,
+        actor: Option<&UserId>,
+    ) -> StoreResult<RegistrationSchema>;
+    /// The item's recorded changes, oldest first. History outlives the item, so a deleted item's
+    /// changes can still be read.
+    async fn get_item_history(&self, item_id: &SchemaItemId) -> StoreResult<Vec<ItemHistoryEntry>>;
 }
 
 pub struct EventIdField;
@@ -182,6 +408,12 @@ impl super::Queryable for Query {
     }
 }
 
+impl super::Compound for Query {
+    fn compound(query: super::CompoundQuery<Query>) -> Self {
+        Query::Compound(query)
+    }
+}
+
 impl<'q, DB: sqlx::Database> super::Bindable<'q, DB> for Query
 where
     <EventIdField as super::Field>::Item: sqlx::Encode<'q, DB> + sqlx::Type<DB> + Sync,
@@ -207,26 +439,148 @@ fn option_values_bind<'q>(
     item_id: &'q str,
     idx: usize,
     option: &'q SelectOption,
-) -> Result<QueryBuilder<'q>, Error> {
+) -> StoreResult<QueryBuilder<'q>> {
     Ok(query_builder
         .bind(&option.id)
         .bind(item_id)
         .bind(i32::try_from(idx).unwrap())
         .bind(&option.name)
-        .bind(&option.product_id))
+        .bind(&option.product_id)
+        .bind(option.archived))
+}
+
+// The oneof already keeps one type's settings from being sent with another, but an item can still
+// be missing its type, which values_bind can't store, have defaults pointing past its options, or
+// be a group nested in another group.
+fn type_is_consistent(item: &RegistrationSchemaItem) -> bool {
+    match item.r#type.as_ref().and_then(|t| t.r#type.as_ref()) {
+        None => false,
+        Some(ItemType::Group(group)) => group.items.iter().all(|item| group_items(item).is_none()),
+        // An untouched select has default 0 before any options are added.
+        Some(ItemType::Select(select)) => {
+            select.options.is_empty() || (select.default as usize) < select.options.len()
+        }
+        Some(ItemType::MultiSelect(multi_select)) => multi_select
+            .defaults
+            .iter()
+            .all(|default| (*default as usize) < multi_select.options.len()),
+        Some(_) => true,
+    }
+}
+
+// Checks an item before it's saved and puts its values into their stored form.
+fn prepare_schemas(schemas: &mut [RegistrationSchema]) -> StoreResult<()> {
+    for item in schemas
+        .iter_mut()
+        .flat_map(|schema| schema.items.iter_mut())
+    {
+        prepare_item(item)?;
+
+        if let Some(ItemType::Group(group)) = item.r#type.as_mut().and_then(|t| t.r#type.as_mut()) {
+            for group_item in group.items.iter_mut() {
+                prepare_item(group_item)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn prepare_item(item: &mut RegistrationSchemaItem) -> StoreResult<()> {
+    item.required_after = parse_timestamp(&item.required_after)
+        .map_err(|_| Error::InvalidTimestamp(item.required_after.clone()))?
+        .as_ref()
+        .map(format_timestamp)
+        .unwrap_or_default();
+
+    if !type_is_consistent(item) {
+        return Err(Error::InconsistentItemType {
+            item_id: item.id.clone(),
+        });
+    }
+
+    // Defaults are a set of option indices, so they're stored in one canonical order.
+    if let Some(ItemType::MultiSelect(multi_select)) =
+        item.r#type.as_mut().and_then(|t| t.r#type.as_mut())
+    {
+        multi_select.defaults.sort_unstable();
+        multi_select.defaults.dedup();
+    }
+
+    let options = match item.r#type.as_mut().and_then(|t| t.r#type.as_mut()) {
+        Some(ItemType::Select(select)) => &mut select.options,
+        Some(ItemType::MultiSelect(multi_select)) => &mut multi_select.options,
+        _ => return Ok(()),
+    };
+
+    let mut names = HashSet::new();
+    for option in options.iter_mut() {
+        option.name = normalize(&option.name, Normalization::Collapse);
+        if !names.insert(option.name.to_lowercase()) {
+            return Err(Error::DuplicateOptionName {
+                item_id: item.id.clone(),
+                name: option.name.clone(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+// Top-level items belong to their event, and the items inside a group belong to the group.
+#[derive(Clone, Debug)]
+enum Owner {
+    Event(String),
+    Group(String),
+}
+
+impl Owner {
+    fn id(&self) -> &str {
+        match self {
+            Owner::Event(id) | Owner::Group(id) => id,
+        }
+    }
+}
+
+fn group_items(item: &RegistrationSchemaItem) -> Option<&Vec<RegistrationSchemaItem>> {
+    match item.r#type.as_ref().and_then(|t| t.r#type.as_ref()) {
+        Some(ItemType::Group(group)) => Some(&group.items),
+        _ => None,
+    }
+}
+
+// Each item followed by the items in it, if it's a group.
+fn with_group_items(
+    items: &[RegistrationSchemaItem],
+) -> impl Iterator<Item = &RegistrationSchemaItem> + Clone {
+    items
+        .iter()
+        .flat_map(|item| std::iter::once(item).chain(group_items(item).into_iter().flatten()))
 }
 
 fn values_bind<'q>(
     query_builder: QueryBuilder<'q>,
-    event_id: &'q str,
+    owner: &'q Owner,
     idx: usize,
     item: &'q RegistrationSchemaItem,
-) -> Result<QueryBuilder<'q>, Error> {
+) -> StoreResult<QueryBuilder<'q>> {
+    let query_builder = query_builder.bind(&item.id);
+    let query_builder = match owner {
+        Owner::Event(event_id) => query_builder
+            .bind(Some(event_id))
+            .bind::<Option<&str>>(None),
+        Owner::Group(group_id) => query_builder
+            .bind::<Option<&str>>(None)
+            .bind(Some(group_id)),
+    };
+
     let query_builder = query_builder
-        .bind(&item.id)
-        .bind(event_id)
         .bind(i32::try_from(idx).unwrap())
-        .bind(&item.name);
+        .bind(&item.name)
+        .bind(item.required)
+        .bind(nullable(&item.required_after))
+        .bind(item.blank_on_copy)
+        .bind(item.display_idx);
 
     let typ = item.r#type.as_ref().unwrap().r#type.as_ref().unwrap();
 
@@ -235,15 +589,26 @@ fn values_bind<'q>(
         ItemType::Checkbox(_) => query_builder.bind("CheckboxType"),
         ItemType::Select(_) => query_builder.bind("SelectType"),
         ItemType::MultiSelect(_) => query_builder.bind("MultiSelectType"),
+        ItemType::Section(_) => query_builder.bind("SectionType"),
+        ItemType::Group(_) => query_builder.bind("GroupType"),
+        ItemType::Tags(_) => query_builder.bind("TagsType"),
     };
 
     let query_builder = match typ {
-        ItemType::Text(text) => query_builder.bind(&text.default).bind(
-            text_type::Display::try_from(text.display)
-                .unwrap()
-                .as_str_name(),
-        ),
+        ItemType::Text(text) => query_builder
+            .bind(&text.default)
+            .bind(
+                text_type::Display::try_from(text.display)
+                    .unwrap()
+                    .as_str_name(),
+            )
+            .bind(&text.placeholder)
+            .bind(&text.prefix)
+            .bind(&text.suffix),
         _ => query_builder
+            .bind::<Option<String>>(None)
+            .bind::<Option<String>>(None)
+            .bind::<Option<String>>(None)
             .bind::<Option<String>>(None)
             .bind::<Option<String>>(None),
     };
@@ -285,49 +650,278 @@ fn values_bind<'q>(
             .bind::<Option<String>>(None),
     };
 
+    let query_builder = match typ {
+        ItemType::Section(section) => query_builder.bind(&section.body),
+        _ => query_builder.bind::<Option<String>>(None),
+    };
+
+    let query_builder = match typ {
+        ItemType::Tags(tags) => query_builder.bind(tags.max_tags),
+        _ => query_builder.bind::<Option<u32>>(None),
+    };
+
     Ok(query_builder)
 }
 
+// How a save treats the items it's given.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SaveMode {
+    // The schemas replace the events' schemas entirely, including item order.
+    Replace,
+    // Only the given items' contents change. Their positions and any other items are left alone,
+    // and new items go after the stored ones.
+    Edit,
+    // Like Edit, for an item whose type change was already checked against its registration
+    // values, so the schema lock doesn't apply.
+    Retype,
+}
+
+// What a save returns.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SaveOutput {
+    // Every item that was written.
+    Written,
+    // Only the items that were inserted or whose stored contents or position changed.
+    Changed,
+}
+
+// Keeps the written items that differ from the stored ones, dropping schemas left empty. Group
+// items are compared as part of their group.
+fn changed_items(
+    stored: &[RegistrationSchema],
+    written: Vec<RegistrationSchema>,
+) -> Vec<RegistrationSchema> {
+    let stored_items = stored
+        .iter()
+        .flat_map(|schema| schema.items.iter().enumerate())
+        .map(|(idx, item)| (item.id.as_str(), (idx, item)))
+        .collect::<HashMap<_, _>>();
+
+    written
+        .into_iter()
+        .filter_map(|mut schema| {
+            schema.items = schema
+                .items
+                .into_iter()
+                .enumerate()
+                .filter(|(idx, item)| stored_items.get(item.id.as_str()) != Some(&(*idx, item)))
+                .map(|(_, item)| item)
+                .collect();
+
+            (!schema.items.is_empty()).then_some(schema)
+        })
+        .collect()
+}
+
+// Renumbers idx within each parent so that the values are contiguous from zero, keeping the
+// existing relative order. Rows sharing an idx are ordered by id, matching build_items_map.
+async fn compact_idx<'a>(
+    conn: &mut sqlx::SqliteConnection,
+    table: &'static str,
+    parent_column: &'static str,
+    parent_ids: impl IntoIterator<Item = &'a str> + Clone,
+) -> StoreResult<()> {
+    let where_clause: String = itertools::Itertools::intersperse(
+        parent_ids
+            .clone()
+            .into_iter()
+            .map(|_| format!("{} = ?", parent_column)),
+        " OR ".to_owned(),
+    )
+    .collect();
+
+    if where_clause.is_empty() {
+        return Ok(());
+    }
+
+    let query = format!(
+        "WITH ranked AS (
+            SELECT id, ROW_NUMBER() OVER (PARTITION BY {1} ORDER BY idx, id) - 1 AS new_idx FROM {0} WHERE {2}
+        ) UPDATE {0} SET idx = ranked.new_idx FROM ranked WHERE {0}.id = ranked.id AND {0}.idx != ranked.new_idx",
+        table, parent_column, where_clause
+    );
+
+    let query_builder = parent_ids
+        .into_iter()
+        .fold(sqlx::query(&query), |query_builder, id| {
+            query_builder.bind(id)
+        });
+
+    query_builder.execute(conn).await.update_err()?;
+
+    Ok(())
+}
+
+// The parts of an item that its history tracks: name, type and option summary.
+type ItemSnapshot = (String, String, String);
+
+// SQLite transactions start out reading, and one that reads before it writes can't wait for a
+// concurrent writer to finish: its upgrade fails with "database is locked" straight away. An
+// empty write takes the lock up front, so the snapshot read that follows waits its turn instead.
+async fn lock_for_write(conn: &mut sqlx::SqliteConnection) -> StoreResult<()> {
+    sqlx::query("UPDATE registration_schema_items SET id = id WHERE FALSE")
+        .execute(conn)
+        .await
+        .update_err()?;
+
+    Ok(())
+}
+
+async fn item_snapshots(
+    conn: &mut sqlx::SqliteConnection,
+    item_ids: &[&str],
+) -> StoreResult<HashMap<String, ItemSnapshot>> {
+    if item_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let query = format!(
+        "SELECT id, name, item_type, (
+            SELECT COALESCE(GROUP_CONCAT(name, ', '), '') FROM (
+                SELECT name FROM registration_schema_select_options
+                WHERE schema_item = registration_schema_items.id AND archived = FALSE
+                ORDER BY idx, id
+            )
+        ) FROM registration_schema_items WHERE id IN ({})",
+        vec!["?"; item_ids.len()].join(", ")
+    );
+
+    let rows: Vec<(String, String, String, String)> = item_ids
+        .iter()
+        .fold(sqlx::query_as(&query), |query_builder, id| {
+            query_builder.bind(*id)
+        })
+        .fetch_all(conn)
+        .await
+        .fetch_err()?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(id, name, item_type, options)| (id, (name, item_type, options)))
+        .collect())
+}
+
+// Appends a history row for every item whose snapshot changed. Items missing from either side
+// were inserted or deleted rather than changed, so they're skipped.
+async fn record_history(
+    conn: &mut sqlx::SqliteConnection,
+    before: HashMap<String, ItemSnapshot>,
+    after: &HashMap<String, ItemSnapshot>,
+    actor: Option<&UserId>,
+) -> StoreResult<()> {
+    let mut changes = before
+        .into_iter()
+        .filter_map(|(id, old)| {
+            let new = after.get(&id)?;
+            (old != *new).then_some((id, old, new))
+        })
+        .collect::<Vec<_>>();
+
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    changes.sort_by(|(a, _, _), (b, _, _)| a.cmp(b));
+
+    let values_clause: String = itertools::Itertools::intersperse(
+        changes.iter().map(|_| "(?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"),
+        ", ",
+    )
+    .collect();
+    let query = format!(
+        "INSERT INTO registration_schema_item_history(id, schema_item, changed_at, actor, old_name, new_name, old_type, new_type, old_options, new_options) VALUES {}",
+        values_clause
+    );
+
+    let changed_at = now_rfc3339();
+    changes
+        .iter()
+        .fold(sqlx::query(&query), |query_builder, (id, old, new)| {
+            query_builder
+                .bind(new_id())
+                .bind(id)
+                .bind(&changed_at)
+                .bind(actor)
+                .bind(&old.0)
+                .bind(&new.0)
+                .bind(&old.1)
+                .bind(&new.1)
+                .bind(&old.2)
+                .bind(&new.2)
+        })
+        .execute(conn)
+        .await
+        .insert_err()?;
+
+    Ok(())
+}
+
+// Items and options are ordered by (idx, id) rather than idx alone, so rows sharing an idx (from
+// legacy or externally written data) are all kept, ordered by id. The next upsert renumbers them.
+// Rows are moved into their parents and sorted in place, so nothing is cloned along the way. Items
+// whose parent is a group end up in that group rather than under an event.
 fn build_items_map(
     items: impl IntoIterator<Item = (String, usize, RegistrationSchemaItem)>,
     options: impl IntoIterator<Item = (String, usize, SelectOption)>,
-) -> HashMap<String, Vec<RegistrationSchemaItem>> {
-    let mut items_to_options_map = HashMap::new();
+) -> BTreeMap<String, Vec<RegistrationSchemaItem>> {
+    let mut items_to_options_map: HashMap<String, Vec<(usize, SelectOption)>> = HashMap::new();
     for (item_id, idx, option) in options {
-        let option_map = items_to_options_map
+        items_to_options_map
             .entry(item_id)
-            .or_insert(BTreeMap::new());
-        option_map.insert(idx, option);
+            .or_default()
+            .push((idx, option));
     }
 
-    let mut items_to_options_map = items_to_options_map
-        .into_iter()
-        .map(|(item_id, options_map)| (item_id, options_map.into_values().collect::<Vec<_>>()))
-        .collect::<HashMap<_, _>>();
-
-    let mut schema_map = HashMap::new();
+    let mut schema_map: BTreeMap<String, Vec<(usize, RegistrationSchemaItem)>> = BTreeMap::new();
     for (event_id, idx, mut item) in items {
-        match item.r#type.as_mut().unwrap().r#type.as_mut().unwrap() {
-            ItemType::Select(select) => {
-                select.options = items_to_options_map.remove(&item.id).unwrap_or_default()
-            }
-            ItemType::MultiSelect(select) => {
-                select.options = items_to_options_map.remove(&item.id).unwrap_or_default()
-            }
-            _ => (),
+        let options = match item.r#type.as_mut().unwrap().r#type.as_mut().unwrap() {
+            ItemType::Select(select) => Some(&mut select.options),
+            ItemType::MultiSelect(select) => Some(&mut select.options),
+            _ => None,
         };
 
-        let item_map = schema_map.entry(event_id).or_insert(BTreeMap::new());
-        item_map.insert(idx, item);
+        if let Some(options) = options {
+            let mut item_options = items_to_options_map.remove(&item.id).unwrap_or_default();
+            item_options
+                .sort_unstable_by(|(l_idx, l), (r_idx, r)| (l_idx, &l.id).cmp(&(r_idx, &r.id)));
+            *options = item_options.into_iter().map(|(_, option)| option).collect();
+        }
+
+        schema_map.entry(event_id).or_default().push((idx, item));
     }
 
-    schema_map
+    let mut schema_map: BTreeMap<String, Vec<RegistrationSchemaItem>> = schema_map
         .into_iter()
-        .map(|(event_id, item_map)| (event_id, item_map.into_values().collect()))
-        .collect()
+        .map(|(parent_id, mut items)| {
+            items.sort_unstable_by(|(l_idx, l), (r_idx, r)| (l_idx, &l.id).cmp(&(r_idx, &r.id)));
+            (parent_id, items.into_iter().map(|(_, item)| item).collect())
+        })
+        .collect();
+
+    let group_ids = schema_map
+        .values()
+        .flatten()
+        .filter(|item| group_items(item).is_some())
+        .map(|item| item.id.clone())
+        .collect::<Vec<_>>();
+
+    let mut group_map = group_ids
+        .into_iter()
+        .filter_map(|group_id| schema_map.remove_entry(&group_id))
+        .collect::<HashMap<_, _>>();
+
+    for item in schema_map.values_mut().flatten() {
+        if let Some(ItemType::Group(group)) = item.r#type.as_mut().and_then(|t| t.r#type.as_mut()) {
+            group.items = group_map.remove(&item.id).unwrap_or_default();
+        }
+    }
+
+    schema_map
 }
 
-fn items_to_schema(
+/// Assembles schemas, one per event, from item and option rows tagged with their parent id and
+/// idx. Exposed for benchmarking.
+pub fn items_to_schema(
     items: impl IntoIterator<Item = (String, usize, RegistrationSchemaItem)>,
     options: impl IntoIterator<Item = (String, usize, SelectOption)>,
 ) -> Vec<RegistrationSchema> {
@@ -339,396 +933,322 @@ fn items_to_schema(
         .collect()
 }
 
-#[tonic::async_trait]
-impl Store for SqliteStore {
-    async fn upsert(
-        &self,
-        schemas: Vec<RegistrationSchema>,
-    ) -> Result<Vec<RegistrationSchema>, Error> {
-        ids_in_table(
-            &*self.pool,
-            "events",
-            schemas.iter().map(|schema| schema.event_id.as_str()),
-        )
-        .await?;
+/// Reorders items, and the items in each group, the way registrants see them: by display_idx,
+/// with items that don't set one keeping their place. Ties keep schema order.
+pub fn sort_for_display(items: &mut Vec<RegistrationSchemaItem>) {
+    let mut keyed = std::mem::take(items)
+        .into_iter()
+        .enumerate()
+        .map(|(idx, item)| {
+            let display_idx = item
+                .display_idx
+                .map_or(idx, |display_idx| display_idx as usize);
+            (display_idx, idx, item)
+        })
+        .collect::<Vec<_>>();
+    keyed.sort_by_key(|(display_idx, idx, _)| (*display_idx, *idx));
+
+    items.extend(keyed.into_iter().map(|(_, _, mut item)| {
+        if let Some(ItemType::Group(group)) = item.r#type.as_mut().and_then(|t| t.r#type.as_mut()) {
+            sort_for_display(&mut group.items);
+        }
+        item
+    }));
+}
 
-        let (inserts, updates): (Vec<_>, Vec<_>) = schemas
-            .into_iter()
-            .map(|s| {
-                s.items
-                    .into_iter()
-                    .enumerate()
-                    .map(move |(item_idx, item)| (s.event_id.clone(), item_idx, item))
-            })
-            .flatten()
-            .partition(|(_, _, item)| item.id == "");
+fn option_ids(item: &RegistrationSchemaItem) -> Vec<&str> {
+    let options = match item.r#type.as_ref().and_then(|t| t.r#type.as_ref()) {
+        Some(ItemType::Select(select)) => &select.options,
+        Some(ItemType::MultiSelect(multi_select)) => &multi_select.options,
+        _ => return Vec::new(),
+    };
 
-        if !updates.is_empty() {
-            // Make sure events exist
-            ids_in_table(
-                &*self.pool,
-                "registration_schema_items",
-                updates.iter().map(|(_, _, item)| item.id.as_str()),
-            )
-            .await?;
-        }
+    options.iter().map(|option| option.id.as_str()).collect()
+}
 
-        let (updates, options_from_updates): (Vec<_>, Vec<_>) = updates
-            .into_iter()
-            .map(|mut item| {
-                let (_, _, i) = &mut item;
+fn group_item_ids(item: &RegistrationSchemaItem) -> HashSet<&str> {
+    group_items(item)
+        .into_iter()
+        .flatten()
+        .map(|item| item.id.as_str())
+        .collect()
+}
 
-                let options = match i.r#type.as_mut().unwrap().r#type.as_mut().unwrap() {
-                    ItemType::Select(ref mut select) => std::mem::take(&mut select.options),
-                    ItemType::MultiSelect(multi_select) => {
-                        std::mem::take(&mut multi_select.options)
-                    }
-                    _ => Vec::new(),
-                };
+// Stored registration values refer to items by id and to options by id or position, so adding,
+// removing or reordering either, or changing an item's type, can invalidate them. The same goes
+// for adding or removing the items in a group, though not for reordering them. Names, required
+// flags, defaults and display settings are safe to change.
+fn is_structural_change(
+    stored: &RegistrationSchema,
+    incoming: &RegistrationSchema,
+    mode: SaveMode,
+) -> bool {
+    let stored_items = with_group_items(&stored.items)
+        .map(|item| (item.id.as_str(), item))
+        .collect::<HashMap<_, _>>();
 
-                let options = options
-                    .into_iter()
-                    .enumerate()
-                    .map(|(idx, option)| (i.id.clone(), idx, option))
-                    .collect::<Vec<_>>();
+    let changed = with_group_items(&incoming.items).any(|item| {
+        let Some(stored_item) = stored_items.get(item.id.as_str()) else {
+            return true;
+        };
 
-                (item, options)
-            })
-            .unzip();
+        let stored_type = stored_item.r#type.as_ref().and_then(|t| t.r#type.as_ref());
+        let incoming_type = item.r#type.as_ref().and_then(|t| t.r#type.as_ref());
 
-        let (insert_options, update_options): (Vec<_>, Vec<_>) = options_from_updates
-            .into_iter()
-            .flatten()
-            .partition(|(_, _, option)| option.id == "");
+        stored_type.map(std::mem::discriminant) != incoming_type.map(std::mem::discriminant)
+            || option_ids(stored_item) != option_ids(item)
+            || group_item_ids(stored_item) != group_item_ids(item)
+    });
 
-        if !update_options.is_empty() {
-            ids_in_table(
-                &*self.pool,
-                "registration_schema_select_options",
-                update_options
-                    .iter()
-                    .map(|(_, _, option)| option.id.as_str()),
-            )
-            .await?;
+    // Replacing a schema deletes any stored items that aren't passed in.
+    changed || (mode == SaveMode::Replace && incoming.items.len() != stored.items.len())
+}
+
+// How the values registrations hold for an item carry over when the item changes type.
+#[derive(Debug, PartialEq)]
+enum TypeChange {
+    // The values read the same under the new type. Select options are kept, so positions still
+    // resolve.
+    Keep,
+    // Option positions are replaced with the options' names, which read the same as text.
+    OptionNames,
+    // Some values would be lost or misread.
+    Unsafe,
+}
+
+fn type_change(from: &ItemType, to: &ItemType) -> TypeChange {
+    match (from, to) {
+        (ItemType::Text(_), ItemType::Text(_))
+        | (ItemType::Checkbox(_), ItemType::Checkbox(_) | ItemType::Text(_))
+        | (ItemType::Select(_), ItemType::Select(_) | ItemType::MultiSelect(_))
+        | (ItemType::MultiSelect(_), ItemType::MultiSelect(_))
+        | (ItemType::Tags(_), ItemType::Tags(_) | ItemType::Text(_)) => TypeChange::Keep,
+        (ItemType::Select(_) | ItemType::MultiSelect(_), ItemType::Text(_)) => {
+            TypeChange::OptionNames
         }
+        _ => TypeChange::Unsafe,
+    }
+}
 
-        let mut tx = self
-            .pool
-            .begin()
-            .await
-            .map_err(|e| Error::TransactionStartError(e))?;
+impl SqliteStore {
+    // Whether any registration has answered the item, or any item in it if it's a group.
+    async fn has_values(
+        &self,
+        conn: &mut SqliteConnection,
+        item_id: &SchemaItemId,
+    ) -> StoreResult<bool> {
+        let (count,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM registration_items WHERE value != '' AND (schema_item = ? OR schema_item IN (SELECT id FROM registration_schema_items WHERE parent = ?))",
+        )
+        .bind(item_id)
+        .bind(item_id)
+        .fetch_one(conn)
+        .await
+        .fetch_err()?;
 
-        let inserted = if !inserts.is_empty() {
-            let items_with_ids = inserts
-                .into_iter()
-                .map(|(event_id, item_idx, mut item)| {
-                    item.id = new_id();
-                    (event_id, item_idx, item)
-                })
-                .collect::<Vec<_>>();
+        Ok(count > 0)
+    }
 
-            let values_clause: String = itertools::Itertools::intersperse(
-                items_with_ids
-                    .iter()
-                    .map(|_| "(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"),
-                ", ",
-            )
-            .collect();
+    // Rewrites the item's stored answers from option positions to option names. Names are valid
+    // answers for a select too, so this is safe to do before the type itself changes.
+    async fn use_option_names(
+        &self,
+        conn: &mut SqliteConnection,
+        item_id: &SchemaItemId,
+        options: &[SelectOption],
+        multi: bool,
+    ) -> StoreResult<()> {
+        let rows: Vec<(String, String)> =
+            sqlx::query_as("SELECT id, value FROM registration_items WHERE schema_item = ?")
+                .bind(item_id)
+                .fetch_all(&mut *conn)
+                .await
+                .fetch_err()?;
 
-            let query = format!(
-                "INSERT INTO registration_schema_items(
-                    id, 
-                    event, 
-                    idx, 
-                    name, 
-                    item_type, 
-                    text_type_default, 
-                    text_type_display, 
-                    checkbox_type_default, 
-                    select_type_default, 
-                    select_type_display, 
-                    multi_select_type_defaults, 
-                    multi_select_type_display
-                ) VALUES {}",
-                values_clause
-            );
-
-            let mut query_builder = sqlx::query(&query);
-            for (event_id, item_idx, item) in items_with_ids.iter() {
-                query_builder = values_bind(query_builder, event_id, *item_idx, item)?;
-            }
-
-            query_builder
-                .execute(&mut *tx)
-                .await
-                .map_err(|e| Error::InsertionError(e))?;
-
-            items_with_ids
-        } else {
-            Vec::new()
+        let option_name = |value: &str| match value.parse::<usize>() {
+            Ok(idx) if idx < options.len() => options[idx].name.clone(),
+            _ => value.to_owned(),
         };
 
-        if !updates.is_empty() {
-            let values_clause: String = itertools::Itertools::intersperse(
-                updates
-                    .iter()
-                    .map(|_| "(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"),
-                ", ",
-            )
-            .collect();
-
-            let query = format!(
-                "WITH mydata(
-                    id, 
-                    event,
-                    idx,
-                    name,
-                    item_type,
-                    text_type_default,
-                    text_type_display,
-                    checkbox_type_default,
-                    select_type_default,
-                    select_type_display,
-                    multi_select_type_defaults,
-                    multi_select_type_display
-                ) AS (VALUES {}) UPDATE registration_schema_items SET 
-                    event = mydata.event,
-                    name = mydata.name,
-                    idx = mydata.idx,
-                    item_type = mydata.item_type,
-                    text_type_default = mydata.text_type_default,
-                    text_type_display = mydata.text_type_display,
-                    checkbox_type_default = mydata.checkbox_type_default,
-                    select_type_default = mydata.select_type_default,
-                    multi_select_type_defaults = mydata.multi_select_type_defaults,
-                    multi_select_type_display = mydata.multi_select_type_display
-                FROM mydata WHERE registration_schema_items.id = mydata.id",
-                values_clause
-            );
+        for (id, value) in rows {
+            let named = if multi {
+                itertools::join(value.split(',').map(option_name), ",")
+            } else {
+                option_name(&value)
+            };
 
-            let mut query_builder = sqlx::query(&query);
-            for (event_id, idx, item) in updates.iter() {
-                query_builder = values_bind(query_builder, event_id, *idx, item)?;
+            if named != value {
+                sqlx::query("UPDATE registration_items SET value = ? WHERE id = ?")
+                    .bind(named)
+                    .bind(id)
+                    .execute(&mut *conn)
+                    .await
+                    .update_err()?;
             }
-
-            query_builder
-                .execute(&mut *tx)
-                .await
-                .map_err(|e| Error::UpdateError(e))?;
         }
 
-        let (inserted, options_from_inserts): (Vec<_>, Vec<_>) = inserted
-            .into_iter()
-            .map(|mut item| {
-                let (_, _, i) = &mut item;
-
-                let options = match i.r#type.as_mut().unwrap().r#type.as_mut().unwrap() {
-                    ItemType::Select(select) => std::mem::take(&mut select.options),
-                    ItemType::MultiSelect(multi_select) => {
-                        std::mem::take(&mut multi_select.options)
-                    }
-                    _ => Vec::new(),
-                };
+        Ok(())
+    }
 
-                let options = options
-                    .into_iter()
-                    .enumerate()
-                    .map(|(idx, option)| (i.id.clone(), idx, option))
-                    .collect::<Vec<_>>();
+    // Events with registrations have locked schemas. Run inside the write's transaction, after
+    // `lock_for_write`, so a registration can't land between the check and the write.
+    async fn locked_events<'a>(
+        &self,
+        conn: &mut SqliteConnection,
+        event_ids: impl Iterator<Item = &'a str>,
+    ) -> StoreResult<HashSet<String>> {
+        let event_ids = event_ids.collect::<Vec<_>>();
+        let query = format!(
+            "SELECT DISTINCT event FROM registrations WHERE event IN ({})",
+            vec!["?"; event_ids.len()].join(", ")
+        );
 
-                (item, options)
+        let rows: Vec<(String,)> = event_ids
+            .iter()
+            .fold(sqlx::query_as(&query), |query_builder, id| {
+                query_builder.bind(*id)
             })
-            .unzip();
-
-        let insert_options = options_from_inserts
-            .into_iter()
-            .flatten()
-            .chain(insert_options.into_iter())
-            .collect::<Vec<_>>();
-
-        let inserted_options = if !insert_options.is_empty() {
-            let options_with_ids = insert_options
-                .into_iter()
-                .map(|(item_id, idx, mut option)| {
-                    option.id = new_id();
-                    (item_id, idx, option)
-                })
-                .collect::<Vec<_>>();
+            .fetch_all(&mut *conn)
+            .await
+            .fetch_err()?;
 
-            let values_clause: String = itertools::Itertools::intersperse(
-                options_with_ids.iter().map(|_| "(?, ?, ?, ?, ?)"),
-                ", ",
-            )
-            .collect();
+        Ok(rows.into_iter().map(|(event,)| event).collect())
+    }
 
-            let query = format!("INSERT INTO registration_schema_select_options(id, schema_item, idx, name, product_id) VALUES {};", values_clause);
+    // Item names head the CSV export's columns, so they have to be unique within an event, and
+    // within each group, ignoring case. Edits are checked against the items they leave alone as
+    // well. Run inside the write's transaction, after `lock_for_write`, so two concurrent writes
+    // can't both take the same name.
+    async fn check_unique_names(
+        &self,
+        conn: &mut SqliteConnection,
+        schemas: &[RegistrationSchema],
+        mode: SaveMode,
+    ) -> StoreResult<()> {
+        if mode == SaveMode::Retype {
+            return Ok(());
+        }
 
-            let mut query_builder = sqlx::query(&query);
-            for (item_id, idx, option) in options_with_ids.iter() {
-                query_builder = option_values_bind(query_builder, item_id, *idx, option)?;
-            }
+        for schema in schemas {
+            let mut names = schema
+                .items
+                .iter()
+                .map(|item| (item.id.clone(), item.name.clone()))
+                .collect::<Vec<_>>();
 
-            query_builder
-                .execute(&mut *tx)
+            if mode == SaveMode::Edit {
+                let stored: Vec<(String, String)> = sqlx::query_as(
+                    "SELECT id, name FROM registration_schema_items WHERE event = ?",
+                )
+                .bind(&schema.event_id)
+                .fetch_all(&mut *conn)
                 .await
-                .map_err(|e| Error::InsertionError(e))?;
-
-            options_with_ids
-        } else {
-            Vec::new()
-        };
+                .fetch_err()?;
 
-        if !update_options.is_empty() {
-            let values_clause: String = itertools::Itertools::intersperse(
-                update_options.iter().map(|_| "(?, ?, ?, ?, ?)"),
-                ", ",
-            )
-            .collect();
+                names.extend(
+                    stored
+                        .into_iter()
+                        .filter(|(id, _)| !schema.items.iter().any(|item| item.id == *id)),
+                );
+            }
 
-            let query = format!(
-                "WITH mydata(id,
-                    schema_item,
-                    idx,
-                    name,
-                    product_id
-                ) AS (VALUES {}) UPDATE registration_schema_select_options SET 
-                    schema_item = mydata.schema_item,
-                    idx = mydata.idx,
-                    name = mydata.name,
-                    product_id = mydata.product_id
-                FROM mydata WHERE registration_schema_select_options.id = mydata.id",
-                values_clause
-            );
+            // A group's items are always given in full, so they're only checked against each
+            // other.
+            let groups = schema.items.iter().filter_map(group_items).map(|items| {
+                items
+                    .iter()
+                    .map(|item| (item.id.clone(), item.name.clone()))
+                    .collect::<Vec<_>>()
+            });
 
-            let mut query_builder = sqlx::query(&query);
-            for (item_id, idx, option) in update_options.iter() {
-                query_builder = option_values_bind(query_builder, item_id, *idx, option)?;
+            for names in std::iter::once(names).chain(groups) {
+                let mut seen = HashSet::new();
+                if let Some((_, name)) = names
+                    .into_iter()
+                    .find(|(_, name)| !seen.insert(name.to_lowercase()))
+                {
+                    return Err(Error::DuplicateItemName { name });
+                }
             }
-
-            query_builder
-                .execute(&mut *tx)
-                .await
-                .map_err(|e| Error::UpdateError(e))?;
         }
 
-        let schema = items_to_schema(
-            inserted.into_iter().chain(updates.into_iter()),
-            inserted_options
-                .into_iter()
-                .chain(update_options.into_iter()),
-        );
+        Ok(())
+    }
 
-        if !schema.is_empty() {
-            let where_clause = itertools::Itertools::intersperse(
-                schema.iter().map(|schema| {
-                    let event_clause = itertools::Itertools::intersperse(
-                        std::iter::once("event = ?").chain(schema.items.iter().map(|_| "id != ?")),
-                        " AND ",
-                    )
-                    .collect::<String>();
+    async fn check_unlocked(
+        &self,
+        conn: &mut SqliteConnection,
+        schemas: &[RegistrationSchema],
+        mode: SaveMode,
+    ) -> StoreResult<()> {
+        if mode == SaveMode::Retype {
+            return Ok(());
+        }
 
-                    format!("({})", event_clause)
-                }),
-                " OR ".to_owned(),
+        let locked = self
+            .locked_events(
+                &mut *conn,
+                schemas.iter().map(|schema| schema.event_id.as_str()),
             )
-            .collect::<String>();
-
-            let query = format!(
-                "DELETE FROM registration_schema_items WHERE {}",
-                where_clause
-            );
-
-            let query_builder = sqlx::query(&query);
-            let query_builder = schema.iter().fold(query_builder, |query_builder, schema| {
-                let query_builder = query_builder.bind(&schema.event_id);
-                schema
-                    .items
-                    .iter()
-                    .fold(query_builder, |query_builder, item| {
-                        query_builder.bind(&item.id)
-                    })
-            });
-
-            query_builder
-                .execute(&mut *tx)
-                .await
-                .map_err(|e| Error::DeleteError(e))?;
-
-            let options_where_clause = itertools::Itertools::intersperse(
-                schema
-                    .iter()
-                    .map(|schema| schema.items.iter())
-                    .flatten()
-                    .map(|item| {
-                        let options: Box<dyn Iterator<Item = &SelectOption>> =
-                            match item.r#type.as_ref().unwrap().r#type.as_ref().unwrap() {
-                                ItemType::Select(select) => Box::new(select.options.iter()),
-                                ItemType::MultiSelect(select) => Box::new(select.options.iter()),
-                                _ => Box::new(std::iter::empty()),
-                            };
+            .await?;
+        if locked.is_empty() {
+            return Ok(());
+        }
 
-                        itertools::Itertools::intersperse(
-                            std::iter::once("schema_item = ?").chain(options.map(|_| "id != ?")),
-                            " AND ",
-                        )
-                        .collect::<String>()
-                    }),
-                " OR ".to_owned(),
+        let stored = self
+            .query_in(
+                &mut *conn,
+                Some(&Query::EventId(EventIdQuery::In(
+                    locked.iter().cloned().collect(),
+                ))),
             )
-            .collect::<String>();
-
-            let options_query = format!(
-                "DELETE FROM registration_schema_select_options WHERE {}",
-                options_where_clause
-            );
-
-            let query_builder = sqlx::query(&options_query);
-            let query_builder = schema
-                .iter()
-                .map(|schema| schema.items.iter())
-                .flatten()
-                .fold(query_builder, |query_builder, item| {
-                    let query_builder = query_builder.bind(&item.id);
-
-                    let options: Box<dyn Iterator<Item = &SelectOption>> =
-                        match item.r#type.as_ref().unwrap().r#type.as_ref().unwrap() {
-                            ItemType::Select(select) => Box::new(select.options.iter()),
-                            ItemType::MultiSelect(select) => Box::new(select.options.iter()),
-                            _ => Box::new(std::iter::empty()),
-                        };
-
-                    options.fold(query_builder, |query_builder, option| {
-                        query_builder.bind(&option.id)
-                    })
-                });
+            .await?
+            .into_iter()
+            .map(|schema| (schema.event_id.clone(), schema))
+            .collect::<HashMap<_, _>>();
 
-            query_builder
-                .execute(&mut *tx)
-                .await
-                .map_err(|e| Error::DeleteError(e))?;
-        };
+        for schema in schemas.iter().filter(|s| locked.contains(&s.event_id)) {
+            let empty = RegistrationSchema::default();
+            let stored_schema = stored.get(&schema.event_id).unwrap_or(&empty);
 
-        tx.commit().await.map_err(|e| Error::TransactionFailed(e))?;
+            if is_structural_change(stored_schema, schema, mode) {
+                return Err(Error::SchemaLocked(schema.event_id.clone()));
+            }
+        }
 
-        Ok(schema)
+        Ok(())
     }
 
-    async fn query(&self, query: Option<&Query>) -> Result<Vec<RegistrationSchema>, Error> {
+    async fn query_in(
+        &self,
+        conn: &mut SqliteConnection,
+        query: Option<&Query>,
+    ) -> StoreResult<Vec<RegistrationSchema>> {
         let base_query = "SELECT id, 
             event,
+            parent,
             idx,
             name,
+            required,
+            required_after,
+            blank_on_copy,
+            display_idx,
             item_type,
             text_type_default,
             text_type_display,
+            text_type_placeholder,
+            text_type_prefix,
+            text_type_suffix,
             checkbox_type_default,
             select_type_default,
             select_type_display,
             multi_select_type_defaults,
-            multi_select_type_display FROM registration_schema_items";
+            multi_select_type_display,
+            section_type_body,
+            tags_type_max_tags FROM registration_schema_items";
 
         let base_options_query =
-            "SELECT id, schema_item, idx, name, product_id FROM registration_schema_select_options";
+            "SELECT id, schema_item, idx, name, product_id, archived FROM registration_schema_select_options";
 
         let items = {
             let query_string = match query {
@@ -742,38 +1262,75 @@ impl Store for SqliteStore {
                 None => query_builder,
             };
 
-            let rows: Vec<ItemRow> = query_builder
-                .fetch_all(&*self.pool)
-                .await
-                .map_err(|e| Error::FetchError(e))?;
-
-            rows.into_iter()
-                .map(|row| row.to_item())
-                .collect::<Result<Vec<_>, _>>()?
-        };
+            let rows: Vec<ItemRow> = query_builder.fetch_all(&mut *conn).await.fetch_err()?;
 
-        if items.is_empty() {
-            return Ok(Vec::new());
-        }
+            let mut items = rows
+                .into_iter()
+                .map(|row| row.into_item(self.enum_policy))
+                .collect::<Result<Vec<_>, _>>()?;
 
-        let options = {
-            let select_items = items
+            // Items inside groups have no event, so most queries can't match them directly. Those
+            // that do, such as no query at all, already have them.
+            let fetched_ids = items
                 .iter()
-                .filter(|(_, _, item)| {
-                    match item.r#type.as_ref().unwrap().r#type.as_ref().unwrap() {
-                        ItemType::Select(_) | ItemType::MultiSelect(_) => true,
-                        _ => false,
-                    }
-                })
+                .map(|(_, _, item)| item.id.clone())
+                .collect::<HashSet<_>>();
+            let group_ids = items
+                .iter()
+                .filter(|(_, _, item)| group_items(item).is_some())
+                .map(|(_, _, item)| item.id.as_str())
                 .collect::<Vec<_>>();
 
-            let where_clause: String = itertools::Itertools::intersperse(
-                select_items.iter().map(|_| "schema_item = ?"),
-                " OR ",
-            )
+            if !group_ids.is_empty() {
+                let where_clause: String = itertools::Itertools::intersperse(
+                    group_ids.iter().map(|_| "parent = ?"),
+                    " OR ",
+                )
+                .collect();
+                let query = format!("{} WHERE {}", base_query, where_clause);
+
+                let rows: Vec<ItemRow> = group_ids
+                    .iter()
+                    .fold(sqlx::query_as(&query), |query_builder, id| {
+                        query_builder.bind(*id)
+                    })
+                    .fetch_all(&mut *conn)
+                    .await
+                    .fetch_err()?;
+
+                let group_items = rows
+                    .into_iter()
+                    .filter(|row| !fetched_ids.contains(&row.id))
+                    .map(|row| row.into_item(self.enum_policy))
+                    .collect::<Result<Vec<_>, _>>()?;
+                items.extend(group_items);
+            }
+
+            items
+        };
+
+        if items.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let options = {
+            let select_items = items
+                .iter()
+                .filter(|(_, _, item)| {
+                    matches!(
+                        item.r#type.as_ref().unwrap().r#type.as_ref().unwrap(),
+                        ItemType::Select(_) | ItemType::MultiSelect(_)
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            let where_clause: String = itertools::Itertools::intersperse(
+                select_items.iter().map(|_| "schema_item = ?"),
+                " OR ",
+            )
             .collect();
 
-            if where_clause == "" {
+            if where_clause.is_empty() {
                 Vec::new()
             } else {
                 let query = format!("{} WHERE {}", base_options_query, where_clause);
@@ -785,13 +1342,10 @@ impl Store for SqliteStore {
                         query_builder.bind(&item.id)
                     });
 
-                let rows: Vec<OptionRow> = query_builder
-                    .fetch_all(&*self.pool)
-                    .await
-                    .map_err(|e| Error::FetchError(e))?;
+                let rows: Vec<OptionRow> = query_builder.fetch_all(&mut *conn).await.fetch_err()?;
 
                 rows.into_iter()
-                    .map(|row| row.to_option())
+                    .map(|row| row.into_option())
                     .collect::<Result<Vec<_>, _>>()?
             }
         };
@@ -801,758 +1355,4041 @@ impl Store for SqliteStore {
         Ok(schemas)
     }
 
-    async fn delete(&self, event_ids: &Vec<String>) -> Result<(), Error> {
-        if event_ids.is_empty() {
-            return Ok(());
+    async fn save(
+        &self,
+        mut schemas: Vec<RegistrationSchema>,
+        mode: SaveMode,
+        output: SaveOutput,
+        actor: Option<&UserId>,
+    ) -> StoreResult<Vec<RegistrationSchema>> {
+        if let Some(result) = nothing_to_do(&schemas) {
+            return result;
         }
 
+        prepare_schemas(&mut schemas)?;
+
         ids_in_table(
             &*self.pool,
             "events",
-            event_ids.iter().map(|id| id.as_str()),
+            schemas.iter().map(|schema| schema.event_id.as_str()),
         )
         .await?;
 
-        let where_clause: String =
-            itertools::Itertools::intersperse(event_ids.iter().map(|_| "event = ?"), " OR ")
-                .collect();
-        let query = format!(
-            "DELETE FROM registration_schema_items WHERE {}",
-            where_clause
-        );
+        // Items and options with ids are updates, so they have to exist already. These reads go
+        // through the pool, so they're done before the transaction takes the write lock.
+        let updated_items = schemas
+            .iter()
+            .flat_map(|schema| with_group_items(&schema.items))
+            .filter(|item| !item.id.is_empty())
+            .collect::<Vec<_>>();
+        ids_in_table(
+            &self.pool,
+            "registration_schema_items",
+            updated_items.iter().map(|item| item.id.as_str()),
+        )
+        .await?;
+        ids_in_table(
+            &self.pool,
+            "registration_schema_select_options",
+            updated_items
+                .iter()
+                .flat_map(|item| option_ids(item))
+                .filter(|id| !id.is_empty()),
+        )
+        .await?;
 
-        let mut query_builder = sqlx::query(&query);
+        let mut tx = self.pool.begin().await.begin_err()?;
+        lock_for_write(&mut tx).await?;
+        let schemas = self.write(&mut tx, schemas, mode, output, actor).await?;
+        tx.commit().await.commit_err()?;
 
-        for id in event_ids.iter() {
-            query_builder = query_builder.bind(id);
-        }
+        Ok(schemas)
+    }
 
-        query_builder
-            .execute(&*self.pool)
-            .await
-            .map_err(|e| Error::DeleteError(e))?;
+    // The part of a save that runs inside its transaction, after `lock_for_write`. The schemas have
+    // already been through `prepare_schemas`, and the items and options they update exist.
+    async fn write(
+        &self,
+        conn: &mut SqliteConnection,
+        schemas: Vec<RegistrationSchema>,
+        mode: SaveMode,
+        output: SaveOutput,
+        actor: Option<&UserId>,
+    ) -> StoreResult<Vec<RegistrationSchema>> {
+        self.check_unique_names(&mut *conn, &schemas, mode).await?;
+        self.check_unlocked(&mut *conn, &schemas, mode).await?;
+
+        let event_ids = schemas
+            .iter()
+            .map(|schema| schema.event_id.clone())
+            .collect::<Vec<_>>();
 
-        Ok(())
-    }
-}
+        let oversized_items = schemas
+            .iter()
+            .flat_map(|schema| with_group_items(&schema.items))
+            .filter_map(|item| {
+                let options = match item.r#type.as_ref()?.r#type.as_ref()? {
+                    ItemType::Select(select) => &select.options,
+                    ItemType::MultiSelect(multi_select) => &multi_select.options,
+                    _ => return None,
+                };
 
-#[cfg(test)]
-mod tests {
-    use std::{collections::HashMap, str::FromStr, sync::Arc};
+                (options.len() > self.max_options).then(|| (item.id.clone(), options.len()))
+            })
+            .collect::<Vec<_>>();
 
-    use common::proto::{
-        multi_select_type, registration_schema_item_type::Type as ItemType, select_type, text_type,
-        CheckboxType, MultiSelectType, RegistrationSchema, RegistrationSchemaItem,
-        RegistrationSchemaItemType, SelectOption, SelectType, TextType,
-    };
-    use sqlx::{
-        migrate::MigrateDatabase, sqlite::SqliteConnectOptions, ConnectOptions, Sqlite, SqlitePool,
-    };
+        // Groups are saved as rows of their own followed by their items. New groups get their ids
+        // up front so that their items have a parent to point to.
+        let mut new_groups = HashSet::new();
+        let mut items = Vec::new();
+        for schema in schemas {
+            let owner = Owner::Event(schema.event_id);
+            for (item_idx, mut item) in schema.items.into_iter().enumerate() {
+                let group_items = match item.r#type.as_mut().and_then(|t| t.r#type.as_mut()) {
+                    Some(ItemType::Group(group)) => Some(std::mem::take(&mut group.items)),
+                    _ => None,
+                };
 
-    use crate::store::{
-        common::new_id,
-        registration_schema::{OptionRow, Store},
-        CompoundOperator, CompoundQuery, Error, LogicalQuery,
-    };
+                let Some(group_items) = group_items else {
+                    items.push((owner.clone(), item_idx, item));
+                    continue;
+                };
 
-    use super::{items_to_schema, ItemRow, Query, SqliteStore};
+                if item.id.is_empty() {
+                    item.id = new_id();
+                    new_groups.insert(item.id.clone());
+                }
 
-    use test_case::test_case;
+                let group_owner = Owner::Group(item.id.clone());
+                items.push((owner.clone(), item_idx, item));
+                items.extend(group_items.into_iter().enumerate().map(
+                    |(group_item_idx, group_item)| {
+                        (group_owner.clone(), group_item_idx, group_item)
+                    },
+                ));
+            }
+        }
 
-    struct Init {
-        event_1: String,
-        event_2: String,
-        db: SqlitePool,
-    }
+        let (inserts, updates): (Vec<_>, Vec<_>) = items
+            .into_iter()
+            .partition(|(_, _, item)| item.id.is_empty() || new_groups.contains(&item.id));
 
-    async fn init_db() -> Init {
-        let db_url = "sqlite://:memory:";
-        Sqlite::create_database(db_url).await.unwrap();
+        let (updates, options_from_updates): (Vec<_>, Vec<_>) = updates
+            .into_iter()
+            .map(|mut item| {
+                let (_, _, i) = &mut item;
 
-        let db = SqlitePool::connect_with(
-            SqliteConnectOptions::from_str(db_url)
-                .unwrap()
-                .log_statements(log::LevelFilter::Trace),
-        )
-        .await
-        .unwrap();
-        sqlx::migrate!("../migrations").run(&db).await.unwrap();
+                let options = match i.r#type.as_mut().unwrap().r#type.as_mut().unwrap() {
+                    ItemType::Select(ref mut select) => std::mem::take(&mut select.options),
+                    ItemType::MultiSelect(multi_select) => {
+                        std::mem::take(&mut multi_select.options)
+                    }
+                    _ => Vec::new(),
+                };
 
-        let org_id = new_id();
-        let org_name = "Org 1";
-        sqlx::query("INSERT INTO organizations(id, name) VALUES (?, ?);")
-            .bind(&org_id)
-            .bind(&org_name)
-            .execute(&db)
-            .await
-            .unwrap();
+                let options = options
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, option)| (i.id.clone(), idx, option))
+                    .collect::<Vec<_>>();
 
-        let id_1 = new_id();
-        let name_1 = "Event 1";
-        let id_2 = new_id();
-        let name_2 = "Event 2";
-        sqlx::query("INSERT INTO events(id, organization, name) VALUES (?, ?, ?), (?, ?, ?);")
-            .bind(&id_1)
-            .bind(&org_id)
-            .bind(&name_1)
-            .bind(&id_2)
-            .bind(&org_id)
-            .bind(&name_2)
-            .execute(&db)
-            .await
-            .unwrap();
+                (item, options)
+            })
+            .unzip();
 
-        Init {
-            event_1: id_1,
-            event_2: id_2,
-            db,
+        let (insert_options, update_options): (Vec<_>, Vec<_>) = options_from_updates
+            .into_iter()
+            .flatten()
+            .partition(|(_, _, option)| option.id == "");
+
+        let updated_ids = updates
+            .iter()
+            .map(|(_, _, item)| item.id.clone())
+            .collect::<Vec<_>>();
+        let updated_ids = updated_ids.iter().map(String::as_str).collect::<Vec<_>>();
+        let before = item_snapshots(&mut *conn, &updated_ids).await?;
+
+        // Read inside the transaction so the comparison sees exactly what is being overwritten.
+        let stored = match output {
+            SaveOutput::Written => None,
+            SaveOutput::Changed => Some(
+                self.query_in(
+                    &mut *conn,
+                    Some(&Query::EventId(EventIdQuery::In(event_ids))),
+                )
+                .await?,
+            ),
+        };
+
+        // Items that are already over the limit may still be edited, as long as they don't grow.
+        for (item_id, option_count) in oversized_items {
+            let existing_count = if item_id.is_empty() {
+                0
+            } else {
+                let (count,): (i64,) = sqlx::query_as(
+                    "SELECT COUNT(*) FROM registration_schema_select_options WHERE schema_item = ?",
+                )
+                .bind(&item_id)
+                .fetch_one(&mut *conn)
+                .await
+                .fetch_err()?;
+
+                count as usize
+            };
+
+            if option_count > existing_count {
+                return Err(Error::TooManyOptions {
+                    max: self.max_options,
+                });
+            }
         }
-    }
 
-    async fn test_data(init: &Init) -> Vec<RegistrationSchema> {
-        let item1_id = new_id();
-        let item1_name = "item 1";
-        let item2_id = new_id();
-        let item2_name = "item 2";
-        let item3_id = new_id();
-        let item3_name = "item 3";
-        let item4_id = new_id();
-        let item4_name = "item 4";
-        let text_default = "text default";
-        let text_display = ("LARGE", text_type::Display::Large);
-        let checkbox_default = true;
-        let select_default = 1;
-        let select_display = ("DROPDOWN", select_type::Display::Dropdown);
-        let select_option1_id = new_id();
-        let select_option1_name = "option 1";
-        let select_option1_product_id = "product 1";
-        let select_option2_id = new_id();
-        let select_option2_name = "option 2";
-        let select_option2_product_id = "product 2";
-        let select_option3_id = new_id();
-        let select_option3_name = "option 3";
-        let select_option3_product_id = "product 3";
-        let text_default_4 = "text default";
-        let text_display_4 = ("SMALL", text_type::Display::Small);
+        let inserted = if !inserts.is_empty() {
+            let mut items_with_ids = inserts
+                .into_iter()
+                .map(|(owner, item_idx, mut item)| {
+                    if item.id.is_empty() {
+                        item.id = new_id();
+                    }
+                    (owner, item_idx, item)
+                })
+                .collect::<Vec<_>>();
 
-        {
-            let query = sqlx::query(
-                "INSERT INTO registration_schema_items(id, 
+            // Read inside the transaction so concurrent additions can't take the same position.
+            if mode == SaveMode::Edit {
+                let mut next_idxs: HashMap<String, usize> = HashMap::new();
+                for (owner, item_idx, _) in items_with_ids.iter_mut() {
+                    let Owner::Event(event_id) = owner else {
+                        continue;
+                    };
+
+                    if !next_idxs.contains_key(event_id) {
+                        let (next_idx,): (i64,) = sqlx::query_as(
+                            "SELECT COALESCE(MAX(idx) + 1, 0) FROM registration_schema_items WHERE event = ?",
+                        )
+                        .bind(&*event_id)
+                        .fetch_one(&mut *conn)
+                        .await
+                        .fetch_err()?;
+
+                        next_idxs.insert(event_id.clone(), next_idx as usize);
+                    }
+
+                    let next_idx = next_idxs.get_mut(event_id).unwrap();
+                    *item_idx = *next_idx;
+                    *next_idx += 1;
+                }
+            }
+
+            let values_clause: String = itertools::Itertools::intersperse(
+                items_with_ids
+                    .iter()
+                    .map(|_| "(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"),
+                ", ",
+            )
+            .collect();
+
+            let query = format!(
+                "INSERT INTO registration_schema_items(
+                    id, 
                     event, 
+                    parent,
                     idx, 
                     name, 
+                    required,
+                    required_after,
+                    blank_on_copy,
+                    display_idx,
                     item_type, 
                     text_type_default, 
                     text_type_display, 
+                    text_type_placeholder,
+                    text_type_prefix,
+                    text_type_suffix,
                     checkbox_type_default, 
-                    select_type_default,
+                    select_type_default, 
                     select_type_display, 
                     multi_select_type_defaults, 
-                    multi_select_type_display 
-                ) VALUES 
-                    (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?),
-                    (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?),
-                    (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?),
-                    (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                    multi_select_type_display,
+                    section_type_body,
+                    tags_type_max_tags
+                ) VALUES {}",
+                values_clause
             );
 
-            // item 1
-            let query = query
-                .bind(&item1_id)
-                .bind(&init.event_1)
-                .bind(0)
-                .bind(item1_name)
-                .bind("TextType")
-                .bind(text_default)
-                .bind(text_display.0)
-                .bind::<Option<i32>>(None)
-                .bind::<Option<i32>>(None)
-                .bind::<Option<String>>(None)
-                .bind::<Option<String>>(None)
-                .bind::<Option<String>>(None);
+            let mut query_builder = sqlx::query(&query);
+            for (owner, item_idx, item) in items_with_ids.iter() {
+                query_builder = values_bind(query_builder, owner, *item_idx, item)?;
+            }
 
-            // item 2
-            let query = query
-                .bind(&item2_id)
-                .bind(&init.event_1)
-                .bind(1)
-                .bind(item2_name)
-                .bind("CheckboxType")
-                .bind::<Option<String>>(None)
-                .bind::<Option<String>>(None)
-                .bind(checkbox_default as i32)
-                .bind::<Option<i32>>(None)
-                .bind::<Option<String>>(None)
-                .bind::<Option<String>>(None)
+            query_builder.execute(&mut *conn).await.insert_err()?;
+
+            items_with_ids
+        } else {
+            Vec::new()
+        };
+
+        if !updates.is_empty() {
+            let values_clause: String = itertools::Itertools::intersperse(
+                updates
+                    .iter()
+                    .map(|_| "(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"),
+                ", ",
+            )
+            .collect();
+
+            // A group's items are always given in full, so their order is kept even in an edit.
+            let idx_clause = match mode {
+                SaveMode::Replace => "idx = mydata.idx,",
+                SaveMode::Edit | SaveMode::Retype => "idx = CASE WHEN mydata.parent IS NULL THEN registration_schema_items.idx ELSE mydata.idx END,",
+            };
+
+            let query = format!(
+                "WITH mydata(
+                    id, 
+                    event,
+                    parent,
+                    idx,
+                    name,
+                    required,
+                    required_after,
+                    blank_on_copy,
+                    display_idx,
+                    item_type,
+                    text_type_default,
+                    text_type_display,
+                    text_type_placeholder,
+                    text_type_prefix,
+                    text_type_suffix,
+                    checkbox_type_default,
+                    select_type_default,
+                    select_type_display,
+                    multi_select_type_defaults,
+                    multi_select_type_display,
+                    section_type_body,
+                    tags_type_max_tags
+                ) AS (VALUES {}) UPDATE registration_schema_items SET 
+                    event = mydata.event,
+                    parent = mydata.parent,
+                    name = mydata.name,
+                    required = mydata.required,
+                    required_after = mydata.required_after,
+                    blank_on_copy = mydata.blank_on_copy,
+                    display_idx = mydata.display_idx,
+                    {}
+                    item_type = mydata.item_type,
+                    text_type_default = mydata.text_type_default,
+                    text_type_display = mydata.text_type_display,
+                    text_type_placeholder = mydata.text_type_placeholder,
+                    text_type_prefix = mydata.text_type_prefix,
+                    text_type_suffix = mydata.text_type_suffix,
+                    checkbox_type_default = mydata.checkbox_type_default,
+                    select_type_default = mydata.select_type_default,
+                    select_type_display = mydata.select_type_display,
+                    multi_select_type_defaults = mydata.multi_select_type_defaults,
+                    multi_select_type_display = mydata.multi_select_type_display,
+                    section_type_body = mydata.section_type_body,
+                    tags_type_max_tags = mydata.tags_type_max_tags
+                FROM mydata WHERE registration_schema_items.id = mydata.id",
+                values_clause, idx_clause
+            );
+
+            let mut query_builder = sqlx::query(&query);
+            for (owner, idx, item) in updates.iter() {
+                query_builder = values_bind(query_builder, owner, *idx, item)?;
+            }
+
+            query_builder.execute(&mut *conn).await.update_err()?;
+        }
+
+        let (inserted, options_from_inserts): (Vec<_>, Vec<_>) = inserted
+            .into_iter()
+            .map(|mut item| {
+                let (_, _, i) = &mut item;
+
+                let options = match i.r#type.as_mut().unwrap().r#type.as_mut().unwrap() {
+                    ItemType::Select(select) => std::mem::take(&mut select.options),
+                    ItemType::MultiSelect(multi_select) => {
+                        std::mem::take(&mut multi_select.options)
+                    }
+                    _ => Vec::new(),
+                };
+
+                let options = options
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, option)| (i.id.clone(), idx, option))
+                    .collect::<Vec<_>>();
+
+                (item, options)
+            })
+            .unzip();
+
+        let insert_options = options_from_inserts
+            .into_iter()
+            .flatten()
+            .chain(insert_options.into_iter())
+            .collect::<Vec<_>>();
+
+        let inserted_options = if !insert_options.is_empty() {
+            let options_with_ids = insert_options
+                .into_iter()
+                .map(|(item_id, idx, mut option)| {
+                    option.id = new_id();
+                    (item_id, idx, option)
+                })
+                .collect::<Vec<_>>();
+
+            let values_clause: String = itertools::Itertools::intersperse(
+                options_with_ids.iter().map(|_| "(?, ?, ?, ?, ?, ?)"),
+                ", ",
+            )
+            .collect();
+
+            let query = format!("INSERT INTO registration_schema_select_options(id, schema_item, idx, name, product_id, archived) VALUES {};", values_clause);
+
+            let mut query_builder = sqlx::query(&query);
+            for (item_id, idx, option) in options_with_ids.iter() {
+                query_builder = option_values_bind(query_builder, item_id, *idx, option)?;
+            }
+
+            query_builder.execute(&mut *conn).await.insert_err()?;
+
+            options_with_ids
+        } else {
+            Vec::new()
+        };
+
+        if !update_options.is_empty() {
+            let values_clause: String = itertools::Itertools::intersperse(
+                update_options.iter().map(|_| "(?, ?, ?, ?, ?, ?)"),
+                ", ",
+            )
+            .collect();
+
+            let query = format!(
+                "WITH mydata(id,
+                    schema_item,
+                    idx,
+                    name,
+                    product_id,
+                    archived
+                ) AS (VALUES {}) UPDATE registration_schema_select_options SET 
+                    schema_item = mydata.schema_item,
+                    idx = mydata.idx,
+                    name = mydata.name,
+                    product_id = mydata.product_id,
+                    archived = mydata.archived
+                FROM mydata WHERE registration_schema_select_options.id = mydata.id",
+                values_clause
+            );
+
+            let mut query_builder = sqlx::query(&query);
+            for (item_id, idx, option) in update_options.iter() {
+                query_builder = option_values_bind(query_builder, item_id, *idx, option)?;
+            }
+
+            query_builder.execute(&mut *conn).await.update_err()?;
+        }
+
+        let schema = items_to_schema(
+            inserted
+                .into_iter()
+                .chain(updates)
+                .map(|(owner, idx, item)| (owner.id().to_owned(), idx, item)),
+            inserted_options
+                .into_iter()
+                .chain(update_options.into_iter()),
+        );
+
+        if !schema.is_empty() {
+            // Edits leave the rest of the schema alone, so only a replacement removes the items it
+            // doesn't name.
+            if mode == SaveMode::Replace {
+                let where_clause = itertools::Itertools::intersperse(
+                    schema.iter().map(|schema| {
+                        let event_clause = itertools::Itertools::intersperse(
+                            std::iter::once("event = ?")
+                                .chain(schema.items.iter().map(|_| "id != ?")),
+                            " AND ",
+                        )
+                        .collect::<String>();
+
+                        format!("({})", event_clause)
+                    }),
+                    " OR ".to_owned(),
+                )
+                .collect::<String>();
+
+                let query = format!(
+                    "DELETE FROM registration_schema_items WHERE {}",
+                    where_clause
+                );
+
+                let query_builder = sqlx::query(&query);
+                let query_builder = schema.iter().fold(query_builder, |query_builder, schema| {
+                    let query_builder = query_builder.bind(&schema.event_id);
+                    schema
+                        .items
+                        .iter()
+                        .fold(query_builder, |query_builder, item| {
+                            query_builder.bind(&item.id)
+                        })
+                });
+
+                query_builder.execute(&mut *conn).await.delete_err()?;
+            }
+
+            // A group's items are always given in full, so any it doesn't name are removed, as are
+            // the items left behind by a group that changed type.
+            let parents_where_clause = itertools::Itertools::intersperse(
+                schema
+                    .iter()
+                    .flat_map(|schema| schema.items.iter())
+                    .map(|item| {
+                        let parent_clause = itertools::Itertools::intersperse(
+                            std::iter::once("parent = ?")
+                                .chain(group_items(item).into_iter().flatten().map(|_| "id != ?")),
+                            " AND ",
+                        )
+                        .collect::<String>();
+
+                        format!("({})", parent_clause)
+                    }),
+                " OR ".to_owned(),
+            )
+            .collect::<String>();
+
+            let parents_query = format!(
+                "DELETE FROM registration_schema_items WHERE {}",
+                parents_where_clause
+            );
+
+            let query_builder = schema.iter().flat_map(|schema| schema.items.iter()).fold(
+                sqlx::query(&parents_query),
+                |query_builder, item| {
+                    group_items(item)
+                        .into_iter()
+                        .flatten()
+                        .fold(query_builder.bind(&item.id), |query_builder, group_item| {
+                            query_builder.bind(&group_item.id)
+                        })
+                },
+            );
+
+            query_builder.execute(&mut *conn).await.delete_err()?;
+
+            let options_where_clause = itertools::Itertools::intersperse(
+                schema
+                    .iter()
+                    .flat_map(|schema| with_group_items(&schema.items))
+                    .map(|item| {
+                        let options: Box<dyn Iterator<Item = &SelectOption>> =
+                            match item.r#type.as_ref().unwrap().r#type.as_ref().unwrap() {
+                                ItemType::Select(select) => Box::new(select.options.iter()),
+                                ItemType::MultiSelect(select) => Box::new(select.options.iter()),
+                                _ => Box::new(std::iter::empty()),
+                            };
+
+                        itertools::Itertools::intersperse(
+                            std::iter::once("schema_item = ?").chain(options.map(|_| "id != ?")),
+                            " AND ",
+                        )
+                        .collect::<String>()
+                    }),
+                " OR ".to_owned(),
+            )
+            .collect::<String>();
+
+            let options_query = format!(
+                "DELETE FROM registration_schema_select_options WHERE {}",
+                options_where_clause
+            );
+
+            let query_builder = sqlx::query(&options_query);
+            let query_builder = schema
+                .iter()
+                .flat_map(|schema| with_group_items(&schema.items))
+                .fold(query_builder, |query_builder, item| {
+                    let query_builder = query_builder.bind(&item.id);
+
+                    let options: Box<dyn Iterator<Item = &SelectOption>> =
+                        match item.r#type.as_ref().unwrap().r#type.as_ref().unwrap() {
+                            ItemType::Select(select) => Box::new(select.options.iter()),
+                            ItemType::MultiSelect(select) => Box::new(select.options.iter()),
+                            _ => Box::new(std::iter::empty()),
+                        };
+
+                    options.fold(query_builder, |query_builder, option| {
+                        query_builder.bind(&option.id)
+                    })
+                });
+
+            query_builder.execute(&mut *conn).await.delete_err()?;
+        };
+
+        let after = item_snapshots(&mut *conn, &updated_ids).await?;
+        record_history(&mut *conn, before, &after, actor).await?;
+
+        Ok(match stored {
+            Some(stored) => changed_items(&stored, schema),
+            None => schema,
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl Store for SqliteStore {
+    async fn upsert(
+        &self,
+        schemas: Vec<RegistrationSchema>,
+    ) -> StoreResult<Vec<RegistrationSchema>> {
+        self.save(schemas, SaveMode::Replace, SaveOutput::Written, None)
+            .await
+    }
+
+    async fn upsert_with_outcomes(
+        &self,
+        schemas: Vec<RegistrationSchema>,
+        actor: Option<&UserId>,
+    ) -> StoreResult<(Vec<RegistrationSchema>, Vec<Outcome>)> {
+        // Any id given has to name a stored item, so the items that come back with an id nobody
+        // asked for are the ones that were inserted.
+        let given_ids = schemas
+            .iter()
+            .flat_map(|schema| with_group_items(&schema.items))
+            .filter(|item| !item.id.is_empty())
+            .map(|item| item.id.clone())
+            .collect::<HashSet<_>>();
+
+        let schemas = self
+            .save(schemas, SaveMode::Replace, SaveOutput::Written, actor)
+            .await?;
+
+        let outcomes = schemas
+            .iter()
+            .flat_map(|schema| with_group_items(&schema.items))
+            .map(|item| Outcome {
+                id: item.id.clone(),
+                created: !given_ids.contains(&item.id),
+            })
+            .collect();
+
+        Ok((schemas, outcomes))
+    }
+
+    async fn upsert_changed(
+        &self,
+        schemas: Vec<RegistrationSchema>,
+        actor: Option<&UserId>,
+    ) -> StoreResult<Vec<RegistrationSchema>> {
+        self.save(schemas, SaveMode::Replace, SaveOutput::Changed, actor)
+            .await
+    }
+
+    async fn query(&self, query: Option<&Query>) -> StoreResult<Vec<RegistrationSchema>> {
+        let mut conn = self.pool.acquire().await.fetch_err()?;
+        self.query_in(&mut conn, query).await
+    }
+
+    async fn query_capped(
+        &self,
+        query: Option<&Query>,
+        cap: u32,
+    ) -> StoreResult<Capped<RegistrationSchema>> {
+        let mut conn = self.pool.acquire().await.fetch_err()?;
+
+        // A schema is made of many item rows, so the cap is applied to the events they belong to.
+        let base_query_string =
+            "SELECT DISTINCT event FROM registration_schema_items WHERE event IS NOT NULL";
+        let query_string = match query {
+            Some(query) => format!(
+                "{} AND ({}) ORDER BY event",
+                base_query_string,
+                query.where_clause()
+            ),
+            None => format!("{} ORDER BY event", base_query_string),
+        };
+
+        let page = Pagination::over_cap(cap);
+        let query_string = append_pagination(query_string, Some(&page));
+
+        let query_builder = sqlx::query_as(&query_string);
+        let query_builder = match query {
+            Some(query) => query.bind(query_builder),
+            None => query_builder,
+        };
+
+        let rows: Vec<(String,)> = page
+            .bind(query_builder)
+            .fetch_all(&mut *conn)
+            .await
+            .fetch_err()?;
+
+        let event_ids = Capped::new(rows.into_iter().map(|(id,)| id).collect(), cap);
+
+        let schemas = self
+            .query_in(
+                &mut conn,
+                Some(&Query::EventId(EventIdQuery::In(event_ids.rows))),
+            )
+            .await?;
+
+        Ok(Capped {
+            rows: schemas,
+            truncated: event_ids.truncated,
+        })
+    }
+
+    async fn delete(&self, event_ids: &[String]) -> StoreResult<()> {
+        if let Some(result) = nothing_to_do(event_ids) {
+            return result;
+        }
+
+        ids_in_table(
+            &*self.pool,
+            "events",
+            event_ids.iter().map(|id| id.as_str()),
+        )
+        .await?;
+
+        let mut tx = self.pool.begin().await.begin_err()?;
+        lock_for_write(&mut tx).await?;
+
+        if let Some(event_id) = self
+            .locked_events(&mut tx, event_ids.iter().map(|id| id.as_str()))
+            .await?
+            .into_iter()
+            .next()
+        {
+            return Err(Error::SchemaLocked(event_id));
+        }
+
+        let where_clause: String =
+            itertools::Itertools::intersperse(event_ids.iter().map(|_| "event = ?"), " OR ")
+                .collect();
+        let query = format!(
+            "DELETE FROM registration_schema_items WHERE {}",
+            where_clause
+        );
+
+        let mut query_builder = sqlx::query(&query);
+
+        for id in event_ids.iter() {
+            query_builder = query_builder.bind(id);
+        }
+
+        query_builder.execute(&mut *tx).await.delete_err()?;
+        tx.commit().await.commit_err()?;
+
+        Ok(())
+    }
+
+    async fn list_item_headers(&self, event_id: &EventId) -> StoreResult<Vec<ItemHeader>> {
+        let rows: Vec<ItemHeaderRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM registration_schema_items WHERE event = ? ORDER BY idx, id",
+            HEADER_COLUMNS
+        ))
+        .bind(event_id)
+        .fetch_all(&*self.pool)
+        .await
+        .fetch_err()?;
+
+        rows.into_iter().map(|row| row.into_header()).collect()
+    }
+
+    async fn add_items(
+        &self,
+        event_id: &EventId,
+        mut items: Vec<RegistrationSchemaItem>,
+        actor: Option<&UserId>,
+    ) -> StoreResult<Vec<RegistrationSchemaItem>> {
+        if let Some(result) = nothing_to_do(&items) {
+            return result;
+        }
+
+        for item in items.iter_mut() {
+            item.id = String::new();
+        }
+
+        let schemas = vec![RegistrationSchema {
+            event_id: event_id.to_string(),
+            items,
+        }];
+
+        self.save(schemas, SaveMode::Edit, SaveOutput::Written, actor)
+            .await?
+            .pop()
+            .map(|schema| schema.items)
+            .ok_or_else(|| Error::IdDoesNotExist(event_id.to_string()))
+    }
+
+    async fn update_items(
+        &self,
+        items: Vec<RegistrationSchemaItem>,
+        actor: Option<&UserId>,
+    ) -> StoreResult<Vec<RegistrationSchema>> {
+        if let Some(result) = nothing_to_do(&items) {
+            return result;
+        }
+
+        ids_in_table(
+            &self.pool,
+            "registration_schema_items",
+            items.iter().map(|item| item.id.as_str()),
+        )
+        .await?;
+
+        let where_clause: String =
+            itertools::Itertools::intersperse(items.iter().map(|_| "id = ?"), " OR ").collect();
+        let query = format!(
+            "SELECT id, event FROM registration_schema_items WHERE event IS NOT NULL AND ({})",
+            where_clause
+        );
+
+        let rows: Vec<(String, String)> = items
+            .iter()
+            .fold(sqlx::query_as(&query), |query_builder, item| {
+                query_builder.bind(&item.id)
+            })
+            .fetch_all(&*self.pool)
+            .await
+            .fetch_err()?;
+        let mut events = rows.into_iter().collect::<HashMap<_, _>>();
+
+        let mut schemas_map: BTreeMap<String, Vec<RegistrationSchemaItem>> = BTreeMap::new();
+        for item in items {
+            let event_id = events
+                .remove(&item.id)
+                .ok_or_else(|| Error::IdDoesNotExist(item.id.clone()))?;
+            schemas_map.entry(event_id).or_default().push(item);
+        }
+
+        let schemas = schemas_map
+            .into_iter()
+            .map(|(event_id, items)| RegistrationSchema { event_id, items })
+            .collect();
+
+        self.save(schemas, SaveMode::Edit, SaveOutput::Written, actor)
+            .await
+    }
+
+    async fn reorder_items(
+        &self,
+        event_id: &EventId,
+        item_ids: &[SchemaItemId],
+    ) -> StoreResult<Vec<ItemHeader>> {
+        ids_in_table(&self.pool, "events", std::iter::once(event_id.as_str())).await?;
+
+        let mut tx = self.pool.begin().await.begin_err()?;
+
+        // Unlisted items are moved past the listed ones, and compacting closes any gaps left by
+        // ids that are no longer in the schema.
+        sqlx::query("UPDATE registration_schema_items SET idx = idx + ? WHERE event = ?")
+            .bind(item_ids.len() as i64)
+            .bind(event_id)
+            .execute(&mut *tx)
+            .await
+            .update_err()?;
+
+        if !item_ids.is_empty() {
+            let values_clause: String =
+                itertools::Itertools::intersperse(item_ids.iter().map(|_| "(?, ?)"), ", ")
+                    .collect();
+
+            let query = format!(
+                "WITH mydata(id, idx) AS (VALUES {})
+                UPDATE registration_schema_items SET idx = mydata.idx
+                FROM mydata WHERE registration_schema_items.id = mydata.id AND registration_schema_items.event = ?",
+                values_clause
+            );
+
+            let query_builder = item_ids
+                .iter()
+                .enumerate()
+                .fold(sqlx::query(&query), |query_builder, (idx, id)| {
+                    query_builder.bind(id).bind(idx as i64)
+                });
+
+            query_builder
+                .bind(event_id)
+                .execute(&mut *tx)
+                .await
+                .update_err()?;
+        }
+
+        compact_idx(
+            &mut tx,
+            "registration_schema_items",
+            "event",
+            std::iter::once(event_id.as_str()),
+        )
+        .await?;
+
+        tx.commit().await.commit_err()?;
+
+        self.list_item_headers(event_id).await
+    }
+
+    async fn rename_item(
+        &self,
+        item_id: &SchemaItemId,
+        name: &str,
+        actor: Option<&UserId>,
+    ) -> StoreResult<ItemHeader> {
+        ids_in_table(
+            &self.pool,
+            "registration_schema_items",
+            std::iter::once(item_id.as_str()),
+        )
+        .await?;
+
+        let mut tx = self.pool.begin().await.begin_err()?;
+        lock_for_write(&mut tx).await?;
+
+        // Items in a group have no event, so their siblings are the group's other items.
+        let siblings: Vec<(String,)> = sqlx::query_as(
+            "SELECT name FROM registration_schema_items
+            WHERE (event = (SELECT event FROM registration_schema_items WHERE id = ?)
+                OR parent = (SELECT parent FROM registration_schema_items WHERE id = ?))
+            AND id != ?",
+        )
+        .bind(item_id)
+        .bind(item_id)
+        .bind(item_id)
+        .fetch_all(&mut *tx)
+        .await
+        .fetch_err()?;
+        if let Some((taken,)) = siblings
+            .into_iter()
+            .find(|(sibling,)| sibling.to_lowercase() == name.to_lowercase())
+        {
+            return Err(Error::DuplicateItemName { name: taken });
+        }
+
+        let before = item_snapshots(&mut tx, &[item_id.as_str()]).await?;
+
+        sqlx::query("UPDATE registration_schema_items SET name = ? WHERE id = ?")
+            .bind(name)
+            .bind(item_id)
+            .execute(&mut *tx)
+            .await
+            .update_err()?;
+
+        let after = item_snapshots(&mut tx, &[item_id.as_str()]).await?;
+        record_history(&mut tx, before, &after, actor).await?;
+        tx.commit().await.commit_err()?;
+
+        let row: ItemHeaderRow = sqlx::query_as(&format!(
+            "SELECT {} FROM registration_schema_items WHERE id = ?",
+            HEADER_COLUMNS
+        ))
+        .bind(item_id)
+        .fetch_one(&*self.pool)
+        .await
+        .fetch_err()?;
+
+        row.into_header()
+    }
+
+    async fn delete_item(&self, item_id: &SchemaItemId) -> StoreResult<ItemHeader> {
+        ids_in_table(
+            &self.pool,
+            "registration_schema_items",
+            std::iter::once(item_id.as_str()),
+        )
+        .await?;
+
+        let row = sqlx::query_as::<_, ItemHeaderRow>(&format!(
+            "SELECT {} FROM registration_schema_items WHERE id = ?",
+            HEADER_COLUMNS
+        ))
+        .bind(item_id)
+        .fetch_one(&*self.pool)
+        .await
+        .fetch_err()?;
+        let parent = row.parent.clone();
+        let header = row.into_header()?;
+
+        let mut tx = self.pool.begin().await.begin_err()?;
+        lock_for_write(&mut tx).await?;
+
+        if !self
+            .locked_events(&mut tx, std::iter::once(header.event_id.as_str()))
+            .await?
+            .is_empty()
+        {
+            return Err(Error::SchemaLocked(header.event_id));
+        }
+
+        sqlx::query("DELETE FROM registration_schema_select_options WHERE schema_item = ?")
+            .bind(item_id)
+            .execute(&mut *tx)
+            .await
+            .delete_err()?;
+
+        sqlx::query("DELETE FROM registration_schema_items WHERE id = ?")
+            .bind(item_id)
+            .execute(&mut *tx)
+            .await
+            .delete_err()?;
+
+        match parent.as_deref() {
+            Some(parent) => {
+                compact_idx(
+                    &mut tx,
+                    "registration_schema_items",
+                    "parent",
+                    std::iter::once(parent),
+                )
+                .await?
+            }
+            None => {
+                compact_idx(
+                    &mut tx,
+                    "registration_schema_items",
+                    "event",
+                    std::iter::once(header.event_id.as_str()),
+                )
+                .await?
+            }
+        }
+
+        tx.commit().await.commit_err()?;
+
+        Ok(header)
+    }
+
+    async fn change_item_type(
+        &self,
+        item_id: &SchemaItemId,
+        mut new_type: RegistrationSchemaItemType,
+        actor: Option<&UserId>,
+    ) -> StoreResult<RegistrationSchema> {
+        ids_in_table(
+            &self.pool,
+            "registration_schema_items",
+            std::iter::once(item_id.as_str()),
+        )
+        .await?;
+
+        // The values check, their rewrite and the item write share one transaction, and the item
+        // is read inside it, so a registration or edit can't land in between.
+        let mut tx = self.pool.begin().await.begin_err()?;
+        lock_for_write(&mut tx).await?;
+
+        let header = sqlx::query_as::<_, ItemHeaderRow>(&format!(
+            "SELECT {} FROM registration_schema_items WHERE id = ?",
+            HEADER_COLUMNS
+        ))
+        .bind(item_id)
+        .fetch_one(&mut *tx)
+        .await
+        .fetch_err()?;
+
+        let mut schema = self
+            .query_in(
+                &mut tx,
+                Some(&Query::EventId(EventIdQuery::Equals(header.event.clone()))),
+            )
+            .await?
+            .pop()
+            .ok_or_else(|| Error::IdDoesNotExist(item_id.to_string()))?;
+
+        // Items inside a group are saved through their group.
+        let top_idx = schema
+            .items
+            .iter()
+            .position(|item| {
+                item.id == item_id.as_str() || Some(item.id.as_str()) == header.parent.as_deref()
+            })
+            .ok_or_else(|| Error::IdDoesNotExist(item_id.to_string()))?;
+        let mut top_item = schema.items.swap_remove(top_idx);
+        let item = match top_item.r#type.as_mut().and_then(|t| t.r#type.as_mut()) {
+            Some(ItemType::Group(group)) if top_item.id != item_id.as_str() => group
+                .items
+                .iter_mut()
+                .find(|item| item.id == item_id.as_str())
+                .ok_or_else(|| Error::IdDoesNotExist(item_id.to_string()))?,
+            _ => &mut top_item,
+        };
+
+        if self.has_values(&mut tx, item_id).await? {
+            let stored_type = item
+                .r#type
+                .as_ref()
+                .and_then(|t| t.r#type.as_ref())
+                .ok_or(Error::ColumnParseError("item_type"))?;
+            let stored_options = match stored_type {
+                ItemType::Select(select) => select.options.clone(),
+                ItemType::MultiSelect(multi_select) => multi_select.options.clone(),
+                _ => Vec::new(),
+            };
+            let unsafe_change = || Error::TypeChangeUnsafe {
+                item_id: item_id.to_string(),
+            };
+
+            match type_change(
+                stored_type,
+                new_type.r#type.as_ref().ok_or_else(unsafe_change)?,
+            ) {
+                TypeChange::Keep => match new_type.r#type.as_mut() {
+                    Some(ItemType::Select(select)) => select.options = stored_options,
+                    Some(ItemType::MultiSelect(multi_select)) => {
+                        multi_select.options = stored_options
+                    }
+                    _ => (),
+                },
+                TypeChange::OptionNames => {
+                    let multi = matches!(stored_type, ItemType::MultiSelect(_));
+                    self.use_option_names(&mut tx, item_id, &stored_options, multi)
+                        .await?
+                }
+                TypeChange::Unsafe => return Err(unsafe_change()),
+            }
+        }
+
+        item.r#type = Some(new_type);
+
+        let mut schemas = vec![RegistrationSchema {
+            event_id: header.event,
+            items: vec![top_item],
+        }];
+        prepare_schemas(&mut schemas)?;
+
+        let schema = self
+            .write(
+                &mut tx,
+                schemas,
+                SaveMode::Retype,
+                SaveOutput::Written,
+                actor,
+            )
+            .await?
+            .pop()
+            .ok_or_else(|| Error::IdDoesNotExist(item_id.to_string()))?;
+        tx.commit().await.commit_err()?;
+
+        Ok(schema)
+    }
+
+    async fn get_item_history(&self, item_id: &SchemaItemId) -> StoreResult<Vec<ItemHistoryEntry>> {
+        sqlx::query_as(
+            "SELECT changed_at, actor, old_name, new_name, old_type, new_type, old_options, new_options
+            FROM registration_schema_item_history WHERE schema_item = ? ORDER BY rowid",
+        )
+        .bind(item_id)
+        .fetch_all(&*self.pool)
+        .await
+        .fetch_err()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{BTreeMap, HashMap},
+        env,
+        path::{Path, PathBuf},
+        str::FromStr,
+        sync::Arc,
+    };
+
+    use common::proto::{
+        item_header, multi_select_type, registration_schema_item_type::Type as ItemType,
+        select_type, text_type, CheckboxType, MultiSelectType, RegistrationSchema,
+        RegistrationSchemaItem, RegistrationSchemaItemType, SelectOption, SelectType, TextType,
+    };
+    use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+    use sqlx::{
+        migrate::MigrateDatabase, sqlite::SqliteConnectOptions, ConnectOptions, Sqlite, SqlitePool,
+    };
+    use tokio::sync::Barrier;
+
+    use crate::store::{
+        common::new_id,
+        connect_options,
+        ids::{EventId, SchemaItemId, UserId},
+        registration_schema::{EnumPolicy, EventIdQuery, OptionRow, Store},
+        CompoundOperator, CompoundQuery, Error, LogicalQuery, Outcome, DEFAULT_BUSY_TIMEOUT,
+    };
+
+    use crate::testing::{
+        checkbox_item, closed_pool, group_item, item, multi_select_item, option, section_item,
+        select_item, tags_item, text_item,
+    };
+
+    use super::{items_to_schema, ItemRow, Query, SqliteStore};
+
+    use test_case::test_case;
+
+    struct Init {
+        event_1: String,
+        event_2: String,
+        db: SqlitePool,
+    }
+
+    async fn init_db() -> Init {
+        let db_url = "sqlite://:memory:";
+        Sqlite::create_database(db_url).await.unwrap();
+
+        let db = SqlitePool::connect_with(
+            SqliteConnectOptions::from_str(db_url)
+                .unwrap()
+                .log_statements(log::LevelFilter::Trace),
+        )
+        .await
+        .unwrap();
+        sqlx::migrate!("../migrations").run(&db).await.unwrap();
+
+        let org_id = new_id();
+        let org_name = "Org 1";
+        sqlx::query("INSERT INTO organizations(id, name) VALUES (?, ?);")
+            .bind(&org_id)
+            .bind(&org_name)
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let id_1 = new_id();
+        let name_1 = "Event 1";
+        let id_2 = new_id();
+        let name_2 = "Event 2";
+        sqlx::query("INSERT INTO events(id, organization, name) VALUES (?, ?, ?), (?, ?, ?);")
+            .bind(&id_1)
+            .bind(&org_id)
+            .bind(&name_1)
+            .bind(&id_2)
+            .bind(&org_id)
+            .bind(&name_2)
+            .execute(&db)
+            .await
+            .unwrap();
+
+        Init {
+            event_1: id_1,
+            event_2: id_2,
+            db,
+        }
+    }
+
+    async fn test_data(init: &Init) -> Vec<RegistrationSchema> {
+        let item1_id = new_id();
+        let item1_name = "item 1";
+        let item2_id = new_id();
+        let item2_name = "item 2";
+        let item3_id = new_id();
+        let item3_name = "item 3";
+        let item4_id = new_id();
+        let item4_name = "item 4";
+        let text_default = "text default";
+        let text_display = ("LARGE", text_type::Display::Large);
+        let checkbox_default = true;
+        let select_default = 1;
+        let select_display = ("DROPDOWN", select_type::Display::Dropdown);
+        let select_option1_id = new_id();
+        let select_option1_name = "option 1";
+        let select_option1_product_id = "product 1";
+        let select_option2_id = new_id();
+        let select_option2_name = "option 2";
+        let select_option2_product_id = "product 2";
+        let select_option3_id = new_id();
+        let select_option3_name = "option 3";
+        let select_option3_product_id = "product 3";
+        let text_default_4 = "text default";
+        let text_display_4 = ("SMALL", text_type::Display::Small);
+
+        {
+            let query = sqlx::query(
+                "INSERT INTO registration_schema_items(id, 
+                    event, 
+                    idx, 
+                    name, 
+                    item_type, 
+                    text_type_default, 
+                    text_type_display, 
+                    checkbox_type_default, 
+                    select_type_default,
+                    select_type_display, 
+                    multi_select_type_defaults, 
+                    multi_select_type_display 
+                ) VALUES 
+                    (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?),
+                    (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?),
+                    (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?),
+                    (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            );
+
+            // item 1
+            let query = query
+                .bind(&item1_id)
+                .bind(&init.event_1)
+                .bind(0)
+                .bind(item1_name)
+                .bind("TextType")
+                .bind(text_default)
+                .bind(text_display.0)
+                .bind::<Option<i32>>(None)
+                .bind::<Option<i32>>(None)
+                .bind::<Option<String>>(None)
+                .bind::<Option<String>>(None)
+                .bind::<Option<String>>(None);
+
+            // item 2
+            let query = query
+                .bind(&item2_id)
+                .bind(&init.event_1)
+                .bind(1)
+                .bind(item2_name)
+                .bind("CheckboxType")
+                .bind::<Option<String>>(None)
+                .bind::<Option<String>>(None)
+                .bind(checkbox_default as i32)
+                .bind::<Option<i32>>(None)
+                .bind::<Option<String>>(None)
+                .bind::<Option<String>>(None)
+                .bind::<Option<String>>(None);
+
+            // item 3
+            let query = query
+                .bind(&item3_id)
+                .bind(&init.event_1)
+                .bind(2)
+                .bind(item3_name)
+                .bind("SelectType")
+                .bind::<Option<String>>(None)
+                .bind::<Option<String>>(None)
+                .bind::<Option<i32>>(None)
+                .bind(select_default)
+                .bind(select_display.0)
+                .bind::<Option<String>>(None)
+                .bind::<Option<String>>(None);
+
+            // item 4
+            let query = query
+                .bind(&item4_id)
+                .bind(&init.event_2)
+                .bind(0)
+                .bind(item4_name)
+                .bind("TextType")
+                .bind(text_default_4)
+                .bind(text_display_4.0)
+                .bind::<Option<i32>>(None)
+                .bind::<Option<i32>>(None)
+                .bind::<Option<String>>(None)
+                .bind::<Option<String>>(None)
                 .bind::<Option<String>>(None);
 
-            // item 3
-            let query = query
-                .bind(&item3_id)
-                .bind(&init.event_1)
-                .bind(2)
-                .bind(item3_name)
-                .bind("SelectType")
-                .bind::<Option<String>>(None)
-                .bind::<Option<String>>(None)
-                .bind::<Option<i32>>(None)
-                .bind(select_default)
-                .bind(select_display.0)
-                .bind::<Option<String>>(None)
-                .bind::<Option<String>>(None);
+            query.execute(&init.db).await.unwrap();
+        }
+        {
+            let query = sqlx::query(
+                "INSERT INTO registration_schema_select_options(id, 
+                    schema_item,
+                    idx,
+                    name,
+                    product_id
+                ) VALUES 
+                    (?, ?, ?, ?, ?),
+                    (?, ?, ?, ?, ?),
+                    (?, ?, ?, ?, ?)",
+            );
+
+            // option 1
+            let query = query
+                .bind(&select_option1_id)
+                .bind(&item3_id)
+                .bind(0)
+                .bind(select_option1_name)
+                .bind(select_option1_product_id);
+
+            // option 2
+            let query = query
+                .bind(&select_option2_id)
+                .bind(&item3_id)
+                .bind(1)
+                .bind(select_option2_name)
+                .bind(select_option2_product_id);
+
+            // option 3
+            let query = query
+                .bind(&select_option3_id)
+                .bind(&item3_id)
+                .bind(2)
+                .bind(select_option3_name)
+                .bind(select_option3_product_id);
+
+            query.execute(&init.db).await.unwrap();
+        }
+
+        let schemas = vec![
+            RegistrationSchema {
+                event_id: init.event_1.clone(),
+                items: vec![
+                    RegistrationSchemaItem {
+                        id: item1_id,
+                        name: item1_name.to_owned(),
+                        r#type: Some(RegistrationSchemaItemType {
+                            r#type: Some(ItemType::Text(TextType {
+                                default: text_default.to_owned(),
+                                display: text_display.1 as i32,
+                                ..Default::default()
+                            })),
+                        }),
+                        required: false,
+                        required_after: "".to_owned(),
+                        blank_on_copy: false,
+                        display_idx: None,
+                    },
+                    RegistrationSchemaItem {
+                        id: item2_id,
+                        name: item2_name.to_owned(),
+                        r#type: Some(RegistrationSchemaItemType {
+                            r#type: Some(ItemType::Checkbox(CheckboxType {
+                                default: checkbox_default,
+                            })),
+                        }),
+                        required: false,
+                        required_after: "".to_owned(),
+                        blank_on_copy: false,
+                        display_idx: None,
+                    },
+                    RegistrationSchemaItem {
+                        id: item3_id,
+                        name: item3_name.to_owned(),
+                        r#type: Some(RegistrationSchemaItemType {
+                            r#type: Some(ItemType::Select(SelectType {
+                                default: select_default,
+                                display: select_display.1 as i32,
+                                options: vec![
+                                    SelectOption {
+                                        id: select_option1_id,
+                                        name: select_option1_name.to_owned(),
+                                        product_id: select_option1_product_id.to_owned(),
+                                        archived: false,
+                                    },
+                                    SelectOption {
+                                        id: select_option2_id,
+                                        name: select_option2_name.to_owned(),
+                                        product_id: select_option2_product_id.to_owned(),
+                                        archived: false,
+                                    },
+                                    SelectOption {
+                                        id: select_option3_id,
+                                        name: select_option3_name.to_owned(),
+                                        product_id: select_option3_product_id.to_owned(),
+                                        archived: false,
+                                    },
+                                ],
+                            })),
+                        }),
+                        required: false,
+                        required_after: "".to_owned(),
+                        blank_on_copy: false,
+                        display_idx: None,
+                    },
+                ],
+            },
+            RegistrationSchema {
+                event_id: init.event_2.clone(),
+                items: vec![RegistrationSchemaItem {
+                    id: item4_id,
+                    name: item4_name.to_owned(),
+                    r#type: Some(RegistrationSchemaItemType {
+                        r#type: Some(ItemType::Text(TextType {
+                            default: text_default_4.to_owned(),
+                            display: text_display_4.1 as i32,
+                            ..Default::default()
+                        })),
+                    }),
+                    required: false,
+                    required_after: "".to_owned(),
+                    blank_on_copy: false,
+                    display_idx: None,
+                }],
+            },
+        ];
+
+        schemas
+    }
+
+    fn sort_schemas(mut schemas: Vec<RegistrationSchema>) -> Vec<RegistrationSchema> {
+        schemas.sort_by(|a, b| a.event_id.cmp(&b.event_id));
+        schemas
+    }
+
+    #[tokio::test]
+    async fn insert() {
+        let init = init_db().await;
+
+        let db_ptr = Arc::new(init.db);
+
+        let store = SqliteStore::new(db_ptr.clone());
+
+        let mut schemas = vec![
+            RegistrationSchema {
+                event_id: init.event_1,
+                items: vec![
+                    RegistrationSchemaItem {
+                        id: "".to_owned(),
+                        name: "field 1".to_owned(),
+                        r#type: Some(RegistrationSchemaItemType {
+                            r#type: Some(ItemType::Text(TextType {
+                                default: "some default".to_owned(),
+                                display: text_type::Display::Small as i32,
+                                ..Default::default()
+                            })),
+                        }),
+                        required: true,
+                        required_after: "".to_owned(),
+                        blank_on_copy: false,
+                        display_idx: None,
+                    },
+                    RegistrationSchemaItem {
+                        id: "".to_owned(),
+                        name: "field 2".to_owned(),
+                        r#type: Some(RegistrationSchemaItemType {
+                            r#type: Some(ItemType::Select(SelectType {
+                                default: 0,
+                                display: select_type::Display::Radio as i32,
+                                options: vec![SelectOption {
+                                    id: "".to_owned(),
+                                    name: "option 1".to_owned(),
+                                    product_id: "product 1".to_owned(),
+                                    archived: false,
+                                }],
+                            })),
+                        }),
+                        required: false,
+                        required_after: "".to_owned(),
+                        blank_on_copy: false,
+                        display_idx: None,
+                    },
+                ],
+            },
+            RegistrationSchema {
+                event_id: init.event_2,
+                items: vec![
+                    RegistrationSchemaItem {
+                        id: "".to_owned(),
+                        name: "field 3".to_owned(),
+                        r#type: Some(RegistrationSchemaItemType {
+                            r#type: Some(ItemType::Checkbox(CheckboxType { default: true })),
+                        }),
+                        required: false,
+                        required_after: "".to_owned(),
+                        blank_on_copy: false,
+                        display_idx: None,
+                    },
+                    RegistrationSchemaItem {
+                        id: "".to_owned(),
+                        name: "field 4".to_owned(),
+                        r#type: Some(RegistrationSchemaItemType {
+                            r#type: Some(ItemType::MultiSelect(MultiSelectType {
+                                defaults: vec![0, 1],
+                                display: multi_select_type::Display::MultiselectBox as i32,
+                                options: vec![
+                                    SelectOption {
+                                        id: "".to_owned(),
+                                        name: "option 2".to_owned(),
+                                        product_id: "product 2".to_owned(),
+                                        archived: false,
+                                    },
+                                    SelectOption {
+                                        id: "".to_owned(),
+                                        name: "option 3".to_owned(),
+                                        product_id: "product 3".to_owned(),
+                                        archived: false,
+                                    },
+                                    SelectOption {
+                                        id: "".to_owned(),
+                                        name: "option 4".to_owned(),
+                                        product_id: "product 4".to_owned(),
+                                        archived: false,
+                                    },
+                                ],
+                            })),
+                        }),
+                        required: false,
+                        required_after: "".to_owned(),
+                        blank_on_copy: false,
+                        display_idx: None,
+                    },
+                ],
+            },
+        ];
+
+        let returned_schemas = store.upsert(schemas.clone()).await.unwrap();
+
+        assert_eq!(schemas.len(), returned_schemas.len());
+
+        let returned_schema_map = returned_schemas
+            .into_iter()
+            .map(|schema| (schema.event_id.clone(), schema))
+            .collect::<HashMap<_, _>>();
+
+        for schema in schemas.iter_mut() {
+            let returned_schema = returned_schema_map.get(&schema.event_id).unwrap();
+
+            for (schema_item, returned_item) in
+                schema.items.iter_mut().zip(returned_schema.items.iter())
+            {
+                schema_item.id = returned_item.id.clone();
+                let options = match schema_item
+                    .r#type
+                    .as_mut()
+                    .unwrap()
+                    .r#type
+                    .as_mut()
+                    .unwrap()
+                {
+                    ItemType::Select(schema_select) => {
+                        if let ItemType::Select(returned_select) = returned_item
+                            .r#type
+                            .as_ref()
+                            .unwrap()
+                            .r#type
+                            .as_ref()
+                            .unwrap()
+                        {
+                            Some((&mut schema_select.options, &returned_select.options))
+                        } else {
+                            panic!()
+                        }
+                    }
+                    ItemType::MultiSelect(schema_select) => {
+                        if let ItemType::MultiSelect(returned_select) = returned_item
+                            .r#type
+                            .as_ref()
+                            .unwrap()
+                            .r#type
+                            .as_ref()
+                            .unwrap()
+                        {
+                            Some((&mut schema_select.options, &returned_select.options))
+                        } else {
+                            panic!()
+                        }
+                    }
+                    _ => None,
+                };
+
+                if let Some((schema_options, returned_options)) = options {
+                    for (schema_option, returned_option) in
+                        schema_options.iter_mut().zip(returned_options.iter())
+                    {
+                        schema_option.id = returned_option.id.clone();
+                    }
+                }
+            }
+
+            assert_eq!(schema, returned_schema);
+        }
+
+        let store_row: Vec<ItemRow> = sqlx::query_as("SELECT * FROM registration_schema_items")
+            .fetch_all(&*db_ptr)
+            .await
+            .unwrap();
+
+        let store_options_row: Vec<OptionRow> =
+            sqlx::query_as("SELECT * FROM registration_schema_select_options")
+                .fetch_all(&*db_ptr)
+                .await
+                .unwrap();
+
+        let store_schemas = items_to_schema(
+            store_row
+                .into_iter()
+                .map(|row| row.into_item(EnumPolicy::Strict).unwrap()),
+            store_options_row
+                .into_iter()
+                .map(|row| row.into_option().unwrap()),
+        );
+
+        assert_eq!(schemas.len(), store_schemas.len());
+
+        let store_schema_map = store_schemas
+            .into_iter()
+            .map(|schema| (schema.event_id.clone(), schema))
+            .collect::<HashMap<_, _>>();
+
+        for schema in schemas.iter() {
+            let store_schema = store_schema_map.get(&schema.event_id).unwrap();
+            assert_eq!(schema, store_schema);
+        }
+    }
+
+    #[tokio::test]
+    async fn update() {
+        let init = init_db().await;
+
+        let mut schemas = test_data(&init).await;
+
+        schemas[0].items.swap(0, 1);
+
+        schemas[0].items[0].name = "item 1 updated".to_owned();
+        schemas[0].items[0].required = true;
+        match schemas[0].items[0]
+            .r#type
+            .as_mut()
+            .unwrap()
+            .r#type
+            .as_mut()
+            .unwrap()
+        {
+            ItemType::Checkbox(checkbox) => checkbox.default = !checkbox.default,
+            _ => panic!("{:?}", schemas[0].items[0]),
+        }
+
+        match schemas[0].items[1]
+            .r#type
+            .as_mut()
+            .unwrap()
+            .r#type
+            .as_mut()
+            .unwrap()
+        {
+            ItemType::Text(text) => text.display = text_type::Display::Small as i32,
+            _ => panic!("{:?}", schemas[0].items[1]),
+        }
+
+        match schemas[0].items[2]
+            .r#type
+            .as_mut()
+            .unwrap()
+            .r#type
+            .as_mut()
+            .unwrap()
+        {
+            ItemType::Select(select) => {
+                select.default = 0;
+                select.options.swap(0, 1);
+                select.options[1].product_id = "updated product id".to_owned();
+                select.options.remove(2);
+            }
+            _ => panic!("{:?}", schemas[0].items[2]),
+        }
+
+        let db_ptr = Arc::new(init.db);
+        let store = SqliteStore::new(db_ptr.clone());
+
+        let returned_schemas = store.upsert(schemas.clone()).await.unwrap();
+
+        assert_eq!(schemas.len(), returned_schemas.len());
+        let returned_schema_map = returned_schemas
+            .into_iter()
+            .map(|schema| (schema.event_id.clone(), schema))
+            .collect::<HashMap<_, _>>();
+
+        for schema in schemas.iter_mut() {
+            let returned_schema = returned_schema_map.get(&schema.event_id).unwrap();
+            assert_eq!(schema, returned_schema);
+        }
+
+        let store_row: Vec<ItemRow> = sqlx::query_as("SELECT * FROM registration_schema_items")
+            .fetch_all(&*db_ptr)
+            .await
+            .unwrap();
+
+        let store_options_row: Vec<OptionRow> =
+            sqlx::query_as("SELECT * FROM registration_schema_select_options")
+                .fetch_all(&*db_ptr)
+                .await
+                .unwrap();
+
+        let store_schemas = items_to_schema(
+            store_row
+                .into_iter()
+                .map(|row| row.into_item(EnumPolicy::Strict).unwrap()),
+            store_options_row
+                .into_iter()
+                .map(|row| row.into_option().unwrap()),
+        );
+
+        assert_eq!(schemas.len(), store_schemas.len());
+
+        let store_schema_map = store_schemas
+            .into_iter()
+            .map(|schema| (schema.event_id.clone(), schema))
+            .collect::<HashMap<_, _>>();
+
+        for schema in schemas.iter() {
+            let store_schema = store_schema_map.get(&schema.event_id).unwrap();
+            assert_eq!(schema, store_schema);
+        }
+    }
+
+    #[tokio::test]
+    async fn section_round_trip() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let schema = RegistrationSchema {
+            event_id: init.event_1.clone(),
+            items: vec![
+                section_item("About you", "Tell us a *little* about yourself."),
+                text_item("field 1"),
+            ],
+        };
+
+        let mut returned_schemas = store.upsert(vec![schema.clone()]).await.unwrap();
+        assert_eq!(returned_schemas.len(), 1);
+        let returned_schema = returned_schemas.pop().unwrap();
+
+        let mut expected = schema;
+        for (item, returned_item) in expected.items.iter_mut().zip(returned_schema.items.iter()) {
+            item.id = returned_item.id.clone();
+        }
+        assert_eq!(expected, returned_schema);
+
+        let queried = store
+            .query(Some(&Query::EventId(LogicalQuery::Equals(
+                init.event_1.clone(),
+            ))))
+            .await
+            .unwrap();
+        assert_eq!(queried, vec![expected]);
+    }
+
+    #[tokio::test]
+    async fn tags_round_trip() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let schema = RegistrationSchema {
+            event_id: init.event_1.clone(),
+            items: vec![tags_item("Dietary needs", 3), tags_item("Dance styles", 0)],
+        };
+
+        let mut returned_schemas = store.upsert(vec![schema.clone()]).await.unwrap();
+        assert_eq!(returned_schemas.len(), 1);
+        let returned_schema = returned_schemas.pop().unwrap();
+
+        let mut expected = schema;
+        for (item, returned_item) in expected.items.iter_mut().zip(returned_schema.items.iter()) {
+            item.id = returned_item.id.clone();
+        }
+        assert_eq!(expected, returned_schema);
+
+        let queried = store
+            .query(Some(&Query::EventId(LogicalQuery::Equals(
+                init.event_1.clone(),
+            ))))
+            .await
+            .unwrap();
+        assert_eq!(queried, vec![expected]);
+    }
+
+    fn adorned_text_item(placeholder: &str, prefix: &str, suffix: &str) -> RegistrationSchemaItem {
+        item(
+            "price",
+            ItemType::Text(TextType {
+                default: "".to_owned(),
+                display: text_type::Display::Small as i32,
+                placeholder: placeholder.to_owned(),
+                prefix: prefix.to_owned(),
+                suffix: suffix.to_owned(),
+            }),
+        )
+    }
+
+    #[tokio::test]
+    async fn text_adornments_round_trip() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let mut schema = store
+            .upsert(vec![RegistrationSchema {
+                event_id: init.event_1.clone(),
+                items: vec![adorned_text_item("20.00", "$", "USD")],
+            }])
+            .await
+            .unwrap()
+            .remove(0);
+        assert_eq!(
+            schema.items[0],
+            RegistrationSchemaItem {
+                id: schema.items[0].id.clone(),
+                ..adorned_text_item("20.00", "$", "USD")
+            }
+        );
+        assert_eq!(store.query(None).await.unwrap(), vec![schema.clone()]);
+
+        schema.items[0] = RegistrationSchemaItem {
+            id: schema.items[0].id.clone(),
+            ..adorned_text_item("", "", "kg")
+        };
+        store.upsert(vec![schema.clone()]).await.unwrap();
+        assert_eq!(store.query(None).await.unwrap(), vec![schema]);
+    }
+
+    #[tokio::test]
+    async fn text_adornments_missing() {
+        let init = init_db().await;
+
+        // Rows saved before adornments were added have no values for them.
+        sqlx::query(
+            "INSERT INTO registration_schema_items(id, event, idx, name, item_type, text_type_default, text_type_display) VALUES (?, ?, ?, ?, ?, ?, ?);",
+        )
+        .bind("item")
+        .bind(&init.event_1)
+        .bind(0)
+        .bind("price")
+        .bind("TextType")
+        .bind("")
+        .bind("SMALL")
+        .execute(&init.db)
+        .await
+        .unwrap();
+
+        let store = SqliteStore::new(Arc::new(init.db));
+        let schemas = store.query(None).await.unwrap();
+        assert_eq!(
+            schemas[0].items,
+            vec![RegistrationSchemaItem {
+                id: "item".to_owned(),
+                ..adorned_text_item("", "", "")
+            }]
+        );
+    }
+
+    #[test_case(EnumPolicy::Strict ; "strict")]
+    #[test_case(EnumPolicy::Lenient ; "lenient")]
+    #[tokio::test]
+    async fn unknown_display(enum_policy: EnumPolicy) {
+        let init = init_db().await;
+
+        // As if written by a newer server with a display this version doesn't know, and whose
+        // migrations have relaxed the CHECK constraint.
+        let mut conn = init.db.acquire().await.unwrap();
+        sqlx::query("PRAGMA ignore_check_constraints = ON")
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO registration_schema_items(id, event, idx, name, item_type, text_type_default, text_type_display) VALUES (?, ?, ?, ?, ?, ?, ?);",
+        )
+        .bind("item")
+        .bind(&init.event_1)
+        .bind(0)
+        .bind("price")
+        .bind("TextType")
+        .bind("")
+        .bind("MARQUEE")
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+        sqlx::query("PRAGMA ignore_check_constraints = OFF")
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        drop(conn);
+
+        let store = SqliteStore::new(Arc::new(init.db)).with_enum_policy(enum_policy);
+        let result = store.query(None).await;
+
+        match (enum_policy, result) {
+            (EnumPolicy::Strict, Err(Error::UnknownEnumValue { column, value })) => {
+                assert_eq!(column, "text_type_display");
+                assert_eq!(value, "MARQUEE");
+            }
+            (EnumPolicy::Strict, result) => {
+                panic!("expected UnknownEnumValue, got {:?}", result)
+            }
+            (EnumPolicy::Lenient, result) => assert_eq!(
+                result.unwrap()[0].items,
+                vec![RegistrationSchemaItem {
+                    id: "item".to_owned(),
+                    ..adorned_text_item("", "", "")
+                }]
+            ),
+        }
+    }
+
+    #[tokio::test]
+    async fn delete_compacts_idx() {
+        let init = init_db().await;
+        let mut schemas = test_data(&init).await;
+
+        let mut schema = schemas.remove(0);
+        schema.items.remove(1);
+        match schema.items[1]
+            .r#type
+            .as_mut()
+            .unwrap()
+            .r#type
+            .as_mut()
+            .unwrap()
+        {
+            ItemType::Select(select) => {
+                select.options.remove(1);
+            }
+            _ => panic!("{:?}", schema.items[1]),
+        }
+        let select_id = schema.items[1].id.clone();
+
+        let db_ptr = Arc::new(init.db);
+        let store = SqliteStore::new(db_ptr.clone());
+        store.upsert(vec![schema]).await.unwrap();
+
+        let item_idxs: Vec<(i32,)> = sqlx::query_as(
+            "SELECT idx FROM registration_schema_items WHERE event = ? ORDER BY idx",
+        )
+        .bind(&init.event_1)
+        .fetch_all(&*db_ptr)
+        .await
+        .unwrap();
+        assert_eq!(item_idxs, vec![(0,), (1,)]);
+
+        let option_idxs: Vec<(i32,)> = sqlx::query_as(
+            "SELECT idx FROM registration_schema_select_options WHERE schema_item = ? ORDER BY idx",
+        )
+        .bind(&select_id)
+        .fetch_all(&*db_ptr)
+        .await
+        .unwrap();
+        assert_eq!(option_idxs, vec![(0,), (1,)]);
+    }
+
+    #[tokio::test]
+    async fn delete_item_compacts_idx() {
+        let init = init_db().await;
+
+        // Gaps like these were left behind by deletes before compaction was added.
+        let ids = [new_id(), new_id(), new_id()];
+        for (id, idx) in ids.iter().zip([0, 3, 7]) {
+            sqlx::query(
+                "INSERT INTO registration_schema_items(id, event, idx, name, item_type, checkbox_type_default) VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(id)
+            .bind(&init.event_1)
+            .bind(idx)
+            .bind(format!("item {}", idx))
+            .bind("CheckboxType")
+            .bind(0)
+            .execute(&init.db)
+            .await
+            .unwrap();
+        }
+
+        let db_ptr = Arc::new(init.db);
+        let store = SqliteStore::new(db_ptr.clone());
+        store
+            .delete_item(&SchemaItemId::from(&ids[1]))
+            .await
+            .unwrap();
+
+        let item_idxs: Vec<(String, i32)> = sqlx::query_as(
+            "SELECT id, idx FROM registration_schema_items WHERE event = ? ORDER BY idx",
+        )
+        .bind(&init.event_1)
+        .fetch_all(&*db_ptr)
+        .await
+        .unwrap();
+        assert_eq!(item_idxs, vec![(ids[0].clone(), 0), (ids[2].clone(), 1)]);
+    }
+
+    #[tokio::test]
+    async fn duplicate_idx() {
+        let init = init_db().await;
+        let mut ids = vec![new_id(), new_id()];
+        ids.sort();
+
+        for (id, name) in ids.iter().zip(["first", "second"]) {
+            sqlx::query(
+                "INSERT INTO registration_schema_items(id, event, idx, name, item_type, checkbox_type_default) VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(id)
+            .bind(&init.event_1)
+            .bind(0)
+            .bind(name)
+            .bind("CheckboxType")
+            .bind(0)
+            .execute(&init.db)
+            .await
+            .unwrap();
+        }
+
+        let db_ptr = Arc::new(init.db);
+        let store = SqliteStore::new(db_ptr.clone());
+        let mut schemas = store
+            .query(Some(&Query::EventId(LogicalQuery::Equals(
+                init.event_1.clone(),
+            ))))
+            .await
+            .unwrap();
+        assert_eq!(schemas.len(), 1);
+        assert_eq!(
+            schemas[0]
+                .items
+                .iter()
+                .map(|item| item.id.clone())
+                .collect::<Vec<_>>(),
+            ids
+        );
+
+        store.upsert(vec![schemas.remove(0)]).await.unwrap();
+
+        let item_idxs: Vec<(String, i32)> = sqlx::query_as(
+            "SELECT id, idx FROM registration_schema_items WHERE event = ? ORDER BY idx",
+        )
+        .bind(&init.event_1)
+        .fetch_all(&*db_ptr)
+        .await
+        .unwrap();
+        assert_eq!(item_idxs, vec![(ids[0].clone(), 0), (ids[1].clone(), 1)]);
+    }
+
+    #[tokio::test]
+    async fn list_item_headers() {
+        let init = init_db().await;
+        let schemas = test_data(&init).await;
+
+        // The headers must not touch the options table at all.
+        sqlx::query("DROP TABLE registration_schema_select_options")
+            .execute(&init.db)
+            .await
+            .unwrap();
+
+        let store = SqliteStore::new(Arc::new(init.db));
+        let headers = store
+            .list_item_headers(&EventId::from(&init.event_1))
+            .await
+            .unwrap();
+
+        let expected = schemas[0]
+            .items
+            .iter()
+            .enumerate()
+            .map(|(idx, item)| (item.id.clone(), item.name.clone(), idx as u32))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            headers
+                .iter()
+                .map(|h| (h.id.clone(), h.name.clone(), h.idx))
+                .collect::<Vec<_>>(),
+            expected
+        );
+        assert_eq!(headers[2].type_kind, item_header::TypeKind::Select as i32);
+    }
+
+    enum OptionNameTest {
+        Duplicate,
+        Distinct,
+        SharedAcrossItems,
+    }
+    #[test_case(OptionNameTest::Duplicate ; "duplicate")]
+    #[test_case(OptionNameTest::Distinct ; "distinct")]
+    #[test_case(OptionNameTest::SharedAcrossItems ; "shared across items")]
+    #[tokio::test]
+    async fn option_names_unique(test_name: OptionNameTest) {
+        let init = init_db().await;
+
+        let select = |names: &[&str]| select_item(&names.join("/"), names);
+
+        let (items, expect_error) = match test_name {
+            OptionNameTest::Duplicate => (vec![select(&["Large", "large "])], true),
+            OptionNameTest::Distinct => (vec![select(&["Large", "Small"])], false),
+            OptionNameTest::SharedAcrossItems => {
+                (vec![select(&["Large", "Small"]), select(&["Large"])], false)
+            }
+        };
+
+        let store = SqliteStore::new(Arc::new(init.db));
+        let result = store
+            .upsert(vec![RegistrationSchema {
+                event_id: init.event_1.clone(),
+                items,
+            }])
+            .await;
+
+        match (expect_error, result) {
+            (false, Ok(_)) => (),
+            (true, Err(Error::DuplicateOptionName { name, .. })) => assert_eq!(name, "large"),
+            (_, result) => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test_case("", Some("") ; "unset")]
+    #[test_case("2024-03-01T12:00:00+01:00", Some("2024-03-01T11:00:00Z") ; "normalized to utc")]
+    #[test_case("next week", None ; "invalid")]
+    #[tokio::test]
+    async fn required_after(required_after: &str, expected: Option<&str>) {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let result = store
+            .upsert(vec![RegistrationSchema {
+                event_id: init.event_1.clone(),
+                items: vec![RegistrationSchemaItem {
+                    required_after: required_after.to_owned(),
+                    ..text_item("travel")
+                }],
+            }])
+            .await;
+
+        match (expected, result) {
+            (Some(expected), Ok(_)) => {
+                let schemas = store.query(None).await.unwrap();
+                assert_eq!(schemas[0].items[0].required_after, expected);
+            }
+            (None, Err(Error::InvalidTimestamp(timestamp))) => {
+                assert_eq!(timestamp, required_after)
+            }
+            (_, result) => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[derive(Debug)]
+    enum ItemTypeTest {
+        Consistent,
+        SelectWithoutOptions,
+        SelectDefaultMissing,
+        MultiSelectDefaultMissing,
+        MissingType,
+    }
+
+    #[test_case(ItemTypeTest::Consistent ; "consistent")]
+    #[test_case(ItemTypeTest::SelectWithoutOptions ; "select without options")]
+    #[test_case(ItemTypeTest::SelectDefaultMissing ; "select default missing")]
+    #[test_case(ItemTypeTest::MultiSelectDefaultMissing ; "multi select default missing")]
+    #[test_case(ItemTypeTest::MissingType ; "missing type")]
+    #[tokio::test]
+    async fn inconsistent_item_type(test_name: ItemTypeTest) {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let options = |count: usize| {
+            (0..count)
+                .map(|idx| SelectOption {
+                    id: "".to_owned(),
+                    name: format!("option {}", idx),
+                    product_id: "".to_owned(),
+                    archived: false,
+                })
+                .collect::<Vec<_>>()
+        };
+        let select = |default: u32, option_count: usize| {
+            ItemType::Select(SelectType {
+                default,
+                display: select_type::Display::Radio as i32,
+                options: options(option_count),
+            })
+        };
+
+        let (typ, expect_error) = match test_name {
+            ItemTypeTest::Consistent => (Some(select(1, 2)), false),
+            ItemTypeTest::SelectWithoutOptions => (Some(select(0, 0)), false),
+            ItemTypeTest::SelectDefaultMissing => (Some(select(2, 2)), true),
+            ItemTypeTest::MultiSelectDefaultMissing => (
+                Some(ItemType::MultiSelect(MultiSelectType {
+                    defaults: vec![0, 2],
+                    display: multi_select_type::Display::Checkboxes as i32,
+                    options: options(2),
+                })),
+                true,
+            ),
+            ItemTypeTest::MissingType => (None, true),
+        };
+
+        let result = store
+            .upsert(vec![RegistrationSchema {
+                event_id: init.event_1.clone(),
+                items: vec![RegistrationSchemaItem {
+                    r#type: Some(RegistrationSchemaItemType { r#type: typ }),
+                    ..checkbox_item("item")
+                }],
+            }])
+            .await;
+
+        match (expect_error, result) {
+            (false, Ok(_)) => (),
+            (true, Err(Error::InconsistentItemType { item_id })) => assert_eq!(item_id, ""),
+            (_, result) => panic!("unexpected result: {:?}", result),
+        }
+
+        if expect_error {
+            assert!(store.query(None).await.unwrap().is_empty());
+        }
+    }
+
+    enum MultiSelectDefaultsTest {
+        Normalized,
+        OutOfRange,
+    }
+
+    #[test_case(MultiSelectDefaultsTest::Normalized ; "normalized")]
+    #[test_case(MultiSelectDefaultsTest::OutOfRange ; "out of range")]
+    #[tokio::test]
+    async fn multi_select_defaults(test_name: MultiSelectDefaultsTest) {
+        let init = init_db().await;
+        let db = Arc::new(init.db);
+        let store = SqliteStore::new(db.clone());
+
+        let option_count = match test_name {
+            MultiSelectDefaultsTest::Normalized => 3,
+            MultiSelectDefaultsTest::OutOfRange => 2,
+        };
+
+        let result = store
+            .upsert(vec![RegistrationSchema {
+                event_id: init.event_1.clone(),
+                items: vec![RegistrationSchemaItem {
+                    id: "".to_owned(),
+                    name: "item".to_owned(),
+                    r#type: Some(RegistrationSchemaItemType {
+                        r#type: Some(ItemType::MultiSelect(MultiSelectType {
+                            defaults: vec![2, 0, 0],
+                            display: multi_select_type::Display::Checkboxes as i32,
+                            options: (0..option_count)
+                                .map(|idx| SelectOption {
+                                    id: "".to_owned(),
+                                    name: format!("option {}", idx),
+                                    product_id: "".to_owned(),
+                                    archived: false,
+                                })
+                                .collect(),
+                        })),
+                    }),
+                    required: false,
+                    required_after: "".to_owned(),
+                    blank_on_copy: false,
+                    display_idx: None,
+                }],
+            }])
+            .await;
+
+        if let MultiSelectDefaultsTest::OutOfRange = test_name {
+            match result {
+                Err(Error::InconsistentItemType { .. }) => (),
+                result => panic!("unexpected result: {:?}", result),
+            }
+            return;
+        }
+
+        let returned_defaults = match result.unwrap()[0].items[0].r#type.as_ref() {
+            Some(RegistrationSchemaItemType {
+                r#type: Some(ItemType::MultiSelect(multi_select)),
+            }) => multi_select.defaults.clone(),
+            typ => panic!("unexpected type: {:?}", typ),
+        };
+        assert_eq!(returned_defaults, vec![0, 2]);
+
+        let (stored,): (String,) =
+            sqlx::query_as("SELECT multi_select_type_defaults FROM registration_schema_items")
+                .fetch_one(&*db)
+                .await
+                .unwrap();
+        assert_eq!(stored, "0,2");
+
+        let schemas = store.query(None).await.unwrap();
+        match schemas[0].items[0].r#type.as_ref() {
+            Some(RegistrationSchemaItemType {
+                r#type: Some(ItemType::MultiSelect(multi_select)),
+            }) => assert_eq!(multi_select.defaults, vec![0, 2]),
+            typ => panic!("unexpected type: {:?}", typ),
+        }
+    }
+
+    enum MaxOptionsTest {
+        AtLimit,
+        OverLimit,
+        UpdateExisting,
+    }
+    #[test_case(MaxOptionsTest::AtLimit ; "at cap")]
+    #[test_case(MaxOptionsTest::OverLimit ; "over cap")]
+    #[test_case(MaxOptionsTest::UpdateExisting ; "update existing over cap")]
+    #[tokio::test]
+    async fn max_options(test_name: MaxOptionsTest) {
+        let init = init_db().await;
+        let mut schema = test_data(&init).await.remove(0);
+
+        let (max_options, expect_error) = match test_name {
+            MaxOptionsTest::AtLimit => {
+                schema
+                    .items
+                    .push(select_item("new select", &["a", "b", "c"]));
+                (3, false)
+            }
+            MaxOptionsTest::OverLimit => {
+                match schema.items[2]
+                    .r#type
+                    .as_mut()
+                    .unwrap()
+                    .r#type
+                    .as_mut()
+                    .unwrap()
+                {
+                    ItemType::Select(select) => select.options.push(option("option 4")),
+                    _ => panic!("{:?}", schema.items[2]),
+                }
+                (3, true)
+            }
+            MaxOptionsTest::UpdateExisting => {
+                schema.items[2].name = "renamed".to_owned();
+                (2, false)
+            }
+        };
+
+        let store = SqliteStore::new(Arc::new(init.db)).with_max_options(max_options);
+        let result = store.upsert(vec![schema]).await;
+
+        match (expect_error, result) {
+            (false, Ok(_)) => (),
+            (true, Err(Error::TooManyOptions { max })) => assert_eq!(max, max_options),
+            (_, result) => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    enum UpdateDoesNotExistTests {
+        BadEventId,
+        BadItemId,
+        BadOptionId,
+    }
+    #[test_case(UpdateDoesNotExistTests::BadEventId ; "bad event id")]
+    #[test_case(UpdateDoesNotExistTests::BadItemId ; "bad item id")]
+    #[test_case(UpdateDoesNotExistTests::BadOptionId ; "bad option id")]
+    #[tokio::test]
+    async fn update_does_not_exist(test_name: UpdateDoesNotExistTests) {
+        let init = init_db().await;
+        let test_data = test_data(&init).await;
+
+        struct TestCase {
+            id: String,
+            schema: RegistrationSchema,
+        }
+        let tc = match test_name {
+            UpdateDoesNotExistTests::BadEventId => {
+                let id = new_id();
+                TestCase {
+                    id: id.clone(),
+                    schema: RegistrationSchema {
+                        event_id: id.clone(),
+                        items: Vec::new(),
+                    },
+                }
+            }
+            UpdateDoesNotExistTests::BadItemId => {
+                let id = new_id();
+                let mut schema = test_data[0].clone();
+                schema.items[0].id = id.clone();
+                TestCase { id, schema }
+            }
+            UpdateDoesNotExistTests::BadOptionId => {
+                let id = new_id();
+                let mut schema = test_data[0].clone();
+                match schema.items[2]
+                    .r#type
+                    .as_mut()
+                    .unwrap()
+                    .r#type
+                    .as_mut()
+                    .unwrap()
+                {
+                    ItemType::Select(select) => select.options[0].id = id.clone(),
+                    _ => panic!(),
+                }
+                TestCase { id, schema }
+            }
+        };
+
+        let store = SqliteStore::new(Arc::new(init.db));
+        let result = store.upsert(vec![tc.schema]).await;
+        match result {
+            Ok(_) => panic!("no error returned"),
+            Err(Error::IdDoesNotExist(err_id)) => assert_eq!(err_id, tc.id),
+            _ => panic!("incorrect error type: {:?}", result),
+        };
+    }
 
-            // item 4
-            let query = query
-                .bind(&item4_id)
-                .bind(&init.event_2)
-                .bind(0)
-                .bind(item4_name)
-                .bind("TextType")
-                .bind(text_default_4)
-                .bind(text_display_4.0)
-                .bind::<Option<i32>>(None)
-                .bind::<Option<i32>>(None)
-                .bind::<Option<String>>(None)
-                .bind::<Option<String>>(None)
-                .bind::<Option<String>>(None);
+    enum QueryTest {
+        All,
+        EventId,
+        NoOptions,
+        CompoundQuery,
+        NoResults,
+        EmptyIn,
+    }
+
+    #[test_case(QueryTest::All ; "all")]
+    #[test_case(QueryTest::EventId ; "event id")]
+    #[test_case(QueryTest::NoOptions ; "no options")]
+    #[test_case(QueryTest::CompoundQuery ; "compound query")]
+    #[test_case(QueryTest::NoResults ; "no results")]
+    #[test_case(QueryTest::EmptyIn ; "empty in")]
+    #[tokio::test]
+    async fn query(test_name: QueryTest) {
+        let init = init_db().await;
+        let schemas = test_data(&init).await;
+
+        struct TestCase {
+            query: Option<Query>,
+            expected: Vec<RegistrationSchema>,
+        }
+
+        let tc = match test_name {
+            QueryTest::All => TestCase {
+                query: None,
+                expected: schemas,
+            },
+            QueryTest::EventId => TestCase {
+                query: Some(Query::EventId(LogicalQuery::Equals(init.event_1.clone()))),
+                expected: vec![schemas[0].clone()],
+            },
+            QueryTest::NoOptions => TestCase {
+                query: Some(Query::EventId(LogicalQuery::Equals(init.event_2.clone()))),
+                expected: vec![schemas[1].clone()],
+            },
+            QueryTest::CompoundQuery => TestCase {
+                query: Some(Query::Compound(CompoundQuery {
+                    operator: CompoundOperator::Or,
+                    queries: schemas
+                        .iter()
+                        .map(|s| Query::EventId(LogicalQuery::Equals(s.event_id.clone())))
+                        .collect(),
+                })),
+                expected: schemas,
+            },
+            QueryTest::NoResults => TestCase {
+                query: Some(Query::EventId(LogicalQuery::Equals(new_id()))),
+                expected: Vec::new(),
+            },
+            QueryTest::EmptyIn => TestCase {
+                query: Some(Query::EventId(LogicalQuery::In(Vec::new()))),
+                expected: Vec::new(),
+            },
+        };
+
+        let store = SqliteStore::new(Arc::new(init.db));
+        let returned_schemas = store.query(tc.query.as_ref()).await.unwrap();
+        let expected = sort_schemas(tc.expected);
+        assert_eq!(expected, returned_schemas);
+    }
+
+    #[tokio::test]
+    async fn query_event_id_in() {
+        let init = init_db().await;
+        let mut schemas = test_data(&init).await;
+
+        let (organization,): (String,) = sqlx::query_as("SELECT organization FROM events LIMIT 1")
+            .fetch_one(&init.db)
+            .await
+            .unwrap();
+        let event_3 = new_id();
+        let event_4 = new_id();
+        sqlx::query("INSERT INTO events(id, organization, name) VALUES (?, ?, ?), (?, ?, ?);")
+            .bind(&event_3)
+            .bind(&organization)
+            .bind("Event 3")
+            .bind(&event_4)
+            .bind(&organization)
+            .bind("Event 4")
+            .execute(&init.db)
+            .await
+            .unwrap();
+
+        let store = SqliteStore::new(Arc::new(init.db));
+        let added = store
+            .upsert(vec![
+                RegistrationSchema {
+                    event_id: event_3.clone(),
+                    items: vec![checkbox_item("item 3a"), checkbox_item("item 3b")],
+                },
+                RegistrationSchema {
+                    event_id: event_4,
+                    items: vec![checkbox_item("item 4")],
+                },
+            ])
+            .await
+            .unwrap();
+        // Upserted schemas come back in event id order, not request order.
+        schemas.extend(added.into_iter().filter(|s| s.event_id == event_3));
+
+        let returned_schemas = store
+            .query(Some(&Query::EventId(LogicalQuery::In(vec![
+                init.event_1,
+                init.event_2,
+                event_3,
+            ]))))
+            .await
+            .unwrap();
+
+        assert_eq!(returned_schemas.len(), 3);
+        assert_eq!(sort_schemas(schemas), returned_schemas);
+    }
+
+    #[test_case(1, true ; "over cap")]
+    #[test_case(2, false ; "at cap")]
+    #[tokio::test]
+    async fn query_capped(cap: u32, truncated: bool) {
+        let init = init_db().await;
+        let mut schemas = sort_schemas(test_data(&init).await);
+        schemas.truncate(cap as usize);
+
+        let store = SqliteStore::new(Arc::new(init.db));
+        let capped = store.query_capped(None, cap).await.unwrap();
+        assert_eq!(capped.rows, schemas);
+        assert_eq!(capped.truncated, truncated);
+    }
+
+    #[tokio::test]
+    async fn delete_one() {
+        let init = init_db().await;
+        let _ = test_data(&init).await;
+
+        let db_ptr = Arc::new(init.db);
+        let store = SqliteStore::new(db_ptr.clone());
+
+        store.delete(&[init.event_1]).await.unwrap();
+
+        let event_ids: Vec<(String,)> =
+            sqlx::query_as("SELECT DISTINCT event FROM registration_schema_items")
+                .fetch_all(&*db_ptr)
+                .await
+                .unwrap();
+
+        assert_eq!(event_ids.len(), 1);
+        assert_eq!(event_ids[0].0, init.event_2);
+
+        let (object_count,): (u32,) =
+            sqlx::query_as("SELECT COUNT(*) FROM registration_schema_select_options")
+                .fetch_one(&*db_ptr)
+                .await
+                .unwrap();
+
+        assert_eq!(object_count, 0);
+    }
+
+    async fn stored_schema(store: &SqliteStore, event_id: &str) -> RegistrationSchema {
+        store
+            .query(Some(&Query::EventId(EventIdQuery::Equals(
+                event_id.to_owned(),
+            ))))
+            .await
+            .unwrap()
+            .remove(0)
+    }
+
+    #[tokio::test]
+    async fn item_names_unique() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let result = store
+            .upsert(vec![RegistrationSchema {
+                event_id: init.event_1.clone(),
+                items: vec![checkbox_item("Waiver"), checkbox_item("waiver")],
+            }])
+            .await;
+        match result {
+            Err(Error::DuplicateItemName { name }) => assert_eq!(name, "waiver"),
+            _ => panic!("unexpected result: {:?}", result),
+        }
+
+        let schema = store
+            .upsert(vec![RegistrationSchema {
+                event_id: init.event_1.clone(),
+                items: vec![checkbox_item("Waiver"), checkbox_item("Photo release")],
+            }])
+            .await
+            .unwrap()
+            .remove(0);
+
+        // Edits are checked against the items they don't touch.
+        let mut renamed = schema.items[1].clone();
+        renamed.name = "WAIVER".to_owned();
+        let result = store.update_items(vec![renamed.clone()], None).await;
+        match result {
+            Err(Error::DuplicateItemName { name }) => assert_eq!(name, "Waiver"),
+            _ => panic!("unexpected result: {:?}", result),
+        }
+
+        renamed.name = "Media release".to_owned();
+        store.update_items(vec![renamed], None).await.unwrap();
+
+        let result = store
+            .rename_item(
+                &SchemaItemId::from(&schema.items[0].id),
+                "media RELEASE",
+                None,
+            )
+            .await;
+        match result {
+            Err(Error::DuplicateItemName { name }) => assert_eq!(name, "Media release"),
+            _ => panic!("unexpected result: {:?}", result),
+        }
+
+        // Other events can reuse the names.
+        store
+            .upsert(vec![RegistrationSchema {
+                event_id: init.event_2.clone(),
+                items: vec![checkbox_item("Waiver"), checkbox_item("Media release")],
+            }])
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn group_item_names_unique() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let result = store
+            .upsert(vec![RegistrationSchema {
+                event_id: init.event_1.clone(),
+                items: vec![group_item(
+                    "Partner",
+                    vec![text_item("Name"), text_item("NAME")],
+                )],
+            }])
+            .await;
+        match result {
+            Err(Error::DuplicateItemName { name }) => assert_eq!(name, "NAME"),
+            _ => panic!("unexpected result: {:?}", result),
+        }
+
+        // A group's items only have to differ from each other.
+        let schema = store
+            .upsert(vec![RegistrationSchema {
+                event_id: init.event_1.clone(),
+                items: vec![
+                    text_item("Name"),
+                    group_item("Partner", vec![text_item("Name"), text_item("Email")]),
+                ],
+            }])
+            .await
+            .unwrap()
+            .remove(0);
+
+        let email = match schema.items[1]
+            .r#type
+            .as_ref()
+            .and_then(|t| t.r#type.as_ref())
+        {
+            Some(ItemType::Group(group)) => group.items[1].id.clone(),
+            typ => panic!("unexpected type: {:?}", typ),
+        };
+        let result = store
+            .rename_item(&SchemaItemId::from(&email), "name", None)
+            .await;
+        match result {
+            Err(Error::DuplicateItemName { name }) => assert_eq!(name, "Name"),
+            _ => panic!("unexpected result: {:?}", result),
+        }
+
+        store
+            .rename_item(&SchemaItemId::from(&email), "Partner", None)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn upsert_outcomes() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+        let schema = store
+            .upsert(vec![RegistrationSchema {
+                event_id: init.event_1.clone(),
+                items: vec![checkbox_item("Waiver")],
+            }])
+            .await
+            .unwrap()
+            .remove(0);
+
+        let mut items = schema.items.clone();
+        items.push(checkbox_item("Photo release"));
+        let (schemas, outcomes) = store
+            .upsert_with_outcomes(
+                vec![RegistrationSchema {
+                    event_id: init.event_1.clone(),
+                    items,
+                }],
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            outcomes,
+            vec![
+                Outcome {
+                    id: schema.items[0].id.clone(),
+                    created: false,
+                },
+                Outcome {
+                    id: schemas[0].items[1].id.clone(),
+                    created: true,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn item_history() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+        let schema = store
+            .upsert(vec![RegistrationSchema {
+                event_id: init.event_1.clone(),
+                items: vec![checkbox_item("Shirt")],
+            }])
+            .await
+            .unwrap()
+            .remove(0);
+        let item_id = SchemaItemId::from(&schema.items[0].id);
+        let actor = UserId::from("user 1");
+
+        // Saving the item as it is changes nothing worth recording.
+        store
+            .update_items(vec![schema.items[0].clone()], Some(&actor))
+            .await
+            .unwrap();
+        assert_eq!(store.get_item_history(&item_id).await.unwrap(), vec![]);
+
+        let options = ["Small", "Medium"]
+            .into_iter()
+            .map(|name| SelectOption {
+                id: "".to_owned(),
+                name: name.to_owned(),
+                product_id: "".to_owned(),
+                archived: false,
+            })
+            .collect();
+        store
+            .update_items(
+                vec![RegistrationSchemaItem {
+                    r#type: Some(RegistrationSchemaItemType {
+                        r#type: Some(ItemType::Select(SelectType {
+                            default: 0,
+                            display: select_type::Display::Radio as i32,
+                            options,
+                        })),
+                    }),
+                    ..schema.items[0].clone()
+                }],
+                Some(&actor),
+            )
+            .await
+            .unwrap();
+        store
+            .rename_item(&item_id, "Shirt size", None)
+            .await
+            .unwrap();
+
+        let history = store.get_item_history(&item_id).await.unwrap();
+        assert_eq!(
+            history
+                .iter()
+                .map(|entry| {
+                    (
+                        entry.actor.as_deref(),
+                        (entry.old_name.as_str(), entry.new_name.as_str()),
+                        (entry.old_type.as_str(), entry.new_type.as_str()),
+                        (entry.old_options.as_str(), entry.new_options.as_str()),
+                    )
+                })
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    Some("user 1"),
+                    ("Shirt", "Shirt"),
+                    ("CheckboxType", "SelectType"),
+                    ("", "Small, Medium"),
+                ),
+                (
+                    None,
+                    ("Shirt", "Shirt size"),
+                    ("SelectType", "SelectType"),
+                    ("Small, Medium", "Small, Medium"),
+                ),
+            ]
+        );
+        assert!(history[0].changed_at <= history[1].changed_at);
+
+        // The history can only be added to.
+        let result = sqlx::query("DELETE FROM registration_schema_item_history")
+            .execute(&*store.pool)
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn update_items_keeps_schema() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+        let schema = store
+            .upsert(vec![RegistrationSchema {
+                event_id: init.event_1.clone(),
+                items: vec![checkbox_item("item 1"), checkbox_item("item 2")],
+            }])
+            .await
+            .unwrap()
+            .remove(0);
 
-            query.execute(&init.db).await.unwrap();
-        }
-        {
-            let query = sqlx::query(
-                "INSERT INTO registration_schema_select_options(id, 
-                    schema_item,
-                    idx,
-                    name,
-                    product_id
-                ) VALUES 
-                    (?, ?, ?, ?, ?),
-                    (?, ?, ?, ?, ?),
-                    (?, ?, ?, ?, ?)",
-            );
+        let mut edited = schema.items[1].clone();
+        edited.name = "item 2 updated".to_owned();
+        let returned = store
+            .update_items(vec![edited.clone()], None)
+            .await
+            .unwrap();
+        assert_eq!(
+            returned,
+            vec![RegistrationSchema {
+                event_id: init.event_1.clone(),
+                items: vec![edited.clone()],
+            }]
+        );
 
-            // option 1
-            let query = query
-                .bind(&select_option1_id)
-                .bind(&item3_id)
-                .bind(0)
-                .bind(select_option1_name)
-                .bind(select_option1_product_id);
+        let stored = stored_schema(&store, &init.event_1).await;
+        assert_eq!(stored.items, vec![schema.items[0].clone(), edited]);
+    }
 
-            // option 2
-            let query = query
-                .bind(&select_option2_id)
-                .bind(&item3_id)
-                .bind(1)
-                .bind(select_option2_name)
-                .bind(select_option2_product_id);
+    fn names(items: &[RegistrationSchemaItem]) -> Vec<&str> {
+        items.iter().map(|item| item.name.as_str()).collect()
+    }
 
-            // option 3
-            let query = query
-                .bind(&select_option3_id)
-                .bind(&item3_id)
-                .bind(2)
-                .bind(select_option3_name)
-                .bind(select_option3_product_id);
+    #[test_case(&[None, None, None], &["a", "b", "c"] ; "unset")]
+    #[test_case(&[Some(2), Some(1), Some(0)], &["c", "b", "a"] ; "reversed")]
+    #[test_case(&[Some(2), None, None], &["b", "a", "c"] ; "one moved")]
+    #[test_case(&[None, Some(0), None], &["a", "b", "c"] ; "tie keeps schema order")]
+    #[test_case(&[Some(9), None, None], &["b", "c", "a"] ; "past the end")]
+    fn sort_for_display(display_idxs: &[Option<u32>], expected: &[&str]) {
+        let mut items = ["a", "b", "c"]
+            .into_iter()
+            .zip(display_idxs)
+            .map(|(name, display_idx)| RegistrationSchemaItem {
+                display_idx: *display_idx,
+                ..checkbox_item(name)
+            })
+            .collect::<Vec<_>>();
 
-            query.execute(&init.db).await.unwrap();
-        }
+        super::sort_for_display(&mut items);
 
-        let schemas = vec![
-            RegistrationSchema {
+        assert_eq!(names(&items), expected);
+    }
+
+    #[tokio::test]
+    async fn display_idx_keeps_stored_order() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let schema = store
+            .upsert(vec![RegistrationSchema {
                 event_id: init.event_1.clone(),
                 items: vec![
                     RegistrationSchemaItem {
-                        id: item1_id,
-                        name: item1_name.to_owned(),
-                        r#type: Some(RegistrationSchemaItemType {
-                            r#type: Some(ItemType::Text(TextType {
-                                default: text_default.to_owned(),
-                                display: text_display.1 as i32,
-                            })),
-                        }),
-                    },
-                    RegistrationSchemaItem {
-                        id: item2_id,
-                        name: item2_name.to_owned(),
-                        r#type: Some(RegistrationSchemaItemType {
-                            r#type: Some(ItemType::Checkbox(CheckboxType {
-                                default: checkbox_default,
-                            })),
-                        }),
-                    },
-                    RegistrationSchemaItem {
-                        id: item3_id,
-                        name: item3_name.to_owned(),
-                        r#type: Some(RegistrationSchemaItemType {
-                            r#type: Some(ItemType::Select(SelectType {
-                                default: select_default,
-                                display: select_display.1 as i32,
-                                options: vec![
-                                    SelectOption {
-                                        id: select_option1_id,
-                                        name: select_option1_name.to_owned(),
-                                        product_id: select_option1_product_id.to_owned(),
-                                    },
-                                    SelectOption {
-                                        id: select_option2_id,
-                                        name: select_option2_name.to_owned(),
-                                        product_id: select_option2_product_id.to_owned(),
-                                    },
-                                    SelectOption {
-                                        id: select_option3_id,
-                                        name: select_option3_name.to_owned(),
-                                        product_id: select_option3_product_id.to_owned(),
-                                    },
-                                ],
-                            })),
-                        }),
+                        display_idx: Some(2),
+                        ..checkbox_item("waiver")
                     },
+                    group_item(
+                        "partners",
+                        vec![
+                            RegistrationSchemaItem {
+                                display_idx: Some(1),
+                                ..checkbox_item("attending")
+                            },
+                            RegistrationSchemaItem {
+                                display_idx: Some(0),
+                                ..checkbox_item("name")
+                            },
+                        ],
+                    ),
                 ],
-            },
-            RegistrationSchema {
-                event_id: init.event_2.clone(),
-                items: vec![RegistrationSchemaItem {
-                    id: item4_id,
-                    name: item4_name.to_owned(),
-                    r#type: Some(RegistrationSchemaItemType {
-                        r#type: Some(ItemType::Text(TextType {
-                            default: text_default_4.to_owned(),
-                            display: text_display_4.1 as i32,
-                        })),
-                    }),
-                }],
-            },
-        ];
+            }])
+            .await
+            .unwrap()
+            .remove(0);
+
+        let stored = stored_schema(&store, &init.event_1).await;
+        assert_eq!(stored, schema);
+        assert_eq!(names(&stored.items), vec!["waiver", "partners"]);
+        assert_eq!(stored.items[0].display_idx, Some(2));
+
+        let mut display = stored.items.clone();
+        super::sort_for_display(&mut display);
+        assert_eq!(names(&display), vec!["partners", "waiver"]);
+        let Some(ItemType::Group(group)) =
+            display[0].r#type.as_ref().and_then(|t| t.r#type.as_ref())
+        else {
+            panic!("expected a group, got {:?}", display[0]);
+        };
+        assert_eq!(names(&group.items), vec!["name", "attending"]);
+    }
 
-        schemas
+    #[tokio::test]
+    async fn group_items() {
+        let init = init_db().await;
+        let db = Arc::new(init.db);
+        let store = SqliteStore::new(db.clone());
+
+        let select_item = RegistrationSchemaItem {
+            r#type: Some(RegistrationSchemaItemType {
+                r#type: Some(ItemType::Select(SelectType {
+                    default: 0,
+                    display: select_type::Display::Dropdown as i32,
+                    options: ["leader", "follower"]
+                        .into_iter()
+                        .map(|name| SelectOption {
+                            id: "".to_owned(),
+                            name: name.to_owned(),
+                            product_id: "".to_owned(),
+                            archived: false,
+                        })
+                        .collect(),
+                })),
+            }),
+            ..checkbox_item("role")
+        };
+
+        let schema = store
+            .upsert(vec![RegistrationSchema {
+                event_id: init.event_1.clone(),
+                items: vec![
+                    checkbox_item("item"),
+                    group_item("partners", vec![checkbox_item("attending"), select_item]),
+                ],
+            }])
+            .await
+            .unwrap()
+            .remove(0);
+
+        let Some(ItemType::Group(group)) = schema.items[1]
+            .r#type
+            .as_ref()
+            .and_then(|t| t.r#type.as_ref())
+        else {
+            panic!("expected a group, got {:?}", schema.items[1]);
+        };
+        assert_eq!(
+            group
+                .items
+                .iter()
+                .map(|item| item.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["attending", "role"]
+        );
+        assert!(group.items.iter().all(|item| !item.id.is_empty()));
+        assert_eq!(stored_schema(&store, &init.event_1).await, schema);
+
+        // Items inside a group belong to the group rather than the event.
+        let rows: Vec<(Option<String>, Option<String>)> =
+            sqlx::query_as("SELECT event, parent FROM registration_schema_items WHERE id = ?")
+                .bind(&group.items[0].id)
+                .fetch_all(&*db)
+                .await
+                .unwrap();
+        assert_eq!(rows, vec![(None, Some(schema.items[1].id.clone()))]);
+
+        let headers = store
+            .list_item_headers(&EventId::from(&init.event_1))
+            .await
+            .unwrap();
+        assert_eq!(headers.len(), 2);
+        assert_eq!(headers[1].type_kind, item_header::TypeKind::Group as i32);
+
+        // A group's items are given in full, so leaving one out removes it.
+        let mut edited = schema.clone();
+        let Some(ItemType::Group(group)) = edited.items[1]
+            .r#type
+            .as_mut()
+            .and_then(|t| t.r#type.as_mut())
+        else {
+            unreachable!();
+        };
+        group.items.remove(0);
+        group.items.push(checkbox_item("dietary"));
+
+        let edited = store.upsert(vec![edited]).await.unwrap().remove(0);
+        assert_eq!(stored_schema(&store, &init.event_1).await, edited);
+
+        let (count,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM registration_schema_items WHERE parent IS NOT NULL",
+        )
+        .fetch_one(&*db)
+        .await
+        .unwrap();
+        assert_eq!(count, 2);
     }
 
-    fn sort_schemas(mut schemas: Vec<RegistrationSchema>) -> Vec<RegistrationSchema> {
-        schemas.sort_by(|a, b| a.event_id.cmp(&b.event_id));
-        schemas
+    #[tokio::test]
+    async fn query_all_includes_group_items_once() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+        let schema = store
+            .upsert(vec![RegistrationSchema {
+                event_id: init.event_1.clone(),
+                items: vec![group_item(
+                    "partners",
+                    vec![checkbox_item("attending"), checkbox_item("paid")],
+                )],
+            }])
+            .await
+            .unwrap()
+            .remove(0);
+
+        // With no query, the group's items are fetched along with everything else, so they must
+        // not be added to the group a second time.
+        let schemas = store.query(None).await.unwrap();
+        assert_eq!(schemas, vec![schema]);
     }
 
+    #[test_case(true ; "nested group")]
+    #[test_case(false ; "flat group")]
     #[tokio::test]
-    async fn insert() {
+    async fn nested_groups(nested: bool) {
         let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
 
-        let db_ptr = Arc::new(init.db);
+        let inner = if nested {
+            group_item("inner", vec![checkbox_item("item")])
+        } else {
+            checkbox_item("item")
+        };
 
-        let store = SqliteStore::new(db_ptr.clone());
+        let result = store
+            .upsert(vec![RegistrationSchema {
+                event_id: init.event_1.clone(),
+                items: vec![group_item("outer", vec![inner])],
+            }])
+            .await;
 
-        let mut schemas = vec![
-            RegistrationSchema {
-                event_id: init.event_1,
+        match (nested, result) {
+            (true, Err(Error::InconsistentItemType { .. })) | (false, Ok(_)) => (),
+            (_, result) => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    enum UpsertChangedTest {
+        Unchanged,
+        OneChanged,
+        Inserted,
+        Reordered,
+    }
+
+    #[test_case(UpsertChangedTest::Unchanged ; "unchanged")]
+    #[test_case(UpsertChangedTest::OneChanged ; "one changed")]
+    #[test_case(UpsertChangedTest::Inserted ; "inserted")]
+    #[test_case(UpsertChangedTest::Reordered ; "reordered")]
+    #[tokio::test]
+    async fn upsert_changed(test_name: UpsertChangedTest) {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let select_item = RegistrationSchemaItem {
+            r#type: Some(RegistrationSchemaItemType {
+                r#type: Some(ItemType::Select(SelectType {
+                    default: 1,
+                    display: select_type::Display::Radio as i32,
+                    options: vec![option("leader"), option("follower")],
+                })),
+            }),
+            required_after: "2024-03-01T00:00:00Z".to_owned(),
+            ..checkbox_item("role")
+        };
+
+        let schema = store
+            .upsert(vec![RegistrationSchema {
+                event_id: init.event_1.clone(),
                 items: vec![
-                    RegistrationSchemaItem {
-                        id: "".to_owned(),
-                        name: "field 1".to_owned(),
-                        r#type: Some(RegistrationSchemaItemType {
-                            r#type: Some(ItemType::Text(TextType {
-                                default: "some default".to_owned(),
-                                display: text_type::Display::Small as i32,
-                            })),
-                        }),
-                    },
-                    RegistrationSchemaItem {
-                        id: "".to_owned(),
-                        name: "field 2".to_owned(),
-                        r#type: Some(RegistrationSchemaItemType {
-                            r#type: Some(ItemType::Select(SelectType {
-                                default: 0,
-                                display: select_type::Display::Radio as i32,
-                                options: vec![SelectOption {
-                                    id: "".to_owned(),
-                                    name: "option 1".to_owned(),
-                                    product_id: "product 1".to_owned(),
-                                }],
-                            })),
-                        }),
-                    },
+                    checkbox_item("item 1"),
+                    select_item,
+                    group_item("partner", vec![checkbox_item("partner item")]),
                 ],
-            },
-            RegistrationSchema {
-                event_id: init.event_2,
+            }])
+            .await
+            .unwrap()
+            .remove(0);
+
+        let mut incoming = schema.clone();
+        match test_name {
+            UpsertChangedTest::Unchanged => (),
+            UpsertChangedTest::OneChanged => incoming.items[1].name = "dance role".to_owned(),
+            UpsertChangedTest::Inserted => incoming.items.push(checkbox_item("item 2")),
+            UpsertChangedTest::Reordered => incoming.items.swap(0, 1),
+        }
+
+        let mut changed = store
+            .upsert_changed(vec![incoming.clone()], None)
+            .await
+            .unwrap();
+        let expected = match test_name {
+            UpsertChangedTest::Unchanged => vec![],
+            UpsertChangedTest::OneChanged => vec![incoming.items[1].clone()],
+            UpsertChangedTest::Inserted => {
+                assert!(!changed[0].items[0].id.is_empty());
+                changed[0].items[0].id = "".to_owned();
+                vec![checkbox_item("item 2")]
+            }
+            UpsertChangedTest::Reordered => incoming.items[..2].to_vec(),
+        };
+
+        let expected = if expected.is_empty() {
+            vec![]
+        } else {
+            vec![RegistrationSchema {
+                event_id: init.event_1.clone(),
+                items: expected,
+            }]
+        };
+        assert_eq!(changed, expected);
+
+        // Unchanged items are left out of the response, not the schema.
+        assert_eq!(
+            stored_schema(&store, &init.event_1).await.items.len(),
+            incoming.items.len()
+        );
+    }
+
+    #[tokio::test]
+    async fn update_items_does_not_exist() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let id = new_id();
+        let result = store
+            .update_items(
+                vec![RegistrationSchemaItem {
+                    id: id.clone(),
+                    ..checkbox_item("item")
+                }],
+                None,
+            )
+            .await;
+
+        match result {
+            Err(Error::IdDoesNotExist(err_id)) => assert_eq!(err_id, id),
+            _ => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[tokio::test]
+    async fn rename_item() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+        let schema = store
+            .upsert(vec![RegistrationSchema {
+                event_id: init.event_1.clone(),
                 items: vec![
-                    RegistrationSchemaItem {
-                        id: "".to_owned(),
-                        name: "field 3".to_owned(),
-                        r#type: Some(RegistrationSchemaItemType {
-                            r#type: Some(ItemType::Checkbox(CheckboxType { default: true })),
-                        }),
-                    },
-                    RegistrationSchemaItem {
-                        id: "".to_owned(),
-                        name: "field 4".to_owned(),
+                    checkbox_item("item 1"),
+                    RegistrationSchemaItem {
                         r#type: Some(RegistrationSchemaItemType {
-                            r#type: Some(ItemType::MultiSelect(MultiSelectType {
-                                defaults: vec![0, 1],
-                                display: multi_select_type::Display::MultiselectBox as i32,
-                                options: vec![
-                                    SelectOption {
-                                        id: "".to_owned(),
-                                        name: "option 2".to_owned(),
-                                        product_id: "product 2".to_owned(),
-                                    },
-                                    SelectOption {
-                                        id: "".to_owned(),
-                                        name: "option 3".to_owned(),
-                                        product_id: "product 3".to_owned(),
-                                    },
-                                    SelectOption {
-                                        id: "".to_owned(),
-                                        name: "option 4".to_owned(),
-                                        product_id: "product 4".to_owned(),
-                                    },
-                                ],
+                            r#type: Some(ItemType::Select(SelectType {
+                                default: 1,
+                                display: select_type::Display::Radio as i32,
+                                options: vec![option("leader"), option("follower")],
                             })),
                         }),
+                        ..checkbox_item("item 2")
                     },
                 ],
-            },
-        ];
-
-        let returned_schemas = store.upsert(schemas.clone()).await.unwrap();
+            }])
+            .await
+            .unwrap()
+            .remove(0);
 
-        assert_eq!(schemas.len(), returned_schemas.len());
+        let header = store
+            .rename_item(&SchemaItemId::from(&schema.items[1].id), "renamed", None)
+            .await
+            .unwrap();
 
-        let returned_schema_map = returned_schemas
-            .into_iter()
-            .map(|schema| (schema.event_id.clone(), schema))
-            .collect::<HashMap<_, _>>();
+        assert_eq!(header.id, schema.items[1].id);
+        assert_eq!(header.event_id, init.event_1);
+        assert_eq!(header.name, "renamed");
+        assert_eq!(header.idx, 1);
 
-        for schema in schemas.iter_mut() {
-            let returned_schema = returned_schema_map.get(&schema.event_id).unwrap();
+        // Only the name changes; the type and options come back exactly as they were.
+        let mut expected = schema;
+        expected.items[1].name = "renamed".to_owned();
+        assert_eq!(store.query(None).await.unwrap(), vec![expected]);
+    }
 
-            for (schema_item, returned_item) in
-                schema.items.iter_mut().zip(returned_schema.items.iter())
-            {
-                schema_item.id = returned_item.id.clone();
-                let options = match schema_item
-                    .r#type
-                    .as_mut()
-                    .unwrap()
-                    .r#type
-                    .as_mut()
-                    .unwrap()
-                {
-                    ItemType::Select(schema_select) => {
-                        if let ItemType::Select(returned_select) = returned_item
-                            .r#type
-                            .as_ref()
-                            .unwrap()
-                            .r#type
-                            .as_ref()
-                            .unwrap()
-                        {
-                            Some((&mut schema_select.options, &returned_select.options))
-                        } else {
-                            panic!()
-                        }
-                    }
-                    ItemType::MultiSelect(schema_select) => {
-                        if let ItemType::MultiSelect(returned_select) = returned_item
-                            .r#type
-                            .as_ref()
-                            .unwrap()
-                            .r#type
-                            .as_ref()
-                            .unwrap()
-                        {
-                            Some((&mut schema_select.options, &returned_select.options))
-                        } else {
-                            panic!()
-                        }
-                    }
-                    _ => None,
-                };
+    #[tokio::test]
+    async fn rename_item_does_not_exist() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
 
-                if let Some((schema_options, returned_options)) = options {
-                    for (schema_option, returned_option) in
-                        schema_options.iter_mut().zip(returned_options.iter())
-                    {
-                        schema_option.id = returned_option.id.clone();
-                    }
-                }
-            }
+        let id = new_id();
+        let result = store
+            .rename_item(&SchemaItemId::from(&id), "renamed", None)
+            .await;
 
-            assert_eq!(schema, returned_schema);
+        match result {
+            Err(Error::IdDoesNotExist(err_id)) => assert_eq!(err_id, id),
+            _ => panic!("unexpected result: {:?}", result),
         }
+    }
 
-        let store_row: Vec<ItemRow> = sqlx::query_as("SELECT * FROM registration_schema_items")
-            .fetch_all(&*db_ptr)
+    #[tokio::test]
+    async fn delete_item() {
+        let init = init_db().await;
+        let db = Arc::new(init.db);
+        let store = SqliteStore::new(db.clone());
+        let schema = store
+            .upsert(vec![RegistrationSchema {
+                event_id: init.event_1.clone(),
+                items: vec![
+                    checkbox_item("item 1"),
+                    select_item("item 2", &["leader", "follower"]),
+                    checkbox_item("item 3"),
+                ],
+            }])
+            .await
+            .unwrap()
+            .remove(0);
+
+        let header = store
+            .delete_item(&SchemaItemId::from(&schema.items[1].id))
             .await
             .unwrap();
+        assert_eq!(header.id, schema.items[1].id);
+        assert_eq!(header.event_id, init.event_1);
+        assert_eq!(header.idx, 1);
 
-        let store_options_row: Vec<OptionRow> =
-            sqlx::query_as("SELECT * FROM registration_schema_select_options")
-                .fetch_all(&*db_ptr)
+        let expected = RegistrationSchema {
+            event_id: init.event_1.clone(),
+            items: vec![schema.items[0].clone(), schema.items[2].clone()],
+        };
+        assert_eq!(store.query(None).await.unwrap(), vec![expected]);
+
+        let headers = store
+            .list_item_headers(&EventId::from(&init.event_1))
+            .await
+            .unwrap();
+        assert_eq!(
+            headers.iter().map(|header| header.idx).collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+
+        let (option_count,): (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM registration_schema_select_options")
+                .fetch_one(&*db)
                 .await
                 .unwrap();
+        assert_eq!(option_count, 0);
+    }
 
-        let store_schemas = items_to_schema(
-            store_row.into_iter().map(|row| row.to_item().unwrap()),
-            store_options_row
-                .into_iter()
-                .map(|row| row.to_option().unwrap()),
-        );
+    #[tokio::test]
+    async fn delete_item_does_not_exist() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
 
-        assert_eq!(schemas.len(), store_schemas.len());
+        let id = new_id();
+        let result = store.delete_item(&SchemaItemId::from(&id)).await;
 
-        let store_schema_map = store_schemas
-            .into_iter()
-            .map(|schema| (schema.event_id.clone(), schema))
-            .collect::<HashMap<_, _>>();
+        match result {
+            Err(Error::IdDoesNotExist(err_id)) => assert_eq!(err_id, id),
+            _ => panic!("unexpected result: {:?}", result),
+        }
+    }
 
-        for schema in schemas.iter() {
-            let store_schema = store_schema_map.get(&schema.event_id).unwrap();
-            assert_eq!(schema, store_schema);
+    enum LockedSchemaTest {
+        Rename,
+        RemoveOption,
+        AddItem,
+        ChangeType,
+    }
+
+    #[test_case(LockedSchemaTest::Rename ; "rename")]
+    #[test_case(LockedSchemaTest::RemoveOption ; "remove option")]
+    #[test_case(LockedSchemaTest::AddItem ; "add item")]
+    #[test_case(LockedSchemaTest::ChangeType ; "change type")]
+    #[tokio::test]
+    async fn locked_schema(test_name: LockedSchemaTest) {
+        let init = init_db().await;
+        let db = Arc::new(init.db);
+        let store = SqliteStore::new(db.clone());
+        let schema = store
+            .upsert(vec![RegistrationSchema {
+                event_id: init.event_1.clone(),
+                items: vec![select_item("role", &["leader", "follower"])],
+            }])
+            .await
+            .unwrap()
+            .remove(0);
+
+        sqlx::query("INSERT INTO registrations(id, event) VALUES (?, ?)")
+            .bind(new_id())
+            .bind(&init.event_1)
+            .execute(&*db)
+            .await
+            .unwrap();
+
+        let mut edited = schema.clone();
+        match test_name {
+            LockedSchemaTest::Rename => edited.items[0].name = "dance role".to_owned(),
+            LockedSchemaTest::RemoveOption => {
+                match edited.items[0].r#type.as_mut().unwrap().r#type.as_mut() {
+                    Some(ItemType::Select(select)) => {
+                        select.options.pop();
+                    }
+                    typ => panic!("unexpected type: {:?}", typ),
+                }
+            }
+            LockedSchemaTest::AddItem => edited.items.push(checkbox_item("housing")),
+            LockedSchemaTest::ChangeType => {
+                edited.items[0] = RegistrationSchemaItem {
+                    id: edited.items[0].id.clone(),
+                    ..checkbox_item("role")
+                }
+            }
+        }
+
+        let result = store.upsert(vec![edited.clone()]).await;
+
+        match (test_name, result) {
+            (LockedSchemaTest::Rename, Ok(returned)) => assert_eq!(returned, vec![edited]),
+            (_, Err(Error::SchemaLocked(event_id))) => {
+                assert_eq!(event_id, init.event_1);
+                assert_eq!(store.query(None).await.unwrap(), vec![schema]);
+            }
+            (_, result) => panic!("unexpected result: {:?}", result),
         }
     }
 
     #[tokio::test]
-    async fn update() {
+    async fn locked_schema_delete() {
         let init = init_db().await;
+        let db = Arc::new(init.db);
+        let store = SqliteStore::new(db.clone());
 
-        let mut schemas = test_data(&init).await;
+        sqlx::query("INSERT INTO registrations(id, event) VALUES (?, ?)")
+            .bind(new_id())
+            .bind(&init.event_1)
+            .execute(&*db)
+            .await
+            .unwrap();
 
-        schemas[0].items.swap(0, 1);
+        match store.delete(std::slice::from_ref(&init.event_1)).await {
+            Err(Error::SchemaLocked(event_id)) => assert_eq!(event_id, init.event_1),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
 
-        schemas[0].items[0].name = "item 1 updated".to_owned();
-        match schemas[0].items[0]
-            .r#type
-            .as_mut()
-            .unwrap()
-            .r#type
-            .as_mut()
+    fn role_type(options: &[&str]) -> Option<ItemType> {
+        select_item("role", options).r#type.unwrap().r#type
+    }
+
+    fn multi_role_type() -> Option<ItemType> {
+        multi_select_item("role", &[]).r#type.unwrap().r#type
+    }
+
+    fn text_type() -> Option<ItemType> {
+        text_item("price").r#type.unwrap().r#type
+    }
+
+    #[derive(Clone, Copy)]
+    enum ChangeTypeTest {
+        SelectToText,
+        SelectToMultiSelect,
+        CheckboxToText,
+        TextToSelect,
+        TextToSelectUnanswered,
+    }
+
+    #[test_case(ChangeTypeTest::SelectToText ; "select to text")]
+    #[test_case(ChangeTypeTest::SelectToMultiSelect ; "select to multi select")]
+    #[test_case(ChangeTypeTest::CheckboxToText ; "checkbox to text")]
+    #[test_case(ChangeTypeTest::TextToSelect ; "text to select")]
+    #[test_case(ChangeTypeTest::TextToSelectUnanswered ; "text to select unanswered")]
+    #[tokio::test]
+    async fn change_item_type(test_name: ChangeTypeTest) {
+        let init = init_db().await;
+        let db = Arc::new(init.db);
+        let store = SqliteStore::new(db.clone());
+
+        let (from, value, to) = match test_name {
+            ChangeTypeTest::SelectToText => (role_type(&["leader", "follower"]), "1", text_type()),
+            ChangeTypeTest::SelectToMultiSelect => {
+                (role_type(&["leader", "follower"]), "1", multi_role_type())
+            }
+            ChangeTypeTest::CheckboxToText => (
+                checkbox_item("").r#type.unwrap().r#type,
+                "true",
+                text_type(),
+            ),
+            ChangeTypeTest::TextToSelect => (text_type(), "leader", role_type(&["leader"])),
+            ChangeTypeTest::TextToSelectUnanswered => (text_type(), "", role_type(&["leader"])),
+        };
+
+        let schema = store
+            .upsert(vec![RegistrationSchema {
+                event_id: init.event_1.clone(),
+                items: vec![RegistrationSchemaItem {
+                    r#type: Some(RegistrationSchemaItemType { r#type: from }),
+                    ..checkbox_item("role")
+                }],
+            }])
+            .await
             .unwrap()
-        {
-            ItemType::Checkbox(checkbox) => checkbox.default = !checkbox.default,
-            _ => panic!("{:?}", schemas[0].items[0]),
+            .remove(0);
+        let item_id = schema.items[0].id.clone();
+
+        let registration = new_id();
+        sqlx::query("INSERT INTO registrations(id, event) VALUES (?, ?)")
+            .bind(&registration)
+            .bind(&init.event_1)
+            .execute(&*db)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO registration_items(id, registration, schema_item, value) VALUES (?, ?, ?, ?)",
+        )
+        .bind(new_id())
+        .bind(&registration)
+        .bind(&item_id)
+        .bind(value)
+        .execute(&*db)
+        .await
+        .unwrap();
+
+        let result = store
+            .change_item_type(
+                &SchemaItemId::from(&item_id),
+                RegistrationSchemaItemType { r#type: to },
+                None,
+            )
+            .await;
+
+        let (stored_value,): (String,) =
+            sqlx::query_as("SELECT value FROM registration_items WHERE schema_item = ?")
+                .bind(&item_id)
+                .fetch_one(&*db)
+                .await
+                .unwrap();
+
+        let returned = match (test_name, result) {
+            (ChangeTypeTest::TextToSelect, Err(Error::TypeChangeUnsafe { item_id: id })) => {
+                assert_eq!(id, item_id);
+                assert_eq!(stored_value, value);
+                assert_eq!(stored_schema(&store, &init.event_1).await, schema);
+                return;
+            }
+            (_, Ok(returned)) => returned,
+            (_, result) => panic!("unexpected result: {:?}", result),
+        };
+
+        assert_eq!(returned.event_id, init.event_1);
+        assert_eq!(returned.items.len(), 1);
+        assert_eq!(returned.items[0].id, item_id);
+        assert_eq!(returned.items[0].name, "role");
+        assert_eq!(stored_schema(&store, &init.event_1).await, returned);
+
+        let typ = returned.items[0].r#type.as_ref().unwrap().r#type.as_ref();
+        match (test_name, typ) {
+            // Answers given by position are rewritten to the option they named.
+            (ChangeTypeTest::SelectToText, Some(ItemType::Text(_))) => {
+                assert_eq!(stored_value, "follower")
+            }
+            // The options carry over, so the stored position still names the same option.
+            (ChangeTypeTest::SelectToMultiSelect, Some(ItemType::MultiSelect(multi_select))) => {
+                assert_eq!(stored_value, "1");
+                assert_eq!(
+                    multi_select
+                        .options
+                        .iter()
+                        .map(|option| option.name.as_str())
+                        .collect::<Vec<_>>(),
+                    vec!["leader", "follower"]
+                );
+            }
+            (ChangeTypeTest::CheckboxToText, Some(ItemType::Text(_))) => {
+                assert_eq!(stored_value, "true")
+            }
+            (ChangeTypeTest::TextToSelectUnanswered, Some(ItemType::Select(select))) => {
+                assert_eq!(select.options.len(), 1)
+            }
+            (_, typ) => panic!("unexpected type: {:?}", typ),
         }
+    }
 
-        match schemas[0].items[1]
-            .r#type
-            .as_mut()
-            .unwrap()
-            .r#type
-            .as_mut()
+    #[tokio::test]
+    async fn failed_type_change_keeps_values() {
+        let init = init_db().await;
+        let db = Arc::new(init.db);
+        let store = SqliteStore::new(db.clone());
+
+        let schema = store
+            .upsert(vec![RegistrationSchema {
+                event_id: init.event_1.clone(),
+                items: vec![select_item("role", &["leader", "follower"])],
+            }])
+            .await
             .unwrap()
-        {
-            ItemType::Text(text) => text.display = text_type::Display::Small as i32,
-            _ => panic!("{:?}", schemas[0].items[1]),
+            .remove(0);
+        let item_id = schema.items[0].id.clone();
+
+        let registration = new_id();
+        sqlx::query("INSERT INTO registrations(id, event) VALUES (?, ?)")
+            .bind(&registration)
+            .bind(&init.event_1)
+            .execute(&*db)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO registration_items(id, registration, schema_item, value) VALUES (?, ?, ?, ?)",
+        )
+        .bind(new_id())
+        .bind(&registration)
+        .bind(&item_id)
+        .bind("1")
+        .execute(&*db)
+        .await
+        .unwrap();
+
+        // The item can't be saved again, so the write fails after the answers have been rewritten.
+        sqlx::query("UPDATE registration_schema_items SET required_after = 'soon' WHERE id = ?")
+            .bind(&item_id)
+            .execute(&*db)
+            .await
+            .unwrap();
+
+        let result = store
+            .change_item_type(
+                &SchemaItemId::from(&item_id),
+                RegistrationSchemaItemType {
+                    r#type: text_type(),
+                },
+                None,
+            )
+            .await;
+        match result {
+            Err(Error::InvalidTimestamp(timestamp)) => assert_eq!(timestamp, "soon"),
+            _ => panic!("unexpected result: {:?}", result),
         }
 
-        match schemas[0].items[2]
-            .r#type
-            .as_mut()
-            .unwrap()
-            .r#type
-            .as_mut()
+        // The answer is still the position the select item reads.
+        let (stored_value,): (String,) =
+            sqlx::query_as("SELECT value FROM registration_items WHERE schema_item = ?")
+                .bind(&item_id)
+                .fetch_one(&*db)
+                .await
+                .unwrap();
+        assert_eq!(stored_value, "1");
+    }
+
+    #[tokio::test]
+    async fn reorder_items() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+        let schema = store
+            .upsert(vec![RegistrationSchema {
+                event_id: init.event_1.clone(),
+                items: vec![
+                    checkbox_item("item 1"),
+                    checkbox_item("item 2"),
+                    checkbox_item("item 3"),
+                ],
+            }])
+            .await
+            .unwrap()
+            .remove(0);
+
+        // A deleted item is skipped rather than brought back, and the unlisted item 2 goes last.
+        let headers = store
+            .reorder_items(
+                &EventId::from(&init.event_1),
+                &[
+                    SchemaItemId::from(schema.items[2].id.clone()),
+                    SchemaItemId::from(new_id()),
+                    SchemaItemId::from(schema.items[0].id.clone()),
+                ],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            headers
+                .iter()
+                .map(|header| (header.name.as_str(), header.idx))
+                .collect::<Vec<_>>(),
+            vec![("item 3", 0), ("item 1", 1), ("item 2", 2)]
+        );
+
+        let stored = stored_schema(&store, &init.event_1).await;
+        assert_eq!(
+            stored.items,
+            vec![
+                schema.items[2].clone(),
+                schema.items[0].clone(),
+                schema.items[1].clone()
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn add_items_go_last() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+        let schema = store
+            .upsert(vec![RegistrationSchema {
+                event_id: init.event_1.clone(),
+                items: vec![checkbox_item("item 1"), checkbox_item("item 2")],
+            }])
+            .await
             .unwrap()
-        {
-            ItemType::Select(select) => {
-                select.default = 0;
-                select.options.swap(0, 1);
-                select.options[1].product_id = "updated product id".to_owned();
-                select.options.remove(2);
-            }
-            _ => panic!("{:?}", schemas[0].items[2]),
-        }
+            .remove(0);
 
-        let db_ptr = Arc::new(init.db);
-        let store = SqliteStore::new(db_ptr.clone());
+        // Adding after a reorder keeps the reordered positions.
+        store
+            .reorder_items(
+                &EventId::from(&init.event_1),
+                &[SchemaItemId::from(schema.items[1].id.clone())],
+            )
+            .await
+            .unwrap();
 
-        let returned_schemas = store.upsert(schemas.clone()).await.unwrap();
+        let added = store
+            .add_items(
+                &EventId::from(&init.event_1),
+                vec![checkbox_item("item 3"), checkbox_item("item 4")],
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(added.iter().all(|item| !item.id.is_empty()));
+        assert_eq!(
+            added
+                .iter()
+                .map(|item| item.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["item 3", "item 4"]
+        );
 
-        assert_eq!(schemas.len(), returned_schemas.len());
-        let returned_schema_map = returned_schemas
-            .into_iter()
-            .map(|schema| (schema.event_id.clone(), schema))
-            .collect::<HashMap<_, _>>();
+        let stored = stored_schema(&store, &init.event_1).await;
+        assert_eq!(
+            stored.items,
+            [schema.items[1].clone(), schema.items[0].clone()]
+                .into_iter()
+                .chain(added)
+                .collect::<Vec<_>>()
+        );
+    }
 
-        for schema in schemas.iter_mut() {
-            let returned_schema = returned_schema_map.get(&schema.event_id).unwrap();
-            assert_eq!(schema, returned_schema);
-        }
+    #[tokio::test]
+    async fn add_items_to_empty_schema() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
 
-        let store_row: Vec<ItemRow> = sqlx::query_as("SELECT * FROM registration_schema_items")
-            .fetch_all(&*db_ptr)
+        let added = store
+            .add_items(
+                &EventId::from(&init.event_1),
+                vec![checkbox_item("item")],
+                None,
+            )
             .await
             .unwrap();
 
-        let store_options_row: Vec<OptionRow> =
-            sqlx::query_as("SELECT * FROM registration_schema_select_options")
-                .fetch_all(&*db_ptr)
-                .await
-                .unwrap();
+        let stored = stored_schema(&store, &init.event_1).await;
+        assert_eq!(stored.items, added);
+    }
 
-        let store_schemas = items_to_schema(
-            store_row.into_iter().map(|row| row.to_item().unwrap()),
-            store_options_row
-                .into_iter()
-                .map(|row| row.to_option().unwrap()),
-        );
+    // WAL mode is not available for in-memory databases, so tests of concurrent writes need a real
+    // file. Returns its path, an event in it and a store over it.
+    async fn init_file_db() -> (PathBuf, String, Arc<SqliteStore>) {
+        let path = env::temp_dir().join(format!("odr-schema-{}.db", new_id()));
+        let db_url = format!("sqlite://{}", path.display());
 
-        assert_eq!(schemas.len(), store_schemas.len());
+        let db = SqlitePool::connect_with(
+            connect_options(&db_url, DEFAULT_BUSY_TIMEOUT)
+                .unwrap()
+                .create_if_missing(true),
+        )
+        .await
+        .unwrap();
+        sqlx::migrate!("../migrations").run(&db).await.unwrap();
 
-        let store_schema_map = store_schemas
-            .into_iter()
-            .map(|schema| (schema.event_id.clone(), schema))
-            .collect::<HashMap<_, _>>();
+        let org = new_id();
+        let event = new_id();
+        sqlx::query("INSERT INTO organizations(id, name) VALUES (?, ?);")
+            .bind(&org)
+            .bind("Org 1")
+            .execute(&db)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO events(id, organization, name) VALUES (?, ?, ?);")
+            .bind(&event)
+            .bind(&org)
+            .bind("Event 1")
+            .execute(&db)
+            .await
+            .unwrap();
 
-        for schema in schemas.iter() {
-            let store_schema = store_schema_map.get(&schema.event_id).unwrap();
-            assert_eq!(schema, store_schema);
+        (path, event, Arc::new(SqliteStore::new(Arc::new(db))))
+    }
+
+    fn remove_file_db(path: &Path) {
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
         }
     }
 
-    enum UpdateDoesNotExistTests {
-        BadEventId,
-        BadItemId,
-        BadOptionId,
+    #[derive(Clone, Copy)]
+    enum Interleaving {
+        ReorderFirst,
+        EditFirst,
+        Concurrent,
     }
-    #[test_case(UpdateDoesNotExistTests::BadEventId ; "bad event id")]
-    #[test_case(UpdateDoesNotExistTests::BadItemId ; "bad item id")]
-    #[test_case(UpdateDoesNotExistTests::BadOptionId ; "bad option id")]
-    #[tokio::test]
-    async fn update_does_not_exist(test_name: UpdateDoesNotExistTests) {
-        let init = init_db().await;
-        let test_data = test_data(&init).await;
 
-        struct TestCase {
-            id: String,
-            schema: RegistrationSchema,
-        }
-        let tc = match test_name {
-            UpdateDoesNotExistTests::BadEventId => {
-                let id = new_id();
-                TestCase {
-                    id: id.clone(),
-                    schema: RegistrationSchema {
-                        event_id: id.clone(),
-                        items: Vec::new(),
-                    },
-                }
+    #[test_case(Interleaving::ReorderFirst ; "reorder first")]
+    #[test_case(Interleaving::EditFirst ; "edit first")]
+    #[test_case(Interleaving::Concurrent ; "concurrent")]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn reorder_and_edit_compose(interleaving: Interleaving) {
+        let (path, event, store) = init_file_db().await;
+        let schema = store
+            .upsert(vec![RegistrationSchema {
+                event_id: event.clone(),
+                items: vec![
+                    checkbox_item("item 1"),
+                    checkbox_item("item 2"),
+                    checkbox_item("item 3"),
+                ],
+            }])
+            .await
+            .unwrap()
+            .remove(0);
+
+        // Both admins start from the same copy of the schema.
+        let reordered_ids = schema
+            .items
+            .iter()
+            .rev()
+            .map(|item| SchemaItemId::from(&item.id))
+            .collect::<Vec<_>>();
+        let mut edited = schema.items[0].clone();
+        edited.name = "item 1 updated".to_owned();
+
+        let reorder = {
+            let store = store.clone();
+            let event = event.clone();
+            let reordered_ids = reordered_ids.clone();
+            async move {
+                store
+                    .reorder_items(&EventId::from(event), &reordered_ids)
+                    .await
             }
-            UpdateDoesNotExistTests::BadItemId => {
-                let id = new_id();
-                let mut schema = test_data[0].clone();
-                schema.items[0].id = id.clone();
-                TestCase { id, schema }
+        };
+        let edit = {
+            let store = store.clone();
+            async move { store.update_items(vec![edited], None).await }
+        };
+
+        match interleaving {
+            Interleaving::ReorderFirst => {
+                reorder.await.unwrap();
+                edit.await.unwrap();
             }
-            UpdateDoesNotExistTests::BadOptionId => {
-                let id = new_id();
-                let mut schema = test_data[0].clone();
-                match schema.items[2]
-                    .r#type
-                    .as_mut()
-                    .unwrap()
-                    .r#type
-                    .as_mut()
-                    .unwrap()
-                {
-                    ItemType::Select(select) => select.options[0].id = id.clone(),
-                    _ => panic!(),
-                }
-                TestCase { id, schema }
+            Interleaving::EditFirst => {
+                edit.await.unwrap();
+                reorder.await.unwrap();
             }
-        };
+            Interleaving::Concurrent => {
+                // Each write runs on its own worker thread, and both are released at once.
+                let barrier = Arc::new(Barrier::new(2));
+                let reorder = tokio::spawn({
+                    let barrier = barrier.clone();
+                    async move {
+                        barrier.wait().await;
+                        reorder.await
+                    }
+                });
+                let edit = tokio::spawn(async move {
+                    barrier.wait().await;
+                    edit.await
+                });
+                reorder.await.unwrap().unwrap();
+                edit.await.unwrap().unwrap();
+            }
+        }
 
-        let store = SqliteStore::new(Arc::new(init.db));
-        let result = store.upsert(vec![tc.schema]).await;
-        match result {
-            Ok(_) => panic!("no error returned"),
-            Err(Error::IdDoesNotExist(err_id)) => assert_eq!(err_id, tc.id),
-            _ => panic!("incorrect error type: {:?}", result),
-        };
-    }
+        let stored = stored_schema(&store, &event).await;
+        assert_eq!(
+            stored
+                .items
+                .iter()
+                .map(|item| SchemaItemId::from(&item.id))
+                .collect::<Vec<_>>(),
+            reordered_ids
+        );
+        assert_eq!(
+            stored
+                .items
+                .iter()
+                .map(|item| item.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["item 3", "item 2", "item 1 updated"]
+        );
 
-    enum QueryTest {
-        All,
-        EventId,
-        NoOptions,
-        CompoundQuery,
-        NoResults,
+        drop(store);
+        remove_file_db(&path);
     }
 
-    #[test_case(QueryTest::All ; "all")]
-    #[test_case(QueryTest::EventId ; "event id")]
-    #[test_case(QueryTest::NoOptions ; "no options")]
-    #[test_case(QueryTest::CompoundQuery ; "compound query")]
-    #[test_case(QueryTest::NoResults ; "no results")]
-    #[tokio::test]
-    async fn query(test_name: QueryTest) {
-        let init = init_db().await;
-        let schemas = test_data(&init).await;
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn concurrent_adds_keep_names_unique() {
+        let (path, event, store) = init_file_db().await;
+
+        // Both adds are released at once, each on its own worker thread.
+        let barrier = Arc::new(Barrier::new(2));
+        let adds = (0..2)
+            .map(|_| {
+                let barrier = barrier.clone();
+                let store = store.clone();
+                let event = EventId::from(&event);
+                tokio::spawn(async move {
+                    barrier.wait().await;
+                    store
+                        .add_items(&event, vec![checkbox_item("Waiver")], None)
+                        .await
+                })
+            })
+            .collect::<Vec<_>>();
 
-        struct TestCase {
-            query: Option<Query>,
-            expected: Vec<RegistrationSchema>,
+        let mut results = Vec::new();
+        for add in adds {
+            results.push(add.await.unwrap());
         }
 
-        let tc = match test_name {
-            QueryTest::All => TestCase {
-                query: None,
-                expected: schemas,
-            },
-            QueryTest::EventId => TestCase {
-                query: Some(Query::EventId(LogicalQuery::Equals(init.event_1.clone()))),
-                expected: vec![schemas[0].clone()],
-            },
-            QueryTest::NoOptions => TestCase {
-                query: Some(Query::EventId(LogicalQuery::Equals(init.event_2.clone()))),
-                expected: vec![schemas[1].clone()],
-            },
-            QueryTest::CompoundQuery => TestCase {
-                query: Some(Query::Compound(CompoundQuery {
-                    operator: CompoundOperator::Or,
-                    queries: schemas
-                        .iter()
-                        .map(|s| Query::EventId(LogicalQuery::Equals(s.event_id.clone())))
-                        .collect(),
-                })),
-                expected: schemas,
-            },
-            QueryTest::NoResults => TestCase {
-                query: Some(Query::EventId(LogicalQuery::Equals(new_id()))),
-                expected: Vec::new(),
-            },
-        };
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 1);
+        assert!(
+            results
+                .iter()
+                .any(|result| matches!(result, Err(Error::DuplicateItemName { .. }))),
+            "{:?}",
+            results
+        );
+        assert_eq!(stored_schema(&store, &event).await.items.len(), 1);
 
-        let store = SqliteStore::new(Arc::new(init.db));
-        let returned_schemas = store.query(tc.query.as_ref()).await.unwrap();
-        let expected = sort_schemas(tc.expected);
-        let returned_schemas = sort_schemas(returned_schemas);
-        assert_eq!(expected, returned_schemas);
+        drop(store);
+        remove_file_db(&path);
     }
 
     #[tokio::test]
-    async fn delete_one() {
-        let init = init_db().await;
-        let _ = test_data(&init).await;
+    async fn empty_input() {
+        let store = SqliteStore::new(closed_pool().await);
 
-        let db_ptr = Arc::new(init.db);
-        let store = SqliteStore::new(db_ptr.clone());
+        assert!(store.upsert(Vec::new()).await.unwrap().is_empty());
+        store.delete(&Vec::new()).await.unwrap();
+    }
 
-        store.delete(&vec![init.event_1]).await.unwrap();
+    // The map building used before rows were moved instead of cloned, kept to check that the
+    // output hasn't changed.
+    fn reference_items_to_schema(
+        items: Vec<(String, usize, RegistrationSchemaItem)>,
+        options: Vec<(String, usize, SelectOption)>,
+    ) -> Vec<RegistrationSchema> {
+        let mut items_to_options_map = HashMap::new();
+        for (item_id, idx, option) in options {
+            let option_map = items_to_options_map
+                .entry(item_id)
+                .or_insert(BTreeMap::new());
+            option_map.insert((idx, option.id.clone()), option);
+        }
 
-        let event_ids: Vec<(String,)> =
-            sqlx::query_as("SELECT DISTINCT event FROM registration_schema_items")
-                .fetch_all(&*db_ptr)
-                .await
-                .unwrap();
+        let mut items_to_options_map = items_to_options_map
+            .into_iter()
+            .map(|(item_id, options_map)| (item_id, options_map.into_values().collect::<Vec<_>>()))
+            .collect::<HashMap<_, _>>();
 
-        assert_eq!(event_ids.len(), 1);
-        assert_eq!(event_ids[0].0, init.event_2);
+        let mut schema_map = BTreeMap::new();
+        for (event_id, idx, mut item) in items {
+            match item.r#type.as_mut().unwrap().r#type.as_mut().unwrap() {
+                ItemType::Select(select) => {
+                    select.options = items_to_options_map.remove(&item.id).unwrap_or_default()
+                }
+                ItemType::MultiSelect(select) => {
+                    select.options = items_to_options_map.remove(&item.id).unwrap_or_default()
+                }
+                _ => (),
+            };
 
-        let (object_count,): (u32,) =
-            sqlx::query_as("SELECT COUNT(*) FROM registration_schema_select_options")
-                .fetch_one(&*db_ptr)
-                .await
-                .unwrap();
+            let item_map = schema_map.entry(event_id).or_insert(BTreeMap::new());
+            item_map.insert((idx, item.id.clone()), item);
+        }
 
-        assert_eq!(object_count, 0);
+        schema_map
+            .into_iter()
+            .map(|(event_id, item_map)| RegistrationSchema {
+                event_id,
+                items: item_map.into_values().collect(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn items_to_schema_matches_reference() {
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let mut items = Vec::new();
+        let mut options = Vec::new();
+        for event_idx in 0..2 {
+            let event_id = format!("event {}", event_idx);
+            for item_idx in 0..50 {
+                let item_id = new_id();
+                let typ = match item_idx % 3 {
+                    0 => ItemType::Select(SelectType {
+                        default: 0,
+                        display: select_type::Display::Radio as i32,
+                        options: Vec::new(),
+                    }),
+                    1 => ItemType::MultiSelect(MultiSelectType {
+                        defaults: Vec::new(),
+                        display: multi_select_type::Display::Checkboxes as i32,
+                        options: Vec::new(),
+                    }),
+                    _ => ItemType::Checkbox(CheckboxType { default: false }),
+                };
+
+                if item_idx % 3 != 2 {
+                    for option_idx in 0..75 {
+                        // Every tenth option shares its idx with the one before it.
+                        let idx = option_idx - usize::from(option_idx % 10 == 9);
+                        options.push((
+                            item_id.clone(),
+                            idx,
+                            SelectOption {
+                                id: new_id(),
+                                name: format!("option {}", option_idx),
+                                product_id: "".to_owned(),
+                                archived: false,
+                            },
+                        ));
+                    }
+                }
+
+                items.push((
+                    event_id.clone(),
+                    item_idx / 2,
+                    RegistrationSchemaItem {
+                        id: item_id,
+                        name: format!("item {}", item_idx),
+                        r#type: Some(RegistrationSchemaItemType { r#type: Some(typ) }),
+                        required: false,
+                        required_after: "".to_owned(),
+                        blank_on_copy: false,
+                        display_idx: None,
+                    },
+                ));
+            }
+        }
+        // Options for an item that isn't loaded are dropped.
+        options.push((
+            new_id(),
+            0,
+            SelectOption {
+                id: new_id(),
+                name: "orphan".to_owned(),
+                product_id: "".to_owned(),
+                archived: false,
+            },
+        ));
+        assert!(options.len() > 5000);
+
+        items.shuffle(&mut rng);
+        options.shuffle(&mut rng);
+
+        assert_eq!(
+            items_to_schema(items.clone(), options.clone()),
+            reference_items_to_schema(items, options)
+        );
     }
 }
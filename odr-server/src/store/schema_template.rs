@@ -0,0 +1,544 @@
+use std::{iter, sync::Arc};
+
+use common::proto::{
+    registration_schema_item_type::Type as ItemType, RegistrationSchema, RegistrationSchemaItem,
+    SchemaTemplate,
+};
+use prost::Message as _;
+use sqlx::SqlitePool;
+
+use super::{
+    common::{ids_in_table, new_id, nothing_to_do},
+    ids::{EventId, TemplateId},
+    Bindable as _, Error, IntoStoreError as _, Queryable as _, StoreResult,
+};
+
+#[derive(sqlx::FromRow)]
+struct SchemaTemplateRow {
+    id: String,
+    organization: String,
+    name: String,
+    items: Vec<u8>,
+}
+
+impl SchemaTemplateRow {
+    fn into_schema_template(self) -> StoreResult<SchemaTemplate> {
+        let items = SchemaTemplate::decode(self.items.as_slice())
+            .map_err(|_| Error::ColumnParseError("items"))?
+            .items;
+
+        Ok(SchemaTemplate {
+            id: self.id,
+            organization_id: self.organization,
+            name: self.name,
+            items,
+        })
+    }
+}
+
+// Template items are never queried on their own, so they're stored as a single encoded blob.
+fn encode_items(items: &[RegistrationSchemaItem]) -> Vec<u8> {
+    SchemaTemplate {
+        items: items.to_vec(),
+        ..Default::default()
+    }
+    .encode_to_vec()
+}
+
+fn clear_item_ids(item: &mut RegistrationSchemaItem) {
+    item.id = String::default();
+
+    let options = match item.r#type.as_mut().and_then(|t| t.r#type.as_mut()) {
+        Some(ItemType::Select(select)) => &mut select.options,
+        Some(ItemType::MultiSelect(select)) => &mut select.options,
+        Some(ItemType::Group(group)) => {
+            group.items.iter_mut().for_each(clear_item_ids);
+            return;
+        }
+        _ => return,
+    };
+
+    for option in options.iter_mut() {
+        option.id = String::default();
+    }
+}
+
+pub struct IdField;
+
+impl super::Field for IdField {
+    type Item = String;
+
+    fn field() -> &'static str {
+        "id"
+    }
+}
+
+pub type IdQuery = super::LogicalQuery<IdField>;
+
+pub struct OrganizationField;
+
+impl super::Field for OrganizationField {
+    type Item = String;
+
+    fn field() -> &'static str {
+        "organization"
+    }
+}
+
+pub type OrganizationQuery = super::LogicalQuery<OrganizationField>;
+
+pub enum Query {
+    Id(IdQuery),
+    Organization(OrganizationQuery),
+    CompoundQuery(super::CompoundQuery<Query>),
+}
+
+impl super::Queryable for Query {
+    fn where_clause(&self) -> String {
+        match self {
+            Query::Id(q) => q.where_clause(),
+            Query::Organization(q) => q.where_clause(),
+            Query::CompoundQuery(compound_query) => compound_query.where_clause(),
+        }
+    }
+}
+
+impl super::Compound for Query {
+    fn compound(query: super::CompoundQuery<Query>) -> Self {
+        Query::CompoundQuery(query)
+    }
+}
+
+impl<'q, DB: sqlx::Database> super::Bindable<'q, DB> for Query
+where
+    <IdField as super::Field>::Item: sqlx::Type<DB> + sqlx::Encode<'q, DB>,
+    <OrganizationField as super::Field>::Item: sqlx::Type<DB> + sqlx::Encode<'q, DB>,
+{
+    fn bind<O>(
+        &'q self,
+        query_builder: sqlx::query::QueryAs<
+            'q,
+            DB,
+            O,
+            <DB as sqlx::database::HasArguments<'q>>::Arguments,
+        >,
+    ) -> sqlx::query::QueryAs<'q, DB, O, <DB as sqlx::database::HasArguments<'q>>::Arguments> {
+        match self {
+            Query::Id(q) => q.bind(query_builder),
+            Query::Organization(q) => q.bind(query_builder),
+            Query::CompoundQuery(compound_query) => compound_query.bind(query_builder),
+        }
+    }
+}
+
+#[tonic::async_trait]
+pub trait Store: Send + Sync + 'static {
+    async fn upsert(&self, templates: Vec<SchemaTemplate>) -> StoreResult<Vec<SchemaTemplate>>;
+    /// Templates are returned in id order.
+    async fn query(&self, query: Option<&Query>) -> StoreResult<Vec<SchemaTemplate>>;
+    async fn delete(&self, ids: &[String]) -> StoreResult<()>;
+    async fn instantiate_template(
+        &self,
+        template_id: &TemplateId,
+        event_id: &EventId,
+    ) -> StoreResult<RegistrationSchema>;
+}
+
+#[derive(Debug)]
+pub struct SqliteStore {
+    pool: Arc<SqlitePool>,
+}
+
+impl SqliteStore {
+    pub fn new(pool: Arc<SqlitePool>) -> Self {
+        SqliteStore { pool }
+    }
+}
+
+#[tonic::async_trait]
+impl Store for SqliteStore {
+    async fn upsert(&self, templates: Vec<SchemaTemplate>) -> StoreResult<Vec<SchemaTemplate>> {
+        if let Some(result) = nothing_to_do(&templates) {
+            return result;
+        }
+
+        ids_in_table(
+            &self.pool,
+            "organizations",
+            templates
+                .iter()
+                .map(|template| template.organization_id.as_str()),
+        )
+        .await?;
+
+        let (inserts, updates): (Vec<_>, Vec<_>) = templates
+            .into_iter()
+            .enumerate()
+            .partition(|(_, template)| template.id.is_empty());
+
+        if !updates.is_empty() {
+            ids_in_table(
+                &self.pool,
+                "schema_templates",
+                updates.iter().map(|(_, template)| template.id.as_str()),
+            )
+            .await?;
+        }
+
+        let inserts = inserts
+            .into_iter()
+            .map(|(idx, mut template)| {
+                template.id = new_id();
+                (idx, template)
+            })
+            .collect::<Vec<_>>();
+
+        let mut tx = self.pool.begin().await.begin_err()?;
+
+        if !inserts.is_empty() {
+            let values_clause = itertools::Itertools::intersperse(
+                iter::repeat_n("(?, ?, ?, ?)", inserts.len()),
+                ", ",
+            )
+            .collect::<String>();
+
+            let query = format!(
+                "INSERT INTO schema_templates(id, organization, name, items) VALUES {}",
+                values_clause
+            );
+
+            let query_builder =
+                inserts
+                    .iter()
+                    .fold(sqlx::query(&query), |query_builder, (_, template)| {
+                        query_builder
+                            .bind(&template.id)
+                            .bind(&template.organization_id)
+                            .bind(&template.name)
+                            .bind(encode_items(&template.items))
+                    });
+
+            query_builder.execute(&mut *tx).await.insert_err()?;
+        }
+
+        if !updates.is_empty() {
+            let values_clause = itertools::Itertools::intersperse(
+                iter::repeat_n("(?, ?, ?, ?)", updates.len()),
+                ", ",
+            )
+            .collect::<String>();
+
+            let query = format!(
+                "WITH mydata(id, organization, name, items) AS (VALUES {})
+                UPDATE schema_templates
+                SET organization = mydata.organization, name = mydata.name, items = mydata.items
+                FROM mydata
+                WHERE schema_templates.id = mydata.id",
+                values_clause
+            );
+
+            let query_builder =
+                updates
+                    .iter()
+                    .fold(sqlx::query(&query), |query_builder, (_, template)| {
+                        query_builder
+                            .bind(&template.id)
+                            .bind(&template.organization_id)
+                            .bind(&template.name)
+                            .bind(encode_items(&template.items))
+                    });
+
+            query_builder.execute(&mut *tx).await.update_err()?;
+        }
+
+        tx.commit().await.commit_err()?;
+
+        let mut outputs = Vec::new();
+        outputs.resize(inserts.len() + updates.len(), SchemaTemplate::default());
+        inserts
+            .into_iter()
+            .chain(updates)
+            .for_each(|(idx, template)| {
+                outputs[idx] = template;
+            });
+
+        Ok(outputs)
+    }
+
+    async fn query(&self, query: Option<&Query>) -> StoreResult<Vec<SchemaTemplate>> {
+        let base_query = "SELECT id, organization, name, items FROM schema_templates";
+        let query_string = match query {
+            Some(query) => format!("{} WHERE {} ORDER BY id", base_query, query.where_clause()),
+            None => format!("{} ORDER BY id", base_query),
+        };
+
+        let query_builder = sqlx::query_as(&query_string);
+        let query_builder = match query {
+            Some(query) => query.bind(query_builder),
+            None => query_builder,
+        };
+
+        let rows: Vec<SchemaTemplateRow> =
+            query_builder.fetch_all(&*self.pool).await.fetch_err()?;
+
+        rows.into_iter()
+            .map(|row| row.into_schema_template())
+            .collect()
+    }
+
+    async fn delete(&self, ids: &[String]) -> StoreResult<()> {
+        if let Some(result) = nothing_to_do(ids) {
+            return result;
+        }
+
+        ids_in_table(
+            &self.pool,
+            "schema_templates",
+            ids.iter().map(|id| id.as_str()),
+        )
+        .await?;
+
+        let where_clause =
+            itertools::Itertools::intersperse(iter::repeat_n("id = ?", ids.len()), " OR ")
+                .collect::<String>();
+
+        let query = format!("DELETE FROM schema_templates WHERE {}", where_clause);
+        let query_builder = ids.iter().fold(sqlx::query(&query), |query_builder, id| {
+            query_builder.bind(id)
+        });
+
+        query_builder.execute(&*self.pool).await.delete_err()?;
+
+        Ok(())
+    }
+
+    async fn instantiate_template(
+        &self,
+        template_id: &TemplateId,
+        event_id: &EventId,
+    ) -> StoreResult<RegistrationSchema> {
+        ids_in_table(&self.pool, "events", iter::once(event_id.as_str())).await?;
+
+        // Templates are scoped to their organization, so a template from another organization is
+        // treated as if it doesn't exist.
+        let row: Option<SchemaTemplateRow> = sqlx::query_as(
+            "SELECT schema_templates.id, schema_templates.organization, schema_templates.name, schema_templates.items
+            FROM schema_templates JOIN events ON schema_templates.organization = events.organization
+            WHERE schema_templates.id = ? AND events.id = ?",
+        )
+        .bind(template_id)
+        .bind(event_id)
+        .fetch_optional(&*self.pool)
+        .await
+        .fetch_err()?;
+
+        let template = row
+            .ok_or_else(|| Error::IdDoesNotExist(template_id.to_string()))?
+            .into_schema_template()?;
+
+        let mut items = template.items;
+        for item in items.iter_mut() {
+            clear_item_ids(item);
+        }
+
+        Ok(RegistrationSchema {
+            event_id: event_id.to_string(),
+            items,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use common::proto::{
+        registration_schema_item_type::Type as ItemType, CheckboxType, RegistrationSchemaItem,
+        RegistrationSchemaItemType, SchemaTemplate, SelectOption, SelectType,
+    };
+    use sqlx::{migrate::MigrateDatabase, Sqlite, SqlitePool};
+
+    use crate::store::{
+        common::new_id,
+        ids::{EventId, TemplateId},
+        Error, LogicalQuery,
+    };
+    use crate::testing::closed_pool;
+
+    use super::{Query, SqliteStore, Store};
+
+    struct Init {
+        org_1: String,
+        org_2: String,
+        event_1: String,
+        db: SqlitePool,
+    }
+
+    async fn init_db() -> Init {
+        let db_url = "sqlite://:memory:";
+        Sqlite::create_database(db_url).await.unwrap();
+
+        let db = SqlitePool::connect(db_url).await.unwrap();
+        sqlx::migrate!("../migrations").run(&db).await.unwrap();
+
+        let org_1 = new_id();
+        let org_2 = new_id();
+        sqlx::query("INSERT INTO organizations(id, name) VALUES (?, ?), (?, ?);")
+            .bind(&org_1)
+            .bind("Organization 1")
+            .bind(&org_2)
+            .bind("Organization 2")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let event_1 = new_id();
+        sqlx::query("INSERT INTO events(id, organization, name) VALUES (?, ?, ?);")
+            .bind(&event_1)
+            .bind(&org_1)
+            .bind("Event 1")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        Init {
+            org_1,
+            org_2,
+            event_1,
+            db,
+        }
+    }
+
+    fn template(organization_id: &str, name: &str) -> SchemaTemplate {
+        SchemaTemplate {
+            id: "".to_owned(),
+            organization_id: organization_id.to_owned(),
+            name: name.to_owned(),
+            items: vec![
+                RegistrationSchemaItem {
+                    id: "".to_owned(),
+                    name: "Level".to_owned(),
+                    r#type: Some(RegistrationSchemaItemType {
+                        r#type: Some(ItemType::Select(SelectType {
+                            default: 0,
+                            display: 0,
+                            options: vec![
+                                SelectOption {
+                                    id: "".to_owned(),
+                                    name: "Beginner".to_owned(),
+                                    product_id: "".to_owned(),
+                                    archived: false,
+                                },
+                                SelectOption {
+                                    id: "".to_owned(),
+                                    name: "Intermediate".to_owned(),
+                                    product_id: "".to_owned(),
+                                    archived: false,
+                                },
+                            ],
+                        })),
+                    }),
+                    required: false,
+                    required_after: "".to_owned(),
+                    blank_on_copy: false,
+                    display_idx: None,
+                },
+                RegistrationSchemaItem {
+                    id: "".to_owned(),
+                    name: "Needs Shoes".to_owned(),
+                    r#type: Some(RegistrationSchemaItemType {
+                        r#type: Some(ItemType::Checkbox(CheckboxType { default: false })),
+                    }),
+                    required: false,
+                    required_after: "".to_owned(),
+                    blank_on_copy: false,
+                    display_idx: None,
+                },
+            ],
+        }
+    }
+
+    #[tokio::test]
+    async fn create() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let template = template(&init.org_1, "Beginner Class Signup");
+        let returned = store.upsert(vec![template.clone()]).await.unwrap();
+
+        assert_eq!(returned.len(), 1);
+        assert!(!returned[0].id.is_empty());
+
+        let stored = store
+            .query(Some(&Query::Id(LogicalQuery::Equals(
+                returned[0].id.clone(),
+            ))))
+            .await
+            .unwrap();
+
+        let expected = SchemaTemplate {
+            id: returned[0].id.clone(),
+            ..template
+        };
+        assert_eq!(stored, vec![expected]);
+    }
+
+    #[tokio::test]
+    async fn list_for_organization() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let returned = store
+            .upsert(vec![
+                template(&init.org_1, "Template 1"),
+                template(&init.org_2, "Template 2"),
+                template(&init.org_1, "Template 3"),
+            ])
+            .await
+            .unwrap();
+
+        let stored = store
+            .query(Some(&Query::Organization(LogicalQuery::Equals(
+                init.org_1.clone(),
+            ))))
+            .await
+            .unwrap();
+
+        let mut expected = vec![returned[0].clone(), returned[2].clone()];
+        expected.sort_by(|a, b| a.id.cmp(&b.id));
+        assert_eq!(stored, expected);
+    }
+
+    #[tokio::test]
+    async fn instantiate_template_other_organization() {
+        let init = init_db().await;
+        let store = SqliteStore::new(Arc::new(init.db));
+
+        let template_id = store
+            .upsert(vec![template(&init.org_2, "Template")])
+            .await
+            .unwrap()
+            .remove(0)
+            .id;
+
+        let result = store
+            .instantiate_template(
+                &TemplateId::from(&template_id),
+                &EventId::from(&init.event_1),
+            )
+            .await;
+        match result {
+            Ok(_) => panic!("no error returned"),
+            Err(Error::IdDoesNotExist(id)) => assert_eq!(id, template_id),
+            _ => panic!("incorrect error type: {:?}", result),
+        }
+    }
+
+    #[tokio::test]
+    async fn empty_input() {
+        let store = SqliteStore::new(closed_pool().await);
+
+        assert!(store.upsert(Vec::new()).await.unwrap().is_empty());
+        store.delete(&[]).await.unwrap();
+    }
+}
@@ -4,8 +4,8 @@ use argon2::password_hash::PasswordHashString;
 use sqlx::SqlitePool;
 
 use super::{
-    common::{ids_in_table, new_id},
-    Bindable as _, Error, Queryable as _,
+    common::{ids_in_table, new_id, nothing_to_do},
+    Bindable as _, Error, IntoStoreError as _, Queryable as _, StoreResult,
 };
 
 #[derive(sqlx::FromRow)]
@@ -19,7 +19,7 @@ struct UserRow {
 impl TryFrom<UserRow> for User {
     type Error = Error;
 
-    fn try_from(row: UserRow) -> Result<Self, Error> {
+    fn try_from(row: UserRow) -> StoreResult<Self> {
         let password = match row.password {
             Some(password) => PasswordType::Set(
                 PasswordHashString::new(&password)
@@ -37,6 +37,24 @@ impl TryFrom<UserRow> for User {
     }
 }
 
+#[derive(sqlx::FromRow)]
+struct UserSearchRow {
+    id: String,
+    email: String,
+    display_name: String,
+}
+
+impl From<UserSearchRow> for User {
+    fn from(row: UserSearchRow) -> Self {
+        User {
+            id: row.id,
+            email: row.email,
+            password: PasswordType::Unchanged,
+            display_name: row.display_name,
+        }
+    }
+}
+
 #[derive(Default, Clone, Debug, PartialEq)]
 pub enum PasswordType {
     Set(PasswordHashString),
@@ -103,6 +121,12 @@ impl super::Queryable for Query {
     }
 }
 
+impl super::Compound for Query {
+    fn compound(query: super::CompoundQuery<Query>) -> Self {
+        Query::CompoundQuery(query)
+    }
+}
+
 impl<'q, DB: sqlx::Database> super::Bindable<'q, DB> for Query
 where
     <EmailField as super::Field>::Item: sqlx::Type<DB> + sqlx::Encode<'q, DB>,
@@ -145,9 +169,14 @@ fn bind_user<'q>(query_builder: QueryBuilder<'q>, user: &'q User) -> QueryBuilde
 
 #[tonic::async_trait]
 pub trait Store: Send + Sync + 'static {
-    async fn upsert(&self, users: Vec<User>) -> Result<Vec<User>, Error>;
-    async fn query(&self, query: Option<&Query>) -> Result<Vec<User>, Error>;
-    async fn delete(&self, ids: &Vec<String>) -> Result<(), Error>;
+    async fn upsert(&self, users: Vec<User>) -> StoreResult<Vec<User>>;
+    /// Users are returned in id order.
+    async fn query(&self, query: Option<&Query>) -> StoreResult<Vec<User>>;
+    async fn delete(&self, ids: &[String]) -> StoreResult<()>;
+    /// Finds users whose email or display name starts with `prefix`, ignoring ASCII case. At most
+    /// `limit` users are returned, ordered by email. Passwords are never loaded, so every returned
+    /// user has `PasswordType::Unchanged`.
+    async fn search(&self, prefix: &str, limit: u32) -> StoreResult<Vec<User>>;
 }
 
 #[derive(Debug)]
@@ -163,7 +192,11 @@ impl SqliteStore {
 
 #[tonic::async_trait]
 impl Store for SqliteStore {
-    async fn upsert(&self, users: Vec<User>) -> Result<Vec<User>, Error> {
+    async fn upsert(&self, users: Vec<User>) -> StoreResult<Vec<User>> {
+        if let Some(result) = nothing_to_do(&users) {
+            return result;
+        }
+
         let (inserts, updates): (Vec<_>, Vec<_>) = users
             .into_iter()
             .enumerate()
@@ -191,11 +224,7 @@ impl Store for SqliteStore {
             })
             .collect::<Vec<_>>();
 
-        let mut tx = self
-            .pool
-            .begin()
-            .await
-            .map_err(|e| Error::TransactionStartError(e))?;
+        let mut tx = self.pool.begin().await.begin_err()?;
 
         if !inserts.is_empty() {
             let values_clause = itertools::Itertools::intersperse(
@@ -216,10 +245,7 @@ impl Store for SqliteStore {
                     bind_user(query_builder, user)
                 });
 
-            query_builder
-                .execute(&mut *tx)
-                .await
-                .map_err(|e| Error::InsertionError(e))?;
+            query_builder.execute(&mut *tx).await.insert_err()?;
         }
 
         if !updates_with_password.is_empty() {
@@ -247,10 +273,7 @@ impl Store for SqliteStore {
                     bind_user(query_builder, user)
                 });
 
-            query_builder
-                .execute(&mut *tx)
-                .await
-                .map_err(|e| Error::UpdateError(e))?;
+            query_builder.execute(&mut *tx).await.update_err()?;
         }
 
         if !updates_without_password.is_empty() {
@@ -277,13 +300,10 @@ impl Store for SqliteStore {
                     bind_user(query_builder, user)
                 });
 
-            query_builder
-                .execute(&mut *tx)
-                .await
-                .map_err(|e| Error::UpdateError(e))?;
+            query_builder.execute(&mut *tx).await.update_err()?;
         }
 
-        tx.commit().await.map_err(|e| Error::TransactionFailed(e))?;
+        tx.commit().await.commit_err()?;
 
         let mut outputs = Vec::new();
         outputs.resize(
@@ -302,11 +322,15 @@ impl Store for SqliteStore {
         Ok(outputs)
     }
 
-    async fn query(&self, query: Option<&Query>) -> Result<Vec<User>, Error> {
+    async fn query(&self, query: Option<&Query>) -> StoreResult<Vec<User>> {
         let base_query_string = "SELECT id, email, password, display_name FROM users";
         let query_string = match query {
-            Some(query) => format!("{} WHERE {}", base_query_string, query.where_clause()),
-            None => base_query_string.to_owned(),
+            Some(query) => format!(
+                "{} WHERE {} ORDER BY id",
+                base_query_string,
+                query.where_clause()
+            ),
+            None => format!("{} ORDER BY id", base_query_string),
         };
 
         let query_builder = sqlx::query_as(&query_string);
@@ -315,10 +339,7 @@ impl Store for SqliteStore {
             None => query_builder,
         };
 
-        let rows: Vec<UserRow> = query_builder
-            .fetch_all(&*self.pool)
-            .await
-            .map_err(|e| Error::FetchError(e))?;
+        let rows: Vec<UserRow> = query_builder.fetch_all(&*self.pool).await.fetch_err()?;
 
         let users = rows
             .into_iter()
@@ -328,9 +349,9 @@ impl Store for SqliteStore {
         Ok(users)
     }
 
-    async fn delete(&self, ids: &Vec<String>) -> Result<(), Error> {
-        if ids.is_empty() {
-            return Ok(());
+    async fn delete(&self, ids: &[String]) -> StoreResult<()> {
+        if let Some(result) = nothing_to_do(ids) {
+            return result;
         }
 
         ids_in_table(&*self.pool, "users", ids.iter().map(|id| id.as_str())).await?;
@@ -345,13 +366,36 @@ impl Store for SqliteStore {
         let query_builder = ids
             .iter()
             .fold(query_builder, |query_builder, id| query_builder.bind(id));
-        query_builder
-            .execute(&*self.pool)
-            .await
-            .map_err(|e| Error::DeleteError(e))?;
+        query_builder.execute(&*self.pool).await.delete_err()?;
 
         Ok(())
     }
+
+    async fn search(&self, prefix: &str, limit: u32) -> StoreResult<Vec<User>> {
+        let pattern = format!(
+            "{}%",
+            prefix
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+
+        let rows: Vec<UserSearchRow> = sqlx::query_as(
+            r#"
+            SELECT id, email, display_name FROM users
+            WHERE email LIKE ?1 ESCAPE '\' OR display_name LIKE ?1 ESCAPE '\'
+            ORDER BY email, id
+            LIMIT ?2
+            "#,
+        )
+        .bind(&pattern)
+        .bind(limit)
+        .fetch_all(&*self.pool)
+        .await
+        .fetch_err()?;
+
+        Ok(rows.into_iter().map(|row| row.into()).collect())
+    }
 }
 
 #[cfg(test)]
@@ -370,6 +414,7 @@ mod tests {
     use crate::store::{
         common::new_id, user::Query, CompoundOperator, CompoundQuery, Error, LogicalQuery,
     };
+    use crate::testing::closed_pool;
 
     use super::{PasswordType, SqliteStore, Store, User, UserRow};
 
@@ -613,8 +658,7 @@ mod tests {
         let db = Arc::new(init.db);
         let store = SqliteStore::new(db.clone());
 
-        let mut returned_users = store.query(tc.query.as_ref()).await.unwrap();
-        returned_users.sort_by(|a, b| a.id.cmp(&b.id));
+        let returned_users = store.query(tc.query.as_ref()).await.unwrap();
 
         let mut expected = tc.expected;
         expected.sort_by(|a, b| a.id.cmp(&b.id));
@@ -630,7 +674,7 @@ mod tests {
         let db = Arc::new(init.db);
         let store = SqliteStore::new(db.clone());
 
-        store.delete(&vec![users[0].id.clone()]).await.unwrap();
+        store.delete(&[users[0].id.clone()]).await.unwrap();
 
         let store_user_rows: Vec<UserRow> =
             sqlx::query_as("SELECT id, email, password, display_name FROM users")
@@ -647,4 +691,62 @@ mod tests {
 
         assert_eq!(users[1], store_users[0]);
     }
+
+    enum SearchTest {
+        Email,
+        DisplayName,
+        CaseInsensitive,
+        Wildcard,
+        Limit,
+    }
+
+    #[test_case(SearchTest::Email ; "email")]
+    #[test_case(SearchTest::DisplayName ; "display name")]
+    #[test_case(SearchTest::CaseInsensitive ; "case insensitive")]
+    #[test_case(SearchTest::Wildcard ; "wildcard is literal")]
+    #[test_case(SearchTest::Limit ; "limit")]
+    #[tokio::test]
+    async fn search(test_name: SearchTest) {
+        let init = init().await;
+        let users = [
+            ("alice@example.com", "Alice"),
+            ("bob@example.com", "Bob"),
+            ("carol@example.com", "Alfred"),
+            ("al_x@example.com", "Dave"),
+        ]
+        .into_iter()
+        .map(|(email, display_name)| User {
+            id: "".to_owned(),
+            email: email.to_owned(),
+            password: PasswordType::Unset,
+            display_name: display_name.to_owned(),
+        })
+        .collect::<Vec<_>>();
+
+        let store = SqliteStore::new(Arc::new(init.db));
+        let users = store.upsert(users).await.unwrap();
+        let summary = |idx: usize| User {
+            password: PasswordType::Unchanged,
+            ..users[idx].clone()
+        };
+
+        let (prefix, limit, expected) = match test_name {
+            SearchTest::Email => ("bo", 10, vec![summary(1)]),
+            SearchTest::DisplayName => ("Alf", 10, vec![summary(2)]),
+            SearchTest::CaseInsensitive => ("AL", 10, vec![summary(3), summary(0), summary(2)]),
+            SearchTest::Wildcard => ("al_", 10, vec![summary(3)]),
+            SearchTest::Limit => ("al", 2, vec![summary(3), summary(0)]),
+        };
+
+        let found = store.search(prefix, limit).await.unwrap();
+        assert_eq!(found, expected);
+    }
+
+    #[tokio::test]
+    async fn empty_input() {
+        let store = SqliteStore::new(closed_pool().await);
+
+        assert!(store.upsert(Vec::new()).await.unwrap().is_empty());
+        store.delete(&Vec::new()).await.unwrap();
+    }
 }
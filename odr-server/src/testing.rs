@@ -0,0 +1,196 @@
+//! Helpers shared by tests across the crate.
+//!
+//! Most are builders for the schema items tests create over and over. Each returns a new, unsaved
+//! item with no id, not required and with the type's usual defaults. Tests that need something
+//! else set it with struct update syntax, such as
+//! `RegistrationSchemaItem { required: true, ..text_item("name") }`. Tests that never save their
+//! items but look them up by id wrap them in [`with_id`].
+
+use std::sync::Arc;
+
+use common::proto::{
+    multi_select_type, registration_schema_item_type::Type as ItemType, select_type, text_type,
+    CheckboxType, GroupType, MultiSelectType, RegistrationSchemaItem, RegistrationSchemaItemType,
+    SectionType, SelectOption, SelectType, TagsType, TextType,
+};
+use sqlx::SqlitePool;
+
+// Every statement fails on a closed pool, so a store built on one only succeeds if it runs no SQL.
+pub async fn closed_pool() -> Arc<SqlitePool> {
+    let db = SqlitePool::connect("sqlite://:memory:").await.unwrap();
+    db.close().await;
+    Arc::new(db)
+}
+
+pub fn option(name: &str) -> SelectOption {
+    SelectOption {
+        id: "".to_owned(),
+        name: name.to_owned(),
+        product_id: "".to_owned(),
+        archived: false,
+    }
+}
+
+pub fn item(name: &str, typ: ItemType) -> RegistrationSchemaItem {
+    RegistrationSchemaItem {
+        id: "".to_owned(),
+        name: name.to_owned(),
+        r#type: Some(RegistrationSchemaItemType { r#type: Some(typ) }),
+        required: false,
+        required_after: "".to_owned(),
+        blank_on_copy: false,
+        display_idx: None,
+    }
+}
+
+// Option ids are left empty. Values pick options by index or name, so nothing looks them up.
+pub fn with_id(id: &str, item: RegistrationSchemaItem) -> RegistrationSchemaItem {
+    RegistrationSchemaItem {
+        id: id.to_owned(),
+        ..item
+    }
+}
+
+pub fn text_item(name: &str) -> RegistrationSchemaItem {
+    item(
+        name,
+        ItemType::Text(TextType {
+            default: "".to_owned(),
+            display: text_type::Display::Small as i32,
+            ..Default::default()
+        }),
+    )
+}
+
+pub fn checkbox_item(name: &str) -> RegistrationSchemaItem {
+    item(name, ItemType::Checkbox(CheckboxType { default: false }))
+}
+
+pub fn select_item(name: &str, options: &[&str]) -> RegistrationSchemaItem {
+    item(
+        name,
+        ItemType::Select(SelectType {
+            default: 0,
+            display: select_type::Display::Radio as i32,
+            options: options.iter().map(|name| option(name)).collect(),
+        }),
+    )
+}
+
+pub fn multi_select_item(name: &str, options: &[&str]) -> RegistrationSchemaItem {
+    item(
+        name,
+        ItemType::MultiSelect(MultiSelectType {
+            defaults: Vec::new(),
+            display: multi_select_type::Display::Checkboxes as i32,
+            options: options.iter().map(|name| option(name)).collect(),
+        }),
+    )
+}
+
+pub fn section_item(name: &str, body: &str) -> RegistrationSchemaItem {
+    item(
+        name,
+        ItemType::Section(SectionType {
+            body: body.to_owned(),
+        }),
+    )
+}
+
+pub fn tags_item(name: &str, max_tags: u32) -> RegistrationSchemaItem {
+    item(name, ItemType::Tags(TagsType { max_tags }))
+}
+
+pub fn group_item(name: &str, items: Vec<RegistrationSchemaItem>) -> RegistrationSchemaItem {
+    item(name, ItemType::Group(GroupType { items }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use common::proto::{
+        registration_schema_item_type::Type as ItemType, RegistrationSchema, RegistrationSchemaItem,
+    };
+    use sqlx::{migrate::MigrateDatabase, Sqlite, SqlitePool};
+    use test_case::test_case;
+
+    use crate::store::{
+        common::new_id,
+        registration_schema::{SqliteStore, Store},
+    };
+
+    use super::{
+        checkbox_item, group_item, multi_select_item, section_item, select_item, tags_item,
+        text_item,
+    };
+
+    // Ids are assigned by the store, so they're the only thing a round trip may change.
+    fn without_ids(mut item: RegistrationSchemaItem) -> RegistrationSchemaItem {
+        item.id = "".to_owned();
+        match item.r#type.as_mut().and_then(|t| t.r#type.as_mut()) {
+            Some(ItemType::Select(select)) => {
+                select.options.iter_mut().for_each(|o| o.id = "".to_owned())
+            }
+            Some(ItemType::MultiSelect(multi_select)) => multi_select
+                .options
+                .iter_mut()
+                .for_each(|o| o.id = "".to_owned()),
+            Some(ItemType::Group(group)) => {
+                group.items = group.items.drain(..).map(without_ids).collect()
+            }
+            _ => (),
+        }
+        item
+    }
+
+    #[test_case(text_item("name") ; "text")]
+    #[test_case(checkbox_item("waiver") ; "checkbox")]
+    #[test_case(select_item("role", &["leader", "follower"]) ; "select")]
+    #[test_case(multi_select_item("classes", &["beginner", "advanced"]) ; "multi select")]
+    #[test_case(section_item("About you", "Tell us about yourself.") ; "section")]
+    #[test_case(tags_item("dietary needs", 3) ; "tags")]
+    #[test_case(group_item("partners", vec![text_item("name"), checkbox_item("attending")]) ; "group")]
+    #[tokio::test]
+    async fn builder_round_trips(item: RegistrationSchemaItem) {
+        let db_url = "sqlite://:memory:";
+        Sqlite::create_database(db_url).await.unwrap();
+
+        let db = SqlitePool::connect(db_url).await.unwrap();
+        sqlx::migrate!("../migrations").run(&db).await.unwrap();
+
+        let org = new_id();
+        let event = new_id();
+        sqlx::query("INSERT INTO organizations(id, name) VALUES (?, ?);")
+            .bind(&org)
+            .bind("Org 1")
+            .execute(&db)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO events(id, organization, name) VALUES (?, ?, ?);")
+            .bind(&event)
+            .bind(&org)
+            .bind("Event 1")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let store = SqliteStore::new(Arc::new(db));
+        store
+            .upsert(vec![RegistrationSchema {
+                event_id: event,
+                items: vec![item.clone()],
+            }])
+            .await
+            .unwrap();
+
+        let mut schemas = store.query(None).await.unwrap();
+        assert_eq!(schemas.len(), 1);
+        let stored = schemas.remove(0).items;
+
+        assert_eq!(
+            stored.into_iter().map(without_ids).collect::<Vec<_>>(),
+            vec![item]
+        );
+    }
+}